@@ -0,0 +1,70 @@
+//! Benchmarks for the work the agent repeats before every request, on a conversation of the
+//! maximum history length.
+
+use std::collections::VecDeque;
+use std::hint::black_box;
+
+use agent::bench::{
+    CONVERSATION_LEN,
+    Workload,
+};
+use criterion::{
+    BatchSize,
+    Criterion,
+    Throughput,
+    criterion_group,
+    criterion_main,
+};
+
+fn bench_enforce_conversation_invariants(c: &mut Criterion) {
+    let workload = Workload::new(CONVERSATION_LEN);
+    let mut group = c.benchmark_group("enforce_conversation_invariants");
+    group.throughput(Throughput::Elements(CONVERSATION_LEN as u64));
+    group.bench_function("history", |b| {
+        b.iter_batched(
+            || (VecDeque::from(workload.messages.clone()), workload.tool_specs.clone()),
+            |(mut messages, mut tool_specs)| {
+                Workload::enforce_conversation_invariants(&mut messages, &mut tool_specs);
+                black_box(messages)
+            },
+            BatchSize::LargeInput,
+        );
+    });
+    group.bench_function("clone_history", |b| {
+        b.iter(|| black_box(VecDeque::from(workload.messages.clone())));
+    });
+    group.finish();
+}
+
+fn bench_format_request(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let workload = Workload::new(CONVERSATION_LEN);
+    let mut group = c.benchmark_group("format_request");
+    group.throughput(Throughput::Elements(CONVERSATION_LEN as u64));
+    group.bench_function("history", |b| {
+        b.iter(|| black_box(runtime.block_on(workload.format_request())));
+    });
+    group.finish();
+}
+
+fn bench_snapshot(c: &mut Criterion) {
+    let workload = Workload::new(CONVERSATION_LEN);
+    let bytes = workload.serialize_snapshot();
+    let mut group = c.benchmark_group("snapshot");
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.bench_function("serialize", |b| {
+        b.iter(|| black_box(workload.serialize_snapshot()));
+    });
+    group.bench_function("deserialize", |b| {
+        b.iter(|| black_box(Workload::deserialize_snapshot(black_box(&bytes))));
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_enforce_conversation_invariants,
+    bench_format_request,
+    bench_snapshot
+);
+criterion_main!(benches);
@@ -76,6 +76,12 @@ impl AgentConfig {
         }
     }
 
+    pub fn watch_resources(&self) -> bool {
+        match self {
+            AgentConfig::V2025_08_22(a) => a.watch_resources,
+        }
+    }
+
     pub fn mcp_servers(&self) -> &HashMap<String, McpServerConfig> {
         match self {
             AgentConfig::V2025_08_22(a) => &a.mcp_servers,
@@ -87,6 +93,19 @@ impl AgentConfig {
             AgentConfig::V2025_08_22(a) => a.use_legacy_mcp_json,
         }
     }
+
+    pub fn model(&self) -> Option<&ModelConfig> {
+        match self {
+            AgentConfig::V2025_08_22(a) => a.model.as_ref(),
+        }
+    }
+
+    /// Selects the model with `model_id`, keeping the rest of the model config.
+    pub fn set_model_id(&mut self, model_id: String) {
+        match self {
+            AgentConfig::V2025_08_22(a) => a.model.get_or_insert_default().model_id = Some(model_id),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -129,6 +148,9 @@ pub struct AgentConfigV2025_08_22 {
     /// Hooks to add additional context
     #[serde(default)]
     pub hooks: HashMap<HookTrigger, Vec<HookConfig>>,
+    /// The model provider and model the agent sends requests to
+    #[serde(default)]
+    pub model: Option<ModelConfig>,
     /// Preferences for selecting a model the agent uses to generate responses.
     ///
     /// TODO: unimplemented
@@ -148,9 +170,15 @@ pub struct AgentConfigV2025_08_22 {
     pub use_legacy_mcp_json: bool,
 
     // context files
-    /// Files to include in the agent's context
+    /// Files to include in the agent's context, as `file://` paths or globs, and resources of the
+    /// MCP servers of the agent, as `mcp://server_name/resource_uri` where the resource uri may
+    /// contain `*` to include all the resources it matches
     #[serde(default)]
     pub resources: Vec<ResourcePath>,
+    /// Whether to only re-read the file resources that changed since the previous request, and
+    /// note the changes in the context so the model prefers them over the earlier contents
+    #[serde(default)]
+    pub watch_resources: bool,
 
     // permissioning stuff
     /// List of tools the agent is explicitly allowed to use
@@ -170,6 +198,7 @@ impl Default for AgentConfigV2025_08_22 {
             tool_aliases: Default::default(),
             tool_schema: Default::default(),
             hooks: Default::default(),
+            model: Default::default(),
             model_preferences: Default::default(),
             mcp_servers: Default::default(),
             use_legacy_mcp_json: false,
@@ -183,6 +212,7 @@ impl Default for AgentConfigV2025_08_22 {
             .into_iter()
             .map(Into::into)
             .collect::<Vec<_>>(),
+            watch_resources: false,
 
             allowed_tools: HashSet::from([BuiltInToolName::FsRead.to_string()]),
         }
@@ -193,6 +223,30 @@ impl Default for AgentConfigV2025_08_22 {
 pub struct ToolSettings {
     pub fs_read: FsReadSettings,
     pub fs_write: FsWriteSettings,
+    /// Directories the file tools are restricted to, e.g. `.` or `~/notes`. Paths are resolved
+    /// following symlinks before being checked. Any path is allowed if empty, and none if none of
+    /// the roots can be resolved.
+    #[serde(default)]
+    pub file_roots: Vec<String>,
+    /// Execution limits by tool name, e.g. `executeCmd` or `@git/git_status`, overriding the
+    /// limits of the agent settings
+    #[serde(default)]
+    pub limits: HashMap<String, ToolLimits>,
+}
+
+/// Execution limits of a tool. Limits that are not set use the ones of the agent settings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolLimits {
+    /// Time after which the tool is cancelled, in ms
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// Bytes of the tool output kept before it is truncated
+    #[serde(default)]
+    pub max_output_size: Option<usize>,
+    /// Most results in a page, for tools that paginate their results like `ls`
+    #[serde(default)]
+    pub page_size: Option<usize>,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
@@ -244,15 +298,31 @@ pub struct LocalMcpServerConfig {
 pub struct StreamableHTTPMcpServerConfig {
     /// The URL endpoint for HTTP-based MCP servers
     pub url: String,
-    /// HTTP headers to include when communicating with HTTP-based MCP servers
+    /// HTTP headers to include when communicating with HTTP-based MCP servers, e.g.
+    /// `Authorization`. Values can reference environment variables as `${env:VAR_NAME}`.
     #[serde(default)]
     pub headers: HashMap<String, String>,
+    /// The transport used to connect to the server. If not set, the streamable HTTP transport is
+    /// tried first, falling back to the legacy SSE transport.
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub transport: Option<RemoteMcpTransport>,
     /// Timeout for each mcp request in ms
     #[serde(alias = "timeout")]
     #[serde(default = "default_timeout")]
     pub timeout_ms: u64,
 }
 
+/// Transport of a remote MCP server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum RemoteMcpTransport {
+    /// The streamable HTTP transport
+    #[serde(rename = "http")]
+    StreamableHttp,
+    /// The legacy HTTP with SSE transport
+    #[serde(rename = "sse")]
+    Sse,
+}
+
 pub fn default_timeout() -> u64 {
     120 * 1000
 }
@@ -373,6 +443,128 @@ pub struct ModelPreferences {
     intelligence_priority: Option<f32>,
 }
 
+/// Selects the model an agent sends requests to, see
+/// [ModelProviderRegistry](crate::agent_loop::model_provider::ModelProviderRegistry).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelConfig {
+    /// Name of the registered model provider, such as `openai`, `bedrock` or `ollama`. The
+    /// default provider is used when not set.
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// Id of the model, in the format of the provider
+    #[serde(default)]
+    pub model_id: Option<String>,
+    /// Settings specific to the provider, such as the endpoint of an OpenAI compatible API
+    #[serde(default)]
+    pub settings: serde_json::Map<String, serde_json::Value>,
+    /// Prices of the model, to estimate the cost of requests when the provider doesn't know them
+    #[serde(default)]
+    pub pricing: Option<ModelPricing>,
+    /// Default inference parameters of the requests sent to the model
+    #[serde(default)]
+    pub inference: InferenceParams,
+}
+
+/// Prices of a model in USD per million tokens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPricing {
+    pub input: f64,
+    pub output: f64,
+    /// Price of the input tokens read from the prompt cache, the input price when not set
+    #[serde(default)]
+    pub cache_read: Option<f64>,
+    /// Price of the input tokens written to the prompt cache, the input price when not set
+    #[serde(default)]
+    pub cache_write: Option<f64>,
+}
+
+/// Parameters controlling how a model samples its responses. Parameters that are not set use the
+/// default of the model, and models whose backend doesn't support a parameter ignore it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InferenceParams {
+    /// Sampling temperature, lower values make responses more deterministic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Nucleus sampling, only the tokens within this cumulative probability are considered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Most tokens generated in a response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+}
+
+impl InferenceParams {
+    /// Names of the parameters, as accepted by [Self::set].
+    pub const NAMES: [&str; 3] = ["temperature", "top_p", "max_tokens"];
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns these parameters with the ones set in `overrides` replaced.
+    pub fn merge(self, overrides: Self) -> Self {
+        Self {
+            temperature: overrides.temperature.or(self.temperature),
+            top_p: overrides.top_p.or(self.top_p),
+            max_tokens: overrides.max_tokens.or(self.max_tokens),
+        }
+    }
+
+    /// Sets the parameter `name` from its string `value`, validating its range.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let parse_unit = |max: f32| {
+            value
+                .parse::<f32>()
+                .ok()
+                .filter(|v| (0.0..=max).contains(v))
+                .ok_or_else(|| format!("{} must be a number between 0 and {}, found '{}'", name, max, value))
+        };
+        match name {
+            "temperature" => self.temperature = Some(parse_unit(2.0)?),
+            "top_p" | "topP" => self.top_p = Some(parse_unit(1.0)?),
+            "max_tokens" | "maxTokens" => {
+                self.max_tokens = Some(
+                    value
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|v| *v > 0)
+                        .ok_or_else(|| format!("{} must be a positive integer, found '{}'", name, value))?,
+                );
+            },
+            other => {
+                return Err(format!(
+                    "unknown inference parameter '{}', expected one of: {}",
+                    other,
+                    Self::NAMES.join(", ")
+                ));
+            },
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for InferenceParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut params = Vec::new();
+        if let Some(temperature) = self.temperature {
+            params.push(format!("temperature={}", temperature));
+        }
+        if let Some(top_p) = self.top_p {
+            params.push(format!("top_p={}", top_p));
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            params.push(format!("max_tokens={}", max_tokens));
+        }
+        match params.is_empty() {
+            true => write!(f, "model defaults"),
+            false => write!(f, "{}", params.join(" ")),
+        }
+    }
+}
+
 fn default_schema() -> String {
     // TODO
     "https://raw.githubusercontent.com/aws/amazon-q-developer-cli/refs/heads/main/schemas/agent-v1.json".into()
@@ -392,4 +584,49 @@ mod tests {
 
         let _: AgentConfig = serde_json::from_value(agent).unwrap();
     }
+
+    #[test]
+    fn test_mcp_server_config_deser() {
+        let servers: McpServers = serde_json::from_value(serde_json::json!({
+            "mcpServers": {
+                "local": { "command": "git-mcp", "args": ["--verbose"] },
+                "remote": { "url": "https://mcp.example.com/mcp" },
+                "legacy": {
+                    "type": "sse",
+                    "url": "https://mcp.example.com/sse",
+                    "headers": { "Authorization": "Bearer ${env:MCP_TOKEN}" }
+                }
+            }
+        }))
+        .unwrap();
+
+        assert!(matches!(servers.mcp_servers["local"], McpServerConfig::Local(_)));
+        let McpServerConfig::StreamableHTTP(remote) = &servers.mcp_servers["remote"] else {
+            panic!("expected a remote server");
+        };
+        assert_eq!(remote.transport, None);
+        let McpServerConfig::StreamableHTTP(legacy) = &servers.mcp_servers["legacy"] else {
+            panic!("expected a remote server");
+        };
+        assert_eq!(legacy.transport, Some(RemoteMcpTransport::Sse));
+        assert_eq!(legacy.headers["Authorization"], "Bearer ${env:MCP_TOKEN}");
+    }
+    #[test]
+    fn test_inference_params() {
+        let mut overrides = InferenceParams::default();
+        overrides.set("temperature", "0.2").unwrap();
+        assert!(overrides.set("top_p", "1.5").is_err());
+        assert!(overrides.set("seed", "1").is_err());
+        assert_eq!(overrides.to_string(), "temperature=0.2");
+
+        let defaults: InferenceParams = serde_json::from_value(serde_json::json!({
+            "temperature": 0.7,
+            "maxTokens": 1024
+        }))
+        .unwrap();
+        let merged = defaults.merge(overrides);
+        assert_eq!(merged.temperature, Some(0.2));
+        assert_eq!(merged.top_p, None);
+        assert_eq!(merged.max_tokens, Some(1024));
+    }
 }
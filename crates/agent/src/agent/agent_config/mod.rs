@@ -1,5 +1,6 @@
 pub mod definitions;
 pub mod parse;
+pub mod schema;
 pub mod types;
 
 use std::collections::{
@@ -244,7 +245,11 @@ async fn load_agents_from_dir(
                     Ok(agent) => agents.push((entry_path, agent)),
                     Err(e) => invalid_agents.push(AgentConfigError::InvalidAgentConfig {
                         path: entry_path.to_string_lossy().to_string(),
-                        message: e.to_string(),
+                        // The schema points at the offending value, where serde only reports
+                        // that no variant of the config matched
+                        message: schema::validate(&schema::agent_config_schema(), &entry_contents)
+                            .first()
+                            .map_or_else(|| e.to_string(), ToString::to_string),
                     }),
                 }
             },
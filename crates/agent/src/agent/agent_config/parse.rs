@@ -10,14 +10,36 @@ use crate::agent::util::providers::SystemProvider;
 /// Represents a value from the `resources` array in the agent config.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ResourceKind<'a> {
-    File { original: &'a str, file_path: String },
-    FileGlob { original: &'a str, pattern: glob::Pattern },
+    File {
+        original: &'a str,
+        file_path: String,
+    },
+    FileGlob {
+        original: &'a str,
+        pattern: glob::Pattern,
+    },
+    /// A resource of an MCP server. Follows the format `mcp://server_name/resource_uri`
+    Mcp {
+        original: &'a str,
+        server_name: &'a str,
+        uri: &'a str,
+    },
+    /// Glob matching the resources listed by an MCP server. Follows the format
+    /// `mcp://server_name/uri_glob`, where `uri_glob` contains one or more `*`.
+    McpGlob {
+        original: &'a str,
+        server_name: &'a str,
+        pattern: glob::Pattern,
+    },
 }
 
 impl<'a> ResourceKind<'a> {
     pub fn parse(value: &'a str, sys: &impl SystemProvider) -> Result<Self, String> {
+        if let Some(mcp_resource) = value.strip_prefix("mcp://") {
+            return Self::parse_mcp(value, mcp_resource);
+        }
         if !value.starts_with("file://") {
-            return Err("Only file schemes are currently supported".to_string());
+            return Err("Only file and mcp schemes are currently supported".to_string());
         }
 
         let file_path = value.trim_start_matches("file://");
@@ -38,6 +60,28 @@ impl<'a> ResourceKind<'a> {
             })
         }
     }
+
+    fn parse_mcp(original: &'a str, mcp_resource: &'a str) -> Result<Self, String> {
+        let (server_name, uri) = mcp_resource
+            .split_once('/')
+            .filter(|(server_name, uri)| !server_name.is_empty() && !uri.is_empty())
+            .ok_or_else(|| format!("Expected mcp://server_name/resource_uri, found {}", original))?;
+        if uri.contains('*') {
+            let pattern =
+                glob::Pattern::new(uri).map_err(|err| format!("Failed to create glob for {}: {}", uri, err))?;
+            Ok(Self::McpGlob {
+                original,
+                server_name,
+                pattern,
+            })
+        } else {
+            Ok(Self::Mcp {
+                original,
+                server_name,
+                uri,
+            })
+        }
+    }
 }
 
 /// Represents the different types of tool name references allowed by the agent
@@ -230,4 +274,26 @@ mod tests {
             pattern: glob::Pattern::new("/home/testuser/project/**/*.rs").unwrap()
         });
     }
+
+    #[test]
+    fn test_resource_kind_parse_mcp_scheme() {
+        let sys = TestProvider::new();
+
+        let resource = "mcp://notes/file:///notes/today.md";
+        assert_eq!(ResourceKind::parse(resource, &sys).unwrap(), ResourceKind::Mcp {
+            original: resource,
+            server_name: "notes",
+            uri: "file:///notes/today.md"
+        });
+
+        let resource = "mcp://notes/memo://*";
+        assert_eq!(ResourceKind::parse(resource, &sys).unwrap(), ResourceKind::McpGlob {
+            original: resource,
+            server_name: "notes",
+            pattern: glob::Pattern::new("memo://*").unwrap()
+        });
+
+        assert!(ResourceKind::parse("mcp://notes", &sys).is_err());
+        assert!(ResourceKind::parse("mcp:///memo://insights", &sys).is_err());
+    }
 }
@@ -0,0 +1,259 @@
+//! JSON Schema of [AgentConfig], and validation of config files that points at the line and
+//! column of each problem.
+
+use std::fmt::Display;
+
+use jsonschema::error::ValidationErrorKind;
+use serde_json::Value;
+
+use super::definitions::AgentConfig;
+
+/// Returns the JSON Schema of [AgentConfig].
+pub fn agent_config_schema() -> Value {
+    serde_json::to_value(schemars::schema_for!(AgentConfig)).expect("schema serializes to JSON")
+}
+
+/// A problem found in a config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    /// JSON pointer to the value with the problem, empty for the whole document
+    pub pointer: String,
+    /// 1-based line of the value in the file
+    pub line: usize,
+    /// 1-based column of the value in the file
+    pub column: usize,
+    pub message: String,
+}
+
+impl Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)?;
+        if !self.pointer.is_empty() {
+            write!(f, " (at {})", self.pointer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Validates the JSON in `source` against `schema` and returns every problem found. Syntax errors
+/// stop validation, so at most one is returned.
+pub fn validate(schema: &Value, source: &str) -> Vec<ConfigIssue> {
+    let instance = match serde_json::from_str::<Value>(source) {
+        Ok(instance) => instance,
+        Err(err) => {
+            // serde_json reports column 0 for errors at the end of a line
+            return vec![ConfigIssue {
+                pointer: String::new(),
+                line: err.line(),
+                column: err.column().max(1),
+                message: strip_position(&err.to_string()),
+            }];
+        },
+    };
+    let validator = match jsonschema::validator_for(schema) {
+        Ok(validator) => validator,
+        Err(err) => {
+            return vec![ConfigIssue {
+                pointer: String::new(),
+                line: 1,
+                column: 1,
+                message: format!("the schema is invalid: {err}"),
+            }];
+        },
+    };
+
+    validator
+        .iter_errors(&instance)
+        .map(|err| {
+            let mut pointer = err.instance_path.to_string();
+            // Point at the unexpected property rather than the object it is in
+            if let ValidationErrorKind::AdditionalProperties { unexpected } = &err.kind {
+                if let [property] = unexpected.as_slice() {
+                    pointer = format!("{pointer}/{}", property.replace('~', "~0").replace('/', "~1"));
+                }
+            }
+            let (line, column) = locate(source, &pointer);
+            ConfigIssue {
+                pointer,
+                line,
+                column,
+                message: err.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Removes the " at line X column Y" suffix serde_json adds to its messages.
+fn strip_position(message: &str) -> String {
+    match message.rfind(" at line ") {
+        Some(index) => message[..index].to_string(),
+        None => message.to_string(),
+    }
+}
+
+/// Returns the line and column where the value at `pointer` starts in `source`, or of the start of
+/// the document if it can't be found.
+fn locate(source: &str, pointer: &str) -> (usize, usize) {
+    let tokens = pointer
+        .split('/')
+        .skip(1)
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect::<Vec<_>>();
+    let bytes = source.as_bytes();
+    let offset = find_value(bytes, skip_whitespace(bytes, 0), &tokens).unwrap_or(0);
+
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+fn find_value(bytes: &[u8], pos: usize, path: &[String]) -> Option<usize> {
+    let Some((token, rest)) = path.split_first() else {
+        return Some(pos);
+    };
+    match bytes.get(pos)? {
+        b'{' => {
+            let mut i = skip_whitespace(bytes, pos + 1);
+            while *bytes.get(i)? == b'"' {
+                let key_end = skip_string(bytes, i)?;
+                let key = serde_json::from_slice::<String>(&bytes[i..key_end]).ok()?;
+                // Skip the colon
+                let value = skip_whitespace(bytes, skip_whitespace(bytes, key_end) + 1);
+                if key == *token {
+                    return find_value(bytes, value, rest);
+                }
+                i = skip_separator(bytes, skip_value(bytes, value)?);
+            }
+            None
+        },
+        b'[' => {
+            let index = token.parse::<usize>().ok()?;
+            let mut i = skip_whitespace(bytes, pos + 1);
+            for _ in 0..index {
+                i = skip_separator(bytes, skip_value(bytes, i)?);
+            }
+            find_value(bytes, i, rest)
+        },
+        _ => None,
+    }
+}
+
+fn skip_whitespace(bytes: &[u8], mut pos: usize) -> usize {
+    while bytes.get(pos).is_some_and(u8::is_ascii_whitespace) {
+        pos += 1;
+    }
+    pos
+}
+
+/// Skips whitespace and the comma after a value.
+fn skip_separator(bytes: &[u8], pos: usize) -> usize {
+    let pos = skip_whitespace(bytes, pos);
+    match bytes.get(pos) {
+        Some(b',') => skip_whitespace(bytes, pos + 1),
+        _ => pos,
+    }
+}
+
+/// Returns the position after the string starting at `pos`.
+fn skip_string(bytes: &[u8], pos: usize) -> Option<usize> {
+    let mut i = pos + 1;
+    loop {
+        match bytes.get(i)? {
+            b'\\' => i += 2,
+            b'"' => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+}
+
+/// Returns the position after the value starting at `pos`.
+fn skip_value(bytes: &[u8], pos: usize) -> Option<usize> {
+    match bytes.get(pos)? {
+        b'"' => skip_string(bytes, pos),
+        b'{' | b'[' => {
+            let mut depth = 0;
+            let mut i = pos;
+            loop {
+                match bytes.get(i)? {
+                    b'"' => {
+                        i = skip_string(bytes, i)?;
+                        continue;
+                    },
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(i + 1);
+                        }
+                    },
+                    _ => {},
+                }
+                i += 1;
+            }
+        },
+        _ => {
+            let mut i = pos;
+            while bytes
+                .get(i)
+                .is_some_and(|b| !matches!(b, b',' | b'}' | b']') && !b.is_ascii_whitespace())
+            {
+                i += 1;
+            }
+            Some(i)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"{
+  "name": "reviewer",
+  "tools": ["fs_read", 42],
+  "nested": { "a\"b": { "x": true } }
+}"#;
+
+    #[test]
+    fn test_locate() {
+        assert_eq!(locate(SOURCE, ""), (1, 1));
+        assert_eq!(locate(SOURCE, "/name"), (2, 11));
+        assert_eq!(locate(SOURCE, "/tools/1"), (3, 24));
+        assert_eq!(locate(SOURCE, "/nested/a\"b/x"), (4, 30));
+        assert_eq!(locate(SOURCE, "/missing"), (1, 1));
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "tools": { "type": "array", "items": { "type": "string" } }
+            }
+        });
+        let issues = validate(&schema, SOURCE);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].pointer, "/tools/1");
+        assert_eq!((issues[0].line, issues[0].column), (3, 24));
+        assert!(issues[0].to_string().starts_with("3:24: 42 is not of type \"string\""));
+
+        let strict = serde_json::json!({ "type": "object", "additionalProperties": false });
+        let issues = validate(&strict, "{\n  \"nmae\": \"a\"\n}");
+        assert_eq!(issues[0].pointer, "/nmae");
+        assert_eq!((issues[0].line, issues[0].column), (2, 11));
+
+        let issues = validate(&schema, "{\n  \"name\": \"a\",\n}");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, 3);
+        assert!(!issues[0].message.contains("at line"));
+    }
+
+    #[test]
+    fn test_agent_config_schema() {
+        let schema = agent_config_schema();
+        let valid = r#"{ "spec_version": "2025_08_22", "name": "orchestrator" }"#;
+        assert_eq!(validate(&schema, valid), vec![]);
+    }
+}
@@ -10,7 +10,7 @@ use serde::{
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, Hash, PartialEq, JsonSchema)]
 pub struct ResourcePath(
     // You can extend this list via "|". e.g. r"^(file://|database://)"
-    #[schemars(regex(pattern = r"^(file://)"))]
+    #[schemars(regex(pattern = r"^(file://|mcp://)"))]
     String,
 );
 
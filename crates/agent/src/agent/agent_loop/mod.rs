@@ -1,10 +1,14 @@
 pub mod model;
+pub mod model_provider;
 pub mod protocol;
 pub mod types;
 
 use std::pin::Pin;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{
+    Duration,
+    Instant,
+};
 
 use chrono::Utc;
 use eyre::Result;
@@ -23,6 +27,7 @@ use protocol::{
     SendRequestArgs,
     StreamMetadata,
     StreamResult,
+    StreamTimeouts,
     UserTurnMetadata,
 };
 use serde::{
@@ -48,10 +53,15 @@ use types::{
     StreamError,
     StreamErrorKind,
     StreamEvent,
+    TokenUsage,
     ToolUseBlock,
 };
 
 use crate::agent::AgentId;
+use crate::agent::agent_config::definitions::{
+    InferenceParams,
+    ModelPricing,
+};
 use crate::agent::util::request_channel::{
     RequestReceiver,
     RequestSender,
@@ -131,6 +141,9 @@ pub struct AgentLoop {
     /// List of completed stream parse states
     stream_states: Vec<StreamParseState>,
 
+    /// Inference parameters of the last request sent
+    inference_params: InferenceParams,
+
     // turn duration tracking
     loop_start_time: Option<Instant>,
     loop_end_time: Option<Instant>,
@@ -164,6 +177,7 @@ impl AgentLoop {
             cancel_token,
             curr_stream: None,
             stream_states: Vec::new(),
+            inference_params: InferenceParams::default(),
             loop_start_time: None,
             loop_end_time: None,
             loop_event_tx,
@@ -207,7 +221,8 @@ impl AgentLoop {
                 res = async {
                     match self.curr_stream.take() {
                         Some((state, mut stream)) => {
-                            let next_ev = stream.next().await;
+                            let timeout = state.next_event_timeout();
+                            let next_ev = tokio::time::timeout(timeout, stream.next()).await.map_err(|_| timeout);
                             (state, stream, next_ev)
                         },
                         None => std::future::pending().await,
@@ -220,12 +235,22 @@ impl AgentLoop {
                     let mut loop_events: Vec<AgentLoopEventKind> = Vec::new();
 
                     // Advance the stream parse state
-                    stream_state.next(stream_event, &mut loop_events);
+                    match stream_event {
+                        Ok(ev) => stream_state.next(ev, &mut loop_events),
+                        Err(duration) => {
+                            // The stream is dropped once ended, abandoning the response.
+                            warn!(?duration, "timed out waiting for the next response stream event");
+                            let err = StreamError::new(StreamErrorKind::StreamTimeout { duration });
+                            stream_state.next(Some(StreamResult::Err(err)), &mut loop_events);
+                            stream_state.next(None, &mut loop_events);
+                        },
+                    }
 
                     if stream_state.ended() {
                         // Pushing the state early here to ensure the metadata event is created
                         // correctly in the case of UserTurnEnded.
                         self.stream_states.push(stream_state);
+                        loop_events.push(self.make_usage_event());
                         let stream_state = self.stream_states.last().expect("should exist after push");
 
                         if stream_state.errored {
@@ -260,7 +285,7 @@ impl AgentLoop {
         debug!(?req, "agent loop handling new request");
         match req {
             AgentLoopRequest::GetExecutionState => Ok(AgentLoopResponse::ExecutionState(self.execution_state)),
-            AgentLoopRequest::SendRequest { model, args } => {
+            AgentLoopRequest::SendRequest { model, args, timeouts } => {
                 if self.curr_stream.is_some() {
                     return Err(AgentLoopResponseError::StreamCurrentlyExecuting);
                 }
@@ -293,8 +318,16 @@ impl AgentLoop {
                     .clone();
 
                 let cancel_token = self.cancel_token.clone();
-                let stream = model.stream(args.messages, args.tool_specs, args.system_prompt, cancel_token);
-                self.curr_stream = Some((StreamParseState::new(next_user_message), stream));
+                let pricing = model.pricing();
+                self.inference_params = args.inference_params;
+                let stream = model.stream(
+                    args.messages,
+                    args.tool_specs,
+                    args.system_prompt,
+                    args.inference_params,
+                    cancel_token,
+                );
+                self.curr_stream = Some((StreamParseState::new(next_user_message, pricing, timeouts), stream));
                 Ok(AgentLoopResponse::Success)
             },
 
@@ -310,6 +343,7 @@ impl AgentLoop {
                     parse_state.next(None, &mut buf);
                     debug_assert!(parse_state.ended());
                     self.stream_states.push(parse_state);
+                    buf.push(self.make_usage_event());
                 }
 
                 self.loop_end_time = Some(Instant::now());
@@ -332,6 +366,14 @@ impl AgentLoop {
         AgentLoopEventKind::LoopStateChange { from, to }
     }
 
+    /// Creates the usage event of the last completed stream.
+    fn make_usage_event(&self) -> AgentLoopEventKind {
+        AgentLoopEventKind::Usage {
+            request: self.stream_states.last().map(|s| s.usage()).unwrap_or_default(),
+            turn: self.stream_states.iter().map(|s| s.usage()).sum(),
+        }
+    }
+
     /// Creates the user turn metadata.
     ///
     /// This should only be called after all completed stream parse states have been pushed to
@@ -352,6 +394,8 @@ impl AgentLoop {
             message_ids,
             total_request_count: self.stream_states.len() as u32,
             number_of_cycles: self.stream_states.iter().filter(|s| s.has_tool_uses()).count() as u32,
+            usage: self.stream_states.iter().map(|s| s.usage()).sum(),
+            inference_params: self.inference_params,
             turn_duration: match (self.loop_start_time, self.loop_end_time) {
                 (Some(start), Some(end)) => Some(end.duration_since(start)),
                 _ => None,
@@ -404,6 +448,11 @@ struct StreamParseState {
     parsing_tool_use: Option<(String, String, String)>,
     /// Buffered metadata event returned from the response stream
     metadata: Option<MetadataEvent>,
+    /// Prices of the model the request was sent to
+    pricing: Option<ModelPricing>,
+    timeouts: StreamTimeouts,
+    /// Whether the last content received was reasoning content
+    reasoning: bool,
     /// Buffered message start event returned from the response stream
     message_start: Option<MessageStartEvent>,
     /// Buffered message stop event returned from the response stream
@@ -419,7 +468,7 @@ struct StreamParseState {
 }
 
 impl StreamParseState {
-    pub fn new(user_message: Message) -> Self {
+    pub fn new(user_message: Message, pricing: Option<ModelPricing>, timeouts: StreamTimeouts) -> Self {
         Self {
             assistant_text: String::new(),
             parsing_tool_use: None,
@@ -428,6 +477,9 @@ impl StreamParseState {
             user_message,
             message_id: None,
             metadata: None,
+            pricing,
+            timeouts,
+            reasoning: false,
             message_start: None,
             message_stop: None,
             stream_err: None,
@@ -501,10 +553,12 @@ impl StreamParseState {
 
                 StreamEvent::ContentBlockDelta(ev) => match ev.delta {
                     types::ContentBlockDelta::Text(text) => {
+                        self.reasoning = false;
                         self.assistant_text.push_str(&text);
                         buf.push(AgentLoopEventKind::AssistantText(text));
                     },
                     types::ContentBlockDelta::ToolUse(ev) => {
+                        self.reasoning = false;
                         debug_assert!(self.parsing_tool_use.is_some());
                         match self.parsing_tool_use.as_mut() {
                             Some((_, _, buf)) => {
@@ -515,7 +569,7 @@ impl StreamParseState {
                             },
                         }
                     },
-                    types::ContentBlockDelta::Reasoning => (),
+                    types::ContentBlockDelta::Reasoning => self.reasoning = true,
                     types::ContentBlockDelta::Document => (),
                 },
 
@@ -569,6 +623,17 @@ impl StreamParseState {
         }
     }
 
+    /// How long to wait for the next event before the stream times out.
+    fn next_event_timeout(&self) -> Duration {
+        if self.message_start.is_none() {
+            self.timeouts.first_event
+        } else if self.reasoning {
+            self.timeouts.reasoning
+        } else {
+            self.timeouts.between_events
+        }
+    }
+
     pub fn has_tool_uses(&self) -> bool {
         !self.tool_uses.is_empty()
     }
@@ -594,10 +659,31 @@ impl StreamParseState {
         }
     }
 
+    /// Tokens used by the request, from the metadata event of the response stream.
+    fn usage(&self) -> TokenUsage {
+        self.metadata
+            .as_ref()
+            .and_then(|m| m.usage.as_ref())
+            .map(|usage| TokenUsage::new(usage, self.pricing.as_ref()))
+            .unwrap_or_default()
+    }
+
     /// Create the final result value from parsing the model response stream
     fn make_result(&self) -> Result<Message, LoopError> {
         if let Some(err) = self.stream_err.as_ref() {
-            Err(LoopError::Stream(err.clone()))
+            match err.kind {
+                StreamErrorKind::Disconnected
+                    if self.tool_uses.is_empty()
+                        && self.parsing_tool_use.is_none()
+                        && !self.assistant_text.trim().is_empty() =>
+                {
+                    Err(LoopError::Disconnected {
+                        assistant_text: self.assistant_text.clone(),
+                        source: err.clone(),
+                    })
+                },
+                _ => Err(LoopError::Stream(err.clone())),
+            }
         } else if !self.invalid_tool_uses.is_empty() {
             Err(LoopError::InvalidJson {
                 invalid_tools: self.invalid_tool_uses.clone(),
@@ -658,9 +744,10 @@ impl AgentLoopHandle {
         &mut self,
         model: Arc<dyn Model>,
         args: SendRequestArgs,
+        timeouts: StreamTimeouts,
     ) -> Result<AgentLoopResponse, AgentLoopResponseError> {
         self.sender
-            .send_recv(AgentLoopRequest::SendRequest { model, args })
+            .send_recv(AgentLoopRequest::SendRequest { model, args, timeouts })
             .await
             .unwrap_or(Err(AgentLoopResponseError::AgentLoopExited))
     }
@@ -704,28 +791,185 @@ impl Drop for AgentLoopHandle {
     }
 }
 
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-//     use crate::agent_loop::model::MockModel;
-//
-//     #[tokio::test]
-//     async fn test_agent_loop() {
-//         let mut handle = AgentLoop::new(AgentLoopId::new("test".into()),
-//         CancellationToken::new()).spawn(); let model = MockModel::new();
-//
-//         handle
-//             .send_request(Arc::new(model.clone()), SendRequestArgs {
-//                 messages: vec![Message {
-//                     id: None,
-//                     role: Role::User,
-//                     content: vec!["test input".to_string().into()],
-//                     timestamp: None,
-//                 }],
-//                 tool_specs: None,
-//                 system_prompt: None,
-//             })
-//             .await
-//             .unwrap();
-//     }
-// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::agent_config::definitions::ModelConfig;
+    use crate::agent::agent_loop::model::{
+        MockModel,
+        MockResponse,
+    };
+    use crate::agent::agent_loop::model_provider::ModelProviderRegistry;
+    use crate::agent::agent_loop::types::{
+        ContentBlockDelta,
+        ContentBlockDeltaEvent,
+        MetadataUsage,
+        StopReason,
+    };
+    use crate::agent::types::AgentSettings;
+
+    fn text_response(text: &str) -> Vec<StreamResult> {
+        vec![
+            StreamResult::Ok(StreamEvent::MessageStart(MessageStartEvent { role: Role::Assistant })),
+            StreamResult::Ok(StreamEvent::ContentBlockDelta(ContentBlockDeltaEvent {
+                delta: ContentBlockDelta::Text(text.to_string()),
+                content_block_index: None,
+            })),
+            StreamResult::Ok(StreamEvent::MessageStop(MessageStopEvent {
+                stop_reason: StopReason::EndTurn,
+            })),
+        ]
+    }
+
+    /// Sends a request to a new agent loop, returning the events of the loop until the response
+    /// stream has ended and its usage is reported.
+    async fn run_request(model: Arc<dyn Model>, timeouts: StreamTimeouts) -> Vec<AgentLoopEventKind> {
+        let mut handle = AgentLoop::new(
+            AgentLoopId::new(AgentId::new("test".to_string())),
+            CancellationToken::new(),
+        )
+        .spawn();
+        let message = Message::new(Role::User, vec![ContentBlock::Text("test input".to_string())], None);
+        handle
+            .send_request(model, SendRequestArgs::new(vec![message], None, None), timeouts)
+            .await
+            .unwrap();
+
+        let mut events = Vec::new();
+        while let Some(event) = handle.recv().await {
+            let is_usage = matches!(event, AgentLoopEventKind::Usage { .. });
+            events.push(event);
+            if is_usage {
+                break;
+            }
+        }
+        events
+    }
+
+    #[tokio::test]
+    async fn test_usage_has_cost_of_configured_pricing() {
+        let mut response = text_response("hello");
+        response.insert(
+            2,
+            StreamResult::Ok(StreamEvent::Metadata(MetadataEvent {
+                metrics: None,
+                usage: Some(MetadataUsage {
+                    input_tokens: Some(1_000_000),
+                    output_tokens: Some(500_000),
+                    cache_read_input_tokens: None,
+                    cache_write_input_tokens: None,
+                }),
+                service: None,
+            })),
+        );
+        let mut models = ModelProviderRegistry::new();
+        models.register("mock", MockModel::new().with_response(response));
+        let config = ModelConfig {
+            pricing: Some(ModelPricing {
+                input: 3.0,
+                output: 15.0,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let model = models.create(Some(&config), None).unwrap();
+
+        let events = run_request(model, StreamTimeouts::default()).await;
+        let Some(AgentLoopEventKind::Usage { request, turn }) = events.last() else {
+            panic!("expected a usage event: {events:?}");
+        };
+        assert_eq!(request.input_tokens, 1_000_000);
+        assert!((request.estimated_cost.unwrap() - 10.5).abs() < 1e-9, "{request:?}");
+        assert_eq!(request, turn);
+    }
+
+    /// The duration of the stream timeout that ended the response, if any.
+    fn stream_timeout(events: &[AgentLoopEventKind]) -> Option<Duration> {
+        events.iter().find_map(|event| match event {
+            AgentLoopEventKind::ResponseStreamEnd {
+                result: Err(LoopError::Stream(err) | LoopError::Disconnected { source: err, .. }),
+                ..
+            } => match err.kind {
+                StreamErrorKind::StreamTimeout { duration } => Some(duration),
+                _ => None,
+            },
+            _ => None,
+        })
+    }
+
+    /// Sends a request to a mock model from the registry, with the stream timeouts that the
+    /// settings have for its provider.
+    async fn run_with_provider_timeouts(response: MockResponse, timeouts: StreamTimeouts) -> Vec<AgentLoopEventKind> {
+        let mut models = ModelProviderRegistry::new();
+        models.register("mock", MockModel::new().with_response(response));
+        let model = models.create(None, None).unwrap();
+        let mut settings = AgentSettings::default();
+        settings.provider_stream_timeouts.insert("mock".to_string(), timeouts);
+        assert_eq!(settings.stream_timeouts(model.provider()), timeouts);
+        run_request(model, timeouts).await
+    }
+
+    const SHORT: Duration = Duration::from_millis(50);
+    const LONG: Duration = Duration::from_secs(30);
+
+    #[tokio::test]
+    async fn test_first_event_timeout() {
+        let response = MockResponse::from(text_response("hello")).with_delay_before(0, Duration::from_secs(5));
+        let timeouts = StreamTimeouts {
+            first_event: SHORT,
+            between_events: LONG,
+            reasoning: LONG,
+        };
+        let events = run_with_provider_timeouts(response, timeouts).await;
+        assert_eq!(stream_timeout(&events), Some(SHORT), "{events:?}");
+    }
+
+    #[tokio::test]
+    async fn test_between_events_timeout() {
+        let response = MockResponse::from(text_response("hello")).with_delay_before(2, Duration::from_secs(5));
+        let timeouts = StreamTimeouts {
+            first_event: LONG,
+            between_events: SHORT,
+            reasoning: LONG,
+        };
+        let events = run_with_provider_timeouts(response, timeouts).await;
+        assert_eq!(stream_timeout(&events), Some(SHORT), "{events:?}");
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_timeout() {
+        let mut items = text_response("hello");
+        items.insert(
+            1,
+            StreamResult::Ok(StreamEvent::ContentBlockDelta(ContentBlockDeltaEvent {
+                delta: ContentBlockDelta::Reasoning,
+                content_block_index: None,
+            })),
+        );
+
+        // Reasoning may go quiet for longer than the time allowed between other events
+        let response = MockResponse::from(items.clone()).with_delay_before(2, Duration::from_millis(200));
+        let timeouts = StreamTimeouts {
+            first_event: LONG,
+            between_events: SHORT,
+            reasoning: LONG,
+        };
+        let events = run_with_provider_timeouts(response, timeouts).await;
+        assert_eq!(stream_timeout(&events), None, "{events:?}");
+        assert!(
+            events
+                .iter()
+                .any(|event| matches!(event, AgentLoopEventKind::ResponseStreamEnd { result: Ok(_), .. })),
+            "{events:?}"
+        );
+
+        let response = MockResponse::from(items).with_delay_before(2, Duration::from_secs(5));
+        let timeouts = StreamTimeouts {
+            first_event: LONG,
+            between_events: LONG,
+            reasoning: SHORT,
+        };
+        let events = run_with_provider_timeouts(response, timeouts).await;
+        assert_eq!(stream_timeout(&events), Some(SHORT), "{events:?}");
+    }
+}
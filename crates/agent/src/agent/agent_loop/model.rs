@@ -23,17 +23,24 @@ use super::types::{
     Message,
     ToolSpec,
 };
+use crate::agent::agent_config::definitions::{
+    InferenceParams,
+    ModelPricing,
+};
 
 /// Represents a backend implementation for a converse stream compatible API.
 ///
 /// **Important** - implementations should be cancel safe
 pub trait Model: std::fmt::Debug + Send + Sync + 'static {
     /// Sends a conversation to a model, returning a stream of events as the response.
+    ///
+    /// Implementations should ignore the `inference_params` their backend doesn't support.
     fn stream(
         &self,
         messages: Vec<Message>,
         tool_specs: Option<Vec<ToolSpec>>,
         system_prompt: Option<String>,
+        inference_params: InferenceParams,
         cancel_token: CancellationToken,
     ) -> Pin<Box<dyn Stream<Item = StreamResult> + Send + 'static>>;
 
@@ -44,6 +51,21 @@ pub trait Model: std::fmt::Debug + Send + Sync + 'static {
     fn state(&self) -> Option<serde_json::Value> {
         None
     }
+
+    /// Prices of the model, used to estimate the cost of its responses.
+    fn pricing(&self) -> Option<ModelPricing> {
+        None
+    }
+
+    /// Name of the provider that created the model, if it was created by a registered provider.
+    fn provider(&self) -> Option<&str> {
+        None
+    }
+
+    /// Whether the backend takes the `inference_params` passed to [Model::stream].
+    fn supports_inference_params(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -73,18 +95,24 @@ impl Default for MockModel {
 #[derive(Debug, Clone, Default)]
 pub struct MockResponse {
     items: Vec<StreamResult>,
-    /// Delay before sending the first stream result.
-    time_to_first_chunk_delay: Option<Duration>,
+    /// Delays before sending stream results, by the index of the result.
+    delays: Vec<(usize, Duration)>,
 }
 
 impl MockResponse {
+    /// Waits for `delay` before sending the stream result at `index`.
+    pub fn with_delay_before(mut self, index: usize, delay: Duration) -> Self {
+        self.delays.push((index, delay));
+        self
+    }
+
     async fn stream(self, tx: mpsc::Sender<StreamResult>) {
         trace!(?self.items, "beginning stream for mock response");
-        if let Some(delay) = self.time_to_first_chunk_delay {
-            debug!(?self.time_to_first_chunk_delay, "sleeping before sending first event");
-            tokio::time::sleep(delay).await;
-        }
-        for item in self.items {
+        for (index, item) in self.items.into_iter().enumerate() {
+            for (_, delay) in self.delays.iter().filter(|(i, _)| *i == index) {
+                debug!(?delay, index, "sleeping before sending event");
+                tokio::time::sleep(*delay).await;
+            }
             let _ = tx.send(item).await;
         }
     }
@@ -99,18 +127,43 @@ impl From<Vec<StreamResult>> for MockResponse {
     }
 }
 
+/// Parses recorded model responses for [MockModel]: one JSON [StreamResult] per line, with an
+/// empty line between responses. Lines starting with `//` are comments.
+pub fn parse_response_streams(content: &str) -> Result<Vec<Vec<StreamResult>>, serde_json::Error> {
+    let mut streams = Vec::new();
+    let mut curr_stream = Vec::new();
+    for line in content.lines() {
+        if line.starts_with("//") {
+            continue;
+        }
+        if line.is_empty() {
+            if !curr_stream.is_empty() {
+                streams.push(std::mem::take(&mut curr_stream));
+            }
+            continue;
+        }
+        curr_stream.push(serde_json::from_str(line)?);
+    }
+    if !curr_stream.is_empty() {
+        streams.push(curr_stream);
+    }
+    Ok(streams)
+}
+
 impl Model for MockModel {
     fn stream(
         &self,
         messages: Vec<Message>,
         tool_specs: Option<Vec<ToolSpec>>,
         system_prompt: Option<String>,
+        inference_params: InferenceParams,
         _cancel_token: CancellationToken,
     ) -> Pin<Box<dyn Stream<Item = StreamResult> + Send + 'static>> {
         let req = SendRequestArgs {
             messages: messages.clone(),
             tool_specs: tool_specs.clone(),
             system_prompt: system_prompt.clone(),
+            inference_params,
         };
         let mut r = self.inner.lock().unwrap();
         let Some(mock_response) = r.mock_responses.get(r.response_index).cloned() else {
@@ -126,6 +179,10 @@ impl Model for MockModel {
         });
         Box::pin(ReceiverStream::new(rx))
     }
+
+    fn supports_inference_params(&self) -> bool {
+        true
+    }
 }
 
 mod mock {
@@ -202,12 +259,26 @@ mod tests {
             .with_response(make_mock_response("first"))
             .with_response(make_mock_response("second"));
 
-        let result = model.stream(vec![], None, None, CancellationToken::new());
+        let result = model.stream(vec![], None, None, InferenceParams::default(), CancellationToken::new());
         let events = consume_response(result).await;
         assert_contains_text(&events, "first");
 
-        let result = model.stream(vec![], None, None, CancellationToken::new());
+        let result = model.stream(vec![], None, None, InferenceParams::default(), CancellationToken::new());
         let events = consume_response(result).await;
         assert_contains_text(&events, "second");
     }
+
+    #[test]
+    fn test_parse_response_streams() {
+        let first = serde_json::to_string(&make_mock_response("first")[0]).unwrap();
+        let second = serde_json::to_string(&make_mock_response("second")[1]).unwrap();
+        let content = format!("// a comment\n{first}\n{second}\n\n\n{first}\n");
+        let streams = parse_response_streams(&content).unwrap();
+        assert_eq!(streams.len(), 2);
+        assert_eq!(streams[0].len(), 2);
+        assert_eq!(streams[1].len(), 1);
+        assert_contains_text(&streams[0], "second");
+
+        assert!(parse_response_streams("not json").is_err());
+    }
 }
@@ -0,0 +1,361 @@
+//! Registry of the backends that [Model]s are created from at runtime, such as OpenAI compatible
+//! endpoints, Bedrock Converse or local models, so that each agent can select one with the
+//! `model` of its config.
+
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use futures::Stream;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tokio_util::sync::CancellationToken;
+
+use super::model::{
+    MockModel,
+    Model,
+};
+use super::protocol::StreamResult;
+use super::types::{
+    Message,
+    ToolSpec,
+};
+use crate::agent::agent_config::definitions::{
+    InferenceParams,
+    ModelConfig,
+    ModelPricing,
+};
+
+/// Creates the [Model]s of one backend.
+pub trait ModelProvider: std::fmt::Debug + Send + Sync + 'static {
+    /// Creates a model from the settings in an agent config.
+    ///
+    /// `state` is what a model of this provider returned from [Model::state], when resuming a
+    /// conversation. It takes precedence over `config`, so the conversation continues with the
+    /// same model.
+    fn create(
+        &self,
+        config: &ModelConfig,
+        state: Option<serde_json::Value>,
+    ) -> Result<Arc<dyn Model>, ModelProviderError>;
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ModelProviderError {
+    #[error("no model provider is registered with the name '{0}'")]
+    NotRegistered(String),
+    #[error("no model provider is selected, and there is no default provider")]
+    NoDefault,
+    #[error("invalid settings for the '{provider}' model provider: {message}")]
+    InvalidConfig { provider: String, message: String },
+    #[error("invalid saved state for the '{provider}' model provider: {source}")]
+    InvalidState {
+        provider: String,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+/// The state saved for the model of an agent in
+/// [AgentSnapshot::model_state](crate::types::AgentSnapshot::model_state), which records the
+/// provider along with the state of its model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelsState {
+    pub provider: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<serde_json::Value>,
+}
+
+impl ModelsState {
+    /// Parses a saved model state. Snapshots from before providers were recorded only hold the
+    /// state of the model, which is returned for the default provider.
+    pub fn from_value(value: serde_json::Value) -> Self {
+        match serde_json::from_value::<Self>(value.clone()) {
+            Ok(state) => state,
+            Err(_) => Self {
+                provider: String::new(),
+                state: Some(value),
+            },
+        }
+    }
+}
+
+/// The model providers available to agents, by name.
+#[derive(Debug, Clone, Default)]
+pub struct ModelProviderRegistry {
+    providers: HashMap<String, Arc<dyn ModelProvider>>,
+    /// Provider of the agents that don't select one, the first registered unless set
+    default_provider: Option<String>,
+}
+
+impl ModelProviderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `provider` under `name`, replacing the provider registered with it before.
+    pub fn register(&mut self, name: impl Into<String>, provider: impl ModelProvider) -> &mut Self {
+        let name = name.into();
+        self.default_provider.get_or_insert_with(|| name.clone());
+        self.providers.insert(name, Arc::new(provider));
+        self
+    }
+
+    /// Makes the provider registered under `name` the default one.
+    pub fn set_default(&mut self, name: impl Into<String>) -> Result<&mut Self, ModelProviderError> {
+        let name = name.into();
+        if !self.providers.contains_key(&name) {
+            return Err(ModelProviderError::NotRegistered(name));
+        }
+        self.default_provider = Some(name);
+        Ok(self)
+    }
+
+    pub fn default_provider(&self) -> Option<&str> {
+        self.default_provider.as_deref()
+    }
+
+    /// Names of the registered providers, sorted.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names = self.providers.keys().map(String::as_str).collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    /// Creates the model of an agent, from its config and the model state of its snapshot.
+    ///
+    /// The provider that saved `state` is used when resuming, so a conversation isn't moved to
+    /// another backend by a config change. Otherwise the provider is selected by `config`, or is
+    /// the default one.
+    pub fn create(
+        &self,
+        config: Option<&ModelConfig>,
+        state: Option<serde_json::Value>,
+    ) -> Result<Arc<dyn Model>, ModelProviderError> {
+        let default_config = ModelConfig::default();
+        let config = config.unwrap_or(&default_config);
+        let state = state.map(ModelsState::from_value);
+
+        let name = match (&state, &config.provider) {
+            (Some(state), _) if !state.provider.is_empty() => state.provider.clone(),
+            (_, Some(provider)) => provider.clone(),
+            _ => self.default_provider.clone().ok_or(ModelProviderError::NoDefault)?,
+        };
+        let provider = self
+            .providers
+            .get(&name)
+            .ok_or_else(|| ModelProviderError::NotRegistered(name.clone()))?;
+
+        let model = provider.create(config, state.and_then(|state| state.state))?;
+        Ok(Arc::new(ProviderModel {
+            provider: name,
+            pricing: config.pricing,
+            inner: model,
+        }))
+    }
+}
+
+/// A model created by a registered provider, whose state records the provider.
+#[derive(Debug)]
+struct ProviderModel {
+    provider: String,
+    /// Prices of the agent config, which take precedence over the ones of the model
+    pricing: Option<ModelPricing>,
+    inner: Arc<dyn Model>,
+}
+
+impl Model for ProviderModel {
+    fn stream(
+        &self,
+        messages: Vec<Message>,
+        tool_specs: Option<Vec<ToolSpec>>,
+        system_prompt: Option<String>,
+        inference_params: InferenceParams,
+        cancel_token: CancellationToken,
+    ) -> Pin<Box<dyn Stream<Item = StreamResult> + Send + 'static>> {
+        self.inner
+            .stream(messages, tool_specs, system_prompt, inference_params, cancel_token)
+    }
+
+    fn state(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(ModelsState {
+            provider: self.provider.clone(),
+            state: self.inner.state(),
+        })
+        .ok()
+    }
+
+    fn pricing(&self) -> Option<ModelPricing> {
+        self.pricing.or_else(|| self.inner.pricing())
+    }
+
+    fn provider(&self) -> Option<&str> {
+        Some(&self.provider)
+    }
+
+    fn supports_inference_params(&self) -> bool {
+        self.inner.supports_inference_params()
+    }
+}
+
+/// Every model created shares the mocked responses, as for a single model.
+impl ModelProvider for MockModel {
+    fn create(
+        &self,
+        _config: &ModelConfig,
+        _state: Option<serde_json::Value>,
+    ) -> Result<Arc<dyn Model>, ModelProviderError> {
+        Ok(Arc::new(self.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// Records the models it creates, and saves their id as state.
+    #[derive(Debug, Default)]
+    struct TestProvider {
+        created: Arc<Mutex<Vec<(Option<String>, Option<serde_json::Value>)>>>,
+    }
+
+    #[derive(Debug)]
+    struct TestModel(Option<String>);
+
+    impl Model for TestModel {
+        fn stream(
+            &self,
+            _messages: Vec<Message>,
+            _tool_specs: Option<Vec<ToolSpec>>,
+            _system_prompt: Option<String>,
+            _inference_params: InferenceParams,
+            _cancel_token: CancellationToken,
+        ) -> Pin<Box<dyn Stream<Item = StreamResult> + Send + 'static>> {
+            Box::pin(futures::stream::empty())
+        }
+
+        fn state(&self) -> Option<serde_json::Value> {
+            self.0.clone().map(serde_json::Value::String)
+        }
+    }
+
+    impl ModelProvider for TestProvider {
+        fn create(
+            &self,
+            config: &ModelConfig,
+            state: Option<serde_json::Value>,
+        ) -> Result<Arc<dyn Model>, ModelProviderError> {
+            self.created
+                .lock()
+                .unwrap()
+                .push((config.model_id.clone(), state.clone()));
+            let model_id = match state {
+                Some(state) => serde_json::from_value(state).map_err(|source| ModelProviderError::InvalidState {
+                    provider: "test".to_string(),
+                    source,
+                })?,
+                None => config.model_id.clone(),
+            };
+            Ok(Arc::new(TestModel(model_id)))
+        }
+    }
+
+    fn config(provider: Option<&str>, model_id: &str) -> ModelConfig {
+        ModelConfig {
+            provider: provider.map(str::to_string),
+            model_id: Some(model_id.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_registry_selects_provider() {
+        let openai = TestProvider::default();
+        let ollama = TestProvider::default();
+        let (openai_created, ollama_created) = (Arc::clone(&openai.created), Arc::clone(&ollama.created));
+        let mut registry = ModelProviderRegistry::new();
+        registry.register("openai", openai).register("ollama", ollama);
+        assert_eq!(registry.names(), vec!["ollama", "openai"]);
+        assert_eq!(registry.default_provider(), Some("openai"));
+
+        let model = registry.create(Some(&config(Some("ollama"), "llama3")), None).unwrap();
+        assert_eq!(ollama_created.lock().unwrap().len(), 1);
+        assert_eq!(
+            model.state(),
+            Some(serde_json::json!({ "provider": "ollama", "state": "llama3" }))
+        );
+        assert_eq!(model.provider(), Some("ollama"));
+        assert!(!model.supports_inference_params());
+
+        let model = registry.create(None, None).unwrap();
+        assert_eq!(openai_created.lock().unwrap().as_slice(), &[(None, None)]);
+        assert_eq!(model.pricing(), None);
+        let pricing = ModelPricing {
+            input: 1.0,
+            output: 2.0,
+            ..Default::default()
+        };
+        let model = registry
+            .create(
+                Some(&ModelConfig {
+                    pricing: Some(pricing),
+                    ..Default::default()
+                }),
+                None,
+            )
+            .unwrap();
+        assert_eq!(model.pricing(), Some(pricing));
+
+        registry.set_default("ollama").unwrap();
+        registry.create(None, None).unwrap();
+        assert_eq!(ollama_created.lock().unwrap().len(), 2);
+
+        assert!(matches!(
+            registry.create(Some(&config(Some("bedrock"), "claude")), None),
+            Err(ModelProviderError::NotRegistered(name)) if name == "bedrock"
+        ));
+        assert!(registry.set_default("bedrock").is_err());
+        assert!(matches!(
+            ModelProviderRegistry::new().create(None, None),
+            Err(ModelProviderError::NoDefault)
+        ));
+    }
+
+    #[test]
+    fn test_registry_restores_state() {
+        let openai = TestProvider::default();
+        let ollama = TestProvider::default();
+        let (openai_created, ollama_created) = (Arc::clone(&openai.created), Arc::clone(&ollama.created));
+        let mut registry = ModelProviderRegistry::new();
+        registry.register("openai", openai).register("ollama", ollama);
+
+        // The saved provider wins over the config
+        let saved = serde_json::json!({ "provider": "ollama", "state": "llama3" });
+        let model = registry
+            .create(Some(&config(Some("openai"), "gpt")), Some(saved.clone()))
+            .unwrap();
+        assert_eq!(model.state(), Some(saved));
+        assert_eq!(ollama_created.lock().unwrap().as_slice(), &[(
+            Some("gpt".to_string()),
+            Some(serde_json::json!("llama3"))
+        )]);
+
+        // State saved without a provider belongs to the default one
+        let model = registry.create(None, Some(serde_json::json!("legacy"))).unwrap();
+        assert_eq!(
+            model.state(),
+            Some(serde_json::json!({ "provider": "openai", "state": "legacy" }))
+        );
+        assert_eq!(openai_created.lock().unwrap().len(), 1);
+
+        assert!(matches!(
+            registry.create(None, Some(serde_json::json!({ "provider": "openai", "state": 1 }))),
+            Err(ModelProviderError::InvalidState { .. })
+        ));
+    }
+}
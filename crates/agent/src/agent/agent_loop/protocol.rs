@@ -17,6 +17,7 @@ use super::types::{
     MetadataEvent,
     StreamError,
     StreamEvent,
+    TokenUsage,
     ToolSpec,
     ToolUseBlock,
 };
@@ -25,6 +26,7 @@ use super::{
     InvalidToolUse,
     LoopState,
 };
+use crate::agent::agent_config::definitions::InferenceParams;
 
 #[derive(Debug)]
 pub enum AgentLoopRequest {
@@ -32,6 +34,7 @@ pub enum AgentLoopRequest {
     SendRequest {
         model: Arc<dyn Model>,
         args: SendRequestArgs,
+        timeouts: StreamTimeouts,
     },
     /// Ends the agent loop
     Cancel,
@@ -43,6 +46,9 @@ pub struct SendRequestArgs {
     pub messages: Vec<Message>,
     pub tool_specs: Option<Vec<ToolSpec>>,
     pub system_prompt: Option<String>,
+    /// Inference parameters of the request, ignored by models that don't support them
+    #[serde(default)]
+    pub inference_params: InferenceParams,
 }
 
 impl SendRequestArgs {
@@ -51,6 +57,37 @@ impl SendRequestArgs {
             messages,
             tool_specs,
             system_prompt,
+            inference_params: InferenceParams::default(),
+        }
+    }
+}
+
+/// How long the response stream may go without an event before it fails with
+/// [StreamErrorKind::StreamTimeout](super::types::StreamErrorKind::StreamTimeout).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamTimeouts {
+    /// Wait for the first event of the response
+    pub first_event: Duration,
+    /// Wait between two events of the response
+    pub between_events: Duration,
+    /// Wait after the model sent reasoning content, since reasoning models can think for a long
+    /// time without sending anything
+    pub reasoning: Duration,
+}
+
+impl StreamTimeouts {
+    const DEFAULT_BETWEEN_EVENTS: Duration = Duration::from_secs(60);
+    const DEFAULT_FIRST_EVENT: Duration = Duration::from_secs(120);
+    const DEFAULT_REASONING: Duration = Duration::from_secs(300);
+}
+
+impl Default for StreamTimeouts {
+    fn default() -> Self {
+        Self {
+            first_event: Self::DEFAULT_FIRST_EVENT,
+            between_events: Self::DEFAULT_BETWEEN_EVENTS,
+            reasoning: Self::DEFAULT_REASONING,
         }
     }
 }
@@ -138,6 +175,13 @@ pub enum AgentLoopEventKind {
         /// Metadata about the stream.
         metadata: StreamMetadata,
     },
+    /// Tokens used by a request, emitted after its [AgentLoopEventKind::ResponseStreamEnd].
+    Usage {
+        /// Usage of the request
+        request: TokenUsage,
+        /// Usage of the user turn so far, including the request
+        turn: TokenUsage,
+    },
     /// Metadata for the entire user turn.
     ///
     /// This is the last event that the agent loop will emit, unless another request is sent that
@@ -180,6 +224,14 @@ pub enum LoopError {
         /// Tool uses that consist of invalid JSON
         invalid_tools: Vec<InvalidToolUse>,
     },
+    /// The connection dropped after part of the response text was received, and before any tool
+    /// use.
+    #[error("{}", .source)]
+    Disconnected {
+        /// Assistant text received before the connection dropped
+        assistant_text: String,
+        source: StreamError,
+    },
     /// Errors associated with the underlying response stream.
     ///
     /// Most errors will be sourced from here.
@@ -228,6 +280,12 @@ pub struct UserTurnMetadata {
     pub total_request_count: u32,
     /// The number of tool use / tool result pairs in the turn
     pub number_of_cycles: u32,
+    /// Tokens used by the requests of the turn
+    #[serde(default)]
+    pub usage: TokenUsage,
+    /// Inference parameters of the last request of the turn
+    #[serde(default)]
+    pub inference_params: InferenceParams,
     /// Total length of time spent in the user turn until completion
     pub turn_duration: Option<Duration>,
     /// Why the user turn ended
@@ -14,6 +14,8 @@ use serde_json::Map;
 use tracing::error;
 use uuid::Uuid;
 
+use crate::agent::agent_config::definitions::ModelPricing;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum StreamEvent {
@@ -130,6 +132,12 @@ pub enum StreamErrorKind {
     StreamTimeout { duration: Duration },
     /// The stream was closed to due being interrupted (for example, on ctrl+c).
     Interrupted,
+    /// The connection dropped before the response completed, for example when the device slept
+    /// or the network changed.
+    ///
+    /// The agent continues the response when only text was received, see
+    /// [LoopError::Disconnected](super::protocol::LoopError::Disconnected).
+    Disconnected,
     /// Catch-all for errors not modeled in [StreamErrorKind].
     Other(String),
 }
@@ -147,6 +155,7 @@ impl std::fmt::Display for StreamErrorKind {
             )
             .into(),
             StreamErrorKind::Interrupted => "The stream was interrupted".into(),
+            StreamErrorKind::Disconnected => "The connection was lost during the response".into(),
             StreamErrorKind::Other(msg) => msg.as_str().into(),
         };
         write!(f, "{}", msg)
@@ -542,6 +551,71 @@ pub struct MetadataUsage {
     pub cache_write_input_tokens: Option<u64>,
 }
 
+/// Tokens used by one or more requests, along with their estimated cost.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_input_tokens: u64,
+    pub cache_write_input_tokens: u64,
+    /// Estimated cost in USD of the requests whose model has known prices
+    pub estimated_cost: Option<f64>,
+}
+
+impl TokenUsage {
+    /// The usage of a single request, whose cost is estimated from `pricing` if available.
+    pub fn new(usage: &MetadataUsage, pricing: Option<&ModelPricing>) -> Self {
+        let mut this = Self {
+            input_tokens: usage.input_tokens.unwrap_or_default(),
+            output_tokens: usage.output_tokens.unwrap_or_default(),
+            cache_read_input_tokens: usage.cache_read_input_tokens.unwrap_or_default(),
+            cache_write_input_tokens: usage.cache_write_input_tokens.unwrap_or_default(),
+            estimated_cost: None,
+        };
+        this.estimated_cost = pricing.map(|pricing| {
+            let per_token = |tokens: u64, price_per_million: f64| tokens as f64 * price_per_million / 1_000_000.0;
+            per_token(this.input_tokens, pricing.input)
+                + per_token(this.output_tokens, pricing.output)
+                + per_token(
+                    this.cache_read_input_tokens,
+                    pricing.cache_read.unwrap_or(pricing.input),
+                )
+                + per_token(
+                    this.cache_write_input_tokens,
+                    pricing.cache_write.unwrap_or(pricing.input),
+                )
+        });
+        this
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens + self.cache_read_input_tokens + self.cache_write_input_tokens
+    }
+}
+
+impl std::ops::AddAssign for TokenUsage {
+    fn add_assign(&mut self, rhs: Self) {
+        self.input_tokens += rhs.input_tokens;
+        self.output_tokens += rhs.output_tokens;
+        self.cache_read_input_tokens += rhs.cache_read_input_tokens;
+        self.cache_write_input_tokens += rhs.cache_write_input_tokens;
+        self.estimated_cost = match (self.estimated_cost, rhs.estimated_cost) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+    }
+}
+
+impl std::iter::Sum for TokenUsage {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |mut total, usage| {
+            total += usage;
+            total
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MetadataService {
@@ -565,6 +639,33 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_token_usage() {
+        let usage = MetadataUsage {
+            input_tokens: Some(2_000),
+            output_tokens: Some(500),
+            cache_read_input_tokens: Some(10_000),
+            cache_write_input_tokens: None,
+        };
+        let pricing = ModelPricing {
+            input: 3.0,
+            output: 15.0,
+            cache_read: Some(0.3),
+            cache_write: None,
+        };
+        let request = TokenUsage::new(&usage, Some(&pricing));
+        assert_eq!(request.total_tokens(), 12_500);
+        // 2k input at $3/M, 500 output at $15/M, 10k cache reads at $0.3/M
+        assert!((request.estimated_cost.unwrap() - 0.0165).abs() < 1e-9);
+
+        let unpriced = TokenUsage::new(&usage, None);
+        assert_eq!(unpriced.estimated_cost, None);
+        let total = [request, unpriced, request].into_iter().sum::<TokenUsage>();
+        assert_eq!(total.input_tokens, 6_000);
+        assert!((total.estimated_cost.unwrap() - 0.033).abs() < 1e-9);
+        assert_eq!(TokenUsage::default().estimated_cost, None);
+    }
+
     #[test]
     fn test_image_format_ser_deser() {
         test_ser_deser!(ImageFormat, ImageFormat::Gif, "gif");
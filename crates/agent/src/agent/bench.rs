@@ -0,0 +1,227 @@
+//! Workloads for benchmarking the work the agent repeats on every turn: enforcing conversation
+//! invariants, formatting the request, and serializing the snapshot.
+//!
+//! The workloads are shared by the criterion benches of this crate and `q bench`, which runs them
+//! without criterion so CI can compare the results against a baseline.
+
+use std::collections::VecDeque;
+use std::hint::black_box;
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::agent_config::definitions::AgentConfig;
+use super::agent_loop::protocol::SendRequestArgs;
+use super::agent_loop::types::{
+    ContentBlock,
+    Message,
+    Role,
+    ToolResultBlock,
+    ToolResultContentBlock,
+    ToolResultStatus,
+    ToolSpec,
+    ToolUseBlock,
+};
+use super::consts::MAX_CONVERSATION_STATE_HISTORY_LEN;
+use super::types::AgentSnapshot;
+use super::util::providers::WorkspaceProvider;
+
+/// Number of messages in the benchmarked conversations, the most the history holds
+pub const CONVERSATION_LEN: usize = MAX_CONVERSATION_STATE_HISTORY_LEN;
+
+/// Names of the tools the synthetic conversation calls
+const TOOL_NAMES: [&str; 4] = ["fs_read", "fs_write", "execute_bash", "grep"];
+
+/// A synthetic conversation and the state needed to build requests from it
+#[derive(Debug, Clone)]
+pub struct Workload {
+    pub messages: Vec<Message>,
+    pub tool_specs: Vec<ToolSpec>,
+    pub snapshot: AgentSnapshot,
+    provider: WorkspaceProvider,
+}
+
+impl Workload {
+    /// Creates a conversation of `len` messages cycling through a prompt, a tool use, its result
+    /// and a response, with text of a typical length in each. The conversation ends with a tool
+    /// result, like the history of the requests sent during a turn.
+    pub fn new(len: usize) -> Self {
+        let text = "The quick brown fox jumps over the lazy dog. ".repeat(20);
+        let offset = (4 - (len + 1) % 4) % 4;
+        let messages = (offset..len + offset)
+            .map(|i| {
+                let tool_use_id = format!("tooluse_{}", i / 4);
+                let (role, content) = match i % 4 {
+                    0 => (Role::User, vec![ContentBlock::Text(format!("Prompt {i}: {text}"))]),
+                    1 => (Role::Assistant, vec![
+                        ContentBlock::Text(text.clone()),
+                        ContentBlock::ToolUse(ToolUseBlock {
+                            tool_use_id,
+                            name: TOOL_NAMES[(i / 4) % TOOL_NAMES.len()].to_string(),
+                            input: serde_json::json!({ "path": format!("src/file_{i}.rs"), "mode": "line" }),
+                        }),
+                    ]),
+                    2 => (Role::User, vec![ContentBlock::ToolResult(ToolResultBlock {
+                        tool_use_id,
+                        content: vec![ToolResultContentBlock::Text(text.repeat(4))],
+                        status: ToolResultStatus::Success,
+                    })]),
+                    _ => (Role::Assistant, vec![ContentBlock::Text(text.clone())]),
+                };
+                Message {
+                    id: Some(format!("message_{i}")),
+                    role,
+                    content,
+                    timestamp: None,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // One of the called tools is left out, so the dummy tool spec is added as well.
+        let tool_specs = TOOL_NAMES[..TOOL_NAMES.len() - 1]
+            .iter()
+            .map(|name| ToolSpec {
+                name: name.to_string(),
+                description: text.clone(),
+                input_schema: serde_json::from_value(serde_json::json!({
+                    "type": "object",
+                    "properties": { "path": { "type": "string" }, "mode": { "type": "string" } },
+                    "required": ["path"],
+                }))
+                .expect("valid schema"),
+            })
+            .collect();
+
+        let mut snapshot = AgentSnapshot::new_empty(AgentConfig::default());
+        snapshot.conversation_state.messages = messages.clone();
+
+        Self {
+            messages,
+            tool_specs,
+            snapshot,
+            // The workspace doesn't exist, so the request has no context files, whatever the
+            // directory the workload runs in
+            provider: WorkspaceProvider::new(std::env::temp_dir().join("q-bench-workspace")),
+        }
+    }
+
+    pub fn enforce_conversation_invariants(messages: &mut VecDeque<Message>, tool_specs: &mut Vec<ToolSpec>) {
+        super::enforce_conversation_invariants(messages, tool_specs);
+    }
+
+    /// Formats a request from the conversation, including the clone of the history the agent makes
+    /// on every request.
+    pub async fn format_request(&self) -> SendRequestArgs {
+        super::format_request(
+            VecDeque::from(self.messages.clone()),
+            self.tool_specs.clone(),
+            &self.snapshot.agent_config,
+            std::iter::empty::<&str>(),
+            None,
+            &self.provider,
+        )
+        .await
+    }
+
+    pub fn serialize_snapshot(&self) -> Vec<u8> {
+        serde_json::to_vec(&self.snapshot).expect("snapshots serialize")
+    }
+
+    pub fn deserialize_snapshot(bytes: &[u8]) -> AgentSnapshot {
+        serde_json::from_slice(bytes).expect("snapshots deserialize")
+    }
+}
+
+/// Timing of one benchmark
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchResult {
+    pub name: String,
+    pub iterations: u32,
+    pub mean_us: f64,
+    pub min_us: f64,
+    pub max_us: f64,
+}
+
+impl BenchResult {
+    fn new(name: &str, samples: &[Duration]) -> Self {
+        let us = |d: &Duration| d.as_secs_f64() * 1_000_000.0;
+        Self {
+            name: name.to_string(),
+            iterations: samples.len() as u32,
+            mean_us: samples.iter().map(us).sum::<f64>() / samples.len().max(1) as f64,
+            min_us: samples.iter().map(us).fold(f64::INFINITY, f64::min),
+            max_us: samples.iter().map(us).fold(0.0, f64::max),
+        }
+    }
+}
+
+/// Runs every benchmark `iterations` times on a conversation of `len` messages.
+///
+/// Only the benchmarked call is timed, so setup such as cloning the history for
+/// `enforce_conversation_invariants` is excluded unless the agent does it on every request too.
+pub async fn run(len: usize, iterations: u32) -> Vec<BenchResult> {
+    let workload = Workload::new(len);
+    let mut results = Vec::new();
+
+    let mut samples = Vec::new();
+    for _ in 0..iterations {
+        let mut messages = VecDeque::from(workload.messages.clone());
+        let mut tool_specs = workload.tool_specs.clone();
+        let start = Instant::now();
+        Workload::enforce_conversation_invariants(&mut messages, &mut tool_specs);
+        samples.push(start.elapsed());
+        black_box(messages);
+    }
+    results.push(BenchResult::new("enforce_conversation_invariants", &samples));
+
+    let mut samples = Vec::new();
+    for _ in 0..iterations {
+        let start = Instant::now();
+        black_box(workload.format_request().await);
+        samples.push(start.elapsed());
+    }
+    results.push(BenchResult::new("format_request", &samples));
+
+    let mut samples = Vec::new();
+    let mut bytes = Vec::new();
+    for _ in 0..iterations {
+        let start = Instant::now();
+        bytes = black_box(workload.serialize_snapshot());
+        samples.push(start.elapsed());
+    }
+    results.push(BenchResult::new("snapshot_serialize", &samples));
+
+    let mut samples = Vec::new();
+    for _ in 0..iterations {
+        let start = Instant::now();
+        black_box(Workload::deserialize_snapshot(&bytes));
+        samples.push(start.elapsed());
+    }
+    results.push(BenchResult::new("snapshot_deserialize", &samples));
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_workload_request_is_valid() {
+        let workload = Workload::new(CONVERSATION_LEN);
+        let request = workload.format_request().await;
+        assert!(request.messages.len() <= CONVERSATION_LEN);
+        assert!(super::super::eval::check_request_invariants(&request).is_empty());
+
+        let results = run(CONVERSATION_LEN, 2).await;
+        assert_eq!(results.len(), 4);
+        assert!(results.iter().all(|r| r.iterations == 2 && r.min_us <= r.max_us));
+    }
+}
@@ -265,11 +265,7 @@ mod tests {
             truncate_large_messages: true,
             max_message_length: 40,
         };
-        let mut request = SendRequestArgs {
-            messages: serde_json::from_str(TEST_MESSAGES).unwrap(),
-            tool_specs: None,
-            system_prompt: None,
-        };
+        let mut request = SendRequestArgs::new(serde_json::from_str(TEST_MESSAGES).unwrap(), None, None);
 
         // WHEN
         strategy.apply_strategy(&mut request);
@@ -7,6 +7,9 @@ pub const DUMMY_TOOL_NAME: &str = "dummy";
 
 pub const MAX_RESOURCE_FILE_LENGTH: u64 = 1024 * 10;
 
+/// Time after which listing or reading the resources of an MCP server fails.
+pub const MCP_RESOURCE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
 pub const RTS_VALID_TOOL_NAME_REGEX: &str = "^[a-zA-Z][a-zA-Z0-9_-]{0,64}$";
 
 pub const MAX_TOOL_NAME_LEN: usize = 64;
@@ -16,5 +19,10 @@ pub const MAX_TOOL_SPEC_DESCRIPTION_LEN: usize = 10_004;
 /// 10 MB
 pub const MAX_IMAGE_SIZE_BYTES: u64 = 10 * 1024 * 1024;
 
+/// Prompt asking the model to continue a response that the connection dropped in the middle of.
+/// It's only sent with the request, the history keeps the partial response and its continuation
+/// as one response.
+pub const STREAM_RESUME_PROMPT: &str = "The connection dropped while you were responding. Continue your response exactly where it stopped, without repeating what you already wrote.";
+
 pub const TOOL_USE_PURPOSE_FIELD_NAME: &str = "__tool_use_purpose";
 pub const TOOL_USE_PURPOSE_FIELD_DESCRIPTION: &str = "A brief explanation why you are making this tool use.";
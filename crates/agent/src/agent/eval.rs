@@ -0,0 +1,834 @@
+//! Scenarios for evaluating the behavior of an agent, defined in YAML.
+//!
+//! A [Scenario] gives a prompt, the files of the workspace the agent runs in, and the
+//! [Expectations] for the turn: the tools called, the response, the files left in the workspace
+//! and how the turn ended. Scenarios run against a real model, or against recorded model responses
+//! in the format of the agent test fixtures.
+//!
+//! Every request sent to the model is also checked against the conversation invariants the backend
+//! enforces, so regressions in how requests are formatted fail the scenario even when the model
+//! copes with them.
+//!
+//! Scenarios can run once per [Variant] of the agent config or system prompt, and the outcomes of
+//! the variants compared with [VariantComparison], to back prompt changes with data.
+
+use std::collections::{
+    BTreeMap,
+    HashSet,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::sync::Arc;
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use eyre::{
+    Result,
+    WrapErr,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::agent_config::definitions::AgentConfig;
+use super::agent_loop::model::{
+    MockModel,
+    Model,
+    parse_response_streams,
+};
+use super::agent_loop::protocol::{
+    SendRequestArgs,
+    StreamResult,
+};
+use super::agent_loop::types::{
+    Message,
+    Role,
+    TokenUsage,
+};
+use super::mcp::McpManager;
+use super::protocol::{
+    AgentEvent,
+    AgentStopReason,
+    ApprovalResult,
+    InternalEvent,
+    SendApprovalResultArgs,
+};
+use super::types::AgentSnapshot;
+use super::util::providers::WorkspaceProvider;
+use super::{
+    Agent,
+    AgentHandle,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Scenario {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub prompt: String,
+    /// Directory copied into the workspace before the scenario runs, relative to the scenario file
+    #[serde(default)]
+    pub workspace: Option<PathBuf>,
+    /// Files written to the workspace before the scenario runs, after [Self::workspace] is copied
+    #[serde(default)]
+    pub files: BTreeMap<String, String>,
+    /// Recorded model responses, relative to the scenario file. One JSON [StreamResult] per line,
+    /// with an empty line between responses.
+    #[serde(default)]
+    pub responses: Option<PathBuf>,
+    #[serde(default)]
+    pub agent_config: Option<AgentConfig>,
+    /// Seconds the turn may take
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default)]
+    pub expect: Expectations,
+    /// Directory of the scenario file, which relative paths are resolved against
+    #[serde(skip)]
+    pub base_dir: PathBuf,
+}
+
+fn default_timeout_secs() -> u64 {
+    120
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Expectations {
+    /// Tools that must be called, in this order. Other calls may happen in between.
+    #[serde(default)]
+    pub tool_calls: Vec<ExpectedToolCall>,
+    /// Names of tools that must not be called
+    #[serde(default)]
+    pub no_tool_calls: Vec<String>,
+    /// Text the responses of the model must contain
+    #[serde(default)]
+    pub response_contains: Vec<String>,
+    /// Text the responses of the model must not contain
+    #[serde(default)]
+    pub response_not_contains: Vec<String>,
+    /// Files in the workspace after the turn, keyed by path relative to the workspace
+    #[serde(default)]
+    pub files: BTreeMap<String, ExpectedFile>,
+    #[serde(default)]
+    pub stop_reason: Option<ExpectedStopReason>,
+    /// Maximum number of requests sent to the model during the turn
+    #[serde(default)]
+    pub max_requests: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ExpectedToolCall {
+    pub name: String,
+    /// Fields the input must contain. Objects are compared recursively, so only the fields given
+    /// here are checked.
+    #[serde(default)]
+    pub input: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ExpectedFile {
+    #[serde(default = "default_exists")]
+    pub exists: bool,
+    #[serde(default)]
+    pub contains: Vec<String>,
+}
+
+fn default_exists() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, strum::Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ExpectedStopReason {
+    EndTurn,
+    MaxTurnRequests,
+    Error,
+}
+
+impl ExpectedStopReason {
+    fn matches(self, reason: &AgentStopReason) -> bool {
+        matches!(
+            (self, reason),
+            (Self::EndTurn, AgentStopReason::EndTurn)
+                | (Self::MaxTurnRequests, AgentStopReason::MaxTurnRequests)
+                | (Self::Error, AgentStopReason::Error(_))
+        )
+    }
+}
+
+/// Outcome of running a [Scenario]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScenarioReport {
+    pub name: String,
+    /// Path of the scenario file
+    pub path: PathBuf,
+    pub passed: bool,
+    /// Why the scenario failed, empty when it passed
+    pub failures: Vec<String>,
+    pub requests: usize,
+    pub tool_calls: Vec<String>,
+    /// Tokens used by the turn, if it ended without an error
+    #[serde(default)]
+    pub usage: TokenUsage,
+    pub duration_ms: u64,
+}
+
+impl Scenario {
+    pub async fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .wrap_err_with(|| format!("failed to read {}", path.display()))?;
+        let mut scenario: Self =
+            serde_yaml_ng::from_str(&content).wrap_err_with(|| format!("failed to parse {}", path.display()))?;
+        scenario.base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(scenario)
+    }
+
+    /// Loads the scenario at `path`, or every `.yaml` and `.yml` scenario in it when it is a
+    /// directory, sorted by path. Subdirectories are not searched, so they can hold workspaces.
+    pub async fn load_all(path: impl AsRef<Path>) -> Result<Vec<(PathBuf, Self)>> {
+        let path = path.as_ref();
+        if !path.is_dir() {
+            return Ok(vec![(path.to_path_buf(), Self::load(path).await?)]);
+        }
+
+        let mut paths = Vec::new();
+        let mut entries = tokio::fs::read_dir(path).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if entry.file_type().await?.is_file() && path.extension().is_some_and(|ext| ext == "yaml" || ext == "yml") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        let mut scenarios = Vec::new();
+        for path in paths {
+            let scenario = Self::load(&path).await?;
+            scenarios.push((path, scenario));
+        }
+        Ok(scenarios)
+    }
+
+    /// Returns a model replaying the recorded responses, if the scenario has them.
+    pub async fn fixture_model(&self) -> Result<Option<MockModel>> {
+        let Some(responses) = self.fixture_responses().await? else {
+            return Ok(None);
+        };
+        Ok(Some(responses.into_iter().fold(MockModel::new(), |model, response| {
+            model.with_response(response)
+        })))
+    }
+
+    async fn fixture_responses(&self) -> Result<Option<Vec<Vec<StreamResult>>>> {
+        let Some(path) = &self.responses else {
+            return Ok(None);
+        };
+        let path = self.base_dir.join(path);
+        let content = tokio::fs::read_to_string(&path)
+            .await
+            .wrap_err_with(|| format!("failed to read {}", path.display()))?;
+        Ok(Some(
+            parse_response_streams(&content).wrap_err_with(|| format!("failed to parse {}", path.display()))?,
+        ))
+    }
+
+    /// Runs the scenario's turn in a new temporary workspace, approving every tool use.
+    ///
+    /// Errors are returned for scenarios that could not be set up. Anything that goes wrong once
+    /// the prompt is sent, including the turn timing out, is reported as a failure instead.
+    pub async fn run(&self, path: impl AsRef<Path>, model: Arc<dyn Model>) -> Result<ScenarioReport> {
+        let start = Instant::now();
+        let recorded_responses = self.fixture_responses().await?.map(|responses| responses.len());
+
+        let workspace = tempfile::tempdir()?;
+        if let Some(dir) = &self.workspace {
+            copy_dir(&self.base_dir.join(dir), workspace.path()).await?;
+        }
+        for (path, content) in &self.files {
+            let relative = Path::new(path);
+            if relative.is_absolute() || relative.components().any(|c| c == std::path::Component::ParentDir) {
+                eyre::bail!("fixture file {path} is outside of the workspace");
+            }
+            let path = workspace.path().join(relative);
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&path, content)
+                .await
+                .wrap_err_with(|| format!("failed to write {}", path.display()))?;
+        }
+        tokio::fs::create_dir_all(workspace.path().join("home")).await?;
+
+        let snapshot = AgentSnapshot::new_empty(self.agent_config.clone().unwrap_or_default());
+        let mut agent = Agent::new(snapshot, model, McpManager::new().spawn()).await?;
+        agent.set_sys_provider(WorkspaceProvider::new(workspace.path()));
+        let mut agent = agent.spawn();
+
+        let mut failures = Vec::new();
+        let mut requests = Vec::new();
+        let mut usage = TokenUsage::default();
+        let result = tokio::time::timeout(
+            Duration::from_secs(self.timeout_secs),
+            drive_turn(&mut agent, &self.prompt, recorded_responses, &mut requests, &mut usage),
+        )
+        .await;
+
+        let stop_reason = match result {
+            Ok(Ok(reason)) => Some(reason),
+            Ok(Err(err)) => {
+                failures.push(format!("the turn failed: {err}"));
+                None
+            },
+            Err(_) => {
+                failures.push(format!("the turn did not end within {}s", self.timeout_secs));
+                None
+            },
+        };
+
+        for (i, request) in requests.iter().enumerate() {
+            for violation in check_request_invariants(request) {
+                failures.push(format!("request {}: {violation}", i + 1));
+            }
+        }
+
+        let messages = match agent.create_snapshot().await {
+            Ok(snapshot) => snapshot.conversation_state.messages,
+            Err(err) => {
+                failures.push(format!("failed to read the conversation: {err}"));
+                Vec::new()
+            },
+        };
+        let tool_calls = self
+            .expect
+            .check(
+                &messages,
+                stop_reason.as_ref(),
+                requests.len(),
+                workspace.path(),
+                &mut failures,
+            )
+            .await;
+
+        Ok(ScenarioReport {
+            name: self.name.clone(),
+            path: path.as_ref().to_path_buf(),
+            passed: failures.is_empty(),
+            failures,
+            requests: requests.len(),
+            tool_calls,
+            usage,
+            duration_ms: start.elapsed().as_millis() as u64,
+        })
+    }
+}
+
+impl Expectations {
+    /// Checks the outcome of a turn, adding unmet expectations to `failures`. Returns the names of
+    /// the tools that were called.
+    async fn check(
+        &self,
+        messages: &[Message],
+        stop_reason: Option<&AgentStopReason>,
+        requests: usize,
+        workspace: &Path,
+        failures: &mut Vec<String>,
+    ) -> Vec<String> {
+        let tool_uses = messages
+            .iter()
+            .filter(|m| m.role == Role::Assistant)
+            .flat_map(|m| m.tool_uses_iter())
+            .collect::<Vec<_>>();
+
+        let mut remaining = tool_uses.iter();
+        for expected in &self.tool_calls {
+            let found = remaining.any(|tool_use| {
+                tool_use.name == expected.name
+                    && expected
+                        .input
+                        .as_ref()
+                        .is_none_or(|input| json_contains(&tool_use.input, input))
+            });
+            if !found {
+                failures.push(match &expected.input {
+                    Some(input) => format!("expected a call to {} with input {input}", expected.name),
+                    None => format!("expected a call to {}", expected.name),
+                });
+                break;
+            }
+        }
+        for name in &self.no_tool_calls {
+            if tool_uses.iter().any(|tool_use| &tool_use.name == name) {
+                failures.push(format!("expected no calls to {name}"));
+            }
+        }
+
+        let response = messages
+            .iter()
+            .filter(|m| m.role == Role::Assistant)
+            .map(Message::text)
+            .collect::<Vec<_>>()
+            .join("\n");
+        for text in &self.response_contains {
+            if !response.contains(text.as_str()) {
+                failures.push(format!("expected the response to contain {text:?}"));
+            }
+        }
+        for text in &self.response_not_contains {
+            if response.contains(text.as_str()) {
+                failures.push(format!("expected the response not to contain {text:?}"));
+            }
+        }
+
+        for (path, expected) in &self.files {
+            match tokio::fs::read_to_string(workspace.join(path)).await {
+                Ok(_) if !expected.exists => failures.push(format!("expected {path} not to exist")),
+                Ok(content) => {
+                    for text in &expected.contains {
+                        if !content.contains(text.as_str()) {
+                            failures.push(format!("expected {path} to contain {text:?}"));
+                        }
+                    }
+                },
+                Err(_) if expected.exists => failures.push(format!("expected {path} to exist")),
+                Err(_) => (),
+            }
+        }
+
+        if let (Some(expected), Some(reason)) = (self.stop_reason, stop_reason) {
+            if !expected.matches(reason) {
+                failures.push(format!("expected the turn to stop with {expected}, got {reason:?}"));
+            }
+        }
+        if let Some(max) = self.max_requests {
+            if requests > max {
+                failures.push(format!("expected at most {max} requests, {requests} were sent"));
+            }
+        }
+
+        tool_uses.iter().map(|tool_use| tool_use.name.clone()).collect()
+    }
+}
+
+/// Sends `prompt` and approves every tool use until the agent stops, collecting the requests sent
+/// to the model and the tokens used.
+async fn drive_turn(
+    agent: &mut AgentHandle,
+    prompt: &str,
+    recorded_responses: Option<usize>,
+    requests: &mut Vec<SendRequestArgs>,
+    usage: &mut TokenUsage,
+) -> Result<AgentStopReason> {
+    loop {
+        if let AgentEvent::Initialized = agent.recv().await? {
+            break;
+        }
+    }
+    agent.send_prompt(prompt.to_string().into()).await?;
+    loop {
+        match agent.recv().await? {
+            AgentEvent::Stop(reason) => return Ok(reason),
+            AgentEvent::EndTurn(metadata) => *usage += metadata.usage,
+            AgentEvent::ApprovalRequest { id, .. } => {
+                agent
+                    .send_tool_use_approval_result(SendApprovalResultArgs {
+                        id,
+                        result: ApprovalResult::Approve,
+                    })
+                    .await?;
+            },
+            AgentEvent::Internal(InternalEvent::RequestSent(args)) => {
+                requests.push(args);
+                if let Some(recorded) = recorded_responses.filter(|recorded| requests.len() > *recorded) {
+                    eyre::bail!("the agent sent more requests than the {recorded} recorded responses");
+                }
+            },
+            _ => (),
+        }
+    }
+}
+
+/// A variant of the agent config that scenarios run with, to compare how it changes their outcome
+/// with the other variants.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Variant {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Agent config replacing the one of every scenario
+    #[serde(default)]
+    pub agent_config: Option<AgentConfig>,
+    /// System prompt replacing the one of the agent config
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+}
+
+impl Variant {
+    /// Loads the list of variants in the YAML file at `path`.
+    pub async fn load_all(path: impl AsRef<Path>) -> Result<Vec<Self>> {
+        let path = path.as_ref();
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .wrap_err_with(|| format!("failed to read {}", path.display()))?;
+        let variants: Vec<Self> =
+            serde_yaml_ng::from_str(&content).wrap_err_with(|| format!("failed to parse {}", path.display()))?;
+        if variants.is_empty() {
+            eyre::bail!("{} has no variants", path.display());
+        }
+        let mut names = HashSet::new();
+        if let Some(variant) = variants.iter().find(|variant| !names.insert(variant.name.as_str())) {
+            eyre::bail!(
+                "variant {} is defined more than once in {}",
+                variant.name,
+                path.display()
+            );
+        }
+        Ok(variants)
+    }
+
+    /// Returns `scenario` running with the agent config of this variant.
+    pub fn apply(&self, scenario: &Scenario) -> Scenario {
+        let mut scenario = scenario.clone();
+        if let Some(agent_config) = &self.agent_config {
+            scenario.agent_config = Some(agent_config.clone());
+        }
+        if let Some(system_prompt) = &self.system_prompt {
+            let mut agent_config = scenario.agent_config.take().unwrap_or_default();
+            match &mut agent_config {
+                AgentConfig::V2025_08_22(config) => config.system_prompt = Some(system_prompt.clone()),
+            }
+            scenario.agent_config = Some(agent_config);
+        }
+        scenario
+    }
+}
+
+/// Outcome of the same scenarios run with each [Variant].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariantComparison {
+    pub variants: Vec<VariantSummary>,
+    /// Scenarios that pass with some of the variants only
+    pub diverging: Vec<DivergingScenario>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariantSummary {
+    pub name: String,
+    pub passed: usize,
+    pub failed: usize,
+    /// Share of the scenarios that passed, from 0 to 1
+    pub success_rate: f64,
+    /// Mean number of requests sent to the model per scenario
+    pub mean_requests: f64,
+    /// Tokens used by all the scenarios
+    pub usage: TokenUsage,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DivergingScenario {
+    pub name: String,
+    pub path: PathBuf,
+    /// Names of the variants the scenario passed with
+    pub passed_with: Vec<String>,
+}
+
+impl VariantComparison {
+    /// Compares the reports of each variant, given by variant name. Every variant is expected to
+    /// have run the same scenarios.
+    pub fn new(results: &[(String, Vec<ScenarioReport>)]) -> Self {
+        let variants = results
+            .iter()
+            .map(|(name, reports)| {
+                let passed = reports.iter().filter(|report| report.passed).count();
+                let ratio = |n: usize| match reports.len() {
+                    0 => 0.0,
+                    len => n as f64 / len as f64,
+                };
+                VariantSummary {
+                    name: name.clone(),
+                    passed,
+                    failed: reports.len() - passed,
+                    success_rate: ratio(passed),
+                    mean_requests: ratio(reports.iter().map(|report| report.requests).sum()),
+                    usage: reports.iter().map(|report| report.usage).sum(),
+                }
+            })
+            .collect();
+
+        let mut outcomes: BTreeMap<&Path, (&str, Vec<String>, usize)> = BTreeMap::new();
+        for (name, reports) in results {
+            for report in reports {
+                let outcome = outcomes
+                    .entry(report.path.as_path())
+                    .or_insert_with(|| (report.name.as_str(), Vec::new(), 0));
+                outcome.2 += 1;
+                if report.passed {
+                    outcome.1.push(name.clone());
+                }
+            }
+        }
+        let diverging = outcomes
+            .into_iter()
+            .filter(|(_, (_, passed_with, runs))| !passed_with.is_empty() && passed_with.len() < *runs)
+            .map(|(path, (name, passed_with, _))| DivergingScenario {
+                name: name.to_string(),
+                path: path.to_path_buf(),
+                passed_with,
+            })
+            .collect();
+
+        Self { variants, diverging }
+    }
+}
+
+/// Returns how `request` violates the invariants the backend enforces on conversations:
+/// - The first message is from the user, without tool results
+/// - Messages alternate between the user and the assistant, ending with the user
+/// - Every tool use is answered by a tool result in the next message, and every tool result answers
+///   a tool use in the previous message
+/// - Every tool use names a tool in the tool specs
+pub fn check_request_invariants(request: &SendRequestArgs) -> Vec<String> {
+    let mut violations = Vec::new();
+    let messages = &request.messages;
+    let Some(first) = messages.first() else {
+        violations.push("the request has no messages".to_string());
+        return violations;
+    };
+    if first.role != Role::User || first.tool_results_iter().next().is_some() {
+        violations.push("the first message is not a user prompt".to_string());
+    }
+    if messages.last().is_some_and(|m| m.role != Role::User) {
+        violations.push("the last message is not from the user".to_string());
+    }
+
+    for (i, pair) in messages.windows(2).enumerate() {
+        if pair[0].role == pair[1].role {
+            violations.push(format!("messages {} and {} have the same role", i + 1, i + 2));
+        }
+    }
+
+    for (i, message) in messages.iter().enumerate() {
+        let next = messages.get(i + 1);
+        for tool_use in message.tool_uses_iter() {
+            if next.is_some_and(|next| next.get_tool_result(&tool_use.tool_use_id).is_none()) {
+                violations.push(format!("tool use {} has no result", tool_use.tool_use_id));
+            }
+        }
+        for tool_result in message.tool_results_iter() {
+            let previous = i.checked_sub(1).and_then(|i| messages.get(i));
+            if previous.is_none_or(|previous| previous.get_tool_use(&tool_result.tool_use_id).is_none()) {
+                violations.push(format!(
+                    "tool result {} does not answer a tool use",
+                    tool_result.tool_use_id
+                ));
+            }
+        }
+    }
+
+    let tool_names = request
+        .tool_specs
+        .iter()
+        .flatten()
+        .map(|spec| spec.name.as_str())
+        .collect::<HashSet<_>>();
+    for tool_use in messages.iter().flat_map(|m| m.tool_uses_iter()) {
+        if !tool_names.contains(tool_use.name.as_str()) {
+            violations.push(format!(
+                "tool use {} names an unknown tool {}",
+                tool_use.tool_use_id, tool_use.name
+            ));
+        }
+    }
+
+    violations
+}
+
+/// Whether `value` contains every field of `expected`, recursively for objects.
+pub fn json_contains(value: &serde_json::Value, expected: &serde_json::Value) -> bool {
+    match (value, expected) {
+        (serde_json::Value::Object(value), serde_json::Value::Object(expected)) => expected
+            .iter()
+            .all(|(key, expected)| value.get(key).is_some_and(|value| json_contains(value, expected))),
+        _ => value == expected,
+    }
+}
+
+async fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    let mut dirs = vec![(from.to_path_buf(), to.to_path_buf())];
+    while let Some((from, to)) = dirs.pop() {
+        tokio::fs::create_dir_all(&to).await?;
+        let mut entries = tokio::fs::read_dir(&from)
+            .await
+            .wrap_err_with(|| format!("failed to read {}", from.display()))?;
+        while let Some(entry) = entries.next_entry().await? {
+            let target = to.join(entry.file_name());
+            if entry.file_type().await?.is_dir() {
+                dirs.push((entry.path(), target));
+            } else {
+                tokio::fs::copy(entry.path(), target).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::agent_loop::types::{
+        ContentBlock,
+        ToolResultBlock,
+        ToolResultStatus,
+        ToolSpec,
+        ToolUseBlock,
+    };
+
+    fn message(role: Role, content: Vec<ContentBlock>) -> Message {
+        Message {
+            id: None,
+            role,
+            content,
+            timestamp: None,
+        }
+    }
+
+    #[test]
+    fn test_check_request_invariants() {
+        let tool_use = ContentBlock::ToolUse(ToolUseBlock {
+            tool_use_id: "1".to_string(),
+            name: "fs_read".to_string(),
+            input: serde_json::json!({}),
+        });
+        let tool_result = ContentBlock::ToolResult(ToolResultBlock {
+            tool_use_id: "1".to_string(),
+            content: Vec::new(),
+            status: ToolResultStatus::Success,
+        });
+        let spec = ToolSpec {
+            name: "fs_read".to_string(),
+            description: String::new(),
+            input_schema: Default::default(),
+        };
+
+        let valid = SendRequestArgs::new(
+            vec![
+                message(Role::User, vec![ContentBlock::Text("hi".to_string())]),
+                message(Role::Assistant, vec![tool_use.clone()]),
+                message(Role::User, vec![tool_result.clone()]),
+            ],
+            Some(vec![spec]),
+            None,
+        );
+        assert!(check_request_invariants(&valid).is_empty());
+
+        let invalid = SendRequestArgs::new(
+            vec![
+                message(Role::User, vec![tool_result]),
+                message(Role::Assistant, vec![tool_use]),
+                message(Role::Assistant, vec![ContentBlock::Text("hi".to_string())]),
+            ],
+            None,
+            None,
+        );
+        let violations = check_request_invariants(&invalid);
+        assert_eq!(violations, vec![
+            "the first message is not a user prompt",
+            "the last message is not from the user",
+            "messages 2 and 3 have the same role",
+            "tool result 1 does not answer a tool use",
+            "tool use 1 has no result",
+            "tool use 1 names an unknown tool fs_read",
+        ]);
+    }
+
+    #[test]
+    fn test_json_contains() {
+        let value = serde_json::json!({ "path": "a.txt", "options": { "mode": "line", "limit": 1 } });
+        assert!(json_contains(
+            &value,
+            &serde_json::json!({ "options": { "mode": "line" } })
+        ));
+        assert!(!json_contains(&value, &serde_json::json!({ "path": "b.txt" })));
+    }
+
+    #[test]
+    fn test_variant_comparison() {
+        let report = |path: &str, passed: bool, requests: usize, output_tokens: u64| ScenarioReport {
+            name: path.to_string(),
+            path: PathBuf::from(path),
+            passed,
+            failures: Vec::new(),
+            requests,
+            tool_calls: Vec::new(),
+            usage: TokenUsage {
+                output_tokens,
+                ..Default::default()
+            },
+            duration_ms: 0,
+        };
+        let comparison = VariantComparison::new(&[
+            ("baseline".to_string(), vec![
+                report("a", true, 2, 10),
+                report("b", false, 4, 20),
+            ]),
+            ("concise".to_string(), vec![
+                report("a", true, 1, 5),
+                report("b", true, 2, 5),
+            ]),
+        ]);
+
+        let baseline = &comparison.variants[0];
+        assert_eq!((baseline.passed, baseline.failed), (1, 1));
+        assert_eq!(baseline.success_rate, 0.5);
+        assert_eq!(baseline.mean_requests, 3.0);
+        assert_eq!(baseline.usage.output_tokens, 30);
+        assert_eq!(comparison.variants[1].success_rate, 1.0);
+
+        assert_eq!(comparison.diverging.len(), 1);
+        assert_eq!(comparison.diverging[0].name, "b");
+        assert_eq!(comparison.diverging[0].passed_with, vec!["concise"]);
+    }
+
+    #[test]
+    fn test_variant_apply() {
+        let scenario: Scenario = serde_yaml_ng::from_str("name: test\nprompt: hi").unwrap();
+        let variant: Variant = serde_yaml_ng::from_str("name: concise\nsystemPrompt: Answer briefly.").unwrap();
+        let scenario = variant.apply(&scenario);
+        assert_eq!(
+            scenario.agent_config.as_ref().and_then(|config| config.system_prompt()),
+            Some("Answer briefly.")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_example_scenarios() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("evals");
+        let scenarios = Scenario::load_all(&dir).await.unwrap();
+        assert!(!scenarios.is_empty());
+        for (path, scenario) in scenarios {
+            let model = scenario
+                .fixture_model()
+                .await
+                .unwrap()
+                .expect("example scenarios have responses");
+            let report = scenario.run(&path, Arc::new(model)).await.unwrap();
+            assert!(report.passed, "{}: {:?}", report.name, report.failures);
+        }
+    }
+}
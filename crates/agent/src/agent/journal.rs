@@ -0,0 +1,297 @@
+//! Recording of a conversation that can be replayed deterministically, for reproducing agent bugs.
+//!
+//! A [Journal] holds the initial [AgentSnapshot], and for each user turn the prompt, the events of
+//! every model response, the tool results and the approvals. [Replay] drives a new agent with
+//! them: the model is a [MockModel] returning the recorded responses, and tools return the
+//! recorded results instead of executing.
+
+use std::collections::HashMap;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::sync::Arc;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tracing::warn;
+
+use super::agent_config::definitions::AgentConfig;
+use super::agent_loop::model::MockModel;
+use super::agent_loop::protocol::{
+    AgentLoopEventKind,
+    StreamResult,
+};
+use super::mcp::McpManager;
+use super::protocol::{
+    AgentError,
+    AgentEvent,
+    AgentStopReason,
+    ApprovalResult,
+    InternalEvent,
+    SendApprovalResultArgs,
+    SendPromptArgs,
+};
+use super::task_executor::{
+    TaskExecutorEvent,
+    ToolExecutorResult,
+};
+use super::types::AgentSnapshot;
+use super::{
+    Agent,
+    AgentHandle,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Journal {
+    /// State of the agent before the first recorded turn
+    pub snapshot: AgentSnapshot,
+    pub turns: Vec<JournalTurn>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalTurn {
+    pub prompt: SendPromptArgs,
+    /// The events of each model response, in the order the requests were sent
+    pub responses: Vec<Vec<StreamResult>>,
+    /// Results of the executed tools, keyed by tool use id
+    pub tool_results: HashMap<String, ToolExecutorResult>,
+    pub approvals: Vec<SendApprovalResultArgs>,
+}
+
+impl Journal {
+    pub async fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        Ok(serde_json::from_slice(&tokio::fs::read(path).await?)?)
+    }
+
+    pub async fn save(&self, path: impl AsRef<Path>) -> eyre::Result<()> {
+        Ok(tokio::fs::write(path, serde_json::to_vec_pretty(self)?).await?)
+    }
+}
+
+/// Builds a [Journal] from the requests a client sends to an agent and the events it receives.
+///
+/// Model stream events and tool results are [InternalEvent]s, which are dropped for handles that
+/// fall behind, so the handle the events are received from should be drained promptly.
+#[derive(Debug, Clone)]
+pub struct JournalRecorder {
+    journal: Journal,
+}
+
+impl JournalRecorder {
+    pub fn new(snapshot: AgentSnapshot) -> Self {
+        Self {
+            journal: Journal {
+                snapshot,
+                turns: Vec::new(),
+            },
+        }
+    }
+
+    pub fn record_prompt(&mut self, prompt: &SendPromptArgs) {
+        self.journal.turns.push(JournalTurn {
+            prompt: prompt.clone(),
+            responses: Vec::new(),
+            tool_results: HashMap::new(),
+            approvals: Vec::new(),
+        });
+    }
+
+    pub fn record_approval(&mut self, approval: &SendApprovalResultArgs) {
+        if let Some(turn) = self.journal.turns.last_mut() {
+            turn.approvals.push(approval.clone());
+        }
+    }
+
+    pub fn record_event(&mut self, event: &AgentEvent) {
+        let (Some(turn), AgentEvent::Internal(event)) = (self.journal.turns.last_mut(), event) else {
+            return;
+        };
+        match event {
+            InternalEvent::RequestSent(_) => turn.responses.push(Vec::new()),
+            InternalEvent::AgentLoop(event) => {
+                if let (AgentLoopEventKind::Stream(result), Some(response)) = (&event.kind, turn.responses.last_mut()) {
+                    response.push(result.clone());
+                }
+            },
+            InternalEvent::TaskExecutor(event) => {
+                if let TaskExecutorEvent::ToolExecutionEnd(end) = event.as_ref() {
+                    turn.tool_results
+                        .insert(end.id.tool_use_id().to_string(), end.result.clone());
+                }
+            },
+            _ => (),
+        }
+    }
+
+    pub fn journal(&self) -> &Journal {
+        &self.journal
+    }
+}
+
+/// Records the conversation of an agent into a journal file, saved whenever a turn stops.
+///
+/// Unlike a [JournalRecorder] fed from an [AgentHandle], this sees every event the agent emits,
+/// including the internal events a lagging handle would miss.
+#[derive(Debug)]
+pub(crate) struct JournalFile {
+    recorder: JournalRecorder,
+    path: PathBuf,
+}
+
+impl JournalFile {
+    pub(crate) fn new(snapshot: AgentSnapshot, path: PathBuf) -> Self {
+        Self {
+            recorder: JournalRecorder::new(snapshot),
+            path,
+        }
+    }
+
+    pub(crate) fn record_prompt(&mut self, prompt: &SendPromptArgs) {
+        self.recorder.record_prompt(prompt);
+    }
+
+    pub(crate) fn record_approval(&mut self, approval: &SendApprovalResultArgs) {
+        self.recorder.record_approval(approval);
+    }
+
+    /// Records the events about to be emitted, saving the journal if one of them stops the turn.
+    pub(crate) async fn record_events(&mut self, events: &[AgentEvent]) {
+        for event in events {
+            self.recorder.record_event(event);
+        }
+        if events.iter().any(|event| matches!(event, AgentEvent::Stop(_))) {
+            if let Err(err) = self.recorder.journal().save(&self.path).await {
+                warn!(?err, path = ?self.path, "failed to save the journal");
+            }
+        }
+    }
+}
+
+/// Drives an agent through the turns of a [Journal], one turn at a time.
+#[derive(Debug)]
+pub struct Replay {
+    journal: Journal,
+    agent: AgentHandle,
+    /// Events the agent emitted in each replayed turn
+    events: Vec<Vec<AgentEvent>>,
+}
+
+impl Replay {
+    /// Spawns an agent from the journal's snapshot.
+    ///
+    /// Hooks and MCP servers are removed from the agent config, since replaying must not run
+    /// commands from a journal that may come from another machine. Turns that depended on them
+    /// can diverge from the recording.
+    pub async fn new(journal: Journal) -> Result<Self, AgentError> {
+        let mut model = MockModel::new();
+        let mut tool_results = HashMap::new();
+        for turn in &journal.turns {
+            for response in &turn.responses {
+                model = model.with_response(response.clone());
+            }
+            tool_results.extend(turn.tool_results.clone());
+        }
+
+        let mut snapshot = journal.snapshot.clone();
+        let AgentConfig::V2025_08_22(config) = &mut snapshot.agent_config;
+        config.hooks.clear();
+        config.mcp_servers.clear();
+        config.use_legacy_mcp_json = false;
+
+        let mut agent = Agent::new(snapshot, Arc::new(model), McpManager::new().spawn())
+            .await
+            .map_err(|err| AgentError::Custom(err.to_string()))?;
+        agent.set_replayed_tool_results(tool_results);
+        let mut agent = agent.spawn();
+
+        loop {
+            if let AgentEvent::Initialized = agent.recv().await.map_err(|_| AgentError::Channel)? {
+                break;
+            }
+        }
+
+        Ok(Self {
+            journal,
+            agent,
+            events: Vec::new(),
+        })
+    }
+
+    pub fn journal(&self) -> &Journal {
+        &self.journal
+    }
+
+    /// Number of turns replayed so far
+    pub fn replayed_turns(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Events the agent emitted in each replayed turn
+    pub fn events(&self) -> &[Vec<AgentEvent>] {
+        &self.events
+    }
+
+    pub fn agent(&self) -> &AgentHandle {
+        &self.agent
+    }
+
+    /// Replays the next turn until the agent stops, answering approval requests with the recorded
+    /// approvals. Returns why the agent stopped, or [None] if every turn was replayed.
+    ///
+    /// Fails if the agent sends a different number of requests than were recorded for the turn,
+    /// since the following turns would receive the wrong responses.
+    pub async fn replay_turn(&mut self) -> Result<Option<AgentStopReason>, AgentError> {
+        let index = self.events.len();
+        let Some(turn) = self.journal.turns.get(index).cloned() else {
+            return Ok(None);
+        };
+        self.events.push(Vec::new());
+        self.agent.send_prompt(turn.prompt).await?;
+
+        let mut requests = 0;
+        let reason = loop {
+            let event = self.agent.recv().await.map_err(|_| AgentError::Channel)?;
+            self.events[index].push(event.clone());
+            match event {
+                AgentEvent::Stop(reason) => break reason,
+                AgentEvent::ApprovalRequest { id, .. } => {
+                    // Tools that ran without an approval request when recorded, e.g. because the
+                    // permissions evaluate differently on this machine, are approved as well.
+                    let result = match turn.approvals.iter().find(|approval| approval.id == id) {
+                        Some(approval) => approval.result.clone(),
+                        None if turn.tool_results.contains_key(&id) => ApprovalResult::Approve,
+                        None => ApprovalResult::Deny {
+                            reason: Some("No approval was recorded for this tool use".to_string()),
+                        },
+                    };
+                    self.agent
+                        .send_tool_use_approval_result(SendApprovalResultArgs { id, result })
+                        .await?;
+                },
+                AgentEvent::Internal(InternalEvent::RequestSent(_)) => {
+                    requests += 1;
+                    if requests > turn.responses.len() {
+                        return Err(diverged(index, requests, turn.responses.len()));
+                    }
+                },
+                _ => (),
+            }
+        };
+        if requests != turn.responses.len() {
+            return Err(diverged(index, requests, turn.responses.len()));
+        }
+        Ok(Some(reason))
+    }
+}
+
+fn diverged(turn: usize, requests: usize, recorded: usize) -> AgentError {
+    AgentError::Custom(format!(
+        "The replay diverged from the recording: turn {} sent {requests} requests, but {recorded} were recorded",
+        turn + 1
+    ))
+}
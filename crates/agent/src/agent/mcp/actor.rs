@@ -17,18 +17,23 @@ use tokio::sync::{
     mpsc,
     oneshot,
 };
+use tokio::time::MissedTickBehavior;
 use tracing::{
     debug,
     error,
     warn,
 };
 
-use super::ExecuteToolResult;
 use super::service::{
     McpService,
     RunningMcpService,
 };
 use super::types::Prompt;
+use super::{
+    ExecuteToolResult,
+    ListResourcesResult,
+    ReadResourceResult,
+};
 use crate::agent::agent_config::definitions::McpServerConfig;
 use crate::agent::agent_loop::types::ToolSpec;
 use crate::agent::util::request_channel::{
@@ -38,12 +43,21 @@ use crate::agent::util::request_channel::{
     respond,
 };
 
+/// Time between the pings checking that a server is still responding.
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+/// Time after which a ping without a response fails.
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+/// Consecutive failed pings after which a server is considered crashed. A server whose transport
+/// closed is considered crashed on the first failed ping.
+const MAX_FAILED_PINGS: u32 = 3;
+
 /// Represents a message from an MCP server to the client.
 #[derive(Debug)]
 pub enum McpMessage {
     Tools(Result<Vec<RmcpTool>, ServiceError>),
     Prompts(Result<Vec<RmcpPrompt>, ServiceError>),
     ExecuteTool { request_id: u32, result: ExecuteToolResult },
+    Ping(Result<(), McpServerActorError>),
 }
 
 #[derive(Debug)]
@@ -88,6 +102,39 @@ impl McpServerActorHandle {
         }
     }
 
+    pub async fn list_resources(&self) -> Result<oneshot::Receiver<ListResourcesResult>, McpServerActorError> {
+        match self
+            .sender
+            .send_recv(McpServerActorRequest::ListResources)
+            .await
+            .unwrap_or(Err(McpServerActorError::Channel))?
+        {
+            McpServerActorResponse::ListResources(rx) => Ok(rx),
+            other => Err(McpServerActorError::Custom(format!(
+                "received unexpected response: {:?}",
+                other
+            ))),
+        }
+    }
+
+    pub async fn read_resource(
+        &self,
+        uri: String,
+    ) -> Result<oneshot::Receiver<ReadResourceResult>, McpServerActorError> {
+        match self
+            .sender
+            .send_recv(McpServerActorRequest::ReadResource { uri })
+            .await
+            .unwrap_or(Err(McpServerActorError::Channel))?
+        {
+            McpServerActorResponse::ReadResource(rx) => Ok(rx),
+            other => Err(McpServerActorError::Custom(format!(
+                "received unexpected response: {:?}",
+                other
+            ))),
+        }
+    }
+
     pub async fn execute_tool(
         &self,
         name: String,
@@ -112,6 +159,10 @@ impl McpServerActorHandle {
 pub enum McpServerActorRequest {
     GetTools,
     GetPrompts,
+    ListResources,
+    ReadResource {
+        uri: String,
+    },
     ExecuteTool {
         name: String,
         args: Option<serde_json::Map<String, Value>>,
@@ -122,6 +173,8 @@ pub enum McpServerActorRequest {
 enum McpServerActorResponse {
     Tools(Vec<ToolSpec>),
     Prompts(Vec<Prompt>),
+    ListResources(oneshot::Receiver<ListResourcesResult>),
+    ReadResource(oneshot::Receiver<ReadResourceResult>),
     ExecuteTool(oneshot::Receiver<ExecuteToolResult>),
 }
 
@@ -166,6 +219,8 @@ pub enum McpServerActorEvent {
     },
     /// The MCP server failed to initialize successfully
     InitializeError(String),
+    /// The MCP server stopped responding to pings. The actor exits after sending this event.
+    Crashed(String),
 }
 
 #[derive(Debug)]
@@ -185,6 +240,13 @@ pub struct McpServerActor {
     curr_tool_execution_id: u32,
     executing_tools: HashMap<u32, oneshot::Sender<ExecuteToolResult>>,
 
+    /// Whether a ping is waiting for its response
+    ping_in_flight: bool,
+    /// Pings that failed since the last successful one
+    failed_pings: u32,
+    /// Set once the server is considered crashed, with the reason
+    crash_reason: Option<String>,
+
     /// Receiver for actor requests
     req_rx: RequestReceiver<McpServerActorRequest, McpServerActorResponse, McpServerActorError>,
     /// Sender for actor events
@@ -233,6 +295,9 @@ impl McpServerActor {
                     message_rx,
                     curr_tool_execution_id: Default::default(),
                     executing_tools: Default::default(),
+                    ping_in_flight: false,
+                    failed_pings: 0,
+                    crash_reason: None,
                 };
                 let _ = s
                     .event_tx
@@ -253,7 +318,15 @@ impl McpServerActor {
     }
 
     async fn main_loop(mut self) {
+        let mut ping_interval = tokio::time::interval_at(tokio::time::Instant::now() + PING_INTERVAL, PING_INTERVAL);
+        ping_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
         loop {
+            if let Some(reason) = self.crash_reason.take() {
+                error!(server_name = &self.server_name, %reason, "MCP server stopped responding, exiting");
+                let _ = self.event_tx.send(McpServerActorEvent::Crashed(reason)).await;
+                break;
+            }
+
             tokio::select! {
                 req = self.req_rx.recv() => {
                     let Some(req) = req else {
@@ -265,6 +338,9 @@ impl McpServerActor {
                 },
                 res = self.message_rx.recv() => {
                     self.handle_mcp_message(res).await;
+                },
+                _ = ping_interval.tick(), if !self.ping_in_flight => {
+                    self.ping();
                 }
             }
         }
@@ -278,6 +354,33 @@ impl McpServerActor {
         match req {
             McpServerActorRequest::GetTools => Ok(McpServerActorResponse::Tools(self.tools.clone())),
             McpServerActorRequest::GetPrompts => Ok(McpServerActorResponse::Prompts(self.prompts.clone())),
+            // Resources are requested from the server as needed rather than kept by the actor, so
+            // that they are current. Requests are sent asynchronously to not block the actor.
+            McpServerActorRequest::ListResources => {
+                let (tx, rx) = oneshot::channel();
+                let service_handle = self.service_handle.clone();
+                tokio::spawn(async move {
+                    let result = service_handle
+                        .list_resources()
+                        .await
+                        .map(|resources| resources.into_iter().map(Into::into).collect())
+                        .map_err(McpServerActorError::from);
+                    let _ = tx.send(result);
+                });
+                Ok(McpServerActorResponse::ListResources(rx))
+            },
+            McpServerActorRequest::ReadResource { uri } => {
+                let (tx, rx) = oneshot::channel();
+                let service_handle = self.service_handle.clone();
+                tokio::spawn(async move {
+                    let result = service_handle
+                        .read_resource(uri)
+                        .await
+                        .map_err(McpServerActorError::from);
+                    let _ = tx.send(result);
+                });
+                Ok(McpServerActorResponse::ReadResource(rx))
+            },
             McpServerActorRequest::ExecuteTool { name, args } => {
                 let (tx, rx) = oneshot::channel();
                 self.curr_tool_execution_id = self.curr_tool_execution_id.wrapping_add(1);
@@ -331,9 +434,39 @@ impl McpServerActor {
                     );
                 },
             },
+            McpMessage::Ping(res) => {
+                self.ping_in_flight = false;
+                match res {
+                    Ok(()) => self.failed_pings = 0,
+                    Err(err) => {
+                        self.failed_pings += 1;
+                        warn!(?self.server_name, ?err, failed_pings = self.failed_pings, "MCP server ping failed");
+                        if is_transport_closed(&err) || self.failed_pings >= MAX_FAILED_PINGS {
+                            self.crash_reason = Some(err.to_string());
+                        }
+                    },
+                }
+            },
         }
     }
 
+    /// Asynchronously ping the server
+    fn ping(&mut self) {
+        self.ping_in_flight = true;
+        let service_handle = self.service_handle.clone();
+        let tx = self.message_tx.clone();
+        tokio::spawn(async move {
+            let res = match tokio::time::timeout(PING_TIMEOUT, service_handle.ping()).await {
+                Ok(res) => res.map_err(McpServerActorError::from),
+                Err(_) => Err(McpServerActorError::Custom(format!(
+                    "no response to a ping within {} seconds",
+                    PING_TIMEOUT.as_secs()
+                ))),
+            };
+            let _ = tx.send(McpMessage::Ping(res)).await;
+        });
+    }
+
     /// Asynchronously fetch all tools
     #[allow(dead_code)]
     fn refresh_tools(&self) {
@@ -356,3 +489,12 @@ impl McpServerActor {
         });
     }
 }
+
+fn is_transport_closed(err: &McpServerActorError) -> bool {
+    match err {
+        McpServerActorError::Service {
+            source: Some(source), ..
+        } => matches!(**source, ServiceError::TransportClosed),
+        _ => false,
+    }
+}
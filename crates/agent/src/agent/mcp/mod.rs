@@ -3,6 +3,8 @@ mod service;
 pub mod types;
 
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 
 use actor::{
     McpServerActor,
@@ -11,22 +13,34 @@ use actor::{
     McpServerActorHandle,
 };
 use futures::stream::FuturesUnordered;
-use rmcp::model::CallToolResult;
+use rmcp::model::{
+    CallToolResult,
+    ReadResourceResult as RmcpReadResourceResult,
+};
 use serde::{
     Deserialize,
     Serialize,
 };
 use serde_json::Value;
 use tokio::sync::oneshot;
+use tokio::time::Instant;
 use tokio_stream::StreamExt as _;
 use tracing::{
     debug,
     error,
     warn,
 };
-use types::Prompt;
+use types::{
+    McpResource,
+    Prompt,
+};
 
 use super::agent_loop::types::ToolSpec;
+use super::util::event_fanout::{
+    EventFanout,
+    EventReceiver,
+    FanoutEvent,
+};
 use super::util::request_channel::{
     RequestReceiver,
     new_request_channel,
@@ -37,15 +51,34 @@ use crate::agent::util::request_channel::{
     respond,
 };
 
+/// Restarts of a crashed server attempted before giving up on it.
+const MAX_RESTART_ATTEMPTS: u32 = 5;
+/// Delay before the first restart of a crashed server, doubled on each further attempt.
+const INITIAL_RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(60);
+/// Time a restarted server must run for before its restart attempts are reset.
+const RESTART_RESET_AFTER: Duration = Duration::from_secs(300);
+/// Number of status changes queued for each subscriber.
+const STATUS_EVENT_QUEUE_CAPACITY: usize = 64;
+
 #[derive(Debug, Clone)]
 pub struct McpManagerHandle {
     /// Sender for sending requests to the tool manager task
     sender: RequestSender<McpManagerRequest, McpManagerResponse, McpManagerError>,
+    status_events: Arc<EventFanout<McpServerStatusChange>>,
 }
 
 impl McpManagerHandle {
-    fn new(sender: RequestSender<McpManagerRequest, McpManagerResponse, McpManagerError>) -> Self {
-        Self { sender }
+    fn new(
+        sender: RequestSender<McpManagerRequest, McpManagerResponse, McpManagerError>,
+        status_events: Arc<EventFanout<McpServerStatusChange>>,
+    ) -> Self {
+        Self { sender, status_events }
+    }
+
+    /// Creates a receiver for the status changes of the servers launched from now on.
+    pub fn subscribe(&self) -> EventReceiver<McpServerStatusChange> {
+        self.status_events.subscribe()
     }
 
     pub async fn launch_server(
@@ -100,6 +133,44 @@ impl McpManagerHandle {
         }
     }
 
+    /// Lists the resources of a server, as currently listed by the server.
+    pub async fn list_resources(
+        &self,
+        server_name: String,
+    ) -> Result<oneshot::Receiver<ListResourcesResult>, McpManagerError> {
+        match self
+            .sender
+            .send_recv(McpManagerRequest::ListResources { server_name })
+            .await
+            .unwrap_or(Err(McpManagerError::Channel))?
+        {
+            McpManagerResponse::ListResources(rx) => Ok(rx),
+            other => Err(McpManagerError::Custom(format!(
+                "received unexpected response: {:?}",
+                other
+            ))),
+        }
+    }
+
+    pub async fn read_resource(
+        &self,
+        server_name: String,
+        uri: String,
+    ) -> Result<oneshot::Receiver<ReadResourceResult>, McpManagerError> {
+        match self
+            .sender
+            .send_recv(McpManagerRequest::ReadResource { server_name, uri })
+            .await
+            .unwrap_or(Err(McpManagerError::Channel))?
+        {
+            McpManagerResponse::ReadResource(rx) => Ok(rx),
+            other => Err(McpManagerError::Custom(format!(
+                "received unexpected response: {:?}",
+                other
+            ))),
+        }
+    }
+
     pub async fn execute_tool(
         &self,
         server_name: String,
@@ -130,8 +201,15 @@ pub struct McpManager {
     request_tx: RequestSender<McpManagerRequest, McpManagerResponse, McpManagerError>,
     request_rx: RequestReceiver<McpManagerRequest, McpManagerResponse, McpManagerError>,
 
-    initializing_servers: HashMap<String, (McpServerActorHandle, oneshot::Sender<LaunchServerResult>)>,
+    /// Servers being initialized, with the sender of the launch result unless they are being
+    /// restarted
+    initializing_servers: HashMap<String, (McpServerActorHandle, Option<oneshot::Sender<LaunchServerResult>>)>,
     servers: HashMap<String, McpServerActorHandle>,
+    /// Configs of the servers launched, used to restart them
+    configs: HashMap<String, McpServerConfig>,
+    /// Servers that crashed since they were launched
+    restarts: HashMap<String, Restart>,
+    status_events: Arc<EventFanout<McpServerStatusChange>>,
 }
 
 impl McpManager {
@@ -142,17 +220,21 @@ impl McpManager {
             request_rx,
             initializing_servers: HashMap::new(),
             servers: HashMap::new(),
+            configs: HashMap::new(),
+            restarts: HashMap::new(),
+            status_events: Arc::new(EventFanout::new(STATUS_EVENT_QUEUE_CAPACITY)),
         }
     }
 
     pub fn spawn(self) -> McpManagerHandle {
         let request_tx = self.request_tx.clone();
+        let status_events = Arc::clone(&self.status_events);
 
         tokio::spawn(async move {
             self.main_loop().await;
         });
 
-        McpManagerHandle::new(request_tx)
+        McpManagerHandle::new(request_tx, status_events)
     }
 
     async fn main_loop(mut self) {
@@ -167,6 +249,7 @@ impl McpManager {
                 let name_clone = name.clone();
                 initialized_servers.push(async { (name_clone, handle.recv().await) });
             }
+            let next_restart = self.restarts.values().filter_map(|restart| restart.at).min();
 
             tokio::select! {
                 req = self.request_rx.recv() => {
@@ -193,6 +276,16 @@ impl McpManager {
                         self.handle_mcp_actor_event(name, evt).await;
                     }
                 },
+                _ = async {
+                    match next_restart {
+                        Some(at) => tokio::time::sleep_until(at).await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    std::mem::drop(initializing_servers);
+                    std::mem::drop(initialized_servers);
+                    self.start_due_restarts();
+                },
             }
         }
     }
@@ -213,8 +306,10 @@ impl McpManager {
                     return Err(McpManagerError::ServerAlreadyLaunched { name });
                 }
                 let (tx, rx) = oneshot::channel();
-                let handle = McpServerActor::spawn(name.clone(), config);
-                self.initializing_servers.insert(name, (handle, tx));
+                let handle = McpServerActor::spawn(name.clone(), config.clone());
+                self.restarts.remove(&name);
+                self.configs.insert(name.clone(), config);
+                self.initializing_servers.insert(name, (handle, Some(tx)));
                 Ok(McpManagerResponse::LaunchServer(rx))
             },
             McpManagerRequest::GetToolSpecs { server_name } => match self.servers.get(&server_name) {
@@ -225,6 +320,14 @@ impl McpManager {
                 Some(handle) => Ok(McpManagerResponse::Prompts(handle.get_prompts().await?)),
                 None => Err(McpManagerError::ServerNotInitialized { name: server_name }),
             },
+            McpManagerRequest::ListResources { server_name } => match self.servers.get(&server_name) {
+                Some(handle) => Ok(McpManagerResponse::ListResources(handle.list_resources().await?)),
+                None => Err(McpManagerError::ServerNotInitialized { name: server_name }),
+            },
+            McpManagerRequest::ReadResource { server_name, uri } => match self.servers.get(&server_name) {
+                Some(handle) => Ok(McpManagerResponse::ReadResource(handle.read_resource(uri).await?)),
+                None => Err(McpManagerError::ServerNotInitialized { name: server_name }),
+            },
             McpManagerRequest::ExecuteTool {
                 server_name,
                 tool_name,
@@ -241,6 +344,15 @@ impl McpManager {
     async fn handle_mcp_actor_event(&mut self, server_name: String, evt: Option<McpServerActorEvent>) {
         debug!(?server_name, ?evt, "Received event from an MCP actor");
         debug_assert!(self.servers.contains_key(&server_name));
+
+        // The actor exits after a crash, closing its channel.
+        let reason = match evt {
+            Some(McpServerActorEvent::Crashed(reason)) => reason,
+            None => "Server channel closed".to_string(),
+            Some(_) => return,
+        };
+        self.servers.remove(&server_name);
+        self.schedule_restart(server_name, reason);
     }
 
     async fn handle_initializing_mcp_actor_event(&mut self, server_name: String, evt: Option<McpServerActorEvent>) {
@@ -253,24 +365,103 @@ impl McpManager {
         };
 
         // Event should always exist, otherwise indicates a bug with the initialization logic.
-        let Some(evt) = evt else {
-            let _ = tx.send(Err(McpManagerError::Custom("Server channel closed".to_string())));
-            self.initializing_servers.remove(&server_name);
-            return;
-        };
-
-        // First event from an initializing server should only be either of these Initialize variants.
-        match evt {
-            McpServerActorEvent::Initialized { .. } => {
-                let _ = tx.send(Ok(()));
-                self.servers.insert(server_name, handle);
+        let error = match evt {
+            // First event from an initializing server should only be either of these Initialize variants.
+            Some(McpServerActorEvent::Initialized { .. }) => {
+                if let Some(tx) = tx {
+                    let _ = tx.send(Ok(()));
+                }
+                if let Some(restart) = self.restarts.get_mut(&server_name) {
+                    restart.running_since = Some(Instant::now());
+                }
+                self.servers.insert(server_name.clone(), handle);
+                self.send_status(server_name, McpServerStatus::Running);
+                return;
             },
-            McpServerActorEvent::InitializeError(msg) => {
-                let _ = tx.send(Err(McpManagerError::Custom(msg)));
-                self.initializing_servers.remove(&server_name);
+            Some(McpServerActorEvent::InitializeError(msg)) => msg,
+            Some(McpServerActorEvent::Crashed(msg)) => msg,
+            None => "Server channel closed".to_string(),
+        };
+        match tx {
+            Some(tx) => {
+                let _ = tx.send(Err(McpManagerError::Custom(error.clone())));
+                self.configs.remove(&server_name);
+                self.send_status(server_name, McpServerStatus::Failed { reason: error });
             },
+            // A restart failed to initialize
+            None => self.schedule_restart(server_name, error),
         }
     }
+
+    /// Schedules the restart of a crashed server with exponential backoff, giving up after
+    /// [MAX_RESTART_ATTEMPTS].
+    fn schedule_restart(&mut self, server_name: String, reason: String) {
+        if !self.configs.contains_key(&server_name) {
+            return;
+        }
+        let restart = self.restarts.entry(server_name.clone()).or_default();
+        if restart
+            .running_since
+            .take()
+            .is_some_and(|since| since.elapsed() >= RESTART_RESET_AFTER)
+        {
+            restart.attempts = 0;
+        }
+        if restart.attempts >= MAX_RESTART_ATTEMPTS {
+            error!(?server_name, %reason, "MCP server crashed too many times, giving up on it");
+            self.restarts.remove(&server_name);
+            self.configs.remove(&server_name);
+            self.send_status(server_name, McpServerStatus::Failed { reason });
+            return;
+        }
+
+        restart.attempts += 1;
+        let attempt = restart.attempts;
+        let delay = restart_delay(attempt);
+        restart.at = Some(Instant::now() + delay);
+        warn!(?server_name, %reason, attempt, ?delay, "MCP server crashed, restarting it");
+        self.send_status(server_name, McpServerStatus::Restarting { attempt, delay, reason });
+    }
+
+    fn start_due_restarts(&mut self) {
+        let now = Instant::now();
+        for (name, restart) in &mut self.restarts {
+            if !restart.at.is_some_and(|at| at <= now) {
+                continue;
+            }
+            restart.at = None;
+            let Some(config) = self.configs.get(name) else {
+                continue;
+            };
+            if self.servers.contains_key(name) || self.initializing_servers.contains_key(name) {
+                continue;
+            }
+            debug!(?name, attempt = restart.attempts, "restarting MCP server");
+            let handle = McpServerActor::spawn(name.clone(), config.clone());
+            self.initializing_servers.insert(name.clone(), (handle, None));
+        }
+    }
+
+    fn send_status(&self, server_name: String, status: McpServerStatus) {
+        self.status_events.send(McpServerStatusChange { server_name, status });
+    }
+}
+
+/// Delay before the given restart attempt, starting at 1.
+fn restart_delay(attempt: u32) -> Duration {
+    INITIAL_RESTART_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(MAX_RESTART_DELAY)
+}
+
+#[derive(Debug, Default)]
+struct Restart {
+    /// Restart attempts since the server last ran for [RESTART_RESET_AFTER]
+    attempts: u32,
+    /// When the next attempt is made, [None] once it started
+    at: Option<Instant>,
+    /// When the last attempt finished initializing
+    running_since: Option<Instant>,
 }
 
 impl Default for McpManager {
@@ -293,6 +484,13 @@ pub enum McpManagerRequest {
     GetPrompts {
         server_name: String,
     },
+    ListResources {
+        server_name: String,
+    },
+    ReadResource {
+        server_name: String,
+        uri: String,
+    },
     ExecuteTool {
         server_name: String,
         tool_name: String,
@@ -305,10 +503,41 @@ pub enum McpManagerResponse {
     LaunchServer(oneshot::Receiver<LaunchServerResult>),
     ToolSpecs(Vec<ToolSpec>),
     Prompts(Vec<Prompt>),
+    ListResources(oneshot::Receiver<ListResourcesResult>),
+    ReadResource(oneshot::Receiver<ReadResourceResult>),
     ExecuteTool(oneshot::Receiver<ExecuteToolResult>),
 }
 
 pub type ExecuteToolResult = Result<CallToolResult, McpServerActorError>;
+pub type ListResourcesResult = Result<Vec<McpResource>, McpServerActorError>;
+pub type ReadResourceResult = Result<RmcpReadResourceResult, McpServerActorError>;
+
+/// A change in the status of a launched MCP server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpServerStatusChange {
+    pub server_name: String,
+    pub status: McpServerStatus,
+}
+
+impl FanoutEvent for McpServerStatusChange {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum McpServerStatus {
+    /// The server is initialized and its tools are available
+    Running,
+    /// The server stopped responding, and is restarted after `delay`
+    Restarting {
+        /// Restart attempt, starting at 1
+        attempt: u32,
+        delay: Duration,
+        reason: String,
+    },
+    /// The server failed to launch, or kept crashing. Its tools are unavailable until it is
+    /// launched again.
+    Failed { reason: String },
+}
 
 type LaunchServerResult = Result<(), McpManagerError>;
 
@@ -327,3 +556,42 @@ pub enum McpManagerError {
     #[error("{}", .0)]
     Custom(String),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restart_delay() {
+        assert_eq!(restart_delay(1), Duration::from_secs(1));
+        assert_eq!(restart_delay(2), Duration::from_secs(2));
+        assert_eq!(restart_delay(4), Duration::from_secs(8));
+        assert_eq!(restart_delay(10), MAX_RESTART_DELAY);
+    }
+
+    #[tokio::test]
+    async fn test_schedule_restart() {
+        let mut manager = McpManager::new();
+        let mut status_rx = manager.status_events.subscribe();
+        let config: McpServerConfig = serde_json::from_value(serde_json::json!({ "command": "my-server" })).unwrap();
+        manager.configs.insert("server".to_string(), config);
+
+        for attempt in 1..=MAX_RESTART_ATTEMPTS {
+            manager.schedule_restart("server".to_string(), "crashed".to_string());
+            let change = status_rx.recv().await.unwrap();
+            assert_eq!(change.server_name, "server");
+            assert_eq!(change.status, McpServerStatus::Restarting {
+                attempt,
+                delay: restart_delay(attempt),
+                reason: "crashed".to_string(),
+            });
+        }
+
+        manager.schedule_restart("server".to_string(), "crashed".to_string());
+        assert_eq!(status_rx.recv().await.unwrap().status, McpServerStatus::Failed {
+            reason: "crashed".to_string()
+        });
+        assert!(manager.configs.is_empty());
+        assert!(manager.restarts.is_empty());
+    }
+}
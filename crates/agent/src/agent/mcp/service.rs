@@ -4,20 +4,30 @@ use std::time::{
     Instant,
 };
 
+use http::HeaderMap;
 use rmcp::model::{
     CallToolRequestParam,
     CallToolResult,
     ClientInfo,
+    ClientRequest,
     ClientResult,
     Implementation,
     LoggingLevel,
+    PingRequest,
     Prompt as RmcpPrompt,
+    ReadResourceRequestParam,
+    ReadResourceResult as RmcpReadResourceResult,
+    Resource as RmcpResource,
     ServerNotification,
     ServerRequest,
     Tool as RmcpTool,
 };
+use rmcp::transport::sse_client::SseClientConfig;
+use rmcp::transport::streamable_http_client::StreamableHttpClientTransportConfig;
 use rmcp::transport::{
     ConfigureCommandExt as _,
+    SseClientTransport,
+    StreamableHttpClientTransport,
     TokioChildProcess,
 };
 use rmcp::{
@@ -41,7 +51,11 @@ use tracing::{
 
 use super::actor::McpMessage;
 use super::types::Prompt;
-use crate::agent::agent_config::definitions::McpServerConfig;
+use crate::agent::agent_config::definitions::{
+    McpServerConfig,
+    RemoteMcpTransport,
+    StreamableHTTPMcpServerConfig,
+};
 use crate::agent::agent_loop::types::ToolSpec;
 use crate::agent::util::expand_env_vars;
 use crate::agent::util::path::expand_path;
@@ -50,7 +64,7 @@ use crate::util::providers::RealProvider;
 /// This struct is consumed by the [rmcp] crate on server launch. The only purpose of this struct
 /// is to handle server-to-client requests. Client-side code will own a [RunningMcpService]
 /// instance.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct McpService {
     server_name: String,
     config: McpServerConfig,
@@ -70,7 +84,11 @@ impl McpService {
     /// Launches the provided MCP server, returning a client handle to the server for sending
     /// requests.
     pub async fn launch(self) -> eyre::Result<(RunningMcpService, LaunchMetadata)> {
-        match &self.config {
+        let server_name = self.server_name.clone();
+        let start_time = Instant::now();
+        info!(?server_name, "Launching MCP server");
+
+        let (service, stderr) = match &self.config {
             McpServerConfig::Local(config) => {
                 // TODO - don't use real provider
                 let cmd = expand_path(&config.command, &RealProvider)?;
@@ -89,79 +107,132 @@ impl McpService {
                     cmd.process_group(0);
                 });
                 let (process, stderr) = TokioChildProcess::builder(cmd).stderr(Stdio::piped()).spawn().unwrap();
-                let server_name = self.server_name.clone();
-
-                let start_time = Instant::now();
-                info!(?server_name, "Launching MCP server");
-                let service = self.serve(process).await?;
-                let serve_time_taken = start_time.elapsed();
-                info!(?serve_time_taken, ?server_name, "MCP server launched successfully");
-
-                let launch_md = match service.peer_info() {
-                    Some(info) => {
-                        debug!(?server_name, ?info, "peer info found");
-
-                        // Fetch tools, if we can
-                        let (tools, list_tools_duration) = if info.capabilities.tools.is_some() {
-                            let start_time = Instant::now();
-                            match service.list_all_tools().await {
-                                Ok(tools) => (
-                                    Some(tools.into_iter().map(Into::into).collect()),
-                                    Some(start_time.elapsed()),
-                                ),
-                                Err(err) => {
-                                    error!(?err, "failed to list tools during server initialization");
-                                    (None, None)
-                                },
-                            }
-                        } else {
+                (self.serve(process).await?, stderr)
+            },
+            McpServerConfig::StreamableHTTP(config) => {
+                let config = config.clone();
+                (self.serve_remote(config).await?, None)
+            },
+        };
+        let serve_time_taken = start_time.elapsed();
+        info!(?serve_time_taken, ?server_name, "MCP server launched successfully");
+
+        let launch_md = match service.peer_info() {
+            Some(info) => {
+                debug!(?server_name, ?info, "peer info found");
+
+                // Fetch tools, if we can
+                let (tools, list_tools_duration) = if info.capabilities.tools.is_some() {
+                    let start_time = Instant::now();
+                    match service.list_all_tools().await {
+                        Ok(tools) => (
+                            Some(tools.into_iter().map(Into::into).collect()),
+                            Some(start_time.elapsed()),
+                        ),
+                        Err(err) => {
+                            error!(?err, "failed to list tools during server initialization");
                             (None, None)
-                        };
-
-                        // Fetch prompts, if we can
-                        let (prompts, list_prompts_duration) = if info.capabilities.prompts.is_some() {
-                            let start_time = Instant::now();
-                            match service.list_all_prompts().await {
-                                Ok(prompts) => (
-                                    Some(prompts.into_iter().map(Into::into).collect()),
-                                    Some(start_time.elapsed()),
-                                ),
-                                Err(err) => {
-                                    error!(?err, "failed to list prompts during server initialization");
-                                    (None, None)
-                                },
-                            }
-                        } else {
+                        },
+                    }
+                } else {
+                    (None, None)
+                };
+
+                // Fetch prompts, if we can
+                let (prompts, list_prompts_duration) = if info.capabilities.prompts.is_some() {
+                    let start_time = Instant::now();
+                    match service.list_all_prompts().await {
+                        Ok(prompts) => (
+                            Some(prompts.into_iter().map(Into::into).collect()),
+                            Some(start_time.elapsed()),
+                        ),
+                        Err(err) => {
+                            error!(?err, "failed to list prompts during server initialization");
                             (None, None)
-                        };
-
-                        LaunchMetadata {
-                            serve_time_taken,
-                            tools,
-                            list_tools_duration,
-                            prompts,
-                            list_prompts_duration,
-                        }
-                    },
-                    None => {
-                        warn!(?server_name, "no peer info found");
-                        LaunchMetadata {
-                            serve_time_taken,
-                            tools: None,
-                            list_tools_duration: None,
-                            prompts: None,
-                            list_prompts_duration: None,
-                        }
-                    },
+                        },
+                    }
+                } else {
+                    (None, None)
                 };
 
-                Ok((RunningMcpService::new(server_name, service, stderr), launch_md))
+                LaunchMetadata {
+                    serve_time_taken,
+                    tools,
+                    list_tools_duration,
+                    prompts,
+                    list_prompts_duration,
+                }
             },
-            McpServerConfig::StreamableHTTP(_) => {
-                eyre::bail!("not supported");
+            None => {
+                warn!(?server_name, "no peer info found");
+                LaunchMetadata {
+                    serve_time_taken,
+                    tools: None,
+                    list_tools_duration: None,
+                    prompts: None,
+                    list_prompts_duration: None,
+                }
+            },
+        };
+
+        Ok((RunningMcpService::new(server_name, service, stderr), launch_md))
+    }
+
+    /// Connects to a remote MCP server. Without a configured transport, the streamable HTTP
+    /// transport is tried first, falling back to the legacy SSE transport.
+    async fn serve_remote(
+        self,
+        config: StreamableHTTPMcpServerConfig,
+    ) -> eyre::Result<rmcp::service::RunningService<RoleClient, Self>> {
+        let mut headers = config.headers;
+        expand_env_vars(&mut headers);
+        let client = reqwest::Client::builder()
+            .default_headers(HeaderMap::try_from(&headers)?)
+            .build()?;
+
+        match config.transport {
+            Some(RemoteMcpTransport::StreamableHttp) => self.serve_streamable_http(client, &config.url).await,
+            Some(RemoteMcpTransport::Sse) => self.serve_sse(client, &config.url).await,
+            None => match self.clone().serve_streamable_http(client.clone(), &config.url).await {
+                Ok(service) => Ok(service),
+                Err(err) => {
+                    warn!(
+                        server_name = %self.server_name,
+                        ?err,
+                        "streamable HTTP handshake failed, trying SSE"
+                    );
+                    self.serve_sse(client, &config.url).await
+                },
             },
         }
     }
+
+    async fn serve_streamable_http(
+        self,
+        client: reqwest::Client,
+        url: &str,
+    ) -> eyre::Result<rmcp::service::RunningService<RoleClient, Self>> {
+        let transport = StreamableHttpClientTransport::with_client(client, StreamableHttpClientTransportConfig {
+            uri: url.into(),
+            allow_stateless: true,
+            ..Default::default()
+        });
+        Ok(self.serve(transport).await?)
+    }
+
+    async fn serve_sse(
+        self,
+        client: reqwest::Client,
+        url: &str,
+    ) -> eyre::Result<rmcp::service::RunningService<RoleClient, Self>> {
+        let transport = SseClientTransport::start_with_client(client, SseClientConfig {
+            sse_endpoint: url.into(),
+            ..Default::default()
+        })
+        .await
+        .map_err(|err| eyre::eyre!("failed to connect to the SSE endpoint: {}", err))?;
+        Ok(self.serve(transport).await?)
+    }
 }
 
 impl rmcp::Service<RoleClient> for McpService {
@@ -309,6 +380,25 @@ impl RunningMcpService {
     pub async fn list_prompts(&self) -> Result<Vec<RmcpPrompt>, ServiceError> {
         self.running_service.peer().list_all_prompts().await
     }
+
+    pub async fn list_resources(&self) -> Result<Vec<RmcpResource>, ServiceError> {
+        self.running_service.peer().list_all_resources().await
+    }
+
+    pub async fn read_resource(&self, uri: String) -> Result<RmcpReadResourceResult, ServiceError> {
+        self.running_service
+            .peer()
+            .read_resource(ReadResourceRequestParam { uri })
+            .await
+    }
+
+    pub async fn ping(&self) -> Result<(), ServiceError> {
+        self.running_service
+            .peer()
+            .send_request(ClientRequest::PingRequest(PingRequest::default()))
+            .await
+            .map(|_| ())
+    }
 }
 
 /// Wrapper around rmcp service types to enable cloning.
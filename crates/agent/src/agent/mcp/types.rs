@@ -1,6 +1,7 @@
 use rmcp::model::{
     Prompt as RmcpPrompt,
     PromptArgument as RmcpPromptArgument,
+    Resource as RmcpResource,
     Tool as RmcpTool,
 };
 use serde::{
@@ -67,3 +68,30 @@ impl From<RmcpPromptArgument> for PromptArgument {
         }
     }
 }
+
+/// A resource listed by an MCP server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpResource {
+    /// The URI of the resource, used to read it
+    pub uri: String,
+    /// The name of the resource
+    pub name: String,
+    /// Optional description of what the resource represents
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The MIME type of the resource, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+impl From<RmcpResource> for McpResource {
+    fn from(value: RmcpResource) -> Self {
+        Self {
+            uri: value.raw.uri,
+            name: value.raw.name,
+            description: value.raw.description,
+            mime_type: value.raw.mime_type,
+        }
+    }
+}
@@ -1,9 +1,15 @@
 pub mod agent_config;
 pub mod agent_loop;
+pub mod bench;
 pub mod consts;
+pub mod eval;
+pub mod journal;
 pub mod mcp;
 mod permissions;
 pub mod protocol;
+mod resource_watcher;
+pub mod runtime;
+pub mod session;
 pub mod task_executor;
 mod tool_utils;
 pub mod tools;
@@ -23,6 +29,7 @@ use agent_config::definitions::{
     AgentConfig,
     HookConfig,
     HookTrigger,
+    InferenceParams,
 };
 use agent_config::parse::{
     CanonicalToolName,
@@ -56,8 +63,13 @@ use agent_loop::{
     LoopState,
 };
 use chrono::Utc;
-use consts::MAX_RESOURCE_FILE_LENGTH;
+use consts::{
+    MAX_RESOURCE_FILE_LENGTH,
+    MCP_RESOURCE_TIMEOUT,
+    STREAM_RESUME_PROMPT,
+};
 use futures::stream::FuturesUnordered;
+use journal::JournalFile;
 use permissions::evaluate_tool_permission;
 use protocol::{
     AgentError,
@@ -74,6 +86,12 @@ use protocol::{
     ToolCall,
     UpdateEvent,
 };
+use resource_watcher::ResourceWatcher;
+use rmcp::model::ResourceContents;
+use runtime::{
+    AgentRuntime,
+    SubagentParent,
+};
 use serde::{
     Deserialize,
     Serialize,
@@ -93,7 +111,6 @@ use task_executor::{
     ToolFuture,
 };
 use tokio::sync::{
-    broadcast,
     mpsc,
     oneshot,
 };
@@ -105,6 +122,10 @@ use tool_utils::{
     add_tool_use_purpose_arg,
     sanitize_tool_specs,
 };
+use tools::path::{
+    PathError,
+    resolve_roots,
+};
 use tools::{
     Tool,
     ToolExecutionError,
@@ -127,21 +148,31 @@ use types::{
     ConversationMetadata,
     ConversationState,
 };
+use util::event_fanout::{
+    EventFanout,
+    EventReceiver,
+    RecvError,
+};
 use util::path::canonicalize_path_sys;
 use util::providers::{
     RealProvider,
     SystemProvider,
 };
-use util::read_file_with_max_limit;
 use util::request_channel::new_request_channel;
+use util::{
+    read_file_with_max_limit,
+    truncate_safe_in_place,
+};
 
 use crate::agent::consts::{
     DUMMY_TOOL_NAME,
     MAX_CONVERSATION_STATE_HISTORY_LEN,
 };
 use crate::agent::mcp::McpManagerHandle;
+use crate::agent::tools::checkpoint::Checkpoints;
 use crate::agent::tools::{
     BuiltInTool,
+    BuiltInToolName,
     ToolKind,
     ToolState,
     built_in_tool_names,
@@ -159,10 +190,20 @@ use crate::agent::util::request_channel::{
 pub const CONTEXT_ENTRY_START_HEADER: &str = "--- CONTEXT ENTRY BEGIN ---\n";
 pub const CONTEXT_ENTRY_END_HEADER: &str = "--- CONTEXT ENTRY END ---\n\n";
 
+/// Number of events queued for each [AgentHandle] before events that can be dropped or merged are.
+const AGENT_EVENT_QUEUE_CAPACITY: usize = 1024;
+
+/// Most times a response is continued after the connection dropped, per user turn.
+const MAX_STREAM_RESUMES: u32 = 3;
+
+/// Chunks of output buffered for each tool that streams its output. Chunks past it are dropped
+/// until the buffered ones are forwarded.
+const TOOL_OUTPUT_BUFFER_SIZE: usize = 64;
+
 #[derive(Debug)]
 pub struct AgentHandle {
     sender: RequestSender<AgentRequest, AgentResponse, AgentError>,
-    event_rx: broadcast::Receiver<AgentEvent>,
+    event_rx: EventReceiver<AgentEvent>,
 }
 
 impl Clone for AgentHandle {
@@ -175,10 +216,19 @@ impl Clone for AgentHandle {
 }
 
 impl AgentHandle {
-    pub async fn recv(&mut self) -> Result<AgentEvent, broadcast::error::RecvError> {
+    /// Receives the next event emitted by the agent. Events that clients depend on, such as
+    /// approval requests and state changes, are never dropped for a handle that falls behind.
+    pub async fn recv(&mut self) -> Result<AgentEvent, RecvError> {
         self.event_rx.recv().await
     }
 
+    /// Takes the events of this handle so they can be received on another task, leaving a handle
+    /// that only sends requests. [Self::recv] fails on the remaining handle and its clones.
+    pub fn take_events(&mut self) -> EventReceiver<AgentEvent> {
+        let closed = EventFanout::new(1).subscribe();
+        std::mem::replace(&mut self.event_rx, closed)
+    }
+
     pub async fn send_prompt(&self, args: SendPromptArgs) -> Result<(), AgentError> {
         match self
             .sender
@@ -203,6 +253,47 @@ impl AgentHandle {
         }
     }
 
+    /// Restores the files modified by tools since checkpoint `id` was created, returning their
+    /// paths.
+    pub async fn revert_to_checkpoint(&self, id: u32) -> Result<Vec<PathBuf>, AgentError> {
+        match self
+            .sender
+            .send_recv(AgentRequest::RevertToCheckpoint { id })
+            .await
+            .unwrap_or(Err(AgentError::Channel))?
+        {
+            AgentResponse::RevertedFiles(paths) => Ok(paths),
+            other => Err(AgentError::Custom(format!("received unexpected response: {:?}", other))),
+        }
+    }
+
+    /// Sets the inference parameters of the conversation, see
+    /// [AgentRequest::SetInferenceParams].
+    pub async fn set_inference_params(&self, params: InferenceParams) -> Result<(), AgentError> {
+        match self
+            .sender
+            .send_recv(AgentRequest::SetInferenceParams(params))
+            .await
+            .unwrap_or(Err(AgentError::Channel))?
+        {
+            AgentResponse::Success => Ok(()),
+            other => Err(AgentError::Custom(format!("received unexpected response: {:?}", other))),
+        }
+    }
+
+    /// Interrupts the agent's execution, ending the current user turn.
+    pub async fn cancel(&self) -> Result<(), AgentError> {
+        match self
+            .sender
+            .send_recv(AgentRequest::Cancel)
+            .await
+            .unwrap_or(Err(AgentError::Channel))?
+        {
+            AgentResponse::Success => Ok(()),
+            other => Err(AgentError::Custom(format!("received unexpected response: {:?}", other))),
+        }
+    }
+
     pub async fn create_snapshot(&self) -> Result<AgentSnapshot, AgentError> {
         match self
             .sender
@@ -226,8 +317,8 @@ pub struct Agent {
     execution_state: ExecutionState,
     tool_state: ToolState,
 
-    agent_event_tx: broadcast::Sender<AgentEvent>,
-    agent_event_rx: Option<broadcast::Receiver<AgentEvent>>,
+    agent_event_tx: EventFanout<AgentEvent>,
+    agent_event_rx: Option<EventReceiver<AgentEvent>>,
 
     // TODO - use this
     agent_event_buf: Vec<AgentEvent>,
@@ -274,6 +365,23 @@ pub struct Agent {
     working_directory: Option<PathBuf>,
     /// Provider for system context like env vars, home dir, current working dir
     sys_provider: Arc<dyn SystemProvider>,
+    /// Results returned instead of executing tools, keyed by tool use id, when replaying a
+    /// recorded conversation.
+    replayed_tool_results: Option<HashMap<String, ToolExecutorResult>>,
+    /// Times a response of the current user turn was continued after the connection dropped.
+    stream_resumes: u32,
+    /// Whether the last response is being continued after the connection dropped, in which case
+    /// the history ends with the partial response and [STREAM_RESUME_PROMPT].
+    resuming_response: bool,
+    /// Files modified by tools in recent user turns, as they were before each turn
+    checkpoints: Checkpoints,
+    /// The runtime the agent was spawned in, required to spawn subagents
+    runtime: Option<AgentRuntime>,
+    /// Contents of the file resources as of the previous request, used when the agent config
+    /// watches its resources
+    resource_watcher: ResourceWatcher,
+    /// Where the conversation is recorded for `q debug replay`, if it is
+    journal: Option<JournalFile>,
 }
 
 impl Agent {
@@ -293,11 +401,17 @@ impl Agent {
     ) -> eyre::Result<Agent> {
         debug!(?snapshot, "initializing agent from snapshot");
 
-        let (agent_event_tx, agent_event_rx) = broadcast::channel(1024);
+        let agent_event_tx = EventFanout::new(AGENT_EVENT_QUEUE_CAPACITY);
+        let agent_event_rx = agent_event_tx.subscribe();
 
         let agent_config = snapshot.agent_config;
-        let cached_mcp_configs = LoadedMcpServerConfigs::from_agent_config(&agent_config).await;
-        let task_executor = TaskExecutor::new();
+        let mut cached_mcp_configs = LoadedMcpServerConfigs::from_agent_config(&agent_config).await;
+        if !snapshot.settings.mcp_enabled && !cached_mcp_configs.configs.is_empty() {
+            warn!("MCP has been disabled by an administrator, skipping the configured MCP servers");
+            cached_mcp_configs.configs.clear();
+        }
+        let mut task_executor = TaskExecutor::new();
+        task_executor.set_max_concurrent_tools(snapshot.settings.tool_execution.max_concurrent);
 
         Ok(Self {
             id: snapshot.id,
@@ -306,6 +420,7 @@ impl Agent {
             conversation_metadata: snapshot.conversation_metadata,
             execution_state: snapshot.execution_state,
             tool_state: snapshot.tool_state,
+            checkpoints: snapshot.checkpoints,
             agent_event_tx,
             agent_event_rx: Some(agent_event_rx),
             agent_event_buf: Vec::new(),
@@ -319,6 +434,12 @@ impl Agent {
             cached_mcp_configs,
             working_directory: None,
             sys_provider: Arc::new(RealProvider),
+            replayed_tool_results: None,
+            stream_resumes: 0,
+            resuming_response: false,
+            runtime: None,
+            resource_watcher: ResourceWatcher::default(),
+            journal: None,
         })
     }
 
@@ -326,6 +447,18 @@ impl Agent {
         self.sys_provider = Arc::new(provider);
     }
 
+    /// Returns the given results instead of executing tools, for replaying a recorded
+    /// conversation. Tool uses without a recorded result fail rather than execute.
+    pub fn set_replayed_tool_results(&mut self, results: HashMap<String, ToolExecutorResult>) {
+        self.replayed_tool_results = Some(results);
+    }
+
+    /// Records the conversation from now on into a [journal::Journal] saved to `path` whenever a
+    /// turn stops, which can be replayed to reproduce the session.
+    pub fn record_journal(&mut self, path: impl Into<PathBuf>) {
+        self.journal = Some(JournalFile::new(self.create_snapshot(), path.into()));
+    }
+
     /// Starts the agent task, returning a handle from which messages can be sent and events can be
     /// received.
     pub fn spawn(mut self) -> AgentHandle {
@@ -439,10 +572,14 @@ impl Agent {
 
     async fn main_loop(mut self, mut request_rx: RequestReceiver<AgentRequest, AgentResponse, AgentError>) {
         let mut task_executor_event_buf = Vec::new();
+        let mut mcp_status_rx = self.mcp_manager_handle.subscribe();
 
         loop {
+            if let Some(journal) = self.journal.as_mut() {
+                journal.record_events(&self.agent_event_buf).await;
+            }
             for event in self.agent_event_buf.drain(..) {
-                let _ = self.agent_event_tx.send(event);
+                self.agent_event_tx.send(event);
             }
 
             tokio::select! {
@@ -483,6 +620,12 @@ impl Agent {
                         self.agent_event_buf.push(evt.into());
                     }
                 }
+
+                Ok(change) = mcp_status_rx.recv() => {
+                    if self.cached_mcp_configs.configs.iter().any(|c| c.server_name == change.server_name) {
+                        self.agent_event_buf.push(AgentEvent::McpServerStatusChange(change));
+                    }
+                }
             }
         }
     }
@@ -508,6 +651,7 @@ impl Agent {
             execution_state: self.execution_state.clone(),
             model_state: self.model.state(),
             tool_state: self.tool_state.clone(),
+            checkpoints: self.checkpoints.clone(),
             settings: self.settings.clone(),
         }
     }
@@ -593,10 +737,34 @@ impl Agent {
         debug!(?req, "handling agent request");
 
         match req {
-            AgentRequest::SendPrompt(args) => self.handle_send_prompt(args).await,
+            AgentRequest::SendPrompt(args) => {
+                let recorded = self.journal.is_some().then(|| args.clone());
+                let res = self.handle_send_prompt(args).await;
+                if let (Ok(_), Some(journal), Some(args)) = (&res, self.journal.as_mut(), recorded) {
+                    journal.record_prompt(&args);
+                }
+                res
+            },
             AgentRequest::Cancel => self.handle_cancel_request().await,
-            AgentRequest::SendApprovalResult(args) => self.handle_approval_result(args).await,
+            AgentRequest::SendApprovalResult(args) => {
+                let recorded = self.journal.is_some().then(|| args.clone());
+                let res = self.handle_approval_result(args).await;
+                if let (Ok(_), Some(journal), Some(args)) = (&res, self.journal.as_mut(), recorded) {
+                    journal.record_approval(&args);
+                }
+                res
+            },
             AgentRequest::CreateSnapshot => Ok(AgentResponse::Snapshot(self.create_snapshot())),
+            AgentRequest::RevertToCheckpoint { id } => self.handle_revert_to_checkpoint(id).await,
+            AgentRequest::SetInferenceParams(params) => {
+                if !params.is_empty() && !self.model.supports_inference_params() {
+                    return Err(AgentError::Custom(
+                        "The backend of the model doesn't support inference parameters".to_string(),
+                    ));
+                }
+                self.conversation_state.inference_params = params;
+                Ok(AgentResponse::Success)
+            },
             AgentRequest::GetMcpPrompts => {
                 let mut response = HashMap::new();
                 for server_name in self.cached_mcp_configs.server_names() {
@@ -654,6 +822,21 @@ impl Agent {
         Ok(AgentResponse::Success)
     }
 
+    /// Handler for a [AgentRequest::RevertToCheckpoint] request.
+    async fn handle_revert_to_checkpoint(&mut self, id: u32) -> Result<AgentResponse, AgentError> {
+        // Tools still running could modify the files again after they are restored
+        match self.active_state() {
+            ActiveState::Idle | ActiveState::Errored(_) => (),
+            _ => return Err(AgentError::NotIdle),
+        }
+        let paths = self
+            .checkpoints
+            .revert(id, &self.sys_provider)
+            .await
+            .map_err(|err| AgentError::Custom(err.to_string()))?;
+        Ok(AgentResponse::RevertedFiles(paths))
+    }
+
     /// Handler for a [AgentRequest::SendApprovalResult] request.
     async fn handle_approval_result(&mut self, args: SendApprovalResultArgs) -> Result<AgentResponse, AgentError> {
         match &mut self.execution_state.active_state {
@@ -745,7 +928,11 @@ impl Agent {
         match evt {
             AgentLoopEventKind::ResponseStreamEnd { result, metadata } => match result {
                 Ok(msg) => {
-                    self.conversation_state.messages.push(msg.clone());
+                    let msg = match self.resuming_response {
+                        true => self.merge_resumed_response(msg),
+                        false => msg,
+                    };
+                    self.conversation_state.messages.push(msg);
                     if !metadata.tool_uses.is_empty() {
                         self.handle_tool_uses(metadata.tool_uses.clone()).await?;
                     }
@@ -761,6 +948,15 @@ impl Agent {
                 self.agent_event_buf.push(AgentEvent::EndTurn(md));
                 self.agent_event_buf.push(AgentEvent::Stop(AgentStopReason::EndTurn));
             },
+            AgentLoopEventKind::Usage { request, turn } => {
+                let mut conversation = self.conversation_metadata.usage();
+                conversation += turn;
+                self.agent_event_buf.push(AgentEvent::Update(UpdateEvent::Usage {
+                    request,
+                    turn,
+                    conversation,
+                }));
+            },
             AgentLoopEventKind::AssistantText(text) => self
                 .agent_event_buf
                 .push(AgentEvent::Update(UpdateEvent::AgentContent(text.into()))),
@@ -773,6 +969,35 @@ impl Agent {
         Ok(())
     }
 
+    /// Merges `msg`, which continues the response that the connection dropped in the middle of,
+    /// into the partial response at the end of the history. The prompt that asked for it is
+    /// removed, so that the history only has the whole response.
+    fn merge_resumed_response(&mut self, msg: Message) -> Message {
+        self.resuming_response = false;
+        let messages = &mut self.conversation_state.messages;
+        debug_assert!(messages.last().is_some_and(|m| m.role == Role::User));
+        messages.pop();
+        let Some(mut partial) = messages.pop_if(|m| m.role == Role::Assistant) else {
+            return msg;
+        };
+
+        let mut content = msg.content;
+        let continues_text = match (partial.content.last_mut(), content.first()) {
+            (Some(ContentBlock::Text(text)), Some(ContentBlock::Text(continuation))) => {
+                text.push_str(continuation);
+                true
+            },
+            _ => false,
+        };
+        if continues_text {
+            content.remove(0);
+        }
+        partial.content.extend(content);
+        partial.id = msg.id;
+        partial.timestamp = msg.timestamp;
+        partial
+    }
+
     /// Handler for errors encountered while sending the request or while consuming the response.
     async fn handle_loop_error_on_stream_end(&mut self, err: &LoopError) -> Result<(), AgentError> {
         debug_assert!(matches!(self.active_state(), ActiveState::ExecutingRequest));
@@ -836,6 +1061,41 @@ impl Agent {
                 let args = self.format_request().await;
                 self.send_request(args).await?;
             },
+            LoopError::Disconnected { assistant_text, .. } if self.stream_resumes < MAX_STREAM_RESUMES => {
+                // Keep the text received so far, and ask the model to pick up from where it
+                // stopped rather than failing the turn.
+                self.stream_resumes += 1;
+                let partial = Message {
+                    id: None,
+                    role: Role::Assistant,
+                    content: vec![ContentBlock::Text(assistant_text.clone())],
+                    timestamp: Some(Utc::now()),
+                };
+                let partial = match self.resuming_response {
+                    true => self.merge_resumed_response(partial),
+                    false => partial,
+                };
+                self.conversation_state.messages.push(partial);
+                self.conversation_state.messages.push(Message {
+                    id: None,
+                    role: Role::User,
+                    content: vec![ContentBlock::Text(STREAM_RESUME_PROMPT.to_string())],
+                    timestamp: Some(Utc::now()),
+                });
+                self.resuming_response = true;
+
+                let args = self.format_request().await;
+                self.send_request(args).await?;
+            },
+            LoopError::Disconnected { .. } => {
+                // The partial response is kept, without the prompt to continue it
+                if std::mem::take(&mut self.resuming_response) {
+                    self.conversation_state.messages.pop();
+                }
+                self.set_active_state(ActiveState::Errored(err.clone().into())).await;
+                self.agent_event_buf
+                    .push(AgentEvent::Stop(AgentStopReason::Error(err.clone().into())));
+            },
             LoopError::Stream(stream_err) => match &stream_err.kind {
                 StreamErrorKind::StreamTimeout { .. } => {
                     self.conversation_state.messages.push(Message {
@@ -862,6 +1122,7 @@ impl Agent {
                     // nothing to do
                 },
                 StreamErrorKind::Validation { .. }
+                | StreamErrorKind::Disconnected
                 | StreamErrorKind::ServiceFailure
                 | StreamErrorKind::ContextWindowOverflow
                 | StreamErrorKind::Throttling
@@ -940,6 +1201,9 @@ impl Agent {
             .push(Message::new(Role::User, user_msg_content.clone(), Some(Utc::now())));
 
         // Create a new agent loop, and send the request.
+        self.stream_resumes = 0;
+        self.resuming_response = false;
+        self.checkpoints.begin(&self.sys_provider).await;
         let loop_id = AgentLoopId::new(self.id.clone());
         let cancel_token = CancellationToken::new();
         self.agent_loop = Some(AgentLoop::new(loop_id.clone(), cancel_token).spawn());
@@ -958,22 +1222,103 @@ impl Agent {
     /// 1. Have context messages prepended to the start of the message history
     /// 2. Have conversation history invariants enforced, mutating messages as required
     async fn format_request(&mut self) -> SendRequestArgs {
-        format_request(
+        let mut resources = match self.agent_config.watch_resources() {
+            true => {
+                self.resource_watcher
+                    .refresh(self.agent_config.resources(), &self.sys_provider)
+                    .await
+            },
+            false => collect_resources(self.agent_config.resources(), &self.sys_provider).await,
+        };
+        resources.extend(self.read_mcp_resources().await);
+        let mut args = format_request(
             VecDeque::from(self.conversation_state.messages.clone()),
             self.make_tool_spec().await,
             &self.agent_config,
             self.agent_spawn_hooks.iter().map(|(_, c)| c),
+            Some(resources),
             &self.sys_provider,
         )
-        .await
+        .await;
+        args.inference_params = self
+            .agent_config
+            .model()
+            .map(|model| model.inference)
+            .unwrap_or_default()
+            .merge(self.conversation_state.inference_params);
+        args
+    }
+
+    /// Reads the resources of MCP servers included by the agent config.
+    ///
+    /// Unlike files, these are read again for every request whether or not resources are watched,
+    /// since servers don't tell when the contents of their resources change.
+    async fn read_mcp_resources(&self) -> Vec<Resource> {
+        let mut return_val = Vec::new();
+        for resource in self.agent_config.resources() {
+            let (original, server_name, uris) = match ResourceKind::parse(resource.as_ref(), &self.sys_provider) {
+                Ok(ResourceKind::Mcp {
+                    original,
+                    server_name,
+                    uri,
+                }) => (original, server_name, vec![uri.to_string()]),
+                Ok(ResourceKind::McpGlob {
+                    original,
+                    server_name,
+                    pattern,
+                }) => {
+                    let listed = match self.mcp_manager_handle.list_resources(server_name.to_string()).await {
+                        Ok(rx) => recv_mcp_response(rx).await,
+                        Err(err) => Err(err.to_string()),
+                    };
+                    match listed {
+                        Ok(listed) => (
+                            original,
+                            server_name,
+                            listed
+                                .into_iter()
+                                .map(|resource| resource.uri)
+                                .filter(|uri| pattern.matches(uri))
+                                .collect(),
+                        ),
+                        Err(err) => {
+                            warn!(?server_name, %err, "failed to list MCP resources");
+                            continue;
+                        },
+                    }
+                },
+                _ => continue,
+            };
+
+            for uri in uris {
+                let result = match self
+                    .mcp_manager_handle
+                    .read_resource(server_name.to_string(), uri.clone())
+                    .await
+                {
+                    Ok(rx) => recv_mcp_response(rx).await,
+                    Err(err) => Err(err.to_string()),
+                };
+                match result {
+                    Ok(result) => return_val.push(Resource {
+                        config_value: original.to_string(),
+                        content: format_mcp_resource_contents(result.contents),
+                    }),
+                    Err(err) => warn!(?server_name, ?uri, %err, "failed to read MCP resource"),
+                }
+            }
+        }
+
+        return_val
     }
 
     async fn send_request(&mut self, request_args: SendRequestArgs) -> Result<AgentLoopResponse, AgentError> {
         debug!(?request_args, "sending request");
         let model = Arc::clone(&self.model);
+        let timeouts = self.settings.stream_timeouts(model.provider());
         let res = self
             .agent_loop_handle()?
-            .send_request(model, request_args.clone())
+            .send_request(model, request_args.clone(), timeouts)
             .await?;
         self.agent_event_buf
             .push(AgentEvent::Internal(InternalEvent::RequestSent(request_args)));
@@ -1141,6 +1486,14 @@ impl Agent {
     async fn handle_task_executor_event(&mut self, evt: TaskExecutorEvent) -> Result<(), AgentError> {
         debug!(?evt, "handling new task executor event");
         match evt {
+            TaskExecutorEvent::ToolExecutionProgress(evt) => {
+                self.agent_event_buf
+                    .push(AgentEvent::Update(UpdateEvent::ToolCallUpdate {
+                        id: evt.id.tool_use_id().to_string(),
+                        content: evt.chunk,
+                    }));
+                Ok(())
+            },
             TaskExecutorEvent::ToolExecutionEnd(evt) => self.handle_tool_execution_end(evt).await,
             TaskExecutorEvent::HookExecutionEnd(evt) => match evt.result {
                 HookExecutorResult::Completed { id, result, .. } => self.handle_hook_finished_event(id, result).await,
@@ -1423,6 +1776,11 @@ impl Agent {
             }
         }
 
+        // Subagents are spawned through the runtime, and can't spawn their own.
+        if self.runtime.is_none() || self.id.parent_id().is_some() {
+            tool_names.remove(&CanonicalToolName::BuiltIn(BuiltInToolName::SpawnSubagent));
+        }
+
         tool_names.into_iter().collect()
     }
 
@@ -1456,6 +1814,16 @@ impl Agent {
                     continue;
                 },
             };
+            // A recorded result means the tool was valid when the conversation was recorded, against
+            // a file system that can differ from the one it is replayed on.
+            if self
+                .replayed_tool_results
+                .as_ref()
+                .is_some_and(|results| results.contains_key(&tool_use.tool_use_id))
+            {
+                tools.push((tool_use, tool));
+                continue;
+            }
             match self.validate_tool(&tool).await {
                 Ok(_) => tools.push((tool_use, tool)),
                 Err(err) => {
@@ -1467,26 +1835,44 @@ impl Agent {
         (tools, parse_errors)
     }
 
+    /// Resolved directories the file tools are restricted to, empty if they are not restricted.
+    ///
+    /// Fails when roots are configured but none resolves, in which case the file tools are refused.
+    fn file_roots(&self) -> Result<Vec<PathBuf>, PathError> {
+        let roots = self
+            .agent_config
+            .tool_settings()
+            .map(|settings| settings.file_roots.as_slice())
+            .unwrap_or_default();
+        resolve_roots(roots, &self.sys_provider)
+    }
+
     async fn validate_tool(&self, tool: &Tool) -> Result<(), ToolParseErrorKind> {
+        let roots = self.file_roots();
+        let roots = || {
+            roots
+                .clone()
+                .map_err(|err| ToolParseErrorKind::invalid_args(err.to_string()))
+        };
         match tool.kind() {
             ToolKind::BuiltIn(built_in) => match built_in {
                 BuiltInTool::FileRead(t) => t
-                    .validate(&self.sys_provider)
+                    .validate(&roots()?, &self.sys_provider)
                     .await
                     .map_err(ToolParseErrorKind::invalid_args),
                 BuiltInTool::FileWrite(t) => t
-                    .validate(&self.sys_provider)
+                    .validate(&roots()?, &self.sys_provider)
                     .await
                     .map_err(ToolParseErrorKind::invalid_args),
                 BuiltInTool::Grep(_) => Ok(()),
                 BuiltInTool::Ls(t) => t
-                    .validate(&self.sys_provider)
+                    .validate(&roots()?, &self.sys_provider)
                     .await
                     .map_err(ToolParseErrorKind::invalid_args),
                 BuiltInTool::Mkdir(_) => Ok(()),
                 BuiltInTool::ExecuteCmd(_) => Ok(()),
                 BuiltInTool::Introspect(_) => Ok(()),
-                BuiltInTool::SpawnSubagent => Ok(()),
+                BuiltInTool::SpawnSubagent(t) => t.validate().map_err(ToolParseErrorKind::invalid_args),
                 BuiltInTool::ImageRead(t) => t.validate().await.map_err(ToolParseErrorKind::invalid_args),
             },
             ToolKind::Mcp(_) => Ok(()),
@@ -1567,33 +1953,104 @@ impl Agent {
     async fn start_tool_execution(&mut self, id: ToolExecutionId, tool: Tool) -> Result<(), AgentError> {
         trace!(?id, ?tool, "starting tool execution");
         let tool_clone = tool.clone();
+        let limits = self.settings.tool_execution.limits(
+            self.agent_config
+                .tool_settings()
+                .and_then(|settings| settings.limits.get(tool.canonical_tool_name().as_full_name().as_ref())),
+        );
 
         // Channel for handling tool-specific state updates.
         let (tx, rx) = oneshot::channel::<ToolState>();
 
         let provider = Arc::clone(&self.sys_provider);
+        let roots = self.file_roots();
+
+        if let Some(results) = self.replayed_tool_results.as_mut() {
+            let result = match results.remove(id.tool_use_id()) {
+                Some(ToolExecutorResult::Completed { result, .. }) => result,
+                Some(ToolExecutorResult::Cancelled { .. }) => Err(ToolExecutionError::Custom(
+                    "The tool use was cancelled in the recorded conversation".to_string(),
+                )),
+                None => Err(ToolExecutionError::Custom(
+                    "No result was recorded for this tool use".to_string(),
+                )),
+            };
+            debug!(?id, ?result, "returning the recorded tool result");
+            self.task_executor
+                .start_tool_execution(StartToolExecution {
+                    id,
+                    tool: tool_clone,
+                    fut: Box::pin(async move { result }),
+                    context_rx: rx,
+                    limits: None,
+                    output_rx: None,
+                })
+                .await;
+            return Ok(());
+        }
 
+        let mut output_rx = None;
         let fut: ToolFuture = match tool.kind {
             ToolKind::BuiltIn(builtin) => match builtin {
-                BuiltInTool::FileRead(t) => Box::pin(async move { t.execute(&provider).await }),
+                BuiltInTool::FileRead(t) => Box::pin(async move { t.execute(&roots?, &provider).await }),
                 BuiltInTool::FileWrite(t) => {
+                    // A file that isn't recorded in the checkpoint isn't modified, since the turn
+                    // could no longer be reverted.
+                    let recorded = match roots
+                        .as_ref()
+                        .map_err(ToString::to_string)
+                        .and_then(|roots| t.canonical_path(roots, &provider))
+                    {
+                        Ok(path) => self
+                            .checkpoints
+                            .record(&path, &provider)
+                            .await
+                            .map_err(|err| err.to_string()),
+                        Err(err) => Err(err),
+                    };
                     let file_write = self.tool_state.file_write.clone();
                     let mut tool_state = ToolState { file_write };
                     Box::pin(async move {
-                        let res = t.execute(tool_state.file_write.as_mut(), &provider).await;
+                        if let Err(err) = recorded {
+                            return Err(ToolExecutionError::Custom(format!(
+                                "the file was not modified, since it could not be recorded in the checkpoint: {err}"
+                            )));
+                        }
+                        let res = t.execute(tool_state.file_write.as_mut(), &roots?, &provider).await;
                         if res.is_ok() {
                             let _ = tx.send(tool_state);
                         }
                         res
                     })
                 },
-                BuiltInTool::ExecuteCmd(t) => Box::pin(async move { t.execute().await }),
+                BuiltInTool::ExecuteCmd(t) => {
+                    let (output_tx, rx) = mpsc::channel(TOOL_OUTPUT_BUFFER_SIZE);
+                    output_rx = Some(rx);
+                    Box::pin(async move { t.execute(Some(output_tx)).await })
+                },
                 BuiltInTool::ImageRead(t) => Box::pin(async move { t.execute().await }),
                 BuiltInTool::Introspect(_) => panic!("unimplemented"),
                 BuiltInTool::Grep(_) => panic!("unimplemented"),
-                BuiltInTool::Ls(t) => Box::pin(async move { t.execute(&provider).await }),
+                BuiltInTool::Ls(t) => {
+                    let page_limits = limits.page_limits();
+                    Box::pin(async move { t.execute(&roots?, page_limits, &provider).await })
+                },
                 BuiltInTool::Mkdir(_) => panic!("unimplemented"),
-                BuiltInTool::SpawnSubagent => panic!("unimplemented"),
+                BuiltInTool::SpawnSubagent(t) => match self.runtime.clone() {
+                    Some(runtime) => {
+                        let parent = SubagentParent {
+                            id: self.id.clone(),
+                            agent_config: self.agent_config.clone(),
+                            settings: self.settings.clone(),
+                        };
+                        Box::pin(async move { t.execute(&runtime, &parent).await })
+                    },
+                    None => Box::pin(async move {
+                        Err(ToolExecutionError::Custom(
+                            "Subagents can only be spawned by agents running in an agent runtime".to_string(),
+                        ))
+                    }),
+                },
             },
             ToolKind::Mcp(t) => {
                 let mcp_tool = t.clone();
@@ -1633,6 +2090,8 @@ impl Agent {
                 tool: tool_clone,
                 fut,
                 context_rx: rx,
+                limits: Some(limits),
+                output_rx,
             })
             .await;
         Ok(())
@@ -1692,6 +2151,7 @@ async fn format_request<T, U, P>(
     mut tool_spec: Vec<ToolSpec>,
     agent_config: &AgentConfig,
     agent_spawn_hooks: T,
+    resources: Option<Vec<Resource>>,
     provider: &P,
 ) -> SendRequestArgs
 where
@@ -1701,7 +2161,7 @@ where
 {
     enforce_conversation_invariants(&mut messages, &mut tool_spec);
 
-    let ctx_messages = create_context_messages(agent_config, agent_spawn_hooks, provider).await;
+    let ctx_messages = create_context_messages(agent_config, agent_spawn_hooks, resources, provider).await;
     for msg in ctx_messages.into_iter().rev() {
         messages.push_front(msg);
     }
@@ -1728,9 +2188,12 @@ where
 /// * Latest conversation summary from compaction
 ///
 /// We use context messages since the API does not allow any system prompt parameterization.
+///
+/// `resources` are read from the agent config if not provided.
 async fn create_context_messages<T, U, P>(
     agent_config: &AgentConfig,
     agent_spawn_hooks: T,
+    resources: Option<Vec<Resource>>,
     provider: &P,
 ) -> Vec<Message>
 where
@@ -1739,7 +2202,10 @@ where
     P: SystemProvider,
 {
     let system_prompt = agent_config.system_prompt();
-    let resources = collect_resources(agent_config.resources(), provider).await;
+    let resources = match resources {
+        Some(resources) => resources,
+        None => collect_resources(agent_config.resources(), provider).await,
+    };
 
     let content = format_user_context_message(system_prompt, resources.iter().map(|r| &r.content), agent_spawn_hooks);
     if content.is_empty() {
@@ -1908,6 +2374,25 @@ struct Resource {
 }
 
 async fn collect_resources<T, U, P>(resources: T, provider: &P) -> Vec<Resource>
+where
+    T: IntoIterator<Item = U>,
+    U: AsRef<str>,
+    P: SystemProvider,
+{
+    let mut return_val = Vec::new();
+    for (config_value, path) in resource_files(resources, provider) {
+        let Ok((content, _)) = read_file_with_max_limit(path, MAX_RESOURCE_FILE_LENGTH, "...truncated").await else {
+            continue;
+        };
+        return_val.push(Resource { config_value, content });
+    }
+
+    return_val
+}
+
+/// Returns the paths of the files matched by `resources`, along with the config value that
+/// matched each.
+fn resource_files<T, U, P>(resources: T, provider: &P) -> Vec<(String, PathBuf)>
 where
     T: IntoIterator<Item = U>,
     U: AsRef<str>,
@@ -1925,14 +2410,7 @@ where
                 let Ok(path) = canonicalize_path_sys(file_path, provider) else {
                     continue;
                 };
-                let Ok((content, _)) = read_file_with_max_limit(path, MAX_RESOURCE_FILE_LENGTH, "...truncated").await
-                else {
-                    continue;
-                };
-                return_val.push(Resource {
-                    config_value: original.to_string(),
-                    content,
-                });
+                return_val.push((original.to_string(), PathBuf::from(path)));
             },
             ResourceKind::FileGlob { original, pattern } => {
                 let Ok(entries) = glob::glob(pattern.as_str()) else {
@@ -1943,24 +2421,49 @@ where
                         continue;
                     };
                     if entry.is_file() {
-                        let Ok((content, _)) =
-                            read_file_with_max_limit(entry.as_path(), MAX_RESOURCE_FILE_LENGTH, "...truncated").await
-                        else {
-                            continue;
-                        };
-                        return_val.push(Resource {
-                            config_value: original.to_string(),
-                            content,
-                        });
+                        return_val.push((original.to_string(), entry));
                     }
                 }
             },
+            // Read from the MCP servers by the agent
+            ResourceKind::Mcp { .. } | ResourceKind::McpGlob { .. } => (),
         }
     }
 
     return_val
 }
 
+/// Waits for the response of an MCP server to a request, up to [MCP_RESOURCE_TIMEOUT].
+async fn recv_mcp_response<T, E>(rx: oneshot::Receiver<Result<T, E>>) -> Result<T, String>
+where
+    E: std::fmt::Display,
+{
+    match tokio::time::timeout(MCP_RESOURCE_TIMEOUT, rx).await {
+        Ok(Ok(result)) => result.map_err(|err| err.to_string()),
+        Ok(Err(_)) => Err("channel dropped".to_string()),
+        Err(_) => Err(format!("no response within {} seconds", MCP_RESOURCE_TIMEOUT.as_secs())),
+    }
+}
+
+/// Formats the contents of an MCP resource for the context. Binary contents are noted rather than
+/// included.
+fn format_mcp_resource_contents(contents: Vec<ResourceContents>) -> String {
+    let mut content = contents
+        .into_iter()
+        .map(|contents| match contents {
+            ResourceContents::TextResourceContents { text, .. } => text,
+            ResourceContents::BlobResourceContents { uri, mime_type, .. } => format!(
+                "[Binary contents of {} ({}) omitted]",
+                uri,
+                mime_type.as_deref().unwrap_or("unknown type")
+            ),
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    truncate_safe_in_place(&mut content, MAX_RESOURCE_FILE_LENGTH as usize, "...truncated");
+    content
+}
+
 fn hook_matches_tool(config: &HookConfig, tool: &Tool) -> bool {
     let Some(matcher) = config.matcher() else {
         // No matcher -> hook runs for all tools.
@@ -2170,8 +2673,41 @@ pub enum HookStage {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::agent::agent_loop::model::MockModel;
+    use crate::agent::mcp::McpManager;
     use crate::util::test::TestBase;
 
+    #[tokio::test]
+    async fn test_mcp_disabled_skips_servers() {
+        let mut config = AgentConfig::default();
+        let AgentConfig::V2025_08_22(inner) = &mut config;
+        inner.mcp_servers.insert(
+            "local".to_string(),
+            serde_json::from_value(serde_json::json!({ "command": "git-mcp" })).unwrap(),
+        );
+        let model = Arc::new(MockModel::new());
+
+        let agent = Agent::new(
+            AgentSnapshot::new_empty(config.clone()),
+            model.clone(),
+            McpManager::new().spawn(),
+        )
+        .await
+        .unwrap();
+        assert!(
+            agent
+                .cached_mcp_configs
+                .configs
+                .iter()
+                .any(|c| c.server_name == "local")
+        );
+
+        let mut snapshot = AgentSnapshot::new_empty(config);
+        snapshot.settings.mcp_enabled = false;
+        let agent = Agent::new(snapshot, model, McpManager::new().spawn()).await.unwrap();
+        assert!(agent.cached_mcp_configs.configs.is_empty());
+    }
+
     #[tokio::test]
     async fn test_collect_resources() {
         let mut test_base = TestBase::new().await;
@@ -2192,4 +2728,22 @@ mod tests {
             assert!(resources.iter().any(|r| r.content == file.1));
         }
     }
+
+    #[test]
+    fn test_format_mcp_resource_contents() {
+        let contents: Vec<ResourceContents> = serde_json::from_value(serde_json::json!([
+            { "uri": "memo://insights", "mimeType": "text/plain", "text": "first insight" },
+            { "uri": "memo://chart", "mimeType": "image/png", "blob": "aGVsbG8=" },
+        ]))
+        .unwrap();
+        assert_eq!(
+            format_mcp_resource_contents(contents),
+            "first insight\n\n[Binary contents of memo://chart (image/png) omitted]"
+        );
+
+        let contents = vec![ResourceContents::text("a".repeat(20_000), "memo://long")];
+        let content = format_mcp_resource_contents(contents);
+        assert_eq!(content.len(), MAX_RESOURCE_FILE_LENGTH as usize);
+        assert!(content.ends_with("...truncated"));
+    }
 }
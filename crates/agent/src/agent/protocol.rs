@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 use serde::{
     Deserialize,
@@ -6,6 +7,7 @@ use serde::{
 };
 
 use super::ExecutionState;
+use super::agent_config::definitions::InferenceParams;
 use super::agent_loop::protocol::{
     AgentLoopEvent,
     AgentLoopResponseError,
@@ -15,17 +17,23 @@ use super::agent_loop::protocol::{
 };
 use super::agent_loop::types::{
     ImageBlock,
+    TokenUsage,
     ToolUseBlock,
 };
-use super::mcp::McpManagerError;
 use super::mcp::types::Prompt;
+use super::mcp::{
+    McpManagerError,
+    McpServerStatusChange,
+};
 use super::task_executor::TaskExecutorEvent;
 use super::tools::{
     Tool,
     ToolExecutionError,
     ToolExecutionOutput,
+    ToolOutputChunk,
 };
 use super::types::AgentSnapshot;
+use super::util::event_fanout::FanoutEvent;
 
 /// Represents a message from the agent to the client
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -70,11 +78,72 @@ pub enum AgentEvent {
         context: Option<super::tools::ToolContext>,
     },
 
+    /// An MCP server used by the agent changed status, e.g. it crashed and is being restarted.
+    ///
+    /// The tools of a server are unavailable until it is running again.
+    McpServerStatusChange(McpServerStatusChange),
+
     /// Lower-level events associated with the agent's execution. Generally only useful for
     /// debugging or telemetry purposes.
     Internal(InternalEvent),
 }
 
+impl FanoutEvent for AgentEvent {
+    /// Internal events are only useful for debugging, except for state changes which clients
+    /// track.
+    fn is_droppable(&self) -> bool {
+        matches!(self, Self::Internal(event) if !matches!(event, InternalEvent::StateChange { .. }))
+    }
+
+    /// Consecutive chunks of streamed text, and of the output of the same tool call, are merged.
+    fn try_merge(&mut self, next: &Self) -> bool {
+        let (Self::Update(current), Self::Update(next)) = (self, next) else {
+            return false;
+        };
+        match (current, next) {
+            (
+                UpdateEvent::UserContent(ContentChunk::Text(text)),
+                UpdateEvent::UserContent(ContentChunk::Text(next)),
+            )
+            | (
+                UpdateEvent::AgentContent(ContentChunk::Text(text)),
+                UpdateEvent::AgentContent(ContentChunk::Text(next)),
+            )
+            | (
+                UpdateEvent::AgentThought(ContentChunk::Text(text)),
+                UpdateEvent::AgentThought(ContentChunk::Text(next)),
+            ) => {
+                text.push_str(next);
+                true
+            },
+            (
+                UpdateEvent::ToolCallUpdate {
+                    id,
+                    content: ToolOutputChunk::Stdout(text),
+                },
+                UpdateEvent::ToolCallUpdate {
+                    id: next_id,
+                    content: ToolOutputChunk::Stdout(next),
+                },
+            )
+            | (
+                UpdateEvent::ToolCallUpdate {
+                    id,
+                    content: ToolOutputChunk::Stderr(text),
+                },
+                UpdateEvent::ToolCallUpdate {
+                    id: next_id,
+                    content: ToolOutputChunk::Stderr(next),
+                },
+            ) if id == next_id => {
+                text.push_str(next);
+                true
+            },
+            _ => false,
+        }
+    }
+}
+
 impl From<TaskExecutorEvent> for AgentEvent {
     fn from(value: TaskExecutorEvent) -> Self {
         Self::Internal(InternalEvent::TaskExecutor(Box::new(value)))
@@ -103,8 +172,12 @@ pub enum UpdateEvent {
     AgentThought(ContentChunk),
     /// Sent once at the beginning of a tool use.
     ToolCall(ToolCall),
-    /// Sent (optionally multiple times) to report the status of a tool execution.
-    ToolCallUpdate { content: ContentChunk },
+    /// Sent (optionally multiple times) with output streamed by a tool while it executes.
+    ToolCallUpdate {
+        /// Identifier of the tool call, see [ToolCall::id]
+        id: String,
+        content: ToolOutputChunk,
+    },
     /// Sent once at the end of a tool execution.
     ToolCallFinished {
         /// The tool that was executed
@@ -112,6 +185,15 @@ pub enum UpdateEvent {
         /// The tool execution result
         result: ToolCallResult,
     },
+    /// Sent after each response with the tokens used so far.
+    Usage {
+        /// Usage of the last request
+        request: TokenUsage,
+        /// Usage of the current user turn
+        turn: TokenUsage,
+        /// Usage of the whole conversation, including the current user turn
+        conversation: TokenUsage,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -140,6 +222,14 @@ pub enum AgentRequest {
     /// Creates a serializable snapshot of the agent's current state
     CreateSnapshot,
     GetMcpPrompts,
+    /// Restores the files modified by tools since the start of a user turn, given by the id of
+    /// its checkpoint in [AgentSnapshot::checkpoints].
+    RevertToCheckpoint {
+        id: u32,
+    },
+    /// Replaces the inference parameters of the conversation, which override the defaults of the
+    /// model config for the following requests. Fails if the model doesn't support them.
+    SetInferenceParams(InferenceParams),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -264,6 +354,8 @@ pub enum AgentResponse {
     Success,
     Snapshot(AgentSnapshot),
     McpPrompts(HashMap<String, Vec<Prompt>>),
+    /// Paths of the files restored by [AgentRequest::RevertToCheckpoint]
+    RevertedFiles(Vec<PathBuf>),
     Unknown,
 }
 
@@ -307,3 +399,71 @@ pub enum InternalEvent {
     /// Events specific to tool and hook execution
     TaskExecutor(Box<TaskExecutorEvent>),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::util::event_fanout::EventFanout;
+
+    #[tokio::test]
+    async fn test_slow_subscriber_never_loses_approvals_or_state_changes() {
+        let fanout = EventFanout::new(2);
+        let mut rx = fanout.subscribe();
+
+        for i in 0..50 {
+            for chunk in ["a", "b", "c"] {
+                fanout.send(AgentEvent::Update(UpdateEvent::AgentContent(ContentChunk::Text(
+                    chunk.to_string(),
+                ))));
+            }
+            fanout.send(AgentEvent::ApprovalRequest {
+                id: i.to_string(),
+                tool_use: ToolUseBlock {
+                    tool_use_id: i.to_string(),
+                    name: "fs_write".to_string(),
+                    input: serde_json::json!({}),
+                },
+                context: None,
+            });
+            fanout.send(AgentEvent::Internal(InternalEvent::StateChange {
+                from: ExecutionState::default(),
+                to: ExecutionState::default(),
+            }));
+        }
+        drop(fanout);
+
+        let mut approvals = Vec::new();
+        let mut state_changes = 0;
+        let mut text = String::new();
+        while let Ok(event) = rx.recv().await {
+            match event {
+                AgentEvent::Update(UpdateEvent::AgentContent(ContentChunk::Text(t))) => text.push_str(&t),
+                AgentEvent::ApprovalRequest { id, .. } => approvals.push(id),
+                AgentEvent::Internal(InternalEvent::StateChange { .. }) => state_changes += 1,
+                other => panic!("unexpected event: {other:?}"),
+            }
+        }
+
+        assert_eq!(approvals, (0..50).map(|i| i.to_string()).collect::<Vec<_>>());
+        assert_eq!(state_changes, 50);
+        assert_eq!(text, "abc".repeat(50));
+    }
+
+    #[test]
+    fn test_merge_tool_output() {
+        let update = |id: &str, content: ToolOutputChunk| {
+            AgentEvent::Update(UpdateEvent::ToolCallUpdate {
+                id: id.to_string(),
+                content,
+            })
+        };
+        let mut event = update("1", ToolOutputChunk::Stdout("a".to_string()));
+        assert!(event.try_merge(&update("1", ToolOutputChunk::Stdout("b".to_string()))));
+        assert!(!event.try_merge(&update("1", ToolOutputChunk::Stderr("c".to_string()))));
+        assert!(!event.try_merge(&update("2", ToolOutputChunk::Stdout("d".to_string()))));
+        assert!(matches!(event, AgentEvent::Update(UpdateEvent::ToolCallUpdate {
+            content: ToolOutputChunk::Stdout(text),
+            ..
+        }) if text == "ab"));
+    }
+}
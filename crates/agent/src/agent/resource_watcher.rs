@@ -0,0 +1,206 @@
+//! Incremental reading of the file resources of an agent.
+//!
+//! Without watching, every resource is read again when formatting each request. When the agent
+//! config sets `watchResources`, a [ResourceWatcher] only re-reads the files whose metadata
+//! changed since the previous request, and notes the files that changed, were added or were
+//! removed in the context, so that the model prefers their current contents over what it saw
+//! earlier in the conversation.
+
+use std::collections::HashMap;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::time::SystemTime;
+
+use tracing::debug;
+
+use super::consts::MAX_RESOURCE_FILE_LENGTH;
+use super::util::providers::SystemProvider;
+use super::util::read_file_with_max_limit;
+use super::{
+    Resource,
+    resource_files,
+};
+
+#[derive(Debug, Default)]
+pub struct ResourceWatcher {
+    /// Files read in the previous refresh, by path
+    files: HashMap<PathBuf, WatchedFile>,
+    /// Whether the resources were refreshed before. Nothing is noted as changed on the first
+    /// refresh.
+    refreshed: bool,
+}
+
+#[derive(Debug)]
+struct WatchedFile {
+    config_value: String,
+    modified: Option<SystemTime>,
+    len: u64,
+    content: String,
+}
+
+impl WatchedFile {
+    fn is_unchanged(&self, modified: Option<SystemTime>, len: u64) -> bool {
+        modified.is_some() && self.modified == modified && self.len == len
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+    Added,
+    Updated,
+}
+
+impl ResourceWatcher {
+    /// Returns the current contents of `resources`, re-reading only the files that changed since
+    /// the previous refresh.
+    ///
+    /// Files that changed or were added since the previous refresh are prefixed with a note, and
+    /// a note is returned for each file that is no longer a resource.
+    pub async fn refresh<T, U, P>(&mut self, resources: T, provider: &P) -> Vec<Resource>
+    where
+        T: IntoIterator<Item = U>,
+        U: AsRef<str>,
+        P: SystemProvider,
+    {
+        let mut files = HashMap::new();
+        let mut return_val = Vec::new();
+        for (config_value, path) in resource_files(resources, provider) {
+            // The same file may be matched by several resources
+            if files.contains_key(&path) {
+                continue;
+            }
+            let Ok(metadata) = tokio::fs::metadata(&path).await else {
+                continue;
+            };
+            let modified = metadata.modified().ok();
+
+            let (content, change) = match self.files.remove(&path) {
+                Some(file) if file.is_unchanged(modified, metadata.len()) => (file.content, None),
+                previous => {
+                    let Ok((content, _)) =
+                        read_file_with_max_limit(&path, MAX_RESOURCE_FILE_LENGTH, "...truncated").await
+                    else {
+                        continue;
+                    };
+                    let change = match previous {
+                        Some(file) if file.content == content => None,
+                        Some(_) => Some(Change::Updated),
+                        None if self.refreshed => Some(Change::Added),
+                        None => None,
+                    };
+                    (content, change)
+                },
+            };
+
+            return_val.push(Resource {
+                config_value: config_value.clone(),
+                content: match change {
+                    Some(change) => {
+                        debug!(?path, ?change, "resource changed since the previous request");
+                        format_change_note(&path, change, &content)
+                    },
+                    None => content.clone(),
+                },
+            });
+            files.insert(path, WatchedFile {
+                config_value,
+                modified,
+                len: metadata.len(),
+                content,
+            });
+        }
+
+        // Files left over were removed, or no longer match the resources
+        let mut removed = self.files.drain().collect::<Vec<_>>();
+        removed.sort_by(|a, b| a.0.cmp(&b.0));
+        for (path, file) in removed {
+            debug!(?path, "resource removed since the previous request");
+            return_val.push(Resource {
+                config_value: file.config_value,
+                content: format!(
+                    "Note: {} was removed since the previous request, disregard its earlier contents.",
+                    path.display()
+                ),
+            });
+        }
+
+        self.files = files;
+        self.refreshed = true;
+        return_val
+    }
+}
+
+fn format_change_note(path: &Path, change: Change, content: &str) -> String {
+    let change = match change {
+        Change::Added => "was added",
+        Change::Updated => "changed",
+    };
+    format!(
+        "Note: {} {} since the previous request, these are its current contents.\n\n{}",
+        path.display(),
+        change,
+        content
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::util::test::TestBase;
+
+    #[tokio::test]
+    async fn test_resource_watcher() {
+        let test_base = TestBase::new()
+            .await
+            .with_file(("rules/first.md", "first"))
+            .await
+            .with_file(("rules/second.md", "second"))
+            .await;
+        let resources = ["file://rules/*.md"];
+        let mut watcher = ResourceWatcher::default();
+
+        let contents = |resources: &[Resource]| resources.iter().map(|r| r.content.clone()).collect::<Vec<_>>();
+
+        // Nothing is noted on the first refresh, nor when nothing changed
+        let refreshed = watcher.refresh(resources, &test_base).await;
+        assert_eq!(refreshed.len(), 2);
+        assert!(contents(&refreshed).contains(&"first".to_string()));
+        assert!(contents(&refreshed).contains(&"second".to_string()));
+        let refreshed = watcher.refresh(resources, &test_base).await;
+        assert!(refreshed.iter().all(|r| !r.content.starts_with("Note:")));
+
+        tokio::fs::write(test_base.join("rules/first.md"), "first, updated")
+            .await
+            .unwrap();
+        tokio::fs::remove_file(test_base.join("rules/second.md")).await.unwrap();
+        tokio::fs::write(test_base.join("rules/third.md"), "third")
+            .await
+            .unwrap();
+
+        let refreshed = contents(&watcher.refresh(resources, &test_base).await);
+        assert_eq!(refreshed.len(), 3);
+        assert!(
+            refreshed
+                .iter()
+                .any(|c| c.contains("first.md changed") && c.ends_with("first, updated")),
+            "{refreshed:?}"
+        );
+        assert!(
+            refreshed
+                .iter()
+                .any(|c| c.contains("third.md was added") && c.ends_with("third")),
+            "{refreshed:?}"
+        );
+        assert!(
+            refreshed.iter().any(|c| c.contains("second.md was removed")),
+            "{refreshed:?}"
+        );
+
+        // Changes are only noted once
+        let refreshed = contents(&watcher.refresh(resources, &test_base).await);
+        assert_eq!(refreshed.len(), 2);
+        assert!(refreshed.iter().all(|c| !c.starts_with("Note:")), "{refreshed:?}");
+    }
+}
@@ -0,0 +1,276 @@
+//! Runs agents that share model providers and MCP servers, so that an agent can fan work out to
+//! subagents with the [SpawnSubagent](super::tools::spawn_subagent::SpawnSubagent) tool.
+//!
+//! A subagent starts with an empty conversation and the config of the agent that spawned it. It
+//! runs a single user turn, and its final response is returned to its parent as a tool result.
+
+use std::collections::HashMap;
+use std::sync::{
+    Arc,
+    Mutex,
+};
+
+use tracing::{
+    debug,
+    warn,
+};
+
+use super::agent_config::definitions::AgentConfig;
+use super::agent_loop::model_provider::ModelProviderRegistry;
+use super::mcp::McpManagerHandle;
+use super::protocol::{
+    AgentError,
+    AgentEvent,
+    AgentStopReason,
+    ApprovalResult,
+    ContentChunk,
+    SendApprovalResultArgs,
+    UpdateEvent,
+};
+use super::types::{
+    AgentId,
+    AgentSettings,
+    AgentSnapshot,
+};
+use super::util::providers::{
+    RealProvider,
+    SystemProvider,
+};
+use super::{
+    Agent,
+    AgentHandle,
+};
+
+/// What a subagent inherits from the agent spawning it.
+#[derive(Debug, Clone)]
+pub struct SubagentParent {
+    pub id: AgentId,
+    pub agent_config: AgentConfig,
+    pub settings: AgentSettings,
+}
+
+#[derive(Debug, Clone)]
+pub struct AgentRuntime {
+    /// Creates the model of each agent from its config, or from the model state of its snapshot
+    models: Arc<ModelProviderRegistry>,
+    mcp_manager_handle: McpManagerHandle,
+    sys_provider: Arc<dyn SystemProvider>,
+    /// Handles of the running agents, which only send requests
+    agents: Arc<Mutex<HashMap<AgentId, AgentHandle>>>,
+}
+
+impl AgentRuntime {
+    pub fn new(models: ModelProviderRegistry, mcp_manager_handle: McpManagerHandle) -> Self {
+        Self {
+            models: Arc::new(models),
+            mcp_manager_handle,
+            sys_provider: Arc::new(RealProvider),
+            agents: Default::default(),
+        }
+    }
+
+    pub fn set_sys_provider(&mut self, provider: impl SystemProvider) {
+        self.sys_provider = Arc::new(provider);
+    }
+
+    /// Creates an agent from `snapshot` and starts it, see [Agent::spawn]. The model of the agent
+    /// is created by the provider its config selects, or restored from the snapshot. The agent
+    /// keeps running until it is removed with [Self::remove].
+    pub async fn spawn(&self, snapshot: AgentSnapshot) -> eyre::Result<AgentHandle> {
+        let model = self
+            .models
+            .create(snapshot.agent_config.model(), snapshot.model_state.clone())?;
+        let mut agent = Agent::new(snapshot, model, self.mcp_manager_handle.clone()).await?;
+        agent.sys_provider = Arc::clone(&self.sys_provider);
+        agent.runtime = Some(self.clone());
+        let id = agent.id.clone();
+        let handle = agent.spawn();
+
+        let mut registered = handle.clone();
+        registered.take_events();
+        self.agents.lock().expect("not poisoned").insert(id, registered);
+        Ok(handle)
+    }
+
+    /// Ids of the running agents, including subagents.
+    pub fn agent_ids(&self) -> Vec<AgentId> {
+        self.agents.lock().expect("not poisoned").keys().cloned().collect()
+    }
+
+    /// A handle to the running agent `id`, which only sends requests.
+    pub fn get(&self, id: &AgentId) -> Option<AgentHandle> {
+        self.agents.lock().expect("not poisoned").get(id).cloned()
+    }
+
+    /// Removes the agent `id` from the runtime, which stops the agent once the other handles to it
+    /// are dropped.
+    pub fn remove(&self, id: &AgentId) -> Option<AgentHandle> {
+        self.agents.lock().expect("not poisoned").remove(id)
+    }
+
+    /// Sends `prompt` to a new subagent of `parent`, returning the final response of the subagent
+    /// once its turn ends.
+    ///
+    /// Approval requests of the subagent are denied, since there is no one to answer them. The
+    /// subagent is cancelled if the returned future is dropped before it finishes.
+    pub async fn run_subagent(&self, parent: &SubagentParent, prompt: String) -> Result<String, AgentError> {
+        let mut snapshot = AgentSnapshot::new_empty(parent.agent_config.clone());
+        snapshot.id = AgentId::new_child(&parent.id);
+        snapshot.settings = parent.settings.clone();
+        let id = snapshot.id.clone();
+        let mut handle = self
+            .spawn(snapshot)
+            .await
+            .map_err(|err| AgentError::Custom(format!("failed to spawn the subagent: {}", err)))?;
+        let mut guard = SubagentGuard {
+            runtime: self.clone(),
+            id,
+            finished: false,
+        };
+
+        loop {
+            if let AgentEvent::Initialized = handle.recv().await.map_err(|_| AgentError::Channel)? {
+                break;
+            }
+        }
+        debug!(id = %guard.id, "sending the prompt to the subagent");
+        handle.send_prompt(prompt.into()).await?;
+
+        let mut response = String::new();
+        let result = loop {
+            match handle.recv().await.map_err(|_| AgentError::Channel)? {
+                AgentEvent::Update(UpdateEvent::AgentContent(ContentChunk::Text(text))) => response.push_str(&text),
+                // Only the response after the last tool use is returned
+                AgentEvent::Update(UpdateEvent::ToolCall(_)) => response.clear(),
+                AgentEvent::ApprovalRequest { id, .. } => {
+                    handle
+                        .send_tool_use_approval_result(SendApprovalResultArgs {
+                            id,
+                            result: ApprovalResult::Deny {
+                                reason: Some("Subagents can only use tools that don't require approval".to_string()),
+                            },
+                        })
+                        .await?;
+                },
+                AgentEvent::Stop(reason) => {
+                    break match reason {
+                        AgentStopReason::EndTurn => Ok(response),
+                        AgentStopReason::MaxTurnRequests => Err(AgentError::Custom(
+                            "The subagent reached the maximum number of requests".to_string(),
+                        )),
+                        AgentStopReason::Cancelled => Err(AgentError::Custom("The subagent was cancelled".to_string())),
+                        AgentStopReason::Error(err) => Err(err),
+                    };
+                },
+                _ => (),
+            }
+        };
+        guard.finished = true;
+        result
+    }
+}
+
+/// Removes a subagent from the runtime once it is no longer awaited, cancelling it if it didn't
+/// finish.
+struct SubagentGuard {
+    runtime: AgentRuntime,
+    id: AgentId,
+    finished: bool,
+}
+
+impl Drop for SubagentGuard {
+    fn drop(&mut self) {
+        let Some(handle) = self.runtime.remove(&self.id) else {
+            return;
+        };
+        if !self.finished {
+            let id = self.id.clone();
+            tokio::spawn(async move {
+                if let Err(err) = handle.cancel().await {
+                    warn!(%id, ?err, "failed to cancel the subagent");
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::agent_loop::model::MockModel;
+    use crate::agent::agent_loop::protocol::StreamResult;
+    use crate::agent::agent_loop::types::{
+        ContentBlockDelta,
+        ContentBlockDeltaEvent,
+        MessageStartEvent,
+        MessageStopEvent,
+        Role,
+        StopReason,
+        StreamEvent,
+    };
+    use crate::agent::mcp::McpManager;
+    use crate::agent::tools::spawn_subagent::SpawnSubagent;
+
+    fn text_response(text: &str) -> Vec<StreamResult> {
+        vec![
+            StreamResult::Ok(StreamEvent::MessageStart(MessageStartEvent { role: Role::Assistant })),
+            StreamResult::Ok(StreamEvent::ContentBlockDelta(ContentBlockDeltaEvent {
+                delta: ContentBlockDelta::Text(text.to_string()),
+                content_block_index: None,
+            })),
+            StreamResult::Ok(StreamEvent::MessageStop(MessageStopEvent {
+                stop_reason: StopReason::EndTurn,
+            })),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_spawn_subagents() {
+        let model = MockModel::new()
+            .with_response(text_response("first result"))
+            .with_response(text_response("second result"));
+        let mut models = ModelProviderRegistry::new();
+        models.register("mock", model);
+        let runtime = AgentRuntime::new(models, McpManager::new().spawn());
+        let parent = SubagentParent {
+            id: AgentId::new("parent".to_string()),
+            agent_config: AgentConfig::default(),
+            settings: AgentSettings::default(),
+        };
+
+        let tool: SpawnSubagent = serde_json::from_value(serde_json::json!({
+            "tasks": [{ "prompt": "first task" }, { "prompt": "second task" }]
+        }))
+        .unwrap();
+        tool.validate().unwrap();
+        let output = tool.execute(&runtime, &parent).await.unwrap();
+        let output = serde_json::to_string(&output).unwrap();
+        assert!(output.contains("first result"), "{output}");
+        assert!(output.contains("second result"), "{output}");
+
+        // Finished subagents are removed from the runtime
+        assert!(runtime.agent_ids().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_spawn_selects_model_provider() {
+        let mut models = ModelProviderRegistry::new();
+        models.register("mock", MockModel::new());
+        let runtime = AgentRuntime::new(models, McpManager::new().spawn());
+
+        let mut config = AgentConfig::default();
+        config.set_model_id("other/model".to_string());
+        let AgentConfig::V2025_08_22(inner) = &mut config;
+        inner.model.as_mut().unwrap().provider = Some("bedrock".to_string());
+        let err = runtime.spawn(AgentSnapshot::new_empty(config)).await.unwrap_err();
+        assert!(err.to_string().contains("'bedrock'"), "{err}");
+
+        let mut snapshot = AgentSnapshot::new_empty(AgentConfig::default());
+        snapshot.model_state = Some(serde_json::json!({ "provider": "mock" }));
+        let handle = runtime.spawn(snapshot).await.unwrap();
+        assert_eq!(
+            handle.create_snapshot().await.unwrap().model_state,
+            Some(serde_json::json!({ "provider": "mock" }))
+        );
+    }
+}
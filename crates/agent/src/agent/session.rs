@@ -0,0 +1,281 @@
+//! Persistence of agent sessions, so that a conversation outlives the process running it.
+//!
+//! The [AgentSnapshot] of a session is saved to a [SessionStore] after each user turn, keyed by
+//! the id of its conversation. An agent created from the loaded snapshot resumes the conversation
+//! with its history, tool state and model state.
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use rusqlite::{
+    Connection,
+    OptionalExtension as _,
+    params,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use uuid::Uuid;
+
+use super::types::AgentSnapshot;
+use super::util::directories::sessions_dir;
+use super::util::error::UtilError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    #[error("no session was saved with the id '{0}'")]
+    NotFound(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    #[error(transparent)]
+    Join(#[from] tokio::task::JoinError),
+}
+
+/// What is listed of a saved session without loading its snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    /// Id of the conversation of the session
+    pub id: String,
+    pub agent_name: String,
+    pub updated_at: DateTime<Utc>,
+    pub user_turns: usize,
+}
+
+impl SessionInfo {
+    fn new(snapshot: &AgentSnapshot) -> Self {
+        Self {
+            id: snapshot.conversation_state.id.to_string(),
+            agent_name: snapshot.agent_config.name().to_string(),
+            // Stored in seconds by SQLite, so JSON sessions are the same after a round trip
+            updated_at: DateTime::from_timestamp(Utc::now().timestamp(), 0).unwrap_or_default(),
+            user_turns: snapshot.conversation_metadata.user_turn_metadatas.len(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SavedSession {
+    #[serde(flatten)]
+    info: SessionInfo,
+    snapshot: AgentSnapshot,
+}
+
+/// Where sessions are saved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionStore {
+    /// A JSON file per session in a directory
+    Json(PathBuf),
+    /// A table in a SQLite database
+    Sqlite(PathBuf),
+}
+
+impl SessionStore {
+    /// JSON files in [sessions_dir].
+    pub fn default_json() -> Result<Self, UtilError> {
+        Ok(Self::Json(sessions_dir()?))
+    }
+
+    /// Saves `snapshot`, replacing the previous snapshot of its session.
+    pub async fn save(&self, snapshot: &AgentSnapshot) -> Result<SessionInfo, SessionError> {
+        let session = SavedSession {
+            info: SessionInfo::new(snapshot),
+            snapshot: snapshot.clone(),
+        };
+        match self {
+            Self::Json(dir) => {
+                tokio::fs::create_dir_all(dir).await?;
+                // Written aside and renamed, so a crash while saving leaves the previous snapshot
+                let path = dir.join(format!("{}.json", session.info.id));
+                let tmp_path = path.with_extension("json.tmp");
+                tokio::fs::write(&tmp_path, serde_json::to_vec(&session)?).await?;
+                tokio::fs::rename(&tmp_path, &path).await?;
+            },
+            Self::Sqlite(path) => {
+                let path = path.clone();
+                let info = session.info.clone();
+                let snapshot = serde_json::to_string(&session.snapshot)?;
+                tokio::task::spawn_blocking(move || {
+                    open_sqlite(&path)?.execute(
+                        "INSERT INTO sessions (id, agent_name, updated_at, user_turns, snapshot)
+                        VALUES (?1, ?2, ?3, ?4, ?5)
+                        ON CONFLICT(id) DO UPDATE SET
+                            agent_name = excluded.agent_name,
+                            updated_at = excluded.updated_at,
+                            user_turns = excluded.user_turns,
+                            snapshot = excluded.snapshot",
+                        params![
+                            info.id,
+                            info.agent_name,
+                            info.updated_at.timestamp(),
+                            info.user_turns as i64,
+                            snapshot
+                        ],
+                    )?;
+                    Ok::<_, SessionError>(())
+                })
+                .await??;
+            },
+        }
+        Ok(session.info)
+    }
+
+    /// Loads the snapshot of the session `id`.
+    pub async fn load(&self, id: &str) -> Result<AgentSnapshot, SessionError> {
+        // Ids are conversation ids, which also keeps them from naming other files
+        let id = Uuid::parse_str(id)
+            .map_err(|_| SessionError::NotFound(id.to_string()))?
+            .to_string();
+        match self {
+            Self::Json(dir) => match tokio::fs::read(dir.join(format!("{id}.json"))).await {
+                Ok(bytes) => Ok(serde_json::from_slice::<SavedSession>(&bytes)?.snapshot),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Err(SessionError::NotFound(id)),
+                Err(err) => Err(err.into()),
+            },
+            Self::Sqlite(path) => {
+                let path = path.clone();
+                tokio::task::spawn_blocking(move || {
+                    let snapshot = open_sqlite(&path)?
+                        .query_row("SELECT snapshot FROM sessions WHERE id = ?1", params![id], |row| {
+                            row.get::<_, String>(0)
+                        })
+                        .optional()?
+                        .ok_or(SessionError::NotFound(id))?;
+                    Ok::<AgentSnapshot, SessionError>(serde_json::from_str(&snapshot)?)
+                })
+                .await?
+            },
+        }
+    }
+
+    /// The saved sessions, most recently updated first.
+    pub async fn list(&self) -> Result<Vec<SessionInfo>, SessionError> {
+        let mut sessions = match self {
+            Self::Json(dir) => {
+                let mut entries = match tokio::fs::read_dir(dir).await {
+                    Ok(entries) => entries,
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+                    Err(err) => return Err(err.into()),
+                };
+                let mut sessions = Vec::new();
+                while let Some(entry) = entries.next_entry().await? {
+                    let path = entry.path();
+                    if path.extension().is_none_or(|ext| ext != "json") {
+                        continue;
+                    }
+                    match serde_json::from_slice::<SavedSession>(&tokio::fs::read(&path).await?) {
+                        Ok(session) => sessions.push(session.info),
+                        Err(err) => tracing::warn!(?err, ?path, "skipping an invalid saved session"),
+                    }
+                }
+                sessions
+            },
+            Self::Sqlite(path) => {
+                let path = path.clone();
+                tokio::task::spawn_blocking(move || {
+                    let conn = open_sqlite(&path)?;
+                    let mut statement = conn.prepare("SELECT id, agent_name, updated_at, user_turns FROM sessions")?;
+                    let sessions = statement
+                        .query_map([], |row| {
+                            Ok(SessionInfo {
+                                id: row.get(0)?,
+                                agent_name: row.get(1)?,
+                                updated_at: DateTime::from_timestamp(row.get(2)?, 0).unwrap_or_default(),
+                                user_turns: row.get::<_, i64>(3)? as usize,
+                            })
+                        })?
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Ok::<_, SessionError>(sessions)
+                })
+                .await??
+            },
+        };
+        sessions.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        Ok(sessions)
+    }
+}
+
+fn open_sqlite(path: &Path) -> Result<Connection, SessionError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let conn = Connection::open(path)?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS sessions (
+            id TEXT PRIMARY KEY,
+            agent_name TEXT NOT NULL,
+            updated_at INTEGER NOT NULL,
+            user_turns INTEGER NOT NULL,
+            snapshot TEXT NOT NULL
+        )",
+    )?;
+    Ok(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::agent_loop::types::{
+        ContentBlock,
+        Message,
+        Role,
+    };
+
+    async fn test_store(store: SessionStore) {
+        assert!(store.list().await.unwrap().is_empty());
+        assert!(matches!(
+            store.load(&Uuid::new_v4().to_string()).await,
+            Err(SessionError::NotFound(_))
+        ));
+        assert!(matches!(
+            store.load("../settings").await,
+            Err(SessionError::NotFound(_))
+        ));
+
+        let mut snapshot = AgentSnapshot::new_built_in_agent();
+        let info = store.save(&snapshot).await.unwrap();
+        snapshot.conversation_state.messages.push(Message::new(
+            Role::User,
+            vec![ContentBlock::Text("hello".to_string())],
+            None,
+        ));
+        snapshot.model_state = Some(serde_json::json!({ "conversationId": "abc" }));
+        assert_eq!(store.save(&snapshot).await.unwrap().id, info.id);
+
+        let loaded = store.load(&info.id).await.unwrap();
+        assert_eq!(loaded.conversation_state.id, snapshot.conversation_state.id);
+        assert_eq!(loaded.conversation_state.messages.len(), 1);
+        assert_eq!(loaded.model_state, snapshot.model_state);
+
+        let other = AgentSnapshot::new_built_in_agent();
+        store.save(&other).await.unwrap();
+        let listed = store.list().await.unwrap();
+        assert_eq!(listed.len(), 2);
+        assert!(listed.iter().any(|session| session.id == info.id));
+        assert_eq!(listed[0].agent_name, snapshot.agent_config.name());
+    }
+
+    #[tokio::test]
+    async fn test_json_store() {
+        let dir = tempfile::tempdir().unwrap();
+        test_store(SessionStore::Json(dir.path().join("sessions"))).await;
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_store() {
+        let dir = tempfile::tempdir().unwrap();
+        test_store(SessionStore::Sqlite(dir.path().join("sessions.sqlite3"))).await;
+    }
+}
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::pin::Pin;
 use std::process::Stdio;
+use std::sync::Arc;
 use std::time::{
     Duration,
     Instant,
@@ -16,6 +17,7 @@ use serde::{
     Serialize,
 };
 use tokio::sync::{
+    Semaphore,
     mpsc,
     oneshot,
 };
@@ -26,15 +28,23 @@ use crate::agent::agent_config::definitions::{
     CommandHook,
     HookConfig,
     HookTrigger,
+    ToolLimits,
 };
 use crate::agent::agent_loop::types::ToolUseBlock;
+use crate::agent::tools::pagination::PageLimits;
 use crate::agent::tools::{
     Tool,
+    ToolExecutionError,
     ToolExecutionOutput,
+    ToolExecutionOutputItem,
     ToolExecutionResult,
+    ToolOutputChunk,
     ToolState,
 };
-use crate::agent::util::truncate_safe;
+use crate::agent::util::{
+    truncate_safe,
+    truncate_safe_in_place,
+};
 
 #[derive(Debug, Clone)]
 pub struct ToolExecutorHandle {}
@@ -56,6 +66,8 @@ pub struct TaskExecutor {
     execute_result_rx: mpsc::Receiver<ExecutorResult>,
     executing_tools: HashMap<ToolExecutionId, ExecutingTool>,
     executing_hooks: HashMap<HookExecutionId, ExecutingHook>,
+    /// Permits of the tools allowed to execute at the same time
+    tool_permits: Arc<Semaphore>,
 
     hooks_cache: HashMap<Hook, CachedHook>,
 }
@@ -72,10 +84,17 @@ impl TaskExecutor {
             execute_result_rx,
             executing_tools: HashMap::new(),
             executing_hooks: HashMap::new(),
+            tool_permits: Arc::new(Semaphore::new(ToolExecutionSettings::DEFAULT_MAX_CONCURRENT)),
             hooks_cache: HashMap::new(),
         }
     }
 
+    /// Sets the most tools executing at the same time. Tools started past it wait for another
+    /// tool to finish, which doesn't count towards their timeout.
+    pub fn set_max_concurrent_tools(&mut self, max: usize) {
+        self.tool_permits = Arc::new(Semaphore::new(max.max(1)));
+    }
+
     pub async fn recv_next(&mut self, event_buf: &mut Vec<TaskExecutorEvent>) {
         tokio::select! {
             req = self.execute_request_rx.recv() => {
@@ -134,35 +153,82 @@ impl TaskExecutor {
     }
 
     fn handle_tool_execute_request(&mut self, req: StartToolExecution) {
+        let StartToolExecution {
+            id,
+            tool,
+            fut,
+            context_rx,
+            limits,
+            mut output_rx,
+        } = req;
         let result_tx = self.execute_result_tx.clone();
         let cancel_token = CancellationToken::new();
 
-        let id_clone = req.id.clone();
+        let id_clone = id.clone();
         let cancel_token_clone = cancel_token.clone();
+        let tool_permits = Arc::clone(&self.tool_permits);
+        // Tools stop when their future is dropped on timeout or cancellation, e.g. ExecuteCmd
+        // kills the process group of its command.
+        let fut = async move {
+            let _permit = tool_permits.acquire_owned().await;
+            let Some(limits) = limits else {
+                return fut.await;
+            };
+            match tokio::time::timeout(limits.timeout, fut).await {
+                Ok(mut result) => {
+                    if let Ok(output) = &mut result {
+                        truncate_output(output, limits.max_output_size);
+                    }
+                    result
+                },
+                Err(_) => Err(ToolExecutionError::TimedOut {
+                    timeout: limits.timeout,
+                }),
+            }
+        };
         tokio::spawn(async move {
-            tokio::select! {
-                _ = cancel_token_clone.cancelled() => {
-                    let _ = result_tx.send(ExecutorResult::Tool(ToolExecutorResult::Cancelled { id: id_clone })).await;
-                }
-                result = req.fut => {
-                    let _ = result_tx.send(ExecutorResult::Tool(ToolExecutorResult::Completed { id: id_clone, result })).await;
+            tokio::pin!(fut);
+            let send_output = |chunk: ToolOutputChunk| {
+                result_tx.send(ExecutorResult::ToolProgress(ToolExecutionProgressEvent {
+                    id: id_clone.clone(),
+                    chunk,
+                }))
+            };
+            // Output is forwarded on the same task as the result so that it is received first.
+            let result = loop {
+                tokio::select! {
+                    _ = cancel_token_clone.cancelled() => break None,
+                    Some(chunk) = recv_output(&mut output_rx) => {
+                        let _ = send_output(chunk).await;
+                    }
+                    result = &mut fut => break Some(result),
                 }
-            }
+            };
+            let result = match result {
+                Some(result) => {
+                    while let Some(chunk) = output_rx.as_mut().and_then(|rx| rx.try_recv().ok()) {
+                        let _ = send_output(chunk).await;
+                    }
+                    ToolExecutorResult::Completed { id: id_clone, result }
+                },
+                None => ToolExecutorResult::Cancelled { id: id_clone },
+            };
+            let _ = result_tx.send(ExecutorResult::Tool(result)).await;
         });
 
         let start_time = Utc::now();
         self.event_buf
             .push(TaskExecutorEvent::ToolExecutionStart(ToolExecutionStartEvent {
-                id: req.id.clone(),
-                tool: req.tool.clone(),
+                id: id.clone(),
+                tool: tool.clone(),
                 start_time,
             }));
-        self.executing_tools.insert(req.id, ExecutingTool {
-            tool: req.tool,
+        self.executing_tools.insert(id, ExecutingTool {
+            tool,
             cancel_token,
             start_instant: Instant::now(),
             start_time,
-            context_rx: req.context_rx,
+            context_rx,
         });
     }
 
@@ -248,6 +314,11 @@ impl TaskExecutor {
 
     async fn handle_execute_result(&mut self, result: ExecutorResult) {
         match result {
+            ExecutorResult::ToolProgress(evt) => {
+                if self.executing_tools.contains_key(&evt.id) {
+                    self.event_buf.push(TaskExecutorEvent::ToolExecutionProgress(evt));
+                }
+            },
             ExecutorResult::Tool(result) => {
                 debug_assert!(self.executing_tools.contains_key(result.id()));
                 if let Some(x) = self.executing_tools.remove(result.id()) {
@@ -304,6 +375,72 @@ pub struct StartToolExecution {
     pub fut: ToolFuture,
     /// A receiver for tool state
     pub context_rx: oneshot::Receiver<ToolState>,
+    /// Limits of the execution, [None] for results that are already known
+    pub limits: Option<ToolExecutionLimits>,
+    /// A receiver for output streamed by the tool while it executes, for tools that stream it
+    pub output_rx: Option<mpsc::Receiver<ToolOutputChunk>>,
+}
+
+/// Settings of tool executions, see [TaskExecutor::set_max_concurrent_tools].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ToolExecutionSettings {
+    /// Time after which a tool is cancelled and fails with [ToolExecutionError::TimedOut]
+    pub timeout: Duration,
+    /// Bytes of the output of a tool kept before it is truncated
+    pub max_output_size: usize,
+    /// Most tools executing at the same time
+    pub max_concurrent: usize,
+    /// Most results in a page of the tools that paginate their results, see
+    /// [crate::agent::tools::pagination]
+    pub page_size: usize,
+}
+
+impl ToolExecutionSettings {
+    const DEFAULT_MAX_CONCURRENT: usize = 8;
+    const DEFAULT_MAX_OUTPUT_SIZE: usize = 400_000;
+    const DEFAULT_PAGE_SIZE: usize = 1000;
+    const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+    /// The limits of a tool, given the `overrides` of the agent config for it.
+    pub fn limits(&self, overrides: Option<&ToolLimits>) -> ToolExecutionLimits {
+        ToolExecutionLimits {
+            timeout: overrides
+                .and_then(|o| o.timeout_ms)
+                .map_or(self.timeout, Duration::from_millis),
+            max_output_size: overrides
+                .and_then(|o| o.max_output_size)
+                .unwrap_or(self.max_output_size),
+            page_size: overrides.and_then(|o| o.page_size).unwrap_or(self.page_size),
+        }
+    }
+}
+
+impl Default for ToolExecutionSettings {
+    fn default() -> Self {
+        Self {
+            timeout: Self::DEFAULT_TIMEOUT,
+            max_output_size: Self::DEFAULT_MAX_OUTPUT_SIZE,
+            max_concurrent: Self::DEFAULT_MAX_CONCURRENT,
+            page_size: Self::DEFAULT_PAGE_SIZE,
+        }
+    }
+}
+
+/// Limits of a single tool execution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToolExecutionLimits {
+    pub timeout: Duration,
+    pub max_output_size: usize,
+    pub page_size: usize,
+}
+
+impl ToolExecutionLimits {
+    /// Limits of the pages of tools that paginate their results, so that a page is never
+    /// truncated.
+    pub fn page_limits(&self) -> PageLimits {
+        PageLimits::new(self.page_size, self.max_output_size)
+    }
 }
 
 impl std::fmt::Debug for StartToolExecution {
@@ -313,6 +450,7 @@ impl std::fmt::Debug for StartToolExecution {
             .field("tool", &self.tool)
             .field("fut", &"<ToolFuture>")
             .field("context_rx", &self.context_rx)
+            .field("output_rx", &self.output_rx)
             .finish()
     }
 }
@@ -346,6 +484,8 @@ struct ExecutingHook {
 pub enum TaskExecutorEvent {
     /// A tool has started executing
     ToolExecutionStart(ToolExecutionStartEvent),
+    /// A tool streamed a chunk of its output while executing
+    ToolExecutionProgress(ToolExecutionProgressEvent),
     /// A tool completed executing
     ToolExecutionEnd(ToolExecutionEndEvent),
 
@@ -363,6 +503,13 @@ pub struct ToolExecutionStartEvent {
     pub start_time: DateTime<Utc>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolExecutionProgressEvent {
+    /// Identifier for the tool execution
+    pub id: ToolExecutionId,
+    pub chunk: ToolOutputChunk,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolExecutionEndEvent {
     /// Identifier for the tool execution
@@ -416,6 +563,7 @@ impl ToolExecutionId {
 #[allow(clippy::large_enum_variant)]
 pub enum ExecutorResult {
     Tool(ToolExecutorResult),
+    ToolProgress(ToolExecutionProgressEvent),
     Hook(HookExecutorResult),
 }
 
@@ -663,6 +811,41 @@ async fn run_command_hook(
     (result, start_time.elapsed())
 }
 
+/// Receives the next chunk of output of a tool, never resolving for tools that don't stream their
+/// output.
+async fn recv_output(output_rx: &mut Option<mpsc::Receiver<ToolOutputChunk>>) -> Option<ToolOutputChunk> {
+    match output_rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Truncates the output of a tool to `max_size` bytes, counting JSON items by their serialized
+/// length. Items past the limit are replaced with text.
+fn truncate_output(output: &mut ToolExecutionOutput, max_size: usize) {
+    let mut remaining = max_size;
+    for item in &mut output.items {
+        let len = match item {
+            ToolExecutionOutputItem::Text(text) => text.len(),
+            ToolExecutionOutputItem::Json(value) => value.to_string().len(),
+            ToolExecutionOutputItem::Image(_) => continue,
+        };
+        if len <= remaining {
+            remaining -= len;
+            continue;
+        }
+        let mut text = match item {
+            ToolExecutionOutputItem::Text(text) => std::mem::take(text),
+            ToolExecutionOutputItem::Json(value) => value.to_string(),
+            ToolExecutionOutputItem::Image(_) => continue,
+        };
+        let suffix = format!("\n... output truncated to {} bytes", max_size);
+        truncate_safe_in_place(&mut text, remaining, &suffix);
+        remaining -= text.len();
+        *item = ToolExecutionOutputItem::Text(text);
+    }
+}
+
 /// Sanitizes a string value to be used as an environment variable
 fn sanitize_user_prompt(input: &str) -> String {
     // Limit the size of input to first 4096 characters
@@ -733,4 +916,217 @@ mod tests {
         })
         .await;
     }
+    #[tokio::test]
+    async fn test_tool_execution_timeout() {
+        use crate::agent::tools::ls::Ls;
+        use crate::agent::tools::{
+            BuiltInTool,
+            ToolKind,
+        };
+
+        let mut executor = TaskExecutor::new();
+        let (_, context_rx) = oneshot::channel();
+        executor
+            .start_tool_execution(StartToolExecution {
+                id: ToolExecutionId::new("slow".to_string()),
+                tool: Tool {
+                    tool_use_purpose: None,
+                    kind: ToolKind::BuiltIn(BuiltInTool::Ls(Ls {
+                        path: ".".to_string(),
+                        depth: None,
+                        ignore: None,
+                        ..Default::default()
+                    })),
+                },
+                fut: Box::pin(async {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    Ok(ToolExecutionOutput::default())
+                }),
+                context_rx,
+                limits: Some(ToolExecutionLimits {
+                    timeout: Duration::from_millis(10),
+                    max_output_size: 100,
+                    page_size: 10,
+                }),
+                output_rx: None,
+            })
+            .await;
+
+        run_with_timeout(Duration::from_millis(1000), async move {
+            let mut event_buf = Vec::new();
+            loop {
+                executor.recv_next(&mut event_buf).await;
+                if let Some(result) = event_buf.iter().find_map(|ev| match ev {
+                    TaskExecutorEvent::ToolExecutionEnd(ToolExecutionEndEvent { result, .. }) => Some(result),
+                    _ => None,
+                }) {
+                    assert!(
+                        matches!(result, ToolExecutorResult::Completed {
+                            result: Err(ToolExecutionError::TimedOut { .. }),
+                            ..
+                        }),
+                        "{:?}",
+                        result
+                    );
+                    break;
+                }
+                event_buf.drain(..);
+            }
+        })
+        .await;
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_tool_execution_timeout_kills_command() {
+        use crate::agent::tools::execute_cmd::ExecuteCmd;
+        use crate::agent::tools::execute_cmd::tests::is_running;
+        use crate::agent::tools::{
+            BuiltInTool,
+            ToolKind,
+        };
+
+        let cmd = ExecuteCmd {
+            command: "sleep 60 & echo $!; wait".to_string(),
+        };
+        let mut executor = TaskExecutor::new();
+        let (_, context_rx) = oneshot::channel();
+        let (output_tx, output_rx) = mpsc::channel(16);
+        executor
+            .start_tool_execution(StartToolExecution {
+                id: ToolExecutionId::new("sleep".to_string()),
+                tool: Tool {
+                    tool_use_purpose: None,
+                    kind: ToolKind::BuiltIn(BuiltInTool::ExecuteCmd(cmd.clone())),
+                },
+                fut: Box::pin(async move { cmd.execute(Some(output_tx)).await }),
+                context_rx,
+                limits: Some(ToolExecutionLimits {
+                    timeout: Duration::from_millis(500),
+                    max_output_size: 100,
+                    page_size: 10,
+                }),
+                output_rx: Some(output_rx),
+            })
+            .await;
+
+        run_with_timeout(Duration::from_secs(5), async move {
+            let mut event_buf = Vec::new();
+            while !event_buf
+                .iter()
+                .any(|ev| matches!(ev, TaskExecutorEvent::ToolExecutionEnd(_)))
+            {
+                executor.recv_next(&mut event_buf).await;
+            }
+            let pid = event_buf
+                .iter()
+                .find_map(|ev| match ev {
+                    TaskExecutorEvent::ToolExecutionProgress(ToolExecutionProgressEvent {
+                        chunk: ToolOutputChunk::Stdout(text),
+                        ..
+                    }) => text.trim().parse::<libc::pid_t>().ok(),
+                    _ => None,
+                })
+                .expect("the pid of sleep is printed");
+            assert!(matches!(
+                event_buf.last(),
+                Some(TaskExecutorEvent::ToolExecutionEnd(ToolExecutionEndEvent {
+                    result: ToolExecutorResult::Completed {
+                        result: Err(ToolExecutionError::TimedOut { .. }),
+                        ..
+                    },
+                    ..
+                }))
+            ));
+            while is_running(pid) {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_tool_execution_progress() {
+        use crate::agent::tools::ls::Ls;
+        use crate::agent::tools::{
+            BuiltInTool,
+            ToolKind,
+        };
+
+        let mut executor = TaskExecutor::new();
+        let (_, context_rx) = oneshot::channel();
+        let (output_tx, output_rx) = mpsc::channel(16);
+        executor
+            .start_tool_execution(StartToolExecution {
+                id: ToolExecutionId::new("streaming".to_string()),
+                tool: Tool {
+                    tool_use_purpose: None,
+                    kind: ToolKind::BuiltIn(BuiltInTool::Ls(Ls {
+                        path: ".".to_string(),
+                        depth: None,
+                        ignore: None,
+                        ..Default::default()
+                    })),
+                },
+                fut: Box::pin(async move {
+                    for line in ["first\n", "second\n"] {
+                        output_tx.send(ToolOutputChunk::Stdout(line.to_string())).await.unwrap();
+                        tokio::task::yield_now().await;
+                    }
+                    Ok(ToolExecutionOutput::default())
+                }),
+                context_rx,
+                limits: None,
+                output_rx: Some(output_rx),
+            })
+            .await;
+
+        run_with_timeout(Duration::from_millis(1000), async move {
+            let mut event_buf = Vec::new();
+            while !event_buf
+                .iter()
+                .any(|ev| matches!(ev, TaskExecutorEvent::ToolExecutionEnd(_)))
+            {
+                executor.recv_next(&mut event_buf).await;
+            }
+            // All of the output is received before the end of the execution
+            let output = event_buf
+                .iter()
+                .filter_map(|ev| match ev {
+                    TaskExecutorEvent::ToolExecutionProgress(ToolExecutionProgressEvent {
+                        chunk: ToolOutputChunk::Stdout(text),
+                        ..
+                    }) => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<String>();
+            assert_eq!(output, "first\nsecond\n");
+            assert!(matches!(event_buf.last(), Some(TaskExecutorEvent::ToolExecutionEnd(_))));
+        })
+        .await;
+    }
+
+    #[test]
+    fn test_truncate_output() {
+        let mut output = ToolExecutionOutput::new(vec![
+            ToolExecutionOutputItem::Text("a".repeat(40)),
+            ToolExecutionOutputItem::Json(serde_json::json!({ "key": "b".repeat(100) })),
+            ToolExecutionOutputItem::Text("c".repeat(10)),
+        ]);
+        truncate_output(&mut output, 100);
+
+        let ToolExecutionOutputItem::Text(first) = &output.items[0] else {
+            panic!("expected text");
+        };
+        assert_eq!(first.len(), 40);
+        let ToolExecutionOutputItem::Text(second) = &output.items[1] else {
+            panic!("expected the JSON to be replaced with text");
+        };
+        assert_eq!(second.len(), 60);
+        assert!(second.ends_with("output truncated to 100 bytes"));
+        let ToolExecutionOutputItem::Text(third) = &output.items[2] else {
+            panic!("expected text");
+        };
+        assert!(third.is_empty());
+    }
 }
@@ -0,0 +1,296 @@
+//! Checkpoints of the files modified by tools, so that the changes made during a user turn can be
+//! reverted.
+//!
+//! A checkpoint is started at the beginning of each user turn. Before a tool modifies a file for
+//! the first time in the turn, a shadow copy of the file is written to the checkpoints directory,
+//! so that reverting to the checkpoint restores every file to how it was before the turn started.
+//! Checkpoints only hold the digests of the shadow copies, which keeps the content of the files
+//! out of agent snapshots and saved sessions.
+
+use std::collections::{
+    BTreeMap,
+    BTreeSet,
+    HashSet,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sha2::{
+    Digest as _,
+    Sha256,
+};
+use tracing::warn;
+
+use crate::agent::util::directories::checkpoints_dir;
+use crate::agent::util::error::UtilError;
+use crate::agent::util::providers::SystemProvider;
+
+/// Most checkpoints kept, the oldest ones are dropped first.
+const MAX_CHECKPOINTS: usize = 20;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CheckpointError {
+    #[error("no checkpoint exists with the id {0}")]
+    NotFound(u32),
+    #[error("failed to record {}: {source}", .path.display())]
+    Record {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to restore {}: {source}", .path.display())]
+    Restore {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    Directory(#[from] UtilError),
+}
+
+/// The files modified during a user turn, as they were before the turn.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Checkpoint {
+    pub id: u32,
+    pub created_at: DateTime<Utc>,
+    /// Digest of the shadow copy of each file before it was first modified, [None] for files that
+    /// didn't exist
+    #[serde(default)]
+    shadows: BTreeMap<PathBuf, Option<String>>,
+}
+
+impl Checkpoint {
+    /// Paths of the files modified since the checkpoint was created.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.shadows.keys().map(PathBuf::as_path)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Checkpoints {
+    checkpoints: Vec<Checkpoint>,
+    next_id: u32,
+    /// Name of the directory holding the shadow copies, chosen when the first file is recorded
+    #[serde(default)]
+    store: Option<String>,
+}
+
+impl Checkpoints {
+    /// Starts a new checkpoint, which following calls to [Self::record] add files to.
+    ///
+    /// The current checkpoint is replaced when no file was recorded in it.
+    pub async fn begin<P: SystemProvider>(&mut self, provider: &P) -> u32 {
+        if self.checkpoints.last().is_some_and(|c| c.shadows.is_empty()) {
+            self.checkpoints.pop();
+        }
+        if self.checkpoints.len() >= MAX_CHECKPOINTS {
+            let dropped = self.checkpoints.remove(0);
+            self.remove_shadows(&[dropped], provider).await;
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.checkpoints.push(Checkpoint {
+            id,
+            created_at: Utc::now(),
+            shadows: BTreeMap::new(),
+        });
+        id
+    }
+
+    /// Writes a shadow copy of `path` before it is modified, unless it was already recorded in the
+    /// current checkpoint.
+    ///
+    /// The file must not be modified when this fails, since the turn could no longer be reverted.
+    pub async fn record<P: SystemProvider>(&mut self, path: &Path, provider: &P) -> Result<(), CheckpointError> {
+        if self.checkpoints.last().is_none_or(|c| c.shadows.contains_key(path)) {
+            return Ok(());
+        }
+        let record_err = |source: std::io::Error| CheckpointError::Record {
+            path: path.to_path_buf(),
+            source,
+        };
+        let digest = match tokio::fs::read(path).await {
+            Ok(content) => {
+                let digest = format!("{:x}", Sha256::digest(&content));
+                let dir = self.shadow_dir(provider)?;
+                tokio::fs::create_dir_all(&dir).await.map_err(record_err)?;
+                tokio::fs::write(dir.join(&digest), content).await.map_err(record_err)?;
+                Some(digest)
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+            Err(err) => return Err(record_err(err)),
+        };
+        if let Some(checkpoint) = self.checkpoints.last_mut() {
+            checkpoint.shadows.insert(path.to_path_buf(), digest);
+        }
+        Ok(())
+    }
+
+    /// Checkpoints that recorded at least one file, oldest first.
+    pub fn list(&self) -> impl Iterator<Item = &Checkpoint> {
+        self.checkpoints.iter().filter(|c| !c.shadows.is_empty())
+    }
+
+    /// Restores the files modified since checkpoint `id` was created, removing it along with the
+    /// checkpoints after it. Returns the restored paths.
+    pub async fn revert<P: SystemProvider>(&mut self, id: u32, provider: &P) -> Result<Vec<PathBuf>, CheckpointError> {
+        let Some(index) = self.checkpoints.iter().position(|c| c.id == id) else {
+            return Err(CheckpointError::NotFound(id));
+        };
+        let dir = self.shadow_dir(provider)?;
+
+        // The most recent checkpoints are restored first, so that each file ends up with the
+        // content recorded by the oldest checkpoint.
+        let mut restored = Vec::new();
+        while self.checkpoints.len() > index {
+            let checkpoint = self.checkpoints.last().expect("not empty");
+            for (path, digest) in &checkpoint.shadows {
+                let shadow = digest.as_ref().map(|digest| dir.join(digest));
+                restore(path, shadow.as_deref())
+                    .await
+                    .map_err(|source| CheckpointError::Restore {
+                        path: path.clone(),
+                        source,
+                    })?;
+                restored.push(path.clone());
+            }
+            let dropped = self.checkpoints.pop().expect("not empty");
+            self.remove_shadows(&[dropped], provider).await;
+        }
+        restored.sort();
+        restored.dedup();
+        Ok(restored)
+    }
+
+    /// Directory of the shadow copies, under the checkpoints directory.
+    fn shadow_dir<P: SystemProvider>(&mut self, provider: &P) -> Result<PathBuf, UtilError> {
+        let store = self.store.get_or_insert_with(|| uuid::Uuid::new_v4().to_string());
+        Ok(checkpoints_dir(provider)?.join(store))
+    }
+
+    /// Removes the shadow copies of the `dropped` checkpoints that no remaining checkpoint refers
+    /// to.
+    async fn remove_shadows<P: SystemProvider>(&mut self, dropped: &[Checkpoint], provider: &P) {
+        let referenced = self
+            .checkpoints
+            .iter()
+            .flat_map(|c| c.shadows.values().flatten())
+            .collect::<HashSet<_>>();
+        let unreferenced = dropped
+            .iter()
+            .flat_map(|c| c.shadows.values().flatten())
+            .filter(|digest| !referenced.contains(digest))
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        if unreferenced.is_empty() {
+            return;
+        }
+        let dir = match self.shadow_dir(provider) {
+            Ok(dir) => dir,
+            Err(err) => {
+                warn!(?err, "failed to find the checkpoints directory");
+                return;
+            },
+        };
+        for digest in unreferenced {
+            if let Err(err) = tokio::fs::remove_file(dir.join(&digest)).await {
+                warn!(?err, %digest, "failed to remove the shadow copy of a file");
+            }
+        }
+    }
+}
+
+/// Restores `path` from its `shadow` copy, removing it when it didn't exist.
+async fn restore(path: &Path, shadow: Option<&Path>) -> std::io::Result<()> {
+    match shadow {
+        Some(shadow) => {
+            let content = tokio::fs::read(shadow).await?;
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(path, content).await
+        },
+        None => match tokio::fs::remove_file(path).await {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::util::test::TestBase;
+
+    #[tokio::test]
+    async fn test_revert() {
+        let test_base = TestBase::new()
+            .await
+            .with_file(("edited.txt", "original content"))
+            .await;
+        let provider = test_base.provider();
+        let edited = test_base.join("edited.txt");
+        let created = test_base.join("nested/created.txt");
+        let mut checkpoints = Checkpoints::default();
+
+        let first = checkpoints.begin(provider).await;
+        checkpoints.record(&edited, provider).await.unwrap();
+        tokio::fs::write(&edited, "first turn").await.unwrap();
+        checkpoints.record(&edited, provider).await.unwrap();
+        tokio::fs::write(&edited, "first turn, again").await.unwrap();
+
+        let second = checkpoints.begin(provider).await;
+        checkpoints.record(&edited, provider).await.unwrap();
+        tokio::fs::write(&edited, "second turn").await.unwrap();
+        checkpoints.record(&created, provider).await.unwrap();
+        tokio::fs::create_dir_all(created.parent().unwrap()).await.unwrap();
+        tokio::fs::write(&created, "new").await.unwrap();
+        assert!(matches!(
+            checkpoints.record(&test_base.join("home"), provider).await,
+            Err(CheckpointError::Record { .. })
+        ));
+
+        // The content of the files is kept in shadow copies rather than in the checkpoints
+        let shadow_dir = checkpoints.shadow_dir(provider).unwrap();
+        assert_eq!(std::fs::read_dir(&shadow_dir).unwrap().count(), 2);
+        assert!(
+            !serde_json::to_string(&checkpoints)
+                .unwrap()
+                .contains("original content")
+        );
+
+        // A turn without any modification replaces its checkpoint
+        let third = checkpoints.begin(provider).await;
+        assert_eq!(checkpoints.begin(provider).await, third + 1);
+        assert_eq!(checkpoints.list().map(|c| c.id).collect::<Vec<_>>(), vec![
+            first, second
+        ]);
+
+        assert_eq!(checkpoints.revert(second, provider).await.unwrap(), vec![
+            edited.clone(),
+            created.clone()
+        ]);
+        assert_eq!(tokio::fs::read_to_string(&edited).await.unwrap(), "first turn, again");
+        assert!(!created.exists());
+
+        assert_eq!(checkpoints.revert(first, provider).await.unwrap(), vec![edited.clone()]);
+        assert_eq!(tokio::fs::read_to_string(&edited).await.unwrap(), "original content");
+        assert_eq!(std::fs::read_dir(&shadow_dir).unwrap().count(), 0);
+        assert!(matches!(
+            checkpoints.revert(first, provider).await,
+            Err(CheckpointError::NotFound(_))
+        ));
+    }
+}
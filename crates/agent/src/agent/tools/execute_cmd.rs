@@ -13,7 +13,12 @@ use serde::{
     Deserialize,
     Serialize,
 };
+use tokio::io::{
+    AsyncRead,
+    AsyncReadExt as _,
+};
 use tokio::process::Command;
+use tokio::sync::mpsc;
 
 use super::{
     BuiltInToolName,
@@ -22,6 +27,7 @@ use super::{
     ToolExecutionOutput,
     ToolExecutionOutputItem,
     ToolExecutionResult,
+    ToolOutputChunk,
 };
 use crate::agent::util::consts::{
     USER_AGENT_APP_NAME,
@@ -96,29 +102,43 @@ impl ExecuteCmd {
         }
     }
 
-    pub async fn execute(&self) -> ToolExecutionResult {
+    /// Executes the command, sending chunks of its stdout and stderr to `output_tx` as they are
+    /// read.
+    ///
+    /// The command runs in its own process group, which is killed if the returned future is
+    /// dropped before the command exits, e.g. when the tool times out or is cancelled. Processes
+    /// left running in the background by a command that exited are not killed.
+    pub async fn execute(&self, output_tx: Option<mpsc::Sender<ToolOutputChunk>>) -> ToolExecutionResult {
         let shell = std::env::var("AMAZON_Q_CHAT_SHELL").unwrap_or("bash".to_string());
 
         let env_vars = env_vars_with_user_agent();
 
-        let child = Command::new(shell)
+        let mut child = Command::new(shell)
             .arg("-c")
             .arg(&self.command)
             .envs(env_vars)
-            .stdin(Stdio::inherit())
+            // A background process group is stopped when it reads from the terminal
+            .stdin(Stdio::null())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
+            .process_group(0)
+            .kill_on_drop(true)
             .spawn()
             .map_err(|e| ToolExecutionError::io(format!("Failed to spawn command '{}'", &self.command), e))?;
+        let mut process_group = ProcessGroupGuard(child.id());
 
-        let output = child
-            .wait_with_output()
-            .await
-            .map_err(|e| ToolExecutionError::io(format!("No exit status for '{}'", &self.command), e))?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+        let (stdout, stderr, exit_status) = tokio::try_join!(
+            read_output(stdout, output_tx.clone(), ToolOutputChunk::Stdout),
+            read_output(stderr, output_tx, ToolOutputChunk::Stderr),
+            child.wait(),
+        )
+        .map_err(|e| ToolExecutionError::io(format!("No exit status for '{}'", &self.command), e))?;
+        process_group.disarm();
 
-        let exit_status = output.status;
-        let clean_stdout = sanitize_unicode_tags(output.stdout.to_str_lossy());
-        let clean_stderr = sanitize_unicode_tags(output.stderr.to_str_lossy());
+        let clean_stdout = sanitize_unicode_tags(stdout.to_str_lossy());
+        let clean_stderr = sanitize_unicode_tags(stderr.to_str_lossy());
 
         let result = serde_json::json!({
             "exit_status": exit_status.to_string(),
@@ -132,6 +152,66 @@ impl ExecuteCmd {
     }
 }
 
+/// Kills the process group led by the process with the given id when dropped, unless
+/// [ProcessGroupGuard::disarm] was called first.
+///
+/// `kill_on_drop` alone only kills the shell, leaving the programs it started running.
+struct ProcessGroupGuard(Option<u32>);
+
+impl ProcessGroupGuard {
+    fn disarm(&mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        let Some(pgid) = self.0.and_then(|id| libc::pid_t::try_from(id).ok()) else {
+            return;
+        };
+        // SAFETY: killpg has no memory safety requirements. The group can't have been reused
+        // yet, since its leader hasn't been reaped.
+        if unsafe { libc::killpg(pgid, libc::SIGKILL) } != 0 {
+            tracing::debug!(pgid, err = %std::io::Error::last_os_error(), "failed to kill process group");
+        }
+    }
+}
+
+/// Reads `reader` to the end, sending the text read so far to `output_tx` after each read.
+///
+/// A multi-byte character split between two reads is sent once it is complete. Chunks are dropped
+/// rather than slowing the command down when the receiver falls behind, since the full output is
+/// returned regardless.
+async fn read_output(
+    mut reader: impl AsyncRead + Unpin,
+    output_tx: Option<mpsc::Sender<ToolOutputChunk>>,
+    to_chunk: fn(String) -> ToolOutputChunk,
+) -> std::io::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    let mut buf = [0; 4096];
+    let mut sent = 0;
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(output);
+        }
+        output.extend_from_slice(&buf[..n]);
+
+        let Some(output_tx) = &output_tx else {
+            continue;
+        };
+        let unsent = &output[sent..];
+        let complete = match std::str::from_utf8(unsent) {
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            _ => unsent.len(),
+        };
+        if complete > 0 {
+            let _ = output_tx.try_send(to_chunk(sanitize_unicode_tags(unsent[..complete].to_str_lossy())));
+            sent += complete;
+        }
+    }
+}
+
 /// Returns `true` if the character is from an invisible or control Unicode range
 /// that is considered unsafe for LLM input. These rarely appear in normal input,
 /// so stripping them is generally safe.
@@ -206,9 +286,100 @@ fn env_vars_with_user_agent() -> HashMap<String, String> {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
+    use tokio::io::AsyncWriteExt as _;
+
     use super::*;
 
+    #[tokio::test]
+    async fn test_execute_streams_output() {
+        let (output_tx, mut output_rx) = mpsc::channel(16);
+        let cmd = ExecuteCmd {
+            command: "echo out; echo err >&2".to_string(),
+        };
+        let output = cmd.execute(Some(output_tx)).await.unwrap();
+
+        let mut chunks = Vec::new();
+        while let Some(chunk) = output_rx.recv().await {
+            chunks.push(chunk);
+        }
+        assert!(
+            chunks.contains(&ToolOutputChunk::Stdout("out\n".to_string())),
+            "{chunks:?}"
+        );
+        assert!(
+            chunks.contains(&ToolOutputChunk::Stderr("err\n".to_string())),
+            "{chunks:?}"
+        );
+        let ToolExecutionOutputItem::Json(result) = &output.items[0] else {
+            panic!("expected json output");
+        };
+        assert_eq!(result["stdout"], "out\n");
+        assert_eq!(result["stderr"], "err\n");
+    }
+
+    #[tokio::test]
+    async fn test_execute_kills_process_group_when_dropped() {
+        let (output_tx, mut output_rx) = mpsc::channel(16);
+        let cmd = ExecuteCmd {
+            command: "sleep 60 & echo $!; wait".to_string(),
+        };
+        let execute = tokio::spawn(async move { cmd.execute(Some(output_tx)).await });
+
+        let Some(ToolOutputChunk::Stdout(pid)) = output_rx.recv().await else {
+            panic!("expected the pid of sleep");
+        };
+        let pid = pid.trim().parse::<libc::pid_t>().unwrap();
+        assert!(is_running(pid));
+
+        // Aborting the task drops the future, as a timeout or cancellation does
+        execute.abort();
+        let _ = execute.await;
+        for _ in 0..100 {
+            if !is_running(pid) {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        panic!("sleep is still running after the command was dropped");
+    }
+
+    /// Whether the process exists and isn't a zombie waiting to be reaped by its parent.
+    pub(crate) fn is_running(pid: libc::pid_t) -> bool {
+        // SAFETY: signal 0 only checks whether the process exists
+        if unsafe { libc::kill(pid, 0) } != 0 {
+            return false;
+        }
+        match std::fs::read_to_string(format!("/proc/{pid}/stat")) {
+            // The state follows the parenthesized name of the program
+            Ok(stat) => !stat
+                .rsplit(')')
+                .next()
+                .is_some_and(|rest| rest.trim_start().starts_with('Z')),
+            Err(_) => true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_output_splits_on_char_boundaries() {
+        let (output_tx, mut output_rx) = mpsc::channel(16);
+        let (mut writer, reader) = tokio::io::duplex(64);
+        let read = tokio::spawn(read_output(reader, Some(output_tx), ToolOutputChunk::Stdout));
+
+        let crab = "🦀".as_bytes();
+        writer.write_all(&crab[..2]).await.unwrap();
+        tokio::task::yield_now().await;
+        writer.write_all(&crab[2..]).await.unwrap();
+        drop(writer);
+
+        assert_eq!(read.await.unwrap().unwrap(), crab);
+        let mut text = String::new();
+        while let Some(ToolOutputChunk::Stdout(chunk)) = output_rx.recv().await {
+            text.push_str(&chunk);
+        }
+        assert_eq!(text, "🦀");
+    }
+
     #[test]
     fn is_hidden_recognises_all_ranges() {
         let samples = ['\u{E0000}', '\u{200B}', '\u{2028}', '\u{205F}', '\u{FFF0}'];
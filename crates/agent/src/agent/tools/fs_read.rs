@@ -16,6 +16,11 @@ use tokio::io::{
 };
 use tokio_stream::wrappers::LinesStream;
 
+use super::path::{
+    io_error,
+    resolve_file,
+    resolve_path,
+};
 use super::{
     BuiltInToolName,
     BuiltInToolTrait,
@@ -24,7 +29,6 @@ use super::{
     ToolExecutionOutputItem,
     ToolExecutionResult,
 };
-use crate::util::path::canonicalize_path_sys;
 use crate::util::providers::SystemProvider;
 
 const MAX_READ_SIZE: u32 = 250 * 1024;
@@ -86,24 +90,11 @@ impl FsRead {
         serde_json::to_value(schema).expect("creating tool schema should not fail")
     }
 
-    pub async fn validate<P: SystemProvider>(&self, provider: &P) -> Result<(), String> {
+    pub async fn validate<P: SystemProvider>(&self, roots: &[PathBuf], provider: &P) -> Result<(), String> {
         let mut errors = Vec::new();
         for op in &self.ops {
-            let path = PathBuf::from(canonicalize_path_sys(&op.path, provider).map_err(|e| e.to_string())?);
-            if !path.exists() {
-                errors.push(format!("'{}' does not exist", path.to_string_lossy()));
-                continue;
-            }
-            let file_md = tokio::fs::symlink_metadata(&path).await;
-            let Ok(file_md) = file_md else {
-                errors.push(format!(
-                    "Failed to check file metadata for '{}'",
-                    path.to_string_lossy()
-                ));
-                continue;
-            };
-            if !file_md.is_file() {
-                errors.push(format!("'{}' is not a file", path.to_string_lossy()));
+            if let Err(err) = resolve_file(&op.path, roots, provider).await {
+                errors.push(err.to_string());
             }
         }
         if !errors.is_empty() {
@@ -113,11 +104,11 @@ impl FsRead {
         }
     }
 
-    pub async fn execute<P: SystemProvider>(&self, provider: &P) -> ToolExecutionResult {
+    pub async fn execute<P: SystemProvider>(&self, roots: &[PathBuf], provider: &P) -> ToolExecutionResult {
         let mut results = Vec::new();
         let mut errors = Vec::new();
         for op in &self.ops {
-            match op.execute(provider).await {
+            match op.execute(roots, provider).await {
                 Ok(res) => results.push(res),
                 Err(err) => errors.push((op.clone(), err)),
             }
@@ -146,17 +137,19 @@ pub struct FsReadOp {
 }
 
 impl FsReadOp {
-    async fn execute<P: SystemProvider>(&self, provider: &P) -> Result<ToolExecutionOutputItem, ToolExecutionError> {
-        let path = PathBuf::from(
-            canonicalize_path_sys(&self.path, provider).map_err(|e| ToolExecutionError::Custom(e.to_string()))?,
-        );
+    async fn execute<P: SystemProvider>(
+        &self,
+        roots: &[PathBuf],
+        provider: &P,
+    ) -> Result<ToolExecutionOutputItem, ToolExecutionError> {
+        let path = resolve_path(&self.path, roots, provider)?;
 
         // TODO: add line numbers
         let file_lines = LinesStream::new(
             BufReader::new(
                 fs::File::open(&path)
                     .await
-                    .map_err(|e| ToolExecutionError::io(format!("failed to read {}", path.to_string_lossy()), e))?,
+                    .map_err(|e| io_error(format!("failed to read {}", path.to_string_lossy()), &path, e))?,
             )
             .lines(),
         );
@@ -213,8 +206,8 @@ mod tests {
             }],
         };
 
-        assert!(tool.validate(&test_base).await.is_ok());
-        let result = tool.execute(&test_base).await.unwrap();
+        assert!(tool.validate(&[], &test_base).await.is_ok());
+        let result = tool.execute(&[], &test_base).await.unwrap();
         assert_eq!(result.items.len(), 1);
         if let ToolExecutionOutputItem::Text(content) = &result.items[0] {
             assert_eq!(content, "line1\nline2\nline3");
@@ -236,7 +229,7 @@ mod tests {
             }],
         };
 
-        let result = tool.execute(&test_base).await.unwrap();
+        let result = tool.execute(&[], &test_base).await.unwrap();
         if let ToolExecutionOutputItem::Text(content) = &result.items[0] {
             assert_eq!(content, "line2\nline3");
         }
@@ -266,7 +259,7 @@ mod tests {
             ],
         };
 
-        let result = tool.execute(&test_base).await.unwrap();
+        let result = tool.execute(&[], &test_base).await.unwrap();
         assert_eq!(result.items.len(), 2);
     }
 
@@ -281,7 +274,7 @@ mod tests {
             }],
         };
 
-        assert!(tool.validate(&test_base).await.is_err());
+        assert!(tool.validate(&[], &test_base).await.is_err());
     }
 
     #[tokio::test]
@@ -296,6 +289,6 @@ mod tests {
             }],
         };
 
-        assert!(tool.validate(&test_base).await.is_err());
+        assert!(tool.validate(&[], &test_base).await.is_err());
     }
 }
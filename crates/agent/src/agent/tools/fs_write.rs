@@ -9,13 +9,17 @@ use serde::{
 };
 use syntect::util::LinesWithEndings;
 
+use super::path::{
+    io_error,
+    resolve_file,
+    resolve_path,
+};
 use super::{
     BuiltInToolName,
     BuiltInToolTrait,
     ToolExecutionError,
     ToolExecutionResult,
 };
-use crate::util::path::canonicalize_path_sys;
 use crate::util::providers::SystemProvider;
 
 const FS_WRITE_TOOL_DESCRIPTION: &str = r#"
@@ -116,33 +120,30 @@ impl FsWrite {
         }
     }
 
-    fn canonical_path<P: SystemProvider>(&self, provider: &P) -> Result<PathBuf, String> {
-        Ok(PathBuf::from(
-            canonicalize_path_sys(self.path(), provider).map_err(|e| e.to_string())?,
-        ))
+    pub fn canonical_path<P: SystemProvider>(&self, roots: &[PathBuf], provider: &P) -> Result<PathBuf, String> {
+        resolve_path(self.path(), roots, provider).map_err(|e| e.to_string())
     }
 
-    pub async fn validate<P: SystemProvider>(&self, provider: &P) -> Result<(), String> {
+    pub async fn validate<P: SystemProvider>(&self, roots: &[PathBuf], provider: &P) -> Result<(), String> {
         let mut errors = Vec::new();
 
         if self.path().is_empty() {
             errors.push("Path must not be empty".to_string());
+        } else {
+            let resolved = match &self {
+                FsWrite::Create(_) => resolve_path(self.path(), roots, provider),
+                // The file must exist in order to replace or insert contents into it
+                FsWrite::StrReplace(_) | FsWrite::Insert(_) => resolve_file(self.path(), roots, provider).await,
+            };
+            if let Err(err) = resolved {
+                errors.push(err.to_string());
+            }
         }
 
-        match &self {
-            FsWrite::Create(_) => (),
-            FsWrite::StrReplace(_) => {
-                if !self.canonical_path(provider)?.exists() {
-                    errors.push(
-                        "The provided path must exist in order to replace or insert contents into it".to_string(),
-                    );
-                }
-            },
-            FsWrite::Insert(v) => {
-                if v.content.is_empty() {
-                    errors.push("Content to insert must not be empty".to_string());
-                }
-            },
+        if let FsWrite::Insert(v) = &self {
+            if v.content.is_empty() {
+                errors.push("Content to insert must not be empty".to_string());
+            }
         }
 
         if !errors.is_empty() {
@@ -162,9 +163,10 @@ impl FsWrite {
     pub async fn execute<P: SystemProvider>(
         &self,
         _state: Option<&mut FsWriteState>,
+        roots: &[PathBuf],
         provider: &P,
     ) -> ToolExecutionResult {
-        let path = self.canonical_path(provider).map_err(ToolExecutionError::Custom)?;
+        let path = resolve_path(self.path(), roots, provider)?;
 
         match &self {
             FsWrite::Create(v) => v.execute(path).await?,
@@ -189,14 +191,18 @@ impl FileCreate {
         if let Some(parent) = path.parent() {
             if !parent.exists() {
                 tokio::fs::create_dir_all(parent).await.map_err(|e| {
-                    ToolExecutionError::io(format!("failed to create directory {}", parent.to_string_lossy()), e)
+                    io_error(
+                        format!("failed to create directory {}", parent.to_string_lossy()),
+                        parent,
+                        e,
+                    )
                 })?;
             }
         }
 
         tokio::fs::write(path, &self.content)
             .await
-            .map_err(|e| ToolExecutionError::io(format!("failed to write to {}", path.to_string_lossy()), e))?;
+            .map_err(|e| io_error(format!("failed to write to {}", path.to_string_lossy()), path, e))?;
 
         Ok(())
     }
@@ -218,7 +224,7 @@ impl StrReplace {
 
         let file = tokio::fs::read_to_string(path)
             .await
-            .map_err(|e| ToolExecutionError::io(format!("failed to read {}", path.to_string_lossy()), e))?;
+            .map_err(|e| io_error(format!("failed to read {}", path.to_string_lossy()), path, e))?;
 
         let matches = file.match_indices(&self.old_str).collect::<Vec<_>>();
         match matches.len() {
@@ -232,7 +238,7 @@ impl StrReplace {
                 let file = file.replacen(&self.old_str, &self.new_str, 1);
                 tokio::fs::write(path, file)
                     .await
-                    .map_err(|e| ToolExecutionError::io(format!("failed to read {}", path.to_string_lossy()), e))?;
+                    .map_err(|e| io_error(format!("failed to read {}", path.to_string_lossy()), path, e))?;
             },
             x => {
                 if !self.replace_all {
@@ -243,7 +249,7 @@ impl StrReplace {
                 let file = file.replace(&self.old_str, &self.new_str);
                 tokio::fs::write(path, file)
                     .await
-                    .map_err(|e| ToolExecutionError::io(format!("failed to read {}", path.to_string_lossy()), e))?;
+                    .map_err(|e| io_error(format!("failed to read {}", path.to_string_lossy()), path, e))?;
             },
         }
 
@@ -265,7 +271,7 @@ impl Insert {
 
         let mut file = tokio::fs::read_to_string(path)
             .await
-            .map_err(|e| ToolExecutionError::io(format!("failed to read {}", path.to_string_lossy()), e))?;
+            .map_err(|e| io_error(format!("failed to read {}", path.to_string_lossy()), path, e))?;
 
         let line_count = file.lines().count() as u32;
 
@@ -292,7 +298,7 @@ impl Insert {
 
         tokio::fs::write(path, file)
             .await
-            .map_err(|e| ToolExecutionError::io(format!("failed to write to {}", path.to_string_lossy()), e))?;
+            .map_err(|e| io_error(format!("failed to write to {}", path.to_string_lossy()), path, e))?;
 
         Ok(())
     }
@@ -364,8 +370,8 @@ mod tests {
             content: "hello world".to_string(),
         });
 
-        assert!(tool.validate(&test_base).await.is_ok());
-        assert!(tool.execute(None, &test_base).await.is_ok());
+        assert!(tool.validate(&[], &test_base).await.is_ok());
+        assert!(tool.execute(None, &[], &test_base).await.is_ok());
 
         let content = tokio::fs::read_to_string(test_base.join("new.txt")).await.unwrap();
         assert_eq!(content, "hello world");
@@ -379,7 +385,7 @@ mod tests {
             content: "nested content".to_string(),
         });
 
-        assert!(tool.execute(None, &test_base).await.is_ok());
+        assert!(tool.execute(None, &[], &test_base).await.is_ok());
 
         let content = tokio::fs::read_to_string(test_base.join("nested/dir/file.txt"))
             .await
@@ -398,7 +404,7 @@ mod tests {
             replace_all: false,
         });
 
-        assert!(tool.execute(None, &test_base).await.is_ok());
+        assert!(tool.execute(None, &[], &test_base).await.is_ok());
 
         let content = tokio::fs::read_to_string(test_base.join("test.txt")).await.unwrap();
         assert_eq!(content, "hello rust");
@@ -415,7 +421,7 @@ mod tests {
             replace_all: true,
         });
 
-        assert!(tool.execute(None, &test_base).await.is_ok());
+        assert!(tool.execute(None, &[], &test_base).await.is_ok());
 
         let content = tokio::fs::read_to_string(test_base.join("test.txt")).await.unwrap();
         assert_eq!(content, "baz bar baz");
@@ -432,7 +438,7 @@ mod tests {
             replace_all: false,
         });
 
-        assert!(tool.execute(None, &test_base).await.is_err());
+        assert!(tool.execute(None, &[], &test_base).await.is_err());
     }
 
     #[tokio::test]
@@ -448,7 +454,7 @@ mod tests {
             insert_line: Some(1),
         });
 
-        assert!(tool.execute(None, &test_base).await.is_ok());
+        assert!(tool.execute(None, &[], &test_base).await.is_ok());
 
         let content = tokio::fs::read_to_string(test_base.join("test.txt")).await.unwrap();
         assert_eq!(content, "line1\ninserted\nline2\nline3");
@@ -464,7 +470,7 @@ mod tests {
             insert_line: None,
         });
 
-        assert!(tool.execute(None, &test_base).await.is_ok());
+        assert!(tool.execute(None, &[], &test_base).await.is_ok());
 
         let content = tokio::fs::read_to_string(test_base.join("test.txt")).await.unwrap();
         assert_eq!(content, "existing\nappended");
@@ -478,7 +484,7 @@ mod tests {
             content: "content".to_string(),
         });
 
-        assert!(tool.validate(&test_base).await.is_err());
+        assert!(tool.validate(&[], &test_base).await.is_err());
     }
 
     #[tokio::test]
@@ -491,6 +497,6 @@ mod tests {
             replace_all: false,
         });
 
-        assert!(tool.validate(&test_base).await.is_err());
+        assert!(tool.validate(&[], &test_base).await.is_err());
     }
 }
@@ -29,6 +29,10 @@ const GREP_SCHEMA: &str = r#"
                 "type": "string",
                 "description": "Glob pattern"
             }
+        },
+        "cursor": {
+            "type": "string",
+            "description": "Cursor returned by the previous page of the same search, to get the next page"
         }
     },
     "required": [
@@ -48,6 +52,9 @@ pub struct Grep {
     pattern: String,
     base: Option<String>,
     paths: Option<String>,
+    /// Cursor of the page of matches to return, see [super::pagination]
+    #[serde(default)]
+    cursor: Option<String>,
 }
 
 impl Grep {}
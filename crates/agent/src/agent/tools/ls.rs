@@ -5,6 +5,10 @@ use std::path::{
     PathBuf,
 };
 
+use globset::{
+    GlobBuilder,
+    GlobMatcher,
+};
 use serde::{
     Deserialize,
     Serialize,
@@ -16,6 +20,17 @@ use tracing::{
     warn,
 };
 
+use super::pagination::{
+    Cursor,
+    Page,
+    PageLimits,
+    fingerprint,
+};
+use super::path::{
+    io_error,
+    resolve_dir,
+    resolve_path,
+};
 use super::{
     BuiltInToolName,
     BuiltInToolTrait,
@@ -26,7 +41,6 @@ use crate::agent::tools::{
     ToolExecutionOutputItem,
 };
 use crate::agent::util::glob::matches_any_pattern;
-use crate::util::path::canonicalize_path_sys;
 use crate::util::providers::SystemProvider;
 
 const LS_TOOL_DESCRIPTION: &str = r#"
@@ -36,10 +50,21 @@ HOW TO USE:
 - Provide the path to the directory you want to view
 - Optionally provide a depth to recursively list directory contents
 - Optionally provide a list of glob patterns to exclude files and directories from being searched
+- Optionally provide a list of glob patterns that listed files must match, directories are still searched
+- Optionally sort the entries of each directory by name, size or modification time (the default, newest first)
+- Optionally provide the stat fields to return, in which case the entries are returned as JSON
+
+FEATURES:
+- Entries ignored by .gitignore files are skipped, unless respect_gitignore is false
+- Returning the stat fields as JSON avoids having to run `ls -la` with the command tool
+
+PAGINATION:
+- Large listings are returned in pages, by default of at most 1000 entries
+- When more entries are available, the result includes a cursor
+- Call the tool again with the same arguments and the cursor to get the next page
 
 LIMITATIONS:
-- Only 1000 entries will be returned
-- Directories containing over 10000 entries will be truncated
+- Only the first 10000 entries of a directory are listed
 "#;
 
 const LS_SCHEMA: &str = r#"
@@ -62,6 +87,37 @@ const LS_SCHEMA: &str = r#"
                 "type": "string",
                 "description": "Glob pattern to ignore"
             }
+        },
+        "include": {
+            "type": "array",
+            "description": "List of glob patterns that listed files must match",
+            "items": {
+                "type": "string",
+                "description": "Glob pattern to include, e.g. *.rs"
+            }
+        },
+        "respect_gitignore": {
+            "type": "boolean",
+            "description": "Whether to skip entries ignored by .gitignore files",
+            "default": true
+        },
+        "sort_by": {
+            "type": "string",
+            "description": "How to sort the entries of each directory",
+            "enum": ["name", "size", "modified"],
+            "default": "modified"
+        },
+        "fields": {
+            "type": "array",
+            "description": "Stat fields to return for each entry, as JSON",
+            "items": {
+                "type": "string",
+                "enum": ["type", "size", "modified", "permissions", "owner", "links"]
+            }
+        },
+        "cursor": {
+            "type": "string",
+            "description": "Cursor returned by the previous page of the same listing, to get the next page"
         }
     },
     "required": [
@@ -75,9 +131,6 @@ const LS_SCHEMA: &str = r#"
 /// The model would have to explicitly search these directories if it wants to.
 const IGNORE_PATTERNS: [&str; 7] = ["node_modules", "bin", "build", "dist", "out", ".cache", ".git"];
 
-// The max number of entry listing results to send to the model.
-const MAX_LS_ENTRIES: usize = 1000;
-
 /// The maximum amount of entries that will be read within a given directory.
 const MAX_ENTRY_COUNT_PER_DIR: usize = 10_000;
 
@@ -95,41 +148,82 @@ impl BuiltInToolTrait for Ls {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Ls {
     pub path: String,
+    #[serde(alias = "max_depth")]
     pub depth: Option<usize>,
     pub ignore: Option<Vec<String>>,
+    /// Glob patterns that listed files must match
+    #[serde(default)]
+    pub include: Option<Vec<String>>,
+    /// Whether to skip entries ignored by .gitignore files, defaults to true
+    #[serde(default)]
+    pub respect_gitignore: Option<bool>,
+    #[serde(default)]
+    pub sort_by: Option<LsSortBy>,
+    /// Stat fields to return as JSON, the entries are returned in the long format of `ls`
+    /// otherwise
+    #[serde(default)]
+    pub fields: Option<Vec<LsField>>,
+    /// Cursor of the page to return, from the previous page
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+/// How the entries of each directory are sorted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LsSortBy {
+    Name,
+    /// Largest first
+    Size,
+    /// Newest first
+    #[default]
+    Modified,
+}
+
+/// Stat fields of an entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LsField {
+    Type,
+    Size,
+    Modified,
+    Permissions,
+    /// User and group ids
+    Owner,
+    /// Number of hard links
+    Links,
 }
 
 impl Ls {
     const DEFAULT_DEPTH: usize = 0;
 
-    pub async fn validate<P: SystemProvider>(&self, provider: &P) -> Result<(), String> {
-        let path = self.canonical_path(provider)?;
-        if !path.exists() {
-            return Err(format!("Directory not found: {}", path.to_string_lossy()));
-        }
-        if !tokio::fs::symlink_metadata(&path)
+    pub async fn validate<P: SystemProvider>(&self, roots: &[PathBuf], provider: &P) -> Result<(), String> {
+        resolve_dir(&self.path, roots, provider)
             .await
-            .map_err(|e| {
-                format!(
-                    "failed to check file metadata for path '{}': {}",
-                    path.to_string_lossy(),
-                    e
-                )
-            })?
-            .is_dir()
-        {
-            return Err(format!("Path is not a directory: {}", path.to_string_lossy()));
-        }
+            .map_err(|e| e.to_string())?;
         Ok(())
     }
 
-    pub async fn execute<P: SystemProvider>(&self, provider: &P) -> ToolExecutionResult {
-        let path = self.canonical_path(provider)?;
+    pub async fn execute<P: SystemProvider>(
+        &self,
+        roots: &[PathBuf],
+        page_limits: PageLimits,
+        provider: &P,
+    ) -> ToolExecutionResult {
+        let path = resolve_path(&self.path, roots, provider)?;
         let max_depth = self.depth();
-        debug!(?path, max_depth, "Reading directory at path with depth");
+        let fingerprint = self.fingerprint();
+        let cursor = match &self.cursor {
+            Some(cursor) => Some(Cursor::parse(cursor, fingerprint)?),
+            None => None,
+        };
+        // Entries listed before there are enough for the requested page, and one more to know
+        // whether there are more pages
+        let max_entries = cursor.map_or(0, |c| c.offset()) + page_limits.max_items + 1;
+        debug!(?path, max_depth, ?cursor, "Reading directory at path with depth");
 
         // Lines to include before the listing results
         let mut prefix = Vec::new();
@@ -142,26 +236,44 @@ impl Ls {
             prefix.push(format!("User id: {}", user_id));
         }
 
+        let mut gitignore = match self.respect_gitignore.unwrap_or(true) {
+            true => Some(GitIgnore::for_ancestors(&path).await),
+            false => None,
+        };
+
         let mut dir_queue = VecDeque::new();
         dir_queue.push_back((path.clone(), 0));
-        while let Some((dir_path, depth)) = dir_queue.pop_front() {
+        'dirs: while let Some((dir_path, depth)) = dir_queue.pop_front() {
             if depth > max_depth {
                 break;
             }
+            if let Some(gitignore) = gitignore.as_mut() {
+                gitignore.add_dir(&dir_path).await;
+            }
 
-            let mut read_dir = tokio::fs::read_dir(&dir_path)
-                .await
-                .map_err(|e| format!("failed to read directory path '{}': {}", dir_path.to_string_lossy(), e))?;
+            let mut read_dir = tokio::fs::read_dir(&dir_path).await.map_err(|e| {
+                io_error(
+                    format!("failed to read directory path '{}'", dir_path.to_string_lossy()),
+                    &dir_path,
+                    e,
+                )
+            })?;
 
             let mut entries = Vec::new();
-            let mut exceeded_threshold = false;
-
-            let mut i = 0;
             while let Some(ent) = read_dir
                 .next_entry()
                 .await
                 .map_err(|e| format!("failed to get next entry: {}", e))?
             {
+                if entries.len() >= MAX_ENTRY_COUNT_PER_DIR {
+                    prefix.push(format!(
+                        "Directory at {} was truncated to its first {} entries",
+                        dir_path.to_string_lossy(),
+                        MAX_ENTRY_COUNT_PER_DIR
+                    ));
+                    break;
+                }
+
                 // Ignore the entry if it matches one of the ignore arguments.
                 let entry_path = ent.path();
                 if self.matches_ignore_patterns(&entry_path) {
@@ -169,51 +281,84 @@ impl Ls {
                     continue;
                 }
 
-                entries.push(Entry::new(ent).await?);
-                i += 1;
-                if i > MAX_ENTRY_COUNT_PER_DIR {
-                    exceeded_threshold = true;
+                let entry = Entry::new(ent).await?;
+                if gitignore
+                    .as_ref()
+                    .is_some_and(|gitignore| gitignore.is_ignored(&entry.path, entry.metadata.is_dir()))
+                {
+                    trace!("ignoring file from .gitignore: {}", entry.path.to_string_lossy());
+                    continue;
                 }
+                entries.push(entry);
             }
 
-            entries.sort_by_key(|ent| ent.last_modified);
-            entries.reverse();
+            match self.sort_by.unwrap_or_default() {
+                LsSortBy::Name => entries.sort_by(|a, b| a.path.cmp(&b.path)),
+                LsSortBy::Size => entries.sort_by_key(|ent| std::cmp::Reverse(ent.metadata.len())),
+                LsSortBy::Modified => entries.sort_by_key(|ent| std::cmp::Reverse(ent.last_modified)),
+            }
 
             // Finally, handle results
-            for entry in &entries {
-                result.push(entry.to_long_format());
-
-                // Break if we've exceeded the Ls result threshold.
-                if result.len() > MAX_LS_ENTRIES {
-                    prefix.push(format!(
-                        "Directory at {} was truncated (has total {}{} entries)",
-                        dir_path.to_string_lossy(),
-                        entries.len(),
-                        if exceeded_threshold { "+" } else { "" }
-                    ));
-                    break;
-                }
-
-                // Otherwise, continue searching
+            for entry in entries {
                 if entry.metadata.is_dir() {
                     // Exclude the directory from being searched if it is a commonly ignored
                     // directory.
-                    if matches_any_pattern(IGNORE_PATTERNS, entry.path.to_string_lossy()) {
-                        continue;
+                    if !matches_any_pattern(IGNORE_PATTERNS, entry.path.to_string_lossy()) {
+                        dir_queue.push_back((entry.path.clone(), depth + 1));
+                    }
+                }
+
+                if self.matches_include_patterns(&entry) {
+                    result.push(entry);
+                    // Stop once the entries of the requested page are listed
+                    if result.len() >= max_entries {
+                        break 'dirs;
                     }
-                    dir_queue.push_back((entry.path.clone(), depth + 1));
                 }
             }
         }
 
+        if let Some(fields) = &self.fields {
+            let page = Page::take(
+                result.iter().map(|entry| entry.to_json(fields)),
+                fingerprint,
+                cursor,
+                page_limits,
+                |value| value.to_string().len(),
+            );
+            return Ok(ToolExecutionOutput::new(vec![ToolExecutionOutputItem::Json(
+                serde_json::json!({
+                    "notes": prefix,
+                    "entries": page.items,
+                    "next_cursor": page.next_cursor.map(|cursor| cursor.to_string()),
+                }),
+            )]));
+        }
+
+        let page = Page::take(
+            result.iter().map(Entry::to_long_format),
+            fingerprint,
+            cursor,
+            page_limits,
+            |line| line.len() + 1,
+        );
+        prefix.extend(page.next_page_note());
         let prefix = prefix.join("\n");
-        let result = result.join("\n");
+        let result = page.items.join("\n");
         Ok(ToolExecutionOutput::new(vec![ToolExecutionOutputItem::Text(format!(
             "{}\n{}",
             prefix, result
         ))]))
     }
 
+    /// Fingerprint of the arguments for the pagination cursors, see [fingerprint].
+    fn fingerprint(&self) -> u64 {
+        fingerprint(&Self {
+            cursor: None,
+            ..self.clone()
+        })
+    }
+
     fn matches_ignore_patterns(&self, path: impl AsRef<Path>) -> bool {
         let path = path.as_ref().to_string_lossy();
         match &self.ignore {
@@ -222,10 +367,19 @@ impl Ls {
         }
     }
 
-    fn canonical_path<P: SystemProvider>(&self, provider: &P) -> Result<PathBuf, String> {
-        Ok(PathBuf::from(
-            canonicalize_path_sys(&self.path, provider).map_err(|e| e.to_string())?,
-        ))
+    /// Whether `entry` should be listed. Directories that don't match are still searched.
+    fn matches_include_patterns(&self, entry: &Entry) -> bool {
+        match &self.include {
+            Some(patterns) if !entry.metadata.is_dir() => {
+                matches_any_pattern(patterns, entry.path.to_string_lossy())
+                    || entry
+                        .path
+                        .file_name()
+                        .is_some_and(|name| matches_any_pattern(patterns, name.to_string_lossy()))
+            },
+            Some(_) => false,
+            None => true,
+        }
     }
 
     fn depth(&self) -> usize {
@@ -276,6 +430,58 @@ impl Entry {
         })
     }
 
+    /// The path and the stat `fields` of the entry.
+    fn to_json(&self, fields: &[LsField]) -> serde_json::Value {
+        #[cfg(unix)]
+        use std::os::unix::fs::{
+            MetadataExt,
+            PermissionsExt,
+        };
+
+        let mut value = serde_json::Map::new();
+        value.insert("path".to_string(), self.path.to_string_lossy().into());
+        for field in fields {
+            match field {
+                LsField::Type => {
+                    let file_type = match format_ftype(&self.metadata) {
+                        'l' => "symlink",
+                        'd' => "directory",
+                        _ => "file",
+                    };
+                    value.insert("type".to_string(), file_type.into());
+                },
+                LsField::Size => {
+                    value.insert("size".to_string(), self.metadata.len().into());
+                },
+                LsField::Modified => {
+                    let modified = time::OffsetDateTime::from_unix_timestamp(self.last_modified as i64)
+                        .ok()
+                        .and_then(|datetime| datetime.format(&time::format_description::well_known::Rfc3339).ok());
+                    value.insert("modified".to_string(), modified.into());
+                },
+                #[cfg(unix)]
+                LsField::Permissions => {
+                    let mode = format_mode(self.metadata.permissions().mode())
+                        .into_iter()
+                        .collect::<String>();
+                    value.insert("permissions".to_string(), mode.into());
+                },
+                #[cfg(unix)]
+                LsField::Owner => {
+                    value.insert("uid".to_string(), self.metadata.uid().into());
+                    value.insert("gid".to_string(), self.metadata.gid().into());
+                },
+                #[cfg(unix)]
+                LsField::Links => {
+                    value.insert("links".to_string(), self.metadata.nlink().into());
+                },
+                #[cfg(not(unix))]
+                LsField::Permissions | LsField::Owner | LsField::Links => (),
+            }
+        }
+        value.into()
+    }
+
     #[cfg(unix)]
     fn to_long_format(&self) -> String {
         use std::os::unix::fs::{
@@ -308,6 +514,106 @@ impl Entry {
     }
 }
 
+/// Rules of the `.gitignore` files that apply to a listing.
+///
+/// Only the common subset of the gitignore syntax is supported: comments, negations, directory
+/// only patterns and patterns relative to the directory of their `.gitignore`.
+#[derive(Debug, Default)]
+struct GitIgnore {
+    rules: Vec<GitIgnoreRule>,
+}
+
+impl GitIgnore {
+    /// Loads the `.gitignore` files of the ancestors of `dir` within its git repository, if it is
+    /// in one.
+    async fn for_ancestors(dir: &Path) -> Self {
+        let mut gitignore = Self::default();
+        let Some(repo_root) = dir.ancestors().find(|path| path.join(".git").exists()) else {
+            return gitignore;
+        };
+        let mut ancestors = dir
+            .ancestors()
+            .skip(1)
+            .take_while(|path| path.starts_with(repo_root))
+            .collect::<Vec<_>>();
+        ancestors.reverse();
+        for ancestor in ancestors {
+            gitignore.add_dir(ancestor).await;
+        }
+        gitignore
+    }
+
+    /// Adds the rules of the `.gitignore` file in `dir`, if there is one.
+    async fn add_dir(&mut self, dir: &Path) {
+        let Ok(content) = tokio::fs::read_to_string(dir.join(".gitignore")).await else {
+            return;
+        };
+        self.rules
+            .extend(content.lines().filter_map(|line| GitIgnoreRule::parse(dir, line)));
+    }
+
+    /// Whether `path` is ignored, the last matching rule taking precedence.
+    fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let mut ignored = false;
+        for rule in &self.rules {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            let Ok(relative) = path.strip_prefix(&rule.base) else {
+                continue;
+            };
+            if rule.matcher.is_match(relative) {
+                ignored = !rule.negated;
+            }
+        }
+        ignored
+    }
+}
+
+#[derive(Debug)]
+struct GitIgnoreRule {
+    /// Directory of the `.gitignore` the rule is from
+    base: PathBuf,
+    matcher: GlobMatcher,
+    negated: bool,
+    dir_only: bool,
+}
+
+impl GitIgnoreRule {
+    fn parse(base: &Path, line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, pattern) = match line.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, line),
+        };
+        let (dir_only, pattern) = match pattern.strip_suffix('/') {
+            Some(pattern) => (true, pattern),
+            None => (false, pattern),
+        };
+        // Patterns containing a separator are relative to the directory of the .gitignore, others
+        // match at any depth
+        let glob = match pattern.strip_prefix('/') {
+            Some(pattern) => pattern.to_string(),
+            None if pattern.contains('/') => pattern.to_string(),
+            None => format!("**/{}", pattern),
+        };
+        let matcher = GlobBuilder::new(&glob)
+            .literal_separator(true)
+            .build()
+            .ok()?
+            .compile_matcher();
+        Some(Self {
+            base: base.to_path_buf(),
+            matcher,
+            negated,
+            dir_only,
+        })
+    }
+}
+
 fn format_ftype(md: &Metadata) -> char {
     if md.is_symlink() {
         'l'
@@ -377,10 +683,11 @@ mod tests {
             path: test_base.join("").to_string_lossy().to_string(),
             depth: None,
             ignore: None,
+            ..Default::default()
         };
 
-        assert!(tool.validate(&test_base).await.is_ok());
-        let result = tool.execute(&test_base).await.unwrap();
+        assert!(tool.validate(&[], &test_base).await.is_ok());
+        let result = tool.execute(&[], PageLimits::default(), &test_base).await.unwrap();
         assert_eq!(result.items.len(), 1);
 
         if let ToolExecutionOutputItem::Text(content) = &result.items[0] {
@@ -402,9 +709,10 @@ mod tests {
             path: test_base.join("").to_string_lossy().to_string(),
             depth: Some(1),
             ignore: None,
+            ..Default::default()
         };
 
-        let result = tool.execute(&test_base).await.unwrap();
+        let result = tool.execute(&[], PageLimits::default(), &test_base).await.unwrap();
 
         if let ToolExecutionOutputItem::Text(content) = &result.items[0] {
             assert!(content.contains("root.txt"));
@@ -426,9 +734,10 @@ mod tests {
             path: test_base.join("").to_string_lossy().to_string(),
             depth: None,
             ignore: Some(vec!["*.log".to_string()]),
+            ..Default::default()
         };
 
-        let result = tool.execute(&test_base).await.unwrap();
+        let result = tool.execute(&[], PageLimits::default(), &test_base).await.unwrap();
 
         if let ToolExecutionOutputItem::Text(content) = &result.items[0] {
             assert!(content.contains("keep.txt"));
@@ -436,6 +745,154 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_ls_gitignore_and_include() {
+        let test_base = TestBase::new()
+            .await
+            .with_file((".gitignore", "*.log\n!keep.log\n/target/\n"))
+            .await
+            .with_file(("main.rs", "fn main() {}"))
+            .await
+            .with_file(("notes.md", "notes"))
+            .await
+            .with_file(("debug.log", "debug"))
+            .await
+            .with_file(("keep.log", "keep"))
+            .await
+            .with_file(("target/out.rs", "out"))
+            .await
+            .with_file(("src/lib.rs", "lib"))
+            .await
+            .with_file(("src/trace.log", "trace"))
+            .await;
+
+        let test_base = &test_base;
+        let list = |tool: Ls| async move {
+            match tool
+                .execute(&[], PageLimits::default(), test_base)
+                .await
+                .unwrap()
+                .items
+                .remove(0)
+            {
+                ToolExecutionOutputItem::Text(content) => content,
+                other => panic!("unexpected output: {:?}", other),
+            }
+        };
+
+        let content = list(Ls {
+            path: test_base.join("").to_string_lossy().to_string(),
+            depth: Some(1),
+            ..Default::default()
+        })
+        .await;
+        assert!(content.contains("main.rs") && content.contains("keep.log") && content.contains("lib.rs"));
+        assert!(
+            !content.contains("debug.log") && !content.contains("trace.log"),
+            "{content}"
+        );
+        assert!(!content.contains("target"), "{content}");
+
+        let content = list(Ls {
+            path: test_base.join("").to_string_lossy().to_string(),
+            depth: Some(1),
+            include: Some(vec!["*.rs".to_string()]),
+            respect_gitignore: Some(false),
+            ..Default::default()
+        })
+        .await;
+        assert!(content.contains("main.rs") && content.contains("lib.rs") && content.contains("out.rs"));
+        assert!(
+            !content.contains("notes.md") && !content.contains("debug.log"),
+            "{content}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ls_sort_and_fields() {
+        let test_base = TestBase::new()
+            .await
+            .with_file(("small.txt", "a"))
+            .await
+            .with_file(("large.txt", "a".repeat(100)))
+            .await
+            .with_file(("dir/nested.txt", "nested"))
+            .await;
+
+        let tool = Ls {
+            path: test_base.join("").to_string_lossy().to_string(),
+            sort_by: Some(LsSortBy::Size),
+            fields: Some(vec![LsField::Type, LsField::Size, LsField::Modified]),
+            ..Default::default()
+        };
+        let result = tool.execute(&[], PageLimits::default(), &test_base).await.unwrap();
+        let ToolExecutionOutputItem::Json(value) = &result.items[0] else {
+            panic!("expected a json output");
+        };
+        let entries = value["entries"].as_array().unwrap();
+        let files = entries
+            .iter()
+            .filter(|entry| entry["type"] == "file")
+            .map(|entry| entry["path"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert!(
+            files[0].ends_with("large.txt") && files[1].ends_with("small.txt"),
+            "{files:?}"
+        );
+        let large = entries.iter().find(|entry| entry["path"] == files[0]).unwrap();
+        assert_eq!(large["size"], 100);
+        assert!(large["modified"].is_string());
+        assert!(entries.iter().any(|entry| entry["type"] == "directory"));
+    }
+
+    #[tokio::test]
+    async fn test_ls_pagination() {
+        let mut test_base = TestBase::new().await;
+        for i in 0..5 {
+            test_base = test_base.with_file((format!("file{}.txt", i), "content")).await;
+        }
+        let limits = PageLimits {
+            max_items: 2,
+            max_bytes: 10_000,
+        };
+
+        let mut tool = Ls {
+            path: test_base.join("").to_string_lossy().to_string(),
+            sort_by: Some(LsSortBy::Name),
+            fields: Some(vec![]),
+            ..Default::default()
+        };
+        let mut pages = Vec::new();
+        loop {
+            let result = tool.execute(&[], limits, &test_base).await.unwrap();
+            let ToolExecutionOutputItem::Json(value) = &result.items[0] else {
+                panic!("expected a json output");
+            };
+            pages.push(
+                value["entries"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|entry| entry["path"].as_str().unwrap().to_string())
+                    .collect::<Vec<_>>(),
+            );
+            match value["next_cursor"].as_str() {
+                Some(cursor) => tool.cursor = Some(cursor.to_string()),
+                None => break,
+            }
+        }
+        assert_eq!(pages.iter().map(Vec::len).collect::<Vec<_>>(), vec![2, 2, 1]);
+        let paths = pages.concat();
+        assert!(
+            paths[0].ends_with("file0.txt") && paths[4].ends_with("file4.txt"),
+            "{paths:?}"
+        );
+
+        // Cursors only apply to the listing they were returned for
+        tool.sort_by = Some(LsSortBy::Size);
+        assert!(tool.execute(&[], limits, &test_base).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_ls_validate_nonexistent_directory() {
         let test_base = TestBase::new().await;
@@ -443,9 +900,10 @@ mod tests {
             path: "/nonexistent/directory".to_string(),
             depth: None,
             ignore: None,
+            ..Default::default()
         };
 
-        assert!(tool.validate(&test_base).await.is_err());
+        assert!(tool.validate(&[], &test_base).await.is_err());
     }
 
     #[tokio::test]
@@ -456,8 +914,9 @@ mod tests {
             path: test_base.join("file.txt").to_string_lossy().to_string(),
             depth: None,
             ignore: None,
+            ..Default::default()
         };
 
-        assert!(tool.validate(&test_base).await.is_err());
+        assert!(tool.validate(&[], &test_base).await.is_err());
     }
 }
@@ -7,11 +7,12 @@ use serde::{
     Serialize,
 };
 
-use super::{
-    ToolExecutionError,
-    ToolExecutionResult,
+use super::ToolExecutionResult;
+use super::path::{
+    io_error,
+    resolve_path,
 };
-use crate::agent::util::path::canonicalize_path;
+use crate::agent::util::providers::SystemProvider;
 
 pub const MKDIR_TOOL_DESCRIPTION: &str = r#"
 A tool for creating directories.
@@ -45,20 +46,9 @@ pub struct Mkdir {
 }
 
 impl Mkdir {
-    fn canonical_path(&self) -> Result<PathBuf, String> {
-        Ok(PathBuf::from(canonicalize_path(&self.path).map_err(|e| e.to_string())?))
-    }
-
-    pub async fn validate(&self) -> Result<(), String> {
-        if self.path.is_empty() {
-            return Err("Path must not be empty".to_string());
-        }
-
-        let path = self.canonical_path()?;
-        if path.exists() {
-            let Ok(file_md) = tokio::fs::symlink_metadata(&path).await else {
-                return Err(format!("A file at {} already exists", self.path));
-            };
+    pub async fn validate<P: SystemProvider>(&self, roots: &[PathBuf], provider: &P) -> Result<(), String> {
+        let path = resolve_path(&self.path, roots, provider).map_err(|e| e.to_string())?;
+        if let Ok(file_md) = tokio::fs::metadata(&path).await {
             if file_md.is_dir() {
                 return Err(format!("A directory at {} already exists", self.path));
             } else {
@@ -69,11 +59,15 @@ impl Mkdir {
         Ok(())
     }
 
-    pub async fn execute(&self) -> ToolExecutionResult {
-        let path = self.canonical_path()?;
-        tokio::fs::create_dir_all(&path)
-            .await
-            .map_err(|e| ToolExecutionError::io(format!("failed to create directory {}", path.to_string_lossy()), e))?;
+    pub async fn execute<P: SystemProvider>(&self, roots: &[PathBuf], provider: &P) -> ToolExecutionResult {
+        let path = resolve_path(&self.path, roots, provider)?;
+        tokio::fs::create_dir_all(&path).await.map_err(|e| {
+            io_error(
+                format!("failed to create directory {}", path.to_string_lossy()),
+                &path,
+                e,
+            )
+        })?;
         Ok(Default::default())
     }
 }
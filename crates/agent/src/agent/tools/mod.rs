@@ -1,3 +1,4 @@
+pub mod checkpoint;
 pub mod execute_cmd;
 pub mod fs_read;
 pub mod fs_write;
@@ -7,10 +8,14 @@ pub mod introspect;
 pub mod ls;
 pub mod mcp;
 pub mod mkdir;
+pub mod pagination;
+pub mod path;
 pub mod rm;
+pub mod spawn_subagent;
 
 use std::borrow::Cow;
 use std::sync::Arc;
+use std::time::Duration;
 
 use execute_cmd::ExecuteCmd;
 use fs_read::FsRead;
@@ -30,6 +35,7 @@ use serde::{
     Deserialize,
     Serialize,
 };
+use spawn_subagent::SpawnSubagent;
 use strum::IntoEnumIterator;
 
 use super::agent_config::parse::CanonicalToolName;
@@ -100,6 +106,7 @@ pub enum BuiltInToolName {
     ExecuteCmd,
     ImageRead,
     Ls,
+    SpawnSubagent,
 }
 
 trait BuiltInToolTrait {
@@ -237,8 +244,7 @@ pub enum BuiltInTool {
     ImageRead(ImageRead),
     ExecuteCmd(ExecuteCmd),
     Introspect(Introspect),
-    /// TODO
-    SpawnSubagent,
+    SpawnSubagent(SpawnSubagent),
 }
 
 impl BuiltInTool {
@@ -259,6 +265,9 @@ impl BuiltInTool {
             BuiltInToolName::Ls => serde_json::from_value::<Ls>(args)
                 .map(Self::Ls)
                 .map_err(ToolParseErrorKind::schema_failure),
+            BuiltInToolName::SpawnSubagent => serde_json::from_value::<SpawnSubagent>(args)
+                .map(Self::SpawnSubagent)
+                .map_err(ToolParseErrorKind::schema_failure),
         }
     }
 
@@ -269,6 +278,7 @@ impl BuiltInTool {
             BuiltInToolName::ExecuteCmd => generate_tool_spec_from_trait::<ExecuteCmd>(),
             BuiltInToolName::ImageRead => generate_tool_spec_from_trait::<ImageRead>(),
             BuiltInToolName::Ls => generate_tool_spec_from_trait::<Ls>(),
+            BuiltInToolName::SpawnSubagent => generate_tool_spec_from_trait::<SpawnSubagent>(),
         }
     }
 
@@ -282,7 +292,7 @@ impl BuiltInTool {
             BuiltInTool::ImageRead(_) => BuiltInToolName::ImageRead,
             BuiltInTool::ExecuteCmd(_) => BuiltInToolName::ExecuteCmd,
             BuiltInTool::Introspect(_) => panic!("unimplemented"),
-            BuiltInTool::SpawnSubagent => panic!("unimplemented"),
+            BuiltInTool::SpawnSubagent(_) => BuiltInToolName::SpawnSubagent,
         }
     }
 
@@ -296,7 +306,7 @@ impl BuiltInTool {
             BuiltInTool::ImageRead(_) => BuiltInToolName::ImageRead.into(),
             BuiltInTool::ExecuteCmd(_) => BuiltInToolName::ExecuteCmd.into(),
             BuiltInTool::Introspect(_) => panic!("unimplemented"),
-            BuiltInTool::SpawnSubagent => panic!("unimplemented"),
+            BuiltInTool::SpawnSubagent(_) => BuiltInToolName::SpawnSubagent.into(),
         }
     }
 }
@@ -342,6 +352,14 @@ pub enum ToolExecutionOutputItem {
     Image(ImageBlock),
 }
 
+/// A chunk of output streamed by a tool while it executes, ahead of its [ToolExecutionOutput].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ToolOutputChunk {
+    Stdout(String),
+    Stderr(String),
+}
+
 impl From<String> for ToolExecutionOutputItem {
     fn from(value: String) -> Self {
         Self::Text(value)
@@ -362,6 +380,10 @@ pub enum ToolExecutionError {
         source: Option<Arc<std::io::Error>>,
     },
     Custom(String),
+    /// The tool didn't complete within its execution timeout, and was cancelled
+    TimedOut {
+        timeout: Duration,
+    },
 }
 
 impl From<String> for ToolExecutionError {
@@ -381,6 +403,11 @@ impl std::fmt::Display for ToolExecutionError {
                 Ok(())
             },
             ToolExecutionError::Custom(msg) => write!(f, "{}", msg),
+            ToolExecutionError::TimedOut { timeout } => write!(
+                f,
+                "The tool did not complete within {} seconds and was cancelled",
+                timeout.as_secs()
+            ),
         }
     }
 }
@@ -396,7 +423,7 @@ impl std::error::Error for ToolExecutionError {
                     None
                 }
             },
-            ToolExecutionError::Custom(_) => None,
+            ToolExecutionError::Custom(_) | ToolExecutionError::TimedOut { .. } => None,
         }
     }
 
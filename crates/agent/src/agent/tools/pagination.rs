@@ -0,0 +1,194 @@
+//! Pagination of the results of tools that can return huge result sets, like ls.
+//!
+//! Instead of truncating results arbitrarily, these tools return them a page at a time. A page
+//! with results left carries a cursor, which the model passes back as the `cursor` argument of a
+//! call with the same arguments to get the next page. Cursors are tied to the arguments of the
+//! call they were returned for, so that they can't be used to page through a different listing.
+//!
+//! The size of pages is bounded by the [PageLimits] of the tool execution, in results and in
+//! bytes, so that pages fit within the output size limit enforced by the task executor.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{
+    Hash,
+    Hasher,
+};
+
+use serde::Serialize;
+
+use crate::agent::task_executor::ToolExecutionSettings;
+
+/// Bytes of a page kept for the notes returned with the results, e.g. the next cursor.
+const NOTES_RESERVED_SIZE: usize = 1024;
+
+/// Size limits of a page of results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageLimits {
+    /// Most results in a page
+    pub max_items: usize,
+    /// Most bytes of the results in a page
+    pub max_bytes: usize,
+}
+
+impl PageLimits {
+    /// The limits of pages returned by a tool whose output is limited to `max_output_size` bytes.
+    pub fn new(max_items: usize, max_output_size: usize) -> Self {
+        Self {
+            max_items: max_items.max(1),
+            max_bytes: max_output_size.saturating_sub(NOTES_RESERVED_SIZE),
+        }
+    }
+}
+
+impl Default for PageLimits {
+    fn default() -> Self {
+        ToolExecutionSettings::default().limits(None).page_limits()
+    }
+}
+
+/// Position of the next page of results of a tool call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    /// See [fingerprint]
+    fingerprint: u64,
+    /// Number of results returned in the previous pages
+    offset: usize,
+}
+
+impl Cursor {
+    /// Parses a cursor returned by a previous page, checking that it was returned for a call
+    /// with the same `fingerprint`.
+    pub fn parse(cursor: &str, fingerprint: u64) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "Invalid cursor '{}', only use cursors returned by a previous call with the same arguments",
+                cursor
+            )
+        };
+        let (cursor_fingerprint, offset) = cursor.split_once('.').ok_or_else(invalid)?;
+        let cursor_fingerprint = u64::from_str_radix(cursor_fingerprint, 16).map_err(|_| invalid())?;
+        let offset = offset.parse().map_err(|_| invalid())?;
+        if cursor_fingerprint != fingerprint {
+            return Err(invalid());
+        }
+        Ok(Self { fingerprint, offset })
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl std::fmt::Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}.{}", self.fingerprint, self.offset)
+    }
+}
+
+/// A page of results.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Cursor of the next page, [None] if there are no results left
+    pub next_cursor: Option<Cursor>,
+}
+
+impl<T> Page<T> {
+    /// Takes the page of `results` starting at `cursor`, or at the first result without a
+    /// cursor. `size` returns the size of a result in bytes once formatted.
+    ///
+    /// A page has at least one result if any are left, even if it is larger than the byte limit.
+    pub fn take<I, F>(results: I, fingerprint: u64, cursor: Option<Cursor>, limits: PageLimits, size: F) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        F: Fn(&T) -> usize,
+    {
+        let offset = cursor.map_or(0, |cursor| cursor.offset);
+        let mut results = results.into_iter().skip(offset).peekable();
+        let mut items = Vec::new();
+        let mut bytes = 0;
+        while let Some(result) = results.peek() {
+            let len = size(result);
+            if items.len() >= limits.max_items || (!items.is_empty() && bytes + len > limits.max_bytes) {
+                break;
+            }
+            bytes += len;
+            items.extend(results.next());
+        }
+
+        let next_cursor = results.peek().is_some().then(|| Cursor {
+            fingerprint,
+            offset: offset + items.len(),
+        });
+        Self { items, next_cursor }
+    }
+
+    /// The note returned with the page, telling how to get the next one.
+    pub fn next_page_note(&self) -> Option<String> {
+        self.next_cursor.map(|cursor| {
+            format!(
+                "More results are available: call the tool again with the same arguments and \
+                 \"cursor\": \"{}\" to get the next page",
+                cursor
+            )
+        })
+    }
+}
+
+/// A fingerprint of the arguments of a tool call, which should not include its cursor.
+pub fn fingerprint<T: Serialize>(args: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(args).unwrap_or_default().hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_pages() {
+        let results = (0..10).map(|i| format!("result {}", i)).collect::<Vec<_>>();
+        let limits = PageLimits {
+            max_items: 4,
+            max_bytes: 1000,
+        };
+
+        let mut pages = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = Page::take(results.clone(), 7, cursor, limits, String::len);
+            cursor = page.next_cursor.map(|c| Cursor::parse(&c.to_string(), 7).unwrap());
+            pages.push(page.items);
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(pages.iter().map(Vec::len).collect::<Vec<_>>(), vec![4, 4, 2]);
+        assert_eq!(pages.concat(), results);
+
+        // Pages are limited in bytes, but always have a result
+        let limits = PageLimits {
+            max_items: 4,
+            max_bytes: 20,
+        };
+        let page = Page::take(results.clone(), 7, None, limits, String::len);
+        assert_eq!(page.items.len(), 2);
+        let page = Page::take(vec!["a".repeat(100)], 7, None, limits, String::len);
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn test_parse_cursor() {
+        let cursor = Cursor {
+            fingerprint: 42,
+            offset: 100,
+        };
+        assert_eq!(Cursor::parse(&cursor.to_string(), 42), Ok(cursor));
+        assert!(Cursor::parse(&cursor.to_string(), 43).is_err());
+        assert!(Cursor::parse("100", 42).is_err());
+        assert!(Cursor::parse("zz.100", 42).is_err());
+        assert!(Cursor::parse("000000000000002a.-1", 42).is_err());
+    }
+}
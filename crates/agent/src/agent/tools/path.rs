@@ -0,0 +1,442 @@
+//! Resolution of the paths given to the file tools.
+//!
+//! Paths are expanded and made absolute, then resolved component by component following
+//! symlinks, including the symlinks of the ancestors of paths that don't exist yet. The resolved
+//! path is where the tool actually reads or writes, so it is what gets checked against the roots
+//! the file tools are restricted to with the `file_roots` tool setting.
+
+use std::io;
+use std::path::{
+    Component,
+    Path,
+    PathBuf,
+};
+
+use tracing::warn;
+
+use super::ToolExecutionError;
+use crate::agent::util::path::expand_path;
+use crate::agent::util::providers::SystemProvider;
+
+/// Most symlinks followed while resolving a path, the same as `MAXSYMLINKS` on Linux.
+const MAX_SYMLINKS: usize = 40;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PathError {
+    #[error("Path must not be empty")]
+    Empty,
+    #[error("Path must not contain null bytes")]
+    NullByte,
+    #[error("Failed to expand the path '{path}': {message}")]
+    Expand { path: String, message: String },
+    #[error("'{}' does not exist", .0.to_string_lossy())]
+    NotFound(PathBuf),
+    #[error("Permission denied for '{}'", .0.to_string_lossy())]
+    PermissionDenied(PathBuf),
+    #[error("'{}' has too many levels of symbolic links, it is likely a symlink loop", .0.to_string_lossy())]
+    SymlinkLoop(PathBuf),
+    #[error("'{}' is not a file", .0.to_string_lossy())]
+    NotAFile(PathBuf),
+    #[error("'{}' is not a directory", .0.to_string_lossy())]
+    NotADirectory(PathBuf),
+    #[error("'{}' is outside of the directories the file tools can access: {allowed}", .path.to_string_lossy())]
+    OutsideRoots { path: PathBuf, allowed: String },
+    #[error("None of the directories the file tools are restricted to could be resolved: {configured}")]
+    NoRoots { configured: String },
+    #[error("Failed to access '{}': {message}", .path.to_string_lossy())]
+    Io { path: PathBuf, message: String },
+}
+
+impl PathError {
+    /// Maps an error of an operation on `path` to a friendlier error for the common causes.
+    pub fn from_io(path: impl Into<PathBuf>, err: &io::Error) -> Self {
+        let path = path.into();
+        match err.kind() {
+            io::ErrorKind::NotFound => Self::NotFound(path),
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied(path),
+            _ if err.raw_os_error() == Some(libc::ELOOP) => Self::SymlinkLoop(path),
+            _ => Self::Io {
+                path,
+                message: err.to_string(),
+            },
+        }
+    }
+}
+
+impl From<PathError> for ToolExecutionError {
+    fn from(value: PathError) -> Self {
+        Self::Custom(value.to_string())
+    }
+}
+
+/// Converts the error of an operation on `path` to a tool error, with a friendly message for the
+/// common causes and `context` otherwise.
+pub fn io_error(context: impl Into<String>, path: &Path, err: io::Error) -> ToolExecutionError {
+    match PathError::from_io(path, &err) {
+        PathError::Io { .. } => ToolExecutionError::io(context, err),
+        err => err.into(),
+    }
+}
+
+/// Resolves `path` to an absolute path without `.` and `..` components nor symlinks, and checks
+/// that it is within one of `roots`. Any path is allowed if `roots` is empty.
+///
+/// The path itself does not need to exist, e.g. for files about to be created, in which case its
+/// missing components are appended to its resolved ancestor.
+pub fn resolve_path<P: SystemProvider>(path: &str, roots: &[PathBuf], provider: &P) -> Result<PathBuf, PathError> {
+    if path.is_empty() {
+        return Err(PathError::Empty);
+    }
+    if path.contains('\0') {
+        return Err(PathError::NullByte);
+    }
+
+    let expanded = expand_path(path, provider).map_err(|err| PathError::Expand {
+        path: path.to_string(),
+        message: err.to_string(),
+    })?;
+    let expanded = Path::new(expanded.as_ref());
+    let absolute = if expanded.is_absolute() {
+        expanded.to_path_buf()
+    } else {
+        let cwd = provider.cwd().map_err(|err| PathError::Io {
+            path: expanded.to_path_buf(),
+            message: format!("could not get the current directory: {}", err),
+        })?;
+        cwd.join(expanded)
+    };
+
+    let resolved = resolve_symlinks(&absolute)?;
+    if roots.is_empty() || roots.iter().any(|root| resolved.starts_with(root)) {
+        Ok(resolved)
+    } else {
+        Err(PathError::OutsideRoots {
+            path: resolved,
+            allowed: roots
+                .iter()
+                .map(|root| root.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(", "),
+        })
+    }
+}
+
+/// Resolves `path` like [resolve_path], checking that it is an existing file.
+pub async fn resolve_file<P: SystemProvider>(
+    path: &str,
+    roots: &[PathBuf],
+    provider: &P,
+) -> Result<PathBuf, PathError> {
+    let path = resolve_path(path, roots, provider)?;
+    let metadata = tokio::fs::metadata(&path)
+        .await
+        .map_err(|err| PathError::from_io(&path, &err))?;
+    match metadata.is_file() {
+        true => Ok(path),
+        false => Err(PathError::NotAFile(path)),
+    }
+}
+
+/// Resolves `path` like [resolve_path], checking that it is an existing directory.
+pub async fn resolve_dir<P: SystemProvider>(path: &str, roots: &[PathBuf], provider: &P) -> Result<PathBuf, PathError> {
+    let path = resolve_path(path, roots, provider)?;
+    let metadata = tokio::fs::metadata(&path)
+        .await
+        .map_err(|err| PathError::from_io(&path, &err))?;
+    match metadata.is_dir() {
+        true => Ok(path),
+        false => Err(PathError::NotADirectory(path)),
+    }
+}
+
+/// Resolves the `file_roots` tool setting, dropping the roots that can't be resolved.
+///
+/// Fails when roots are configured but none of them resolves, rather than returning no roots,
+/// which would leave the file tools unrestricted.
+pub fn resolve_roots<T, U, P>(roots: T, provider: &P) -> Result<Vec<PathBuf>, PathError>
+where
+    T: IntoIterator<Item = U>,
+    U: AsRef<str>,
+    P: SystemProvider,
+{
+    let mut configured = Vec::new();
+    let mut resolved = Vec::new();
+    for root in roots {
+        let root = root.as_ref();
+        match resolve_path(root, &[], provider) {
+            Ok(path) => resolved.push(path),
+            Err(err) => warn!(root, ?err, "failed to resolve the file root"),
+        }
+        configured.push(root.to_string());
+    }
+    if resolved.is_empty() && !configured.is_empty() {
+        return Err(PathError::NoRoots {
+            configured: configured.join(", "),
+        });
+    }
+    Ok(resolved)
+}
+
+/// Resolves every component of the absolute path `path`, following symlinks until the first
+/// component that doesn't exist. The components after it are resolved lexically.
+fn resolve_symlinks(path: &Path) -> Result<PathBuf, PathError> {
+    // Components left to resolve, last one first so that symlink targets can be pushed
+    let mut pending = path
+        .components()
+        .rev()
+        .map(|c| PathBuf::from(c.as_os_str()))
+        .collect::<Vec<_>>();
+    let mut resolved = PathBuf::new();
+    let mut followed = 0;
+    // Components of `resolved` that don't exist
+    let mut missing = 0;
+
+    while let Some(part) = pending.pop() {
+        match part.components().next() {
+            // Pushing an absolute path replaces `resolved`, as needed for absolute symlink targets
+            Some(Component::Prefix(_) | Component::RootDir) => {
+                resolved.push(&part);
+                missing = 0;
+            },
+            Some(Component::CurDir) | None => (),
+            Some(Component::ParentDir) => {
+                resolved.pop();
+                missing = missing.saturating_sub(1);
+            },
+            Some(Component::Normal(name)) => {
+                let candidate = resolved.join(name);
+                if missing > 0 {
+                    resolved = candidate;
+                    missing += 1;
+                    continue;
+                }
+                match std::fs::symlink_metadata(&candidate) {
+                    Ok(metadata) if metadata.file_type().is_symlink() => {
+                        followed += 1;
+                        if followed > MAX_SYMLINKS {
+                            return Err(PathError::SymlinkLoop(path.to_path_buf()));
+                        }
+                        let target =
+                            std::fs::read_link(&candidate).map_err(|err| PathError::from_io(&candidate, &err))?;
+                        // Relative targets are resolved from the directory of the symlink, which is
+                        // `resolved`
+                        pending.extend(target.components().rev().map(|c| PathBuf::from(c.as_os_str())));
+                    },
+                    Ok(_) => resolved = candidate,
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                        resolved = candidate;
+                        missing = 1;
+                    },
+                    Err(err) => return Err(PathError::from_io(candidate, &err)),
+                }
+            },
+        }
+    }
+
+    Ok(resolved)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::symlink;
+
+    use super::*;
+    use crate::agent::util::test::TestBase;
+
+    /// A workspace allowed to the file tools, next to a directory that isn't, with symlinks in and
+    /// out of the workspace.
+    async fn hostile_base() -> (TestBase, Vec<PathBuf>) {
+        let test_base = TestBase::new()
+            .await
+            .with_file(("workspace/dir/file.txt", "inside"))
+            .await
+            .with_file(("outside/secret.txt", "secret"))
+            .await;
+        let ws = test_base.join("workspace");
+        symlink(test_base.join("outside"), ws.join("link_out")).unwrap();
+        symlink("../outside/secret.txt", ws.join("rel_link_out")).unwrap();
+        symlink("dir/file.txt", ws.join("link_in")).unwrap();
+        symlink("loop_b", ws.join("loop_a")).unwrap();
+        symlink("loop_a", ws.join("loop_b")).unwrap();
+        symlink("self", ws.join("self")).unwrap();
+        symlink("..", ws.join("dir/up")).unwrap();
+        let roots = resolve_roots([ws.to_string_lossy()], &test_base).unwrap();
+        assert_eq!(roots.len(), 1);
+        (test_base, roots)
+    }
+
+    /// Checks that a resolved path is within the roots, and that its existing part contains no
+    /// symlinks.
+    fn assert_contained(input: &str, resolved: &Path, roots: &[PathBuf]) {
+        assert!(
+            roots.iter().any(|root| resolved.starts_with(root)),
+            "'{input}' resolved to {resolved:?}, outside of {roots:?}"
+        );
+        let existing = resolved.ancestors().find(|p| p.exists()).unwrap();
+        assert_eq!(
+            existing.canonicalize().unwrap(),
+            existing,
+            "'{input}' resolved to {resolved:?} through a symlink"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_path() {
+        let (test_base, roots) = hostile_base().await;
+        let ws = &roots[0];
+
+        let resolve = |path: &str| resolve_path(path, &roots, &test_base);
+        assert_eq!(resolve("workspace/dir/file.txt").unwrap(), ws.join("dir/file.txt"));
+        assert_eq!(
+            resolve("workspace/./dir/../dir/file.txt").unwrap(),
+            ws.join("dir/file.txt")
+        );
+        assert_eq!(resolve("workspace/link_in").unwrap(), ws.join("dir/file.txt"));
+        assert_eq!(resolve("workspace/new/file.txt").unwrap(), ws.join("new/file.txt"));
+        assert_eq!(resolve("workspace/dir/up/dir").unwrap(), ws.join("dir"));
+
+        assert_eq!(resolve(""), Err(PathError::Empty));
+        assert_eq!(resolve("workspace/\0"), Err(PathError::NullByte));
+        assert!(matches!(resolve("workspace/loop_a"), Err(PathError::SymlinkLoop(_))));
+        assert!(matches!(resolve("workspace/self/file"), Err(PathError::SymlinkLoop(_))));
+        assert!(matches!(
+            resolve("outside/secret.txt"),
+            Err(PathError::OutsideRoots { .. })
+        ));
+        assert!(matches!(
+            resolve("workspace/link_out/secret.txt"),
+            Err(PathError::OutsideRoots { .. })
+        ));
+        assert!(matches!(
+            resolve("workspace/rel_link_out"),
+            Err(PathError::OutsideRoots { .. })
+        ));
+        assert!(matches!(
+            resolve("workspace/missing/../link_out/new.txt"),
+            Err(PathError::OutsideRoots { .. })
+        ));
+
+        // Any path is allowed without roots
+        assert_eq!(
+            resolve_path("workspace/link_out/secret.txt", &[], &test_base).unwrap(),
+            test_base.join("outside/secret.txt").canonicalize().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_file_and_dir() {
+        let (test_base, roots) = hostile_base().await;
+
+        assert!(resolve_file("workspace/dir/file.txt", &roots, &test_base).await.is_ok());
+        assert!(matches!(
+            resolve_file("workspace/dir", &roots, &test_base).await,
+            Err(PathError::NotAFile(_))
+        ));
+        assert!(matches!(
+            resolve_file("workspace/missing.txt", &roots, &test_base).await,
+            Err(PathError::NotFound(_))
+        ));
+        assert!(resolve_dir("workspace/dir", &roots, &test_base).await.is_ok());
+        assert!(matches!(
+            resolve_dir("workspace/link_in", &roots, &test_base).await,
+            Err(PathError::NotADirectory(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_hostile_paths() {
+        let (test_base, roots) = hostile_base().await;
+        let ws = roots[0].to_string_lossy().to_string();
+
+        let mut inputs = [
+            "../../../../../../etc/passwd",
+            "/etc/passwd",
+            "/etc/../etc/./passwd",
+            "workspace/../outside/secret.txt",
+            "workspace/dir/../../outside/secret.txt",
+            "workspace//dir///..//..//outside",
+            "workspace/dir/up/../outside",
+            "workspace/link_out",
+            "workspace/link_out/../workspace/dir",
+            "workspace/rel_link_out/..",
+            "workspace/missing/../../outside/secret.txt",
+            "workspace/missing/../missing/../link_out/x",
+            "workspace/loop_a/../dir",
+            "workspace/%2e%2e/%2e%2e/outside",
+            "workspace/..\\..\\outside",
+            "workspace/dir/file.txt/../../../outside",
+            "workspace/ünïcödé/../../outside",
+            "~/../../../../etc",
+            "$HOME/..",
+            "${HOME}/../../",
+            "$UNDEFINED_VARIABLE/etc",
+            "workspace/\0/../../outside",
+            "   ",
+            ".",
+            "..",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+        inputs.push(format!("workspace/{}", "../".repeat(100)));
+        inputs.push(format!("{ws}/{}", "a/".repeat(200)));
+        inputs.push(format!("{ws}/../outside/secret.txt"));
+
+        // Random combinations of hostile components, from a fixed seed to be reproducible
+        let parts = [
+            "..",
+            ".",
+            "",
+            "dir",
+            "up",
+            "missing",
+            "link_out",
+            "rel_link_out",
+            "link_in",
+            "loop_a",
+            "self",
+            "~",
+        ];
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        for _ in 0..1000 {
+            let mut path = vec!["workspace".to_string()];
+            for _ in 0..1 + seed % 8 {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                path.push(parts[(seed >> 33) as usize % parts.len()].to_string());
+            }
+            inputs.push(path.join("/"));
+        }
+
+        for input in &inputs {
+            match resolve_path(input, &roots, &test_base) {
+                Ok(resolved) => assert_contained(input, &resolved, &roots),
+                Err(PathError::Io { path, message }) => {
+                    // Only expected when resolving a path through a file
+                    assert!(
+                        path.ancestors().skip(1).any(|p| p.is_file()),
+                        "'{input}' failed to resolve: {message}"
+                    );
+                },
+                Err(_) => (),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_roots_fails_closed() {
+        let test_base = TestBase::new().await;
+        let ws = test_base.join("workspace");
+
+        assert_eq!(
+            resolve_roots(Vec::<String>::new(), &test_base).unwrap(),
+            Vec::<PathBuf>::new()
+        );
+        // Roots that don't resolve are dropped
+        assert_eq!(resolve_roots(["", &ws.to_string_lossy()], &test_base).unwrap().len(), 1);
+        // Roots that are configured but don't resolve must not leave the tools unrestricted
+        assert!(matches!(
+            resolve_roots(["", "bad\0root"], &test_base),
+            Err(PathError::NoRoots { .. })
+        ));
+    }
+}
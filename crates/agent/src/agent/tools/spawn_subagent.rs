@@ -0,0 +1,130 @@
+use futures::future::join_all;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::{
+    BuiltInToolName,
+    BuiltInToolTrait,
+    ToolExecutionOutput,
+    ToolExecutionOutputItem,
+    ToolExecutionResult,
+};
+use crate::agent::runtime::{
+    AgentRuntime,
+    SubagentParent,
+};
+
+const SPAWN_SUBAGENT_TOOL_DESCRIPTION: &str = r#"
+A tool for delegating independent tasks to subagents that work on them in parallel.
+
+WHEN TO USE THIS TOOL:
+- Use when the work splits into independent tasks, for example investigating several parts of a codebase
+- Do not use for tasks that depend on each other's results
+
+HOW TO USE:
+- Provide one task per subagent
+- Subagents don't see this conversation, so each prompt must include all the context required for its task
+- Subagents have the same tools, except that they can't spawn subagents nor use tools that require approval
+- The final response of each subagent is returned, in the order of the tasks
+
+LIMITATIONS:
+- At most 10 subagents can be spawned at once
+"#;
+
+const SPAWN_SUBAGENT_SCHEMA: &str = r#"
+{
+    "type": "object",
+    "properties": {
+        "tasks": {
+            "type": "array",
+            "description": "Tasks to run, one per subagent",
+            "items": {
+                "type": "object",
+                "properties": {
+                    "prompt": {
+                        "type": "string",
+                        "description": "Prompt sent to the subagent"
+                    }
+                },
+                "required": [
+                    "prompt"
+                ]
+            }
+        }
+    },
+    "required": [
+        "tasks"
+    ]
+}
+"#;
+
+/// Most subagents spawned by a single tool use.
+const MAX_SUBAGENTS: usize = 10;
+
+impl BuiltInToolTrait for SpawnSubagent {
+    fn name() -> BuiltInToolName {
+        BuiltInToolName::SpawnSubagent
+    }
+
+    fn description() -> std::borrow::Cow<'static, str> {
+        SPAWN_SUBAGENT_TOOL_DESCRIPTION.into()
+    }
+
+    fn input_schema() -> std::borrow::Cow<'static, str> {
+        SPAWN_SUBAGENT_SCHEMA.into()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpawnSubagent {
+    pub tasks: Vec<SubagentTask>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubagentTask {
+    pub prompt: String,
+}
+
+impl SpawnSubagent {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.tasks.is_empty() {
+            return Err("At least one task must be provided".to_string());
+        }
+        if self.tasks.len() > MAX_SUBAGENTS {
+            return Err(format!(
+                "At most {} subagents can be spawned at once, found {} tasks",
+                MAX_SUBAGENTS,
+                self.tasks.len()
+            ));
+        }
+        if self.tasks.iter().any(|task| task.prompt.trim().is_empty()) {
+            return Err("Task prompts must not be empty".to_string());
+        }
+        Ok(())
+    }
+
+    /// Runs each task in its own subagent concurrently, returning the response or error of each,
+    /// in the order of the tasks.
+    pub async fn execute(&self, runtime: &AgentRuntime, parent: &SubagentParent) -> ToolExecutionResult {
+        let results = join_all(
+            self.tasks
+                .iter()
+                .map(|task| runtime.run_subagent(parent, task.prompt.clone())),
+        )
+        .await;
+
+        let results = results
+            .into_iter()
+            .enumerate()
+            .map(|(i, result)| match result {
+                Ok(response) => serde_json::json!({ "task": i + 1, "response": response }),
+                Err(err) => serde_json::json!({ "task": i + 1, "error": err.to_string() }),
+            })
+            .collect::<Vec<_>>();
+        Ok(ToolExecutionOutput::new(vec![ToolExecutionOutputItem::Json(
+            serde_json::Value::Array(results),
+        )]))
+    }
+}
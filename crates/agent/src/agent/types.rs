@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::time::Duration;
 
 use chrono::{
@@ -14,13 +15,22 @@ use uuid::Uuid;
 
 use super::agent_loop::protocol::{
     SendRequestArgs,
+    StreamTimeouts,
     UserTurnMetadata,
 };
-use super::agent_loop::types::Message;
+use super::agent_loop::types::{
+    Message,
+    TokenUsage,
+};
 use super::consts::DEFAULT_AGENT_NAME;
 use crate::agent::ExecutionState;
-use crate::agent::agent_config::definitions::AgentConfig;
+use crate::agent::agent_config::definitions::{
+    AgentConfig,
+    InferenceParams,
+};
+use crate::agent::task_executor::ToolExecutionSettings;
 use crate::agent::tools::ToolState;
+use crate::agent::tools::checkpoint::Checkpoints;
 
 /// A point-in-time snapshot of an agent's state.
 ///
@@ -49,6 +59,9 @@ pub struct AgentSnapshot {
     pub model_state: Option<serde_json::Value>,
     /// Persistent state required by tools during the conversation
     pub tool_state: ToolState,
+    /// Files modified by tools in recent user turns, as they were before each turn
+    #[serde(default)]
+    pub checkpoints: Checkpoints,
     /// Agent settings
     pub settings: AgentSettings,
 }
@@ -63,6 +76,7 @@ impl AgentSnapshot {
             execution_state: Default::default(),
             model_state: Default::default(),
             tool_state: Default::default(),
+            checkpoints: Default::default(),
             settings: Default::default(),
         }
     }
@@ -78,6 +92,7 @@ impl AgentSnapshot {
             execution_state: Default::default(),
             model_state: Default::default(),
             tool_state: Default::default(),
+            checkpoints: Default::default(),
             settings: Default::default(),
         }
     }
@@ -129,16 +144,48 @@ impl AsRef<str> for ConversationSummary {
 pub struct AgentSettings {
     /// Timeout waiting for MCP servers to initialize during agent initialization.
     pub mcp_init_timeout: Duration,
+    /// Timeouts of the response streams of models whose provider has no entry in
+    /// [Self::provider_stream_timeouts].
+    #[serde(default)]
+    pub stream_timeouts: StreamTimeouts,
+    /// Timeouts of the response streams by model provider name, for example longer ones for the
+    /// providers of reasoning models.
+    #[serde(default)]
+    pub provider_stream_timeouts: HashMap<String, StreamTimeouts>,
+    /// Timeout, output size and concurrency limits of tool executions. The agent config can
+    /// override the limits of a tool in its tool settings.
+    #[serde(default)]
+    pub tool_execution: ToolExecutionSettings,
+    /// Whether MCP servers may be launched. Clients set this to false when an administrator
+    /// disabled MCP for the user's profile, in which case no configured server is launched.
+    #[serde(default = "default_mcp_enabled")]
+    pub mcp_enabled: bool,
+}
+
+fn default_mcp_enabled() -> bool {
+    true
 }
 
 impl AgentSettings {
     const DEFAULT_MCP_INIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// The stream timeouts of models created by `provider`.
+    pub fn stream_timeouts(&self, provider: Option<&str>) -> StreamTimeouts {
+        provider
+            .and_then(|provider| self.provider_stream_timeouts.get(provider))
+            .copied()
+            .unwrap_or(self.stream_timeouts)
+    }
 }
 
 impl Default for AgentSettings {
     fn default() -> Self {
         Self {
             mcp_init_timeout: Self::DEFAULT_MCP_INIT_TIMEOUT,
+            stream_timeouts: StreamTimeouts::default(),
+            provider_stream_timeouts: HashMap::new(),
+            tool_execution: ToolExecutionSettings::default(),
+            mcp_enabled: true,
         }
     }
 }
@@ -148,6 +195,10 @@ impl Default for AgentSettings {
 pub struct ConversationState {
     pub id: Uuid,
     pub messages: Vec<Message>,
+    /// Inference parameters set for this conversation, overriding the defaults of the model
+    /// config
+    #[serde(default)]
+    pub inference_params: InferenceParams,
 }
 
 impl ConversationState {
@@ -156,6 +207,7 @@ impl ConversationState {
         Self {
             id: Uuid::new_v4(),
             messages: Vec::new(),
+            inference_params: InferenceParams::default(),
         }
     }
 }
@@ -178,6 +230,13 @@ pub struct ConversationMetadata {
     pub last_request: Option<SendRequestArgs>,
 }
 
+impl ConversationMetadata {
+    /// Tokens used by the ended user turns of the conversation.
+    pub fn usage(&self) -> TokenUsage {
+        self.user_turn_metadatas.iter().map(|md| md.usage).sum()
+    }
+}
+
 /// Unique identifier of an agent instance within a session.
 ///
 /// Formatted as: `parent_id/name#rand`
@@ -209,10 +268,23 @@ impl AgentId {
         }
     }
 
+    /// Creates the id of a subagent of `parent`, with the same name.
+    pub fn new_child(parent: &AgentId) -> Self {
+        Self {
+            parent_id: Some(parent.to_string()),
+            ..Self::new(parent.name.clone())
+        }
+    }
+
     /// Name of the agent, as written in the agent config
     pub fn name(&self) -> &str {
         &self.name
     }
+
+    /// Id of the agent that spawned this one, if it is a subagent
+    pub fn parent_id(&self) -> Option<&str> {
+        self.parent_id.as_deref()
+    }
 }
 
 impl Default for AgentId {
@@ -327,4 +399,24 @@ mod tests {
         };
         assert_agent_id!(a3, "a1#rand|a2|a3");
     }
+
+    #[test]
+    fn test_stream_timeouts() {
+        let reasoning = StreamTimeouts {
+            first_event: Duration::from_secs(600),
+            ..Default::default()
+        };
+        let mut settings = AgentSettings::default();
+        settings
+            .provider_stream_timeouts
+            .insert("reasoning".to_string(), reasoning);
+        assert_eq!(settings.stream_timeouts(Some("reasoning")), reasoning);
+        assert_eq!(settings.stream_timeouts(Some("other")), StreamTimeouts::default());
+        assert_eq!(settings.stream_timeouts(None), StreamTimeouts::default());
+
+        // Settings saved before stream timeouts existed
+        let settings: AgentSettings =
+            serde_json::from_value(serde_json::json!({ "mcp_init_timeout": { "secs": 5, "nanos": 0 } })).unwrap();
+        assert_eq!(settings.stream_timeouts, StreamTimeouts::default());
+    }
 }
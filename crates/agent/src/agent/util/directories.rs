@@ -11,6 +11,7 @@ use super::error::{
     ErrorContext as _,
     UtilError,
 };
+use super::providers::EnvProvider;
 use crate::agent::util::consts::env_var::CLI_DATA_DIR;
 
 const DATA_DIR_NAME: &str = "amazon-q";
@@ -48,6 +49,21 @@ pub fn database_path() -> Result<PathBuf> {
     Ok(data_dir()?.join("data.sqlite3"))
 }
 
+/// Path to the directory containing the saved agent sessions.
+pub fn sessions_dir() -> Result<PathBuf> {
+    Ok(data_dir()?.join("sessions"))
+}
+
+/// Path to the directory of the shadow copies recorded by file checkpoints.
+///
+/// Resolved through `provider`, so that the data directory override applies to it.
+pub fn checkpoints_dir<P: EnvProvider>(provider: &P) -> Result<PathBuf> {
+    match provider.var(CLI_DATA_DIR) {
+        Ok(p) => Ok(PathBuf::from(p).join("checkpoints")),
+        Err(_) => Ok(data_dir()?.join("checkpoints")),
+    }
+}
+
 pub fn settings_path() -> Result<PathBuf> {
     Ok(data_dir()?.join("settings.json"))
 }
@@ -0,0 +1,295 @@
+use std::collections::VecDeque;
+use std::sync::{
+    Arc,
+    Mutex,
+    Weak,
+};
+
+use tokio::sync::Notify;
+use tracing::warn;
+
+/// An event that can be sent through an [EventFanout].
+///
+/// Defines what happens to the event when a subscriber has fallen behind, i.e. its queue is full.
+/// Events that are neither droppable nor mergeable are always queued so that they are never lost,
+/// at the cost of exceeding the capacity.
+pub trait FanoutEvent: Clone {
+    /// Whether the event can be dropped for a subscriber that has fallen behind.
+    fn is_droppable(&self) -> bool {
+        false
+    }
+
+    /// Tries to fold `next` into this event, so that a subscriber that has fallen behind receives
+    /// them as a single event. Returns `true` if `next` was merged.
+    fn try_merge(&mut self, _next: &Self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum RecvError {
+    #[error("the event fanout has closed")]
+    Closed,
+}
+
+/// Sends every event to each of its subscribers through a bounded queue per subscriber.
+///
+/// Unlike a broadcast channel, a slow subscriber never silently loses events that matter: once
+/// its queue reaches capacity, droppable events are dropped, mergeable events are merged into the
+/// last queued event, and everything else is queued regardless. Sending never blocks.
+#[derive(Debug)]
+pub struct EventFanout<T> {
+    subscribers: Arc<Mutex<Subscribers<T>>>,
+    capacity: usize,
+}
+
+impl<T: FanoutEvent> EventFanout<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            subscribers: Arc::new(Mutex::new(Subscribers {
+                queues: Vec::new(),
+                closed: false,
+            })),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Creates a receiver for every event sent from now on.
+    pub fn subscribe(&self) -> EventReceiver<T> {
+        EventReceiver::new(&self.subscribers, self.capacity)
+    }
+
+    /// Sends `event` to every subscriber. Subscribers whose receiver was dropped are removed.
+    pub fn send(&self, event: T) {
+        let Ok(mut subscribers) = self.subscribers.lock() else {
+            return;
+        };
+        subscribers.queues.retain(|queue| {
+            let Some(queue) = queue.upgrade() else {
+                return false;
+            };
+            queue.push(event.clone());
+            true
+        });
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers
+            .lock()
+            .map(|subscribers| subscribers.queues.iter().filter(|q| q.strong_count() > 0).count())
+            .unwrap_or_default()
+    }
+}
+
+impl<T> Drop for EventFanout<T> {
+    fn drop(&mut self) {
+        let Ok(mut subscribers) = self.subscribers.lock() else {
+            return;
+        };
+        subscribers.closed = true;
+        for queue in subscribers.queues.drain(..).filter_map(|queue| queue.upgrade()) {
+            queue.close();
+        }
+    }
+}
+
+/// Receives the events sent through an [EventFanout].
+#[derive(Debug)]
+pub struct EventReceiver<T> {
+    subscribers: Arc<Mutex<Subscribers<T>>>,
+    queue: Arc<SubscriberQueue<T>>,
+}
+
+impl<T: FanoutEvent> EventReceiver<T> {
+    fn new(subscribers: &Arc<Mutex<Subscribers<T>>>, capacity: usize) -> Self {
+        let queue = Arc::new(SubscriberQueue::new(capacity));
+        match subscribers.lock() {
+            Ok(mut subs) if !subs.closed => subs.queues.push(Arc::downgrade(&queue)),
+            _ => queue.close(),
+        }
+        Self {
+            subscribers: Arc::clone(subscribers),
+            queue,
+        }
+    }
+
+    /// Receives the next event, waiting until one is sent. Returns [RecvError::Closed] once the
+    /// fanout has been dropped and all queued events were received.
+    pub async fn recv(&mut self) -> Result<T, RecvError> {
+        loop {
+            if let Some(event) = self.queue.pop()? {
+                return Ok(event);
+            }
+            self.queue.notify.notified().await;
+        }
+    }
+
+    /// Creates a new receiver for every event sent from now on.
+    pub fn resubscribe(&self) -> Self {
+        Self::new(&self.subscribers, self.queue.capacity)
+    }
+}
+
+#[derive(Debug)]
+struct Subscribers<T> {
+    queues: Vec<Weak<SubscriberQueue<T>>>,
+    /// Whether the [EventFanout] has been dropped
+    closed: bool,
+}
+
+#[derive(Debug)]
+struct SubscriberQueue<T> {
+    state: Mutex<QueueState<T>>,
+    notify: Notify,
+    capacity: usize,
+}
+
+#[derive(Debug)]
+struct QueueState<T> {
+    events: VecDeque<T>,
+    /// Number of events dropped since the subscriber last received one
+    dropped: usize,
+    closed: bool,
+}
+
+impl<T: FanoutEvent> SubscriberQueue<T> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                events: VecDeque::new(),
+                dropped: 0,
+                closed: false,
+            }),
+            notify: Notify::new(),
+            capacity,
+        }
+    }
+
+    fn push(&self, event: T) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if state.events.len() >= self.capacity {
+            if event.is_droppable() {
+                state.dropped += 1;
+                return;
+            }
+            if state.events.back_mut().is_some_and(|last| last.try_merge(&event)) {
+                return;
+            }
+        }
+        state.events.push_back(event);
+        drop(state);
+        self.notify.notify_one();
+    }
+
+    fn pop(&self) -> Result<Option<T>, RecvError> {
+        let Ok(mut state) = self.state.lock() else {
+            return Err(RecvError::Closed);
+        };
+        if state.dropped > 0 {
+            warn!(dropped = state.dropped, "event subscriber fell behind, dropped events");
+            state.dropped = 0;
+        }
+        match state.events.pop_front() {
+            Some(event) => Ok(Some(event)),
+            None if state.closed => Err(RecvError::Closed),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<T> SubscriberQueue<T> {
+    fn close(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.closed = true;
+        }
+        self.notify.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    enum TestEvent {
+        Critical(usize),
+        Text(String),
+        Debug,
+    }
+
+    impl FanoutEvent for TestEvent {
+        fn is_droppable(&self) -> bool {
+            matches!(self, Self::Debug)
+        }
+
+        fn try_merge(&mut self, next: &Self) -> bool {
+            match (self, next) {
+                (Self::Text(text), Self::Text(next)) => {
+                    text.push_str(next);
+                    true
+                },
+                _ => false,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscriber_keeps_critical_events() {
+        let fanout = EventFanout::new(4);
+        let fast = fanout.subscribe();
+        let mut slow = fanout.subscribe();
+
+        let mut fast_received = Vec::new();
+        for i in 0..100 {
+            fanout.send(TestEvent::Debug);
+            fanout.send(TestEvent::Text(i.to_string()));
+            fanout.send(TestEvent::Critical(i));
+            while let Some(event) = fast.queue.pop().unwrap() {
+                fast_received.push(event);
+            }
+        }
+        assert_eq!(fast_received.len(), 300);
+
+        drop(fanout);
+        let mut critical = Vec::new();
+        let mut text = String::new();
+        while let Ok(event) = slow.recv().await {
+            match event {
+                TestEvent::Critical(i) => critical.push(i),
+                TestEvent::Text(t) => text.push_str(&t),
+                TestEvent::Debug => (),
+            }
+        }
+        assert_eq!(critical, (0..100).collect::<Vec<_>>());
+        assert_eq!(text, (0..100).map(|i| i.to_string()).collect::<String>());
+    }
+
+    #[tokio::test]
+    async fn test_recv_waits_for_send() {
+        let fanout = EventFanout::new(4);
+        let mut rx = fanout.subscribe();
+        let handle = tokio::spawn(async move { rx.recv().await });
+        tokio::task::yield_now().await;
+        fanout.send(TestEvent::Critical(1));
+        assert_eq!(handle.await.unwrap(), Ok(TestEvent::Critical(1)));
+    }
+
+    #[tokio::test]
+    async fn test_subscribers() {
+        let fanout = EventFanout::new(4);
+        let rx = fanout.subscribe();
+        let mut resubscribed = rx.resubscribe();
+        assert_eq!(fanout.subscriber_count(), 2);
+
+        drop(rx);
+        fanout.send(TestEvent::Critical(1));
+        assert_eq!(fanout.subscriber_count(), 1);
+        assert_eq!(resubscribed.recv().await, Ok(TestEvent::Critical(1)));
+
+        drop(fanout);
+        assert_eq!(resubscribed.recv().await, Err(RecvError::Closed));
+        assert_eq!(resubscribed.resubscribe().recv().await, Err(RecvError::Closed));
+    }
+}
@@ -1,6 +1,7 @@
 pub mod consts;
 pub mod directories;
 pub mod error;
+pub mod event_fanout;
 pub mod glob;
 pub mod path;
 pub mod providers;
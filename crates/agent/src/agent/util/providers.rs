@@ -2,6 +2,7 @@ use std::env::VarError;
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use super::consts::env_var::CLI_DATA_DIR;
 use super::directories;
 
 /// A trait for accessing system and process context (env vars, home dir, current working dir,
@@ -110,3 +111,46 @@ impl CwdProvider for RealProvider {
 }
 
 impl SystemProvider for RealProvider {}
+
+/// Provides a [SystemProvider] confined to a directory, for running an agent in a throwaway
+/// workspace:
+/// - cwd: $root
+/// - home: $root/home
+/// - env vars: HOME=$root/home, Q_CLI_DATA_DIR=$root/data, and no others
+#[derive(Debug, Clone)]
+pub struct WorkspaceProvider {
+    root: PathBuf,
+}
+
+impl WorkspaceProvider {
+    /// `root` must be an absolute path, otherwise this method panics.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        assert!(root.is_absolute(), "the workspace root must be an absolute path");
+        Self { root }
+    }
+}
+
+impl EnvProvider for WorkspaceProvider {
+    fn var(&self, input: &str) -> Result<String, VarError> {
+        match input {
+            "HOME" => Ok(self.root.join("home").to_string_lossy().to_string()),
+            CLI_DATA_DIR => Ok(self.root.join("data").to_string_lossy().to_string()),
+            _ => Err(VarError::NotPresent),
+        }
+    }
+}
+
+impl HomeProvider for WorkspaceProvider {
+    fn home(&self) -> Option<PathBuf> {
+        Some(self.root.join("home"))
+    }
+}
+
+impl CwdProvider for WorkspaceProvider {
+    fn cwd(&self) -> Result<PathBuf, std::io::Error> {
+        Ok(self.root.clone())
+    }
+}
+
+impl SystemProvider for WorkspaceProvider {}
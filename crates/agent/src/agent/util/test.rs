@@ -6,6 +6,7 @@ use std::path::{
     PathBuf,
 };
 
+use super::consts::env_var::CLI_DATA_DIR;
 use super::path::canonicalize_path_sys;
 use super::providers::{
     CwdProvider,
@@ -23,7 +24,7 @@ pub struct TestBase {
 
 impl TestBase {
     /// Creates a new temporary directory with the following defaults configured:
-    /// - env vars: HOME=$tempdir_path/home/testuser
+    /// - env vars: HOME=$tempdir_path/home/testuser, Q_CLI_DATA_DIR=$tempdir_path/data
     /// - cwd: $tempdir_path
     /// - home: $tempdir_path/home/testuser
     pub async fn new() -> Self {
@@ -32,7 +33,9 @@ impl TestBase {
         tokio::fs::create_dir_all(&home_path)
             .await
             .expect("failed to create test home directory");
-        let provider = TestProvider::new_with_base(home_path).with_cwd(test_dir.path());
+        let provider = TestProvider::new_with_base(home_path)
+            .with_cwd(test_dir.path())
+            .with_var(CLI_DATA_DIR, test_dir.path().join("data").to_string_lossy());
         Self { test_dir, provider }
     }
 
@@ -1,12 +1,14 @@
 use std::io::Write as _;
 use std::process::ExitCode;
-use std::sync::Arc;
 
+use agent::AgentHandle;
 use agent::agent_config::load_agents;
+use agent::agent_loop::model_provider::ModelProviderRegistry;
 use agent::agent_loop::protocol::{
     AgentLoopEventKind,
     LoopEndReason,
 };
+use agent::agent_loop::types::TokenUsage;
 use agent::api_client::ApiClient;
 use agent::mcp::McpManager;
 use agent::protocol::{
@@ -20,14 +22,14 @@ use agent::protocol::{
     UpdateEvent,
 };
 use agent::rts::{
-    RtsModel,
-    RtsModelState,
+    RTS_PROVIDER_NAME,
+    RtsModelProvider,
 };
+use agent::runtime::AgentRuntime;
+use agent::session::SessionStore;
+use agent::tools::ToolOutputChunk;
 use agent::types::AgentSnapshot;
-use agent::{
-    Agent,
-    AgentHandle,
-};
+use chrono::Utc;
 use clap::Args;
 use eyre::{
     Result,
@@ -40,7 +42,6 @@ use serde::{
 use tracing::{
     debug,
     error,
-    info,
     warn,
 };
 
@@ -55,6 +56,10 @@ pub struct RunArgs {
     /// Resumes the session given by the provided ID
     #[arg(short, long)]
     resume: Option<String>,
+    /// Reverts the files modified by tools since the given checkpoint of the resumed session,
+    /// before running the prompt if any
+    #[arg(long, requires = "resume")]
+    revert: Option<u32>,
     /// The output format
     #[arg(long)]
     output_format: Option<OutputFormat>,
@@ -67,31 +72,15 @@ pub struct RunArgs {
 
 impl RunArgs {
     pub async fn execute(self) -> Result<ExitCode> {
-        // TODO - implement resume. For now, just use a new default snapshot every time.
-        let mut snapshot = AgentSnapshot::default();
-
-        // Create the RTS model
-        let model = {
-            let rts_state: RtsModelState = snapshot
-                .model_state
-                .as_ref()
-                .and_then(|s| {
-                    serde_json::from_value(s.clone())
-                        .map_err(|err| error!(?err, ?s, "failed to deserialize RTS state"))
-                        .ok()
-                })
-                .unwrap_or({
-                    let state = RtsModelState::new();
-                    info!(?state.conversation_id, "generated new conversation id");
-                    state
-                });
-            Arc::new(RtsModel::new(
-                ApiClient::new().await?,
-                rts_state.conversation_id,
-                rts_state.model_id,
-            ))
+        let store = SessionStore::default_json()?;
+        let mut snapshot = match &self.resume {
+            Some(id) => store.load(id).await?,
+            None => AgentSnapshot::default(),
         };
 
+        let mut models = ModelProviderRegistry::new();
+        models.register(RTS_PROVIDER_NAME, RtsModelProvider::new(ApiClient::new().await?));
+
         // Override the agent config if a custom agent name was provided.
         if let Some(name) = &self.agent {
             let (configs, _) = load_agents().await?;
@@ -101,13 +90,17 @@ impl RunArgs {
                 bail!("unable to find agent with name: {}", name);
             }
         };
+        if let Some(model_id) = &self.model {
+            snapshot.agent_config.set_model_id(model_id.clone());
+        }
 
-        let agent = Agent::new(snapshot, model, McpManager::new().spawn()).await?.spawn();
+        let runtime = AgentRuntime::new(models, McpManager::new().spawn());
+        let agent = runtime.spawn(snapshot).await?;
 
-        self.main_loop(agent).await
+        self.main_loop(agent, &store).await
     }
 
-    async fn main_loop(&self, mut agent: AgentHandle) -> Result<ExitCode> {
+    async fn main_loop(&self, mut agent: AgentHandle, store: &SessionStore) -> Result<ExitCode> {
         let initial_prompt = self.prompt.join(" ");
 
         // First, wait for agent initialization
@@ -117,6 +110,19 @@ impl RunArgs {
             }
         }
 
+        if let Some(checkpoint) = self.revert {
+            let paths = agent.revert_to_checkpoint(checkpoint).await?;
+            eprintln!("Reverted {} file(s) to checkpoint {checkpoint}", paths.len());
+            for path in &paths {
+                eprintln!("  {}", path.display());
+            }
+            if initial_prompt.trim().is_empty() {
+                store.save(&agent.create_snapshot().await?).await?;
+                return Ok(ExitCode::SUCCESS);
+            }
+        }
+
+        let turn_start = Utc::now();
         agent
             .send_prompt(SendPromptArgs {
                 content: vec![ContentChunk::Text(initial_prompt)],
@@ -163,6 +169,37 @@ impl RunArgs {
             }
         }
 
+        // Saved after the turn, so a later crash doesn't lose the conversation
+        let (session_id, checkpoint) = match agent.create_snapshot().await {
+            Ok(snapshot) => {
+                // The checkpoint of this turn, if a tool modified files
+                let checkpoint = snapshot
+                    .checkpoints
+                    .list()
+                    .last()
+                    .filter(|c| c.created_at >= turn_start)
+                    .map(|c| c.id);
+                match store.save(&snapshot).await {
+                    Ok(session) => (Some(session.id), checkpoint),
+                    Err(err) => {
+                        error!(?err, "failed to save the session");
+                        (None, None)
+                    },
+                }
+            },
+            Err(err) => {
+                error!(?err, "failed to create a snapshot of the session");
+                (None, None)
+            },
+        };
+
+        if let (None | Some(OutputFormat::Text), Some(id)) = (self.output_format, &session_id) {
+            eprintln!("\n\nResume this session with --resume {id}");
+            if let Some(checkpoint) = checkpoint {
+                eprintln!("Undo the file changes of this turn with --resume {id} --revert {checkpoint}");
+            }
+        }
+
         if self.output_format == Some(OutputFormat::Json) {
             let md = user_turn_metadata.expect("user turn metadata should exist");
             let is_error = md.end_reason != LoopEndReason::UserTurnEnd || md.result.as_ref().is_none_or(|v| v.is_err());
@@ -174,6 +211,8 @@ impl RunArgs {
                 number_of_requests: md.total_request_count,
                 number_of_cycles: md.number_of_cycles,
                 duration_ms: md.turn_duration.map(|d| d.as_millis() as u32).unwrap_or_default(),
+                session_id,
+                usage: md.usage,
             };
             println!("{}", serde_json::to_string(&output)?);
         }
@@ -196,6 +235,13 @@ impl RunArgs {
                                 serde_json::to_string_pretty(&tool_call.tool_use_block).expect("does not fail")
                             );
                         },
+                        UpdateEvent::ToolCallUpdate { content, .. } => match content {
+                            ToolOutputChunk::Stdout(text) => {
+                                print!("{}", text);
+                                let _ = std::io::stdout().flush();
+                            },
+                            ToolOutputChunk::Stderr(text) => eprint!("{}", text),
+                        },
                         _ => (),
                     }
                 }
@@ -236,4 +282,8 @@ struct JsonOutput {
     number_of_cycles: u32,
     /// Duration of the turn, in milliseconds
     duration_ms: u32,
+    /// Id to pass to `--resume` to continue the session, if it was saved
+    session_id: Option<String>,
+    /// Tokens used by the turn, and their estimated cost
+    usage: TokenUsage,
 }
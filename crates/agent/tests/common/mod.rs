@@ -80,6 +80,12 @@ impl TestCaseBuilder {
         self
     }
 
+    /// Adds a response that can't be parsed from a file, like one with delays between its events
+    pub fn with_mock_response(mut self, response: MockResponse) -> Self {
+        self.mock_responses.push(response);
+        self
+    }
+
     pub fn with_trust_all_tools(mut self, trust_all: bool) -> Self {
         self.trust_all_tools = trust_all;
         self
@@ -164,6 +170,10 @@ impl TestCase {
         &self.sent_requests
     }
 
+    pub async fn snapshot(&self) -> AgentSnapshot {
+        self.agent.create_snapshot().await.expect("failed to create snapshot")
+    }
+
     pub async fn wait_until_agent_stop(&mut self, timeout: Duration) {
         let timeout_at = Instant::now() + timeout;
         loop {
@@ -265,27 +275,3 @@ impl From<SendRequestArgs> for SentRequest {
         Self { original: value }
     }
 }
-
-pub async fn parse_response_streams(content: impl AsRef<str>) -> Result<MockResponseStreams> {
-    let mut stream: Vec<Vec<StreamResult>> = Vec::new();
-    let mut curr_stream = Vec::new();
-    for line in content.as_ref().lines() {
-        // ignore comments
-        if line.starts_with("//") {
-            continue;
-        }
-        // empty line -> new response stream
-        if line.is_empty() && !curr_stream.is_empty() {
-            let mut temp = Vec::new();
-            std::mem::swap(&mut temp, &mut curr_stream);
-            stream.push(temp);
-            continue;
-        }
-        // otherwise, push the value to the current response
-        curr_stream.push(serde_json::from_str(line)?);
-    }
-    if !curr_stream.is_empty() {
-        stream.push(curr_stream);
-    }
-    Ok(stream)
-}
@@ -1,12 +1,43 @@
 mod common;
 
+use std::sync::Arc;
 use std::time::Duration;
 
+use agent::Agent;
 use agent::agent_config::definitions::AgentConfig;
+use agent::agent_loop::model::{
+    MockModel,
+    MockResponse,
+    parse_response_streams,
+};
+use agent::agent_loop::protocol::StreamResult;
+use agent::agent_loop::types::{
+    ContentBlock,
+    ContentBlockDelta,
+    ContentBlockDeltaEvent,
+    MessageStartEvent,
+    MessageStopEvent,
+    Role,
+    StopReason,
+    StreamError,
+    StreamErrorKind,
+    StreamEvent,
+};
+use agent::consts::STREAM_RESUME_PROMPT;
+use agent::journal::{
+    Journal,
+    Replay,
+};
+use agent::mcp::McpManager;
 use agent::protocol::{
+    AgentEvent,
+    AgentStopReason,
     ApprovalResult,
+    InternalEvent,
     SendApprovalResultArgs,
 };
+use agent::types::AgentSnapshot;
+use agent::util::test::TestBase;
 use common::*;
 
 #[tokio::test]
@@ -27,11 +58,7 @@ async fn test_agent_defaults() {
         .with_file(("README.md", README_MD_CONTENT))
         .with_file((".amazonq/rules/local_rule.md", LOCAL_RULE_MD_CONTENT))
         .with_file((".amazonq/rules/subfolder/sub_local_rule.md", SUB_LOCAL_RULE_MD_CONTENT))
-        .with_responses(
-            parse_response_streams(include_str!("./mock_responses/builtin_tools.jsonl"))
-                .await
-                .unwrap(),
-        )
+        .with_responses(parse_response_streams(include_str!("./mock_responses/builtin_tools.jsonl")).unwrap())
         .with_tool_use_approvals([
             SendApprovalResultArgs {
                 id: "tooluse_first".into(),
@@ -71,3 +98,129 @@ async fn test_agent_defaults() {
         assert_contains(SUB_LOCAL_RULE_MD_CONTENT);
     }
 }
+
+#[tokio::test]
+async fn test_replay_journal() {
+    let _ = tracing_subscriber::fmt::try_init();
+
+    // The agent is driven directly, since the journal is recorded by the agent and not the test case
+    let mut model = MockModel::new();
+    for response in parse_response_streams(include_str!("./mock_responses/builtin_tools.jsonl")).unwrap() {
+        model = model.with_response(response);
+    }
+    let snapshot = AgentSnapshot::new_empty(AgentConfig::default());
+    let mut agent = Agent::new(snapshot, Arc::new(model), McpManager::new().spawn())
+        .await
+        .unwrap();
+    let test_base = TestBase::new().await;
+    agent.set_sys_provider(test_base.provider().clone());
+    let journal_dir = tempfile::tempdir().unwrap();
+    let journal_path = journal_dir.path().join("journal.json");
+    agent.record_journal(&journal_path);
+    let mut agent = agent.spawn();
+
+    agent.send_prompt("start turn".to_string().into()).await.unwrap();
+    let mut requests = 0;
+    loop {
+        let event = tokio::time::timeout(Duration::from_secs(2), agent.recv())
+            .await
+            .expect("timed out")
+            .unwrap();
+        match event {
+            AgentEvent::Stop(_) => break,
+            AgentEvent::ApprovalRequest { id, .. } => agent
+                .send_tool_use_approval_result(SendApprovalResultArgs {
+                    id,
+                    result: ApprovalResult::Approve,
+                })
+                .await
+                .unwrap(),
+            AgentEvent::Internal(InternalEvent::RequestSent(_)) => requests += 1,
+            _ => (),
+        }
+    }
+
+    let journal = Journal::load(&journal_path).await.unwrap();
+    assert_eq!(journal.turns.len(), 1);
+    assert_eq!(journal.turns[0].responses.len(), requests);
+    assert!(!journal.turns[0].tool_results.is_empty());
+
+    // Tools return the recorded results, so the replayed conversation matches even though the
+    // files the tools created don't exist outside of the test case.
+    let mut replay = Replay::new(journal).await.unwrap();
+    let reason = tokio::time::timeout(Duration::from_secs(2), replay.replay_turn())
+        .await
+        .expect("timed out")
+        .unwrap();
+    assert!(matches!(reason, Some(AgentStopReason::EndTurn)));
+    assert!(replay.replay_turn().await.unwrap().is_none());
+
+    let content = |messages: &[agent::agent_loop::types::Message]| -> Vec<Vec<ContentBlock>> {
+        messages.iter().map(|m| m.content.clone()).collect()
+    };
+    let original = agent.create_snapshot().await.unwrap().conversation_state.messages;
+    let replayed = replay
+        .agent()
+        .create_snapshot()
+        .await
+        .unwrap()
+        .conversation_state
+        .messages;
+    assert_eq!(content(&original), content(&replayed));
+}
+
+#[tokio::test]
+async fn test_resume_after_disconnect() {
+    let _ = tracing_subscriber::fmt::try_init();
+
+    let text = |text: &str| {
+        StreamResult::Ok(StreamEvent::ContentBlockDelta(ContentBlockDeltaEvent {
+            delta: ContentBlockDelta::Text(text.to_string()),
+            content_block_index: None,
+        }))
+    };
+    let start = StreamResult::Ok(StreamEvent::MessageStart(MessageStartEvent { role: Role::Assistant }));
+    // The connection drops a while after the start of the response
+    let disconnected = MockResponse::from(vec![
+        start.clone(),
+        text("The answer is"),
+        StreamResult::Err(StreamError::new(StreamErrorKind::Disconnected)),
+    ])
+    .with_delay_before(2, Duration::from_millis(100));
+    let continued = vec![
+        start,
+        text(" 42."),
+        StreamResult::Ok(StreamEvent::MessageStop(MessageStopEvent {
+            stop_reason: StopReason::EndTurn,
+        })),
+    ];
+
+    let mut test = TestCase::builder()
+        .test_name("resume after disconnect")
+        .with_mock_response(disconnected)
+        .with_responses(vec![continued])
+        .build()
+        .await
+        .unwrap();
+
+    test.send_prompt("what is the answer?".to_string()).await;
+    test.wait_until_agent_stop(Duration::from_secs(2)).await;
+
+    // The continuation is requested with the partial response and the prompt to continue it
+    let requests = test.requests();
+    assert_eq!(requests.len(), 2);
+    let messages = requests[1].messages();
+    assert_eq!(messages[messages.len() - 2].role, Role::Assistant);
+    assert_eq!(messages[messages.len() - 2].text(), "The answer is");
+    assert!(requests[1].prompt_contains_text(STREAM_RESUME_PROMPT));
+
+    // The history only keeps the whole response, not the prompt to continue it
+    let messages = test.snapshot().await.conversation_state.messages;
+    assert!(
+        !messages.iter().any(|m| m.text().contains(STREAM_RESUME_PROMPT)),
+        "{messages:?}"
+    );
+    let last = messages.last().unwrap();
+    assert_eq!(last.role, Role::Assistant);
+    assert_eq!(last.text(), "The answer is 42.");
+}
@@ -0,0 +1,196 @@
+//! Benchmarks for rendering streamed responses, guarding against regressions in how output is
+//! coalesced before it reaches the terminal.
+
+use std::hint::black_box;
+use std::io::{
+    self,
+    Write,
+};
+
+use chat_cli_ui::conduit::get_legacy_conduits;
+use chat_cli_ui::legacy_ui_util::ThemeSource;
+use chat_cli_ui::render::{
+    DEFAULT_MAX_FPS,
+    FrameBuffer,
+};
+use criterion::{
+    BenchmarkId,
+    Criterion,
+    Throughput,
+    criterion_group,
+    criterion_main,
+};
+use crossterm::style::{
+    Attribute,
+    Color,
+    ResetColor,
+    SetAttribute,
+    SetForegroundColor,
+};
+
+/// Number of chunks in a simulated response
+const CHUNKS: usize = 5_000;
+/// Typical size of a streamed chunk
+const CHUNK: &str = "lorem ipsum ";
+
+/// Discards output while counting how often it's flushed, i.e. how many frames hit the terminal
+#[derive(Default)]
+struct Terminal {
+    flushes: usize,
+}
+
+impl Write for Terminal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flushes += 1;
+        Ok(())
+    }
+}
+
+struct PlainTheme;
+
+impl ThemeSource for PlainTheme {
+    fn error(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn info(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn emphasis(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn command(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn prompt(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn profile(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn tangent(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn usage_low(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn usage_medium(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn usage_high(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn brand(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn primary(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn secondary(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn success(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn error_fg(&self) -> SetForegroundColor {
+        SetForegroundColor(Color::Reset)
+    }
+
+    fn warning_fg(&self) -> SetForegroundColor {
+        SetForegroundColor(Color::Reset)
+    }
+
+    fn success_fg(&self) -> SetForegroundColor {
+        SetForegroundColor(Color::Reset)
+    }
+
+    fn info_fg(&self) -> SetForegroundColor {
+        SetForegroundColor(Color::Reset)
+    }
+
+    fn brand_fg(&self) -> SetForegroundColor {
+        SetForegroundColor(Color::Reset)
+    }
+
+    fn secondary_fg(&self) -> SetForegroundColor {
+        SetForegroundColor(Color::Reset)
+    }
+
+    fn emphasis_fg(&self) -> SetForegroundColor {
+        SetForegroundColor(Color::Reset)
+    }
+
+    fn reset(&self) -> ResetColor {
+        ResetColor
+    }
+
+    fn reset_attributes(&self) -> SetAttribute {
+        SetAttribute(Attribute::Reset)
+    }
+}
+
+/// Streams a response through the conduits the chat session uses, returning the number of
+/// frames that were presented
+fn stream_response(max_fps: u32) -> usize {
+    let (view_end, _input_receiver, stderr, mut stdout) = get_legacy_conduits(false);
+    for _ in 0..CHUNKS {
+        stdout.write_all(CHUNK.as_bytes()).unwrap();
+        stdout.flush().unwrap();
+    }
+    drop(stdout);
+    drop(stderr);
+
+    let mut terminal = Terminal::default();
+    view_end
+        .into_legacy_mode(PlainTheme, None, io::sink(), &mut terminal, max_fps)
+        .unwrap();
+    terminal.flushes
+}
+
+fn bench_stream_response(c: &mut Criterion) {
+    let mut group = c.benchmark_group("stream_response");
+    group.throughput(Throughput::Elements(CHUNKS as u64));
+    for max_fps in [0, DEFAULT_MAX_FPS] {
+        group.bench_with_input(BenchmarkId::from_parameter(max_fps), &max_fps, |b, &max_fps| {
+            b.iter(|| black_box(stream_response(max_fps)));
+        });
+    }
+    group.finish();
+}
+
+fn bench_frame_buffer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frame_buffer");
+    group.throughput(Throughput::Bytes((CHUNKS * CHUNK.len()) as u64));
+    group.bench_function("coalesce_and_present", |b| {
+        b.iter(|| {
+            let mut frame = FrameBuffer::default();
+            for _ in 0..CHUNKS {
+                frame.write_all(black_box(CHUNK.as_bytes())).unwrap();
+            }
+            let mut terminal = Terminal::default();
+            frame.present(&mut terminal).unwrap();
+            black_box(terminal.flushes)
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_stream_response, bench_frame_buffer);
+criterion_main!(benches);
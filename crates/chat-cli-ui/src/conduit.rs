@@ -1,5 +1,7 @@
-use std::io::Write as _;
+use std::io::Write;
 use std::marker::PhantomData;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::Instant;
 
 use crossterm::style::{
     self,
@@ -19,6 +21,10 @@ use crate::protocol::{
     ToolCallRejection,
     ToolCallStart,
 };
+use crate::render::{
+    FrameBuffer,
+    FrameLimiter,
+};
 
 const TOOL_BULLET: &str = " ● ";
 const CONTINUATION_LINE: &str = " ⋮ ";
@@ -51,14 +57,52 @@ impl ViewEnd {
     /// Method to facilitate in the interim
     /// It takes possible messages from the old even loop and queues write to the output provided
     /// This blocks the current thread and consumes the [ViewEnd]
+    ///
+    /// Streamed output (response text and tool arguments) is coalesced into frames that are
+    /// presented at most `max_fps` times per second. Every other event presents the pending frame
+    /// right away so that output stays in order.
     pub fn into_legacy_mode(
         self,
         theme_source: impl ThemeSource,
         prompt_ack: Option<std::sync::mpsc::Sender<()>>,
-        mut stderr: std::io::Stderr,
-        mut stdout: std::io::Stdout,
+        mut stderr: impl Write,
+        mut stdout: impl Write,
+        max_fps: u32,
     ) -> Result<(), ConduitError> {
-        while let Ok(event) = self.receiver.recv() {
+        let mut frame = FrameBuffer::default();
+        let mut limiter = FrameLimiter::new(max_fps);
+
+        loop {
+            let event = if frame.is_dirty() {
+                match self.receiver.recv_timeout(limiter.time_until_due(Instant::now())) {
+                    Ok(event) => Some(event),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            } else {
+                match self.receiver.recv() {
+                    Ok(event) => Some(event),
+                    Err(_) => break,
+                }
+            };
+
+            let Some(event) = event else {
+                // The next frame is due
+                frame.present(&mut stdout)?;
+                limiter.mark_presented(Instant::now());
+                continue;
+            };
+
+            let is_streamed = matches!(
+                event,
+                Event::LegacyPassThrough(LegacyPassThroughOutput::Stdout(_))
+                    | Event::TextMessageContent(_)
+                    | Event::ToolCallArgs(_)
+            );
+            if !is_streamed {
+                frame.present(&mut stdout)?;
+            }
+
             match event {
                 Event::LegacyPassThrough(content) => match content {
                     LegacyPassThroughOutput::Stderr(content) => {
@@ -66,8 +110,7 @@ impl ViewEnd {
                         stderr.flush()?;
                     },
                     LegacyPassThroughOutput::Stdout(content) => {
-                        stdout.write_all(&content)?;
-                        stdout.flush()?;
+                        frame.write_all(&content)?;
                     },
                 },
                 Event::RunStarted(_run_started) => {},
@@ -76,15 +119,14 @@ impl ViewEnd {
                 Event::StepStarted(_step_started) => {},
                 Event::StepFinished(_step_finished) => {},
                 Event::TextMessageStart(_text_message_start) => {
-                    queue!(stdout, theme_source.success_fg(), Print("> "), theme_source.reset(),)?;
+                    queue!(frame, theme_source.success_fg(), Print("> "), theme_source.reset(),)?;
                 },
                 Event::TextMessageContent(text_message_content) => {
-                    stdout.write_all(&text_message_content.delta)?;
-                    stdout.flush()?;
+                    frame.write_all(&text_message_content.delta)?;
                 },
                 Event::TextMessageEnd(_text_message_end) => {
                     queue!(stderr, theme_source.reset(), theme_source.reset_attributes())?;
-                    execute!(stdout, style::Print("\n"))?;
+                    queue!(frame, style::Print("\n"))?;
                 },
                 Event::TextMessageChunk(_text_message_chunk) => {},
                 Event::ToolCallStart(tool_call_start) => {
@@ -96,7 +138,7 @@ impl ViewEnd {
                     } = tool_call_start;
 
                     queue!(
-                        stdout,
+                        frame,
                         theme_source.emphasis_fg(),
                         Print(format!(
                             "🛠️  Using tool: {}{}",
@@ -112,7 +154,7 @@ impl ViewEnd {
 
                     if let Some(server_name) = mcp_server_name {
                         queue!(
-                            stdout,
+                            frame,
                             theme_source.reset(),
                             Print(" from mcp server "),
                             theme_source.emphasis_fg(),
@@ -121,8 +163,8 @@ impl ViewEnd {
                         )?;
                     }
 
-                    execute!(
-                        stdout,
+                    queue!(
+                        frame,
                         Print("\n"),
                         Print(CONTINUATION_LINE),
                         Print("\n"),
@@ -131,9 +173,9 @@ impl ViewEnd {
                 },
                 Event::ToolCallArgs(tool_call_args) => {
                     if let serde_json::Value::String(content) = tool_call_args.delta {
-                        execute!(stdout, style::Print(content))?;
+                        queue!(frame, style::Print(content))?;
                     } else {
-                        execute!(stdout, style::Print(tool_call_args.delta))?;
+                        queue!(frame, style::Print(tool_call_args.delta))?;
                     }
                 },
                 Event::ToolCallEnd(_tool_call_end) => {
@@ -183,8 +225,15 @@ impl ViewEnd {
                     )?;
                 },
             }
+
+            let now = Instant::now();
+            if !is_streamed || limiter.is_due(now) {
+                frame.present(&mut stdout)?;
+                limiter.mark_presented(now);
+            }
         }
 
+        frame.present(&mut stdout)?;
         Ok(())
     }
 }
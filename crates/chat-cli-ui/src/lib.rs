@@ -1,5 +1,8 @@
 pub mod conduit;
 pub mod input_bar;
 pub mod legacy_ui_util;
+pub mod palette;
+pub mod path_completion;
 pub mod protocol;
+pub mod render;
 pub mod ui;
@@ -0,0 +1,118 @@
+//! Data model and fuzzy matching for the chat command palette.
+//!
+//! The palette lists everything a user can insert into the prompt (slash commands, files,
+//! saved prompts, and agents) alongside a short help text. Rendering is left to the consumer.
+
+/// Score awarded for every matched character
+const MATCH_SCORE: i64 = 16;
+/// Bonus for a match directly following the previous match
+const CONSECUTIVE_BONUS: i64 = 12;
+/// Bonus for a match at the start of a word, e.g. the `a` in `/context add`
+const BOUNDARY_BONUS: i64 = 8;
+/// Penalty for every skipped character between two matches
+const GAP_PENALTY: i64 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PaletteItemKind {
+    Command,
+    File,
+    Prompt,
+    Agent,
+}
+
+impl PaletteItemKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PaletteItemKind::Command => "command",
+            PaletteItemKind::File => "file",
+            PaletteItemKind::Prompt => "prompt",
+            PaletteItemKind::Agent => "agent",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaletteItem {
+    pub kind: PaletteItemKind,
+    /// What the item is matched against, e.g. `/context add` or `src/main.rs`
+    pub value: String,
+    /// Short help text shown next to the value
+    pub help: Option<String>,
+}
+
+impl PaletteItem {
+    pub fn new(kind: PaletteItemKind, value: impl Into<String>, help: Option<String>) -> Self {
+        Self {
+            kind,
+            value: value.into(),
+            help,
+        }
+    }
+
+    /// The text inserted into the prompt when the item is selected
+    pub fn insertion(&self) -> String {
+        match self.kind {
+            PaletteItemKind::Command | PaletteItemKind::File => self.value.clone(),
+            PaletteItemKind::Prompt => format!("@{}", self.value),
+            PaletteItemKind::Agent => format!("/agent swap {}", self.value),
+        }
+    }
+
+    /// Single line representation of the item, with the value and help text in aligned columns
+    pub fn display(&self, value_width: usize) -> String {
+        let line = format!("{:<8} {:<value_width$}", self.kind.label(), self.value);
+        match &self.help {
+            Some(help) if !help.is_empty() => format!("{line}  {help}"),
+            _ => line.trim_end().to_string(),
+        }
+    }
+}
+
+/// Scores how well `query` fuzzy matches `candidate`, ignoring case. Every character of the query
+/// has to appear in the candidate, in order. Returns [None] if the candidate does not match.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let candidate = candidate.chars().collect::<Vec<_>>();
+    let mut score = 0;
+    let mut next = 0;
+    let mut prev_match: Option<usize> = None;
+
+    for query_char in query.chars() {
+        let offset = candidate[next..]
+            .iter()
+            .position(|c| c.eq_ignore_ascii_case(&query_char))?;
+        let idx = next + offset;
+
+        score += MATCH_SCORE;
+        if prev_match.is_some_and(|prev| prev + 1 == idx) {
+            score += CONSECUTIVE_BONUS;
+        }
+        if idx == 0 || matches!(candidate[idx - 1], '/' | ' ' | '-' | '_' | '.') {
+            score += BOUNDARY_BONUS;
+        }
+        if prev_match.is_some() {
+            score -= offset as i64 * GAP_PENALTY;
+        }
+
+        prev_match = Some(idx);
+        next = idx + 1;
+    }
+
+    Some(score)
+}
+
+/// Returns the items matching `query`, best match first. Ties are broken in favor of shorter
+/// keys, then the original order.
+pub fn fuzzy_filter<T>(items: impl IntoIterator<Item = T>, query: &str, key: impl Fn(&T) -> &str) -> Vec<T> {
+    let mut scored = items
+        .into_iter()
+        .filter_map(|item| {
+            let key = key(&item);
+            let score = fuzzy_score(query, key)?;
+            let len = key.len();
+            Some((score, len, item))
+        })
+        .collect::<Vec<_>>();
+
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    scored.into_iter().map(|(_, _, item)| item).collect()
+}
@@ -0,0 +1,119 @@
+//! Filesystem path completion for the chat input, e.g. for `@src/ma<TAB>` mentions.
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process::{
+    Command,
+    Stdio,
+};
+
+/// Completes paths relative to a working directory, leaving out anything ignored by git.
+#[derive(Debug, Clone)]
+pub struct WorkspacePathCompleter {
+    cwd: PathBuf,
+}
+
+impl WorkspacePathCompleter {
+    pub fn new(cwd: impl Into<PathBuf>) -> Self {
+        Self { cwd: cwd.into() }
+    }
+
+    pub fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    /// Lists the completions of `partial`, a path relative to the working directory or an
+    /// absolute path. Directories are suffixed with a `/` so that completion can continue into
+    /// them. Hidden entries are only listed when `partial` asks for them, e.g. `.git`.
+    pub async fn complete(&self, partial: &str) -> Vec<String> {
+        let (dir_part, prefix) = match partial.rfind('/') {
+            Some(i) => partial.split_at(i + 1),
+            None => ("", partial),
+        };
+        let dir = if Path::new(dir_part).is_absolute() {
+            PathBuf::from(dir_part)
+        } else {
+            self.cwd.join(dir_part)
+        };
+
+        let Ok(mut entries) = tokio::fs::read_dir(&dir).await else {
+            return Vec::new();
+        };
+
+        let mut candidates = Vec::new();
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.starts_with(prefix) || name == ".git" || (name.starts_with('.') && !prefix.starts_with('.')) {
+                continue;
+            }
+            // Follow symlinks so that links to directories can be completed into
+            let is_dir = tokio::fs::metadata(entry.path()).await.is_ok_and(|m| m.is_dir());
+            candidates.push(if is_dir { format!("{name}/") } else { name });
+        }
+
+        let ignored = ignored_by_git(&dir, &candidates).await;
+        let mut completions = candidates
+            .into_iter()
+            .filter(|name| !ignored.contains(name))
+            .map(|name| format!("{dir_part}{name}"))
+            .collect::<Vec<_>>();
+        completions.sort();
+        completions
+    }
+
+    /// Blocking version of [Self::complete] for synchronous callers such as the line editor. Has
+    /// to be called from within a Tokio runtime since the directory listing runs on its blocking
+    /// thread pool.
+    pub fn complete_blocking(&self, partial: &str) -> Vec<String> {
+        if tokio::runtime::Handle::try_current().is_err() {
+            return Vec::new();
+        }
+        futures::executor::block_on(self.complete(partial))
+    }
+}
+
+/// Returns the subset of `names` (entries of `dir`) that are ignored by git. Nothing is ignored
+/// outside of a git repository or if git is not installed.
+async fn ignored_by_git(dir: &Path, names: &[String]) -> HashSet<String> {
+    if names.is_empty() {
+        return HashSet::new();
+    }
+
+    let dir = dir.to_path_buf();
+    let input = names.join("\0");
+    tokio::task::spawn_blocking(move || {
+        let Ok(mut child) = Command::new("git")
+            .arg("-C")
+            .arg(&dir)
+            .args(["check-ignore", "--stdin", "-z"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            return HashSet::new();
+        };
+
+        // Write from a separate thread so that a full stdout pipe can't deadlock us
+        if let Some(mut stdin) = child.stdin.take() {
+            std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+        }
+
+        child
+            .wait_with_output()
+            .map(|output| {
+                String::from_utf8_lossy(&output.stdout)
+                    .split('\0')
+                    .filter(|path| !path.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    })
+    .await
+    .unwrap_or_default()
+}
@@ -0,0 +1,165 @@
+//! Frame coalescing for streamed terminal output.
+//!
+//! Responses stream in as many small chunks. Writing and flushing each of them to the terminal
+//! separately causes flicker and burns CPU, so output is instead collected in a [FrameBuffer] and
+//! presented at most once per frame as paced by a [FrameLimiter].
+
+use std::io::{
+    self,
+    Write,
+};
+use std::time::{
+    Duration,
+    Instant,
+};
+
+/// Frame rate cap used when none is configured
+pub const DEFAULT_MAX_FPS: u32 = 60;
+
+/// Output queued for the next frame
+#[derive(Debug, Default)]
+pub struct FrameBuffer {
+    pending: Vec<u8>,
+}
+
+impl FrameBuffer {
+    /// Whether there is output that hasn't been presented yet
+    pub fn is_dirty(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Writes the pending output to `output` with a single flush, leaving the buffer clean.
+    /// Nothing is written if the buffer isn't dirty.
+    pub fn present(&mut self, output: &mut impl Write) -> io::Result<()> {
+        if !self.is_dirty() {
+            return Ok(());
+        }
+
+        output.write_all(&self.pending)?;
+        output.flush()?;
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+impl Write for FrameBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    /// Flushing is deferred until the frame is presented
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Caps how often frames are presented
+#[derive(Debug, Clone)]
+pub struct FrameLimiter {
+    interval: Duration,
+    last_presented: Option<Instant>,
+}
+
+impl FrameLimiter {
+    /// Creates a limiter presenting at most `max_fps` frames per second. A `max_fps` of 0
+    /// disables the limit.
+    pub fn new(max_fps: u32) -> Self {
+        let interval = match max_fps {
+            0 => Duration::ZERO,
+            fps => Duration::from_secs(1) / fps,
+        };
+        Self {
+            interval,
+            last_presented: None,
+        }
+    }
+
+    /// Time left until the next frame can be presented, zero if it's already due
+    pub fn time_until_due(&self, now: Instant) -> Duration {
+        match self.last_presented {
+            Some(last) => (last + self.interval).saturating_duration_since(now),
+            None => Duration::ZERO,
+        }
+    }
+
+    pub fn is_due(&self, now: Instant) -> bool {
+        self.time_until_due(now).is_zero()
+    }
+
+    pub fn mark_presented(&mut self, now: Instant) {
+        self.last_presented = Some(now);
+    }
+}
+
+impl Default for FrameLimiter {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_FPS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Terminal recording what is written to it and how often it is flushed
+    #[derive(Default)]
+    struct Terminal {
+        output: Vec<u8>,
+        flushes: usize,
+    }
+
+    impl Write for Terminal {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushes += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_frame_buffer_coalesces_writes() {
+        let mut frame = FrameBuffer::default();
+        let mut terminal = Terminal::default();
+        assert!(!frame.is_dirty());
+
+        write!(frame, "Hello").unwrap();
+        frame.flush().unwrap();
+        write!(frame, ", world").unwrap();
+        assert!(frame.is_dirty());
+        assert!(terminal.output.is_empty());
+
+        frame.present(&mut terminal).unwrap();
+        assert!(!frame.is_dirty());
+        assert_eq!(terminal.output, b"Hello, world");
+        assert_eq!(terminal.flushes, 1);
+
+        // A clean frame isn't presented
+        frame.present(&mut terminal).unwrap();
+        assert_eq!(terminal.flushes, 1);
+    }
+
+    #[test]
+    fn test_frame_limiter() {
+        let start = Instant::now();
+        let mut limiter = FrameLimiter::new(10);
+        assert!(limiter.is_due(start));
+
+        limiter.mark_presented(start);
+        assert!(!limiter.is_due(start + Duration::from_millis(50)));
+        assert_eq!(
+            limiter.time_until_due(start + Duration::from_millis(40)),
+            Duration::from_millis(60)
+        );
+        assert!(limiter.is_due(start + Duration::from_millis(100)));
+
+        // No limit
+        let mut limiter = FrameLimiter::new(0);
+        limiter.mark_presented(start);
+        assert!(limiter.is_due(start));
+    }
+}
@@ -0,0 +1,162 @@
+//! Benchmarks for the conversation history, which is snapshotted on every turn by checkpoints and
+//! tangent mode. Each benchmark runs twice: once measuring the bytes it allocates, which is what
+//! sharing entries between snapshots saves, and once measuring the time it takes.
+
+use std::alloc::{
+    GlobalAlloc,
+    Layout,
+    System,
+};
+use std::hint::black_box;
+use std::sync::atomic::{
+    AtomicUsize,
+    Ordering,
+};
+
+use chat_cli::cli::chat::history::History;
+use criterion::measurement::{
+    Measurement,
+    ValueFormatter,
+};
+use criterion::{
+    BatchSize,
+    Criterion,
+    Throughput,
+    criterion_group,
+    criterion_main,
+};
+
+/// Number of entries in the benchmarked history, each a prompt and a response
+const HISTORY_LEN: usize = 5000;
+
+/// Total number of bytes allocated since the start of the process
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// The system allocator, counting the bytes it allocates
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATED.fetch_add(new_size.saturating_sub(layout.size()), Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+/// Measures the bytes allocated by the benchmarked routine instead of the time it takes
+struct Allocated;
+
+impl Measurement for Allocated {
+    type Intermediate = usize;
+    type Value = usize;
+
+    fn start(&self) -> usize {
+        ALLOCATED.load(Ordering::SeqCst)
+    }
+
+    fn end(&self, start: usize) -> usize {
+        ALLOCATED.load(Ordering::SeqCst) - start
+    }
+
+    fn add(&self, a: &usize, b: &usize) -> usize {
+        a + b
+    }
+
+    fn zero(&self) -> usize {
+        0
+    }
+
+    fn to_f64(&self, value: &usize) -> f64 {
+        *value as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        &BytesFormatter
+    }
+}
+
+struct BytesFormatter;
+
+impl ValueFormatter for BytesFormatter {
+    fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+        let (factor, unit) = match typical_value {
+            v if v < 1024.0 => (1.0, "B"),
+            v if v < 1024.0 * 1024.0 => (1024.0, "KiB"),
+            _ => (1024.0 * 1024.0, "MiB"),
+        };
+        for value in values {
+            *value /= factor;
+        }
+        unit
+    }
+
+    fn scale_throughputs(&self, typical_value: f64, _throughput: &Throughput, values: &mut [f64]) -> &'static str {
+        self.scale_values(typical_value, values)
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "B"
+    }
+}
+
+fn bench_history<M: Measurement>(c: &mut Criterion<M>, group_name: &str) {
+    let history = History::synthetic(HISTORY_LEN);
+    let mut group = c.benchmark_group(group_name);
+
+    group.bench_function("snapshot", |b| {
+        b.iter(|| black_box(history.clone()));
+    });
+    // What every snapshot cost before entries were shared
+    group.bench_function("snapshot_deep_copy", |b| {
+        b.iter(|| black_box(history.iter().cloned().collect::<Vec<_>>()));
+    });
+    group.bench_function("append_to_snapshot", |b| {
+        let entry = history.back().cloned().unwrap();
+        b.iter_batched(
+            || (history.clone(), entry.clone()),
+            |(mut snapshot, entry)| {
+                snapshot.push_back(entry);
+                snapshot
+            },
+            BatchSize::SmallInput,
+        );
+    });
+    group.bench_function("edit_snapshot_entry", |b| {
+        b.iter_batched(
+            || history.clone(),
+            |mut snapshot| {
+                black_box(snapshot.get_mut(HISTORY_LEN / 2));
+                snapshot
+            },
+            BatchSize::SmallInput,
+        );
+    });
+    group.finish();
+}
+
+fn bench_history_allocated(c: &mut Criterion<Allocated>) {
+    bench_history(c, "history_allocated");
+}
+
+fn bench_history_time(c: &mut Criterion) {
+    bench_history(c, "history");
+}
+
+criterion_group! {
+    name = memory;
+    config = Criterion::default().with_measurement(Allocated);
+    targets = bench_history_allocated
+}
+criterion_group!(time, bench_history_time);
+criterion_main!(memory, time);
@@ -91,6 +91,16 @@ fn main() {
     #[cfg(target_os = "macos")]
     write_plist();
 
+    write_dependencies();
+    println!(
+        "cargo:rustc-env=AMAZON_Q_BUILD_TARGET={}",
+        std::env::var("TARGET").unwrap()
+    );
+    println!(
+        "cargo:rustc-env=AMAZON_Q_BUILD_PROFILE={}",
+        std::env::var("PROFILE").unwrap()
+    );
+
     let outdir = std::env::var("OUT_DIR").unwrap();
 
     let data = serde_json::from_str::<Def>(DEF).unwrap();
@@ -328,6 +338,165 @@ fn main() {
     std::fs::write(format!("{}/mod.rs", outdir), pp).unwrap();
 }
 
+#[derive(Debug, serde::Serialize)]
+struct CompiledPackage {
+    name: String,
+    version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checksum: Option<String>,
+}
+
+/// Writes the packages compiled into this crate's binary to `$OUT_DIR/dependencies.json`, which
+/// `q about --sbom` reports.
+///
+/// The dependency graph is resolved by `cargo metadata` for the target being built. Packages are
+/// followed from this crate through normal dependencies only, so dev and build dependencies, proc
+/// macros, and packages only used on other targets or by other workspace members are left out.
+/// Checksums aren't reported by `cargo metadata`, so they are read from Cargo.lock, line by line
+/// since it only uses a small subset of TOML.
+fn write_dependencies() {
+    use std::collections::{
+        BTreeSet,
+        HashMap,
+    };
+
+    use serde_json::Value;
+
+    let manifest_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+    let lock_path = manifest_dir.join("../../Cargo.lock");
+    println!("cargo:rerun-if-changed={}", lock_path.display());
+    let outdir = std::env::var("OUT_DIR").unwrap();
+    let write = |packages: &[CompiledPackage]| {
+        std::fs::write(
+            format!("{outdir}/dependencies.json"),
+            serde_json::to_string(packages).unwrap(),
+        )
+        .unwrap();
+    };
+
+    let output = std::process::Command::new(std::env::var("CARGO").unwrap())
+        .args([
+            "metadata",
+            "--format-version",
+            "1",
+            "--locked",
+            "--offline",
+            "--filter-platform",
+        ])
+        .arg(std::env::var("TARGET").unwrap())
+        .current_dir(manifest_dir)
+        .output();
+    let metadata = match output {
+        Ok(output) if output.status.success() => serde_json::from_slice::<Value>(&output.stdout).unwrap(),
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            println!(
+                "cargo:warning=the SBOM is empty, cargo metadata failed: {}",
+                stderr.trim()
+            );
+            return write(&[]);
+        },
+        Err(err) => {
+            println!("cargo:warning=the SBOM is empty, cargo metadata failed: {err}");
+            return write(&[]);
+        },
+    };
+
+    let packages = metadata["packages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|package| Some((package["id"].as_str()?, package)))
+        .collect::<HashMap<_, _>>();
+    let nodes = metadata["resolve"]["nodes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|node| Some((node["id"].as_str()?, node)))
+        .collect::<HashMap<_, _>>();
+    let is_proc_macro = |id: &str| {
+        packages.get(id).is_some_and(|package| {
+            package["targets"].as_array().into_iter().flatten().any(|target| {
+                target["kind"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .any(|kind| kind == "proc-macro")
+            })
+        })
+    };
+
+    // The workspace is virtual, so this crate is found among its members rather than as the root
+    let root = metadata["workspace_members"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .find(|id| {
+            packages
+                .get(id)
+                .is_some_and(|package| package["name"] == env!("CARGO_PKG_NAME"))
+        });
+    let mut included = BTreeSet::new();
+    let mut queue = root.into_iter().collect::<Vec<_>>();
+    while let Some(id) = queue.pop() {
+        let Some(node) = nodes.get(id) else {
+            continue;
+        };
+        for dependency in node["deps"].as_array().into_iter().flatten() {
+            let Some(dependency_id) = dependency["pkg"].as_str() else {
+                continue;
+            };
+            // Normal dependencies have no kind, dev and build dependencies aren't in the binary
+            let normal = dependency["dep_kinds"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .any(|kind| kind["kind"].is_null());
+            if normal && !is_proc_macro(dependency_id) && included.insert(dependency_id) {
+                queue.push(dependency_id);
+            }
+        }
+    }
+
+    let lock = std::fs::read_to_string(&lock_path).unwrap_or_default();
+    let mut checksums = HashMap::new();
+    let (mut name, mut version) = (String::new(), String::new());
+    for line in lock.lines() {
+        let Some((key, value)) = line.split_once(" = ") else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"');
+        match key {
+            "name" => name = value.to_string(),
+            "version" => version = value.to_string(),
+            "checksum" => {
+                checksums.insert((name.clone(), version.clone()), value.to_string());
+            },
+            _ => {},
+        }
+    }
+
+    let mut compiled = included
+        .into_iter()
+        .filter_map(|id| packages.get(id))
+        .map(|package| {
+            let name = package["name"].as_str().unwrap_or_default().to_string();
+            let version = package["version"].as_str().unwrap_or_default().to_string();
+            CompiledPackage {
+                source: package["source"].as_str().map(str::to_string),
+                checksum: checksums.get(&(name.clone(), version.clone())).cloned(),
+                name,
+                version,
+            }
+        })
+        .collect::<Vec<_>>();
+    compiled.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    write(&compiled);
+}
+
 /// Downloads the latest feed.json from the autocomplete repository.
 /// This ensures official builds have the most up-to-date changelog information.
 ///
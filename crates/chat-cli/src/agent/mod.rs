@@ -1 +1 @@
-mod rts;
+pub mod rts;
@@ -9,7 +9,16 @@ use std::time::{
     Instant,
 };
 
+use agent::agent_config::definitions::{
+    InferenceParams,
+    ModelConfig,
+};
 use agent::agent_loop::model::Model;
+use agent::agent_loop::model_provider::{
+    ModelProvider,
+    ModelProviderError,
+    ModelProviderRegistry,
+};
 use agent::agent_loop::protocol::StreamResult;
 use agent::agent_loop::types::{
     ContentBlock,
@@ -344,8 +353,12 @@ impl Model for RtsModel {
         messages: Vec<Message>,
         tool_specs: Option<Vec<ToolSpec>>,
         system_prompt: Option<String>,
+        inference_params: InferenceParams,
         cancel_token: CancellationToken,
     ) -> Pin<Box<dyn Stream<Item = StreamResult> + Send + 'static>> {
+        if !inference_params.is_empty() {
+            debug!(%inference_params, "ignoring inference parameters, which the backend doesn't support");
+        }
         let (tx, rx) = mpsc::channel(16);
 
         let self_clone = self.clone();
@@ -359,6 +372,61 @@ impl Model for RtsModel {
 
         Box::pin(ReceiverStream::new(rx))
     }
+
+    fn state(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(RtsModelState {
+            conversation_id: self.conversation_id,
+            model_id: self.model_id.clone(),
+        })
+        .ok()
+    }
+}
+
+/// Name the RTS backend is registered under in a
+/// [ModelProviderRegistry](agent::agent_loop::model_provider::ModelProviderRegistry).
+pub const RTS_PROVIDER_NAME: &str = "rts";
+
+/// Creates [RtsModel]s, each with a new conversation unless resumed.
+#[derive(Debug, Clone)]
+pub struct RtsModelProvider {
+    client: ApiClient,
+}
+
+impl RtsModelProvider {
+    pub fn new(client: ApiClient) -> Self {
+        Self { client }
+    }
+}
+
+impl ModelProvider for RtsModelProvider {
+    fn create(
+        &self,
+        config: &ModelConfig,
+        state: Option<serde_json::Value>,
+    ) -> Result<Arc<dyn Model>, ModelProviderError> {
+        let state = match state {
+            Some(state) => serde_json::from_value(state).map_err(|source| ModelProviderError::InvalidState {
+                provider: RTS_PROVIDER_NAME.to_string(),
+                source,
+            })?,
+            None => RtsModelState {
+                model_id: config.model_id.clone(),
+                ..RtsModelState::new()
+            },
+        };
+        Ok(Arc::new(RtsModel::new(
+            self.client.clone(),
+            state.conversation_id,
+            state.model_id,
+        )))
+    }
+}
+
+/// The model providers agents can select from, with RTS as the default.
+pub fn model_providers(client: ApiClient) -> ModelProviderRegistry {
+    let mut models = ModelProviderRegistry::new();
+    models.register(RTS_PROVIDER_NAME, RtsModelProvider::new(client));
+    models
 }
 
 /// Contains only the serializable data associated with [RtsModel].
@@ -649,6 +717,9 @@ impl ResponseParser {
             RecvError::Timeout { source, duration } => StreamError::new(StreamErrorKind::StreamTimeout { duration })
                 .set_original_request_id(self.request_id.clone())
                 .with_source(Arc::new(source)),
+            RecvError::Other { source } if source.is_disconnect() => StreamError::new(StreamErrorKind::Disconnected)
+                .set_original_request_id(self.request_id.clone())
+                .with_source(Arc::new(source)),
             RecvError::Other { source } => StreamError::new(StreamErrorKind::Other(format!(
                 "An unexpected error occurred during the response stream: {:?}",
                 source
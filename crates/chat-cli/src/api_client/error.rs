@@ -98,6 +98,16 @@ impl ApiClientError {
             Self::GetProfileError(e) => sdk_status_code(e),
         }
     }
+
+    /// Whether the connection of a response stream dropped, such as when the network changed or
+    /// the device slept, rather than the service returning an error.
+    pub fn is_disconnect(&self) -> bool {
+        match self {
+            Self::CodewhispererChatResponseStream(e) => is_transport_error(e),
+            Self::QDeveloperChatResponseStream(e) => is_transport_error(e),
+            _ => false,
+        }
+    }
 }
 
 impl ReasonCode for ApiClientError {
@@ -218,6 +228,13 @@ pub fn sdk_error_code<T: ProvideErrorMetadata, R>(e: &SdkError<T, R>) -> String
         .unwrap_or_else(|| e.to_string())
 }
 
+fn is_transport_error<E, R>(e: &SdkError<E, R>) -> bool {
+    matches!(
+        e,
+        SdkError::DispatchFailure(_) | SdkError::ResponseError(_) | SdkError::TimeoutError(_)
+    )
+}
+
 fn sdk_status_code<E>(e: &SdkError<E, Response>) -> Option<u16> {
     e.raw_response().map(|res| res.status().as_u16())
 }
@@ -307,4 +324,16 @@ mod tests {
             println!("{error} {error:?}");
         }
     }
+
+    #[test]
+    fn test_is_disconnect() {
+        assert!(all_errors().iter().all(|error| !error.is_disconnect()));
+        assert!(
+            ApiClientError::QDeveloperChatResponseStream(SdkError::response_error("connection reset", raw_message()))
+                .is_disconnect()
+        );
+        assert!(
+            ApiClientError::CodewhispererChatResponseStream(SdkError::timeout_error("read timed out")).is_disconnect()
+        );
+    }
 }
@@ -52,6 +52,7 @@ use crate::api_client::delay_interceptor::DelayTrackingInterceptor;
 use crate::api_client::model::{
     ChatResponseStream,
     ConversationState,
+    UserInputMessage,
 };
 use crate::api_client::opt_out::OptOutInterceptor;
 use crate::api_client::send_message_output::SendMessageOutput;
@@ -102,6 +103,8 @@ pub struct ApiClient {
     mock_client: Option<Arc<Mutex<std::vec::IntoIter<Vec<ChatResponseStream>>>>>,
     profile: Option<AuthProfile>,
     model_cache: ModelCache,
+    /// Held while fetching the model list so that concurrent cache misses share one request
+    model_fetch_lock: Arc<tokio::sync::Mutex<()>>,
 }
 
 impl ApiClient {
@@ -142,6 +145,7 @@ impl ApiClient {
                 mock_client: None,
                 profile: None,
                 model_cache: Arc::new(RwLock::new(None)),
+                model_fetch_lock: Default::default(),
             };
 
             if let Some(json) = crate::util::env_var::get_mock_chat_response(env) {
@@ -221,6 +225,7 @@ impl ApiClient {
             mock_client: None,
             profile,
             model_cache: Arc::new(RwLock::new(None)),
+            model_fetch_lock: Default::default(),
         })
     }
 
@@ -318,6 +323,17 @@ impl ApiClient {
             }
         }
 
+        // Callers that miss the cache while another fetch is in flight wait for its result rather
+        // than sending their own request.
+        let _fetching = self.model_fetch_lock.lock().await;
+        {
+            let cache = self.model_cache.read().await;
+            if let Some(cached) = cache.as_ref() {
+                tracing::debug!("Returning model list fetched by a concurrent request");
+                return Ok(cached.clone());
+            }
+        }
+
         tracing::debug!("Cache miss, fetching models from list_available_models API");
         let result = self.list_available_models().await?;
         {
@@ -491,6 +507,27 @@ impl ApiClient {
         }
     }
 
+    /// Sends `content` on its own, without a conversation or history, for one-off requests such as
+    /// generating a title or a command.
+    pub async fn send_prompt(
+        &self,
+        content: String,
+        model_id: Option<String>,
+    ) -> Result<SendMessageOutput, ConverseStreamError> {
+        self.send_message(ConversationState {
+            conversation_id: None,
+            user_input_message: UserInputMessage {
+                content,
+                user_input_message_context: None,
+                user_intent: None,
+                images: None,
+                model_id,
+            },
+            history: None,
+        })
+        .await
+    }
+
     /// Only meant for testing. Do not use outside of testing responses.
     pub fn set_mock_output(&mut self, json: serde_json::Value) {
         let mut mock = Vec::new();
@@ -636,7 +673,6 @@ mod tests {
     use bstr::ByteSlice;
 
     use super::*;
-    use crate::api_client::model::UserInputMessage;
 
     #[tokio::test]
     async fn create_clients() {
@@ -710,6 +746,26 @@ mod tests {
         assert_eq!(output_content, "Hello! How can I assist you today?");
     }
 
+    #[tokio::test]
+    async fn test_send_prompt() {
+        let env = Env::new();
+        let fs = Fs::new();
+        let mut database = crate::database::Database::new().await.unwrap();
+        let mut client = ApiClient::new(&env, &fs, &mut database, None).await.unwrap();
+        client.set_mock_output(serde_json::json!([
+            ["Hello!", " How can I", " assist you today?"],
+            ["Reading it", {"tool_use_id": "1", "name": "fs_read", "args": {"path": "."}}, " now."],
+        ]));
+
+        let mut output = client.send_prompt("Hello".to_string(), None).await.unwrap();
+        assert_eq!(output.recv_text().await.unwrap().as_deref(), Some("Hello!"));
+        assert_eq!(output.text().await.unwrap(), " How can I assist you today?");
+
+        // Tool uses are skipped
+        let output = client.send_prompt("Read it".to_string(), None).await.unwrap();
+        assert_eq!(output.text().await.unwrap(), "Reading it now.");
+    }
+
     #[test]
     fn test_classify_error_kind() {
         use aws_smithy_runtime_api::http::Response;
@@ -32,6 +32,25 @@ impl SendMessageOutput {
             SendMessageOutput::Mock(vec) => Ok(vec.pop()),
         }
     }
+
+    /// Returns the next chunk of the response text, skipping the other events.
+    pub async fn recv_text(&mut self) -> Result<Option<String>, ApiClientError> {
+        while let Some(event) = self.recv().await? {
+            if let ChatResponseStream::AssistantResponseEvent { content } = event {
+                return Ok(Some(content));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reads the rest of the response, returning its text.
+    pub async fn text(mut self) -> Result<String, ApiClientError> {
+        let mut text = String::new();
+        while let Some(content) = self.recv_text().await? {
+            text.push_str(&content);
+        }
+        Ok(text)
+    }
 }
 
 impl RequestId for SendMessageOutput {
@@ -1,3 +1,4 @@
+use std::sync::LazyLock;
 use std::time::Duration;
 
 use aws_smithy_runtime_api::client::http::{
@@ -13,11 +14,19 @@ use aws_smithy_runtime_api::http::Request;
 use aws_smithy_types::body::SdkBody;
 use reqwest::Client as ReqwestClient;
 
-/// Returns a wrapper around the global [fig_request::client] that implements
-/// [HttpClient].
+/// Shared by every SDK client so that connections, and their TLS sessions, are reused across
+/// services and turns instead of each client keeping its own pool.
+static SHARED_CLIENT: LazyLock<ReqwestClient> =
+    LazyLock::new(|| crate::request::new_client().expect("failed to create http client"));
+
+/// Returns a wrapper around the global [reqwest::Client] that implements [HttpClient].
 pub fn client() -> Client {
-    let client = crate::request::new_client().expect("failed to create http client");
-    Client::new(client.clone())
+    if cfg!(test) {
+        // Every test runs its own runtime, and pooled connections can't outlive the runtime that
+        // opened them.
+        return Client::new(crate::request::new_client().expect("failed to create http client"));
+    }
+    Client::new(SHARED_CLIENT.clone())
 }
 
 /// A wrapper around [reqwest::Client] that implements [HttpClient].
@@ -0,0 +1,217 @@
+use std::fmt::Write as _;
+use std::process::ExitCode;
+
+use clap::Args;
+use eyre::Result;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::json;
+use sha2::{
+    Digest,
+    Sha256,
+};
+
+use super::OutputFormat;
+use crate::util::CHAT_BINARY_NAME;
+use crate::util::consts::build;
+
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct AboutArgs {
+    /// Print a CycloneDX software bill of materials listing the packages compiled into the binary
+    #[arg(long)]
+    pub sbom: bool,
+    /// The format of the output
+    #[arg(long, short, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+/// A package compiled into the binary, as written by the build script.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dependency {
+    pub name: String,
+    pub version: String,
+    pub source: Option<String>,
+    pub checksum: Option<String>,
+}
+
+/// What was built and how, for checking which build is installed.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub git_sha: Option<&'static str>,
+    pub build_date: Option<&'static str>,
+    pub target: &'static str,
+    pub profile: &'static str,
+    /// Identity the release was signed with, [None] for unsigned builds
+    pub signing_identity: Option<&'static str>,
+    pub executable: Option<String>,
+    /// SHA-256 of the running executable, to compare with the published checksums
+    pub executable_sha256: Option<String>,
+    pub dependency_count: usize,
+}
+
+impl AboutArgs {
+    pub async fn execute(self) -> Result<ExitCode> {
+        let dependencies = dependencies()?;
+        let info = build_info(&dependencies).await;
+        if self.sbom {
+            let sbom = sbom(&info, &dependencies);
+            match self.format {
+                OutputFormat::Json => println!("{}", serde_json::to_string(&sbom)?),
+                OutputFormat::Plain | OutputFormat::JsonPretty => {
+                    println!("{}", serde_json::to_string_pretty(&sbom)?);
+                },
+            }
+        } else {
+            self.format.print(|| info.user_readable(), || &info);
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+impl BuildInfo {
+    fn user_readable(&self) -> String {
+        let unknown = "unknown";
+        let mut text = String::new();
+        let _ = writeln!(text, "Version:      {}", self.version);
+        let _ = writeln!(text, "Git SHA:      {}", self.git_sha.unwrap_or(unknown));
+        let _ = writeln!(text, "Build date:   {}", self.build_date.unwrap_or(unknown));
+        let _ = writeln!(text, "Target:       {} ({})", self.target, self.profile);
+        let _ = writeln!(
+            text,
+            "Signed by:    {}",
+            self.signing_identity.unwrap_or("not signed at build time")
+        );
+        let _ = writeln!(text, "Executable:   {}", self.executable.as_deref().unwrap_or(unknown));
+        let _ = writeln!(
+            text,
+            "SHA-256:      {}",
+            self.executable_sha256.as_deref().unwrap_or(unknown)
+        );
+        let _ = write!(
+            text,
+            "Dependencies: {} packages, run `{CHAT_BINARY_NAME} about --sbom` to list them",
+            self.dependency_count
+        );
+        text
+    }
+}
+
+fn dependencies() -> Result<Vec<Dependency>> {
+    Ok(serde_json::from_str(build::DEPENDENCIES_JSON)?)
+}
+
+async fn build_info(dependencies: &[Dependency]) -> BuildInfo {
+    let executable = std::env::current_exe().ok();
+    let executable_sha256 = match &executable {
+        Some(path) => tokio::fs::read(path)
+            .await
+            .ok()
+            .map(|bytes| hex::encode(Sha256::digest(bytes))),
+        None => None,
+    };
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_sha: build::HASH,
+        build_date: build::DATETIME,
+        target: build::TARGET,
+        profile: build::PROFILE,
+        signing_identity: build::SIGNING_IDENTITY,
+        executable: executable.map(|path| path.to_string_lossy().to_string()),
+        executable_sha256,
+        dependency_count: dependencies.len(),
+    }
+}
+
+/// Returns a CycloneDX 1.5 SBOM of the binary and its dependencies.
+fn sbom(info: &BuildInfo, dependencies: &[Dependency]) -> serde_json::Value {
+    let properties = [
+        ("gitSha", info.git_sha),
+        ("buildDate", info.build_date),
+        ("target", Some(info.target)),
+        ("profile", Some(info.profile)),
+        ("signingIdentity", info.signing_identity),
+        ("executableSha256", info.executable_sha256.as_deref()),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| Some(json!({ "name": format!("amazonq:{name}"), "value": value? })))
+    .collect::<Vec<_>>();
+
+    let components = dependencies
+        .iter()
+        .map(|dependency| {
+            let purl = format!("pkg:cargo/{}@{}", dependency.name, dependency.version);
+            let mut component = json!({
+                "type": "library",
+                "bom-ref": purl,
+                "name": dependency.name,
+                "version": dependency.version,
+                "purl": purl,
+            });
+            if let Some(checksum) = &dependency.checksum {
+                component["hashes"] = json!([{ "alg": "SHA-256", "content": checksum }]);
+            }
+            if let Some(source) = &dependency.source {
+                component["externalReferences"] =
+                    json!([{ "type": "distribution", "url": source.trim_start_matches("registry+") }]);
+            }
+            component
+        })
+        .collect::<Vec<_>>();
+
+    let mut metadata = json!({
+        "component": {
+            "type": "application",
+            "bom-ref": format!("pkg:cargo/{}@{}", env!("CARGO_PKG_NAME"), info.version),
+            "name": CHAT_BINARY_NAME,
+            "version": info.version,
+            "properties": properties,
+        },
+    });
+    if let Some(date) = info.build_date {
+        metadata["timestamp"] = json!(date);
+    }
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "version": 1,
+        "metadata": metadata,
+        "components": components,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_dependencies() {
+        let dependencies = dependencies().unwrap();
+        assert!(dependencies.iter().any(|d| d.name == "serde"));
+        assert!(!dependencies.iter().any(|d| d.name == env!("CARGO_PKG_NAME")));
+        // Dev and build dependencies and proc macros aren't compiled into the binary
+        for name in ["criterion", "mockito", "prettyplease", "serde_derive"] {
+            assert!(!dependencies.iter().any(|d| d.name == name), "{name} is in the SBOM");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sbom() {
+        let dependencies = vec![Dependency {
+            name: "serde".to_string(),
+            version: "1.0.219".to_string(),
+            source: Some("registry+https://github.com/rust-lang/crates.io-index".to_string()),
+            checksum: Some("abc123".to_string()),
+        }];
+        let info = build_info(&dependencies).await;
+        let sbom = sbom(&info, &dependencies);
+        assert_eq!(sbom["bomFormat"], "CycloneDX");
+        assert_eq!(sbom["metadata"]["component"]["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(sbom["components"][0]["purl"], "pkg:cargo/serde@1.0.219");
+        assert_eq!(sbom["components"][0]["hashes"][0]["content"], "abc123");
+    }
+}
@@ -0,0 +1,197 @@
+use schemars::JsonSchema;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::AgentConfigError;
+use super::hook::HookTrigger;
+
+const DEFAULT_ENTRY: &str = "--- CONTEXT ENTRY BEGIN ---\n{{content}}--- CONTEXT ENTRY END ---\n\n";
+
+const DEFAULT_SUMMARY: &str = "This summary contains ALL relevant information from our previous conversation including \
+tool uses, results, code analysis, and file operations. YOU MUST reference this information when answering questions \
+and explicitly acknowledge specific details from the summary when they're relevant to the current question.\n\n\
+SUMMARY CONTENT:\n{{summary}}\n";
+
+const DEFAULT_FILE: &str = "[{{path}}]\n{{content}}\n";
+
+const DEFAULT_AGENT_PROMPT: &str = "Follow this instruction: {{prompt}}";
+
+const DEFAULT_HOOKS: &str = "This section (like others) contains important information that I want you to use in your \
+responses. I have gathered this context from valuable programmatic script hooks. You must follow any requests and \
+consider all of the information in this section{{scope}}\n\n{{output}}";
+
+const DEFAULT_ACKNOWLEDGEMENT: &str = "I will fully incorporate this information when generating my responses, and \
+explicitly acknowledge relevant parts of the summary when answering questions.";
+
+/// Overrides for the text that frames the context sent at the start of every conversation, such as
+/// the context files, the conversation summary and the agent prompt. Each template must keep its
+/// placeholders so the context it frames is still sent.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextTemplates {
+    /// Wraps each context entry. Must contain `{{content}}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry: Option<String>,
+    /// Introduces the summary of a compacted conversation. Must contain `{{summary}}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// Formats each context file. Must contain `{{path}}` and `{{content}}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Introduces the agent prompt. Must contain `{{prompt}}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_prompt: Option<String>,
+    /// Introduces the output of hooks. Must contain `{{output}}`, and may contain `{{scope}}`,
+    /// which says the context applies to the entire conversation for agentSpawn hooks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<String>,
+    /// The reply to the context message that the model sees as its own. Must not be empty
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acknowledgement: Option<String>,
+}
+
+impl ContextTemplates {
+    /// Checks that every overridden template keeps the placeholders it requires.
+    pub fn validate(&self) -> Result<(), AgentConfigError> {
+        let required: [(&str, &Option<String>, &[&str]); 6] = [
+            ("entry", &self.entry, &["{{content}}"]),
+            ("summary", &self.summary, &["{{summary}}"]),
+            ("file", &self.file, &["{{path}}", "{{content}}"]),
+            ("agentPrompt", &self.agent_prompt, &["{{prompt}}"]),
+            ("hooks", &self.hooks, &["{{output}}"]),
+            ("acknowledgement", &self.acknowledgement, &[]),
+        ];
+        for (name, template, placeholders) in required {
+            let Some(template) = template else {
+                continue;
+            };
+            if template.trim().is_empty() {
+                return Err(AgentConfigError::InvalidContextTemplate {
+                    name: name.to_string(),
+                    reason: "it is empty".to_string(),
+                });
+            }
+            if let Some(placeholder) = placeholders.iter().find(|p| !template.contains(**p)) {
+                return Err(AgentConfigError::InvalidContextTemplate {
+                    name: name.to_string(),
+                    reason: format!("it must contain {placeholder}"),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn render_entry(&self, content: &str) -> String {
+        self.entry
+            .as_deref()
+            .unwrap_or(DEFAULT_ENTRY)
+            .replace("{{content}}", content)
+    }
+
+    pub fn render_summary(&self, summary: &str) -> String {
+        self.summary
+            .as_deref()
+            .unwrap_or(DEFAULT_SUMMARY)
+            .replace("{{summary}}", summary)
+    }
+
+    pub fn render_file(&self, path: &str, content: &str) -> String {
+        // The content is substituted last so placeholders in it are left alone.
+        self.file
+            .as_deref()
+            .unwrap_or(DEFAULT_FILE)
+            .replace("{{path}}", path)
+            .replace("{{content}}", content)
+    }
+
+    pub fn render_agent_prompt(&self, prompt: &str) -> String {
+        self.agent_prompt
+            .as_deref()
+            .unwrap_or(DEFAULT_AGENT_PROMPT)
+            .replace("{{prompt}}", prompt)
+    }
+
+    pub fn render_hooks(&self, trigger: HookTrigger, output: &str) -> String {
+        let scope = match trigger {
+            HookTrigger::AgentSpawn => " for the entire conversation",
+            _ => "",
+        };
+        self.hooks
+            .as_deref()
+            .unwrap_or(DEFAULT_HOOKS)
+            .replace("{{scope}}", scope)
+            .replace("{{output}}", output)
+    }
+
+    pub fn acknowledgement(&self) -> &str {
+        self.acknowledgement.as_deref().unwrap_or(DEFAULT_ACKNOWLEDGEMENT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults() {
+        let templates = ContextTemplates::default();
+        assert!(templates.validate().is_ok());
+        assert_eq!(
+            templates.render_entry("[AGENTS.md]\nbe brief\n"),
+            "--- CONTEXT ENTRY BEGIN ---\n[AGENTS.md]\nbe brief\n--- CONTEXT ENTRY END ---\n\n"
+        );
+        assert_eq!(
+            templates.render_file("AGENTS.md", "be brief"),
+            "[AGENTS.md]\nbe brief\n"
+        );
+        assert_eq!(
+            templates.render_agent_prompt("be brief"),
+            "Follow this instruction: be brief"
+        );
+        assert!(
+            templates
+                .render_hooks(HookTrigger::AgentSpawn, "out")
+                .contains("in this section for the entire conversation\n\nout")
+        );
+        assert!(
+            templates
+                .render_hooks(HookTrigger::UserPromptSubmit, "out")
+                .contains("in this section\n\nout")
+        );
+    }
+
+    #[test]
+    fn test_overrides() {
+        let templates: ContextTemplates = serde_json::from_value(serde_json::json!({
+            "file": "<file path=\"{{path}}\">\n{{content}}\n</file>\n",
+            "acknowledgement": "Understood."
+        }))
+        .unwrap();
+        assert!(templates.validate().is_ok());
+        assert_eq!(
+            templates.render_file("a.md", "uses {{path}}"),
+            "<file path=\"a.md\">\nuses {{path}}\n</file>\n"
+        );
+        assert_eq!(templates.acknowledgement(), "Understood.");
+        assert_eq!(templates.render_agent_prompt("x"), "Follow this instruction: x");
+    }
+
+    #[test]
+    fn test_validate_requires_placeholders() {
+        let templates = ContextTemplates {
+            file: Some("[{{path}}]".to_string()),
+            ..Default::default()
+        };
+        assert!(
+            matches!(templates.validate(), Err(AgentConfigError::InvalidContextTemplate { name, reason }) if name == "file" && reason.contains("{{content}}"))
+        );
+
+        let templates = ContextTemplates {
+            acknowledgement: Some("  ".to_string()),
+            ..Default::default()
+        };
+        assert!(templates.validate().is_err());
+    }
+}
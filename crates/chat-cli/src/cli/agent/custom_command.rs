@@ -0,0 +1,122 @@
+use schemars::JsonSchema;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+const DEFAULT_MAX_OUTPUT_SIZE: usize = 1024 * 10;
+
+/// Placeholder replaced with all of the arguments supplied to the command
+const ARGS_PLACEHOLDER: &str = "{{args}}";
+
+/// A user defined slash command. Invoking `/<name> [args..]` in chat renders the command into a
+/// prompt which is then submitted as if the user had typed it.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomCommand {
+    /// Short description listed next to the command in /help
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Prompt template. `{{args}}` is replaced with all arguments and `{{1}}`, `{{2}}`, ... with
+    /// individual arguments
+    #[serde(default)]
+    pub prompt: Option<String>,
+    /// Shell command to run. Its output becomes the prompt, or is attached as context to the
+    /// rendered `prompt` when both are set. Supports the same placeholders as `prompt`, with the
+    /// arguments shell quoted.
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Max time the command can run before it throws a timeout error
+    #[serde(default = "CustomCommand::default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Max output size of the command before it is truncated
+    #[serde(default = "CustomCommand::default_max_output_size")]
+    pub max_output_size: usize,
+}
+
+impl CustomCommand {
+    fn default_timeout_ms() -> u64 {
+        DEFAULT_TIMEOUT_MS
+    }
+
+    fn default_max_output_size() -> usize {
+        DEFAULT_MAX_OUTPUT_SIZE
+    }
+
+    /// Renders the prompt template with the given arguments
+    pub fn render_prompt(&self, args: &[String]) -> Option<String> {
+        self.prompt
+            .as_ref()
+            .map(|template| render_template(template, args, |arg| arg.to_string()))
+    }
+
+    /// Renders the shell command with the given arguments, quoting each of them
+    pub fn render_command(&self, args: &[String]) -> Option<String> {
+        self.command.as_ref().map(|template| {
+            render_template(template, args, |arg| {
+                shlex::try_quote(arg).map_or_else(|_| arg.to_string(), |quoted| quoted.to_string())
+            })
+        })
+    }
+}
+
+fn render_template(template: &str, args: &[String], escape: impl Fn(&str) -> String) -> String {
+    let mut rendered = template.replace(
+        ARGS_PLACEHOLDER,
+        &args.iter().map(|arg| escape(arg)).collect::<Vec<_>>().join(" "),
+    );
+
+    // Replace in reverse so that e.g. {{10}} is not clobbered by {{1}}
+    for (i, arg) in args.iter().enumerate().rev() {
+        rendered = rendered.replace(&format!("{{{{{}}}}}", i + 1), &escape(arg));
+    }
+
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(prompt: Option<&str>, command: Option<&str>) -> CustomCommand {
+        CustomCommand {
+            description: None,
+            prompt: prompt.map(str::to_string),
+            command: command.map(str::to_string),
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+            max_output_size: DEFAULT_MAX_OUTPUT_SIZE,
+        }
+    }
+
+    #[test]
+    fn test_render_prompt() {
+        let cmd = command(Some("Summarize ticket {{1}} for {{2}}. Raw: {{args}}"), None);
+        let args = vec!["ABC-123".to_string(), "the team".to_string()];
+        assert_eq!(
+            cmd.render_prompt(&args).unwrap(),
+            "Summarize ticket ABC-123 for the team. Raw: ABC-123 the team"
+        );
+        assert!(cmd.render_command(&args).is_none());
+    }
+
+    #[test]
+    fn test_render_command_quotes_args() {
+        let cmd = command(None, Some("./scripts/ticket.sh {{args}}"));
+        let args = vec!["ABC-123".to_string(), "it's; rm -rf /".to_string()];
+        let rendered = cmd.render_command(&args).unwrap();
+        assert_eq!(shlex::split(&rendered).unwrap(), vec![
+            "./scripts/ticket.sh".to_string(),
+            "ABC-123".to_string(),
+            "it's; rm -rf /".to_string()
+        ]);
+    }
+
+    #[test]
+    fn test_deserialize_defaults() {
+        let cmd: CustomCommand = serde_json::from_value(serde_json::json!({ "prompt": "hi" })).unwrap();
+        assert_eq!(cmd.timeout_ms, DEFAULT_TIMEOUT_MS);
+        assert_eq!(cmd.max_output_size, DEFAULT_MAX_OUTPUT_SIZE);
+        assert!(cmd.command.is_none());
+    }
+}
@@ -0,0 +1,224 @@
+use chrono::{
+    DateTime,
+    Utc,
+};
+use schemars::JsonSchema;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use thiserror::Error;
+use tracing::warn;
+
+use super::Agent;
+use crate::cli::chat::tools::Tool;
+use crate::os::Os;
+use crate::util::paths::PathResolver;
+
+/// Outbound hosts that the agent's tools and remote MCP servers may connect to. Tools that reach
+/// the network are denied when their destination isn't listed, and remote MCP servers with a URL
+/// on another host are not started. MCP servers run as local commands can't be restricted.
+///
+/// Every connection checked against the policy is recorded in the egress audit log, see
+/// [EgressRecord].
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EgressPolicy {
+    /// Hosts that can be reached, such as `crates.io`. A leading `*.` matches any subdomain, e.g.
+    /// `*.amazonaws.com`, and `*` matches every host
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum EgressError {
+    #[error("'{0}' is not a valid URL")]
+    InvalidUrl(String),
+    #[error("connecting to {host} is not allowed, add it to egress.allowedHosts in the agent to allow it")]
+    HostNotAllowed { host: String },
+}
+
+impl EgressPolicy {
+    pub fn allows_host(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        self.allowed_hosts.iter().any(|allowed| {
+            let allowed = allowed.trim_end_matches('.').to_ascii_lowercase();
+            if allowed == "*" {
+                return true;
+            }
+            match allowed.strip_prefix("*.") {
+                Some(domain) => host.strip_suffix(domain).is_some_and(|sub| sub.ends_with('.')),
+                None => host == allowed,
+            }
+        })
+    }
+
+    /// Checks that `url` may be connected to.
+    pub fn check_url(&self, url: &str) -> Result<(), EgressError> {
+        let host = url::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .ok_or_else(|| EgressError::InvalidUrl(url.to_string()))?;
+        // IPv6 hosts are bracketed in URLs
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if self.allows_host(host) {
+            Ok(())
+        } else {
+            Err(EgressError::HostNotAllowed { host: host.to_string() })
+        }
+    }
+
+    /// Checks that `source`, a tool or MCP server of `agent`, may connect to `url`, and records
+    /// the outcome in the egress audit log.
+    pub async fn check(&self, os: &Os, agent: &str, source: &str, url: &str) -> Result<(), EgressError> {
+        let result = self.check_url(url);
+        let record = EgressRecord {
+            timestamp: Utc::now(),
+            agent: agent.to_string(),
+            source: source.to_string(),
+            url: url.to_string(),
+            denied: result.as_ref().err().map(ToString::to_string),
+        };
+        if let Err(err) = record.append(os).await {
+            warn!(?err, ?record, "failed to write the egress audit record");
+        }
+        result
+    }
+}
+
+/// Checks the connections that `tool` would make against the egress policy of `agent`, if it has
+/// one. Tools whose connections can't be known ahead of time, like shell commands, aren't checked.
+pub async fn check_tool(os: &Os, agent: &Agent, tool: &Tool) -> Result<(), EgressError> {
+    let Some(policy) = &agent.egress else {
+        return Ok(());
+    };
+    for url in tool.egress_urls() {
+        policy.check(os, &agent.name, &tool.display_name(), &url).await?;
+    }
+    Ok(())
+}
+
+/// A connection checked against an egress policy, written as a line of JSON to the egress audit
+/// log at `~/.aws/amazonq/egress_audit.jsonl`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EgressRecord {
+    pub timestamp: DateTime<Utc>,
+    /// Name of the agent whose policy was applied
+    pub agent: String,
+    /// Name of the tool or MCP server making the connection
+    pub source: String,
+    pub url: String,
+    /// Why the connection was denied, [None] if it was allowed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub denied: Option<String>,
+}
+
+impl EgressRecord {
+    async fn append(&self, os: &Os) -> eyre::Result<()> {
+        let path = PathResolver::new(os).global().egress_audit_log()?;
+        if let Some(parent) = path.parent() {
+            os.fs.create_dir_all(parent).await?;
+        }
+        let mut line = serde_json::to_string(self)?;
+        line.push('\n');
+        os.fs.append_file(&path, line).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::chat::tools::dependencies::{
+        DependencyVersions,
+        Ecosystem,
+    };
+    use crate::cli::chat::tools::use_aws::UseAws;
+
+    fn policy(hosts: &[&str]) -> EgressPolicy {
+        EgressPolicy {
+            allowed_hosts: hosts.iter().map(|h| h.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_allows_host() {
+        let policy = policy(&["crates.io", "*.amazonaws.com"]);
+        assert!(policy.allows_host("crates.io"));
+        assert!(policy.allows_host("Crates.IO."));
+        assert!(policy.allows_host("s3.us-east-1.amazonaws.com"));
+        assert!(!policy.allows_host("amazonaws.com"));
+        assert!(!policy.allows_host("evilamazonaws.com"));
+        assert!(!policy.allows_host("static.crates.io"));
+        assert!(!EgressPolicy::default().allows_host("crates.io"));
+        assert!(self::policy(&["*"]).allows_host("example.com"));
+    }
+
+    #[test]
+    fn test_check_url() {
+        let policy = policy(&["registry.npmjs.org", "::1"]);
+        assert_eq!(policy.check_url("https://registry.npmjs.org/serde/latest"), Ok(()));
+        assert_eq!(policy.check_url("http://[::1]:8080/mcp"), Ok(()));
+        assert_eq!(
+            policy.check_url("https://pypi.org/pypi/requests/json"),
+            Err(EgressError::HostNotAllowed {
+                host: "pypi.org".to_string()
+            })
+        );
+        assert_eq!(
+            policy.check_url("not a url"),
+            Err(EgressError::InvalidUrl("not a url".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_tool() {
+        let os = Os::new().await.unwrap();
+        let mut agent = Agent {
+            name: "restricted".to_string(),
+            egress: Some(policy(&["crates.io"])),
+            ..Default::default()
+        };
+        let versions = Tool::DependencyVersions(DependencyVersions {
+            ecosystem: Ecosystem::Pypi,
+            packages: vec!["requests".to_string()],
+        });
+        let use_aws = Tool::UseAws(UseAws {
+            service_name: "s3".to_string(),
+            operation_name: "list-buckets".to_string(),
+            parameters: None,
+            region: "us-west-2".to_string(),
+            profile_name: None,
+            label: None,
+        });
+        assert!(check_tool(&os, &agent, &versions).await.is_err());
+        assert!(check_tool(&os, &agent, &use_aws).await.is_err());
+
+        agent.egress = Some(policy(&["pypi.org", "*.amazonaws.com"]));
+        assert_eq!(check_tool(&os, &agent, &versions).await, Ok(()));
+        assert_eq!(check_tool(&os, &agent, &use_aws).await, Ok(()));
+
+        let log = PathResolver::new(&os).global().egress_audit_log().unwrap();
+        let records = os
+            .fs
+            .read_to_string(log)
+            .await
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<EgressRecord>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].agent, "restricted");
+        assert_eq!(records[0].source, "dependency_versions");
+        assert_eq!(records[0].url, "https://pypi.org/pypi/requests/json");
+        assert!(
+            records[0]
+                .denied
+                .as_ref()
+                .is_some_and(|reason| reason.contains("pypi.org"))
+        );
+        assert_eq!(records[1].url, "https://s3.us-west-2.amazonaws.com");
+        assert!(records[2..].iter().all(|record| record.denied.is_none()));
+    }
+}
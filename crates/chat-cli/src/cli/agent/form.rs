@@ -0,0 +1,597 @@
+//! Interactive form for editing an agent config, used by `q agent edit`.
+//!
+//! The form edits the JSON of the config rather than an [Agent] so that fields it doesn't know
+//! about are written back untouched. The result is checked against the agent schema before it is
+//! saved.
+
+use std::io::Write;
+use std::path::Path;
+
+use agent::agent_config::schema;
+use crossterm::{
+    queue,
+    style,
+};
+use dialoguer::{
+    Confirm,
+    Input,
+    MultiSelect,
+    Select,
+};
+use eyre::{
+    Result,
+    bail,
+    eyre,
+};
+use schemars::schema_for;
+use serde_json::{
+    Map,
+    Value,
+    json,
+};
+
+use super::Agent;
+use crate::cli::chat::tools::NATIVE_TOOLS;
+use crate::os::Os;
+use crate::theme::StyledText;
+use crate::util::dialoguer_theme;
+
+/// Entry of the tools field standing for every tool
+const ALL_TOOLS: &str = "*";
+/// Entry of the tools field standing for every built-in tool
+const BUILTIN_TOOLS: &str = "@builtin";
+
+const HOOK_TRIGGERS: [&str; 5] = ["agentSpawn", "userPromptSubmit", "preToolUse", "postToolUse", "stop"];
+
+/// The fields of an agent config edited by the form.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgentForm {
+    config: Map<String, Value>,
+}
+
+impl AgentForm {
+    pub fn new(config: Value) -> Result<Self> {
+        match config {
+            Value::Object(config) => Ok(Self { config }),
+            _ => bail!("an agent config must be a JSON object"),
+        }
+    }
+
+    pub fn description(&self) -> &str {
+        self.config
+            .get("description")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+    }
+
+    pub fn set_description(&mut self, description: &str) {
+        match description.trim() {
+            "" => self.config.remove("description"),
+            description => self.config.insert("description".into(), json!(description)),
+        };
+    }
+
+    fn strings(&self, key: &str) -> Vec<String> {
+        self.config
+            .get(key)
+            .and_then(Value::as_array)
+            .map(|values| values.iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn tools(&self) -> Vec<String> {
+        self.strings("tools")
+    }
+
+    /// Tools the form offers to pick from: every tool (`*`), the built-in tools as a whole
+    /// (`@builtin`) or one by one, and every tool of each MCP server.
+    pub fn tool_choices(&self) -> Vec<String> {
+        [ALL_TOOLS, BUILTIN_TOOLS]
+            .into_iter()
+            .chain(NATIVE_TOOLS.iter().copied())
+            .map(str::to_string)
+            .chain(self.mcp_servers().into_iter().map(|name| format!("@{name}")))
+            .collect()
+    }
+
+    /// Whether each of [Self::tool_choices] is selected, including the ones selected through `*`
+    /// or `@builtin`.
+    pub fn tool_choices_checked(&self) -> Vec<bool> {
+        let tools = self.tools();
+        self.tool_choices()
+            .iter()
+            .map(|choice| covers(&tools, choice))
+            .collect()
+    }
+
+    /// Sets the tools picked from [Self::tool_choices]. Tools that can't be picked in the form,
+    /// such as single MCP tools, are kept.
+    ///
+    /// `*` and `@builtin` are only kept while every tool they stand for stays picked. Unpicking one
+    /// of those tools replaces them with the tools that are still picked.
+    pub fn set_tools(&mut self, picked: &[String]) {
+        let choices = self.tool_choices();
+        let previous = self.tools();
+        let is_picked = |tool: &str| picked.iter().any(|p| p == tool);
+        let keeps = |wildcard: &str, covered: &mut dyn Iterator<Item = &String>| {
+            is_picked(wildcard) && (!covers(&previous, wildcard) || covered.all(|tool| is_picked(tool.as_str())))
+        };
+
+        let mut tools = previous
+            .iter()
+            .filter(|tool| !choices.contains(tool))
+            .cloned()
+            .collect::<Vec<_>>();
+        if keeps(ALL_TOOLS, &mut choices.iter().filter(|c| c.as_str() != ALL_TOOLS)) {
+            tools.push(ALL_TOOLS.to_string());
+        } else {
+            let natives = choices
+                .iter()
+                .filter(|c| NATIVE_TOOLS.contains(&c.as_str()))
+                .collect::<Vec<_>>();
+            if keeps(BUILTIN_TOOLS, &mut natives.iter().copied()) {
+                tools.push(BUILTIN_TOOLS.to_string());
+            } else {
+                tools.extend(natives.into_iter().filter(|tool| is_picked(tool.as_str())).cloned());
+            }
+            tools.extend(
+                choices
+                    .iter()
+                    .filter(|c| c.starts_with('@') && c.as_str() != BUILTIN_TOOLS && is_picked(c.as_str()))
+                    .cloned(),
+            );
+        }
+
+        // Tools that are no longer available can't be allowed either
+        let allowed = self
+            .allowed_tools()
+            .into_iter()
+            .filter(|tool| !choices.contains(tool) || covers(&tools, tool))
+            .collect::<Vec<_>>();
+        self.config.insert("tools".into(), json!(tools));
+        self.set_allowed_tools(&allowed);
+    }
+
+    pub fn allowed_tools(&self) -> Vec<String> {
+        self.strings("allowedTools")
+    }
+
+    pub fn set_allowed_tools(&mut self, allowed: &[String]) {
+        self.config.insert("allowedTools".into(), json!(allowed));
+    }
+
+    pub fn mcp_servers(&self) -> Vec<String> {
+        self.config
+            .get("mcpServers")
+            .and_then(Value::as_object)
+            .map(|servers| servers.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn add_mcp_server(&mut self, name: &str, command: &str, args: Vec<String>) {
+        let servers = self
+            .config
+            .entry("mcpServers")
+            .or_insert_with(|| json!({}))
+            .as_object_mut();
+        if let Some(servers) = servers {
+            servers.insert(name.to_string(), json!({ "command": command, "args": args }));
+        }
+    }
+
+    /// Removes a server along with the tools and permissions that refer to it.
+    pub fn remove_mcp_server(&mut self, name: &str) {
+        if let Some(servers) = self.config.get_mut("mcpServers").and_then(Value::as_object_mut) {
+            servers.remove(name);
+        }
+        let prefix = format!("@{name}");
+        let refers_to_server = |tool: &String| *tool == prefix || tool.starts_with(&format!("{prefix}/"));
+        for key in ["tools", "allowedTools"] {
+            if self.config.contains_key(key) {
+                let kept = self
+                    .strings(key)
+                    .into_iter()
+                    .filter(|t| !refers_to_server(t))
+                    .collect::<Vec<_>>();
+                self.config.insert(key.into(), json!(kept));
+            }
+        }
+    }
+
+    /// Returns the hooks as `(trigger, command)` pairs.
+    pub fn hooks(&self) -> Vec<(String, String)> {
+        let Some(hooks) = self.config.get("hooks").and_then(Value::as_object) else {
+            return Vec::new();
+        };
+        hooks
+            .iter()
+            .flat_map(|(trigger, hooks)| {
+                hooks
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|hook| hook.get("command").and_then(Value::as_str))
+                    .map(move |command| (trigger.clone(), command.to_string()))
+            })
+            .collect()
+    }
+
+    pub fn add_hook(&mut self, trigger: &str, command: &str, matcher: Option<&str>) {
+        let mut hook = json!({ "command": command });
+        if let Some(matcher) = matcher {
+            hook["matcher"] = json!(matcher);
+        }
+        let hooks = self.config.entry("hooks").or_insert_with(|| json!({}));
+        if let Some(hooks) = hooks.as_object_mut() {
+            if let Some(list) = hooks.entry(trigger).or_insert_with(|| json!([])).as_array_mut() {
+                list.push(hook);
+            }
+        }
+    }
+
+    pub fn remove_hook(&mut self, trigger: &str, command: &str) {
+        let Some(hooks) = self.config.get_mut("hooks").and_then(Value::as_object_mut) else {
+            return;
+        };
+        if let Some(list) = hooks.get_mut(trigger).and_then(Value::as_array_mut) {
+            if let Some(index) = list
+                .iter()
+                .position(|hook| hook.get("command").and_then(Value::as_str) == Some(command))
+            {
+                list.remove(index);
+            }
+            if list.is_empty() {
+                hooks.remove(trigger);
+            }
+        }
+    }
+
+    pub fn resources(&self) -> Vec<String> {
+        self.strings("resources")
+    }
+
+    /// Adds a file or glob to the context of the agent.
+    pub fn add_resource(&mut self, path: &str) {
+        let resource = match path.starts_with("file://") {
+            true => path.to_string(),
+            false => format!("file://{path}"),
+        };
+        let mut resources = self.resources();
+        if !resources.contains(&resource) {
+            resources.push(resource);
+        }
+        self.config.insert("resources".into(), json!(resources));
+    }
+
+    pub fn remove_resource(&mut self, resource: &str) {
+        let resources = self
+            .resources()
+            .into_iter()
+            .filter(|r| r != resource)
+            .collect::<Vec<_>>();
+        self.config.insert("resources".into(), json!(resources));
+    }
+
+    /// Returns the config as pretty printed JSON, or the problems that keep it from being a valid
+    /// agent config.
+    pub fn to_json(&self) -> Result<String, Vec<String>> {
+        let json = serde_json::to_string_pretty(&self.config).map_err(|e| vec![e.to_string()])?;
+        let schema = serde_json::to_value(schema_for!(Agent)).map_err(|e| vec![e.to_string()])?;
+        let issues = schema::validate(&schema, &json);
+        if !issues.is_empty() {
+            return Err(issues.iter().map(ToString::to_string).collect());
+        }
+        serde_json::from_str::<Agent>(&json).map_err(|e| vec![e.to_string()])?;
+        Ok(json)
+    }
+}
+
+/// Whether `tools`, written like the tools field of an agent, selects the choice `tool`.
+fn covers(tools: &[String], tool: &str) -> bool {
+    tools
+        .iter()
+        .any(|entry| entry == tool || entry == ALL_TOOLS || (entry == BUILTIN_TOOLS && NATIVE_TOOLS.contains(&tool)))
+}
+
+/// Runs the form for the config at `path`, and returns whether it was saved.
+pub async fn run(os: &Os, path: &Path) -> Result<bool> {
+    let content = os.fs.read_to_string(path).await?;
+    let mut form = AgentForm::new(serde_json::from_str(&content)?)?;
+    let mut stderr = std::io::stderr();
+    let theme = dialoguer_theme();
+
+    loop {
+        let items = [
+            format!("Description    {}", preview(form.description())),
+            format!("Tools          {} selected", form.tools().len()),
+            format!("Permissions    {} allowed without asking", form.allowed_tools().len()),
+            format!("MCP servers    {}", form.mcp_servers().len()),
+            format!("Hooks          {}", form.hooks().len()),
+            format!("Resources      {}", form.resources().len()),
+            "Save".to_string(),
+            "Quit without saving".to_string(),
+        ];
+        let Some(choice) = Select::with_theme(&theme)
+            .with_prompt(format!("Editing {}", path.display()))
+            .items(&items)
+            .default(0)
+            .interact_opt()?
+        else {
+            return Ok(false);
+        };
+
+        match choice {
+            0 => {
+                let description: String = Input::with_theme(&theme)
+                    .with_prompt("Description")
+                    .with_initial_text(form.description())
+                    .allow_empty(true)
+                    .interact_text()?;
+                form.set_description(&description);
+            },
+            1 => {
+                let choices = form.tool_choices();
+                let checked = form.tool_choices_checked();
+                if let Some(picked) = MultiSelect::with_theme(&theme)
+                    .with_prompt("Tools the agent can use (Space to toggle, Enter to confirm)")
+                    .items(&choices)
+                    .defaults(&checked)
+                    .interact_opt()?
+                {
+                    let picked = picked.into_iter().map(|i| choices[i].clone()).collect::<Vec<_>>();
+                    form.set_tools(&picked);
+                }
+            },
+            2 => {
+                let tools = form.tools();
+                if tools.is_empty() {
+                    print_note(&mut stderr, "Select the tools of the agent first.")?;
+                    continue;
+                }
+                let allowed = form.allowed_tools();
+                let checked = tools.iter().map(|t| allowed.contains(t)).collect::<Vec<_>>();
+                if let Some(picked) = MultiSelect::with_theme(&theme)
+                    .with_prompt("Tools allowed without asking, the others ask before each use")
+                    .items(&tools)
+                    .defaults(&checked)
+                    .interact_opt()?
+                {
+                    // Keep allowed entries that aren't tools of the agent, such as single MCP tools
+                    let mut allowed = allowed.into_iter().filter(|t| !tools.contains(t)).collect::<Vec<_>>();
+                    allowed.extend(picked.into_iter().map(|i| tools[i].clone()));
+                    form.set_allowed_tools(&allowed);
+                }
+            },
+            3 => {
+                let servers = form.mcp_servers();
+                match pick_action(&theme, "MCP servers", &servers)? {
+                    Action::Add => {
+                        let name: String = Input::with_theme(&theme).with_prompt("Server name").interact_text()?;
+                        let command: String = Input::with_theme(&theme)
+                            .with_prompt("Command that starts the server")
+                            .interact_text()?;
+                        let args: String = Input::with_theme(&theme)
+                            .with_prompt("Arguments")
+                            .allow_empty(true)
+                            .interact_text()?;
+                        let args = shlex::split(&args).ok_or_else(|| eyre!("the arguments have unbalanced quotes"))?;
+                        form.add_mcp_server(&name, &command, args);
+                        if Confirm::with_theme(&theme)
+                            .with_prompt(format!("Let the agent use the tools of {name}?"))
+                            .default(true)
+                            .interact()?
+                        {
+                            let mut tools = form.tools();
+                            tools.push(format!("@{name}"));
+                            form.set_tools(&tools);
+                        }
+                    },
+                    Action::Remove(index) => form.remove_mcp_server(&servers[index]),
+                    Action::Back => {},
+                }
+            },
+            4 => {
+                let hooks = form.hooks();
+                let labels = hooks
+                    .iter()
+                    .map(|(trigger, command)| format!("{trigger}: {command}"))
+                    .collect::<Vec<_>>();
+                match pick_action(&theme, "Hooks", &labels)? {
+                    Action::Add => {
+                        let trigger = Select::with_theme(&theme)
+                            .with_prompt("When the hook runs")
+                            .items(&HOOK_TRIGGERS)
+                            .default(0)
+                            .interact()?;
+                        let trigger = HOOK_TRIGGERS[trigger];
+                        let command: String = Input::with_theme(&theme).with_prompt("Command").interact_text()?;
+                        let matcher = if matches!(trigger, "preToolUse" | "postToolUse") {
+                            let matcher: String = Input::with_theme(&theme)
+                                .with_prompt("Tools the hook runs for, empty for all")
+                                .allow_empty(true)
+                                .interact_text()?;
+                            Some(matcher).filter(|m| !m.is_empty())
+                        } else {
+                            None
+                        };
+                        form.add_hook(trigger, &command, matcher.as_deref());
+                    },
+                    Action::Remove(index) => form.remove_hook(&hooks[index].0, &hooks[index].1),
+                    Action::Back => {},
+                }
+            },
+            5 => {
+                let resources = form.resources();
+                match pick_action(&theme, "Resources", &resources)? {
+                    Action::Add => {
+                        let path: String = Input::with_theme(&theme)
+                            .with_prompt("File or glob to add to the context, e.g. README.md or docs/**/*.md")
+                            .interact_text()?;
+                        form.add_resource(&path);
+                    },
+                    Action::Remove(index) => form.remove_resource(&resources[index]),
+                    Action::Back => {},
+                }
+            },
+            6 => match form.to_json() {
+                Ok(json) => {
+                    os.fs.write(path, json).await?;
+                    return Ok(true);
+                },
+                Err(problems) => {
+                    for problem in problems {
+                        queue!(
+                            stderr,
+                            StyledText::error_fg(),
+                            style::Print("error"),
+                            StyledText::reset(),
+                            style::Print(format!(": {problem}\n")),
+                        )?;
+                    }
+                    print_note(&mut stderr, "The config was not saved, fix the problems above first.")?;
+                },
+            },
+            _ => return Ok(false),
+        }
+    }
+}
+
+enum Action {
+    Add,
+    Remove(usize),
+    Back,
+}
+
+/// Shows the entries of a list section and asks whether to add one, remove one, or go back.
+fn pick_action(theme: &dyn dialoguer::theme::Theme, section: &str, entries: &[String]) -> Result<Action> {
+    let mut items = entries.iter().map(|e| format!("Remove {e}")).collect::<Vec<_>>();
+    items.push("Add".to_string());
+    items.push("Back".to_string());
+    let choice = Select::with_theme(theme)
+        .with_prompt(section)
+        .items(&items)
+        .default(entries.len())
+        .interact_opt()?;
+    Ok(match choice {
+        Some(index) if index < entries.len() => Action::Remove(index),
+        Some(index) if index == entries.len() => Action::Add,
+        _ => Action::Back,
+    })
+}
+
+fn preview(text: &str) -> String {
+    match text.chars().count() {
+        0 => "(none)".to_string(),
+        n if n > 40 => format!("{}…", text.chars().take(40).collect::<String>()),
+        _ => text.to_string(),
+    }
+}
+
+fn print_note(output: &mut impl Write, note: &str) -> Result<()> {
+    queue!(
+        output,
+        StyledText::secondary_fg(),
+        style::Print(format!("{note}\n")),
+        StyledText::reset(),
+    )?;
+    output.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn form() -> AgentForm {
+        AgentForm::new(json!({
+            "name": "reviewer",
+            "tools": ["fs_read", "@git/git_status"],
+            "allowedTools": ["fs_read"],
+            "model": "some-model",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_tools_and_permissions() {
+        let mut form = form();
+        form.set_tools(&["fs_write".to_string()]);
+        // Tools that can't be picked in the form are kept
+        assert_eq!(form.tools(), vec!["@git/git_status", "fs_write"]);
+        assert!(form.allowed_tools().is_empty());
+
+        form.add_mcp_server("git", "git-mcp", vec!["--repo".into(), ".".into()]);
+        assert!(form.tool_choices().contains(&"@git".to_string()));
+        form.remove_mcp_server("git");
+        assert_eq!(form.tools(), vec!["fs_write"]);
+        assert!(form.mcp_servers().is_empty());
+    }
+
+    #[test]
+    fn test_wildcard_tools() {
+        let mut form = form();
+        form.add_mcp_server("git", "git-mcp", vec![]);
+        let choices = form.tool_choices();
+        let all_but = |left_out: &[&str]| {
+            choices
+                .iter()
+                .filter(|c| !left_out.contains(&c.as_str()))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        form.set_tools(&["*".to_string()]);
+        assert_eq!(form.tools(), vec!["@git/git_status", "*"]);
+        assert!(form.tool_choices_checked().iter().all(|checked| *checked));
+
+        // Unpicking a tool while `*` stays picked replaces `*` with the tools still picked
+        form.set_tools(&all_but(&["fs_write"]));
+        let tools = form.tools();
+        assert!(!tools.contains(&"*".to_string()) && !tools.contains(&"fs_write".to_string()));
+        assert!(tools.contains(&"fs_read".to_string()) && tools.contains(&"@git".to_string()));
+        assert!(!tools.contains(&"@builtin".to_string()));
+
+        form.set_tools(&["@builtin".to_string()]);
+        assert_eq!(form.tools(), vec!["@git/git_status", "@builtin"]);
+        let checked = form.tool_choices_checked();
+        let fs_read = choices.iter().position(|c| c == "fs_read").unwrap();
+        let git = choices.iter().position(|c| c == "@git").unwrap();
+        assert!(checked[fs_read] && !checked[git]);
+        assert_eq!(form.allowed_tools(), vec!["fs_read"]);
+
+        // Unpicking `*` while every other tool stays picked selects them one by one
+        form.set_tools(&["*".to_string()]);
+        form.set_tools(&all_but(&["*"]));
+        assert_eq!(form.tools(), vec!["@git/git_status", "@builtin", "@git"]);
+    }
+
+    #[test]
+    fn test_hooks_and_resources() {
+        let mut form = form();
+        form.add_hook("preToolUse", "./check.sh", Some("fs_write"));
+        form.add_hook("stop", "cargo fmt", None);
+        assert_eq!(form.hooks().len(), 2);
+        form.remove_hook("stop", "cargo fmt");
+        assert_eq!(form.hooks(), vec![("preToolUse".to_string(), "./check.sh".to_string())]);
+
+        form.add_resource("README.md");
+        form.add_resource("file://README.md");
+        assert_eq!(form.resources(), vec!["file://README.md"]);
+        form.remove_resource("file://README.md");
+        assert!(form.resources().is_empty());
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut form = form();
+        form.set_description("Reviews changes");
+        let json = form.to_json().unwrap();
+        let value: Value = serde_json::from_str(&json).unwrap();
+        // Fields the form doesn't edit are written back
+        assert_eq!(value["model"], "some-model");
+        assert_eq!(value["description"], "Reviews changes");
+
+        form.config.insert("tools".into(), json!("fs_read"));
+        assert!(form.to_json().is_err());
+    }
+}
@@ -1,3 +1,7 @@
+pub mod context_templates;
+pub mod custom_command;
+pub mod egress;
+mod form;
 pub mod hook;
 mod legacy;
 mod mcp_config;
@@ -48,6 +52,7 @@ pub use wrapper_types::{
     OriginalToolName,
     ToolSettingTarget,
     alias_schema,
+    description_schema,
     tool_settings_schema,
 };
 
@@ -56,6 +61,9 @@ use super::chat::tools::{
     NATIVE_TOOLS,
     ToolOrigin,
 };
+use crate::cli::agent::context_templates::ContextTemplates;
+use crate::cli::agent::custom_command::CustomCommand;
+use crate::cli::agent::egress::EgressPolicy;
 use crate::cli::agent::hook::{
     Hook,
     HookTrigger,
@@ -69,6 +77,7 @@ use crate::util::{
     MCP_SERVER_TOOL_DELIMITER,
     file_uri,
     paths,
+    secrets,
 };
 
 pub const DEFAULT_AGENT_NAME: &str = "q_cli_default";
@@ -100,6 +109,8 @@ pub enum AgentConfigError {
     },
     #[error("Invalid file URI format: {uri}")]
     InvalidFileUri { uri: String },
+    #[error("Context template {name} is invalid: {reason}")]
+    InvalidContextTemplate { name: String, reason: String },
 }
 
 /// An [Agent] is a declarative way of configuring a given instance of q chat. Currently, it is
@@ -152,6 +163,14 @@ pub struct Agent {
     #[serde(default)]
     #[schemars(schema_with = "alias_schema")]
     pub tool_aliases: HashMap<OriginalToolName, String>,
+    /// Descriptions that replace the ones mcp server tools provide, keyed like tool aliases
+    #[serde(default)]
+    #[schemars(schema_with = "description_schema")]
+    pub tool_descriptions: HashMap<OriginalToolName, String>,
+    /// Named sets of tools that `/tools use` switches to mid-conversation, written like the tools
+    /// field
+    #[serde(default)]
+    pub tool_sets: HashMap<String, Vec<String>>,
     /// List of tools the agent is explicitly allowed to use
     #[serde(default)]
     pub allowed_tools: HashSet<String>,
@@ -161,6 +180,29 @@ pub struct Agent {
     /// Commands to run when a chat session is created
     #[serde(default)]
     pub hooks: HashMap<HookTrigger, Vec<Hook>>,
+    /// User defined slash commands, keyed by the command name without the leading slash
+    #[serde(default)]
+    pub commands: HashMap<String, CustomCommand>,
+    /// Environment variables passed to shell commands, hooks, and MCP servers. Values can
+    /// reference the environment of the chat process with `${VAR}`
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Overrides for the text that frames context files, the conversation summary, hook output and
+    /// the agent prompt in the context sent to the model
+    #[serde(default)]
+    pub context_templates: ContextTemplates,
+    /// Outbound hosts that tools and remote MCP servers may connect to. Every host can be reached
+    /// if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub egress: Option<EgressPolicy>,
+    /// Rough number of tokens the model may generate while answering a prompt, tool uses included.
+    /// The model is told about it, and is asked to answer once it is spent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_budget_tokens: Option<u32>,
+    /// Rounds of tool uses the model may make while answering a prompt. The model is told about
+    /// it, and is asked to answer instead of running more tools once it is spent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tool_iterations: Option<u32>,
     /// Settings for specific tools. These are mostly for native tools. The actual schema differs by
     /// tools and is documented in detail in our documentation
     #[serde(default)]
@@ -188,6 +230,8 @@ impl Default for Agent {
             mcp_servers: Default::default(),
             tools: vec!["*".to_string()],
             tool_aliases: Default::default(),
+            tool_descriptions: Default::default(),
+            tool_sets: Default::default(),
             allowed_tools: {
                 let mut set = HashSet::<String>::new();
                 let default_approve = DEFAULT_APPROVE.iter().copied().map(str::to_string);
@@ -201,6 +245,12 @@ impl Default for Agent {
                 resources
             },
             hooks: Default::default(),
+            commands: Default::default(),
+            env: Default::default(),
+            context_templates: Default::default(),
+            egress: None,
+            response_budget_tokens: None,
+            max_tool_iterations: None,
             tools_settings: Default::default(),
             use_legacy_mcp_json: true,
             model: None,
@@ -231,6 +281,7 @@ impl Agent {
         output: &mut impl Write,
     ) -> Result<(), AgentConfigError> {
         self.path = Some(path.to_path_buf());
+        self.context_templates.validate()?;
 
         // Resolve file:// URIs in the prompt field
         if let Some(resolved_prompt) = self.resolve_prompt()? {
@@ -297,6 +348,12 @@ impl Agent {
         Ok(serde_json::to_string_pretty(&agent_clone)?)
     }
 
+    /// The [Agent::env] variables with `${VAR}` references resolved against the environment of
+    /// the chat process
+    pub fn resolved_env(&self, os: &Os) -> HashMap<String, String> {
+        resolve_env(&self.env, os)
+    }
+
     /// Resolves the prompt field, handling file:// URIs if present.
     /// Returns the prompt content as-is if it doesn't start with file://,
     /// or resolves the file URI and returns the file content.
@@ -421,6 +478,11 @@ impl Agent {
         // Remove MCP references from other fields
         self.allowed_tools.retain(|tool| !is_mcp_tool_ref(tool));
         self.tool_aliases.retain(|orig, _| !is_mcp_tool_ref(&orig.to_string()));
+        self.tool_descriptions
+            .retain(|orig, _| !is_mcp_tool_ref(&orig.to_string()));
+        for tools in self.tool_sets.values_mut() {
+            tools.retain(|tool| !is_mcp_tool_ref(tool));
+        }
         self.tools_settings
             .retain(|target, _| !is_mcp_tool_ref(&target.to_string()));
     }
@@ -853,6 +915,10 @@ impl Agents {
     fn default_permission_label(&self, tool_name: &str) -> String {
         let label = match tool_name {
             "fs_read" => "trust working directory".dark_grey(),
+            "data_preview" | "archive_list" | "archive_read_member" | "dependency_list" => {
+                "trust working directory".dark_grey()
+            },
+            "dependency_versions" => "not trusted".dark_grey(),
             "fs_write" => "not trusted".dark_grey(),
             #[cfg(not(windows))]
             "execute_bash" => "not trusted".dark_grey(),
@@ -861,6 +927,7 @@ impl Agents {
             "use_aws" => "trust read-only commands".dark_grey(),
             "report_issue" => "trusted".dark_green().bold(),
             "introspect" => "trusted".dark_green().bold(),
+            "kb_search" => "trusted".dark_green().bold(),
             "thinking" => "trusted (prerelease)".dark_green().bold(),
             "todo_list" => "trusted".dark_green().bold(),
             _ if self.trust_all_tools => "trusted".dark_grey().bold(),
@@ -871,6 +938,21 @@ impl Agents {
     }
 }
 
+/// Resolves `${VAR}` references in the values of `env` against the environment of the chat
+/// process, and `${secret:name}` references against the secrets set with `q secrets set`.
+/// References to variables or secrets that are not set are left as is.
+pub fn resolve_env(env: &HashMap<String, String>, os: &Os) -> HashMap<String, String> {
+    let mut env = env
+        .iter()
+        .map(|(name, value)| {
+            let value = shellexpand::env_with_context_no_errors(value, |var| os.env.get(var).ok());
+            (name.clone(), value.into_owned())
+        })
+        .collect();
+    secrets::interpolate_values(&os.database, &mut env);
+    env
+}
+
 /// Metadata from the executed [Agents::load] operation.
 #[derive(Debug, Clone, Default)]
 pub struct AgentsLoadMetadata {
@@ -1286,10 +1368,18 @@ mod tests {
             mcp_servers: Default::default(),
             tools: Vec::new(),
             tool_aliases: Default::default(),
+            tool_descriptions: Default::default(),
+            tool_sets: Default::default(),
             allowed_tools,
             tools_settings: Default::default(),
             resources: Vec::new(),
             hooks: Default::default(),
+            commands: Default::default(),
+            env: Default::default(),
+            context_templates: Default::default(),
+            egress: None,
+            response_budget_tokens: None,
+            max_tool_iterations: None,
             use_legacy_mcp_json: false,
             model: None,
             path: None,
@@ -1387,6 +1477,34 @@ mod tests {
         assert!(serialized.contains("\"model\":\"test-model\""));
     }
 
+    #[tokio::test]
+    async fn test_agent_resolved_env() {
+        let os = Os::new().await.unwrap();
+        unsafe {
+            os.env.set_var("AGENT_ENV_TEST_TOKEN", "secret");
+        }
+        crate::util::secrets::set(&os.database, "gh", "ghp_stored").unwrap();
+
+        let agent: Agent = serde_json::from_str(
+            r#"{
+                "name": "test-agent",
+                "env": {
+                    "PLAIN": "value",
+                    "TOKEN": "${AGENT_ENV_TEST_TOKEN}",
+                    "MISSING": "prefix-${AGENT_ENV_TEST_MISSING}",
+                    "STORED": "${secret:gh}"
+                }
+            }"#,
+        )
+        .expect("Failed to deserialize agent with env");
+
+        let env = agent.resolved_env(&os);
+        assert_eq!(env.get("PLAIN").unwrap(), "value");
+        assert_eq!(env.get("TOKEN").unwrap(), "secret");
+        assert_eq!(env.get("MISSING").unwrap(), "prefix-${AGENT_ENV_TEST_MISSING}");
+        assert_eq!(env.get("STORED").unwrap(), "ghp_stored");
+    }
+
     #[test]
     fn test_agent_model_fallback_priority() {
         // Test that agent model is checked and falls back correctly
@@ -1,7 +1,11 @@
-use std::io::Write;
+use std::io::{
+    IsTerminal,
+    Write,
+};
 use std::path::PathBuf;
 use std::process::ExitCode;
 
+use agent::agent_config::schema;
 use clap::{
     Args,
     Subcommand,
@@ -20,6 +24,7 @@ use super::{
     Agent,
     Agents,
     McpServerConfig,
+    form,
     legacy,
 };
 use crate::database::settings::Setting;
@@ -47,17 +52,32 @@ pub enum AgentSubcommands {
         #[arg(long, short)]
         from: Option<String>,
     },
-    /// Edit an existing agent config
+    /// Edit an existing agent config in $EDITOR, or with a form for picking tools, permissions, MCP
+    /// servers, hooks and resources
     Edit {
         /// Name of the agent to edit
-        #[arg(long, short)]
-        name: String,
+        #[arg(required_unless_present = "name_flag", conflicts_with = "name_flag")]
+        name: Option<String>,
+        /// Same as the positional name, kept for compatibility
+        #[arg(long = "name", short, hide = true)]
+        name_flag: Option<String>,
+        /// Edit the config with a form instead of $EDITOR
+        #[arg(long)]
+        form: bool,
     },
-    /// Validate a config with the given path
+    /// Validate an agent config against the agent JSON schema, reporting the line and column of
+    /// each problem
     Validate {
-        #[arg(long, short)]
-        path: String,
+        /// Path of the agent config
+        #[arg(required_unless_present = "path_flag", conflicts_with = "path_flag")]
+        path: Option<String>,
+        /// Same as the positional path, kept for compatibility
+        #[arg(long = "path", short, hide = true)]
+        path_flag: Option<String>,
     },
+    /// Print the JSON schema of agent configs. Point "$schema" in a config at it to get completion
+    /// and validation in editors
+    Schema,
     /// Migrate profiles to agent
     /// Note that doing this is potentially destructive to agents that are already in the global
     /// agent directories
@@ -140,11 +160,19 @@ impl AgentArgs {
                     path_with_file_name.display()
                 )?;
             },
-            Some(AgentSubcommands::Edit { name }) => {
+            Some(AgentSubcommands::Edit { name, name_flag, form }) => {
+                let name = name.or(name_flag).unwrap_or_default();
                 let _agents = Agents::load(os, None, true, &mut stderr, mcp_enabled).await.0;
                 let (_agent, path_with_file_name) = Agent::get_agent_by_name(os, &name).await?;
 
-                crate::util::editor::launch_editor(&path_with_file_name)?;
+                if !form {
+                    crate::util::editor::launch_editor(&path_with_file_name)?;
+                } else if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+                    bail!("The form can only be used in a terminal");
+                } else if !form::run(os, &path_with_file_name).await? {
+                    writeln!(stderr, "\nNo changes saved to agent {name}\n")?;
+                    return Ok(ExitCode::SUCCESS);
+                }
 
                 let Ok(content) = os.fs.read(&path_with_file_name).await else {
                     bail!(
@@ -166,72 +194,55 @@ impl AgentArgs {
                     path_with_file_name.display()
                 )?;
             },
-            Some(AgentSubcommands::Validate { path }) => {
-                let mut global_mcp_config = None::<McpServerConfig>;
-                let agent = Agent::load(os, path.as_str(), &mut global_mcp_config, mcp_enabled, &mut stderr).await;
-
-                'validate: {
-                    match agent {
-                        Ok(agent) => {
-                            let Ok(instance) = serde_json::to_value(&agent) else {
-                                queue!(
-                                    stderr,
-                                    StyledText::error_fg(),
-                                    style::Print("Error: "),
-                                    StyledText::reset(),
-                                    style::Print("failed to obtain value from agent provided. Aborting validation"),
-                                )?;
-                                break 'validate;
-                            };
+            Some(AgentSubcommands::Validate { path, path_flag }) => {
+                let path = path.or(path_flag).unwrap_or_default();
+                let content = match os.fs.read_to_string(&path).await {
+                    Ok(content) => content,
+                    Err(e) => bail!("Failed to read {path}: {e}"),
+                };
 
-                            let schema = match serde_json::to_value(schema_for!(Agent)) {
-                                Ok(schema) => schema,
-                                Err(e) => {
-                                    queue!(
-                                        stderr,
-                                        StyledText::error_fg(),
-                                        style::Print("Error: "),
-                                        StyledText::reset(),
-                                        style::Print(format!("failed to obtain schema: {e}. Aborting validation"))
-                                    )?;
-                                    break 'validate;
-                                },
-                            };
+                let schema = serde_json::to_value(schema_for!(Agent))?;
+                let issues = schema::validate(&schema, &content);
+                for issue in &issues {
+                    queue!(
+                        stderr,
+                        StyledText::error_fg(),
+                        style::Print("error"),
+                        StyledText::reset(),
+                        style::Print(format!(": {path}:{issue}\n")),
+                    )?;
+                }
+                if !issues.is_empty() {
+                    stderr.flush()?;
+                    return Ok(ExitCode::FAILURE);
+                }
 
-                            if let Err(e) = jsonschema::validate(&schema, &instance).map_err(|e| e.to_owned()) {
-                                let name = &agent.name;
-                                queue!(
-                                    stderr,
-                                    StyledText::warning_fg(),
-                                    style::Print("WARNING "),
-                                    StyledText::reset(),
-                                    style::Print("Agent config "),
-                                    StyledText::success_fg(),
-                                    style::Print(name),
-                                    StyledText::reset(),
-                                    style::Print(" is malformed at "),
-                                    StyledText::warning_fg(),
-                                    style::Print(&e.instance_path),
-                                    StyledText::reset(),
-                                    style::Print(format!(": {e}\n")),
-                                )?;
-                            }
-                        },
-                        Err(e) => {
-                            let _ = queue!(
-                                stderr,
-                                StyledText::error_fg(),
-                                style::Print("Error: "),
-                                StyledText::reset(),
-                                style::Print(e),
-                                style::Print("\n"),
-                            );
-                        },
-                    }
+                // Some problems, such as a missing prompt file, are only found when loading the agent
+                let mut global_mcp_config = None::<McpServerConfig>;
+                if let Err(e) = Agent::load(os, &path, &mut global_mcp_config, mcp_enabled, &mut stderr).await {
+                    queue!(
+                        stderr,
+                        StyledText::error_fg(),
+                        style::Print("error"),
+                        StyledText::reset(),
+                        style::Print(format!(": {path}: {e}\n")),
+                    )?;
+                    stderr.flush()?;
+                    return Ok(ExitCode::FAILURE);
                 }
 
+                queue!(
+                    stderr,
+                    StyledText::success_fg(),
+                    style::Print("✓ "),
+                    StyledText::reset(),
+                    style::Print(format!("{path} is a valid agent config\n")),
+                )?;
                 stderr.flush()?;
             },
+            Some(AgentSubcommands::Schema) => {
+                println!("{}", serde_json::to_string_pretty(&schema_for!(Agent))?);
+            },
             Some(AgentSubcommands::Migrate { force }) => {
                 if !force {
                     let _ = queue!(
@@ -415,21 +426,57 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_agent_subcommand_validate() {
+        assert_parse!(
+            ["agent", "validate", "my_agent.json"],
+            RootSubcommand::Agent(AgentArgs {
+                cmd: Some(AgentSubcommands::Validate {
+                    path: Some("my_agent.json".to_string()),
+                    path_flag: None,
+                })
+            })
+        );
+        assert_parse!(
+            ["agent", "validate", "--path", "my_agent.json"],
+            RootSubcommand::Agent(AgentArgs {
+                cmd: Some(AgentSubcommands::Validate {
+                    path: None,
+                    path_flag: Some("my_agent.json".to_string()),
+                })
+            })
+        );
+    }
+
     #[test]
     fn test_agent_subcommand_edit() {
+        assert_parse!(
+            ["agent", "edit", "existing_agent"],
+            RootSubcommand::Agent(AgentArgs {
+                cmd: Some(AgentSubcommands::Edit {
+                    name: Some("existing_agent".to_string()),
+                    name_flag: None,
+                    form: false,
+                })
+            })
+        );
         assert_parse!(
             ["agent", "edit", "--name", "existing_agent"],
             RootSubcommand::Agent(AgentArgs {
                 cmd: Some(AgentSubcommands::Edit {
-                    name: "existing_agent".to_string(),
+                    name: None,
+                    name_flag: Some("existing_agent".to_string()),
+                    form: false,
                 })
             })
         );
         assert_parse!(
-            ["agent", "edit", "-n", "existing_agent"],
+            ["agent", "edit", "-n", "existing_agent", "--form"],
             RootSubcommand::Agent(AgentArgs {
                 cmd: Some(AgentSubcommands::Edit {
-                    name: "existing_agent".to_string(),
+                    name: None,
+                    name_flag: Some("existing_agent".to_string()),
+                    form: true,
                 })
             })
         );
@@ -51,6 +51,26 @@ pub fn alias_schema(generator: &mut SchemaGenerator) -> Schema {
     })
 }
 
+pub fn description_schema(generator: &mut SchemaGenerator) -> Schema {
+    let key_schema = generator.subschema_for::<OriginalToolName>();
+    let key_description = key_schema
+        .get("description")
+        .and_then(|v| v.as_str())
+        .unwrap_or("Tool whose description is replaced. Tools in mcp servers are prefixed with their server names");
+
+    json_schema!({
+        "type": "object",
+        "additionalProperties": {
+            "type": "string",
+            "description": "The description the model sees instead of the one the tool provides"
+        },
+        "propertyNames": {
+            "type": "string",
+            "description": key_description
+        }
+    })
+}
+
 /// The name of the tool to be configured
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, Hash, PartialEq, JsonSchema)]
 pub struct ToolSettingTarget(pub String);
@@ -0,0 +1,131 @@
+use std::io::{
+    IsTerminal,
+    Read,
+    Write,
+};
+use std::process::ExitCode;
+
+use clap::Args;
+use eyre::{
+    Result,
+    bail,
+};
+
+use crate::database::settings::Setting;
+use crate::os::Os;
+
+/// Most of the piped input sent with the question, in bytes
+const MAX_INPUT_BYTES: usize = 50_000;
+
+/// Answers a question with a single request, without tools, context files or a conversation, and
+/// streams the answer to stdout.
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct AskArgs {
+    /// The question, e.g. "which tar flag lists the contents of an archive"
+    pub question: Vec<String>,
+    /// Model to answer with, defaults to the chat.defaultModel setting
+    #[arg(long)]
+    pub model: Option<String>,
+}
+
+impl AskArgs {
+    pub async fn execute(self, os: &Os) -> Result<ExitCode> {
+        let question = self.question.join(" ");
+        // Piped input is sent along with the question, e.g. `cargo build 2>&1 | q ask why`
+        let mut input = String::new();
+        if !std::io::stdin().is_terminal() {
+            std::io::stdin().read_to_string(&mut input)?;
+        }
+        if question.trim().is_empty() && input.trim().is_empty() {
+            bail!("Ask a question, e.g. `ask which tar flag lists the contents of an archive`");
+        }
+
+        let model_id = self
+            .model
+            .or_else(|| os.database.settings.get_string(Setting::ChatDefaultModel));
+        let shell = os
+            .env
+            .get("SHELL")
+            .ok()
+            .and_then(|shell| shell.rsplit('/').next().map(str::to_string));
+        let content = build_prompt(question.trim(), &input, shell.as_deref());
+
+        stream_answer(os, content, model_id, &mut std::io::stdout()).await?;
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+fn build_prompt(question: &str, input: &str, shell: Option<&str>) -> String {
+    let mut prompt = format!(
+        "Answer the question below as tersely as you can. Lead with the answer itself, such as the command, \
+        flag or value, and add at most one short sentence of explanation. Use a code block only for commands \
+        or code. The user is on {}{}.\n\n",
+        std::env::consts::OS,
+        shell.map(|shell| format!(" with {shell}")).unwrap_or_default(),
+    );
+    if !input.trim().is_empty() {
+        let mut end = input.len().min(MAX_INPUT_BYTES);
+        while !input.is_char_boundary(end) {
+            end -= 1;
+        }
+        prompt.push_str(&format!("<input>\n{}\n</input>\n\n", input[..end].trim_end()));
+    }
+    match question.is_empty() {
+        true => prompt.push_str("Question: explain the input"),
+        false => prompt.push_str(&format!("Question: {question}")),
+    }
+    prompt
+}
+
+/// Sends `content` and writes the answer to `output` as it streams in.
+pub async fn stream_answer(os: &Os, content: String, model_id: Option<String>, output: &mut impl Write) -> Result<()> {
+    let mut response = os.client.send_prompt(content, model_id).await?;
+
+    let mut ends_with_newline = true;
+    while let Some(content) = response.recv_text().await? {
+        // Skip the blank lines the answer may start with
+        let content = match ends_with_newline {
+            true => content.trim_start_matches('\n'),
+            false => &content,
+        };
+        if content.is_empty() {
+            continue;
+        }
+        write!(output, "{content}")?;
+        output.flush()?;
+        ends_with_newline = content.ends_with('\n');
+    }
+    if !ends_with_newline {
+        writeln!(output)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_prompt() {
+        let prompt = build_prompt("which tar flag lists an archive", "", Some("zsh"));
+        assert!(prompt.contains(" with zsh."));
+        assert!(prompt.ends_with("Question: which tar flag lists an archive"));
+        assert!(!prompt.contains("<input>"));
+
+        let prompt = build_prompt("", "error[E0308]: mismatched types\n", None);
+        assert!(prompt.contains("<input>\nerror[E0308]: mismatched types\n</input>"));
+        assert!(prompt.ends_with("Question: explain the input"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_answer() {
+        let mut os = Os::new().await.unwrap();
+        os.client
+            .set_mock_output(serde_json::json!([["\n", "`tar -tf archive.tar`"]]));
+        let mut output = Vec::new();
+        stream_answer(&os, "which tar flag lists an archive".to_string(), None, &mut output)
+            .await
+            .unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "`tar -tf archive.tar`\n");
+    }
+}
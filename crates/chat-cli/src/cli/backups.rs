@@ -0,0 +1,88 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Subcommand;
+use eyre::Result;
+
+use crate::os::Os;
+use crate::util::backups;
+
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum BackupsSubcommand {
+    /// List the backups taken before the agent overwrote files
+    List {
+        /// Only list the backups of this conversation
+        #[arg(long)]
+        conversation: Option<String>,
+    },
+    /// Put the files of a backup back where they were
+    Restore {
+        /// Id of the backup, as shown by `list`
+        id: String,
+        /// Only restore this file
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Remove old backups, by default those over the limits in the backups.* settings
+    Prune {
+        /// Remove the backups older than this many days
+        #[arg(long)]
+        max_age_days: Option<usize>,
+        /// Remove the oldest backups until all of them take at most this many megabytes
+        #[arg(long)]
+        max_size_mb: Option<usize>,
+    },
+}
+
+impl BackupsSubcommand {
+    pub async fn execute(self, os: &Os) -> Result<ExitCode> {
+        let mut stdout = std::io::stdout();
+        let mut stderr = std::io::stderr();
+        match self {
+            Self::List { conversation } => {
+                let backups = backups::list(os, conversation.as_deref()).await?;
+                if backups.is_empty() {
+                    writeln!(stderr, "No backups found")?;
+                }
+                for backup in backups {
+                    writeln!(stdout, "{}  {}", backup.id, backup.manifest.created_at)?;
+                    for entry in &backup.manifest.files {
+                        let note = if entry.existed { "" } else { " (created)" };
+                        writeln!(stdout, "    {}{note}", entry.path.display())?;
+                    }
+                }
+            },
+            Self::Restore { id, file } => {
+                let file = match file {
+                    Some(file) if file.is_relative() => Some(os.env.current_dir()?.join(file)),
+                    file => file,
+                };
+                let backup = backups::find(os, &id).await?;
+                for path in backups::restore(os, &backup, file.as_deref()).await? {
+                    writeln!(stderr, "Restored {}", path.display())?;
+                }
+            },
+            Self::Prune {
+                max_age_days,
+                max_size_mb,
+            } => {
+                let retention = match (max_age_days, max_size_mb) {
+                    (None, None) => backups::Retention::from_settings(os),
+                    (max_age_days, max_size_mb) => {
+                        backups::Retention::new(max_age_days.unwrap_or(0), max_size_mb.unwrap_or(0))
+                    },
+                };
+                let removed = backups::prune(os, &retention).await?;
+                if removed.is_empty() {
+                    writeln!(stderr, "No backups to remove")?;
+                }
+                for backup in removed {
+                    writeln!(stderr, "Removed {}", backup.id)?;
+                }
+            },
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
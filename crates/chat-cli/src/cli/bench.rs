@@ -0,0 +1,128 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use agent::bench::{
+    BenchResult,
+    CONVERSATION_LEN,
+};
+use clap::Args;
+use eyre::{
+    Result,
+    WrapErr,
+};
+
+use super::OutputFormat;
+
+#[derive(Debug, Clone, PartialEq, Args)]
+pub struct BenchArgs {
+    /// Times each benchmark runs
+    #[arg(long, default_value_t = 50, value_parser = clap::value_parser!(u32).range(1..))]
+    pub iterations: u32,
+    /// Number of messages in the benchmarked conversation
+    #[arg(long, default_value_t = CONVERSATION_LEN)]
+    pub messages: usize,
+    /// Results of a previous run, printed with `--format json`, to compare against
+    #[arg(long)]
+    pub baseline: Option<PathBuf>,
+    /// Percentage the mean of a benchmark may exceed the baseline by before failing
+    #[arg(long, default_value_t = 20.0, requires = "baseline")]
+    pub max_regression: f64,
+    /// Format of the output
+    #[arg(long, short, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+impl BenchArgs {
+    pub async fn execute(self) -> Result<ExitCode> {
+        let baseline: Option<Vec<BenchResult>> = match &self.baseline {
+            Some(path) => {
+                let content = tokio::fs::read(path)
+                    .await
+                    .wrap_err_with(|| format!("failed to read {}", path.display()))?;
+                Some(
+                    serde_json::from_slice(&content)
+                        .wrap_err("the baseline is not the output of `bench --format json`")?,
+                )
+            },
+            None => None,
+        };
+
+        let results = agent::bench::run(self.messages, self.iterations).await;
+        self.format.print(
+            || {
+                let mut text = format!(
+                    "{:<34}{:>12}{:>12}{:>12}",
+                    "benchmark", "mean (µs)", "min (µs)", "max (µs)"
+                );
+                for result in &results {
+                    text.push_str(&format!(
+                        "\n{:<34}{:>12.1}{:>12.1}{:>12.1}",
+                        result.name, result.mean_us, result.min_us, result.max_us
+                    ));
+                }
+                text
+            },
+            || &results,
+        );
+
+        let Some(baseline) = baseline else {
+            return Ok(ExitCode::SUCCESS);
+        };
+        let regressions = regressions(&results, &baseline, self.max_regression);
+        let mut stderr = std::io::stderr();
+        for (name, change) in &regressions {
+            writeln!(
+                stderr,
+                "{name} is {change:.1}% slower than the baseline, more than the allowed {}%",
+                self.max_regression
+            )?;
+        }
+        Ok(if regressions.is_empty() {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        })
+    }
+}
+
+/// Returns the benchmarks whose mean exceeds the baseline by more than `max_regression` percent,
+/// with the percentage they exceed it by.
+fn regressions(results: &[BenchResult], baseline: &[BenchResult], max_regression: f64) -> Vec<(String, f64)> {
+    results
+        .iter()
+        .filter_map(|result| {
+            let base = baseline.iter().find(|base| base.name == result.name)?;
+            let change = (result.mean_us / base.mean_us - 1.0) * 100.0;
+            (base.mean_us > 0.0 && change > max_regression).then(|| (result.name.clone(), change))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(name: &str, mean_us: f64) -> BenchResult {
+        BenchResult {
+            name: name.to_string(),
+            iterations: 1,
+            mean_us,
+            min_us: mean_us,
+            max_us: mean_us,
+        }
+    }
+
+    #[test]
+    fn test_regressions() {
+        let baseline = [result("format_request", 100.0), result("snapshot_serialize", 100.0)];
+        let results = [
+            result("format_request", 150.0),
+            result("snapshot_serialize", 110.0),
+            result("new_benchmark", 1000.0),
+        ];
+        let regressions = regressions(&results, &baseline, 20.0);
+        assert_eq!(regressions.len(), 1);
+        assert_eq!(regressions[0].0, "format_request");
+    }
+}
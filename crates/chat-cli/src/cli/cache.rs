@@ -0,0 +1,47 @@
+use std::io::Write;
+use std::process::ExitCode;
+
+use clap::Subcommand;
+use eyre::Result;
+
+use crate::os::Os;
+use crate::util::cache;
+
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum CacheSubcommand {
+    /// Remove every cached tool result and MCP tool list
+    Clear,
+}
+
+impl CacheSubcommand {
+    pub async fn execute(self, os: &Os) -> Result<ExitCode> {
+        let mut stderr = std::io::stderr();
+        match self {
+            Self::Clear => {
+                let cache = cache::open(os)?;
+                let removed = cache.clear()?;
+                writeln!(stderr, "Removed {removed} cache entries from {}", cache.dir().display())?;
+            },
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use disk_cache::CacheKey;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_clear() {
+        let os = Os::new().await.unwrap();
+        let cache = cache::open(&os).unwrap();
+        cache.insert(&CacheKey::new(["key"]), &"value").unwrap();
+        assert_eq!(cache.stats().unwrap().entries, 1);
+
+        CacheSubcommand::Clear.execute(&os).await.unwrap();
+        assert_eq!(cache.stats().unwrap().entries, 0);
+    }
+}
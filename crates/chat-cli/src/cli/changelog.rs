@@ -0,0 +1,204 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Args;
+use eyre::{
+    Result,
+    bail,
+};
+
+use crate::cli::chat::ChatArgs;
+use crate::os::Os;
+use crate::util::paths::PathResolver;
+
+/// Most commits to include in the prompt, older ones are summarized as a count
+const MAX_COMMITS: usize = 500;
+
+/// Longest commit body to include in the prompt, in characters
+const MAX_BODY_CHARS: usize = 600;
+
+/// Layout of the release notes when neither `--template` nor the workspace template is present
+const DEFAULT_TEMPLATE: &str = "## {version}\n\n\
+### Features\n\n- ...\n\n\
+### Fixes\n\n- ...\n\n\
+### Other changes\n\n- ...\n";
+
+/// Writes grouped release notes from the git history since a tag or commit. With `--update` the
+/// notes are also added to CHANGELOG.md, and the change is previewed for approval before it is
+/// written.
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct ChangelogArgs {
+    /// Tag or commit the release notes start after, such as v1.2.0
+    #[arg(long)]
+    pub from: String,
+    /// Tag or commit the release notes end at
+    #[arg(long, default_value = "HEAD")]
+    pub to: String,
+    /// Markdown file with the layout of the release notes, .amazonq/changelog-template.md by
+    /// default if it exists
+    #[arg(long)]
+    pub template: Option<PathBuf>,
+    /// Add the release notes to the top of CHANGELOG.md
+    #[arg(long)]
+    pub update: bool,
+    /// Model to use
+    #[arg(long)]
+    pub model: Option<String>,
+}
+
+impl ChangelogArgs {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        if self.from.starts_with('-') || self.to.starts_with('-') {
+            bail!("--from and --to must be tags or commits");
+        }
+
+        let cwd = os.env.current_dir()?;
+        let output = tokio::process::Command::new("git")
+            .arg("-C")
+            .arg(&cwd)
+            .args(["log", "--no-merges", "--format=%h%x1f%s%x1f%b%x1e"])
+            .arg(format!("{}..{}", self.from, self.to))
+            .output()
+            .await?;
+        if !output.status.success() {
+            bail!(
+                "Failed to read the git history: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        let commits = parse_log(&String::from_utf8_lossy(&output.stdout));
+        if commits.is_empty() {
+            bail!("There are no commits between {} and {}", self.from, self.to);
+        }
+
+        let template = match &self.template {
+            Some(path) => os.fs.read_to_string(path).await?,
+            None => {
+                let path = PathResolver::new(os).workspace().changelog_template()?;
+                match os.fs.exists(&path) {
+                    true => os.fs.read_to_string(&path).await?,
+                    false => DEFAULT_TEMPLATE.to_string(),
+                }
+            },
+        };
+
+        // Printing the notes doesn't need any tools, updating CHANGELOG.md needs a session so the
+        // write can be approved.
+        ChatArgs {
+            model: self.model,
+            input: Some(release_notes_prompt(
+                &self.from,
+                &self.to,
+                &commits,
+                &template,
+                self.update,
+            )),
+            no_interactive: !self.update,
+            trust_tools: (!self.update).then(Vec::new),
+            ..Default::default()
+        }
+        .execute(os)
+        .await
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Commit {
+    hash: String,
+    subject: String,
+    body: String,
+}
+
+/// Parses `git log` output with fields separated by `\x1f` and commits by `\x1e`
+fn parse_log(log: &str) -> Vec<Commit> {
+    log.split('\x1e')
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').splitn(3, '\x1f');
+            let hash = fields.next()?.trim();
+            let subject = fields.next()?.trim();
+            if hash.is_empty() {
+                return None;
+            }
+            Some(Commit {
+                hash: hash.to_string(),
+                subject: subject.to_string(),
+                body: fields.next().unwrap_or_default().trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+fn release_notes_prompt(from: &str, to: &str, commits: &[Commit], template: &str, update: bool) -> String {
+    let mut history = String::new();
+    for commit in commits.iter().take(MAX_COMMITS) {
+        history.push_str(&format!("- {} {}\n", commit.hash, commit.subject));
+        if !commit.body.is_empty() {
+            let body = match commit.body.char_indices().nth(MAX_BODY_CHARS) {
+                Some((i, _)) => format!("{}...", &commit.body[..i]),
+                None => commit.body.clone(),
+            };
+            for line in body.lines().filter(|line| !line.trim().is_empty()) {
+                history.push_str(&format!("  {}\n", line.trim()));
+            }
+        }
+    }
+    if commits.len() > MAX_COMMITS {
+        history.push_str(&format!("- ... and {} older commits\n", commits.len() - MAX_COMMITS));
+    }
+
+    let version = if to == "HEAD" { "Unreleased" } else { to };
+    let task = if update {
+        "Show me the release notes, then add them to CHANGELOG.md in the current directory with fs_write, \
+        above the newest existing entry and below any title, creating the file if it doesn't exist. Don't \
+        change any other entry."
+    } else {
+        "Reply with only the release notes in markdown, without any introduction."
+    };
+    format!(
+        "Write release notes for the changes from {from} to {to}, using {version} as the version.\n\n\
+        These are the commits, newest first:\n{history}\n\
+        Follow the layout of this template, replacing its placeholders and leaving out sections with no \
+        changes:\n```markdown\n{template}\n```\n\n\
+        Group related commits into a single entry written for users rather than developers, leave out \
+        commits that don't change behavior such as formatting, test and CI changes, and call out breaking \
+        changes first.\n\n\
+        {task}"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log() {
+        let log = "abc1234\x1fAdd q changelog\x1fReads the history\nsince a tag\n\x1e\n\
+                   def5678\x1fFix typo\x1f\x1e\n";
+        assert_eq!(parse_log(log), vec![
+            Commit {
+                hash: "abc1234".to_string(),
+                subject: "Add q changelog".to_string(),
+                body: "Reads the history\nsince a tag".to_string(),
+            },
+            Commit {
+                hash: "def5678".to_string(),
+                subject: "Fix typo".to_string(),
+                body: String::new(),
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_release_notes_prompt() {
+        let commits = parse_log("abc1234\x1fAdd q changelog\x1f\x1e");
+        let prompt = release_notes_prompt("v1.2.0", "HEAD", &commits, DEFAULT_TEMPLATE, false);
+        assert!(prompt.contains("from v1.2.0 to HEAD, using Unreleased as the version"));
+        assert!(prompt.contains("- abc1234 Add q changelog\n"));
+        assert!(prompt.contains("### Features"));
+        assert!(!prompt.contains("CHANGELOG.md"));
+
+        let prompt = release_notes_prompt("v1.2.0", "v1.3.0", &commits, DEFAULT_TEMPLATE, true);
+        assert!(prompt.contains("using v1.3.0 as the version"));
+        assert!(prompt.contains("CHANGELOG.md"));
+    }
+}
@@ -1,7 +1,4 @@
-use std::collections::{
-    HashMap,
-    VecDeque,
-};
+use std::collections::HashMap;
 use std::path::{
     Path,
     PathBuf,
@@ -29,7 +26,7 @@ use tracing::debug;
 
 use super::util::truncate_safe;
 use crate::cli::ConversationState;
-use crate::cli::chat::conversation::HistoryEntry;
+use crate::cli::chat::history::History;
 use crate::os::Os;
 
 /// Manages a shadow git repository for tracking and restoring workspace changes
@@ -76,18 +73,14 @@ pub struct Checkpoint {
     pub tag: String,
     pub timestamp: DateTime<Local>,
     pub description: String,
-    pub history_snapshot: VecDeque<HistoryEntry>,
+    pub history_snapshot: History,
     pub is_turn: bool,
     pub tool_name: Option<String>,
 }
 
 impl CheckpointManager {
     /// Initialize checkpoint manager automatically (when in a git repo)
-    pub async fn auto_init(
-        os: &Os,
-        shadow_path: impl AsRef<Path>,
-        current_history: &VecDeque<HistoryEntry>,
-    ) -> Result<Self> {
+    pub async fn auto_init(os: &Os, shadow_path: impl AsRef<Path>, current_history: &History) -> Result<Self> {
         if !is_git_installed() {
             bail!("Checkpoints are not available. Git is required but not installed.");
         }
@@ -100,16 +93,14 @@ impl CheckpointManager {
     }
 
     /// Initialize checkpoint manager manually
-    pub async fn manual_init(
-        os: &Os,
-        path: impl AsRef<Path>,
-        current_history: &VecDeque<HistoryEntry>,
-    ) -> Result<Self> {
+    pub async fn manual_init(os: &Os, path: impl AsRef<Path>, current_history: &History) -> Result<Self> {
         let path = path.as_ref();
         os.fs.create_dir_all(path).await?;
 
-        let work_tree_path =
-            std::env::current_dir().map_err(|e| eyre!("Failed to get current working directory: {}", e))?;
+        let work_tree_path = os
+            .env
+            .current_dir()
+            .map_err(|e| eyre!("Failed to get current working directory: {}", e))?;
 
         // Initialize bare repository
         run_git(path, None, &["init", "--bare", &path.to_string_lossy()])?;
@@ -150,7 +141,7 @@ impl CheckpointManager {
         &mut self,
         tag: &str,
         description: &str,
-        history: &VecDeque<HistoryEntry>,
+        history: &History,
         is_turn: bool,
         tool_name: Option<String>,
     ) -> Result<()> {
@@ -0,0 +1,79 @@
+use clap::Subcommand;
+use crossterm::{
+    execute,
+    style,
+};
+
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::theme::StyledText;
+use crate::util::CLI_BINARY_NAME;
+use crate::util::cache::format_size;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+/// Subcommands for inspecting the cache of tool results and MCP tool lists
+pub enum CacheSubcommand {
+    /// Show the size of the cache and how often it was hit in this session
+    Stats,
+}
+
+impl CacheSubcommand {
+    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        match self {
+            Self::Stats => {
+                let Some(cache) = session.tool_cache.as_ref() else {
+                    return Err(ChatError::Custom(
+                        "The cache is unavailable in this session, see /logdump for details".into(),
+                    ));
+                };
+                let stats = cache
+                    .stats()
+                    .map_err(|e| ChatError::Custom(format!("Failed to read the cache: {e}").into()))?;
+
+                execute!(
+                    session.stderr,
+                    style::Print("\n"),
+                    StyledText::brand_fg(),
+                    style::Print("Location: "),
+                    StyledText::reset(),
+                    style::Print(format!("{}\n", cache.dir().display())),
+                    StyledText::brand_fg(),
+                    style::Print("Entries:  "),
+                    StyledText::reset(),
+                    style::Print(format!(
+                        "{} ({} of {})\n",
+                        stats.entries,
+                        format_size(stats.size),
+                        format_size(stats.max_size)
+                    )),
+                    StyledText::brand_fg(),
+                    style::Print("Session:  "),
+                    StyledText::reset(),
+                    style::Print(format!(
+                        "{} hits, {} misses, {} evicted\n",
+                        stats.hits, stats.misses, stats.evictions
+                    )),
+                    StyledText::secondary_fg(),
+                    style::Print(format!(
+                        "\nRun {CLI_BINARY_NAME} cache clear to remove every entry.\n\n"
+                    )),
+                    StyledText::reset(),
+                )?;
+            },
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Stats => "stats",
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use clap::Args;
+use crossterm::{
+    execute,
+    style,
+};
+use tokio::process::Command;
+use tracing::debug;
+
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::{
+    Env,
+    Os,
+};
+use crate::theme::StyledText;
+
+const DEFAULT_LINES: usize = 100;
+const MAX_LINES: usize = 2000;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+/// Arguments for the capture command that attaches recent terminal output to the next message.
+pub struct CaptureArgs {
+    /// Number of lines of terminal output to capture
+    #[arg(default_value_t = DEFAULT_LINES)]
+    pub lines: usize,
+}
+
+impl CaptureArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        if self.lines == 0 || self.lines > MAX_LINES {
+            return Err(ChatError::Custom(
+                format!("The number of lines must be between 1 and {MAX_LINES}").into(),
+            ));
+        }
+        let Some(source) = CaptureSource::detect(&os.env) else {
+            return Err(ChatError::Custom(
+                "Capturing terminal output requires running inside tmux, GNU screen, kitty or WezTerm".into(),
+            ));
+        };
+
+        let output = source.capture(self.lines).await.map_err(|err| {
+            ChatError::Custom(format!("Failed to capture terminal output from {}: {err}", source.name()).into())
+        })?;
+        let captured = last_lines(&output, self.lines);
+        if captured.is_empty() {
+            return Err(ChatError::Custom("The terminal has no output to capture".into()));
+        }
+        let line_count = captured.lines().count();
+
+        execute!(
+            session.stderr,
+            style::Print("Captured "),
+            StyledText::success_fg(),
+            style::Print(line_count),
+            StyledText::reset(),
+            style::Print(format!(
+                " lines of terminal output from {}. They will be sent with your next message.\n",
+                source.name()
+            )),
+        )?;
+
+        let context = format!("Recent output from the user's terminal:\n```\n{captured}\n```");
+        session.pending_additional_context = Some(match session.pending_additional_context.take() {
+            Some(existing) => format!("{existing}\n\n{context}"),
+            None => context,
+        });
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+/// Returns the last `lines` lines of terminal output, or [None] if the terminal can't report its
+/// scrollback.
+pub async fn capture_terminal(env: &Env, lines: usize) -> Option<String> {
+    let source = CaptureSource::detect(env)?;
+    match source.capture(lines).await {
+        Ok(output) => Some(last_lines(&output, lines)).filter(|captured| !captured.is_empty()),
+        Err(err) => {
+            debug!(?err, source = source.name(), "failed to capture terminal output");
+            None
+        },
+    }
+}
+
+/// A terminal or multiplexer that can report the contents of its scrollback.
+#[derive(Debug, PartialEq)]
+enum CaptureSource {
+    Tmux { pane: String },
+    Screen,
+    Kitty { window: String },
+    Wezterm { pane: String },
+}
+
+impl CaptureSource {
+    /// Multiplexers are checked first since they wrap the terminal emulator.
+    fn detect(env: &Env) -> Option<Self> {
+        if env.get("TMUX").is_ok() {
+            if let Ok(pane) = env.get("TMUX_PANE") {
+                return Some(Self::Tmux { pane });
+            }
+        }
+        if env.get("STY").is_ok() {
+            return Some(Self::Screen);
+        }
+        if let Ok(window) = env.get("KITTY_WINDOW_ID") {
+            return Some(Self::Kitty { window });
+        }
+        if let Ok(pane) = env.get("WEZTERM_PANE") {
+            return Some(Self::Wezterm { pane });
+        }
+        None
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Tmux { .. } => "tmux",
+            Self::Screen => "GNU screen",
+            Self::Kitty { .. } => "kitty",
+            Self::Wezterm { .. } => "WezTerm",
+        }
+    }
+
+    async fn capture(&self, lines: usize) -> eyre::Result<String> {
+        let start = format!("-{lines}");
+        let output = match self {
+            Self::Tmux { pane } => {
+                Command::new("tmux")
+                    .args(["capture-pane", "-p", "-J", "-t", pane, "-S", &start])
+                    .output()
+                    .await?
+            },
+            Self::Kitty { window } => {
+                Command::new("kitty")
+                    .args(["@", "get-text", "--extent", "all", "--match", &format!("id:{window}")])
+                    .output()
+                    .await?
+            },
+            Self::Wezterm { pane } => {
+                Command::new("wezterm")
+                    .args(["cli", "get-text", "--pane-id", pane, "--start-line", &start])
+                    .output()
+                    .await?
+            },
+            Self::Screen => {
+                // screen can only write its scrollback to a file.
+                let dir = tempfile::tempdir()?;
+                let file = dir.path().join("hardcopy");
+                let status = Command::new("screen")
+                    .args(["-X", "hardcopy", "-h"])
+                    .arg(&file)
+                    .status()
+                    .await?;
+                if !status.success() {
+                    eyre::bail!("screen exited with {status}");
+                }
+                return Ok(String::from_utf8_lossy(&tokio::fs::read(&file).await?).into_owned());
+            },
+        };
+
+        if !output.status.success() {
+            eyre::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// The last `n` lines of `text`, ignoring trailing blank lines such as the unused rows at the
+/// bottom of the screen.
+fn last_lines(text: &str, n: usize) -> String {
+    let lines = text.lines().map(str::trim_end).collect::<Vec<_>>();
+    let end = lines.iter().rposition(|line| !line.is_empty()).map_or(0, |i| i + 1);
+    let start = end.saturating_sub(n);
+    lines[start..end].join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            CaptureSource::detect(&Env::from_slice(&[
+                ("TMUX", "/tmp/tmux-1000/default"),
+                ("TMUX_PANE", "%3")
+            ])),
+            Some(CaptureSource::Tmux { pane: "%3".to_string() })
+        );
+        // tmux running inside kitty takes precedence.
+        assert_eq!(
+            CaptureSource::detect(&Env::from_slice(&[
+                ("KITTY_WINDOW_ID", "1"),
+                ("TMUX", "/tmp/tmux-1000/default"),
+                ("TMUX_PANE", "%0"),
+            ])),
+            Some(CaptureSource::Tmux { pane: "%0".to_string() })
+        );
+        assert_eq!(
+            CaptureSource::detect(&Env::from_slice(&[("STY", "1234.pts-0.host")])),
+            Some(CaptureSource::Screen)
+        );
+        assert_eq!(
+            CaptureSource::detect(&Env::from_slice(&[("WEZTERM_PANE", "7")])),
+            Some(CaptureSource::Wezterm { pane: "7".to_string() })
+        );
+        assert_eq!(CaptureSource::detect(&Env::from_slice(&[("TERM", "xterm")])), None);
+    }
+
+    #[test]
+    fn test_last_lines() {
+        let screen = "$ cargo build\nerror[E0425]: cannot find value `x`   \n  --> src/main.rs:2:5\n\n$ \n\n\n";
+        assert_eq!(last_lines(screen, 3), "  --> src/main.rs:2:5\n\n$");
+        assert_eq!(last_lines(screen, 100).lines().count(), 5);
+        assert_eq!(last_lines("\n\n", 10), "");
+    }
+}
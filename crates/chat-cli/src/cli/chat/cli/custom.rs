@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::time::Duration;
+
+use bstr::ByteSlice;
+use clap::CommandFactory;
+use crossterm::{
+    execute,
+    style,
+};
+use eyre::{
+    Result,
+    eyre,
+};
+
+use super::SlashCommand;
+use crate::cli::agent::custom_command::CustomCommand;
+use crate::cli::chat::util::truncate_safe;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+use crate::theme::StyledText;
+
+/// Looks up a user defined slash command on the active agent. Built-in commands (and their
+/// aliases) always take precedence over user defined ones.
+pub fn find_custom_command(session: &ChatSession, name: &str) -> Option<CustomCommand> {
+    if SlashCommand::command().find_subcommand(name).is_some() {
+        return None;
+    }
+
+    session
+        .conversation
+        .agents
+        .get_active()
+        .and_then(|agent| agent.commands.get(name))
+        .cloned()
+}
+
+/// Names of the user defined slash commands of the active agent (with the leading slash), for use
+/// in command completion
+pub fn custom_command_names(session: &ChatSession) -> Vec<String> {
+    let builtin = SlashCommand::command();
+    let mut names = session
+        .conversation
+        .agents
+        .get_active()
+        .map(|agent| {
+            agent
+                .commands
+                .keys()
+                .filter(|name| builtin.find_subcommand(name.as_str()).is_none())
+                .map(|name| format!("/{name}"))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// Lists the user defined slash commands of the active agent with their descriptions, for the
+/// end of /help. Returns [None] when the agent has none.
+pub fn custom_commands_help(session: &ChatSession) -> Option<String> {
+    session
+        .conversation
+        .agents
+        .get_active()
+        .and_then(|agent| render_help(&agent.commands))
+}
+
+fn render_help(commands: &HashMap<String, CustomCommand>) -> Option<String> {
+    let builtin = SlashCommand::command();
+    let mut commands = commands
+        .iter()
+        .filter(|(name, _)| builtin.find_subcommand(name.as_str()).is_none())
+        .map(|(name, command)| (format!("/{name}"), command.description.as_deref().unwrap_or_default()))
+        .collect::<Vec<_>>();
+    if commands.is_empty() {
+        return None;
+    }
+    commands.sort();
+
+    let width = commands.iter().map(|(name, _)| name.len()).max().unwrap_or_default();
+    let mut help = StyledText::brand("Custom commands:");
+    help.push('\n');
+    for (name, description) in commands {
+        help.push_str(&format!(
+            "  {}  {}\n",
+            StyledText::primary(&format!("{name:width$}")),
+            StyledText::secondary(description)
+        ));
+    }
+    Some(help)
+}
+
+/// Renders a user defined slash command into a prompt and submits it to the model
+pub async fn execute_custom_command(
+    os: &Os,
+    session: &mut ChatSession,
+    name: &str,
+    command: &CustomCommand,
+    args: &[String],
+) -> Result<ChatState, ChatError> {
+    let prompt = command.render_prompt(args);
+    let output = match command.render_command(args) {
+        Some(shell_command) => {
+            let cwd = os.env.current_dir()?;
+            let env = session
+                .conversation
+                .agents
+                .get_active()
+                .map(|agent| agent.resolved_env(os))
+                .unwrap_or_default();
+            match run_command(&shell_command, &cwd, env, command).await {
+                Ok(output) => Some(output),
+                Err(err) => {
+                    execute!(
+                        session.stderr,
+                        StyledText::error_fg(),
+                        style::Print(format!("/{name} failed: {err}\n")),
+                        StyledText::reset(),
+                    )?;
+                    return Ok(ChatState::PromptUser {
+                        skip_printing_tools: true,
+                    });
+                },
+            }
+        },
+        None => None,
+    };
+
+    let input = match (prompt, output) {
+        (Some(prompt), Some(output)) => format!("{prompt}\n\n--- Output of /{name} ---\n{output}"),
+        (Some(prompt), None) => prompt,
+        (None, Some(output)) => output,
+        (None, None) => {
+            return Err(ChatError::Custom(
+                format!("/{name} must define at least one of `prompt` or `command`").into(),
+            ));
+        },
+    };
+
+    if input.trim().is_empty() {
+        execute!(
+            session.stderr,
+            StyledText::warning_fg(),
+            style::Print(format!("/{name} produced an empty prompt, nothing was sent\n")),
+            StyledText::reset(),
+        )?;
+        return Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        });
+    }
+
+    Ok(ChatState::HandleInput { input })
+}
+
+async fn run_command(
+    shell_command: &str,
+    cwd: &std::path::Path,
+    env: HashMap<String, String>,
+    command: &CustomCommand,
+) -> Result<String> {
+    #[cfg(unix)]
+    let mut cmd = tokio::process::Command::new("bash");
+    #[cfg(unix)]
+    cmd.arg("-c");
+
+    #[cfg(windows)]
+    let mut cmd = tokio::process::Command::new("cmd");
+    #[cfg(windows)]
+    cmd.arg("/C");
+
+    cmd.arg(shell_command)
+        .current_dir(cwd)
+        .envs(env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let timeout = Duration::from_millis(command.timeout_ms);
+    let output = match tokio::time::timeout(timeout, cmd.output()).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(err)) => return Err(eyre!("failed to execute command: {}", err)),
+        Err(_) => return Err(eyre!("command timed out after {} ms", timeout.as_millis())),
+    };
+
+    if !output.status.success() {
+        return Err(eyre!(
+            "command exited with {}: {}",
+            output.status,
+            output.stderr.to_str_lossy().trim()
+        ));
+    }
+
+    let stdout = output.stdout.to_str_lossy();
+    let mut result = truncate_safe(&stdout, command.max_output_size).to_string();
+    if stdout.len() > command.max_output_size {
+        result.push_str(" ... truncated");
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_help() {
+        assert!(render_help(&HashMap::new()).is_none());
+
+        let command = |description: Option<&str>| CustomCommand {
+            description: description.map(str::to_string),
+            prompt: Some("hi".to_string()),
+            command: None,
+            timeout_ms: 0,
+            max_output_size: 0,
+        };
+        let commands = HashMap::from([
+            ("ticket".to_string(), command(Some("Summarize a ticket"))),
+            ("pr".to_string(), command(None)),
+            // Shadowed by the built-in command, so never run
+            ("clear".to_string(), command(Some("Never shown"))),
+        ]);
+        let help = strip_ansi_escapes::strip_str(render_help(&commands).unwrap());
+        assert_eq!(help, "Custom commands:\n  /pr      \n  /ticket  Summarize a ticket\n");
+    }
+}
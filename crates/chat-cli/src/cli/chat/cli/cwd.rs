@@ -0,0 +1,139 @@
+use std::path::Path;
+
+use clap::Args;
+use crossterm::{
+    execute,
+    style,
+};
+
+use crate::cli::chat::tools::sanitize_path_tool_arg;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+use crate::theme::StyledText;
+
+/// Max number of `git status` lines included in the workspace summary
+const MAX_STATUS_LINES: usize = 20;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+/// Arguments for the cd command that changes the working directory of the chat session.
+pub struct CdArgs {
+    /// Directory to change to. Defaults to the home directory.
+    pub path: Option<String>,
+}
+
+impl CdArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let target = match &self.path {
+            Some(path) => os.env.current_dir()?.join(sanitize_path_tool_arg(os, path)),
+            None => os
+                .env
+                .home()
+                .ok_or(ChatError::Custom("Unable to determine the home directory".into()))?,
+        };
+        let target = match tokio::fs::canonicalize(&target).await {
+            Ok(target) if target.is_dir() => target,
+            _ => {
+                return Err(ChatError::Custom(
+                    format!("No such directory: {}", target.display()).into(),
+                ));
+            },
+        };
+
+        // Only the session moves: tools resolve relative paths and spawn commands in the
+        // directory of os.env, while the process keeps its own.
+        os.env.set_current_dir(&target)?;
+        session.conversation.tool_manager.notify_roots_changed().await;
+
+        execute!(
+            session.stderr,
+            style::Print("Working directory: "),
+            StyledText::success_fg(),
+            style::Print(format!("{}\n", target.display())),
+            StyledText::reset(),
+        )?;
+
+        let summary = workspace_summary(&target).await;
+        if let Some(summary) = &summary {
+            execute!(
+                session.stderr,
+                StyledText::secondary_fg(),
+                style::Print(format!("{summary}\n")),
+                StyledText::reset(),
+            )?;
+        }
+
+        // Let the model know about the change with the next message
+        let mut context = format!("The working directory changed to {}.", target.display());
+        if let Some(summary) = summary {
+            context.push_str(&format!("\nWorkspace status (git status --short --branch):\n{summary}"));
+        }
+        session.pending_additional_context = Some(match session.pending_additional_context.take() {
+            Some(existing) => format!("{existing}\n\n{context}"),
+            None => context,
+        });
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+/// Arguments for the pwd command that prints the working directory of the chat session.
+pub struct PwdArgs;
+
+impl PwdArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let cwd = os.env.current_dir()?;
+        execute!(session.stderr, style::Print(format!("{}\n", cwd.display())))?;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+/// A `git status`-style summary of the workspace at `dir`, or [None] outside of a git repository
+async fn workspace_summary(dir: &Path) -> Option<String> {
+    let output = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["status", "--short", "--branch"])
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let status = String::from_utf8_lossy(&output.stdout);
+    let lines = status.lines().collect::<Vec<_>>();
+    let mut summary = lines
+        .iter()
+        .take(MAX_STATUS_LINES)
+        .copied()
+        .collect::<Vec<_>>()
+        .join("\n");
+    if lines.len() > MAX_STATUS_LINES {
+        summary.push_str(&format!("\n... and {} more", lines.len() - MAX_STATUS_LINES));
+    }
+
+    Some(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_workspace_summary_outside_git_repo() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(workspace_summary(dir.path()).await, None);
+    }
+}
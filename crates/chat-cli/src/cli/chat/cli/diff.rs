@@ -0,0 +1,63 @@
+use clap::Args;
+use crossterm::{
+    execute,
+    style,
+};
+
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+    turn_changes,
+};
+use crate::theme::StyledText;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+/// Arguments for the diff command that shows the files changed during the last turn.
+pub struct DiffArgs {
+    /// File to show the change of, all changed files if omitted
+    pub file: Option<String>,
+}
+
+impl DiffArgs {
+    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let changes = session.turn_changes.last_turn();
+        if changes.is_empty() {
+            execute!(
+                session.stderr,
+                StyledText::secondary_fg(),
+                style::Print("No files were changed during the last turn.\n\n"),
+                StyledText::reset(),
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
+        match self.file {
+            Some(file) => {
+                let Some(change) = session.turn_changes.find(&file) else {
+                    let changed = changes
+                        .iter()
+                        .map(|change| change.display_path.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    return Err(ChatError::Custom(
+                        format!("'{file}' was not changed during the last turn, or matches several files. Changed files: {changed}").into(),
+                    ));
+                };
+                turn_changes::print_diff(&mut session.stdout, change)?;
+            },
+            None => {
+                for change in changes {
+                    turn_changes::print_diff(&mut session.stdout, change)?;
+                }
+            },
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
@@ -0,0 +1,48 @@
+use clap::Args;
+
+use super::retry::{
+    no_prompt,
+    rewind_last_turn,
+};
+use crate::cli::chat::message::UserMessageContent;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+/// Arguments for the edit command that recalls the last prompt for editing and sends it again.
+pub struct EditArgs {}
+
+impl EditArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let last_prompt = session
+            .conversation
+            .history()
+            .iter()
+            .rev()
+            .find_map(|entry| match entry.user.content() {
+                UserMessageContent::Prompt { prompt } => Some(prompt.clone()),
+                _ => None,
+            });
+        let Some(last_prompt) = last_prompt else {
+            return no_prompt(session);
+        };
+
+        // The conversation is only rewound once the edited prompt is sent, so that cancelling
+        // the edit with Ctrl+C leaves it as it was
+        session.input_source.set_initial_text(last_prompt);
+        let prompt = session.generate_tool_trust_prompt(os).await;
+        let Some(input) = session.read_user_input(&prompt, true)? else {
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        };
+
+        rewind_last_turn(session);
+        Ok(ChatState::HandleInput { input })
+    }
+}
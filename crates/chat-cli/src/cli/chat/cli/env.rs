@@ -0,0 +1,241 @@
+use std::path::Path;
+
+use clap::Subcommand;
+use crossterm::{
+    execute,
+    style,
+};
+
+use crate::cli::agent::resolve_env;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+use crate::theme::StyledText;
+
+/// Parts of variable names that mark their values as secret, e.g. `GITHUB_TOKEN`
+const SECRET_NAME_PARTS: &[&str] = &["TOKEN", "SECRET", "PASSWORD", "PASSWD", "KEY", "CREDENTIAL", "AUTH"];
+
+/// Shown in place of secret values
+const MASKED_VALUE: &str = "********";
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+/// Subcommands for managing the environment passed to shell commands, hooks, and MCP servers
+pub enum EnvSubcommand {
+    /// Show the environment variables set for the session
+    Show,
+    /// Set an environment variable for the session
+    Set {
+        /// Name of the variable
+        name: String,
+        /// Value of the variable. Can reference the environment of the chat process with ${VAR}
+        value: String,
+        /// Also write the variable to the env of the agent config
+        #[arg(long)]
+        persist: bool,
+    },
+    /// Unset an environment variable of the session
+    Unset {
+        /// Name of the variable
+        name: String,
+        /// Also remove the variable from the env of the agent config
+        #[arg(long)]
+        persist: bool,
+    },
+}
+
+impl EnvSubcommand {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let Some(agent) = session.conversation.agents.get_active_mut() else {
+            return Err(ChatError::Custom("No active agent".into()));
+        };
+
+        match self {
+            Self::Show => {
+                let env = resolve_env(&agent.env, os);
+                if env.is_empty() {
+                    execute!(
+                        session.stderr,
+                        StyledText::secondary_fg(),
+                        style::Print("No environment variables are set. Use /env set <NAME> <VALUE> to set one.\n"),
+                        StyledText::reset(),
+                    )?;
+                } else {
+                    let mut names = env.keys().collect::<Vec<_>>();
+                    names.sort();
+                    for name in names {
+                        execute!(
+                            session.stderr,
+                            StyledText::brand_fg(),
+                            style::Print(name),
+                            StyledText::reset(),
+                            style::Print(format!("={}\n", mask_value(name, &env[name]))),
+                        )?;
+                    }
+                }
+            },
+            Self::Set { name, value, persist } => {
+                validate_name(&name)?;
+                if persist {
+                    persist_env(os, agent.path.as_deref(), &name, Some(&value)).await?;
+                }
+                let masked = mask_value(&name, &value);
+                agent.env.insert(name.clone(), value);
+                execute!(
+                    session.stderr,
+                    StyledText::success_fg(),
+                    style::Print("Set "),
+                    StyledText::brand_fg(),
+                    style::Print(&name),
+                    StyledText::reset(),
+                    style::Print(format!("={masked}\n")),
+                )?;
+            },
+            Self::Unset { name, persist } => {
+                if persist {
+                    persist_env(os, agent.path.as_deref(), &name, None).await?;
+                }
+                if agent.env.remove(&name).is_none() && !persist {
+                    return Err(ChatError::Custom(format!("{name} is not set").into()));
+                }
+                execute!(
+                    session.stderr,
+                    StyledText::success_fg(),
+                    style::Print("Unset "),
+                    StyledText::brand_fg(),
+                    style::Print(&name),
+                    StyledText::reset(),
+                    style::Print("\n"),
+                )?;
+            },
+        }
+
+        let has_mcp_servers = !agent.mcp_servers.mcp_servers.is_empty();
+        let env = agent.env.clone();
+        if let Some(context_manager) = session.conversation.context_manager.as_mut() {
+            context_manager.env = env;
+        }
+        if has_mcp_servers {
+            execute!(
+                session.stderr,
+                StyledText::secondary_fg(),
+                style::Print("MCP servers that are already running keep their environment until they are restarted\n"),
+                StyledText::reset(),
+            )?;
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Show => "show",
+            Self::Set { .. } => "set",
+            Self::Unset { .. } => "unset",
+        }
+    }
+}
+
+fn validate_name(name: &str) -> Result<(), ChatError> {
+    if name.is_empty() || name.contains(['=', '\0']) {
+        return Err(ChatError::Custom(
+            format!("Invalid environment variable name: {name}").into(),
+        ));
+    }
+    Ok(())
+}
+
+pub(super) fn is_secret_name(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    SECRET_NAME_PARTS.iter().any(|part| name.contains(part))
+}
+
+/// Masks the value of variables whose names look like they hold secrets
+fn mask_value(name: &str, value: &str) -> String {
+    if is_secret_name(name) && !value.is_empty() {
+        MASKED_VALUE.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Sets (or removes if `value` is [None]) a variable in the `env` block of the agent config at
+/// `path`. Only the single variable is touched so that session only changes are not persisted
+/// along with it.
+async fn persist_env(os: &Os, path: Option<&Path>, name: &str, value: Option<&str>) -> Result<(), ChatError> {
+    let Some(path) = path else {
+        return Err(ChatError::Custom(
+            "The active agent has no config file to persist to, the change was not applied".into(),
+        ));
+    };
+
+    let content = os.fs.read_to_string(path).await?;
+    let mut config = serde_json::from_str::<serde_json::Value>(&content)
+        .map_err(|e| ChatError::Custom(format!("Failed to parse {}: {e}", path.display()).into()))?;
+    let Some(config) = config.as_object_mut() else {
+        return Err(ChatError::Custom(
+            format!("Failed to parse {}: expected an object", path.display()).into(),
+        ));
+    };
+
+    let env = config
+        .entry("env")
+        .or_insert_with(|| serde_json::Value::Object(Default::default()));
+    let Some(env) = env.as_object_mut() else {
+        return Err(ChatError::Custom(
+            format!("Failed to update {}: env is not an object", path.display()).into(),
+        ));
+    };
+    match value {
+        Some(value) => {
+            env.insert(name.to_string(), serde_json::Value::String(value.to_string()));
+        },
+        None => {
+            env.remove(name);
+        },
+    }
+
+    let content = serde_json::to_string_pretty(config)
+        .map_err(|e| ChatError::Custom(format!("Failed to serialize agent config: {e}").into()))?;
+    os.fs.write(path, content).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_value() {
+        assert_eq!(mask_value("GITHUB_TOKEN", "ghp_123"), MASKED_VALUE);
+        assert_eq!(mask_value("db_password", "hunter2"), MASKED_VALUE);
+        assert_eq!(mask_value("AWS_SECRET_ACCESS_KEY", "abc"), MASKED_VALUE);
+        assert_eq!(mask_value("AWS_PROFILE", "dev"), "dev");
+        assert_eq!(mask_value("API_KEY", ""), "");
+    }
+
+    #[tokio::test]
+    async fn test_persist_env() {
+        let os = Os::new().await.unwrap();
+        let path = Path::new("/agent.json");
+        os.fs
+            .write(path, r#"{"name": "test-agent", "env": {"KEEP": "1"}}"#)
+            .await
+            .unwrap();
+
+        persist_env(&os, Some(path), "NEW", Some("${HOME}/bin")).await.unwrap();
+        persist_env(&os, Some(path), "KEEP", None).await.unwrap();
+
+        let config: serde_json::Value = serde_json::from_str(&os.fs.read_to_string(path).await.unwrap()).unwrap();
+        assert_eq!(config["name"], "test-agent");
+        assert_eq!(config["env"], serde_json::json!({ "NEW": "${HOME}/bin" }));
+
+        assert!(persist_env(&os, None, "NEW", None).await.is_err());
+    }
+}
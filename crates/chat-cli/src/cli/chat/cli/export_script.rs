@@ -0,0 +1,424 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use chrono::Utc;
+use clap::Args;
+use crossterm::{
+    execute,
+    style,
+};
+
+use super::env::is_secret_name;
+use crate::api_client::model::ToolResultStatus;
+use crate::cli::chat::conversation::HistoryEntry;
+use crate::cli::chat::message::{
+    ToolUseResult,
+    UserMessageContent,
+};
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+use crate::theme::StyledText;
+
+/// Start of references to secrets in env values, see [crate::util::secrets]
+const SECRET_REFERENCE_START: &str = "${secret:";
+
+/// Names of the tools that run shell commands
+const COMMAND_TOOL_NAMES: &[&str] = &["execute_bash", "execute_cmd"];
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+/// Arguments for the export-script command that writes the shell commands run during the
+/// conversation to a script, so that manual steps can be turned into automation.
+pub struct ExportScriptArgs {
+    /// Path to write the script to, a new file in the current directory by default
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+    /// Overwrite the output file if it exists
+    #[arg(long, short)]
+    pub force: bool,
+}
+
+impl ExportScriptArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let commands = executed_commands(session.conversation.history().iter());
+        if commands.is_empty() {
+            execute!(
+                session.stderr,
+                StyledText::warning_fg(),
+                style::Print("\nNo shell commands were run in this conversation yet.\n\n"),
+                StyledText::reset(),
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
+        let path = self
+            .output
+            .unwrap_or_else(|| PathBuf::from(format!("q-commands-{}.sh", Utc::now().format("%Y-%m-%dT%H-%M-%SZ"))));
+        if os.fs.exists(&path) && !self.force {
+            execute!(
+                session.stderr,
+                StyledText::error_fg(),
+                style::Print(format!(
+                    "\nFile at {} already exists. To overwrite, use -f or --force\n\n",
+                    path.display()
+                )),
+                StyledText::reset(),
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
+        let env = session
+            .conversation
+            .agents
+            .get_active()
+            .map(|agent| agent.env.clone())
+            .unwrap_or_default();
+        let home = os.env.home().map(|home| home.to_string_lossy().into_owned());
+        let script = to_script(&commands, &env, home.as_deref());
+        if let Err(err) = os.fs.write(&path, script).await {
+            execute!(
+                session.stderr,
+                StyledText::error_fg(),
+                style::Print(format!("\nFailed to write {}: {}\n\n", path.display(), err)),
+                StyledText::reset(),
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = os
+                .fs
+                .set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+                .await;
+        }
+
+        let mut note = "Review it before running it".to_string();
+        match commands.iter().filter(|command| !command.success).count() {
+            0 => note.push_str(".\n\n"),
+            failed => note.push_str(&format!(", the {failed} commands that failed are commented out.\n\n")),
+        }
+        execute!(
+            session.stderr,
+            StyledText::success_fg(),
+            style::Print(format!("\n✔ Wrote {} commands to {}\n", commands.len(), path.display())),
+            StyledText::secondary_fg(),
+            style::Print(note),
+            StyledText::reset(),
+        )?;
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+/// A shell command the model ran during the conversation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ExecutedCommand {
+    command: String,
+    /// Description of the command given by the model
+    summary: Option<String>,
+    /// Working directory of the session when the command was requested
+    cwd: Option<String>,
+    exit_code: Option<i32>,
+    success: bool,
+}
+
+/// The shell commands that ran, in order. Commands that were denied or cancelled are left out, as
+/// they're answered with cancelled tool uses rather than tool use results.
+fn executed_commands<'a>(entries: impl Iterator<Item = &'a HistoryEntry>) -> Vec<ExecutedCommand> {
+    let entries = entries.collect::<Vec<_>>();
+    let results = entries
+        .iter()
+        .filter_map(|entry| match entry.user.content() {
+            UserMessageContent::ToolUseResults { tool_use_results } => Some(tool_use_results),
+            _ => None,
+        })
+        .flatten()
+        .map(|result| (result.tool_use_id.as_str(), result))
+        .collect::<HashMap<&str, &ToolUseResult>>();
+
+    let mut commands = Vec::new();
+    for entry in &entries {
+        for tool_use in entry.assistant.tool_uses().unwrap_or_default() {
+            if !COMMAND_TOOL_NAMES.contains(&tool_use.orig_name.as_str()) {
+                continue;
+            }
+            let (Some(result), Some(command)) = (
+                results.get(tool_use.id.as_str()),
+                tool_use.orig_args.get("command").and_then(|command| command.as_str()),
+            ) else {
+                continue;
+            };
+            let exit_code = result.provenance.as_ref().and_then(|provenance| provenance.exit_code);
+            commands.push(ExecutedCommand {
+                command: command.to_string(),
+                summary: tool_use
+                    .orig_args
+                    .get("summary")
+                    .and_then(|summary| summary.as_str())
+                    .map(str::to_string),
+                cwd: entry.user.env_context.current_working_directory().map(str::to_string),
+                exit_code,
+                success: matches!(result.status, ToolResultStatus::Success) && exit_code.is_none_or(|code| code == 0),
+            });
+        }
+    }
+    commands
+}
+
+/// Formats the commands as a shell script, run from the working directory of the session at the
+/// time of each command. Commands that failed are commented out.
+///
+/// `env` holds the values of the agent's env as configured, before references are resolved, so
+/// that the values of secrets never end up in the script. Variables that reference a secret or
+/// look like one have to be set by whoever runs the script instead.
+///
+/// Each command ran in a shell of its own, so the directory is changed again after a command that
+/// could have changed it.
+fn to_script(commands: &[ExecutedCommand], env: &HashMap<String, String>, home: Option<&str>) -> String {
+    let mut script = format!(
+        "#!/usr/bin/env bash\n\
+         # Shell commands run during an Amazon Q conversation, exported {}.\n\
+         # They ran with the approval of the user, but may depend on the state of the machine at the time.\n\
+         set -e\n",
+        Utc::now().format("%Y-%m-%d %H:%M UTC")
+    );
+
+    if !env.is_empty() {
+        script.push_str("\n# Environment set for the commands with /env\n");
+        let mut names = env.keys().collect::<Vec<_>>();
+        names.sort();
+        for name in names {
+            let value = &env[name];
+            if is_secret_name(name) || value.contains(SECRET_REFERENCE_START) {
+                script.push_str(&format!(": \"${{{name}:?{name} must be set}}\"\n"));
+            } else {
+                script.push_str(&format!("export {name}={}\n", env_value(value)));
+            }
+        }
+    }
+
+    let mut cwd = None;
+    let mut changed_dir = false;
+    for command in commands {
+        script.push('\n');
+        if command.cwd.is_some() && (command.cwd != cwd || changed_dir) {
+            cwd = command.cwd.clone();
+            script.push_str(&format!(
+                "cd {}\n",
+                quote_path(cwd.as_deref().unwrap_or_default(), home)
+            ));
+        }
+        for line in command.summary.iter().flat_map(|summary| summary.lines()) {
+            script.push_str(&format!("# {line}\n"));
+        }
+        let text = command.command.trim_end();
+        if command.success {
+            script.push_str(text);
+            script.push('\n');
+        } else {
+            match command.exit_code {
+                Some(code) => script.push_str(&format!("# Failed with exit code {code}:\n")),
+                None => script.push_str("# Failed:\n"),
+            }
+            for line in text.lines() {
+                script.push_str(&format!("# {line}\n"));
+            }
+        }
+        changed_dir = text
+            .split(|c: char| c.is_whitespace() || ";&|(".contains(c))
+            .any(|word| word == "cd" || word == "pushd");
+    }
+    script
+}
+
+/// Quotes `path` for the shell, relative to `$HOME` when under `home` so the script works for
+/// other users.
+fn quote_path(path: &str, home: Option<&str>) -> String {
+    match home.and_then(|home| path.strip_prefix(home)) {
+        Some("") => "\"$HOME\"".to_string(),
+        Some(rest) if rest.starts_with('/') => format!("\"$HOME\"{}", shell_quote(rest)),
+        _ => shell_quote(path),
+    }
+}
+
+/// Quotes an env value for the shell, keeping the references to variables in it, such as
+/// `${HOME}/bin`, so that the shell resolves them like the agent did. Nothing else is expanded.
+fn env_value(value: &str) -> String {
+    if !value.contains('$') {
+        return shell_quote(value);
+    }
+    let mut quoted = String::from('"');
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' | '\\' | '`' => quoted.push('\\'),
+            // Only `${VAR}` and `$VAR` are references, not command substitutions
+            '$' if chars.peek().is_some_and(|next| *next == '(') => quoted.push('\\'),
+            _ => (),
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Quotes `value` as a single word for the shell, leaving it as is when it has no special
+/// characters.
+fn shell_quote(value: &str) -> String {
+    if !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./:=,+@%".contains(c))
+    {
+        return value.to_string();
+    }
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::chat::message::{
+        AssistantMessage,
+        AssistantToolUse,
+        ToolProvenance,
+        ToolUseResultBlock,
+        UserMessage,
+    };
+
+    fn command_tool_use(id: &str, command: &str) -> AssistantToolUse {
+        let args = serde_json::json!({ "command": command, "summary": format!("Run {command}") });
+        AssistantToolUse {
+            id: id.to_string(),
+            name: "execute_bash".to_string(),
+            orig_name: "execute_bash".to_string(),
+            args: args.clone(),
+            orig_args: args,
+        }
+    }
+
+    fn result(id: &str, exit_code: i32) -> ToolUseResult {
+        ToolUseResult {
+            tool_use_id: id.to_string(),
+            content: vec![ToolUseResultBlock::Text(String::new())],
+            status: ToolResultStatus::Success,
+            provenance: Some(ToolProvenance {
+                tool_name: "execute_bash".to_string(),
+                exit_code: Some(exit_code),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn test_executed_commands() {
+        let entries = [
+            HistoryEntry::new(
+                UserMessage::new_prompt("Build it".to_string(), None),
+                AssistantMessage::new_tool_use(None, "Building".to_string(), vec![
+                    command_tool_use("1", "cargo build"),
+                    command_tool_use("2", "cargo tset"),
+                ]),
+                None,
+            ),
+            HistoryEntry::new(
+                UserMessage::new_tool_use_results(vec![result("1", 0), result("2", 101)]),
+                AssistantMessage::new_tool_use(None, "Cleaning".to_string(), vec![command_tool_use(
+                    "3",
+                    "rm -rf target",
+                )]),
+                None,
+            ),
+            HistoryEntry::new(
+                UserMessage::new_cancelled_tool_uses(Some("Don't".to_string()), ["3"].into_iter(), None),
+                AssistantMessage::new_response(None, "Ok".to_string()),
+                None,
+            ),
+        ];
+
+        let commands = executed_commands(entries.iter());
+        assert_eq!(
+            commands
+                .iter()
+                .map(|c| (c.command.as_str(), c.success))
+                .collect::<Vec<_>>(),
+            vec![("cargo build", true), ("cargo tset", false)]
+        );
+        assert_eq!(commands[0].summary.as_deref(), Some("Run cargo build"));
+        assert!(commands[0].cwd.is_some());
+    }
+
+    #[test]
+    fn test_to_script() {
+        let command = |command: &str, cwd: &str, success: bool| ExecutedCommand {
+            command: command.to_string(),
+            summary: None,
+            cwd: Some(cwd.to_string()),
+            exit_code: Some(if success { 0 } else { 1 }),
+            success,
+        };
+        let commands = [
+            command("cd sub && make", "/home/alice/my project", true),
+            command("make test", "/home/alice/my project", false),
+            command("ls", "/tmp", true),
+        ];
+        let env = HashMap::from([
+            ("GITHUB_TOKEN".to_string(), "ghp_secret".to_string()),
+            (
+                "DATABASE_URL".to_string(),
+                "postgres://app:${secret:db-password}@db".to_string(),
+            ),
+            ("PATH".to_string(), "${HOME}/bin:$PATH".to_string()),
+            ("RUST_LOG".to_string(), "debug info".to_string()),
+        ]);
+
+        let script = to_script(&commands, &env, Some("/home/alice"));
+        let body = script.split_once("set -e\n").unwrap().1;
+        assert_eq!(
+            body,
+            "\n# Environment set for the commands with /env\n\
+             : \"${DATABASE_URL:?DATABASE_URL must be set}\"\n\
+             : \"${GITHUB_TOKEN:?GITHUB_TOKEN must be set}\"\n\
+             export PATH=\"${HOME}/bin:$PATH\"\n\
+             export RUST_LOG='debug info'\n\
+             \n\
+             cd \"$HOME\"'/my project'\n\
+             cd sub && make\n\
+             \n\
+             cd \"$HOME\"'/my project'\n\
+             # Failed with exit code 1:\n\
+             # make test\n\
+             \n\
+             cd /tmp\n\
+             ls\n"
+        );
+        assert!(!script.contains("ghp_secret"));
+        assert!(!script.contains("secret:"));
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("src/main.rs"), "src/main.rs");
+        assert_eq!(env_value("a b"), "'a b'");
+        assert_eq!(
+            env_value("${HOME}/\"$(rm -rf /)\""),
+            "\"${HOME}/\\\"\\$(rm -rf /)\\\"\""
+        );
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(quote_path("/home/alice", Some("/home/alice")), "\"$HOME\"");
+        assert_eq!(quote_path("/home/alicex", Some("/home/alice")), "/home/alicex");
+    }
+}
@@ -0,0 +1,225 @@
+use clap::{
+    Args,
+    ValueEnum,
+};
+use crossterm::style::{
+    self,
+    Stylize,
+};
+use crossterm::{
+    cursor,
+    execute,
+};
+
+use super::share::Redactor;
+use crate::cli::chat::util::issue::IssueCreator;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+    pins,
+};
+use crate::os::Os;
+use crate::telemetry::core::FeedbackSentiment;
+use crate::telemetry::{
+    TelemetryCategory,
+    TelemetryConsent,
+};
+use crate::theme::StyledText;
+
+/// Most of the prompt and of the response included with feedback, in characters each
+const MAX_CONTEXT_CHARS: usize = 1_500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FeedbackKind {
+    /// The last response was helpful
+    #[value(aliases = ["+", "👍"])]
+    Up,
+    /// The last response was not helpful
+    #[value(aliases = ["-", "👎"])]
+    Down,
+    /// Open a GitHub issue pre-filled with the comment and diagnostics
+    Issue,
+}
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+/// Arguments for the feedback command that rates the last response or reports an issue about it.
+pub struct FeedbackArgs {
+    /// Rate the last response, or report an issue about it
+    #[arg(value_enum)]
+    pub kind: FeedbackKind,
+    /// What was good or wrong about the response
+    #[arg(trailing_var_arg = true)]
+    pub comment: Vec<String>,
+}
+
+impl FeedbackArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let sentiment = match self.kind {
+            FeedbackKind::Up => Some(FeedbackSentiment::Positive),
+            FeedbackKind::Down => Some(FeedbackSentiment::Negative),
+            FeedbackKind::Issue => None,
+        };
+        let consent = TelemetryConsent::from_settings(&os.database.settings);
+        if sentiment.is_some() && !(consent.transmits() && consent.collects(TelemetryCategory::Usage)) {
+            execute!(
+                session.stderr,
+                StyledText::warning_fg(),
+                style::Print("\nFeedback is sent with telemetry, which is disabled. Use "),
+                StyledText::brand_fg(),
+                style::Print("/feedback issue"),
+                StyledText::warning_fg(),
+                style::Print(" to report an issue on GitHub instead.\n\n"),
+                StyledText::reset(),
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
+        let comment = self.comment.join(" ").trim().to_string();
+        let context = match pins::turns(session.conversation.history()).pop() {
+            Some((prompt, response)) => {
+                let question = match sentiment {
+                    Some(_) => "Include your last prompt and the response with the feedback? They are sent to AWS",
+                    None => "Include your last prompt and the response in the issue? They will be public on GitHub",
+                };
+                execute!(
+                    session.stderr,
+                    StyledText::secondary_fg(),
+                    style::Print(format!("\n{question}, with known secret formats redacted. ")),
+                    style::Print("["),
+                    StyledText::success_fg(),
+                    style::Print("y"),
+                    StyledText::secondary_fg(),
+                    style::Print("/"),
+                    StyledText::success_fg(),
+                    style::Print("n"),
+                    StyledText::secondary_fg(),
+                    style::Print("]:\n\n"),
+                    StyledText::reset(),
+                    cursor::Show,
+                )?;
+                let user_input = session
+                    .read_user_input("> ".yellow().to_string().as_str(), true)?
+                    .unwrap_or_default();
+                ["y", "Y"]
+                    .contains(&user_input.as_str())
+                    .then(|| feedback_context(&prompt, &response, &mut Redactor::from_env(os)))
+            },
+            None => None,
+        };
+
+        let conversation_id = session.conversation.conversation_id().to_string();
+        match sentiment {
+            Some(sentiment) => {
+                let comment = [Some(comment), context]
+                    .into_iter()
+                    .flatten()
+                    .filter(|part| !part.is_empty())
+                    .collect::<Vec<_>>()
+                    .join("\n\n");
+                if let Err(err) = os
+                    .telemetry
+                    .send_chat_feedback(
+                        &os.database,
+                        conversation_id,
+                        sentiment,
+                        (!comment.is_empty()).then_some(comment),
+                    )
+                    .await
+                {
+                    tracing::warn!(?err, "Failed to send feedback");
+                }
+                execute!(
+                    session.stderr,
+                    StyledText::success_fg(),
+                    style::Print("\n✔ Thanks for the feedback!\n\n"),
+                    StyledText::reset(),
+                )?;
+            },
+            None => {
+                let title = match comment.is_empty() {
+                    true => "Feedback on a chat response".to_string(),
+                    false => comment.clone(),
+                };
+                let actual_behavior = context.map(|context| format!("```\n{}\n```", context.replace("```", r"\```")));
+                let model = session.conversation.model_info.as_ref().map(|m| m.model_id.as_str());
+                let failed_request_ids = match session.failed_request_ids.is_empty() {
+                    true => "none".to_string(),
+                    false => session.failed_request_ids.join("\n"),
+                };
+                let _ = IssueCreator {
+                    title: Some(title),
+                    expected_behavior: None,
+                    actual_behavior,
+                    steps_to_reproduce: None,
+                    additional_environment: Some(format!(
+                        "[chat-conversation]\nconversation_id={conversation_id}\nmodel={}\n\n[chat-failed_request_ids]\n{failed_request_ids}",
+                        model.unwrap_or("default")
+                    )),
+                }
+                .create_url(os)
+                .await;
+            },
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+/// The prompt and response shared with feedback, redacted and cut to [MAX_CONTEXT_CHARS] each.
+fn feedback_context(prompt: &str, response: &str, redactor: &mut Redactor) -> String {
+    let truncate = |text: String| match text.char_indices().nth(MAX_CONTEXT_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    };
+    format!(
+        "Prompt:\n{}\n\nResponse:\n{}",
+        truncate(redactor.redact(prompt)),
+        truncate(redactor.redact(response))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::cli::chat::cli::SlashCommand;
+
+    #[test]
+    fn test_feedback_args() {
+        for (args, kind) in [
+            ("+", FeedbackKind::Up),
+            ("👎", FeedbackKind::Down),
+            ("issue", FeedbackKind::Issue),
+        ] {
+            let command = SlashCommand::try_parse_from(["", "feedback", args, "wrong", "flag"]).unwrap();
+            assert_eq!(
+                command,
+                SlashCommand::Feedback(FeedbackArgs {
+                    kind,
+                    comment: vec!["wrong".to_string(), "flag".to_string()],
+                })
+            );
+        }
+        assert!(SlashCommand::try_parse_from(["", "feedback"]).is_err());
+    }
+
+    #[test]
+    fn test_feedback_context() {
+        let mut redactor = Redactor::new(Some("/home/alice".to_string()), ["hunter2-hunter2".to_string()]);
+        let context = feedback_context(
+            "Why does /home/alice/app fail with hunter2-hunter2?",
+            &"x".repeat(MAX_CONTEXT_CHARS + 10),
+            &mut redactor,
+        );
+        assert!(context.starts_with("Prompt:\nWhy does ~/app fail with [REDACTED]?\n\nResponse:\n"));
+        assert!(context.ends_with(&format!("{}…", "x".repeat(10))));
+        assert_eq!(context.matches('x').count(), MAX_CONTEXT_CHARS);
+    }
+}
@@ -0,0 +1,68 @@
+use clap::Args;
+use crossterm::{
+    execute,
+    style,
+};
+
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+use crate::theme::StyledText;
+use crate::util::security_scan::{
+    ScanReport,
+    fix_prompt,
+};
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+/// Arguments for the fix command that asks the agent to fix a finding of the last `q scan`.
+pub struct FixArgs {
+    /// Id of the finding, such as F1. Lists the findings if omitted
+    pub id: Option<String>,
+}
+
+impl FixArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let report = ScanReport::load(os)
+            .await
+            .map_err(|err| ChatError::Custom(format!("Failed to read the scan findings: {err}").into()))?
+            .ok_or_else(|| {
+                ChatError::Custom("There are no scan findings, run q scan in this directory first".into())
+            })?;
+
+        let Some(id) = self.id else {
+            for finding in &report.findings {
+                execute!(
+                    session.stderr,
+                    StyledText::emphasis_fg(),
+                    style::Print(format!("{} ", finding.id)),
+                    StyledText::reset(),
+                    style::Print(format!(
+                        "[{}] {} ",
+                        finding.severity,
+                        finding.location().unwrap_or_default()
+                    )),
+                    StyledText::secondary_fg(),
+                    style::Print(format!("{}\n", finding.message)),
+                    StyledText::reset(),
+                )?;
+            }
+            execute!(session.stderr, style::Print("\n"))?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        };
+
+        match report.get(&id) {
+            Some(finding) => Ok(ChatState::HandleInput {
+                input: fix_prompt(finding),
+            }),
+            None => Err(ChatError::Custom(
+                format!("No finding with the id {id}, see the findings with /fix").into(),
+            )),
+        }
+    }
+}
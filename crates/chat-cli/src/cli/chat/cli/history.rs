@@ -0,0 +1,72 @@
+use clap::Args;
+use crossterm::{
+    execute,
+    style,
+};
+
+use crate::cli::chat::workspace::Workspace;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+    saved_conversations,
+};
+use crate::os::Os;
+use crate::theme::StyledText;
+use crate::util::CLI_BINARY_NAME;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+/// Arguments for the history command that lists the saved conversations of the workspace
+pub struct HistoryArgs {
+    /// List the conversations of every workspace
+    #[arg(long)]
+    pub all: bool,
+}
+
+impl HistoryArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let workspace = match session.conversation.workspace() {
+            Some(workspace) => workspace.clone(),
+            None => Workspace::detect(&os.env.current_dir()?).await,
+        };
+        let conversations = saved_conversations::load(os, (!self.all).then_some(&workspace))
+            .await
+            .map_err(|e| ChatError::Custom(format!("Failed to read the saved conversations: {e}").into()))?;
+
+        if conversations.is_empty() {
+            execute!(
+                session.stderr,
+                StyledText::secondary_fg(),
+                style::Print(format!("\nNo saved conversations in {}\n\n", workspace.root.display())),
+                StyledText::reset(),
+            )?;
+        } else {
+            execute!(session.stderr, style::Print("\n"))?;
+            let rows = saved_conversations::rows(os, &conversations);
+            for (conversation, row) in conversations.iter().zip(rows) {
+                let current = conversation.conversation_id == session.conversation.conversation_id();
+                execute!(
+                    session.stderr,
+                    StyledText::success_fg(),
+                    style::Print(if current { "● " } else { "  " }),
+                    StyledText::reset(),
+                    style::Print(format!("{row}\n")),
+                )?;
+            }
+            execute!(
+                session.stderr,
+                StyledText::secondary_fg(),
+                style::Print(format!(
+                    "\nRun {CLI_BINARY_NAME} chat --list{} to resume one of them\n\n",
+                    if self.all { " --all" } else { "" }
+                )),
+                StyledText::reset(),
+            )?;
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
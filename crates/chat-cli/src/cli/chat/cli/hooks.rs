@@ -31,6 +31,7 @@ use spinners::{
     Spinners,
 };
 
+use crate::cli::agent::context_templates::ContextTemplates;
 use crate::cli::agent::hook::{
     Hook,
     HookTrigger,
@@ -52,8 +53,46 @@ use crate::util::pattern_matching::matches_any_pattern;
 /// Output is stdout if exit_code is 0, stderr otherwise.
 pub type HookOutput = (i32, String);
 
+/// Formats hook output to be used within context blocks (e.g., in context messages or in new user
+/// prompts).
+///
+/// # Returns
+/// [Option::Some] if `hook_results` is not empty and at least one hook has content. Otherwise,
+/// [Option::None]
+pub fn format_hook_context(
+    hook_results: &[((HookTrigger, Hook), HookOutput)],
+    trigger: HookTrigger,
+    templates: &ContextTemplates,
+) -> Option<String> {
+    // Note: only format context when hook command exit code is 0
+    if hook_results
+        .iter()
+        .all(|(_, (exit_code, content))| *exit_code != 0 || content.is_empty())
+    {
+        return None;
+    }
+
+    let mut output = String::new();
+    for (_, (_, hook_output)) in hook_results
+        .iter()
+        .filter(|((h_trigger, _), (exit_code, _))| *h_trigger == trigger && *exit_code == 0)
+    {
+        output.push_str(&format!("{hook_output}\n\n"));
+    }
+    Some(templates.render_entry(&templates.render_hooks(trigger, &output)))
+}
+
+/// The message sent to the model in place of the tool result when a preToolUse hook exits with
+/// code 2, blocking the tool.
+///
+/// # Returns
+/// [Option::None] if the hook does not block the tool.
+pub fn pre_tool_use_block_message(exit_code: i32, output: &str) -> Option<String> {
+    (exit_code == 2).then(|| format!("PreToolHook blocked the tool execution: {output}"))
+}
+
 /// Check if a hook matches a tool name based on its matcher pattern
-fn hook_matches_tool(hook: &Hook, tool_name: &str) -> bool {
+pub fn hook_matches_tool(hook: &Hook, tool_name: &str) -> bool {
     match &hook.matcher {
         None => true, // No matcher means the hook runs for all tools
         Some(pattern) => {
@@ -101,11 +140,16 @@ pub struct CachedHook {
 #[derive(Debug, Clone, Default)]
 pub struct HookExecutor {
     pub cache: HashMap<(HookTrigger, Hook), CachedHook>,
+    /// Environment variables set on top of the inherited environment of every hook
+    pub env: HashMap<String, String>,
 }
 
 impl HookExecutor {
     pub fn new() -> Self {
-        Self { cache: HashMap::new() }
+        Self {
+            cache: HashMap::new(),
+            env: HashMap::new(),
+        }
     }
 
     /// Run and cache [`Hook`]s. Any hooks that are already cached will be returned without
@@ -264,7 +308,8 @@ impl HookExecutor {
         Ok(results)
     }
 
-    async fn run_hook(
+    /// Runs a single hook, without filtering by matcher or using the cache.
+    pub async fn run_hook(
         &self,
         hook: (HookTrigger, Hook),
         cwd: &str,
@@ -295,30 +340,18 @@ impl HookExecutor {
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
 
-        let timeout = Duration::from_millis(hook.1.timeout_ms);
+        cmd.envs(&self.env);
 
-        // Generate hook command input in JSON format
-        let mut hook_input = serde_json::json!({
-            "hook_event_name": hook.0.to_string(),
-            "cwd": cwd
-        });
+        let timeout = Duration::from_millis(hook.1.timeout_ms);
 
-        // Set USER_PROMPT environment variable and add to JSON input if provided
+        // Set USER_PROMPT environment variable if provided
         if let Some(prompt) = prompt {
             // Sanitize the prompt to avoid issues with special characters
             let sanitized_prompt = sanitize_user_prompt(prompt);
             cmd.env("USER_PROMPT", sanitized_prompt);
-            hook_input["prompt"] = serde_json::Value::String(prompt.to_string());
         }
 
-        // ToolUse specific input
-        if let Some(tool_ctx) = tool_context {
-            hook_input["tool_name"] = serde_json::Value::String(tool_ctx.tool_name);
-            hook_input["tool_input"] = tool_ctx.tool_input;
-            if let Some(response) = tool_ctx.tool_response {
-                hook_input["tool_response"] = response;
-            }
-        }
+        let hook_input = hook_input(hook.0, cwd, prompt, tool_context);
         let json_input = serde_json::to_string(&hook_input).unwrap_or_default();
 
         // Build a future for hook command w/ the JSON input passed in through STDIN
@@ -376,6 +409,33 @@ impl HookExecutor {
     }
 }
 
+/// Returns the JSON a hook receives on stdin.
+pub fn hook_input(
+    trigger: HookTrigger,
+    cwd: &str,
+    prompt: Option<&str>,
+    tool_context: Option<ToolContext>,
+) -> serde_json::Value {
+    let mut hook_input = serde_json::json!({
+        "hook_event_name": trigger.to_string(),
+        "cwd": cwd
+    });
+
+    if let Some(prompt) = prompt {
+        hook_input["prompt"] = serde_json::Value::String(prompt.to_string());
+    }
+
+    // ToolUse specific input
+    if let Some(tool_ctx) = tool_context {
+        hook_input["tool_name"] = serde_json::Value::String(tool_ctx.tool_name);
+        hook_input["tool_input"] = tool_ctx.tool_input;
+        if let Some(response) = tool_ctx.tool_response {
+            hook_input["tool_response"] = response;
+        }
+    }
+    hook_input
+}
+
 /// Sanitizes a string value to be used as an environment variable
 fn sanitize_user_prompt(input: &str) -> String {
     let truncated = truncate_safe(input, 4096);
@@ -742,6 +802,37 @@ mod tests {
         assert!(hook_output.contains("Turn completed successfully"));
     }
 
+    #[tokio::test]
+    async fn test_hook_env() {
+        let mut executor = HookExecutor::new();
+        executor
+            .env
+            .insert("HOOK_ENV_TEST".to_string(), "from_session".to_string());
+        let mut output = Vec::new();
+
+        #[cfg(unix)]
+        let command = "echo $HOOK_ENV_TEST";
+        #[cfg(windows)]
+        let command = "echo %HOOK_ENV_TEST%";
+
+        let hook = Hook {
+            command: command.to_string(),
+            timeout_ms: 5000,
+            cache_ttl_seconds: 0,
+            max_output_size: 1000,
+            matcher: None,
+            source: crate::cli::agent::hook::Source::Session,
+        };
+
+        let hooks = HashMap::from([(HookTrigger::Stop, vec![hook])]);
+        let results = executor.run_hooks(hooks, &mut output, ".", None, None).await.unwrap();
+
+        assert_eq!(results.len(), 1);
+        let (_, (exit_code, hook_output)) = &results[0];
+        assert_eq!(*exit_code, 0);
+        assert!(hook_output.contains("from_session"));
+    }
+
     #[test]
     fn test_sanitize_user_prompt_cjk_characters() {
         // Test with CJK characters that would cause panic with naive byte slicing
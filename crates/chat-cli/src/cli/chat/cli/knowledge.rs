@@ -20,7 +20,14 @@ use crate::cli::experiment::experiment_manager::{
 };
 use crate::os::Os;
 use crate::theme::StyledText;
-use crate::util::knowledge_store::KnowledgeStore;
+use crate::util::knowledge_store::{
+    KnowledgeStore,
+    is_url,
+    scoped_knowledge_bases,
+};
+
+/// Maximum number of results printed by `/knowledge search`
+const SEARCH_RESULT_LIMIT: usize = 10;
 
 /// Knowledge base management commands
 #[derive(Clone, Debug, PartialEq, Eq, Subcommand)]
@@ -32,7 +39,7 @@ pub enum KnowledgeSubcommand {
         /// Name for the knowledge base entry
         #[arg(long, short = 'n')]
         name: String,
-        /// Path to file or directory to add
+        /// Path to file or directory to add, or an http(s) url of a document to download
         #[arg(long, short = 'p')]
         path: String,
         /// Include patterns (e.g., `**/*.ts`, `**/*.md`)
@@ -45,6 +52,14 @@ pub enum KnowledgeSubcommand {
         #[arg(long)]
         index_type: Option<String>,
     },
+    /// Search the knowledge base
+    Search {
+        /// The search query
+        query: Vec<String>,
+        /// Name of a knowledge base entry to restrict the search to
+        #[arg(long, short = 'n')]
+        name: Option<String>,
+    },
     /// Remove specified knowledge base entry by path
     #[command(alias = "rm")]
     Remove { path: String },
@@ -122,6 +137,9 @@ impl KnowledgeSubcommand {
                 exclude,
                 index_type,
             } => Self::handle_add(os, session, name, path, include, exclude, index_type).await,
+            KnowledgeSubcommand::Search { query, name } => {
+                Self::handle_search(os, session, &query.join(" "), name.as_deref()).await
+            },
             KnowledgeSubcommand::Remove { path } => Self::handle_remove(os, session, path).await,
             KnowledgeSubcommand::Update { path } => Self::handle_update(os, session, path).await,
             KnowledgeSubcommand::Clear => Self::handle_clear(os, session).await,
@@ -262,7 +280,13 @@ impl KnowledgeSubcommand {
         exclude_patterns: &[String],
         index_type: &Option<String>,
     ) -> OperationResult {
-        match Self::validate_and_sanitize_path(os, path) {
+        let sanitized = if is_url(path) {
+            Ok(path.to_string())
+        } else {
+            Self::validate_and_sanitize_path(os, path)
+        };
+
+        match sanitized {
             Ok(sanitized_path) => {
                 let agent = Self::get_agent(session);
 
@@ -296,7 +320,13 @@ impl KnowledgeSubcommand {
                     .with_exclude_patterns(exclude)
                     .with_embedding_type(embedding_type_resolved);
 
-                match store.add(name, &sanitized_path.clone(), options).await {
+                let added = if is_url(&sanitized_path) {
+                    store.add_url(name, &sanitized_path, options).await
+                } else {
+                    store.add(name, &sanitized_path, options).await
+                };
+
+                match added {
                     Ok(message) => OperationResult::Info(message),
                     Err(e) => {
                         if e.contains("Invalid include pattern") || e.contains("Invalid exclude pattern") {
@@ -311,6 +341,44 @@ impl KnowledgeSubcommand {
         }
     }
 
+    /// Handle search operation
+    async fn handle_search(os: &Os, session: &ChatSession, query: &str, name: Option<&str>) -> OperationResult {
+        if query.trim().is_empty() {
+            return OperationResult::Error("Please provide a search query".to_string());
+        }
+
+        let agent = Self::get_agent(session);
+        let async_knowledge_store = match KnowledgeStore::get_async_instance(os, agent).await {
+            Ok(store) => store,
+            Err(e) => return OperationResult::Error(format!("Error accessing knowledge base: {}", e)),
+        };
+        let store = async_knowledge_store.lock().await;
+
+        let names = match name {
+            Some(name) => vec![name.to_string()],
+            None => scoped_knowledge_bases(agent),
+        };
+
+        match store.search_scoped(query, &names).await {
+            Ok(results) if results.is_empty() => {
+                OperationResult::Warning(format!("No matching entries found for query: \"{}\"", query))
+            },
+            Ok(results) => {
+                let mut output = format!("Search results for \"{}\":\n", query);
+                for result in results.iter().take(SEARCH_RESULT_LIMIT) {
+                    if let Some(text) = result.text() {
+                        if let Some(path) = result.point.payload.get("path").and_then(|v| v.as_str()) {
+                            output.push_str(&format!("\n📄 {}\n", path));
+                        }
+                        output.push_str(&format!("{}\n", text.trim()));
+                    }
+                }
+                OperationResult::Info(output)
+            },
+            Err(e) => OperationResult::Error(format!("Search failed: {}", e)),
+        }
+    }
+
     /// Handle remove operation
     async fn handle_remove(os: &Os, session: &ChatSession, path: &str) -> OperationResult {
         let sanitized_path = sanitize_path_tool_arg(os, path);
@@ -536,6 +604,7 @@ impl KnowledgeSubcommand {
         match self {
             KnowledgeSubcommand::Show => "show",
             KnowledgeSubcommand::Add { .. } => "add",
+            KnowledgeSubcommand::Search { .. } => "search",
             KnowledgeSubcommand::Remove { .. } => "remove",
             KnowledgeSubcommand::Update { .. } => "update",
             KnowledgeSubcommand::Clear => "clear",
@@ -678,6 +747,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_search_command() {
+        let cli = TestCli::try_parse_from(["test", "search", "how", "to", "rotate", "keys"]).unwrap();
+        assert_eq!(cli.knowledge, KnowledgeSubcommand::Search {
+            query: vec!["how", "to", "rotate", "keys"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            name: None,
+        });
+
+        let cli = TestCli::try_parse_from(["test", "search", "--name", "runbooks", "deploy"]).unwrap();
+        assert_eq!(cli.knowledge, KnowledgeSubcommand::Search {
+            query: vec!["deploy".to_string()],
+            name: Some("runbooks".to_string()),
+        });
+    }
+
     #[test]
     fn test_multiple_exclude_patterns() {
         // Test multiple exclude patterns
@@ -1,29 +1,51 @@
 use std::io::Write;
 
-use clap::Args;
+use clap::{
+    Args,
+    Subcommand,
+};
 use crossterm::{
+    execute,
     queue,
     style,
 };
+use url::Url;
 
 use crate::cli::chat::tool_manager::LoadingRecord;
+use crate::cli::chat::tools::custom_tool::TransportType;
 use crate::cli::chat::{
     ChatError,
     ChatSession,
     ChatState,
 };
+use crate::mcp_client::compute_key;
+use crate::os::Os;
 use crate::theme::StyledText;
 
 /// Arguments for the MCP (Model Context Protocol) command.
 ///
 /// This struct handles MCP-related functionality, allowing users to view
-/// the status of MCP servers and their loading progress.
+/// the status of MCP servers and their loading progress, and to authorize
+/// remote servers again.
 #[deny(missing_docs)]
 #[derive(Debug, PartialEq, Args)]
-pub struct McpArgs;
+pub struct McpArgs {
+    #[command(subcommand)]
+    subcommand: Option<McpSubcommand>,
+}
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+enum McpSubcommand {
+    /// Forget the OAuth token of a remote server and go through its authorization flow again
+    Auth {
+        /// Name of the server in the agent config
+        server: String,
+    },
+}
 
 impl McpArgs {
-    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+    pub async fn execute(self, os: &mut Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
         if !session.conversation.mcp_enabled {
             queue!(
                 session.stderr,
@@ -39,52 +61,95 @@ impl McpArgs {
             });
         }
 
-        let terminal_width = session.terminal_width();
-        let still_loading = session
-            .conversation
-            .tool_manager
-            .pending_clients()
-            .await
-            .into_iter()
-            .map(|name| format!(" - {name}\n"))
-            .collect::<Vec<_>>()
-            .join("");
-
-        for (server_name, msg) in session.conversation.tool_manager.mcp_load_record.lock().await.iter() {
-            let msg = msg
-                .iter()
-                .map(|record| match record {
-                    LoadingRecord::Err(timestamp, content)
-                    | LoadingRecord::Warn(timestamp, content)
-                    | LoadingRecord::Success(timestamp, content) => format!("[{timestamp}]: {content}"),
-                })
-                .collect::<Vec<_>>()
-                .join("\n--- tools refreshed ---\n");
-
-            queue!(
-                session.stderr,
-                style::Print(server_name),
-                style::Print("\n"),
-                style::Print(format!("{}\n", "▔".repeat(terminal_width))),
-                style::Print(msg),
-                style::Print("\n")
-            )?;
-        }
-
-        if !still_loading.is_empty() {
-            queue!(
-                session.stderr,
-                style::Print("Still loading:\n"),
-                style::Print(format!("{}\n", "▔".repeat(terminal_width))),
-                style::Print(still_loading),
-                style::Print("\n")
-            )?;
+        match self.subcommand {
+            Some(McpSubcommand::Auth { server }) => authorize(os, session, &server).await?,
+            None => print_status(session).await?,
         }
 
-        session.stderr.flush()?;
-
         Ok(ChatState::PromptUser {
             skip_printing_tools: true,
         })
     }
 }
+
+/// Deletes the stored token of the remote `server` and reconnects the remote servers, which
+/// starts the authorization flow of `server` again.
+async fn authorize(os: &mut Os, session: &mut ChatSession, server: &str) -> Result<(), ChatError> {
+    let config = session
+        .conversation
+        .agents
+        .get_active()
+        .and_then(|agent| agent.mcp_servers.mcp_servers.get(server))
+        .ok_or_else(|| ChatError::Custom(format!("No MCP server named '{server}' in the current agent").into()))?;
+    if config.r#type != TransportType::Http {
+        return Err(ChatError::Custom(
+            format!("'{server}' is a local MCP server, only remote servers are authorized with OAuth").into(),
+        ));
+    }
+    let url = Url::parse(&config.url)
+        .map_err(|e| ChatError::Custom(format!("Invalid url for MCP server '{server}': {e}").into()))?;
+
+    os.database
+        .delete_mcp_oauth_token(&compute_key(&url))
+        .map_err(|e| ChatError::Custom(format!("Failed to delete the token of '{server}': {e}").into()))?;
+    execute!(
+        session.stderr,
+        StyledText::secondary_fg(),
+        style::Print(format!(
+            "\nReconnecting '{server}', follow the authorization flow opened in your browser.\n\n"
+        )),
+        StyledText::reset(),
+    )?;
+    session
+        .conversation
+        .reconnect_remote_mcp_servers(os, &mut session.stderr)
+        .await
+}
+
+async fn print_status(session: &mut ChatSession) -> Result<(), ChatError> {
+    let terminal_width = session.terminal_width();
+    let still_loading = session
+        .conversation
+        .tool_manager
+        .pending_clients()
+        .await
+        .into_iter()
+        .map(|name| format!(" - {name}\n"))
+        .collect::<Vec<_>>()
+        .join("");
+
+    for (server_name, msg) in session.conversation.tool_manager.mcp_load_record.lock().await.iter() {
+        let msg = msg
+            .iter()
+            .map(|record| match record {
+                LoadingRecord::Err(timestamp, content)
+                | LoadingRecord::Warn(timestamp, content)
+                | LoadingRecord::Success(timestamp, content) => format!("[{timestamp}]: {content}"),
+            })
+            .collect::<Vec<_>>()
+            .join("\n--- tools refreshed ---\n");
+
+        queue!(
+            session.stderr,
+            style::Print(server_name),
+            style::Print("\n"),
+            style::Print(format!("{}\n", "▔".repeat(terminal_width))),
+            style::Print(msg),
+            style::Print("\n")
+        )?;
+    }
+
+    if !still_loading.is_empty() {
+        queue!(
+            session.stderr,
+            style::Print("Still loading:\n"),
+            style::Print(format!("{}\n", "▔".repeat(terminal_width))),
+            style::Print(still_loading),
+            style::Print("\n")
+        )?;
+    }
+
+    session.stderr.flush()?;
+
+    Ok(())
+}
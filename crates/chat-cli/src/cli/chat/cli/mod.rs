@@ -1,44 +1,80 @@
 use crate::theme::StyledText;
+pub mod cache;
+pub mod capture;
 pub mod changelog;
 pub mod checkpoint;
 pub mod clear;
 pub mod compact;
 pub mod context;
+pub mod custom;
+pub mod cwd;
+pub mod diff;
+pub mod edit;
 pub mod editor;
+pub mod env;
 pub mod experiment;
+pub mod export_script;
+pub mod feedback;
+pub mod fix;
+pub mod history;
 pub mod hooks;
 pub mod knowledge;
 pub mod logdump;
 pub mod mcp;
 pub mod model;
+pub mod params;
 pub mod paste;
 pub mod persist;
+pub mod pin;
 pub mod profile;
 pub mod prompts;
 pub mod reply;
+pub mod retry;
+pub mod share;
 pub mod subscribe;
+pub mod syntax;
 pub mod tangent;
 pub mod todos;
 pub mod tools;
 pub mod usage;
 
+use cache::CacheSubcommand;
+use capture::CaptureArgs;
 use changelog::ChangelogArgs;
 use clap::Parser;
 use clear::ClearArgs;
 use compact::CompactArgs;
 use context::ContextSubcommand;
+use cwd::{
+    CdArgs,
+    PwdArgs,
+};
+use diff::DiffArgs;
+use edit::EditArgs;
 use editor::EditorArgs;
+use env::EnvSubcommand;
 use experiment::ExperimentArgs;
+use export_script::ExportScriptArgs;
+use feedback::FeedbackArgs;
+use fix::FixArgs;
+use history::HistoryArgs;
 use hooks::HooksArgs;
 use knowledge::KnowledgeSubcommand;
 use logdump::LogdumpArgs;
 use mcp::McpArgs;
 use model::ModelArgs;
+use params::ParamsArgs;
 use paste::PasteArgs;
 use persist::PersistSubcommand;
+use pin::{
+    PinArgs,
+    PinsArgs,
+};
 use profile::AgentSubcommand;
 use prompts::PromptsArgs;
 use reply::ReplyArgs;
+use retry::RetryArgs;
+use share::ShareArgs;
 use tangent::TangentArgs;
 use todos::TodoSubcommand;
 use tools::ToolsArgs;
@@ -75,19 +111,29 @@ pub enum SlashCommand {
     Context(ContextSubcommand),
     /// (Beta) Manage knowledge base for persistent context storage. Requires "q settings
     /// chat.enableKnowledge true"
-    #[command(subcommand, hide = true)]
+    #[command(subcommand, hide = true, alias = "kb")]
     Knowledge(KnowledgeSubcommand),
     /// Open $EDITOR (defaults to vi) to compose a prompt
     #[command(name = "editor")]
     PromptEditor(EditorArgs),
     /// Open $EDITOR with the most recent assistant message quoted for reply
     Reply(ReplyArgs),
+    /// Regenerate the response to the last prompt
+    Retry(RetryArgs),
+    /// Recall the last prompt for editing, and send it again in place of the original
+    Edit(EditArgs),
     /// Summarize the conversation to free up context space
     Compact(CompactArgs),
+    /// Pin a message so that compaction keeps it verbatim
+    Pin(PinArgs),
+    /// List pinned messages, or unpin them
+    Pins(PinsArgs),
     /// View tools and permissions
     Tools(ToolsArgs),
     /// Create a new Github issue or make a feature request
     Issue(issue::IssueArgs),
+    /// Rate the last response with up or down, or report an issue about it
+    Feedback(FeedbackArgs),
     /// Create a zip file with logs for support investigation
     Logdump(LogdumpArgs),
     /// View changelog for Amazon Q CLI
@@ -103,6 +149,8 @@ pub enum SlashCommand {
     Mcp(McpArgs),
     /// Select a model for the current conversation session
     Model(ModelArgs),
+    /// Show or set inference parameters such as the temperature for this conversation
+    Params(ParamsArgs),
     /// Toggle experimental features
     Experiment(ExperimentArgs),
     /// Upgrade to a Q Developer Pro subscription for increased query limits
@@ -127,6 +175,28 @@ pub enum SlashCommand {
     Todos(TodoSubcommand),
     /// Paste an image from clipboard
     Paste(PasteArgs),
+    /// Change the working directory of the chat session
+    Cd(CdArgs),
+    /// Print the working directory of the chat session
+    Pwd(PwdArgs),
+    /// List the saved conversations of this workspace
+    History(HistoryArgs),
+    /// Manage environment variables for shell commands, hooks, and MCP servers
+    #[command(subcommand)]
+    Env(EnvSubcommand),
+    /// Inspect the cache of tool results and MCP tool lists
+    #[command(subcommand)]
+    Cache(CacheSubcommand),
+    /// Attach recent terminal output to your next message
+    Capture(CaptureArgs),
+    /// Write a redacted copy of the conversation to attach to tickets
+    Share(ShareArgs),
+    /// Write the shell commands run during the conversation to a script
+    ExportScript(ExportScriptArgs),
+    /// Ask the agent to fix a finding of the last q scan
+    Fix(FixArgs),
+    /// Show the changes made to files during the last turn
+    Diff(DiffArgs),
 }
 
 impl SlashCommand {
@@ -162,8 +232,12 @@ impl SlashCommand {
             Self::Knowledge(subcommand) => subcommand.execute(os, session).await,
             Self::PromptEditor(args) => args.execute(session).await,
             Self::Reply(args) => args.execute(session).await,
+            Self::Retry(args) => args.execute(os, session).await,
+            Self::Edit(args) => args.execute(os, session).await,
             Self::Compact(args) => args.execute(os, session).await,
-            Self::Tools(args) => args.execute(session).await,
+            Self::Pin(args) => args.execute(session).await,
+            Self::Pins(args) => args.execute(session).await,
+            Self::Tools(args) => args.execute(os, session).await,
             Self::Issue(args) => {
                 if let Err(err) = args.execute(os).await {
                     return Err(ChatError::Custom(err.to_string().into()));
@@ -173,13 +247,15 @@ impl SlashCommand {
                     skip_printing_tools: true,
                 })
             },
+            Self::Feedback(args) => args.execute(os, session).await,
             Self::Logdump(args) => args.execute(session).await,
             Self::Changelog(args) => args.execute(session).await,
             Self::Prompts(args) => args.execute(os, session).await,
             Self::Hooks(args) => args.execute(session).await,
             Self::Usage(args) => args.execute(os, session).await,
-            Self::Mcp(args) => args.execute(session).await,
+            Self::Mcp(args) => args.execute(os, session).await,
             Self::Model(args) => args.execute(os, session).await,
+            Self::Params(args) => args.execute(session).await,
             Self::Experiment(args) => args.execute(os, session).await,
             Self::Subscribe(args) => args.execute(os, session).await,
             Self::Tangent(args) => args.execute(os, session).await,
@@ -196,6 +272,16 @@ impl SlashCommand {
             Self::Checkpoint(subcommand) => subcommand.execute(os, session).await,
             Self::Todos(subcommand) => subcommand.execute(os, session).await,
             Self::Paste(args) => args.execute(os, session).await,
+            Self::Cd(args) => args.execute(os, session).await,
+            Self::Pwd(args) => args.execute(os, session).await,
+            Self::History(args) => args.execute(os, session).await,
+            Self::Env(subcommand) => subcommand.execute(os, session).await,
+            Self::Cache(subcommand) => subcommand.execute(session).await,
+            Self::Capture(args) => args.execute(os, session).await,
+            Self::Share(args) => args.execute(os, session).await,
+            Self::ExportScript(args) => args.execute(os, session).await,
+            Self::Fix(args) => args.execute(os, session).await,
+            Self::Diff(args) => args.execute(session).await,
         }
     }
 
@@ -209,9 +295,14 @@ impl SlashCommand {
             Self::Knowledge(_) => "knowledge",
             Self::PromptEditor(_) => "editor",
             Self::Reply(_) => "reply",
+            Self::Retry(_) => "retry",
+            Self::Edit(_) => "edit",
             Self::Compact(_) => "compact",
+            Self::Pin(_) => "pin",
+            Self::Pins(_) => "pins",
             Self::Tools(_) => "tools",
             Self::Issue(_) => "issue",
+            Self::Feedback(_) => "feedback",
             Self::Logdump(_) => "logdump",
             Self::Changelog(_) => "changelog",
             Self::Prompts(_) => "prompts",
@@ -219,6 +310,7 @@ impl SlashCommand {
             Self::Usage(_) => "usage",
             Self::Mcp(_) => "mcp",
             Self::Model(_) => "model",
+            Self::Params(_) => "params",
             Self::Experiment(_) => "experiment",
             Self::Subscribe(_) => "subscribe",
             Self::Tangent(_) => "tangent",
@@ -229,6 +321,16 @@ impl SlashCommand {
             Self::Checkpoint(_) => "checkpoint",
             Self::Todos(_) => "todos",
             Self::Paste(_) => "paste",
+            Self::Cd(_) => "cd",
+            Self::Pwd(_) => "pwd",
+            Self::History(_) => "history",
+            Self::Env(_) => "env",
+            Self::Cache(_) => "cache",
+            Self::Capture(_) => "capture",
+            Self::Share(_) => "share",
+            Self::ExportScript(_) => "export-script",
+            Self::Fix(_) => "fix",
+            Self::Diff(_) => "diff",
         }
     }
 
@@ -237,6 +339,8 @@ impl SlashCommand {
             SlashCommand::Agent(sub) => Some(sub.name()),
             SlashCommand::Context(sub) => Some(sub.name()),
             SlashCommand::Knowledge(sub) => Some(sub.name()),
+            SlashCommand::Env(sub) => Some(sub.name()),
+            SlashCommand::Cache(sub) => Some(sub.name()),
             SlashCommand::Tools(arg) => arg.subcommand_name(),
             SlashCommand::Prompts(arg) => arg.subcommand_name(),
             _ => None,
@@ -0,0 +1,115 @@
+use agent::agent_config::definitions::InferenceParams;
+use clap::{
+    Args,
+    Subcommand,
+};
+use crossterm::{
+    execute,
+    style,
+};
+
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::theme::StyledText;
+
+/// Whether the backend chat sends requests to takes inference parameters. Chat only sends requests
+/// to Amazon Q, which doesn't.
+const BACKEND_SUPPORTS_PARAMS: bool = false;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+/// Arguments for the params command that shows and sets the inference parameters of the
+/// conversation.
+pub struct ParamsArgs {
+    #[command(subcommand)]
+    subcommand: Option<ParamsSubcommand>,
+}
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+enum ParamsSubcommand {
+    /// Set parameters, e.g. `/params set temperature=0.2 top_p=0.9`
+    Set {
+        /// Parameters as name=value, one of temperature, top_p or max_tokens
+        #[arg(required = true)]
+        params: Vec<String>,
+    },
+    /// Go back to the defaults of the model
+    Reset,
+}
+
+impl ParamsArgs {
+    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        match self.subcommand {
+            None => (),
+            Some(ParamsSubcommand::Set { .. }) if !BACKEND_SUPPORTS_PARAMS => {
+                return Err(ChatError::Custom(
+                    "Amazon Q models don't support inference parameters, so they can't be set".into(),
+                ));
+            },
+            Some(ParamsSubcommand::Set { params }) => {
+                session.conversation.inference_params = parse_params(session.conversation.inference_params, &params)
+                    .map_err(|err| ChatError::Custom(err.into()))?;
+            },
+            Some(ParamsSubcommand::Reset) => session.conversation.inference_params = InferenceParams::default(),
+        }
+
+        execute!(
+            session.stderr,
+            style::Print("\nInference parameters: "),
+            StyledText::success_fg(),
+            style::Print(format!("{}\n", session.conversation.inference_params)),
+            StyledText::reset(),
+        )?;
+        if !BACKEND_SUPPORTS_PARAMS && !session.conversation.inference_params.is_empty() {
+            execute!(
+                session.stderr,
+                StyledText::secondary_fg(),
+                style::Print(
+                    "Amazon Q models don't support inference parameters, so they are ignored. \
+                     Use /params reset to clear them.\n"
+                ),
+                StyledText::reset(),
+            )?;
+        }
+        execute!(session.stderr, style::Print("\n"))?;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+/// Applies `name=value` pairs to `current`, leaving it unchanged if any pair is invalid.
+fn parse_params(mut current: InferenceParams, params: &[String]) -> Result<InferenceParams, String> {
+    for param in params {
+        let Some((name, value)) = param.split_once('=') else {
+            return Err(format!("Expected name=value, found '{param}'"));
+        };
+        current.set(name.trim(), value.trim())?;
+    }
+    Ok(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_params() {
+        let current = InferenceParams {
+            max_tokens: Some(512),
+            ..Default::default()
+        };
+        let params = parse_params(current, &["temperature=0.2".to_string(), "top_p = 0.9".to_string()]).unwrap();
+        assert_eq!(params.temperature, Some(0.2));
+        assert_eq!(params.top_p, Some(0.9));
+        assert_eq!(params.max_tokens, Some(512));
+
+        assert!(parse_params(current, &["temperature".to_string()]).is_err());
+        assert!(parse_params(current, &["temperature=hot".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,171 @@
+use clap::{
+    Args,
+    Subcommand,
+};
+use crossterm::{
+    execute,
+    style,
+};
+
+use crate::cli::chat::pins::{
+    self,
+    PinnedMessage,
+    PinnedRole,
+};
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::theme::StyledText;
+
+/// Characters of each pinned message shown by `/pins`
+const PREVIEW_CHARS: usize = 80;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+/// Arguments for the pin command that keeps a message through compaction.
+pub struct PinArgs {
+    /// Turn to pin the response of, counting back from 1 for the most recent turn
+    #[arg(default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub turn: u32,
+    /// Pin the prompt of the turn instead of its response
+    #[arg(long)]
+    pub prompt: bool,
+}
+
+impl PinArgs {
+    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let turns = pins::turns(session.conversation.history());
+        let Some((prompt, response)) = turns.into_iter().rev().nth(self.turn as usize - 1) else {
+            execute!(
+                session.stderr,
+                StyledText::warning_fg(),
+                style::Print(format!(
+                    "\nThere is no turn {} in the conversation history.\n\n",
+                    self.turn
+                )),
+                StyledText::reset(),
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        };
+
+        let message = match self.prompt {
+            true => PinnedMessage {
+                role: PinnedRole::User,
+                content: prompt,
+            },
+            false => PinnedMessage {
+                role: PinnedRole::Assistant,
+                content: response,
+            },
+        };
+        if message.content.trim().is_empty() {
+            return Err(ChatError::Custom(
+                "The message is empty, there is nothing to pin".into(),
+            ));
+        }
+
+        let preview = message.preview(PREVIEW_CHARS);
+        let status = match session.conversation.pin(message) {
+            true => "Pinned",
+            false => "Already pinned",
+        };
+        execute!(
+            session.stderr,
+            StyledText::success_fg(),
+            style::Print(format!("\n{status}: ")),
+            StyledText::reset(),
+            style::Print(format!("{preview}\n\n")),
+        )?;
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+/// Arguments for the pins command that lists and removes pinned messages.
+pub struct PinsArgs {
+    #[command(subcommand)]
+    subcommand: Option<PinsSubcommand>,
+}
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+enum PinsSubcommand {
+    /// Unpin a message, by its number in the list
+    Rm {
+        /// Number of the pinned message as listed by /pins
+        #[arg(value_parser = clap::value_parser!(u32).range(1..))]
+        number: u32,
+    },
+    /// Unpin all messages
+    Clear,
+}
+
+impl PinsArgs {
+    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        match self.subcommand {
+            None => {
+                let pinned = session.conversation.pinned();
+                if pinned.is_empty() {
+                    execute!(
+                        session.stderr,
+                        StyledText::secondary_fg(),
+                        style::Print("\nNo messages are pinned. Pin one with /pin.\n\n"),
+                        StyledText::reset(),
+                    )?;
+                } else {
+                    execute!(session.stderr, style::Print("\n"))?;
+                    for (i, pin) in pinned.iter().enumerate() {
+                        let role = match pin.role {
+                            PinnedRole::User => "prompt",
+                            PinnedRole::Assistant => "response",
+                        };
+                        execute!(
+                            session.stderr,
+                            StyledText::emphasis_fg(),
+                            style::Print(format!("{:>3}. ", i + 1)),
+                            StyledText::secondary_fg(),
+                            style::Print(format!("{role:<9}")),
+                            StyledText::reset(),
+                            style::Print(format!("{}\n", pin.preview(PREVIEW_CHARS))),
+                        )?;
+                    }
+                    execute!(session.stderr, style::Print("\n"))?;
+                }
+            },
+            Some(PinsSubcommand::Rm { number }) => match session.conversation.unpin(number as usize - 1) {
+                Some(pin) => execute!(
+                    session.stderr,
+                    StyledText::success_fg(),
+                    style::Print("\nUnpinned: "),
+                    StyledText::reset(),
+                    style::Print(format!("{}\n\n", pin.preview(PREVIEW_CHARS))),
+                )?,
+                None => execute!(
+                    session.stderr,
+                    StyledText::warning_fg(),
+                    style::Print(format!("\nThere is no pinned message {number}.\n\n")),
+                    StyledText::reset(),
+                )?,
+            },
+            Some(PinsSubcommand::Clear) => {
+                session.conversation.clear_pins();
+                execute!(
+                    session.stderr,
+                    StyledText::success_fg(),
+                    style::Print("\nUnpinned all messages.\n\n"),
+                    StyledText::reset(),
+                )?;
+            },
+        }
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
@@ -204,6 +204,11 @@ impl Prompts {
     }
 }
 
+/// Names of the saved prompts in the global and workspace prompt directories
+pub fn saved_prompt_names(os: &Os) -> Result<Vec<String>, GetPromptError> {
+    Prompts::get_available_names(os)
+}
+
 /// Validate prompt name to ensure it's safe and follows naming conventions
 fn validate_prompt_name(name: &str) -> Result<(), String> {
     // Check for empty name
@@ -0,0 +1,111 @@
+use clap::Args;
+use crossterm::{
+    execute,
+    style,
+};
+
+use crate::cli::chat::cli::model::{
+    find_model,
+    get_available_models,
+};
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+use crate::theme::StyledText;
+
+/// Most of the previous response sent along with `/retry --vary`, in bytes
+const MAX_PREVIOUS_RESPONSE_BYTES: usize = 20_000;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+/// Arguments for the retry command that regenerates the response to the last prompt.
+pub struct RetryArgs {
+    /// Ask for a different response than the previous one, instead of the same request again
+    #[arg(long)]
+    pub vary: bool,
+    /// Model to regenerate the response with, which stays selected afterwards
+    #[arg(long)]
+    pub model: Option<String>,
+}
+
+impl RetryArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        if let Some(name) = &self.model {
+            let (models, _default_model) = get_available_models(os).await?;
+            let Some(model) = find_model(&models, name) else {
+                return Err(ChatError::Custom(format!("Model '{name}' does not exist").into()));
+            };
+            session.conversation.model_info = Some(model.clone());
+        }
+
+        let Some((prompt, response)) = rewind_last_turn(session) else {
+            return no_prompt(session);
+        };
+        if self.vary && !response.is_empty() {
+            session.pending_additional_context = Some(vary_note(&response));
+        }
+
+        execute!(
+            session.stderr,
+            StyledText::secondary_fg(),
+            style::Print("Regenerating the response to:\n"),
+            StyledText::reset(),
+            StyledText::emphasis_fg(),
+            style::Print("> "),
+            StyledText::reset_attributes(),
+            style::Print(&prompt),
+            style::Print("\n"),
+        )?;
+        Ok(ChatState::HandleInput { input: prompt })
+    }
+}
+
+/// Removes the last prompt and what followed it from the conversation, including tool uses still
+/// waiting for approval, and returns the prompt and the responses to it.
+pub fn rewind_last_turn(session: &mut ChatSession) -> Option<(String, String)> {
+    let turn = session.conversation.pop_last_turn()?;
+    session.tool_uses.clear();
+    session.pending_tool_index = None;
+    Some(turn)
+}
+
+pub fn no_prompt(session: &mut ChatSession) -> Result<ChatState, ChatError> {
+    execute!(
+        session.stderr,
+        StyledText::warning_fg(),
+        style::Print("\nNo prompt was sent yet in this conversation.\n\n"),
+        StyledText::reset(),
+    )?;
+    Ok(ChatState::PromptUser {
+        skip_printing_tools: true,
+    })
+}
+
+/// The context that asks the model for a different response than `previous`.
+fn vary_note(previous: &str) -> String {
+    let mut end = previous.len().min(MAX_PREVIOUS_RESPONSE_BYTES);
+    while !previous.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!(
+        "I asked for a new response to this prompt. Your previous response was:\n<previous_response>\n{}\n</previous_response>\nTake a different approach this time, rather than repeating it.",
+        &previous[..end]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vary_note() {
+        let note = vary_note("Use `tar -tf`.");
+        assert!(note.contains("<previous_response>\nUse `tar -tf`.\n</previous_response>"));
+
+        let long = "é".repeat(MAX_PREVIOUS_RESPONSE_BYTES);
+        assert!(vary_note(&long).len() < MAX_PREVIOUS_RESPONSE_BYTES + 300);
+    }
+}
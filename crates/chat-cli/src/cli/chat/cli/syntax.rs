@@ -0,0 +1,150 @@
+//! Checks and completes slash commands against the same clap grammar that [SlashCommand] uses to
+//! parse them on submit, so the prompt can flag malformed commands while the user is typing.
+
+use clap::CommandFactory;
+use clap::error::{
+    ContextKind,
+    ContextValue,
+    ErrorKind,
+};
+
+use super::SlashCommand;
+
+/// Checks a (partially typed) slash command line, returning a short description of the problem
+/// if the line would fail to parse.
+pub fn check_slash_command(line: &str) -> Option<String> {
+    let mut args = shlex::split(line.strip_prefix('/')?)?;
+    if args.is_empty() {
+        return None;
+    }
+    args.insert(0, "slash_command".to_owned());
+
+    let err = SlashCommand::try_parse_from(args).err()?;
+    let message = match err.kind() {
+        ErrorKind::DisplayHelp | ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand | ErrorKind::DisplayVersion => {
+            return None;
+        },
+        ErrorKind::InvalidSubcommand => format!(
+            "unknown command '{}'",
+            context_string(&err, ContextKind::InvalidSubcommand)?
+        ),
+        ErrorKind::UnknownArgument => format!(
+            "unexpected argument '{}'",
+            context_string(&err, ContextKind::InvalidArg)?
+        ),
+        ErrorKind::InvalidValue => format!(
+            "invalid value '{}' for {}",
+            context_string(&err, ContextKind::InvalidValue)?,
+            context_string(&err, ContextKind::InvalidArg)?
+        ),
+        ErrorKind::MissingRequiredArgument => {
+            format!("missing {}", context_strings(&err, ContextKind::InvalidArg)?.join(", "))
+        },
+        _ => err
+            .render()
+            .to_string()
+            .lines()
+            .next()?
+            .trim_start_matches("error: ")
+            .to_string(),
+    };
+
+    let suggestion = [
+        ContextKind::SuggestedSubcommand,
+        ContextKind::SuggestedArg,
+        ContextKind::SuggestedValue,
+    ]
+    .into_iter()
+    .find_map(|kind| context_string(&err, kind));
+
+    Some(match suggestion {
+        Some(suggestion) => format!("{message}, did you mean '{suggestion}'?"),
+        None => message,
+    })
+}
+
+/// Completes subcommands and flags of the slash command in `preceding`, i.e. the line up to the
+/// word being completed.
+pub fn complete_slash_command_args(preceding: &str, word: &str) -> Vec<String> {
+    let Some(preceding) = preceding.strip_prefix('/') else {
+        return Vec::new();
+    };
+    let tokens = preceding.split_whitespace().collect::<Vec<_>>();
+    if tokens.is_empty() {
+        return Vec::new();
+    }
+
+    let root = SlashCommand::command();
+    let mut cmd = &root;
+    for token in tokens {
+        if let Some(subcommand) = cmd.find_subcommand(token) {
+            cmd = subcommand;
+        }
+    }
+
+    let mut candidates = if word.starts_with('-') {
+        cmd.get_arguments()
+            .filter(|arg| !arg.is_hide_set())
+            .filter_map(|arg| arg.get_long())
+            .map(|long| format!("--{long}"))
+            .collect::<Vec<_>>()
+    } else {
+        cmd.get_subcommands()
+            .filter(|subcommand| !subcommand.is_hide_set())
+            .map(|subcommand| subcommand.get_name().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    candidates.retain(|candidate| candidate.starts_with(word));
+    candidates.sort();
+    candidates
+}
+
+fn context_string(err: &clap::Error, kind: ContextKind) -> Option<String> {
+    context_strings(err, kind)?.into_iter().next()
+}
+
+fn context_strings(err: &clap::Error, kind: ContextKind) -> Option<Vec<String>> {
+    match err.get(kind)? {
+        ContextValue::String(value) => Some(vec![value.clone()]),
+        ContextValue::Strings(values) => Some(values.clone()),
+        ContextValue::StyledStr(value) => Some(vec![value.to_string()]),
+        ContextValue::StyledStrs(values) => Some(values.iter().map(|value| value.to_string()).collect()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_slash_command() {
+        assert_eq!(check_slash_command("/help"), None);
+        assert_eq!(check_slash_command("/context show --expand"), None);
+        assert_eq!(check_slash_command("not a command"), None);
+        // Unclosed quotes are still being typed
+        assert_eq!(check_slash_command("/context add \"some"), None);
+
+        let message = check_slash_command("/contxt").unwrap();
+        assert!(message.contains("unknown command 'contxt'"), "{message}");
+        assert!(message.contains("did you mean 'context'?"), "{message}");
+
+        let message = check_slash_command("/context show --expnd").unwrap();
+        assert!(message.contains("unexpected argument '--expnd'"), "{message}");
+        assert!(message.contains("--expand"), "{message}");
+
+        let message = check_slash_command("/model sett").unwrap();
+        assert!(message.contains("unexpected argument 'sett'"), "{message}");
+    }
+
+    #[test]
+    fn test_complete_slash_command_args() {
+        assert_eq!(complete_slash_command_args("/context ", "sh"), vec!["show".to_string()]);
+        assert_eq!(complete_slash_command_args("/context show ", "--ex"), vec![
+            "--expand".to_string()
+        ]);
+        assert!(complete_slash_command_args("/", "con").is_empty());
+        assert!(complete_slash_command_args("hello ", "wor").is_empty());
+    }
+}
@@ -2,17 +2,25 @@ use std::collections::{
     BTreeSet,
     HashSet,
 };
-use std::io::Write;
+use std::io::{
+    IsTerminal,
+    Write,
+};
+use std::path::Path;
 
 use clap::{
     Args,
     Subcommand,
 };
-use crossterm::style::Attribute;
+use crossterm::style::{
+    Attribute,
+    Stylize,
+};
 use crossterm::{
     queue,
     style,
 };
+use dialoguer::Select;
 
 use crate::api_client::model::Tool as FigTool;
 use crate::cli::agent::{
@@ -23,6 +31,10 @@ use crate::cli::chat::consts::{
     AGENT_FORMAT_TOOLS_DOC_URL,
     DUMMY_TOOL_NAME,
 };
+use crate::cli::chat::tool_manager::{
+    self,
+    tool_set_includes_server,
+};
 use crate::cli::chat::tools::ToolOrigin;
 use crate::cli::chat::{
     ChatError,
@@ -31,8 +43,10 @@ use crate::cli::chat::{
     trust_all_text,
 };
 use crate::constants::help_text::tools_long_help;
+use crate::os::Os;
 use crate::theme::StyledText;
 use crate::util::consts::MCP_SERVER_TOOL_DELIMITER;
+use crate::util::tool_permission_checker::is_tool_in_allowlist;
 
 /// Command-line arguments for managing tools in the chat session
 #[deny(missing_docs)]
@@ -43,12 +57,17 @@ pub struct ToolsArgs {
 }
 
 impl ToolsArgs {
-    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+    pub async fn execute(self, os: &mut Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
         if let Some(subcommand) = self.subcommand {
-            return subcommand.execute(session).await;
+            return subcommand.execute(os, session).await;
+        }
+
+        // No subcommand - let the user edit the tools in a terminal, otherwise print the current
+        // tools and their permissions.
+        if session.interactive && std::io::stdin().is_terminal() && std::io::stdout().is_terminal() {
+            return edit_tools(os, session).await;
         }
 
-        // No subcommand - print the current tools and their permissions.
         // Determine how to format the output nicely.
         let terminal_width = session.terminal_width();
         let longest = session
@@ -193,6 +212,468 @@ impl ToolsArgs {
     }
 }
 
+/// How much a tool is trusted in the interactive `/tools` table, cycled through in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TrustLevel {
+    /// The default permissions of the tool, asking before uses they don't cover
+    Ask,
+    /// Trusted for the rest of the session, e.g. with `--trust-tools` or `/tools trust`. Not saved
+    /// to the agent.
+    Session,
+    /// Trusted by the `allowedTools` of the agent's config file, and saved there
+    Agent,
+}
+
+impl TrustLevel {
+    fn next(self) -> Self {
+        match self {
+            Self::Ask => Self::Session,
+            Self::Session => Self::Agent,
+            Self::Agent => Self::Ask,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Ask => "ask before each use",
+            Self::Session => "trusted for this session",
+            Self::Agent => "trusted by the agent",
+        }
+    }
+}
+
+/// A tool as listed by the interactive `/tools` table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ToolRow {
+    origin: ToolOrigin,
+    /// The name the model knows the tool by
+    model_tool_name: String,
+    /// The name the tool has in its server
+    host_tool_name: String,
+}
+
+impl ToolRow {
+    /// How the tool is referred to in the `tools` and `allowedTools` fields of an agent.
+    fn config_ref(&self) -> String {
+        match &self.origin {
+            ToolOrigin::Native => self.host_tool_name.clone(),
+            ToolOrigin::McpServer(server_name) => {
+                format!("@{server_name}{MCP_SERVER_TOOL_DELIMITER}{}", self.host_tool_name)
+            },
+        }
+    }
+
+    /// The entry in the `tools` field of an agent that includes every tool from the same source.
+    fn source_ref(&self) -> String {
+        match &self.origin {
+            ToolOrigin::Native => "@builtin".to_string(),
+            ToolOrigin::McpServer(server_name) => format!("@{server_name}"),
+        }
+    }
+
+    fn server_name(&self) -> Option<&str> {
+        match &self.origin {
+            ToolOrigin::Native => None,
+            ToolOrigin::McpServer(server_name) => Some(server_name),
+        }
+    }
+}
+
+fn tool_rows(session: &ChatSession) -> Vec<ToolRow> {
+    let tool_manager = &session.conversation.tool_manager;
+    let mut rows = tool_manager
+        .schema
+        .values()
+        .filter(|spec| spec.name != DUMMY_TOOL_NAME)
+        .map(|spec| ToolRow {
+            origin: spec.tool_origin.clone(),
+            model_tool_name: spec.name.clone(),
+            host_tool_name: tool_manager
+                .tn_map
+                .get(&spec.name)
+                .map_or(spec.name.clone(), |info| info.host_tool_name.clone()),
+        })
+        .collect::<Vec<_>>();
+    // Built in tools always appear first.
+    rows.sort_by(|a, b| {
+        let source = |row: &ToolRow| (row.origin != ToolOrigin::Native, row.server_name().map(str::to_string));
+        (source(a), &a.host_tool_name).cmp(&(source(b), &b.host_tool_name))
+    });
+    rows
+}
+
+/// Shows the tools as a table where each can be turned on or off and have its trust level cycled,
+/// until the user leaves it or saves the changes to the agent.
+async fn edit_tools(os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+    let rows = tool_rows(session);
+    if rows.is_empty() {
+        queue!(
+            session.stderr,
+            style::Print(
+                "\nNo tools are currently enabled.\n\nRefer to the documentation for how to add tools to your agent: "
+            ),
+            StyledText::success_fg(),
+            style::Print(AGENT_FORMAT_TOOLS_DOC_URL),
+            StyledText::reset(),
+            style::Print("\n"),
+        )?;
+        return Ok(ChatState::default());
+    }
+
+    let name_width = rows.iter().map(|row| row.host_tool_name.len()).max().unwrap_or(0);
+    let source_width = rows
+        .iter()
+        .map(|row| row.server_name().unwrap_or("built-in").len())
+        .max()
+        .unwrap_or(0);
+    let initially_disabled = session.conversation.tool_manager.disabled_tools.clone();
+    // Tools trusted by the config file are at the agent level, those trusted otherwise at the
+    // session level
+    let saved_allowed_tools = match session.conversation.agents.get_active().and_then(|a| a.path.clone()) {
+        Some(path) => read_allowed_tools(os, &path).await.unwrap_or_default(),
+        None => Vec::new(),
+    }
+    .into_iter()
+    .collect::<HashSet<_>>();
+    let mut levels = rows
+        .iter()
+        .map(|row| {
+            if !is_trusted(session, row) {
+                TrustLevel::Ask
+            } else if is_tool_in_allowlist(&saved_allowed_tools, &row.host_tool_name, row.server_name()) {
+                TrustLevel::Agent
+            } else {
+                TrustLevel::Session
+            }
+        })
+        .collect::<Vec<_>>();
+    let mut cursor = 0;
+
+    loop {
+        let mut labels = rows
+            .iter()
+            .zip(&levels)
+            .map(|(row, level)| {
+                let available = if session
+                    .conversation
+                    .tool_manager
+                    .disabled_tools
+                    .contains(&row.model_tool_name)
+                {
+                    format!("{}", "[OFF]".grey())
+                } else {
+                    format!("{}", "[ON] ".green())
+                };
+                let trust = match level {
+                    TrustLevel::Ask => session
+                        .conversation
+                        .agents
+                        .display_label(&row.host_tool_name, &row.origin),
+                    level => format!("* {}", level.describe().dark_green().bold()),
+                };
+                format!(
+                    "{:<name_width$}  {:<source_width$}  {available}  {trust}",
+                    row.host_tool_name,
+                    row.server_name().unwrap_or("built-in"),
+                )
+            })
+            .collect::<Vec<_>>();
+        labels.push("Save to agent".to_string());
+        labels.push("Done".to_string());
+
+        let selection = match Select::with_theme(&crate::util::dialoguer_theme())
+            .with_prompt("Select a tool to turn on or off, or to change whether it's trusted")
+            .items(&labels)
+            .default(cursor)
+            .interact_on_opt(&dialoguer::console::Term::stdout())
+        {
+            Ok(selection) => selection,
+            // Ctrl‑C -> Err(Interrupted)
+            Err(dialoguer::Error::IO(ref e)) if e.kind() == std::io::ErrorKind::Interrupted => None,
+            Err(e) => return Err(ChatError::Custom(format!("Failed to choose a tool: {e}").into())),
+        };
+        let Some(index) = selection else {
+            break;
+        };
+        cursor = index;
+
+        if index == rows.len() {
+            save_to_agent(os, session, &rows, &levels).await?;
+            break;
+        }
+        let Some(row) = rows.get(index) else {
+            break;
+        };
+
+        let disabled = session
+            .conversation
+            .tool_manager
+            .disabled_tools
+            .contains(&row.model_tool_name);
+        let level = levels[index];
+        let actions = [
+            if disabled {
+                "Turn on for this session".to_string()
+            } else {
+                "Turn off for this session".to_string()
+            },
+            format!("Change to: {}", level.next().describe()),
+        ];
+        let action = match Select::with_theme(&crate::util::dialoguer_theme())
+            .with_prompt(&row.host_tool_name)
+            .items(&actions)
+            .default(0)
+            .interact_on_opt(&dialoguer::console::Term::stdout())
+        {
+            Ok(action) => action,
+            Err(dialoguer::Error::IO(ref e)) if e.kind() == std::io::ErrorKind::Interrupted => None,
+            Err(e) => return Err(ChatError::Custom(format!("Failed to choose an action: {e}").into())),
+        };
+
+        match action {
+            Some(0) if disabled => {
+                session
+                    .conversation
+                    .tool_manager
+                    .disabled_tools
+                    .remove(&row.model_tool_name);
+            },
+            Some(0) => {
+                session
+                    .conversation
+                    .tool_manager
+                    .disabled_tools
+                    .insert(row.model_tool_name.clone());
+            },
+            Some(1) if level.next() == TrustLevel::Ask => {
+                if session.conversation.agents.trust_all_tools {
+                    queue!(
+                        session.stderr,
+                        StyledText::warning_fg(),
+                        style::Print("All tools are trusted. Use /tools reset to ask before each use again.\n"),
+                        StyledText::reset(),
+                    )?;
+                    continue;
+                }
+                session.conversation.agents.untrust_tools(&[row.config_ref()]);
+                if is_trusted(session, row) {
+                    queue!(
+                        session.stderr,
+                        StyledText::warning_fg(),
+                        style::Print(format!(
+                            "{} is still trusted by a pattern in the agent's allowedTools.\n",
+                            row.host_tool_name
+                        )),
+                        StyledText::reset(),
+                    )?;
+                } else {
+                    levels[index] = TrustLevel::Ask;
+                }
+            },
+            Some(1) => {
+                session.conversation.agents.trust_tools(vec![row.config_ref()]);
+                levels[index] = level.next();
+            },
+            _ => {},
+        }
+        session.stderr.flush()?;
+    }
+
+    if session.conversation.tool_manager.disabled_tools != initially_disabled {
+        session.conversation.update_state(true).await;
+    }
+    session.stderr.flush()?;
+
+    Ok(ChatState::PromptUser {
+        skip_printing_tools: true,
+    })
+}
+
+fn is_trusted(session: &ChatSession, row: &ToolRow) -> bool {
+    session.conversation.agents.trust_all_tools
+        || session
+            .conversation
+            .agents
+            .get_active()
+            .is_some_and(|agent| is_tool_in_allowlist(&agent.allowed_tools, &row.host_tool_name, row.server_name()))
+}
+
+/// Whether a tool set, written like the tools field of an agent, includes the tool.
+fn tool_set_includes(tool_set: &[String], row: &ToolRow) -> bool {
+    tool_manager::tool_set_includes(tool_set, &row.origin, &row.host_tool_name)
+}
+
+/// Reads the `allowedTools` field of an agent's config file.
+async fn read_allowed_tools(os: &Os, path: &Path) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let config = serde_json::from_slice::<serde_json::Value>(&os.fs.read(path).await?)?;
+    Ok(config
+        .get("allowedTools")
+        .and_then(|tools| serde_json::from_value::<Vec<String>>(tools.clone()).ok())
+        .unwrap_or_default())
+}
+
+/// Writes which tools are on, and which are trusted at the [TrustLevel::Agent] level, to the active
+/// agent's config file. Tools trusted for the session only, including with `--trust-tools` or
+/// `/tools trust-all`, are not written.
+async fn save_to_agent(
+    os: &Os,
+    session: &mut ChatSession,
+    rows: &[ToolRow],
+    levels: &[TrustLevel],
+) -> Result<(), ChatError> {
+    let Some(agent) = session.conversation.agents.get_active() else {
+        return Err(ChatError::Custom("There is no active agent to save to".into()));
+    };
+    let Some(path) = agent.path.clone() else {
+        queue!(
+            session.stderr,
+            StyledText::warning_fg(),
+            style::Print(format!(
+                "\nAgent {} has no config file to save to. Create one with /agent create.\n",
+                agent.name
+            )),
+            StyledText::reset(),
+        )?;
+        return Ok(());
+    };
+
+    let disabled_tools = &session.conversation.tool_manager.disabled_tools;
+    let availability = rows
+        .iter()
+        .map(|row| (row, !disabled_tools.contains(&row.model_tool_name)))
+        .collect::<Vec<_>>();
+    let mut server_names = agent.mcp_servers.mcp_servers.keys().cloned().collect::<Vec<_>>();
+    server_names.sort();
+    let trust = rows
+        .iter()
+        .zip(levels)
+        .map(|(row, level)| (row, *level == TrustLevel::Agent))
+        .collect::<Vec<_>>();
+
+    let result = async {
+        let content = os.fs.read(&path).await?;
+        let mut config = serde_json::from_slice::<serde_json::Value>(&content)?;
+        let Some(config) = config.as_object_mut() else {
+            return Err::<_, Box<dyn std::error::Error>>("the config is not a JSON object".into());
+        };
+        let tools = config
+            .get("tools")
+            .and_then(|tools| serde_json::from_value::<Vec<String>>(tools.clone()).ok())
+            .unwrap_or_default();
+        config.insert(
+            "tools".to_string(),
+            serde_json::json!(apply_tool_availability(&tools, &availability, &server_names)),
+        );
+        let allowed_tools = config
+            .get("allowedTools")
+            .and_then(|tools| serde_json::from_value::<Vec<String>>(tools.clone()).ok())
+            .unwrap_or_default();
+        config.insert(
+            "allowedTools".to_string(),
+            serde_json::json!(apply_saved_trust(&allowed_tools, &trust)),
+        );
+        os.fs.write(&path, serde_json::to_string_pretty(&config)?).await?;
+        Ok(())
+    }
+    .await;
+
+    match result {
+        Ok(()) => queue!(
+            session.stderr,
+            StyledText::success_fg(),
+            style::Print(format!(
+                "\nSaved the tools of agent {} to {}\n",
+                agent.name,
+                path.display()
+            )),
+            StyledText::reset(),
+        )?,
+        Err(e) => queue!(
+            session.stderr,
+            StyledText::error_fg(),
+            style::Print(format!("\nFailed to save to {}: {e}\n", path.display())),
+            StyledText::reset(),
+        )?,
+    }
+    Ok(())
+}
+
+/// Rewrites the `tools` field of an agent so that it includes exactly the tools marked as on.
+/// Entries that include every tool of a source (`*`, `@builtin` and `@server`) are replaced by the
+/// tools of that source that are on when one of them is turned off, and entries for tools that are
+/// off are removed.
+fn apply_tool_availability(
+    tools: &[String],
+    availability: &[(&ToolRow, bool)],
+    server_names: &[String],
+) -> Vec<String> {
+    let mut out = tools.to_vec();
+    let any_off = availability.iter().any(|(_, on)| !on);
+
+    if any_off && out.iter().any(|t| t == "*") {
+        let mut sources = vec!["@builtin".to_string()];
+        for (row, _) in availability {
+            if !sources.contains(&row.source_ref()) {
+                sources.push(row.source_ref());
+            }
+        }
+        for server_name in server_names {
+            let source = format!("@{server_name}");
+            if !sources.contains(&source) {
+                sources.push(source);
+            }
+        }
+        out = out
+            .into_iter()
+            .flat_map(|t| if t == "*" { sources.clone() } else { vec![t] })
+            .collect();
+    }
+
+    for (row, _) in availability.iter().filter(|(_, on)| !on) {
+        let builtin_ref = format!("@builtin{MCP_SERVER_TOOL_DELIMITER}{}", row.host_tool_name);
+        out.retain(|t| *t != row.config_ref() && *t != builtin_ref);
+        if let Some(index) = out.iter().position(|t| *t == row.source_ref()) {
+            let expanded = availability
+                .iter()
+                .filter(|(other, on)| *on && other.origin == row.origin)
+                .map(|(other, _)| other.config_ref())
+                .collect::<Vec<_>>();
+            out.splice(index..=index, expanded);
+        }
+    }
+
+    for (row, _) in availability.iter().filter(|(_, on)| *on) {
+        let builtin_ref = format!("@builtin{MCP_SERVER_TOOL_DELIMITER}{}", row.host_tool_name);
+        let included = out
+            .iter()
+            .any(|t| t == "*" || *t == row.source_ref() || *t == row.config_ref() || *t == builtin_ref);
+        if !included {
+            out.push(row.config_ref());
+        }
+    }
+
+    let mut seen = HashSet::new();
+    out.retain(|t| seen.insert(t.clone()));
+    out
+}
+
+/// Rewrites the `allowedTools` field of an agent so that it trusts the tools marked as trusted,
+/// and no longer lists those that aren't. Patterns are left alone.
+fn apply_saved_trust(allowed_tools: &[String], trust: &[(&ToolRow, bool)]) -> Vec<String> {
+    let mut out = allowed_tools.to_vec();
+    for (row, trusted) in trust {
+        let builtin_ref = format!("@builtin{MCP_SERVER_TOOL_DELIMITER}{}", row.host_tool_name);
+        if *trusted {
+            if !out.iter().any(|t| *t == row.config_ref() || *t == builtin_ref) {
+                out.push(row.config_ref());
+            }
+        } else {
+            out.retain(|t| *t != row.config_ref() && *t != builtin_ref);
+        }
+    }
+    out
+}
+
 #[deny(missing_docs)]
 #[derive(Debug, PartialEq, Subcommand)]
 #[command(
@@ -218,10 +699,18 @@ pub enum ToolsSubcommand {
     TrustAll,
     /// Reset all tools to default permission levels
     Reset,
+    /// Show how mcp server tools were renamed or changed, and which were left out and why
+    Doctor,
+    /// Switch to a set of tools for the rest of the session: minimal (built-in tools only), full,
+    /// or a set from the agent's toolSets
+    Use {
+        /// Name of the tool set
+        name: String,
+    },
 }
 
 impl ToolsSubcommand {
-    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+    pub async fn execute(self, os: &mut Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
         // Here we need to obtain the list of host tool names
         let existing_custom_tools = session
             .conversation
@@ -404,6 +893,147 @@ impl ToolsSubcommand {
                     StyledText::reset(),
                 )?;
             },
+            Self::Doctor => {
+                let report = session.conversation.tool_manager.tool_adjustments();
+                if report.values().all(|adjustments| adjustments.is_empty()) {
+                    queue!(
+                        session.stderr,
+                        style::Print("\nAll mcp server tools are sent to the model as the servers provide them.\n"),
+                    )?;
+                }
+                for (server_name, adjustments) in report.iter().filter(|(_, a)| !a.is_empty()) {
+                    queue!(
+                        session.stderr,
+                        style::SetAttribute(Attribute::Bold),
+                        style::Print(format!("\n{server_name}\n")),
+                        style::SetAttribute(Attribute::Reset),
+                    )?;
+                    for (host_tool_name, adjustment) in adjustments {
+                        queue!(
+                            session.stderr,
+                            style::Print("- "),
+                            if adjustment.is_filtered() {
+                                StyledText::error_fg()
+                            } else {
+                                StyledText::warning_fg()
+                            },
+                            style::Print(host_tool_name),
+                            StyledText::reset(),
+                            style::Print(format!(": {adjustment}\n")),
+                        )?;
+                    }
+                }
+            },
+            Self::Use { name } => {
+                let tool_set = match session
+                    .conversation
+                    .agents
+                    .get_active()
+                    .and_then(|agent| agent.tool_sets.get(&name))
+                {
+                    Some(tool_set) => tool_set.clone(),
+                    None if name == "minimal" => vec!["@builtin".to_string()],
+                    None if name == "full" => vec!["*".to_string()],
+                    None => {
+                        let mut names = session
+                            .conversation
+                            .agents
+                            .get_active()
+                            .map(|agent| agent.tool_sets.keys().cloned().collect::<Vec<_>>())
+                            .unwrap_or_default();
+                        names.sort();
+                        names.splice(0..0, ["minimal".to_string(), "full".to_string()]);
+                        queue!(
+                            session.stderr,
+                            StyledText::error_fg(),
+                            style::Print(format!("\nThere is no tool set named {name}. ")),
+                            StyledText::reset(),
+                            style::Print(format!("Available tool sets: {}\n", names.join(", "))),
+                        )?;
+                        session.stderr.flush()?;
+                        return Ok(ChatState::PromptUser {
+                            skip_printing_tools: true,
+                        });
+                    },
+                };
+
+                // Servers the set has no tools from are stopped, and ones it brings back are
+                // started again. Restarting goes through the same path as swapping agents, so it
+                // is only done when the servers to run change.
+                if let Some(agent) = session.conversation.agents.get_active().cloned() {
+                    let servers = |tool_set: Option<&[String]>| {
+                        agent
+                            .mcp_servers
+                            .mcp_servers
+                            .iter()
+                            .filter(|(server_name, server_config)| {
+                                !server_config.disabled
+                                    && tool_set.is_none_or(|tool_set| tool_set_includes_server(tool_set, server_name))
+                            })
+                            .map(|(server_name, _)| server_name.clone())
+                            .collect::<BTreeSet<_>>()
+                    };
+                    let tool_manager = &mut session.conversation.tool_manager;
+                    let running = servers(tool_manager.tool_set.as_deref());
+                    let wanted = servers(Some(&tool_set));
+                    tool_manager.tool_set = Some(tool_set.clone());
+                    if running != wanted {
+                        tool_manager
+                            .swap_agent(os, &mut session.stderr, &agent)
+                            .await
+                            .map_err(|e| ChatError::Custom(format!("Failed to restart the MCP servers: {e}").into()))?;
+                        let stopped = running.difference(&wanted).cloned().collect::<Vec<_>>();
+                        if !stopped.is_empty() {
+                            queue!(
+                                session.stderr,
+                                style::Print(format!(
+                                    "\nStopped MCP servers with no tools in the set: {}\n",
+                                    stopped.join(", ")
+                                )),
+                            )?;
+                        }
+                    }
+                } else {
+                    session.conversation.tool_manager.tool_set = Some(tool_set.clone());
+                }
+
+                let (included, excluded) = tool_rows(session)
+                    .into_iter()
+                    .partition::<Vec<_>, _>(|row| tool_set_includes(&tool_set, row));
+                session.conversation.tool_manager.disabled_tools =
+                    excluded.iter().map(|row| row.model_tool_name.clone()).collect();
+                session.conversation.update_state(true).await;
+
+                // The model only sees the new tool specs, so tell it why tools it used earlier in
+                // the conversation are gone.
+                let tool_names = included
+                    .iter()
+                    .map(|row| row.model_tool_name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let note = format!(
+                    "Note: the user switched to the {name} tool set. From now on only these tools are available, \
+                    even if others were used earlier in the conversation: {tool_names}"
+                );
+                match session.pending_additional_context.as_mut() {
+                    Some(context) => {
+                        context.push_str("\n\n");
+                        context.push_str(&note);
+                    },
+                    None => session.pending_additional_context = Some(note),
+                }
+
+                queue!(
+                    session.stderr,
+                    StyledText::success_fg(),
+                    style::Print(format!(
+                        "\nSwitched to the {name} tool set: {} tools on, {} off.\n",
+                        included.len(),
+                        excluded.len()
+                    )),
+                    StyledText::reset(),
+                )?;
+            },
         };
 
         session.stderr.flush()?;
@@ -420,6 +1050,107 @@ impl ToolsSubcommand {
             ToolsSubcommand::Untrust { .. } => "untrust",
             ToolsSubcommand::TrustAll => "trust-all",
             ToolsSubcommand::Reset => "reset",
+            ToolsSubcommand::Doctor => "doctor",
+            ToolsSubcommand::Use { .. } => "use",
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(origin: ToolOrigin, name: &str) -> ToolRow {
+        ToolRow {
+            origin,
+            model_tool_name: name.to_string(),
+            host_tool_name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_tool_set_includes() {
+        let fs_read = row(ToolOrigin::Native, "fs_read");
+        let git_log = row(ToolOrigin::McpServer("git".to_string()), "git_log");
+        let set = |t: &[&str]| t.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+
+        assert!(tool_set_includes(&set(&["*"]), &git_log));
+        assert!(tool_set_includes(&set(&["@builtin"]), &fs_read));
+        assert!(!tool_set_includes(&set(&["@builtin"]), &git_log));
+        assert!(tool_set_includes(&set(&["@builtin/fs_read"]), &fs_read));
+        assert!(tool_set_includes(&set(&["fs_read", "@git"]), &git_log));
+        assert!(tool_set_includes(&set(&["@git/git_log"]), &git_log));
+        assert!(!tool_set_includes(&set(&["git_log", "@git/git_status"]), &git_log));
+    }
+
+    #[test]
+    fn test_trust_level_cycles() {
+        assert_eq!(TrustLevel::Ask.next(), TrustLevel::Session);
+        assert_eq!(TrustLevel::Session.next(), TrustLevel::Agent);
+        assert_eq!(TrustLevel::Agent.next(), TrustLevel::Ask);
+    }
+
+    #[test]
+    fn test_apply_saved_trust() {
+        let fs_read = row(ToolOrigin::Native, "fs_read");
+        let fs_write = row(ToolOrigin::Native, "fs_write");
+        let git_log = row(ToolOrigin::McpServer("git".to_string()), "git_log");
+        let tools = |t: &[&str]| t.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+
+        // Only the agent level is saved, and patterns are kept
+        let trust = [(&fs_read, true), (&fs_write, false), (&git_log, true)];
+        assert_eq!(
+            apply_saved_trust(&tools(&["@builtin/fs_write", "@jira/*"]), &trust),
+            tools(&["@jira/*", "fs_read", "@git/git_log"])
+        );
+        assert_eq!(
+            apply_saved_trust(&tools(&["@builtin/fs_read"]), &trust),
+            tools(&["@builtin/fs_read", "@git/git_log"])
+        );
+    }
+
+    #[test]
+    fn test_apply_tool_availability() {
+        let fs_read = row(ToolOrigin::Native, "fs_read");
+        let fs_write = row(ToolOrigin::Native, "fs_write");
+        let git_log = row(ToolOrigin::McpServer("git".to_string()), "git_log");
+        let git_push = row(ToolOrigin::McpServer("git".to_string()), "git_push");
+        let servers = vec!["git".to_string(), "jira".to_string()];
+        let tools = |t: &[&str]| t.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+
+        // Nothing turned off leaves the list alone
+        let all_on = [(&fs_read, true), (&fs_write, true), (&git_log, true), (&git_push, true)];
+        assert_eq!(
+            apply_tool_availability(&tools(&["*"]), &all_on, &servers),
+            tools(&["*"])
+        );
+
+        // Turning off a tool covered by * expands it into its sources
+        let push_off = [
+            (&fs_read, true),
+            (&fs_write, true),
+            (&git_log, true),
+            (&git_push, false),
+        ];
+        assert_eq!(
+            apply_tool_availability(&tools(&["*"]), &push_off, &servers),
+            tools(&["@builtin", "@git/git_log", "@jira"])
+        );
+
+        // Explicit entries are removed, and tools turned back on are added
+        let write_off = [
+            (&fs_read, true),
+            (&fs_write, false),
+            (&git_log, true),
+            (&git_push, true),
+        ];
+        assert_eq!(
+            apply_tool_availability(
+                &tools(&["fs_write", "@builtin/fs_read", "@git/git_log"]),
+                &write_off,
+                &servers
+            ),
+            tools(&["@builtin/fs_read", "@git/git_log", "@git/git_push"])
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use chat_cli_ui::palette::{
+    PaletteItem,
+    PaletteItemKind,
+};
+use clap::CommandFactory;
+use eyre::Result;
+use rustyline::{
+    Cmd,
+    ConditionalEventHandler,
+    EventContext,
+    RepeatCount,
+};
+
+use super::ChatSession;
+use super::cli::SlashCommand;
+use super::cli::prompts::saved_prompt_names;
+use super::context::ContextManager;
+use super::prompt::get_available_commands;
+use super::skim_integration::{
+    complete_command_selection,
+    launch_skim_selector,
+};
+use crate::os::Os;
+
+/// Max number of recently modified files listed in the palette
+const MAX_RECENT_FILES: usize = 50;
+
+/// Lists slash commands, recent files, saved prompts, and agents in a single fuzzy finder
+pub struct CommandPalette {
+    os: Os,
+    context_manager: Arc<ContextManager>,
+    tool_names: Vec<String>,
+    /// Commands and agents of the current session. Files and prompts are looked up each time the
+    /// palette is opened since they can change at any time.
+    items: Vec<PaletteItem>,
+}
+
+impl CommandPalette {
+    pub fn new(os: Os, context_manager: Arc<ContextManager>, tool_names: Vec<String>, items: Vec<PaletteItem>) -> Self {
+        Self {
+            os,
+            context_manager,
+            tool_names,
+            items,
+        }
+    }
+
+    fn select(&self) -> Result<Option<String>> {
+        let mut items = self.items.clone();
+        items.extend(
+            saved_prompt_names(&self.os)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|name| PaletteItem::new(PaletteItemKind::Prompt, name, Some("Saved prompt".to_string()))),
+        );
+        items.extend(
+            recent_files(&self.os)
+                .into_iter()
+                .map(|path| PaletteItem::new(PaletteItemKind::File, path, None)),
+        );
+
+        let width = items.iter().map(|item| item.value.len()).max().unwrap_or_default();
+        let lines = items.iter().map(|item| item.display(width)).collect::<Vec<_>>();
+
+        let Some(selection) = launch_skim_selector(&lines, "Command palette: ", false)?
+            .and_then(|selections| selections.into_iter().next())
+        else {
+            return Ok(None);
+        };
+        let Some(item) = lines.iter().position(|line| *line == selection).map(|i| &items[i]) else {
+            return Ok(None);
+        };
+
+        match item.kind {
+            PaletteItemKind::Command => {
+                complete_command_selection(&item.value, &self.context_manager, &self.tool_names)
+            },
+            _ => Ok(Some(item.insertion())),
+        }
+    }
+}
+
+impl ConditionalEventHandler for CommandPalette {
+    fn handle(&self, _evt: &rustyline::Event, _n: RepeatCount, _positive: bool, _os: &EventContext<'_>) -> Option<Cmd> {
+        match self.select() {
+            Ok(Some(text)) => Some(Cmd::Insert(1, text)),
+            // If cancelled or error, do nothing
+            _ => Some(Cmd::Noop),
+        }
+    }
+}
+
+/// Builds the palette entries that only depend on the session: slash commands (built-in and user
+/// defined) and agents.
+pub fn session_palette_items(os: &Os, session: &ChatSession) -> Vec<PaletteItem> {
+    let slash_command = SlashCommand::command();
+    let mut items = get_available_commands(os)
+        .into_iter()
+        .map(|command| PaletteItem::new(PaletteItemKind::Command, command, command_help(&slash_command, command)))
+        .collect::<Vec<_>>();
+
+    if let Some(agent) = session.conversation.agents.get_active() {
+        let mut custom = agent
+            .commands
+            .iter()
+            .filter(|(name, _)| slash_command.find_subcommand(name.as_str()).is_none())
+            .map(|(name, command)| {
+                PaletteItem::new(
+                    PaletteItemKind::Command,
+                    format!("/{name}"),
+                    command.description.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        custom.sort_by(|a, b| a.value.cmp(&b.value));
+        items.extend(custom);
+    }
+
+    let mut agents = session
+        .conversation
+        .agents
+        .agents
+        .iter()
+        .map(|(name, agent)| PaletteItem::new(PaletteItemKind::Agent, name.clone(), agent.description.clone()))
+        .collect::<Vec<_>>();
+    agents.sort_by(|a, b| a.value.cmp(&b.value));
+    items.extend(agents);
+
+    items
+}
+
+/// Looks up the help text of a slash command such as `/context add`. Falls back to the help text
+/// of the parent command for arguments, e.g. `/context show --expand`.
+fn command_help(slash_command: &clap::Command, command: &str) -> Option<String> {
+    let mut names = command.trim_start_matches('/').split_whitespace();
+    let mut cmd = slash_command.find_subcommand(names.next()?)?;
+    for name in names {
+        match cmd.find_subcommand(name) {
+            Some(subcommand) => cmd = subcommand,
+            None => break,
+        }
+    }
+    cmd.get_about().map(|about| about.to_string())
+}
+
+/// Files tracked (or not ignored) by git in the current directory, most recently modified first
+fn recent_files(os: &Os) -> Vec<String> {
+    let Ok(cwd) = os.env.current_dir() else {
+        return Vec::new();
+    };
+    let Ok(output) = std::process::Command::new("git")
+        .args(["ls-files", "--cached", "--others", "--exclude-standard"])
+        .current_dir(&cwd)
+        .stderr(std::process::Stdio::null())
+        .output()
+    else {
+        return Vec::new();
+    };
+    if !output.status.success() {
+        return Vec::new();
+    }
+
+    let mut files = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|path| {
+            let modified = std::fs::metadata(cwd.join(path)).and_then(|m| m.modified()).ok()?;
+            Some((modified, path.to_string()))
+        })
+        .collect::<Vec<(SystemTime, String)>>();
+    files.sort_by(|a, b| b.0.cmp(&a.0));
+    files.into_iter().take(MAX_RECENT_FILES).map(|(_, path)| path).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_help() {
+        let slash_command = SlashCommand::command();
+        assert!(command_help(&slash_command, "/context add").is_some());
+        assert_eq!(
+            command_help(&slash_command, "/context show --expand"),
+            command_help(&slash_command, "/context show")
+        );
+        assert!(command_help(&slash_command, "/doesnotexist").is_none());
+    }
+}
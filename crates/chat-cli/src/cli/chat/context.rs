@@ -16,12 +16,18 @@ use serde::{
 
 use super::cli::hooks::HookOutput;
 use super::cli::model::context_window_tokens;
-use super::util::drop_matched_context_files;
-use crate::cli::agent::Agent;
+use super::util::{
+    documents,
+    drop_matched_context_files,
+};
 use crate::cli::agent::hook::{
     Hook,
     HookTrigger,
 };
+use crate::cli::agent::{
+    Agent,
+    resolve_env,
+};
 use crate::cli::chat::ChatError;
 use crate::cli::chat::cli::hooks::HookExecutor;
 use crate::cli::chat::cli::model::ModelInfo;
@@ -99,6 +105,10 @@ pub struct ContextManager {
     pub paths: Vec<ContextFilePath>,
     /// Map of Hook Name to [`Hook`]. The hook name serves as the hook's ID.
     pub hooks: HashMap<HookTrigger, Vec<Hook>>,
+    /// Environment variables passed to hooks, see [Agent::env]. Not serialized since values may
+    /// hold secrets.
+    #[serde(skip)]
+    pub env: HashMap<String, String>,
     #[serde(skip)]
     pub hook_executor: HookExecutor,
 }
@@ -117,6 +127,7 @@ impl ContextManager {
             current_profile: agent.name.clone(),
             paths,
             hooks: agent.hooks.clone(),
+            env: agent.env.clone(),
             hook_executor: HookExecutor::new(),
         })
     }
@@ -254,6 +265,7 @@ impl ContextManager {
         let mut hooks = self.hooks.clone();
         hooks.retain(|t, _| *t == trigger);
         let cwd = os.env.current_dir()?.to_string_lossy().to_string();
+        self.hook_executor.env = resolve_env(&self.env, os);
         self.hook_executor
             .run_hooks(hooks, output, &cwd, prompt, tool_context)
             .await
@@ -365,7 +377,7 @@ async fn process_path(
 /// Add a file to the context collection.
 ///
 /// This method:
-/// 1. Reads the content of the file
+/// 1. Reads the content of the file, extracting the text of documents such as PDFs and notebooks
 /// 2. Adds the (filename, content) pair to the context collection
 ///
 /// # Arguments
@@ -376,7 +388,10 @@ async fn process_path(
 /// A Result indicating success or an error
 async fn add_file_to_context(os: &Os, path: &Path, context_files: &mut Vec<(String, String)>) -> Result<()> {
     let filename = path.to_string_lossy().to_string();
-    let content = os.fs.read_to_string(path).await?;
+    let content = match documents::extract_text(os, path).await? {
+        Some(text) => text,
+        None => os.fs.read_to_string(path).await?,
+    };
     context_files.push((filename, content));
     Ok(())
 }
@@ -437,6 +452,25 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_add_notebook() -> Result<()> {
+        let os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(None).expect("Failed to create test context manager");
+
+        let notebook = serde_json::json!({
+            "metadata": { "kernelspec": { "language": "python" } },
+            "cells": [{ "cell_type": "code", "source": "x = 1", "outputs": [] }]
+        });
+        os.fs.write("analysis.ipynb", notebook.to_string()).await?;
+        manager
+            .add_paths(&os, vec!["analysis.ipynb".to_string()], false)
+            .await?;
+
+        let files = manager.get_context_files(&os).await?;
+        assert_eq!(files[0].1, "```python\nx = 1\n```");
+        Ok(())
+    }
+
     #[test]
     fn test_calc_max_context_files_size() {
         assert_eq!(
@@ -4,8 +4,11 @@ use std::collections::{
     VecDeque,
 };
 use std::io::Write;
+use std::sync::OnceLock;
 use std::sync::atomic::Ordering;
 
+use agent::agent_config::definitions::InferenceParams;
+use agent::consts::STREAM_RESUME_PROMPT;
 use chrono::Local;
 use crossterm::{
     execute,
@@ -28,7 +31,7 @@ use tracing::{
 };
 
 use super::cli::compact::CompactStrategy;
-use super::cli::hooks::HookOutput;
+use super::cli::hooks::format_hook_context;
 use super::cli::model::context_window_tokens;
 use super::consts::{
     DUMMY_TOOL_NAME,
@@ -38,13 +41,27 @@ use super::context::{
     ContextManager,
     calc_max_context_files_size,
 };
+use super::history::{
+    self,
+    History,
+};
 use super::line_tracker::FileLineTracker;
 use super::message::{
     AssistantMessage,
+    AssistantToolUse,
     ToolUseResult,
     UserMessage,
+    UserMessageContent,
 };
 use super::parser::RequestMetadata;
+use super::pins::{
+    self,
+    PinnedMessage,
+};
+use super::stale_context::{
+    self,
+    ContextSnapshot,
+};
 use super::token_counter::{
     CharCount,
     CharCounter,
@@ -57,7 +74,9 @@ use super::tools::{
     ToolOrigin,
     ToolSpec,
 };
+use super::turn_budget::TurnBudget;
 use super::util::serde_value_to_document;
+use super::workspace::Workspace;
 use crate::api_client::model::{
     ChatMessage,
     ConversationState as FigConversationState,
@@ -68,10 +87,8 @@ use crate::api_client::model::{
     UserInputMessage,
 };
 use crate::cli::agent::Agents;
-use crate::cli::agent::hook::{
-    Hook,
-    HookTrigger,
-};
+use crate::cli::agent::context_templates::ContextTemplates;
+use crate::cli::agent::hook::HookTrigger;
 use crate::cli::chat::ChatError;
 use crate::cli::chat::checkpoint::{
     Checkpoint,
@@ -81,19 +98,48 @@ use crate::cli::chat::cli::model::{
     ModelInfo,
     get_model_info,
 };
-use crate::cli::chat::tools::custom_tool::CustomToolConfig;
+use crate::cli::chat::tools::custom_tool::{
+    CustomToolConfig,
+    TransportType,
+};
 use crate::os::Os;
 use crate::theme::StyledText;
 
-pub const CONTEXT_ENTRY_START_HEADER: &str = "--- CONTEXT ENTRY BEGIN ---\n";
-pub const CONTEXT_ENTRY_END_HEADER: &str = "--- CONTEXT ENTRY END ---\n\n";
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HistoryEntry {
-    user: UserMessage,
-    assistant: AssistantMessage,
+    pub(super) user: UserMessage,
+    pub(super) assistant: AssistantMessage,
     #[serde(default)]
-    request_metadata: Option<RequestMetadata>,
+    pub(super) request_metadata: Option<RequestMetadata>,
+    /// The entry in the form sent to the backend, built the first time it's requested so that
+    /// unchanged history isn't converted again on every request. Cleared by [History] whenever
+    /// the entry is edited.
+    #[serde(skip)]
+    pub(super) request_messages: OnceLock<[ChatMessage; 2]>,
+}
+
+impl HistoryEntry {
+    pub fn new(user: UserMessage, assistant: AssistantMessage, request_metadata: Option<RequestMetadata>) -> Self {
+        Self {
+            user,
+            assistant,
+            request_metadata,
+            request_messages: OnceLock::new(),
+        }
+    }
+
+    /// The user and assistant messages of this entry as sent in the history of a request.
+    ///
+    /// The messages are built with the `templates` of the first request that sends the entry, and
+    /// stay the same afterwards so the history keeps matching the model's prompt cache.
+    pub fn request_messages(&self, templates: &ContextTemplates) -> &[ChatMessage; 2] {
+        self.request_messages.get_or_init(|| {
+            [
+                ChatMessage::UserInputMessage(self.user.clone().into_history_entry(templates)),
+                ChatMessage::AssistantResponseMessage(self.assistant.clone().into()),
+            ]
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -110,7 +156,7 @@ pub struct ConversationState {
     /// The next user message to be sent as part of the conversation. Required to be [Some] before
     /// calling [Self::as_sendable_conversation_state].
     next_message: Option<UserMessage>,
-    history: VecDeque<HistoryEntry>,
+    history: History,
     /// The range in the history sendable to the backend (start inclusive, end exclusive).
     valid_history_range: (usize, usize),
     /// Similar to history in that stores user and assistant responses, except that it is not used
@@ -149,12 +195,29 @@ pub struct ConversationState {
     /// Tangent mode checkpoint - stores main conversation when in tangent mode
     #[serde(default, skip_serializing_if = "Option::is_none")]
     tangent_state: Option<ConversationCheckpoint>,
+    /// Short title generated after the first turn, shown by `q chat --list`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    /// Workspace the conversation was started in, [None] for conversations saved before it was
+    /// recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    workspace: Option<Workspace>,
+    /// Hashes of the context files sent with the last request, to detect the ones that changed
+    /// when the conversation is resumed
+    #[serde(default, skip_serializing_if = "ContextSnapshot::is_empty")]
+    context_snapshot: ContextSnapshot,
+    /// Messages pinned with `/pin`, sent with the context once they are no longer in the history
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pinned: Vec<PinnedMessage>,
+    /// Inference parameters set with `/params`, for backends that support them
+    #[serde(default, skip_serializing_if = "InferenceParams::is_empty")]
+    pub inference_params: InferenceParams,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct ConversationCheckpoint {
     /// Main conversation history stored while in tangent mode
-    main_history: VecDeque<HistoryEntry>,
+    main_history: History,
     /// Main conversation next message
     main_next_message: Option<UserMessage>,
     /// Main conversation transcript
@@ -194,10 +257,15 @@ impl ConversationState {
             None
         };
 
+        let workspace = match os.env.current_dir() {
+            Ok(cwd) => Some(Workspace::detect(&cwd).await),
+            Err(_) => None,
+        };
+
         Self {
             conversation_id: conversation_id.to_string(),
             next_message: None,
-            history: VecDeque::new(),
+            history: History::default(),
             valid_history_range: Default::default(),
             transcript: VecDeque::new(),
             tools: format_tool_spec(tool_config),
@@ -212,6 +280,11 @@ impl ConversationState {
             checkpoint_manager: None,
             mcp_enabled,
             tangent_state: None,
+            title: None,
+            workspace,
+            context_snapshot: ContextSnapshot::new(),
+            pinned: Vec::new(),
+            inference_params: InferenceParams::default(),
         }
     }
 
@@ -219,15 +292,56 @@ impl ConversationState {
         self.latest_summary.as_ref().map(|(s, _)| s.as_str())
     }
 
-    pub fn history(&self) -> &VecDeque<HistoryEntry> {
+    pub fn history(&self) -> &History {
         &self.history
     }
 
-    /// Clears the conversation history and summary.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    pub fn set_title(&mut self, title: String) {
+        self.title = Some(title);
+    }
+
+    pub fn workspace(&self) -> Option<&Workspace> {
+        self.workspace.as_ref()
+    }
+
+    pub fn context_snapshot(&self) -> &ContextSnapshot {
+        &self.context_snapshot
+    }
+
+    /// Clears the conversation history, summary, pins and title.
     pub fn clear(&mut self) {
         self.next_message = None;
         self.history.clear();
         self.latest_summary = None;
+        self.pinned.clear();
+        self.title = None;
+    }
+
+    pub fn pinned(&self) -> &[PinnedMessage] {
+        &self.pinned
+    }
+
+    /// Pins `message` so that it's kept through compaction, returning false if it was already
+    /// pinned.
+    pub fn pin(&mut self, message: PinnedMessage) -> bool {
+        if self.pinned.contains(&message) {
+            return false;
+        }
+        self.pinned.push(message);
+        true
+    }
+
+    /// Removes the pin at `index`, which starts at 0 for the oldest pin.
+    pub fn unpin(&mut self, index: usize) -> Option<PinnedMessage> {
+        (index < self.pinned.len()).then(|| self.pinned.remove(index))
+    }
+
+    pub fn clear_pins(&mut self) {
+        self.pinned.clear();
     }
 
     /// Check if currently in tangent mode
@@ -359,11 +473,7 @@ impl ConversationState {
                 let assistant = candidate_asst.take().unwrap();
                 let user = candidate_user.take().unwrap();
                 self.append_assistant_transcript(&assistant);
-                self.history.push_back(HistoryEntry {
-                    user,
-                    assistant,
-                    request_metadata: None,
-                });
+                self.history.push_back(HistoryEntry::new(user, assistant, None));
             }
         }
 
@@ -411,17 +521,40 @@ impl ConversationState {
         let next_user_message = self.next_message.take().expect("next user message should exist");
 
         self.append_assistant_transcript(&message);
-        self.history.push_back(HistoryEntry {
-            user: next_user_message,
-            assistant: message,
-            request_metadata,
-        });
+        let last_entry = self.history.len().checked_sub(1).and_then(|i| self.history.get_mut(i));
+        match last_entry {
+            // A continued response is merged into the partial response, without the prompt that
+            // asked for it
+            Some(entry) if next_user_message.prompt() == Some(STREAM_RESUME_PROMPT) => {
+                let partial = std::mem::replace(
+                    &mut entry.assistant,
+                    AssistantMessage::new_response(None, String::new()),
+                );
+                entry.assistant = partial.continued_by(message);
+            },
+            _ => self
+                .history
+                .push_back(HistoryEntry::new(next_user_message, message, request_metadata)),
+        }
 
-        if let Ok(cwd) = std::env::current_dir() {
+        if let Ok(cwd) = os.env.current_dir() {
             os.database.set_conversation_by_path(cwd, self).ok();
         }
     }
 
+    /// Keeps `partial`, a response that the connection dropped in the middle of, and asks the
+    /// model to continue it from where it stopped. The prompt asking for it is only sent with the
+    /// request, the continuation is merged into `partial` once it's pushed.
+    pub async fn resume_response(
+        &mut self,
+        os: &mut Os,
+        partial: AssistantMessage,
+        request_metadata: Option<RequestMetadata>,
+    ) {
+        self.push_assistant_message(os, partial, request_metadata);
+        self.set_next_user_message(STREAM_RESUME_PROMPT.to_string()).await;
+    }
+
     /// Returns the conversation id.
     pub fn conversation_id(&self) -> &str {
         self.conversation_id.as_ref()
@@ -494,9 +627,14 @@ impl ConversationState {
 
     /// Sets the next user message with "cancelled" tool results.
     pub fn abandon_tool_use(&mut self, tools_to_be_abandoned: &[QueuedTool], deny_input: String) {
+        self.cancel_tool_uses(tools_to_be_abandoned.iter().map(|t| t.id.as_str()), deny_input);
+    }
+
+    /// Sets the next user message with "cancelled" results for the tool uses of `tool_use_ids`.
+    pub fn cancel_tool_uses<'a>(&mut self, tool_use_ids: impl Iterator<Item = &'a str>, prompt: String) {
         self.next_message = Some(UserMessage::new_cancelled_tool_uses(
-            Some(deny_input),
-            tools_to_be_abandoned.iter().map(|t| t.id.as_str()),
+            Some(prompt),
+            tool_use_ids,
             Some(Local::now().fixed_offset()),
         ));
     }
@@ -513,8 +651,8 @@ impl ConversationState {
     ) -> Result<FigConversationState, ChatError> {
         debug_assert!(self.next_message.is_some());
         self.enforce_conversation_invariants();
-        self.history.drain(self.valid_history_range.1..);
-        self.history.drain(..self.valid_history_range.0);
+        self.history
+            .retain_range(self.valid_history_range.0..self.valid_history_range.1);
 
         let context = self.backend_conversation_state(os, run_perprompt_hooks, stderr).await?;
         if !context.dropped_context_files.is_empty() {
@@ -547,6 +685,7 @@ impl ConversationState {
             .tool_manager
             .schema
             .values()
+            .filter(|v| !self.tool_manager.disabled_tools.contains(&v.name))
             .fold(HashMap::<ToolOrigin, Vec<Tool>>::new(), |mut acc, v| {
                 let tool = Tool::ToolSpecification(ToolSpecification {
                     name: v.name.clone(),
@@ -577,6 +716,7 @@ impl ConversationState {
         self.enforce_conversation_invariants();
 
         // Run hooks and add to conversation start and next user message.
+        let templates = self.context_templates();
         let mut agent_spawn_context = None;
         if let Some(cm) = self.context_manager.as_mut() {
             let user_prompt = self.next_message.as_ref().and_then(|m| m.prompt());
@@ -589,7 +729,7 @@ impl ConversationState {
                     None, // tool_context
                 )
                 .await?;
-            agent_spawn_context = format_hook_context(&agent_spawn, HookTrigger::AgentSpawn);
+            agent_spawn_context = format_hook_context(&agent_spawn, HookTrigger::AgentSpawn, &templates);
 
             if let (true, Some(next_message)) = (run_perprompt_hooks, self.next_message.as_mut()) {
                 let per_prompt = cm
@@ -601,7 +741,7 @@ impl ConversationState {
                         None, // tool_context
                     )
                     .await?;
-                if let Some(ctx) = format_hook_context(&per_prompt, HookTrigger::UserPromptSubmit) {
+                if let Some(ctx) = format_hook_context(&per_prompt, HookTrigger::UserPromptSubmit, &templates) {
                     next_message.additional_context = ctx;
                 }
             }
@@ -619,6 +759,7 @@ impl ConversationState {
             dropped_context_files,
             tools: &self.tools,
             model_id: self.model_info.as_ref().map(|m| m.model_id.as_str()),
+            templates: self.context_templates(),
         })
     }
 
@@ -681,24 +822,33 @@ impl ConversationState {
                         FILTER OUT CHAT CONVENTIONS (greetings, offers to help, etc).".to_string()
             },
         };
+        let templates = self.context_templates();
         if let Some((summary, _)) = &self.latest_summary {
             summary_content.push_str("\n\n");
-            summary_content.push_str(CONTEXT_ENTRY_START_HEADER);
-            summary_content.push_str("This summary contains ALL relevant information from our previous conversation including tool uses, results, code analysis, and file operations. YOU MUST be sure to include this information when creating your summarization document.\n\n");
-            summary_content.push_str("SUMMARY CONTENT:\n");
-            summary_content.push_str(summary);
-            summary_content.push('\n');
-            summary_content.push_str(CONTEXT_ENTRY_END_HEADER);
+            summary_content.push_str(&templates.render_entry(&format!(
+                "This summary contains ALL relevant information from our previous conversation including tool uses, \
+                 results, code analysis, and file operations. YOU MUST be sure to include this information when \
+                 creating your summarization document.\n\nSUMMARY CONTENT:\n{summary}\n"
+            )));
+        }
+        if !self.pinned.is_empty() {
+            summary_content.push_str(
+                "\n\nThe user pinned some messages of this conversation, which are kept verbatim apart from your summary. \
+                Refer to them briefly where relevant rather than repeating their content.",
+            );
         }
 
-        let conv_state = self.backend_conversation_state(os, false, &mut vec![]).await?;
+        // Brings the history in line with the current tools and invariants.
+        self.backend_conversation_state(os, false, &mut vec![]).await?;
         let mut summary_message = Some(UserMessage::new_prompt(summary_content.clone(), None));
 
         // Create the history according to the passed compact strategy.
-        let mut history = conv_state.history.cloned().collect::<VecDeque<_>>();
-        history.drain((history.len().saturating_sub(strategy.messages_to_exclude))..);
+        let mut history = self
+            .history
+            .slice(self.valid_history_range.0..self.valid_history_range.1);
+        history.retain_range(0..history.len().saturating_sub(strategy.messages_to_exclude));
         if strategy.truncate_large_messages {
-            for HistoryEntry { user, .. } in &mut history {
+            for HistoryEntry { user, .. } in history.iter_mut() {
                 user.truncate_safe(strategy.max_message_length);
             }
         }
@@ -722,8 +872,8 @@ impl ConversationState {
             conversation_id: Some(self.conversation_id.clone()),
             user_input_message: summary_message
                 .unwrap_or(UserMessage::new_prompt(summary_content, None)) // should not happen
-                .into_user_input_message(self.model_info.as_ref().map(|m| m.model_id.clone()), &tools),
-            history: Some(flatten_history(history.iter())),
+                .into_user_input_message(self.model_info.as_ref().map(|m| m.model_id.clone()), &tools, &templates),
+            history: Some(flatten_history(history.iter(), &templates)),
         })
     }
 
@@ -735,8 +885,9 @@ impl ConversationState {
         strategy: CompactStrategy,
         request_metadata: RequestMetadata,
     ) {
+        let len = self.history.len();
         self.history
-            .drain(..(self.history.len().saturating_sub(strategy.messages_to_exclude)));
+            .retain_range(len.saturating_sub(strategy.messages_to_exclude)..len);
         self.latest_summary = Some((summary, request_metadata));
     }
 
@@ -764,7 +915,7 @@ Return only the JSON configuration, no additional text.",
         let generation_message = UserMessage::new_prompt(generation_content.clone(), None);
 
         // Use empty history since this is a standalone generation request
-        let history = VecDeque::new();
+        let history = History::default();
 
         // Only send the dummy tool spec to prevent the model from attempting tool use during generation
         let mut tools = self.tools.clone();
@@ -778,10 +929,11 @@ Return only the JSON configuration, no additional text.",
             ToolOrigin::McpServer(_) => false,
         });
 
+        let templates = self.context_templates();
         Ok(FigConversationState {
             conversation_id: Some(self.conversation_id.clone()),
-            user_input_message: generation_message.into_user_input_message(self.model.clone(), &tools),
-            history: Some(flatten_history(history.iter())),
+            user_input_message: generation_message.into_user_input_message(self.model.clone(), &tools, &templates),
+            history: Some(flatten_history(history.iter(), &templates)),
         })
     }
 
@@ -806,31 +958,39 @@ Return only the JSON configuration, no additional text.",
         os: &Os,
         additional_context: Option<String>,
     ) -> (Option<Vec<HistoryEntry>>, Vec<(String, String)>) {
+        let templates = self.context_templates();
         let mut context_content = String::new();
         let mut dropped_context_files = Vec::new();
         if let Some((summary, _)) = &self.latest_summary {
-            context_content.push_str(CONTEXT_ENTRY_START_HEADER);
-            context_content.push_str("This summary contains ALL relevant information from our previous conversation including tool uses, results, code analysis, and file operations. YOU MUST reference this information when answering questions and explicitly acknowledge specific details from the summary when they're relevant to the current question.\n\n");
-            context_content.push_str("SUMMARY CONTENT:\n");
-            context_content.push_str(summary);
-            context_content.push('\n');
-            context_content.push_str(CONTEXT_ENTRY_END_HEADER);
+            context_content.push_str(&templates.render_entry(&templates.render_summary(summary)));
+        }
+
+        // Pinned messages that were compacted or trimmed away are sent verbatim instead
+        if !self.pinned.is_empty() {
+            let sent = self
+                .history
+                .slice(self.valid_history_range.0..self.valid_history_range.1);
+            let dropped_pins = pins::dropped(&self.pinned, &sent);
+            if !dropped_pins.is_empty() {
+                context_content.push_str(&templates.render_entry(&pins::render(&dropped_pins)));
+            }
         }
 
         // Add context files if available
         if let Some(context_manager) = self.context_manager.as_mut() {
             match context_manager.collect_context_files_with_limit(os).await {
                 Ok((files_to_use, files_dropped)) => {
+                    self.context_snapshot = stale_context::snapshot(&files_to_use);
                     if !files_dropped.is_empty() {
                         dropped_context_files.extend(files_dropped);
                     }
 
                     if !files_to_use.is_empty() {
-                        context_content.push_str(CONTEXT_ENTRY_START_HEADER);
-                        for (filename, content) in files_to_use {
-                            context_content.push_str(&format!("[{}]\n{}\n", filename, content));
-                        }
-                        context_content.push_str(CONTEXT_ENTRY_END_HEADER);
+                        let files = files_to_use
+                            .iter()
+                            .map(|(filename, content)| templates.render_file(filename, content))
+                            .collect::<String>();
+                        context_content.push_str(&templates.render_entry(&files));
                     }
                 },
                 Err(e) => {
@@ -844,19 +1004,19 @@ Return only the JSON configuration, no additional text.",
         }
 
         if let Some(agent_prompt) = self.agents.get_active().and_then(|a| a.prompt.as_ref()) {
-            context_content.push_str(&format!("Follow this instruction: {}", agent_prompt));
+            context_content.push_str(&templates.render_agent_prompt(agent_prompt));
+        }
+
+        if let Some(hint) = self.agents.get_active().and_then(|a| TurnBudget::from_agent(a).hint()) {
+            context_content.push_str(&templates.render_entry(&hint));
         }
 
         if !context_content.is_empty() {
             self.context_message_length = Some(context_content.len());
             let user = UserMessage::new_prompt(context_content, None);
-            let assistant = AssistantMessage::new_response(None, "I will fully incorporate this information when generating my responses, and explicitly acknowledge relevant parts of the summary when answering questions.".into());
+            let assistant = AssistantMessage::new_response(None, templates.acknowledgement().to_string());
             (
-                Some(vec![HistoryEntry {
-                    user,
-                    assistant,
-                    request_metadata: None,
-                }]),
+                Some(vec![HistoryEntry::new(user, assistant, None)]),
                 dropped_context_files,
             )
         } else {
@@ -864,6 +1024,14 @@ Return only the JSON configuration, no additional text.",
         }
     }
 
+    /// The context templates of the active agent.
+    pub fn context_templates(&self) -> ContextTemplates {
+        self.agents
+            .get_active()
+            .map(|agent| agent.context_templates.clone())
+            .unwrap_or_default()
+    }
+
     /// The length of the user message used as context, if any.
     pub fn context_message_length(&self) -> Option<usize> {
         self.context_message_length
@@ -907,6 +1075,36 @@ Return only the JSON configuration, no additional text.",
         self.transcript.push_back(message);
     }
 
+    /// Removes the last prompt of the user along with the responses and tool uses that followed
+    /// it, and returns the prompt and the text of the responses. Returns [None], leaving the
+    /// history as is, when no prompt was sent yet.
+    pub fn pop_last_turn(&mut self) -> Option<(String, String)> {
+        let start = (0..self.history.len()).rev().find(|&i| {
+            self.history
+                .get(i)
+                .is_some_and(|entry| matches!(entry.user.content(), UserMessageContent::Prompt { .. }))
+        })?;
+        let prompt = self.history.get(start)?.user.prompt()?.to_string();
+        let response = self
+            .history
+            .range(start..self.history.len())
+            .map(|entry| entry.assistant.content())
+            .filter(|content| !content.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        self.history.retain_range(0..start);
+        self.next_message = None;
+        self.valid_history_range = (self.valid_history_range.0.min(start), start);
+        // The transcript ends with the prompt, prefixed with "> ", and the responses to it
+        while let Some(message) = self.transcript.pop_back() {
+            if message.starts_with("> ") {
+                break;
+            }
+        }
+        Some((prompt, response))
+    }
+
     /// Restore conversation from a checkpoint's history snapshot
     pub fn restore_to_checkpoint(&mut self, checkpoint: &Checkpoint) -> Result<(), eyre::Report> {
         // 1. Restore history from snapshot
@@ -951,6 +1149,8 @@ Return only the JSON configuration, no additional text.",
                 .map_err(|e| ChatError::Custom(format!("Context manager has failed to instantiate: {e}").into()))?
         });
 
+        // A tool set picked with `/tools use` refers to the tools of the previous agent
+        self.tool_manager.tool_set = None;
         self.tool_manager
             .swap_agent(os, output, agent)
             .await
@@ -960,6 +1160,34 @@ Return only the JSON configuration, no additional text.",
 
         Ok(())
     }
+
+    /// Reconnects the MCP servers of the active agent when some of them are remote, since their
+    /// connections don't survive the system sleeping.
+    pub async fn reconnect_remote_mcp_servers(
+        &mut self,
+        os: &mut Os,
+        output: &mut impl Write,
+    ) -> Result<(), ChatError> {
+        let Some(agent) = self.agents.get_active() else {
+            return Ok(());
+        };
+        if !agent
+            .mcp_servers
+            .mcp_servers
+            .values()
+            .any(|server| !server.disabled && server.r#type == TransportType::Http)
+        {
+            return Ok(());
+        }
+
+        self.tool_manager
+            .swap_agent(os, output, agent)
+            .await
+            .map_err(|e| ChatError::Custom(format!("Failed to reconnect the MCP servers: {e}").into()))?;
+        self.update_state(true).await;
+
+        Ok(())
+    }
 }
 
 pub fn format_tool_spec(tool_spec: HashMap<String, ToolSpec>) -> HashMap<ToolOrigin, Vec<Tool>> {
@@ -984,8 +1212,7 @@ pub fn format_tool_spec(tool_spec: HashMap<String, ToolSpec>) -> HashMap<ToolOri
 ///
 /// This is intended to provide us ways to accurately assess the exact state that is sent to the
 /// model without having to needlessly clone and mutate [ConversationState] in strange ways.
-pub type BackendConversationState<'a> =
-    BackendConversationStateImpl<'a, std::collections::vec_deque::Iter<'a, HistoryEntry>, Option<Vec<HistoryEntry>>>;
+pub type BackendConversationState<'a> = BackendConversationStateImpl<'a, history::Iter<'a>, Option<Vec<HistoryEntry>>>;
 
 /// See [BackendConversationState]
 #[derive(Debug, Clone)]
@@ -997,15 +1224,20 @@ pub struct BackendConversationStateImpl<'a, T, U> {
     pub dropped_context_files: Vec<(String, String)>,
     pub tools: &'a HashMap<ToolOrigin, Vec<Tool>>,
     pub model_id: Option<&'a str>,
+    /// Context templates of the active agent
+    pub templates: ContextTemplates,
 }
 
-impl BackendConversationStateImpl<'_, std::collections::vec_deque::Iter<'_, HistoryEntry>, Option<Vec<HistoryEntry>>> {
+impl BackendConversationStateImpl<'_, history::Iter<'_>, Option<Vec<HistoryEntry>>> {
     fn into_fig_conversation_state(self) -> eyre::Result<FigConversationState> {
-        let history = flatten_history(self.context_messages.unwrap_or_default().iter().chain(self.history));
+        let history = flatten_history(
+            self.context_messages.unwrap_or_default().iter().chain(self.history),
+            &self.templates,
+        );
         let user_input_message: UserInputMessage = self
             .next_user_message
             .cloned()
-            .map(|msg| msg.into_user_input_message(self.model_id.map(str::to_string), self.tools))
+            .map(|msg| msg.into_user_input_message(self.model_id.map(str::to_string), self.tools, &self.templates))
             .ok_or(eyre::eyre!("next user message is not set"))?;
 
         Ok(FigConversationState {
@@ -1056,13 +1288,16 @@ pub struct ConversationSize {
 }
 
 /// Converts a list of user/assistant message pairs into a flattened list of ChatMessage.
-fn flatten_history<'a, T>(history: T) -> Vec<ChatMessage>
+///
+/// The backend has no notion of a server side conversation, so the full history has to be sent
+/// with every request. Entries cache their converted messages (see
+/// [HistoryEntry::request_messages]), so only new or edited entries are converted here.
+fn flatten_history<'a, T>(history: T, templates: &ContextTemplates) -> Vec<ChatMessage>
 where
     T: Iterator<Item = &'a HistoryEntry>,
 {
-    history.fold(Vec::new(), |mut acc, HistoryEntry { user, assistant, .. }| {
-        acc.push(ChatMessage::UserInputMessage(user.clone().into_history_entry()));
-        acc.push(ChatMessage::AssistantResponseMessage(assistant.clone().into()));
+    history.fold(Vec::new(), |mut acc, entry| {
+        acc.extend_from_slice(entry.request_messages(templates));
         acc
     })
 }
@@ -1084,42 +1319,8 @@ impl From<InputSchema> for ToolInputSchema {
     }
 }
 
-/// Formats hook output to be used within context blocks (e.g., in context messages or in new user
-/// prompts).
-///
-/// # Returns
-/// [Option::Some] if `hook_results` is not empty and at least one hook has content. Otherwise,
-/// [Option::None]
-fn format_hook_context(hook_results: &[((HookTrigger, Hook), HookOutput)], trigger: HookTrigger) -> Option<String> {
-    // Note: only format context when hook command exit code is 0
-    if hook_results
-        .iter()
-        .all(|(_, (exit_code, content))| *exit_code != 0 || content.is_empty())
-    {
-        return None;
-    }
-
-    let mut context_content = String::new();
-
-    context_content.push_str(CONTEXT_ENTRY_START_HEADER);
-    context_content.push_str("This section (like others) contains important information that I want you to use in your responses. I have gathered this context from valuable programmatic script hooks. You must follow any requests and consider all of the information in this section");
-    if trigger == HookTrigger::AgentSpawn {
-        context_content.push_str(" for the entire conversation");
-    }
-    context_content.push_str("\n\n");
-
-    for (_, (_, output)) in hook_results
-        .iter()
-        .filter(|((h_trigger, _), (exit_code, _))| *h_trigger == trigger && *exit_code == 0)
-    {
-        context_content.push_str(&format!("{output}\n\n"));
-    }
-    context_content.push_str(CONTEXT_ENTRY_END_HEADER);
-    Some(context_content)
-}
-
 fn enforce_conversation_invariants(
-    history: &mut VecDeque<HistoryEntry>,
+    history: &mut History,
     next_message: &mut Option<UserMessage>,
     tools: &HashMap<ToolOrigin, Vec<Tool>>,
 ) -> (usize, usize) {
@@ -1161,8 +1362,11 @@ fn enforce_conversation_invariants(
 
     // If the first message contains tool results, then we add the results to the content field
     // instead. This is required to avoid validation errors.
-    if let Some(HistoryEntry { user, .. }) = history.front_mut() {
-        if user.has_tool_use_results() {
+    if history
+        .front()
+        .is_some_and(|HistoryEntry { user, .. }| user.has_tool_use_results())
+    {
+        if let Some(HistoryEntry { user, .. }) = history.front_mut() {
             user.replace_content_with_tool_use_results();
         }
     }
@@ -1216,7 +1420,7 @@ fn enforce_conversation_invariants(
     valid_history_range
 }
 
-fn enforce_tool_use_history_invariants(history: &mut VecDeque<HistoryEntry>, tools: &HashMap<ToolOrigin, Vec<Tool>>) {
+fn enforce_tool_use_history_invariants(history: &mut History, tools: &HashMap<ToolOrigin, Vec<Tool>>) {
     let tool_names: HashSet<_> = tools
         .values()
         .flat_map(|tools| {
@@ -1227,42 +1431,73 @@ fn enforce_tool_use_history_invariants(history: &mut VecDeque<HistoryEntry>, too
         .filter(|name| *name != DUMMY_TOOL_NAME)
         .collect();
 
-    for HistoryEntry { assistant, .. } in history {
-        if let AssistantMessage::ToolUse { tool_uses, .. } = assistant {
+    // Entries can be shared with snapshots of the history, so only the ones that actually change
+    // are edited.
+    for i in 0..history.len() {
+        let needs_fix = history.get(i).is_some_and(|HistoryEntry { assistant, .. }| {
+            assistant.tool_uses().is_some_and(|tool_uses| {
+                tool_uses
+                    .iter()
+                    .any(|tool_use| fix_tool_use(&tool_names, tool_use).is_some())
+            })
+        });
+        if !needs_fix {
+            continue;
+        }
+
+        if let Some(HistoryEntry {
+            assistant: AssistantMessage::ToolUse { tool_uses, .. },
+            ..
+        }) = history.get_mut(i)
+        {
             for tool_use in tool_uses {
-                if tool_names.contains(tool_use.name.as_str()) {
-                    continue;
+                match fix_tool_use(&tool_names, tool_use) {
+                    Some(ToolUseFix::RestoreOriginal) => {
+                        tool_use.name = tool_use.orig_name.clone();
+                        tool_use.args = tool_use.orig_args.clone();
+                    },
+                    Some(ToolUseFix::Rename(name)) => tool_use.name = name,
+                    None => (),
                 }
+            }
+        }
+    }
+}
 
-                if tool_names.contains(tool_use.orig_name.as_str()) {
-                    tool_use.name = tool_use.orig_name.clone();
-                    tool_use.args = tool_use.orig_args.clone();
-                    continue;
-                }
+/// How a tool use in the history has to change for its name to be valid
+enum ToolUseFix {
+    RestoreOriginal,
+    Rename(String),
+}
 
-                let names: Vec<&str> = tool_names
-                    .iter()
-                    .filter_map(|name| {
-                        if name.ends_with(&tool_use.name) {
-                            Some(*name)
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-
-                // There's only one tool use matching, so we can just replace it with the
-                // found name.
-                if names.len() == 1 {
-                    tool_use.name = (*names.first().unwrap()).to_string();
-                    continue;
-                }
+fn fix_tool_use(tool_names: &HashSet<&str>, tool_use: &AssistantToolUse) -> Option<ToolUseFix> {
+    if tool_names.contains(tool_use.name.as_str()) {
+        return None;
+    }
+
+    if tool_names.contains(tool_use.orig_name.as_str()) {
+        return Some(ToolUseFix::RestoreOriginal);
+    }
 
-                // Otherwise, we have to replace it with a dummy.
-                tool_use.name = DUMMY_TOOL_NAME.to_string();
+    let names: Vec<&str> = tool_names
+        .iter()
+        .filter_map(|name| {
+            if name.ends_with(&tool_use.name) {
+                Some(*name)
+            } else {
+                None
             }
-        }
+        })
+        .collect();
+
+    // There's only one tool use matching, so we can just replace it with the
+    // found name.
+    if names.len() == 1 {
+        return Some(ToolUseFix::Rename((*names.first().unwrap()).to_string()));
     }
+
+    // Otherwise, we have to replace it with a dummy.
+    (tool_use.name != DUMMY_TOOL_NAME).then(|| ToolUseFix::Rename(DUMMY_TOOL_NAME.to_string()))
 }
 
 fn default_true() -> bool {
@@ -1271,6 +1506,7 @@ fn default_true() -> bool {
 #[cfg(test)]
 mod tests {
     use super::super::message::AssistantToolUse;
+    use super::super::pins::PinnedRole;
     use super::*;
     use crate::api_client::model::{
         AssistantResponseMessage,
@@ -1445,6 +1681,7 @@ mod tests {
                 tool_use_id: "tool_id".to_string(),
                 content: vec![],
                 status: ToolResultStatus::Success,
+                provenance: None,
             }]);
         }
 
@@ -1481,6 +1718,7 @@ mod tests {
                     tool_use_id: "tool_id".to_string(),
                     content: vec![],
                     status: ToolResultStatus::Success,
+                    provenance: None,
                 }]);
             } else {
                 conversation.push_assistant_message(&mut os, AssistantMessage::new_response(None, i.to_string()), None);
@@ -1489,6 +1727,165 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_pop_last_turn() {
+        let mut os = Os::new().await.unwrap();
+        let mut tool_manager = ToolManager::default();
+        let tool_config = tool_manager.load_tools(&mut os, &mut vec![]).await.unwrap();
+        let mut conversation = ConversationState::new(
+            "fake_conv_id",
+            Agents::default(),
+            tool_config,
+            tool_manager,
+            None,
+            &os,
+            false,
+        )
+        .await;
+        assert_eq!(conversation.pop_last_turn(), None);
+
+        conversation.append_user_transcript("first");
+        conversation.set_next_user_message("first".to_string()).await;
+        conversation.push_assistant_message(&mut os, AssistantMessage::new_response(None, "one".to_string()), None);
+        conversation.append_user_transcript("second");
+        conversation.set_next_user_message("second".to_string()).await;
+        conversation.push_assistant_message(
+            &mut os,
+            AssistantMessage::new_tool_use(None, "Reading".to_string(), vec![AssistantToolUse {
+                id: "tool_id".to_string(),
+                name: "fs_read".to_string(),
+                ..Default::default()
+            }]),
+            None,
+        );
+        conversation.add_tool_results(vec![ToolUseResult {
+            tool_use_id: "tool_id".to_string(),
+            content: vec![],
+            status: ToolResultStatus::Success,
+            provenance: None,
+        }]);
+        conversation.push_assistant_message(&mut os, AssistantMessage::new_response(None, "two".to_string()), None);
+
+        assert_eq!(
+            conversation.pop_last_turn(),
+            Some(("second".to_string(), "Reading\ntwo".to_string()))
+        );
+        assert_eq!(conversation.history().len(), 1);
+        assert_eq!(
+            conversation.transcript.back().map(String::as_str),
+            Some("one\n[Tool uses: none]")
+        );
+        assert_eq!(
+            conversation.pop_last_turn(),
+            Some(("first".to_string(), "one".to_string()))
+        );
+        assert!(conversation.history().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_resume_response() {
+        let mut os = Os::new().await.unwrap();
+        let mut tool_manager = ToolManager::default();
+        let tool_config = tool_manager.load_tools(&mut os, &mut vec![]).await.unwrap();
+        let mut conversation = ConversationState::new(
+            "fake_conv_id",
+            Agents::default(),
+            tool_config,
+            tool_manager,
+            None,
+            &os,
+            false,
+        )
+        .await;
+
+        conversation.set_next_user_message("count to five".to_string()).await;
+        conversation
+            .resume_response(&mut os, AssistantMessage::new_response(None, "1, 2".to_string()), None)
+            .await;
+        // The prompt to continue is sent with the request
+        let sendable = conversation
+            .as_sendable_conversation_state(&os, &mut vec![], false)
+            .await
+            .unwrap();
+        assert!(sendable.user_input_message.content.contains(STREAM_RESUME_PROMPT));
+
+        // The connection can drop again while continuing
+        conversation
+            .resume_response(&mut os, AssistantMessage::new_response(None, ", 3".to_string()), None)
+            .await;
+        conversation.push_assistant_message(
+            &mut os,
+            AssistantMessage::new_response(None, ", 4, 5".to_string()),
+            None,
+        );
+
+        assert_eq!(conversation.history().len(), 1);
+        let entry = conversation.history().back().unwrap();
+        assert_eq!(entry.user.prompt(), Some("count to five"));
+        assert_eq!(entry.assistant.content(), "1, 2, 3, 4, 5");
+    }
+
+    #[tokio::test]
+    async fn test_pinned_messages_survive_compaction() {
+        let mut os = Os::new().await.unwrap();
+        let mut tool_manager = ToolManager::default();
+        let tool_config = tool_manager.load_tools(&mut os, &mut vec![]).await.unwrap();
+        let mut conversation = ConversationState::new(
+            "fake_conv_id",
+            Agents::default(),
+            tool_config,
+            tool_manager,
+            None,
+            &os,
+            false,
+        )
+        .await;
+
+        conversation
+            .set_next_user_message("Use Postgres for storage".to_string())
+            .await;
+        conversation.push_assistant_message(&mut os, AssistantMessage::new_response(None, "Noted".to_string()), None);
+        assert!(conversation.pin(PinnedMessage {
+            role: PinnedRole::User,
+            content: "Use Postgres for storage".to_string(),
+        }));
+        assert!(!conversation.pin(conversation.pinned()[0].clone()));
+
+        // Not repeated while the message is still in the history
+        conversation.set_next_user_message("next".to_string()).await;
+        let state = conversation
+            .as_sendable_conversation_state(&os, &mut vec![], true)
+            .await
+            .unwrap();
+        assert!(!state.history.unwrap().iter().any(|message| match message {
+            ChatMessage::UserInputMessage(user) => user.content.contains("<pinned_message>"),
+            _ => false,
+        }));
+
+        conversation.replace_history_with_summary(
+            "Chose a database".to_string(),
+            CompactStrategy::default(),
+            RequestMetadata::default(),
+        );
+        let state = conversation
+            .as_sendable_conversation_state(&os, &mut vec![], true)
+            .await
+            .unwrap();
+        match &state.history.unwrap()[0] {
+            ChatMessage::UserInputMessage(user) => {
+                assert!(user.content.contains("Chose a database"));
+                assert!(
+                    user.content
+                        .contains("<pinned_message>\nUse Postgres for storage\n</pinned_message>")
+                );
+            },
+            _ => panic!("Expected the first message to be the context message"),
+        }
+
+        assert!(conversation.unpin(0).is_some());
+        assert!(conversation.pinned().is_empty());
+    }
+
     #[tokio::test]
     async fn test_conversation_state_with_context_files() {
         let mut os = Os::new().await.unwrap();
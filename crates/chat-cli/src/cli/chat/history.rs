@@ -0,0 +1,292 @@
+//! Storage for the conversation history.
+//!
+//! The history is snapshotted on every turn (checkpoints, tangent mode), so entries are kept
+//! behind [Arc]s and shared between copies. Cloning a [History] is a reference count increment,
+//! modifying a shared history only copies the list of pointers, and an entry is only copied when
+//! it is edited while shared.
+
+use std::collections::{
+    VecDeque,
+    vec_deque,
+};
+use std::ops::Range;
+use std::sync::Arc;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::conversation::HistoryEntry;
+use super::message::{
+    AssistantMessage,
+    UserMessage,
+};
+
+/// Pairs of user and assistant messages, oldest first. Serializes the same as a list of
+/// [HistoryEntry].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct History {
+    entries: Arc<VecDeque<Arc<HistoryEntry>>>,
+}
+
+impl History {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter(self.entries.iter())
+    }
+
+    /// Iterates over the entries in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds, like [VecDeque::range].
+    pub fn range(&self, range: Range<usize>) -> Iter<'_> {
+        Iter(self.entries.range(range))
+    }
+
+    pub fn get(&self, index: usize) -> Option<&HistoryEntry> {
+        self.entries.get(index).map(Arc::as_ref)
+    }
+
+    pub fn front(&self) -> Option<&HistoryEntry> {
+        self.entries.front().map(Arc::as_ref)
+    }
+
+    pub fn back(&self) -> Option<&HistoryEntry> {
+        self.entries.back().map(Arc::as_ref)
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut HistoryEntry> {
+        self.get_mut(0)
+    }
+
+    /// Returns the entry at `index` for editing, copying it first if it's shared with another
+    /// history.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut HistoryEntry> {
+        if index >= self.len() {
+            return None;
+        }
+        Arc::make_mut(&mut self.entries).get_mut(index).map(edit)
+    }
+
+    /// Iterates over the entries for editing. Every entry that is shared with another history is
+    /// copied, so prefer [Self::get_mut] when only some entries change.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut HistoryEntry> {
+        Arc::make_mut(&mut self.entries).iter_mut().map(edit)
+    }
+
+    pub fn push_back(&mut self, entry: HistoryEntry) {
+        Arc::make_mut(&mut self.entries).push_back(Arc::new(entry));
+    }
+
+    pub fn clear(&mut self) {
+        self.entries = Default::default();
+    }
+
+    /// Drops every entry outside of `range`. Bounds past the end of the history are clamped.
+    pub fn retain_range(&mut self, range: Range<usize>) {
+        let end = range.end.min(self.len());
+        let start = range.start.min(end);
+        if start == 0 && end == self.len() {
+            return;
+        }
+
+        let entries = Arc::make_mut(&mut self.entries);
+        entries.truncate(end);
+        entries.drain(..start);
+    }
+
+    /// Returns a history of the entries in `range`, sharing them with `self`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds, like [VecDeque::range].
+    pub fn slice(&self, range: Range<usize>) -> Self {
+        Self {
+            entries: Arc::new(self.entries.range(range).cloned().collect()),
+        }
+    }
+
+    /// Creates a history of `len` prompts and responses with text of a typical length, for the
+    /// benches of the work repeated on every turn.
+    #[allow(dead_code)] // only used by the benches, through the library target
+    pub fn synthetic(len: usize) -> Self {
+        let text = "The quick brown fox jumps over the lazy dog. ".repeat(20);
+        (0..len)
+            .map(|i| {
+                HistoryEntry::new(
+                    UserMessage::new_prompt(format!("{i}: {text}"), None),
+                    AssistantMessage::new_response(None, format!("{i}: {text}")),
+                    None,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Prepares `entry` for editing, dropping the request messages cached for its current content.
+fn edit(entry: &mut Arc<HistoryEntry>) -> &mut HistoryEntry {
+    let entry = Arc::make_mut(entry);
+    entry.request_messages.take();
+    entry
+}
+
+impl FromIterator<HistoryEntry> for History {
+    fn from_iter<T: IntoIterator<Item = HistoryEntry>>(iter: T) -> Self {
+        Self {
+            entries: Arc::new(iter.into_iter().map(Arc::new).collect()),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a History {
+    type IntoIter = Iter<'a>;
+    type Item = &'a HistoryEntry;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the entries of a [History]
+#[derive(Debug, Clone)]
+pub struct Iter<'a>(vec_deque::Iter<'a, Arc<HistoryEntry>>);
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a HistoryEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(Arc::as_ref)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    fn last(mut self) -> Option<Self::Item> {
+        self.next_back()
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(Arc::as_ref)
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_client::model::ChatMessage;
+    use crate::cli::agent::context_templates::ContextTemplates;
+
+    fn entry(i: usize) -> HistoryEntry {
+        HistoryEntry::new(
+            UserMessage::new_prompt(format!("prompt {i}"), None),
+            AssistantMessage::new_response(None, format!("response {i}")),
+            None,
+        )
+    }
+
+    fn history(len: usize) -> History {
+        (0..len).map(entry).collect()
+    }
+
+    fn shared_entries(a: &History, b: &History) -> usize {
+        a.entries
+            .iter()
+            .zip(b.entries.iter())
+            .filter(|(a, b)| Arc::ptr_eq(a, b))
+            .count()
+    }
+
+    #[test]
+    fn test_clone_shares_entries() {
+        let original = history(100);
+        let mut snapshot = original.clone();
+        assert!(Arc::ptr_eq(&original.entries, &snapshot.entries));
+
+        // Appending copies the list of pointers, never the entries.
+        snapshot.push_back(entry(100));
+        assert_eq!(original.len(), 100);
+        assert_eq!(snapshot.len(), 101);
+        assert_eq!(shared_entries(&original, &snapshot), 100);
+    }
+
+    #[test]
+    fn test_edit_copies_only_the_edited_entry() {
+        let original = history(10);
+        let mut edited = original.clone();
+
+        edited.get_mut(3).unwrap().user.truncate_safe(1);
+        assert_eq!(shared_entries(&original, &edited), 9);
+        assert_eq!(original.get(3).unwrap().user.prompt(), Some("prompt 3"));
+        assert_ne!(edited.get(3).unwrap().user.prompt(), Some("prompt 3"));
+
+        assert!(edited.get_mut(10).is_none());
+    }
+
+    #[test]
+    fn test_edit_clears_request_messages() {
+        let templates = ContextTemplates::default();
+        let mut history = history(2);
+        let snapshot = history.clone();
+        let ChatMessage::UserInputMessage(cached) = &snapshot.get(0).unwrap().request_messages(&templates)[0] else {
+            panic!("expected a user message");
+        };
+        assert_eq!(cached.content, "prompt 0");
+
+        history.get_mut(0).unwrap().user.truncate_safe(1);
+        let ChatMessage::UserInputMessage(edited) = &history.get(0).unwrap().request_messages(&templates)[0] else {
+            panic!("expected a user message");
+        };
+        assert_ne!(edited.content, "prompt 0");
+
+        // Untouched entries keep the messages that were already built.
+        assert!(std::ptr::eq(
+            snapshot.get(1).unwrap().request_messages(&templates),
+            history.get(1).unwrap().request_messages(&templates)
+        ));
+    }
+
+    #[test]
+    fn test_retain_range() {
+        let mut history = history(10);
+        let snapshot = history.clone();
+
+        history.retain_range(2..8);
+        let prompts = history.iter().map(|e| e.user.prompt().unwrap()).collect::<Vec<_>>();
+        assert_eq!(prompts.first(), Some(&"prompt 2"));
+        assert_eq!(prompts.last(), Some(&"prompt 7"));
+        assert_eq!(snapshot.len(), 10);
+
+        history.retain_range(0..100);
+        assert_eq!(history.len(), 6);
+
+        let slice = snapshot.slice(8..10);
+        assert_eq!(slice.len(), 2);
+        assert!(Arc::ptr_eq(&slice.entries[0], &snapshot.entries[8]));
+    }
+
+    #[test]
+    fn test_serializes_as_list() {
+        let history = history(2);
+        let json = serde_json::to_value(&history).unwrap();
+        assert_eq!(json.as_array().map(Vec::len), Some(2));
+
+        let deserialized: History = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized.len(), 2);
+        assert_eq!(deserialized.back().unwrap().user.prompt(), Some("prompt 1"));
+    }
+}
@@ -1,6 +1,10 @@
+use std::io::Write;
+
 use eyre::Result;
 use rustyline::error::ReadlineError;
 
+#[cfg(unix)]
+use super::command_palette::CommandPalette;
 use super::prompt::{
     PasteState,
     PromptQueryResponseReceiver,
@@ -15,6 +19,8 @@ use crate::os::Os;
 pub struct InputSource {
     inner: inner::Inner,
     paste_state: PasteState,
+    /// Text the next line starts with, for the user to edit
+    initial_text: Option<String>,
 }
 
 mod inner {
@@ -46,6 +52,7 @@ impl InputSource {
         Ok(Self {
             inner: inner::Inner::Readline(rl(os, sender, receiver, paste_state.clone())?),
             paste_state,
+            initial_text: None,
         })
     }
 
@@ -66,6 +73,24 @@ impl InputSource {
         Ok(())
     }
 
+    /// Registers the user defined slash commands of the active agent for completion
+    pub fn set_custom_commands(&mut self, commands: Vec<String>) {
+        if let inner::Inner::Readline(rl) = &mut self.inner {
+            if let Some(helper) = rl.helper_mut() {
+                helper.set_custom_commands(commands);
+            }
+        }
+    }
+
+    /// Keeps path completion in sync with the working directory of the session
+    pub fn set_cwd(&mut self, cwd: std::path::PathBuf) {
+        if let inner::Inner::Readline(rl) = &mut self.inner {
+            if let Some(helper) = rl.helper_mut() {
+                helper.set_cwd(cwd);
+            }
+        }
+    }
+
     #[cfg(unix)]
     pub fn put_skim_command_selector(
         &mut self,
@@ -96,21 +121,67 @@ impl InputSource {
         }
     }
 
+    #[cfg(unix)]
+    pub fn put_command_palette(
+        &mut self,
+        os: &Os,
+        context_manager: std::sync::Arc<super::context::ContextManager>,
+        tool_names: Vec<String>,
+        items: Vec<chat_cli_ui::palette::PaletteItem>,
+    ) {
+        use rustyline::{
+            EventHandler,
+            KeyEvent,
+        };
+
+        use crate::database::settings::Setting;
+
+        if let inner::Inner::Readline(rl) = &mut self.inner {
+            let key_char = match os.database.settings.get_string(Setting::CommandPaletteKey) {
+                Some(key) if key.len() == 1 => key.chars().next().unwrap_or('p'),
+                _ => 'p', // Default to 'p' if setting is missing or invalid
+            };
+            rl.bind_sequence(
+                KeyEvent::ctrl(key_char),
+                EventHandler::Conditional(Box::new(CommandPalette::new(
+                    os.clone(),
+                    context_manager,
+                    tool_names,
+                    items,
+                ))),
+            );
+        }
+    }
+
     #[allow(dead_code)]
     pub fn new_mock(lines: Vec<String>) -> Self {
         Self {
             inner: inner::Inner::Mock { index: 0, lines },
             paste_state: PasteState::new(),
+            initial_text: None,
         }
     }
 
+    /// Starts the next line read with `text`, with the cursor at its end.
+    pub fn set_initial_text(&mut self, text: String) {
+        self.initial_text = Some(text);
+    }
+
     pub fn read_line(&mut self, prompt: Option<&str>) -> Result<Option<String>, ReadlineError> {
+        let initial_text = self.initial_text.take();
         match &mut self.inner {
             inner::Inner::Readline(rl) => {
                 let prompt = prompt.unwrap_or_default();
-                let curr_line = rl.readline(prompt);
+                let curr_line = match &initial_text {
+                    Some(text) => rl.readline_with_initial(prompt, (text, "")),
+                    None => rl.readline(prompt),
+                };
                 match curr_line {
                     Ok(line) => {
+                        if let Some(sequences) = rl.helper().and_then(|helper| helper.command_started(&line)) {
+                            let mut stdout = std::io::stdout();
+                            let _ = write!(stdout, "{sequences}").and_then(|_| stdout.flush());
+                        }
                         if Self::should_append_history(&line) {
                             let _ = rl.add_history_entry(line.as_str());
                         }
@@ -19,10 +19,6 @@ use super::consts::{
     MAX_CURRENT_WORKING_DIRECTORY_LEN,
     MAX_USER_MESSAGE_SIZE,
 };
-use super::conversation::{
-    CONTEXT_ENTRY_END_HEADER,
-    CONTEXT_ENTRY_START_HEADER,
-};
 use super::tools::{
     InvokeOutput,
     OutputKind,
@@ -46,6 +42,7 @@ use crate::api_client::model::{
     UserInputMessage,
     UserInputMessageContext,
 };
+use crate::cli::agent::context_templates::ContextTemplates;
 
 const USER_ENTRY_START_HEADER: &str = "--- USER MESSAGE BEGIN ---\n";
 const USER_ENTRY_END_HEADER: &str = "--- USER MESSAGE END ---\n\n";
@@ -137,6 +134,7 @@ impl UserMessage {
                             "Tool use was cancelled by the user".to_string(),
                         )],
                         status: ToolResultStatus::Error,
+                        provenance: None,
                     })
                     .collect(),
             },
@@ -173,8 +171,8 @@ impl UserMessage {
 
     /// Converts this message into a [UserInputMessage] to be stored in the history of
     /// [api_client::model::ConversationState].
-    pub fn into_history_entry(self) -> UserInputMessage {
-        let content = self.content_with_context();
+    pub fn into_history_entry(self, templates: &ContextTemplates) -> UserInputMessage {
+        let content = self.content_with_context(templates);
         UserInputMessage {
             images: self.images.clone(),
             content,
@@ -201,8 +199,9 @@ impl UserMessage {
         self,
         model_id: Option<String>,
         tools: &HashMap<ToolOrigin, Vec<Tool>>,
+        templates: &ContextTemplates,
     ) -> UserInputMessage {
-        let content = self.content_with_context();
+        let content = self.content_with_context(templates);
         UserInputMessage {
             images: self.images,
             content,
@@ -288,8 +287,8 @@ impl UserMessage {
     }
 
     /// Returns a formatted [String] containing [Self::additional_context], [Self::timestamp], and
-    /// [Self::prompt].
-    fn content_with_context(&self) -> String {
+    /// [Self::prompt]. The timestamp is wrapped like the other context entries, by `templates`.
+    fn content_with_context(&self, templates: &ContextTemplates) -> String {
         let mut content = String::new();
 
         if let Some(ts) = self.timestamp {
@@ -304,10 +303,7 @@ impl UserMessage {
             };
             // Format the time with iso8601 format using a timezone offset.
             let timestamp = ts.to_rfc3339_opts(chrono::SecondsFormat::Millis, false);
-            content.push_str(&format!(
-                "{}Current time: {}, {}\n{}",
-                CONTEXT_ENTRY_START_HEADER, weekday, timestamp, CONTEXT_ENTRY_END_HEADER,
-            ));
+            content.push_str(&templates.render_entry(&format!("Current time: {weekday}, {timestamp}\n")));
         }
 
         if !self.additional_context.is_empty() {
@@ -337,6 +333,47 @@ pub struct ToolUseResult {
     pub content: Vec<ToolUseResultBlock>,
     /// Status of the tool result.
     pub status: ToolResultStatus,
+    /// How the result was produced. Kept for exports and never sent to the model.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<ToolProvenance>,
+}
+
+/// How a tool result was produced.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolProvenance {
+    /// Name of the tool as the model called it
+    pub tool_name: String,
+    /// Time the tool took to run, in ms
+    pub duration_ms: u64,
+    /// Exit code of the command, for tools that run one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Bytes of output left out of the result to keep it small enough for the model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes_truncated: Option<usize>,
+    /// Paths the tool read or wrote, as the model gave them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+}
+
+impl ToolProvenance {
+    /// One line description, such as `execute_bash · 1.2s · exit 1 · 512 bytes truncated`.
+    pub fn summary(&self) -> String {
+        let mut parts = vec![
+            self.tool_name.clone(),
+            format!("{:.1}s", self.duration_ms as f64 / 1000.0),
+        ];
+        if let Some(exit_code) = self.exit_code {
+            parts.push(format!("exit {exit_code}"));
+        }
+        if let Some(bytes) = self.bytes_truncated {
+            parts.push(format!("{bytes} bytes truncated"));
+        }
+        if !self.paths.is_empty() {
+            parts.push(self.paths.join(", "));
+        }
+        parts.join(" · ")
+    }
 }
 
 impl From<ToolResult> for ToolUseResult {
@@ -345,6 +382,7 @@ impl From<ToolResult> for ToolUseResult {
             tool_use_id: value.tool_use_id,
             content: value.content.into_iter().map(Into::into).collect(),
             status: value.status,
+            provenance: None,
         }
     }
 }
@@ -430,6 +468,11 @@ impl UserEnvContext {
             env_state: Some(build_env_state()),
         }
     }
+
+    /// The working directory of the session when the message was created.
+    pub fn current_working_directory(&self) -> Option<&str> {
+        self.env_state.as_ref()?.current_working_directory.as_deref()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -480,6 +523,18 @@ impl AssistantMessage {
             AssistantMessage::Response { .. } => None,
         }
     }
+
+    /// Appends `continuation`, the rest of a response that the connection dropped in the middle
+    /// of. The message id and tool uses are taken from the continuation.
+    pub fn continued_by(self, continuation: AssistantMessage) -> Self {
+        let content = format!("{}{}", self.content(), continuation.content());
+        match continuation {
+            AssistantMessage::Response { message_id, .. } => Self::new_response(message_id, content),
+            AssistantMessage::ToolUse {
+                message_id, tool_uses, ..
+            } => Self::new_tool_use(message_id, content, tool_uses),
+        }
+    }
 }
 
 impl From<AssistantMessage> for AssistantResponseMessage {
@@ -580,15 +635,16 @@ mod tests {
         let msgs = {
             let msg = UserMessage::new_prompt(USER_PROMPT.to_string(), Some(timestamp));
             [
-                msg.clone().into_user_input_message(None, &HashMap::new()),
-                msg.clone().into_history_entry(),
+                msg.clone()
+                    .into_user_input_message(None, &HashMap::new(), &ContextTemplates::default()),
+                msg.clone().into_history_entry(&ContextTemplates::default()),
             ]
         };
         let expected = [
-            CONTEXT_ENTRY_START_HEADER,
+            "--- CONTEXT ENTRY BEGIN ---\n",
             "Current time",
             "Friday",
-            CONTEXT_ENTRY_END_HEADER,
+            "--- CONTEXT ENTRY END ---\n\n",
             USER_ENTRY_START_HEADER,
             USER_PROMPT,
             USER_ENTRY_END_HEADER.trim(), /* user message content is trimmed, so remove any
@@ -606,6 +662,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_timestamp_uses_entry_template() {
+        let timestamp = DateTime::parse_from_rfc3339("2018-01-26T12:30:09.453-07:00").unwrap();
+        let templates = ContextTemplates {
+            entry: Some("<context>{{content}}</context>\n".to_string()),
+            ..Default::default()
+        };
+        let msg = UserMessage::new_prompt("hello".to_string(), Some(timestamp)).into_history_entry(&templates);
+        assert!(
+            msg.content
+                .starts_with("<context>Current time: Friday, 2018-01-26T12:30:09.453-07:00\n</context>"),
+            "{}",
+            msg.content
+        );
+    }
+
     #[test]
     fn test_user_input_message_without_context() {
         const USER_PROMPT: &str = "hello world";
@@ -613,17 +685,50 @@ mod tests {
         let msg = UserMessage::new_prompt(USER_PROMPT.to_string(), None);
 
         let msgs = [
-            msg.clone().into_user_input_message(None, &HashMap::new()),
-            msg.clone().into_history_entry(),
+            msg.clone()
+                .into_user_input_message(None, &HashMap::new(), &ContextTemplates::default()),
+            msg.clone().into_history_entry(&ContextTemplates::default()),
         ];
 
         for m in msgs {
-            assert!(!m.content.contains(CONTEXT_ENTRY_START_HEADER));
+            assert!(!m.content.contains("--- CONTEXT ENTRY BEGIN ---"));
             assert!(!m.content.contains("Current UTC time"));
-            assert!(!m.content.contains(CONTEXT_ENTRY_END_HEADER));
+            assert!(!m.content.contains("--- CONTEXT ENTRY END ---"));
             assert!(!m.content.contains(USER_ENTRY_START_HEADER));
             assert!(m.content.contains(USER_PROMPT));
             assert!(!m.content.contains(USER_ENTRY_END_HEADER.trim()));
         }
     }
+
+    #[test]
+    fn test_tool_provenance() {
+        let result = ToolUseResult {
+            tool_use_id: "1".to_string(),
+            content: vec![ToolUseResultBlock::Text("ok".to_string())],
+            status: ToolResultStatus::Success,
+            provenance: Some(ToolProvenance {
+                tool_name: "execute_bash".to_string(),
+                duration_ms: 1300,
+                exit_code: Some(1),
+                bytes_truncated: Some(512),
+                paths: vec![],
+            }),
+        };
+        assert_eq!(
+            result.provenance.as_ref().unwrap().summary(),
+            "execute_bash · 1.3s · exit 1 · 512 bytes truncated"
+        );
+
+        let stored = serde_json::from_value::<ToolUseResult>(serde_json::to_value(&result).unwrap()).unwrap();
+        assert_eq!(stored.provenance, result.provenance);
+
+        // Results stored before provenance existed still load
+        let old = serde_json::json!({ "tool_use_id": "1", "content": [], "status": "Success" });
+        assert!(
+            serde_json::from_value::<ToolUseResult>(old)
+                .unwrap()
+                .provenance
+                .is_none()
+        );
+    }
 }
@@ -9,27 +9,44 @@ use crate::api_client::error::ConverseStreamErrorKind;
 use crate::theme::StyledText;
 use crate::util::ui::should_send_structured_message;
 pub mod cli;
+#[cfg(unix)]
+mod command_palette;
 mod consts;
+mod content_scan;
 pub mod context;
 mod conversation;
+pub mod history;
 mod input_source;
 mod message;
 mod parse;
+pub mod shell_activity;
+pub mod shell_completion;
+mod shell_integration;
+mod tool_output;
+mod turn_changes;
 use std::path::MAIN_SEPARATOR;
 pub mod checkpoint;
 mod line_tracker;
 mod parser;
+mod pins;
 mod prompt;
 mod prompt_parser;
+mod saved_conversations;
+pub mod script;
 pub mod server_messenger;
 use crate::cli::chat::checkpoint::CHECKPOINT_MESSAGE_MAX_LENGTH;
 use crate::constants::ui_text;
 #[cfg(unix)]
 mod skim_integration;
-mod token_counter;
+mod stale_context;
+mod title;
+pub mod token_counter;
+mod tool_block;
 pub mod tool_manager;
 pub mod tools;
+mod turn_budget;
 pub mod util;
+mod workspace;
 use std::borrow::Cow;
 use std::collections::{
     HashMap,
@@ -64,6 +81,7 @@ use chat_cli_ui::protocol::{
     ToolCallRejection,
     ToolCallStart,
 };
+use chat_cli_ui::render::DEFAULT_MAX_FPS;
 use clap::{
     Args,
     CommandFactory,
@@ -77,6 +95,10 @@ use cli::model::{
     get_available_models,
     select_model,
 };
+use content_scan::{
+    ContentScanner,
+    ScanMode,
+};
 pub use conversation::ConversationState;
 use conversation::TokenWarningLevel;
 use crossterm::style::{
@@ -90,6 +112,7 @@ use crossterm::{
     style,
     terminal,
 };
+use disk_cache::DiskCache;
 use eyre::{
     Report,
     Result,
@@ -100,6 +123,7 @@ use input_source::InputSource;
 use message::{
     AssistantMessage,
     AssistantToolUse,
+    ToolProvenance,
     ToolUseResult,
     ToolUseResultBlock,
 };
@@ -114,20 +138,31 @@ use parser::{
 };
 use regex::Regex;
 use rmcp::model::PromptMessage;
+use script::ScriptRun;
+use shell_activity::ShellActivity;
 use thiserror::Error;
 use time::OffsetDateTime;
-use token_counter::TokenCounter;
+use token_counter::{
+    CharCounter,
+    TokenCount,
+    TokenCounter,
+};
 use tokio::signal::ctrl_c;
 use tokio::sync::{
     Mutex,
     broadcast,
 };
+use tool_block::{
+    CollapsedTool,
+    CollapsedTools,
+};
 use tool_manager::{
     PromptQuery,
     PromptQueryResult,
     ToolManager,
     ToolManagerBuilder,
 };
+use tool_output::ToolOutputReducer;
 use tools::delegate::{
     AgentExecution,
     save_agent_execution,
@@ -135,6 +170,7 @@ use tools::delegate::{
 };
 use tools::gh_issue::GhIssueContext;
 use tools::{
+    InvokeOutput,
     NATIVE_TOOLS,
     OutputKind,
     QueuedTool,
@@ -148,6 +184,11 @@ use tracing::{
     trace,
     warn,
 };
+use turn_budget::{
+    TurnBudget,
+    TurnUsage,
+};
+use turn_changes::TurnChanges;
 use util::images::RichImageBlock;
 use util::ui::draw_box;
 use util::{
@@ -156,6 +197,7 @@ use util::{
 };
 use winnow::Partial;
 use winnow::stream::Offset;
+use workspace::Workspace;
 
 use super::agent::{
     Agent,
@@ -168,14 +210,26 @@ use crate::api_client::{
     ApiClientError,
 };
 use crate::auth::AuthError;
-use crate::auth::builder_id::is_idc_user;
+use crate::auth::builder_id::{
+    BuilderIdToken,
+    is_idc_user,
+};
 use crate::cli::TodoListState;
-use crate::cli::agent::Agents;
+use crate::cli::agent::{
+    Agents,
+    egress,
+};
 use crate::cli::chat::checkpoint::{
     CheckpointManager,
     truncate_message,
 };
 use crate::cli::chat::cli::SlashCommand;
+use crate::cli::chat::cli::custom::{
+    custom_command_names,
+    custom_commands_help,
+    execute_custom_command,
+    find_custom_command,
+};
 use crate::cli::chat::cli::editor::open_editor;
 use crate::cli::chat::cli::prompts::{
     GetPromptError,
@@ -207,8 +261,15 @@ use crate::telemetry::{
     get_error_reason,
 };
 use crate::util::paths::PathResolver;
+use crate::util::sleep_monitor::SleepMonitor;
+use crate::util::startup_profile::stage;
+use crate::util::terminal::TerminalQuirks;
+use crate::util::time_format::TimeFormatter;
 use crate::util::{
     MCP_SERVER_TOOL_DELIMITER,
+    backups,
+    cache,
+    startup_profile,
     ui,
 };
 
@@ -249,14 +310,42 @@ pub struct ChatArgs {
     pub no_interactive: bool,
     /// The first question to ask
     pub input: Option<String>,
+    /// Ask why the last command in your shell failed, using the shell activity log
+    #[arg(long, conflicts_with = "input")]
+    pub explain_error: bool,
     /// Control line wrapping behavior (default: auto-detect)
     #[arg(short = 'w', long, value_enum)]
     pub wrap: Option<WrapMode>,
+    /// Print a breakdown of how long each stage of startup took
+    #[arg(long)]
+    pub profile_startup: bool,
+    /// List the saved conversations of this workspace with their titles, and pick one to resume
+    #[arg(long, conflicts_with_all = ["input", "resume", "explain_error"])]
+    pub list: bool,
+    /// With --list, list the conversations of every workspace
+    #[arg(long, requires = "list")]
+    pub all: bool,
 }
 
 impl ChatArgs {
     pub async fn execute(mut self, os: &mut Os) -> Result<ExitCode> {
-        let mut input = self.input;
+        if self.list {
+            let interactive = !self.no_interactive && std::io::stdin().is_terminal();
+            match saved_conversations::select(os, interactive, self.all).await? {
+                Some(workspace) => {
+                    if let Err(e) = os.env.set_current_dir(&workspace) {
+                        bail!("Failed to open {}: {e}", workspace.display());
+                    }
+                    self.resume = true;
+                },
+                None => return Ok(ExitCode::SUCCESS),
+            }
+        }
+
+        let mut input = self.input.take();
+        if self.explain_error {
+            input = Some(shell_activity::explain_error_prompt(os).await?);
+        }
 
         if self.no_interactive && input.is_none() {
             if !std::io::stdin().is_terminal() {
@@ -278,6 +367,18 @@ impl ChatArgs {
             }
         }
 
+        let profile_startup = self.profile_startup;
+        let mut session = self.into_session(os, input).await?;
+        if profile_startup {
+            startup_profile::write_report(&mut std::io::stderr())?;
+        }
+
+        session.spawn(os).await.map(|_| ExitCode::SUCCESS)
+    }
+
+    /// Loads the agents, tools and model the arguments ask for, and sets up a session that starts
+    /// with `input`.
+    async fn into_session(mut self, os: &mut Os, input: Option<String>) -> Result<ChatSession> {
         let mut stderr = std::io::stderr();
 
         let args: Vec<String> = std::env::args().collect();
@@ -301,8 +402,14 @@ impl ChatArgs {
         let conversation_id = uuid::Uuid::new_v4().to_string();
         info!(?conversation_id, "Generated new conversation id");
 
+        // Fetching the models doesn't depend on the agents, so it runs while they are loaded
+        let models_handle = {
+            let os = os.clone();
+            tokio::spawn(async move { stage("model handshake", get_available_models(&os)).await })
+        };
+
         // Check MCP status once at the beginning of the session
-        let mcp_enabled = match os.client.is_mcp_enabled().await {
+        let mcp_enabled = match stage("mcp config check", os.client.is_mcp_enabled()).await {
             Ok(enabled) => enabled,
             Err(err) => {
                 tracing::warn!(?err, "Failed to check MCP configuration, defaulting to enabled");
@@ -312,8 +419,11 @@ impl ChatArgs {
 
         let agents = {
             let skip_migration = self.no_interactive;
-            let (mut agents, md) =
-                Agents::load(os, self.agent.as_deref(), skip_migration, &mut stderr, mcp_enabled).await;
+            let (mut agents, md) = stage(
+                "agent config parse",
+                Agents::load(os, self.agent.as_deref(), skip_migration, &mut stderr, mcp_enabled),
+            )
+            .await;
             agents.trust_all_tools = self.trust_all_tools;
 
             os.telemetry
@@ -378,7 +488,7 @@ impl ChatArgs {
 
         // If modelId is specified, verify it exists before starting the chat
         // Otherwise, CLI will use a default model when starting chat
-        let (models, default_model_opt) = get_available_models(os).await?;
+        let (models, default_model_opt) = models_handle.await??;
         // Fallback logic: try user's saved default, then system default
         let fallback_model_id = || {
             if let Some(saved) = os.database.settings.get_string(Setting::ChatDefaultModel) {
@@ -427,36 +537,43 @@ impl ChatArgs {
         let (prompt_request_sender, prompt_request_receiver) = tokio::sync::broadcast::channel::<PromptQuery>(5);
         let (prompt_response_sender, prompt_response_receiver) =
             tokio::sync::broadcast::channel::<PromptQueryResult>(5);
-        let mut tool_manager = ToolManagerBuilder::default()
+        let tool_manager = ToolManagerBuilder::default()
             .prompt_query_result_sender(prompt_response_sender)
             .prompt_query_receiver(prompt_request_receiver)
             .prompt_query_sender(prompt_request_sender.clone())
             .prompt_query_result_receiver(prompt_response_receiver.resubscribe())
             .conversation_id(&conversation_id)
-            .agent(agents.get_active().cloned().unwrap_or_default())
-            .build(os, Box::new(std::io::stderr()), !self.no_interactive)
-            .await?;
-        let tool_config = tool_manager.load_tools(os, &mut stderr).await?;
+            .agent(agents.get_active().cloned().unwrap_or_default());
+        let mut tool_manager = stage(
+            "mcp server launch",
+            tool_manager.build(os, Box::new(std::io::stderr()), !self.no_interactive),
+        )
+        .await?;
+        let tool_config = stage("tool load", tool_manager.load_tools(os, &mut stderr)).await?;
 
-        ChatSession::new(
-            os,
-            &conversation_id,
-            agents,
-            input,
-            InputSource::new(os, prompt_request_sender, prompt_response_receiver)?,
-            self.resume,
-            || terminal::window_size().map(|s| s.columns.into()).ok(),
-            tool_manager,
-            model_id,
-            tool_config,
-            !self.no_interactive,
-            mcp_enabled,
-            self.wrap,
+        stage(
+            "chat session init",
+            ChatSession::new(
+                os,
+                &conversation_id,
+                agents,
+                input,
+                InputSource::new(os, prompt_request_sender, prompt_response_receiver)?,
+                self.resume,
+                || {
+                    terminal::window_size()
+                        .map(|s| TerminalQuirks::current().usable_width(s.columns.into()))
+                        .ok()
+                },
+                tool_manager,
+                model_id,
+                tool_config,
+                !self.no_interactive,
+                mcp_enabled,
+                self.wrap,
+            ),
         )
-        .await?
-        .spawn(os)
         .await
-        .map(|_| ExitCode::SUCCESS)
     }
 }
 
@@ -469,11 +586,18 @@ const KIRO_UPGRADE_MAX_SHOW_COUNT: i64 = 5;
 const GREETING_BREAK_POINT: usize = 80;
 
 const RESPONSE_TIMEOUT_CONTENT: &str = "Response timed out - message took too long to generate";
+
+/// Sent in place of the tool results when tool uses are denied
+const TOOL_USE_DENIED_MESSAGE: &str =
+    "I deny this tool request. Ask a follow up question clarifying the expected action";
+
+/// Most times a response is continued after the connection dropped, per user turn
+const MAX_STREAM_RESUMES: u32 = 3;
 fn trust_all_text() -> String {
     ui_text::trust_all_warning()
 }
 
-fn format_rich_notification(executions: &[AgentExecution]) -> String {
+fn format_rich_notification(executions: &[AgentExecution], formatter: &TimeFormatter) -> String {
     let count = executions.len();
     let header = if count == 1 {
         "1 Background Task Completed".to_string()
@@ -491,19 +615,9 @@ fn format_rich_notification(executions: &[AgentExecution]) -> String {
             tools::delegate::AgentStatus::Running => "⏳ RUNNING", // shouldn't happen but just in case
         };
 
-        let time_ago = if let Some(completed_at) = execution.completed_at {
-            let duration = chrono::Utc::now().signed_duration_since(completed_at);
-            if duration.num_minutes() < 1 {
-                "Completed just now".to_string()
-            } else if duration.num_minutes() < 60 {
-                format!("Completed {} min ago", duration.num_minutes())
-            } else if duration.num_hours() < 24 {
-                format!("Completed {} hr ago", duration.num_hours())
-            } else {
-                format!("Completed {} days ago", duration.num_days())
-            }
-        } else {
-            "unknown".to_string()
+        let time_ago = match execution.completed_at {
+            Some(completed_at) => format!("Completed {}", formatter.format(&completed_at)),
+            None => "unknown".to_string(),
         };
 
         // Shorten CWD path - replace home directory with ~
@@ -665,6 +779,19 @@ pub struct ChatSession {
     tool_turn_start_time: Option<Instant>,
     /// [RequestMetadata] about the ongoing operation.
     user_turn_request_metadata: Vec<RequestMetadata>,
+    /// Times the connection dropped during a response of the ongoing user turn and the response
+    /// was continued.
+    stream_resumes: u32,
+    /// Notices when the system resumes from sleep, which can expire the login and break
+    /// connections.
+    sleep_monitor: SleepMonitor,
+    /// When the last resume from sleep that was handled was detected
+    handled_resume: Option<Instant>,
+    /// Identifies the ongoing user turn, which scopes the tool results reused from
+    /// [Self::tool_cache].
+    user_turn_id: String,
+    /// Results of read-only tool uses, [None] if the cache couldn't be opened.
+    tool_cache: Option<DiskCache>,
     /// Telemetry events to be sent as part of the conversation. The HashMap key is tool_use_id.
     tool_use_telemetry_events: HashMap<String, ToolUseEventBuilder>,
     /// State used to keep track of tool use relation
@@ -680,6 +807,23 @@ pub struct ChatSession {
     prompt_ack_rx: std::sync::mpsc::Receiver<()>,
     /// Additional context to be added to the next user message (e.g., delegate task summaries)
     pending_additional_context: Option<String>,
+    /// Shell commands already shared with the model
+    shell_activity: ShellActivity,
+    /// Executed tools shown as a single line, [None] if tool output is shown in full.
+    collapsed_tools: Option<CollapsedTools>,
+    /// Files written during the current and the last completed turn
+    turn_changes: TurnChanges,
+    /// What the model did so far to answer the current prompt, checked against the [TurnBudget] of
+    /// the agent
+    turn_usage: TurnUsage,
+    /// Checks prompts for sensitive content before they are sent, [None] if disabled
+    content_scanner: Option<ContentScanner>,
+    /// Title being generated for the conversation, see [title::spawn]
+    title_task: Option<tokio::task::JoinHandle<Option<String>>>,
+    /// Whether a title was requested in this session, so that a failed request isn't repeated
+    title_requested: bool,
+    /// Script the prompts are taken from instead of the user, see [script]
+    script: Option<ScriptRun>,
 }
 
 impl ChatSession {
@@ -707,20 +851,44 @@ impl ChatSession {
             get_legacy_conduits(should_send_structured_msg);
         let (prompt_ack_tx, prompt_ack_rx) = std::sync::mpsc::channel::<()>();
 
+        let max_fps = os
+            .database
+            .settings
+            .get_int(Setting::ChatRenderMaxFps)
+            .and_then(|fps| u32::try_from(fps).ok())
+            .unwrap_or(DEFAULT_MAX_FPS);
         tokio::task::spawn_blocking(move || {
             let stderr = std::io::stderr();
             let stdout = std::io::stdout();
-            if let Err(e) = view_end.into_legacy_mode(StyledText, Some(prompt_ack_tx), stderr, stdout) {
+            if let Err(e) = view_end.into_legacy_mode(StyledText, Some(prompt_ack_tx), stderr, stdout, max_fps) {
                 error!("Conduit view end legacy mode exited: {:?}", e);
             }
         });
 
+        let collapsed_tools = (interactive
+            && os
+                .database
+                .settings
+                .get_bool(Setting::ChatCollapseToolOutput)
+                .unwrap_or(false))
+        .then(CollapsedTools::default);
+
         let conversation = match resume_conversation {
             true => {
-                let previous_conversation = std::env::current_dir()
-                    .ok()
-                    .and_then(|cwd| os.database.get_conversation_by_path(cwd).ok())
-                    .flatten();
+                // Fall back to the latest conversation of the workspace, e.g. one started at the
+                // root of the repository when resuming from a subdirectory
+                let previous_conversation = match os.env.current_dir() {
+                    Ok(cwd) => {
+                        let at_cwd = os.database.get_conversation_by_path(&cwd).ok().flatten();
+                        match at_cwd.filter(|cs| !cs.history().is_empty()) {
+                            Some(cs) => Some(cs),
+                            None => saved_conversations::latest_in_workspace(os, &Workspace::detect(&cwd).await)
+                                .await
+                                .and_then(|path| os.database.get_conversation_by_path(path).ok().flatten()),
+                        }
+                    },
+                    Err(_) => None,
+                };
 
                 // Only restore conversations where there were actual messages
                 // Prevents edge case where user clears conversation then exits without chatting.
@@ -794,6 +962,10 @@ impl ChatSession {
             }
         });
 
+        let tool_cache = cache::open(os)
+            .map_err(|err| warn!(?err, "failed to open the tool cache"))
+            .ok();
+
         Ok(Self {
             stdout: control_end_stdout,
             stderr: control_end_stderr,
@@ -805,6 +977,11 @@ impl ChatSession {
             conversation,
             tool_uses: vec![],
             user_turn_request_metadata: vec![],
+            stream_resumes: 0,
+            sleep_monitor: SleepMonitor::spawn(),
+            handled_resume: None,
+            user_turn_id: uuid::Uuid::new_v4().to_string(),
+            tool_cache,
             pending_tool_index: None,
             tool_turn_start_time: None,
             tool_use_telemetry_events: HashMap::new(),
@@ -817,10 +994,27 @@ impl ChatSession {
             wrap,
             prompt_ack_rx,
             pending_additional_context: None,
+            shell_activity: ShellActivity::default(),
+            collapsed_tools,
+            turn_changes: TurnChanges::default(),
+            turn_usage: TurnUsage::default(),
+            content_scanner: ContentScanner::from_settings(os),
+            title_task: None,
+            title_requested: false,
+            script: None,
         })
     }
 
     pub async fn next(&mut self, os: &mut Os) -> Result<(), ChatError> {
+        if let Some(resume) = self
+            .sleep_monitor
+            .last_resume()
+            .filter(|resume| self.handled_resume.is_none_or(|at| resume.at > at))
+        {
+            self.handled_resume = Some(resume.at);
+            self.refresh_after_sleep(os, resume.slept).await;
+        }
+
         // Update conversation state with new tool information
         self.conversation.update_state(false).await;
 
@@ -829,16 +1023,28 @@ impl ChatSession {
             ChatState::PromptUser { skip_printing_tools } => {
                 match (self.interactive, self.tool_uses.is_empty()) {
                     (false, true) => {
-                        self.inner = Some(ChatState::Exit);
+                        // Scripts go on with their next prompt once the model is done
+                        let next_prompt = match self.script.as_mut() {
+                            Some(script) => script.next_prompt(os).await,
+                            None => None,
+                        };
+                        self.inner = Some(match next_prompt {
+                            Some(input) => ChatState::HandleInput { input },
+                            None => ChatState::Exit,
+                        });
                         return Ok(());
                     },
                     (false, false) => {
-                        return Err(ChatError::NonInteractiveToolApproval);
+                        // Scripts deny the tool uses they don't approve, and the model is told so
+                        let pending = self.pending_tool_index.and_then(|i| self.tool_uses.get(i));
+                        match (self.script.as_mut(), pending) {
+                            (Some(script), Some(tool)) => script.record_denial(&tool.name),
+                            _ => return Err(ChatError::NonInteractiveToolApproval),
+                        }
+                        self.deny_tool_uses(os).await
                     },
-                    _ => (),
-                };
-
-                self.prompt_user(os, skip_printing_tools).await
+                    _ => self.prompt_user(os, skip_printing_tools).await,
+                }
             },
             ChatState::HandleInput { input } => {
                 tokio::select! {
@@ -1475,6 +1681,10 @@ impl ChatSession {
             self.conversation.checkpoint_manager = checkpoint_manager;
         }
 
+        if self.existing_conversation {
+            stale_context::offer_refresh(os, self).await?;
+        }
+
         if let Some(user_input) = self.initial_input.take() {
             self.inner = Some(ChatState::HandleInput { input: user_input });
         }
@@ -2010,6 +2220,13 @@ impl ChatSession {
     async fn prompt_user(&mut self, os: &Os, skip_printing_tools: bool) -> Result<ChatState, ChatError> {
         execute!(self.stderr, cursor::Show)?;
 
+        // The generated title is saved by the task itself, it only has to be kept for later saves
+        if self.title_task.as_ref().is_some_and(|task| task.is_finished()) {
+            if let Some(Ok(Some(title))) = self.title_task.take().and_then(futures::FutureExt::now_or_never) {
+                self.conversation.set_title(title);
+            }
+        }
+
         // Check token usage and display warnings if needed
         if self.pending_tool_index.is_none() {
             // Only display warnings when not waiting for tool approval
@@ -2061,8 +2278,18 @@ impl ChatSession {
                 .filter(|name| *name != DUMMY_TOOL_NAME)
                 .cloned()
                 .collect::<Vec<_>>();
+            let context_manager = Arc::new(context_manager.clone());
+            let palette_items = command_palette::session_palette_items(os, self);
             self.input_source
-                .put_skim_command_selector(os, Arc::new(context_manager.clone()), tool_names);
+                .put_skim_command_selector(os, context_manager.clone(), tool_names.clone());
+            self.input_source
+                .put_command_palette(os, context_manager, tool_names, palette_items);
+        }
+
+        let custom_commands = custom_command_names(self);
+        self.input_source.set_custom_commands(custom_commands);
+        if let Ok(cwd) = os.env.current_dir() {
+            self.input_source.set_cwd(cwd);
         }
 
         execute!(self.stderr, StyledText::reset(), StyledText::reset_attributes())?;
@@ -2086,7 +2313,7 @@ impl ChatSession {
             error!("Failed to receive user prompting acknowledgement from UI: {:?}", e);
         }
 
-        let user_input = match self.read_user_input(&prompt, false) {
+        let user_input = match self.read_user_input(&prompt, false)? {
             Some(input) => input,
             None => return Ok(ChatState::Exit),
         };
@@ -2129,6 +2356,13 @@ impl ChatSession {
             // Required for printing errors correctly.
             let orig_args = args.clone();
 
+            // User defined commands from the agent config
+            if let Some((name, custom_args)) = orig_args.split_first() {
+                if let Some(custom_command) = find_custom_command(self, name) {
+                    return execute_custom_command(os, self, name, &custom_command, custom_args).await;
+                }
+            }
+
             // We set the binary name as a dummy name "slash_command" which we
             // replace anytime we error out and print a usage statement.
             args.insert(0, "slash_command".to_owned());
@@ -2194,6 +2428,13 @@ impl ChatSession {
 
                     writeln!(self.stderr, "{}", ansi_output)?;
 
+                    // The help of the top level lists the user defined commands after the built-in ones
+                    if err.kind() == clap::error::ErrorKind::DisplayHelp && orig_args.len() == 1 {
+                        if let Some(help) = custom_commands_help(self) {
+                            writeln!(self.stderr, "{help}")?;
+                        }
+                    }
+
                     // Print the subcommand help, if available. Required since by default we won't
                     // show what the actual arguments are, requiring an unnecessary --help call.
                     if let clap::error::ErrorKind::InvalidValue
@@ -2237,11 +2478,19 @@ impl ChatSession {
             return subcommand.execute(os, self).await;
         } else if let Some(command) = input.strip_prefix("!") {
             // Use platform-appropriate shell
-            let result = if cfg!(target_os = "windows") {
-                std::process::Command::new("cmd").args(["/C", command]).status()
+            let mut shell = if cfg!(target_os = "windows") {
+                let mut shell = std::process::Command::new("cmd");
+                shell.args(["/C", command]);
+                shell
             } else {
-                std::process::Command::new("bash").args(["-c", command]).status()
+                let mut shell = std::process::Command::new("bash");
+                shell.args(["-c", command]);
+                shell
             };
+            if let Some(dir) = os.env.changed_dir() {
+                shell.current_dir(dir);
+            }
+            let result = shell.status();
 
             // Handle the result and provide appropriate feedback
             match result {
@@ -2330,37 +2579,75 @@ impl ChatSession {
                 // get a meaningful response from the user - this is a short term solution before
                 // we decide on a better flow.
                 let user_input = if ["n", "N"].contains(&user_input.trim()) {
-                    "I deny this tool request. Ask a follow up question clarifying the expected action".to_string()
+                    TOOL_USE_DENIED_MESSAGE.to_string()
                 } else {
                     user_input
                 };
                 self.conversation.abandon_tool_use(&self.tool_uses, user_input);
             } else {
+                if let Some(scanner) = self.content_scanner.as_mut() {
+                    let findings = scanner
+                        .scan_request(
+                            os,
+                            &user_input,
+                            self.pending_additional_context.as_deref(),
+                            self.conversation.context_manager.as_ref(),
+                        )
+                        .await;
+                    if !findings.is_empty() {
+                        content_scan::print_findings(&mut self.stderr, &findings, scanner.mode())?;
+                        if scanner.mode() == ScanMode::Block {
+                            return Ok(ChatState::PromptUser {
+                                skip_printing_tools: true,
+                            });
+                        }
+                    }
+                }
+
                 // Add additional context if available (e.g., delegate summaries)
-                let context = self.pending_additional_context.take().unwrap_or_default();
+                let mut context = self.pending_additional_context.take().unwrap_or_default();
+                if let Some(activity) = self.shell_activity.recent(os).await {
+                    if !context.is_empty() {
+                        context.push_str("\n\n");
+                    }
+                    context.push_str(&activity);
+                }
                 self.conversation
                     .set_next_user_message_with_context(user_input, context)
                     .await;
             }
 
-            self.reset_user_turn();
+            self.send_user_turn(os).await
+        }
+    }
 
-            let conv_state = self
-                .conversation
-                .as_sendable_conversation_state(os, &mut self.stderr, true)
-                .await?;
-            self.send_tool_use_telemetry(os).await;
+    /// Denies the pending tool uses without asking the user, as scripts do for tools they don't
+    /// approve, and tells the model so.
+    async fn deny_tool_uses(&mut self, os: &mut Os) -> Result<ChatState, ChatError> {
+        self.tool_use_status = ToolUseStatus::Idle;
+        self.conversation
+            .abandon_tool_use(&self.tool_uses, TOOL_USE_DENIED_MESSAGE.to_string());
+        self.send_user_turn(os).await
+    }
 
-            queue!(self.stderr, StyledText::emphasis_fg())?;
-            queue!(self.stderr, StyledText::reset())?;
-            queue!(self.stderr, cursor::Hide)?;
+    async fn send_user_turn(&mut self, os: &mut Os) -> Result<ChatState, ChatError> {
+        self.reset_user_turn();
 
-            if self.interactive {
-                self.spinner = Some(Spinner::new(Spinners::Dots, "Thinking...".to_owned()));
-            }
+        let conv_state = self
+            .conversation
+            .as_sendable_conversation_state(os, &mut self.stderr, true)
+            .await?;
+        self.send_tool_use_telemetry(os).await;
 
-            Ok(ChatState::HandleResponseStream(conv_state))
+        queue!(self.stderr, StyledText::emphasis_fg())?;
+        queue!(self.stderr, StyledText::reset())?;
+        queue!(self.stderr, cursor::Hide)?;
+
+        if self.interactive {
+            self.spinner = Some(Spinner::new(Spinners::Dots, "Thinking...".to_owned()));
         }
+
+        Ok(ChatState::HandleResponseStream(conv_state))
     }
 
     async fn tool_use_execute(&mut self, os: &mut Os) -> Result<ChatState, ChatError> {
@@ -2389,20 +2676,30 @@ impl ChatSession {
                 continue;
             }
 
-            let mut denied_match_set = None::<Vec<String>>;
-            let allowed =
-                self.conversation
+            // The egress policy applies even to tools that are trusted, so it is checked before
+            // the user can be asked to accept the tool
+            let mut denied_match_set = match self.conversation.agents.get_active() {
+                Some(agent) => egress::check_tool(os, agent, &tool.tool)
+                    .await
+                    .err()
+                    .map(|err| vec![err.to_string()]),
+                None => None,
+            };
+
+            let allowed = self.conversation
                     .agents
                     .get_active()
                     .is_some_and(|a| match tool.tool.requires_acceptance(os, a) {
                         PermissionEvalResult::Allow => true,
                         PermissionEvalResult::Ask => false,
                         PermissionEvalResult::Deny(matches) => {
-                            denied_match_set.replace(matches);
+                            denied_match_set.get_or_insert(matches);
                             false
                         },
                     })
-                    || self.conversation.agents.trust_all_tools;
+                    // Deleting files always asks, even when every tool is trusted
+                    || (self.conversation.agents.trust_all_tools
+                        && !matches!(&tool.tool, Tool::FsWrite(w) if w.is_delete()));
 
             if let Some(match_set) = denied_match_set {
                 let formatted_set = match_set.into_iter().fold(String::new(), |mut acc, rule| {
@@ -2476,9 +2773,63 @@ impl ChatSession {
         // Execute the requested tools.
         let mut tool_results = vec![];
         let mut image_blocks: Vec<RichImageBlock> = Vec::new();
+        let tool_output_reducer = ToolOutputReducer::from_settings(os);
 
         for tool in &self.tool_uses {
             let tool_start = std::time::Instant::now();
+            // Set when the tool must not run, with the reason
+            let mut refusal = None;
+            if let Tool::FsWrite(w) = &tool.tool {
+                let path = w.path(os);
+                let hard_delete = w.is_delete()
+                    && self
+                        .conversation
+                        .agents
+                        .get_active()
+                        .is_some_and(tools::fs_write::allows_hard_delete);
+                // Keep a copy of the file so the write can be undone with `q backups restore`
+                if hard_delete {
+                    debug!("hard deleting {} without a backup", path.display());
+                } else if let Err(err) = backups::create(os, self.conversation.conversation_id(), &[path.clone()]).await
+                {
+                    warn!(?err, "failed to back up {}", path.display());
+                    if w.is_delete() {
+                        // Deleting is only allowed when the file can be recovered
+                        refusal = Some(eyre::eyre!(
+                            "{} was not deleted because it could not be backed up first: {err}. Set toolsSettings.fs_write.allowHardDelete in the agent to delete files without a backup.",
+                            path.display()
+                        ));
+                    } else {
+                        execute!(
+                            self.stderr,
+                            StyledText::warning_fg(),
+                            style::Print(format!(
+                                "Could not back up {} before writing it: {err}\n",
+                                path.display()
+                            )),
+                            StyledText::reset(),
+                        )?;
+                    }
+                }
+                self.turn_changes.record(os, path).await;
+            }
+            if let Tool::ExecuteCommand(command) = &tool.tool {
+                let hard_delete = self
+                    .conversation
+                    .agents
+                    .get_active()
+                    .is_some_and(tools::fs_write::allows_hard_delete);
+                // Files removed with `rm` are kept like those deleted with fs_write
+                let removed = command.removed_paths(os).await;
+                if !removed.is_empty() && !hard_delete {
+                    if let Err(err) = backups::create(os, self.conversation.conversation_id(), &removed).await {
+                        warn!(?err, "failed to back up the files removed by {}", command.command);
+                        refusal = Some(eyre::eyre!(
+                            "The command was not run because the files it removes could not be backed up first: {err}. Set toolsSettings.fs_write.allowHardDelete in the agent to delete files without a backup."
+                        ));
+                    }
+                }
+            }
             let mut tool_telemetry = self.tool_use_telemetry_events.entry(tool.id.clone());
             tool_telemetry = tool_telemetry.and_modify(|ev| {
                 ev.is_accepted = true;
@@ -2496,15 +2847,58 @@ impl ChatSession {
                 }
             }
 
-            let invoke_result = tool
-                .tool
-                .invoke(
-                    os,
-                    &mut self.stdout,
-                    &mut self.conversation.file_line_tracker,
-                    &self.conversation.agents,
+            // Repeating a read-only tool use within the same turn reuses the earlier result, as
+            // long as what it reads hasn't changed since.
+            let cache_key = match &self.tool_cache {
+                Some(_) => tool.tool.cache_key(os, &self.user_turn_id).await,
+                None => None,
+            };
+            let cached_output = cache_key
+                .as_ref()
+                .zip(self.tool_cache.as_ref())
+                .and_then(|(key, cache)| cache.get::<String>(key));
+            // Output of collapsed tools is held back until the user expands them.
+            let mut held_output = Vec::new();
+            let mut tool_writer: &mut (dyn Write + Send) = match self.collapsed_tools {
+                Some(_) => &mut held_output,
+                None => &mut self.stdout,
+            };
+            let invoke_result = match (refusal, cached_output) {
+                (Some(err), _) => Err(err),
+                (None, Some(text)) => tools::queue_function_result(
+                    "Reused the result of an identical call earlier in this turn",
+                    &mut tool_writer,
+                    false,
+                    false,
                 )
-                .await;
+                .map(|()| InvokeOutput {
+                    output: OutputKind::Text(text),
+                }),
+                (None, None) => {
+                    let result = tool
+                        .tool
+                        .invoke(
+                            os,
+                            &mut tool_writer,
+                            &mut self.conversation.file_line_tracker,
+                            &self.conversation.agents,
+                        )
+                        .await;
+                    if let (
+                        Some(key),
+                        Some(cache),
+                        Ok(InvokeOutput {
+                            output: OutputKind::Text(text),
+                        }),
+                    ) = (&cache_key, &self.tool_cache, &result)
+                    {
+                        if let Err(err) = cache.insert(key, text) {
+                            warn!(?err, "failed to cache tool result");
+                        }
+                    }
+                    result
+                },
+            };
 
             if let Some(spinner) = self.spinner.take() {
                 drop(spinner);
@@ -2599,6 +2993,12 @@ impl ChatSession {
 
             let tool_end_time = Instant::now();
             let tool_time = tool_end_time.duration_since(tool_start);
+            let mut provenance = ToolProvenance {
+                tool_name: tool.name.clone(),
+                duration_ms: tool_time.as_millis() as u64,
+                paths: tool.tool.paths(),
+                ..Default::default()
+            };
             tool_telemetry = tool_telemetry.and_modify(|ev| {
                 ev.execution_duration = Some(tool_time);
                 ev.turn_duration = self.tool_turn_start_time.map(|t| tool_end_time.duration_since(t));
@@ -2631,26 +3031,37 @@ impl ChatSession {
                     }
 
                     debug!("tool result output: {:#?}", result);
-                    execute!(
-                        self.stdout,
-                        style::Print(CONTINUATION_LINE),
-                        style::Print("\n"),
-                        StyledText::success_fg(),
-                        style::SetAttribute(Attribute::Bold),
-                        style::Print(format!(" ● Completed in {}s", tool_time)),
-                        StyledText::reset(),
-                    )?;
-                    if let Some(tag) = checkpoint_tag {
+                    provenance.exit_code = tool.tool.exit_code(&result);
+                    if let Some(collapsed_tools) = self.collapsed_tools.as_mut() {
+                        let mut output = String::from_utf8_lossy(&held_output).into_owned();
+                        if output.trim().is_empty() {
+                            output = result.as_str().into_owned();
+                        }
+                        let collapsed = CollapsedTool::new(&provenance, tool.tool_input.clone(), output);
+                        tool_block::print_collapsed(&mut self.stdout, &collapsed, checkpoint_tag.as_deref())?;
+                        collapsed_tools.push(collapsed);
+                    } else {
                         execute!(
                             self.stdout,
-                            StyledText::info_fg(),
+                            style::Print(CONTINUATION_LINE),
+                            style::Print("\n"),
+                            StyledText::success_fg(),
                             style::SetAttribute(Attribute::Bold),
-                            style::Print(format!(" [{tag}]")),
+                            style::Print(format!(" ● Completed in {}s", tool_time)),
                             StyledText::reset(),
-                            StyledText::reset_attributes(),
                         )?;
+                        if let Some(tag) = checkpoint_tag {
+                            execute!(
+                                self.stdout,
+                                StyledText::info_fg(),
+                                style::SetAttribute(Attribute::Bold),
+                                style::Print(format!(" [{tag}]")),
+                                StyledText::reset(),
+                                StyledText::reset_attributes(),
+                            )?;
+                        }
+                        execute!(self.stdout, style::Print("\n\n"))?;
                     }
-                    execute!(self.stdout, style::Print("\n\n"))?;
 
                     tool_telemetry = tool_telemetry.and_modify(|ev| ev.is_success = Some(true));
                     if let Tool::Custom(_) = &tool.tool {
@@ -2684,14 +3095,35 @@ impl ChatSession {
                         }
                     }
 
+                    let model_id = self.conversation.model_info.as_ref().map(|m| m.model_id.as_str());
+                    let content = match tool_output_reducer.reduce(os, &tool.name, &result, model_id).await {
+                        Some(reduced) => {
+                            provenance.bytes_truncated = Some(result.as_str().len().saturating_sub(reduced.text.len()));
+                            execute!(
+                                self.stderr,
+                                StyledText::secondary_fg(),
+                                style::Print(format!(
+                                    " The output (~{} tokens) was {} to keep it under {} tokens\n\n",
+                                    reduced.original_tokens, reduced.strategy, tool_output_reducer.token_threshold
+                                )),
+                                StyledText::reset(),
+                            )?;
+                            ToolUseResultBlock::Text(reduced.text)
+                        },
+                        None => result.into(),
+                    };
+
                     tool_results.push(ToolUseResult {
                         tool_use_id: tool.id.clone(),
-                        content: vec![result.into()],
+                        content: vec![content],
                         status: ToolResultStatus::Success,
+                        provenance: Some(provenance),
                     });
                 },
                 Err(err) => {
                     error!(?err, "An error occurred processing the tool");
+                    // Failures are always shown in full.
+                    self.stdout.write_all(&held_output)?;
                     execute!(
                         self.stderr,
                         style::Print(CONTINUATION_LINE),
@@ -2717,6 +3149,7 @@ impl ChatSession {
                             &err
                         ))],
                         status: ToolResultStatus::Error,
+                        provenance: Some(provenance),
                     });
                     if let ToolUseStatus::Idle = self.tool_use_status {
                         self.tool_use_status = ToolUseStatus::RetryInProgress(
@@ -2815,6 +3248,7 @@ impl ChatSession {
         state: crate::api_client::model::ConversationState,
         request_metadata_lock: Arc<Mutex<Option<RequestMetadata>>>,
     ) -> Result<ChatState, ChatError> {
+        let started = Instant::now();
         let mut rx = self.send_message(os, state, request_metadata_lock, None).await?;
 
         let request_id = rx.request_id().map(String::from);
@@ -2838,6 +3272,7 @@ impl ChatSession {
             terminal_width,
             os.database.settings.get_bool(Setting::ChatDisableMarkdownRendering),
         );
+        state.hyperlinks = TerminalQuirks::current().hyperlinks;
         let mut response_prefix_printed = false;
 
         let mut tool_uses = Vec::new();
@@ -2910,6 +3345,7 @@ impl ChatSession {
                             if message.content() == RESPONSE_TIMEOUT_CONTENT {
                                 error!(?request_id, ?message, "Encountered an unexpected model response");
                             }
+                            self.turn_usage.response_tokens += TokenCount::from(message.char_count()).value();
                             self.conversation.push_assistant_message(os, message, Some(rm.clone()));
                             self.user_turn_request_metadata.push(rm);
                             ended = true;
@@ -2926,6 +3362,47 @@ impl ChatSession {
                     let (reason, reason_desc) = get_error_reason(&recv_error);
                     let status_code = recv_error.status_code();
 
+                    // A request interrupted by the system sleeping times out or loses its
+                    // connection, which is not worth reporting. It's sent again instead.
+                    let slept = self.sleep_monitor.slept_since(started).filter(|_| {
+                        self.stream_resumes < MAX_STREAM_RESUMES
+                            && match &recv_error.source {
+                                RecvErrorKind::StreamTimeout { .. } => true,
+                                RecvErrorKind::Client(err) => err.is_disconnect(),
+                                _ => false,
+                            }
+                    });
+                    if let Some(slept) = slept {
+                        self.send_chat_telemetry(
+                            os,
+                            TelemetryResult::Failed,
+                            Some(reason),
+                            Some(reason_desc),
+                            status_code,
+                            false, // We retry the request, so don't end the current turn yet.
+                        )
+                        .await;
+                        info!(
+                            recv_error.request_metadata.request_id,
+                            ?slept,
+                            "retrying a request interrupted by the system sleeping"
+                        );
+
+                        self.stream_resumes += 1;
+                        self.refresh_after_sleep(os, slept).await;
+                        self.handled_resume = self.sleep_monitor.last_resume().map(|resume| resume.at);
+                        execute!(self.stderr, cursor::Hide)?;
+                        self.spinner = Some(Spinner::new(
+                            Spinners::Dots,
+                            "Resuming after the system slept...".to_string(),
+                        ));
+                        return Ok(ChatState::HandleResponseStream(
+                            self.conversation
+                                .as_sendable_conversation_state(os, &mut self.stderr, false)
+                                .await?,
+                        ));
+                    }
+
                     match recv_error.source {
                         RecvErrorKind::StreamTimeout { source, duration } => {
                             self.send_chat_telemetry(
@@ -2968,6 +3445,44 @@ impl ChatSession {
                                     .await?,
                             ));
                         },
+                        RecvErrorKind::Disconnected { source, message } if self.stream_resumes < MAX_STREAM_RESUMES => {
+                            self.send_chat_telemetry(
+                                os,
+                                TelemetryResult::Failed,
+                                Some(reason),
+                                Some(reason_desc),
+                                status_code,
+                                false, // We continue the response, so don't end the current turn yet.
+                            )
+                            .await;
+
+                            error!(
+                                recv_error.request_metadata.request_id,
+                                ?source,
+                                "The connection dropped during the response, continuing it"
+                            );
+                            self.stream_resumes += 1;
+
+                            // Keep the text received so far, and ask the model to pick up from where it
+                            // stopped once the network is back.
+                            execute!(
+                                self.stdout,
+                                style::Print("\n\n"),
+                                StyledText::warning_fg(),
+                                style::Print("Connection lost, reconnecting to continue the response...\n"),
+                                StyledText::reset(),
+                            )?;
+                            tokio::time::sleep(Duration::from_secs(2u64.pow(self.stream_resumes))).await;
+                            self.conversation
+                                .resume_response(os, *message, Some(recv_error.request_metadata))
+                                .await;
+                            self.send_tool_use_telemetry(os).await;
+                            return Ok(ChatState::HandleResponseStream(
+                                self.conversation
+                                    .as_sendable_conversation_state(os, &mut self.stderr, false)
+                                    .await?,
+                            ));
+                        },
                         RecvErrorKind::UnexpectedToolUseEos {
                             tool_use_id,
                             name,
@@ -2996,6 +3511,7 @@ impl ChatSession {
                                         "The generated tool was too large, try again but this time split up the work between multiple tool uses".to_string(),
                                     )],
                                     status: ToolResultStatus::Error,
+                                    provenance: None,
                                 }];
                             self.conversation.add_tool_results(tool_results);
                             self.send_tool_use_telemetry(os).await;
@@ -3034,6 +3550,7 @@ impl ChatSession {
                                     error_message
                                 ))],
                                 status: ToolResultStatus::Error,
+                                provenance: None,
                             }];
                             // User hint of what happened
                             let _ = queue!(
@@ -3184,6 +3701,30 @@ impl ChatSession {
             }
         }
 
+        if !tool_uses.is_empty() {
+            self.turn_usage.tool_iterations += 1;
+            let budget = self
+                .conversation
+                .agents
+                .get_active()
+                .map(TurnBudget::from_agent)
+                .unwrap_or_default();
+            if let Some(reason) = budget.exceeded(&self.turn_usage) {
+                if !self.turn_usage.wrap_up_requested {
+                    return self.wrap_up_turn(os, &tool_uses, &reason).await;
+                }
+                // The model was already asked to answer, the tool uses left in the history are
+                // cancelled with the next prompt.
+                execute!(
+                    self.stderr,
+                    StyledText::warning_fg(),
+                    style::Print(format!("\n{reason}, ending the response.\n\n")),
+                    StyledText::reset(),
+                )?;
+                tool_uses.clear();
+            }
+        }
+
         if !tool_uses.is_empty() {
             Ok(ChatState::ValidateTools { tool_uses })
         } else {
@@ -3244,9 +3785,25 @@ impl ChatSession {
                 }
             }
 
+            let changes = self.turn_changes.finish_turn(os).await;
+            turn_changes::print_summary(&mut self.stdout, changes)?;
+
             self.send_chat_telemetry(os, TelemetryResult::Succeeded, None, None, None, true)
                 .await;
 
+            if !self.title_requested && self.conversation.title().is_none() && title::enabled(os) {
+                if let (Some(first_turn), Ok(cwd)) = (title::first_turn(&self.conversation), os.env.current_dir()) {
+                    self.title_requested = true;
+                    self.title_task = Some(title::spawn(
+                        os,
+                        cwd,
+                        self.conversation.conversation_id().to_string(),
+                        first_turn,
+                        self.conversation.model_info.as_ref().map(|m| m.model_id.clone()),
+                    ));
+                }
+            }
+
             // Run Stop hooks when the assistant finishes responding
             if let Some(cm) = self.conversation.context_manager.as_mut() {
                 let _ = cm
@@ -3266,6 +3823,37 @@ impl ChatSession {
         }
     }
 
+    /// Asks the model to answer with what it has instead of running `tool_uses`, once the turn went
+    /// over the [TurnBudget] of the agent.
+    async fn wrap_up_turn(
+        &mut self,
+        os: &mut Os,
+        tool_uses: &[AssistantToolUse],
+        reason: &str,
+    ) -> Result<ChatState, ChatError> {
+        execute!(
+            self.stderr,
+            StyledText::warning_fg(),
+            style::Print(format!("\n{reason}, asking for an answer without tools.\n\n")),
+            StyledText::reset(),
+            cursor::Hide,
+        )?;
+        self.turn_usage.wrap_up_requested = true;
+        self.conversation.cancel_tool_uses(
+            tool_uses.iter().map(|t| t.id.as_str()),
+            turn_budget::wrap_up_prompt(reason),
+        );
+        if self.interactive {
+            self.spinner = Some(Spinner::new(Spinners::Dots, "Thinking...".to_string()));
+        }
+
+        Ok(ChatState::HandleResponseStream(
+            self.conversation
+                .as_sendable_conversation_state(os, &mut self.stderr, false)
+                .await?,
+        ))
+    }
+
     // Validate the tool use request from LLM, including basic checks like fs_read file should exist, as
     // well as user-defined preToolUse hook check.
     async fn validate_tools(&mut self, os: &Os, tool_uses: Vec<AssistantToolUse>) -> Result<ChatState, ChatError> {
@@ -3310,6 +3898,7 @@ impl ChatSession {
                                     "Failed to validate tool parameters: {err}"
                                 ))],
                                 status: ToolResultStatus::Error,
+                                provenance: None,
                             });
                         },
                     };
@@ -3403,14 +3992,12 @@ impl ChatSession {
 
                 // Check for exit code 2 and add to tool_results
                 for (_, (exit_code, output)) in &hook_results {
-                    if *exit_code == 2 {
+                    if let Some(message) = cli::hooks::pre_tool_use_block_message(*exit_code, output) {
                         tool_results.push(ToolUseResult {
                             tool_use_id: tool.id.clone(),
-                            content: vec![ToolUseResultBlock::Text(format!(
-                                "PreToolHook blocked the tool execution: {}",
-                                output
-                            ))],
+                            content: vec![ToolUseResultBlock::Text(message)],
                             status: ToolResultStatus::Error,
+                            provenance: None,
                         });
                     }
                 }
@@ -3449,6 +4036,22 @@ impl ChatSession {
         Ok(ChatState::ExecuteTools)
     }
 
+    /// Refreshes what may have gone stale while the system was asleep: the login, whose token
+    /// can have expired, and the connections to remote MCP servers.
+    async fn refresh_after_sleep(&mut self, os: &mut Os, slept: Duration) {
+        info!(?slept, "refreshing the session after the system slept");
+        if let Err(err) = BuilderIdToken::load(&os.database, Some(&os.telemetry)).await {
+            warn!(?err, "failed to refresh the login after the system slept");
+        }
+        if let Err(err) = self
+            .conversation
+            .reconnect_remote_mcp_servers(os, &mut std::io::sink())
+            .await
+        {
+            warn!(?err, "failed to reconnect the MCP servers after the system slept");
+        }
+    }
+
     async fn retry_model_overload(&mut self, os: &mut Os) -> Result<ChatState, ChatError> {
         os.client.invalidate_model_cache().await;
         match select_model(os, self).await {
@@ -3560,19 +4163,23 @@ impl ChatSession {
     }
 
     /// Helper function to read user input with a prompt and Ctrl+C handling
-    fn read_user_input(&mut self, prompt: &str, exit_on_single_ctrl_c: bool) -> Option<String> {
+    fn read_user_input(&mut self, prompt: &str, exit_on_single_ctrl_c: bool) -> Result<Option<String>, ChatError> {
         let mut ctrl_c = false;
         loop {
             match (self.input_source.read_line(Some(prompt)), ctrl_c) {
                 (Ok(Some(line)), _) => {
                     if line.trim().is_empty() {
+                        // An empty line expands the most recent collapsed tool, if any
+                        if let Some(tool) = self.collapsed_tools.as_mut().and_then(CollapsedTools::expand_last) {
+                            tool_block::print_expanded(&mut self.stdout, &tool)?;
+                        }
                         continue; // Reprompt if the input is empty
                     }
-                    return Some(line);
+                    return Ok(Some(line));
                 },
                 (Ok(None), false) => {
                     if exit_on_single_ctrl_c {
-                        return None;
+                        return Ok(None);
                     }
                     execute!(
                         self.stderr,
@@ -3584,8 +4191,8 @@ impl ChatSession {
                     .unwrap_or_default();
                     ctrl_c = true;
                 },
-                (Ok(None), true) => return None, // Exit if Ctrl+C was pressed twice
-                (Err(_), _) => return None,
+                (Ok(None), true) => return Ok(None), // Exit if Ctrl+C was pressed twice
+                (Err(_), _) => return Ok(None),
             }
         }
     }
@@ -3610,11 +4217,18 @@ impl ChatSession {
         if ExperimentManager::is_enabled(os, ExperimentName::Delegate) {
             if let Ok(mut executions) = status_all_agents(os).await {
                 if !executions.is_empty() {
-                    let rich_notification = format_rich_notification(&executions);
+                    let rich_notification =
+                        format_rich_notification(&executions, &TimeFormatter::from_settings(&os.database.settings));
                     generated_prompt = format!("{}\n{}", rich_notification, generated_prompt);
 
                     // Use the notification text as context for the model (it's already plain text)
-                    self.pending_additional_context = Some(rich_notification.clone());
+                    match self.pending_additional_context.as_mut() {
+                        Some(context) => {
+                            context.push_str("\n\n");
+                            context.push_str(&rich_notification);
+                        },
+                        None => self.pending_additional_context = Some(rich_notification.clone()),
+                    }
 
                     // Mark all shown tasks as user_notified
                     for execution in &mut executions {
@@ -3684,6 +4298,9 @@ impl ChatSession {
     fn reset_user_turn(&mut self) {
         info!(?self.user_turn_request_metadata, "Resetting the current user turn");
         self.user_turn_request_metadata.clear();
+        self.stream_resumes = 0;
+        self.user_turn_id = uuid::Uuid::new_v4().to_string();
+        self.turn_usage = TurnUsage::default();
     }
 
     /// Sends an "codewhispererterminal_addChatMessage" telemetry event.
@@ -3854,7 +4471,11 @@ impl ChatSession {
 
         Ok(ChatState::HandleInput {
             input: summary_message
-                .into_user_input_message(self.conversation.model.clone(), &self.conversation.tools)
+                .into_user_input_message(
+                    self.conversation.model.clone(),
+                    &self.conversation.tools,
+                    &self.conversation.context_templates(),
+                )
                 .content,
         })
     }
@@ -4079,6 +4700,51 @@ mod tests {
         assert_eq!(os.fs.read_to_string("/file.txt").await.unwrap(), "Hello, world!\n");
     }
 
+    #[tokio::test]
+    async fn test_retry_flow() {
+        let mut os = Os::new().await.unwrap();
+        os.database
+            .settings
+            .set(Setting::ChatGenerateTitles, false)
+            .await
+            .unwrap();
+        os.client
+            .set_mock_output(serde_json::json!([["The first answer"], ["The second answer"],]));
+
+        let agents = get_test_agents(&os).await;
+        let tool_manager = ToolManager::default();
+        let tool_config = serde_json::from_str::<HashMap<String, ToolSpec>>(include_str!("tools/tool_index.json"))
+            .expect("Tools failed to load");
+        let mut session = ChatSession::new(
+            &mut os,
+            "fake_conv_id",
+            agents,
+            None,
+            InputSource::new_mock(vec![
+                "what is rust".to_string(),
+                "/retry".to_string(),
+                "exit".to_string(),
+            ]),
+            false,
+            || Some(80),
+            tool_manager,
+            None,
+            tool_config,
+            true,
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+        session.spawn(&mut os).await.unwrap();
+
+        let history = session.conversation.history();
+        assert_eq!(history.len(), 1);
+        let entry = history.back().unwrap();
+        assert_eq!(entry.user.prompt(), Some("what is rust"));
+        assert_eq!(entry.assistant.content(), "The second answer");
+    }
+
     #[tokio::test]
     async fn test_flow_tool_permissions() {
         let mut os = Os::new().await.unwrap();
@@ -86,6 +86,8 @@ pub struct ParseState {
     pub strikethrough: bool,
     pub set_newline: bool,
     pub newline: bool,
+    /// Whether links can be written as `OSC 8` hyperlinks
+    pub hyperlinks: bool,
     pub citations: Vec<(String, String)>,
 }
 
@@ -102,6 +104,7 @@ impl ParseState {
             strikethrough: false,
             set_newline: false,
             newline: true,
+            hyperlinks: false,
             citations: vec![],
         }
     }
@@ -442,7 +445,14 @@ fn url<'a, 'b>(
         // Only generate output if the complete URL pattern matches
         queue_newline_or_advance(&mut o, state, display.width() + 1)?;
         queue(&mut o, StyledText::info_fg())?;
-        queue(&mut o, style::Print(format!("{display} ")))?;
+        if state.hyperlinks {
+            queue(
+                &mut o,
+                style::Print(format!("\x1b]8;;{link}\x1b\\{display}\x1b]8;;\x1b\\ ")),
+            )?;
+        } else {
+            queue(&mut o, style::Print(format!("{display} ")))?;
+        }
         queue(&mut o, StyledText::secondary_fg())?;
         state.column += link.width();
         queue(&mut o, style::Print(link))?;
@@ -824,4 +834,14 @@ mod tests {
         [style::Print("+ % @ . ?")],
         true
     );
+
+    #[test]
+    fn url_hyperlink() {
+        let mut state = ParseState::new(Some(80), Some(false));
+        state.hyperlinks = true;
+        let mut output = vec![];
+        interpret_markdown(Partial::new("[google](google.com)  "), &mut output, &mut state).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("\x1b]8;;google.com\x1b\\google\x1b]8;;\x1b\\ "));
+    }
 }
@@ -87,6 +87,7 @@ impl RecvError {
             RecvErrorKind::Client(e) => e.status_code(),
             RecvErrorKind::Json(_) => None,
             RecvErrorKind::StreamTimeout { .. } => None,
+            RecvErrorKind::Disconnected { .. } => None,
             RecvErrorKind::UnexpectedToolUseEos { .. } => None,
             RecvErrorKind::Cancelled => None,
             RecvErrorKind::ToolValidationError { .. } => None,
@@ -100,6 +101,7 @@ impl ReasonCode for RecvError {
             RecvErrorKind::Client(_) => "RecvErrorApiClient".to_string(),
             RecvErrorKind::Json(_) => "RecvErrorJson".to_string(),
             RecvErrorKind::StreamTimeout { .. } => "RecvErrorStreamTimeout".to_string(),
+            RecvErrorKind::Disconnected { .. } => "RecvErrorDisconnected".to_string(),
             RecvErrorKind::UnexpectedToolUseEos { .. } => "RecvErrorUnexpectedToolUseEos".to_string(),
             RecvErrorKind::Cancelled => "Interrupted".to_string(),
             RecvErrorKind::ToolValidationError { .. } => "RecvErrorToolValidation".to_string(),
@@ -134,6 +136,16 @@ pub enum RecvErrorKind {
         source: crate::api_client::ApiClientError,
         duration: std::time::Duration,
     },
+    /// The connection dropped after part of the response text was received.
+    ///
+    /// *Context*: laptops going to sleep and network changes break the connection mid-response.
+    /// `message` holds the text received so far, so that the model can be asked to continue it
+    /// instead of the turn failing.
+    #[error("The connection was lost during the response: {source}")]
+    Disconnected {
+        source: crate::api_client::ApiClientError,
+        message: Box<AssistantMessage>,
+    },
     /// Unexpected end of stream while receiving a tool use.
     ///
     /// *Context*: the stream can unexpectedly end with `Ok(None)` while waiting for an
@@ -624,6 +636,16 @@ impl ResponseParser {
                 error!(?err, "failed to receive the next event");
                 if duration.as_secs() >= 59 {
                     Err(self.error(RecvErrorKind::StreamTimeout { source: err, duration }))
+                } else if err.is_disconnect() && self.tool_uses.is_empty() && !self.assistant_text.trim().is_empty() {
+                    // Tool uses can't be kept without their results, so only text is continued
+                    let message = AssistantMessage::new_response(
+                        Some(self.message_id.clone()),
+                        std::mem::take(&mut self.assistant_text),
+                    );
+                    Err(self.error(RecvErrorKind::Disconnected {
+                        source: err,
+                        message: Box::new(message),
+                    }))
                 } else {
                     Err(self.error(err))
                 }
@@ -0,0 +1,175 @@
+//! Messages pinned with `/pin`, which outlive compaction and the trimming of long histories.
+//!
+//! Pins are kept with the conversation apart from its history. While a pinned message is still in
+//! the history sent to the model nothing changes, and once it was summarized or trimmed away it is
+//! sent verbatim with the context instead.
+
+use std::collections::HashSet;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::history::History;
+use super::message::UserMessageContent;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PinnedRole {
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PinnedMessage {
+    pub role: PinnedRole,
+    pub content: String,
+}
+
+impl PinnedMessage {
+    /// The first line of the message, cut to `max_chars`.
+    pub fn preview(&self, max_chars: usize) -> String {
+        let line = self.content.trim().lines().next().unwrap_or_default();
+        match line.char_indices().nth(max_chars) {
+            Some((end, _)) => format!("{}…", &line[..end]),
+            None if self.content.trim().lines().nth(1).is_some() => format!("{line}…"),
+            None => line.to_string(),
+        }
+    }
+}
+
+/// The prompts of the user in `history` with the text of the responses to each, oldest first.
+/// Responses that span several tool uses are joined.
+pub fn turns(history: &History) -> Vec<(String, String)> {
+    let mut turns: Vec<(String, String)> = Vec::new();
+    for entry in history {
+        if let UserMessageContent::Prompt { prompt } = entry.user.content() {
+            turns.push((prompt.clone(), String::new()));
+        }
+        let content = entry.assistant.content().trim();
+        if let (Some((_, response)), false) = (turns.last_mut(), content.is_empty()) {
+            if !response.is_empty() {
+                response.push('\n');
+            }
+            response.push_str(content);
+        }
+    }
+    turns
+}
+
+/// The pins that are no longer part of `sent`, the history sent to the model.
+pub fn dropped<'a>(pins: &'a [PinnedMessage], sent: &History) -> Vec<&'a PinnedMessage> {
+    let sent_turns = turns(sent);
+    let sent_content = sent_turns
+        .iter()
+        .flat_map(|(prompt, response)| [prompt.as_str(), response.as_str()])
+        .collect::<HashSet<_>>();
+    pins.iter()
+        .filter(|pin| !sent_content.contains(pin.content.as_str()))
+        .collect()
+}
+
+/// Formats pinned messages for the model.
+pub fn render(pins: &[&PinnedMessage]) -> String {
+    let mut rendered = String::from(
+        "I pinned these messages from earlier in our conversation because they matter for the rest of it, such as \
+        decisions and specifications. They are quoted verbatim, and take precedence over any summary of them.\n\n",
+    );
+    for pin in pins {
+        let author = match pin.role {
+            PinnedRole::User => "I wrote",
+            PinnedRole::Assistant => "You wrote",
+        };
+        rendered.push_str(&format!(
+            "{author}:\n<pinned_message>\n{}\n</pinned_message>\n",
+            pin.content
+        ));
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::chat::conversation::HistoryEntry;
+    use crate::cli::chat::message::{
+        AssistantMessage,
+        AssistantToolUse,
+        ToolUseResult,
+        UserMessage,
+    };
+
+    fn history() -> History {
+        [
+            HistoryEntry::new(
+                UserMessage::new_prompt("Use Postgres for storage".to_string(), None),
+                AssistantMessage::new_response(None, "Noted, Postgres it is.".to_string()),
+                None,
+            ),
+            HistoryEntry::new(
+                UserMessage::new_prompt("Add the schema".to_string(), None),
+                AssistantMessage::new_tool_use(None, "Writing schema.sql".to_string(), vec![AssistantToolUse {
+                    id: "1".to_string(),
+                    name: "fs_write".to_string(),
+                    ..Default::default()
+                }]),
+                None,
+            ),
+            HistoryEntry::new(
+                UserMessage::new_tool_use_results(vec![ToolUseResult {
+                    tool_use_id: "1".to_string(),
+                    content: vec![],
+                    status: crate::api_client::model::ToolResultStatus::Success,
+                    provenance: None,
+                }]),
+                AssistantMessage::new_response(None, "Done.".to_string()),
+                None,
+            ),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn test_turns() {
+        assert_eq!(turns(&history()), vec![
+            (
+                "Use Postgres for storage".to_string(),
+                "Noted, Postgres it is.".to_string()
+            ),
+            ("Add the schema".to_string(), "Writing schema.sql\nDone.".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_dropped() {
+        let history = history();
+        let pins = vec![
+            PinnedMessage {
+                role: PinnedRole::User,
+                content: "Use Postgres for storage".to_string(),
+            },
+            PinnedMessage {
+                role: PinnedRole::Assistant,
+                content: "Writing schema.sql\nDone.".to_string(),
+            },
+        ];
+        assert!(dropped(&pins, &history).is_empty());
+        assert_eq!(dropped(&pins, &history.slice(1..3)), vec![&pins[0]]);
+
+        let rendered = render(&dropped(&pins, &History::default()));
+        assert!(rendered.contains("I wrote:\n<pinned_message>\nUse Postgres for storage\n</pinned_message>"));
+        assert!(rendered.contains("You wrote:\n<pinned_message>\nWriting schema.sql\nDone.\n</pinned_message>"));
+    }
+
+    #[test]
+    fn test_preview() {
+        let pin = PinnedMessage {
+            role: PinnedRole::User,
+            content: "Use Postgres\nfor storage".to_string(),
+        };
+        assert_eq!(pin.preview(40), "Use Postgres…");
+        assert_eq!(pin.preview(3), "Use…");
+    }
+}
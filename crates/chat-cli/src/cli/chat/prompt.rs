@@ -6,6 +6,8 @@ use std::sync::{
     Mutex,
 };
 
+use chat_cli_ui::palette::fuzzy_filter;
+use chat_cli_ui::path_completion::WorkspacePathCompleter;
 use eyre::Result;
 use rustyline::completion::{
     Completer,
@@ -17,7 +19,10 @@ use rustyline::highlight::{
     CmdKind,
     Highlighter,
 };
-use rustyline::hint::Hinter as RustylineHinter;
+use rustyline::hint::{
+    Hint,
+    Hinter as RustylineHinter,
+};
 use rustyline::history::{
     FileHistory,
     SearchDirection,
@@ -37,15 +42,20 @@ use rustyline::{
     Editor,
     EventHandler,
     Helper,
-    Hinter,
     KeyCode,
     KeyEvent,
     Modifiers,
 };
 use winnow::stream::AsChar;
 
+use super::cli::syntax::{
+    check_slash_command,
+    complete_slash_command_args,
+};
 pub use super::prompt_parser::generate_prompt;
 use super::prompt_parser::parse_prompt_components;
+use super::shell_completion::ShellCompleter;
+use super::shell_integration::ShellIntegration;
 use super::tool_manager::{
     PromptQuery,
     PromptQueryResult,
@@ -61,6 +71,7 @@ use crate::cli::experiment::experiment_manager::{
 use crate::database::settings::Setting;
 use crate::os::Os;
 use crate::util::paths::PathResolver;
+use crate::util::terminal::TerminalQuirks;
 
 /// Shared state for clipboard paste operations triggered by Ctrl+V
 #[derive(Clone, Debug)]
@@ -102,15 +113,27 @@ pub const COMMANDS: &[&str] = &[
     "/help",
     "/editor",
     "/reply",
+    "/retry",
+    "/retry --vary",
+    "/edit",
     "/issue",
+    "/feedback up",
+    "/feedback down",
+    "/feedback issue",
     "/quit",
     "/tools",
     "/tools trust",
     "/tools untrust",
     "/tools trust-all",
     "/tools reset",
+    "/tools doctor",
+    "/tools use",
     "/mcp",
+    "/mcp auth",
     "/model",
+    "/params",
+    "/params set",
+    "/params reset",
     "/experiment",
     "/agent",
     "/agent help",
@@ -139,12 +162,32 @@ pub const COMMANDS: &[&str] = &[
     "/hooks disable-all",
     "/compact",
     "/compact help",
+    "/pin",
+    "/pin --prompt",
+    "/pins",
+    "/pins rm",
+    "/pins clear",
     "/usage",
     "/changelog",
     "/save",
     "/load",
     "/paste",
     "/subscribe",
+    "/cd",
+    "/pwd",
+    "/history",
+    "/history --all",
+    "/env",
+    "/env show",
+    "/env set",
+    "/env unset",
+    "/cache",
+    "/cache stats",
+    "/capture",
+    "/share",
+    "/export-script",
+    "/fix",
+    "/diff",
 ];
 
 /// Generate dynamic command list including experiment-based commands when enabled
@@ -158,14 +201,24 @@ pub fn get_available_commands(os: &Os) -> Vec<&'static str> {
 pub type PromptQuerySender = tokio::sync::broadcast::Sender<PromptQuery>;
 pub type PromptQueryResponseReceiver = tokio::sync::broadcast::Receiver<PromptQueryResult>;
 
-/// Complete commands that start with a slash
-fn complete_command(commands: Vec<&'static str>, word: &str, start: usize) -> (usize, Vec<String>) {
+/// Complete commands that start with a slash. Falls back to fuzzy matching, e.g. `/ctxa` for
+/// `/context add`, when no command starts with the word.
+fn complete_command<'a>(commands: impl IntoIterator<Item = &'a str>, word: &str, start: usize) -> (usize, Vec<String>) {
+    let commands = commands.into_iter().collect::<Vec<_>>();
+    let prefix_matches = commands
+        .iter()
+        .filter(|p| p.starts_with(word))
+        .map(|s| (*s).to_owned())
+        .collect::<Vec<_>>();
+    if !prefix_matches.is_empty() {
+        return (start, prefix_matches);
+    }
+
     (
         start,
-        commands
-            .iter()
-            .filter(|p| p.starts_with(word))
-            .map(|s| (*s).to_owned())
+        fuzzy_filter(commands, word, |cmd| *cmd)
+            .into_iter()
+            .map(|s| s.to_owned())
             .collect(),
     )
 }
@@ -269,8 +322,14 @@ impl PromptCompleter {
 
 pub struct ChatCompleter {
     path_completer: PathCompleter,
+    /// Completes the paths of `@` mentions relative to the session's working directory
+    mention_completer: WorkspacePathCompleter,
     prompt_completer: PromptCompleter,
+    /// Completes shell commands run with `!`
+    shell_completer: ShellCompleter,
     available_commands: Vec<&'static str>,
+    /// User defined slash commands of the active agent
+    custom_commands: Vec<String>,
 }
 
 impl ChatCompleter {
@@ -281,8 +340,11 @@ impl ChatCompleter {
     ) -> Self {
         Self {
             path_completer: PathCompleter::new(),
+            mention_completer: WorkspacePathCompleter::new(std::env::current_dir().unwrap_or_default()),
             prompt_completer: PromptCompleter::new(sender, receiver),
+            shell_completer: ShellCompleter::default(),
             available_commands,
+            custom_commands: Vec::new(),
         }
     }
 }
@@ -294,13 +356,39 @@ impl Completer for ChatCompleter {
         &self,
         line: &str,
         pos: usize,
-        _ctx: &Context<'_>,
+        ctx: &Context<'_>,
     ) -> Result<(usize, Vec<Self::Candidate>), ReadlineError> {
         let (start, word) = extract_word(line, pos, None, |c| c.is_space());
 
+        // Handle shell command completion
+        if let Some(command) = line.strip_prefix('!') {
+            let history = ctx.history();
+            let recent = (0..history.len())
+                .filter_map(|i| history.get(i, SearchDirection::Forward).ok().flatten())
+                .filter_map(|result| result.entry.strip_prefix('!').map(str::to_string))
+                .collect::<Vec<_>>();
+            let (start, completions) =
+                self.shell_completer
+                    .complete(command, pos.saturating_sub(1), recent.iter().map(String::as_str));
+            return Ok((start + 1, completions));
+        }
+
         // Handle command completion
         if word.starts_with('/') {
-            return Ok(complete_command(self.available_commands.clone(), word, start));
+            let commands = self
+                .available_commands
+                .iter()
+                .copied()
+                .chain(self.custom_commands.iter().map(String::as_str));
+            return Ok(complete_command(commands, word, start));
+        }
+
+        // Handle subcommand and flag completion of slash commands
+        if line.starts_with('/') {
+            let completions = complete_slash_command_args(&line[..start], word);
+            if !completions.is_empty() {
+                return Ok((start, completions));
+            }
         }
 
         if line.starts_with('@') {
@@ -312,8 +400,21 @@ impl Completer for ChatCompleter {
             }
         }
 
+        // Handle path completion of @ mentions
+        if let Some(partial) = word.strip_prefix('@') {
+            let completions = self
+                .mention_completer
+                .complete_blocking(partial)
+                .into_iter()
+                .map(|path| format!("@{path}"))
+                .collect::<Vec<_>>();
+            if !completions.is_empty() {
+                return Ok((start, completions));
+            }
+        }
+
         // Handle file path completion as fallback
-        if let Ok((pos, completions)) = self.path_completer.complete_path(line, pos, _ctx) {
+        if let Ok((pos, completions)) = self.path_completer.complete_path(line, pos, ctx) {
             if !completions.is_empty() {
                 return Ok((pos, completions));
             }
@@ -330,6 +431,8 @@ pub struct ChatHinter {
     history_hints_enabled: bool,
     history_path: PathBuf,
     available_commands: Vec<&'static str>,
+    /// User defined slash commands of the active agent
+    custom_commands: Vec<String>,
 }
 
 impl ChatHinter {
@@ -339,6 +442,7 @@ impl ChatHinter {
             history_hints_enabled,
             history_path,
             available_commands,
+            custom_commands: Vec::new(),
         }
     }
 
@@ -358,6 +462,8 @@ impl ChatHinter {
             return self
                 .available_commands
                 .iter()
+                .copied()
+                .chain(self.custom_commands.iter().map(String::as_str))
                 .find(|cmd| cmd.starts_with(line))
                 .map(|cmd| cmd[line.len()..].to_string());
         }
@@ -422,19 +528,89 @@ impl Validator for MultiLineValidator {
     }
 }
 
-#[derive(Helper, Completer, Hinter)]
+/// Prefix of hints that describe a problem with the current line rather than a completion
+const DIAGNOSTIC_HINT_PREFIX: &str = "  ← ";
+
+/// A hint shown after the cursor. Diagnostics for malformed slash commands are displayed like
+/// completion hints but cannot be accepted into the line.
+pub struct ChatHint {
+    display: String,
+    is_completion: bool,
+}
+
+impl Hint for ChatHint {
+    fn display(&self) -> &str {
+        &self.display
+    }
+
+    fn completion(&self) -> Option<&str> {
+        self.is_completion.then_some(self.display.as_str())
+    }
+}
+
+#[derive(Helper, Completer)]
 pub struct ChatHelper {
     #[rustyline(Completer)]
     completer: ChatCompleter,
-    #[rustyline(Hinter)]
     hinter: ChatHinter,
     validator: MultiLineValidator,
+    /// Set when the terminal understands VS Code's shell integration sequences
+    shell_integration: Option<ShellIntegration>,
 }
 
 impl ChatHelper {
     pub fn get_history_path(&self) -> PathBuf {
         self.hinter.get_history_path()
     }
+
+    /// Updates the user defined slash commands offered for completion and hints
+    pub fn set_custom_commands(&mut self, commands: Vec<String>) {
+        self.completer.custom_commands = commands.clone();
+        self.hinter.custom_commands = commands;
+    }
+
+    /// Updates the working directory that `@` mentions are completed relative to
+    pub fn set_cwd(&mut self, cwd: PathBuf) {
+        if self.completer.mention_completer.cwd() != cwd {
+            self.completer.shell_completer.set_cwd(cwd.clone());
+            self.completer.mention_completer = WorkspacePathCompleter::new(cwd);
+        }
+    }
+
+    /// Sequences to print once `line` is submitted, marking the start of its output
+    pub fn command_started(&self, line: &str) -> Option<String> {
+        self.shell_integration
+            .as_ref()
+            .map(|shell_integration| shell_integration.command_started(line))
+    }
+
+    fn is_custom_command(&self, line: &str) -> bool {
+        line.split_whitespace()
+            .next()
+            .is_some_and(|name| self.hinter.custom_commands.iter().any(|cmd| cmd == name))
+    }
+}
+
+impl RustylineHinter for ChatHelper {
+    type Hint = ChatHint;
+
+    fn hint(&self, line: &str, pos: usize, ctx: &Context<'_>) -> Option<Self::Hint> {
+        if let Some(hint) = self.hinter.hint(line, pos, ctx).filter(|hint| !hint.is_empty()) {
+            return Some(ChatHint {
+                display: hint,
+                is_completion: true,
+            });
+        }
+
+        // Flag malformed slash commands while the user is still typing
+        if pos < line.len() || line.contains('\n') || self.is_custom_command(line) {
+            return None;
+        }
+        check_slash_command(line).map(|message| ChatHint {
+            display: format!("{DIAGNOSTIC_HINT_PREFIX}{message}"),
+            is_completion: false,
+        })
+    }
 }
 
 impl Validator for ChatHelper {
@@ -445,6 +621,9 @@ impl Validator for ChatHelper {
 
 impl Highlighter for ChatHelper {
     fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        if hint.starts_with(DIAGNOSTIC_HINT_PREFIX) {
+            return Cow::Owned(crate::theme::StyledText::error(hint));
+        }
         Cow::Owned(format!("\x1b[38;5;240m{hint}\x1b[m"))
     }
 
@@ -456,7 +635,19 @@ impl Highlighter for ChatHelper {
         false
     }
 
-    fn highlight_prompt<'b, 's: 'b, 'p: 'b>(&'s self, prompt: &'p str, _default: bool) -> Cow<'b, str> {
+    fn highlight_prompt<'b, 's: 'b, 'p: 'b>(&'s self, prompt: &'p str, default: bool) -> Cow<'b, str> {
+        let highlighted = self.style_prompt(prompt);
+        match &self.shell_integration {
+            Some(shell_integration) if default => {
+                Cow::Owned(shell_integration.prompt(self.completer.mention_completer.cwd(), &highlighted))
+            },
+            _ => highlighted,
+        }
+    }
+}
+
+impl ChatHelper {
+    fn style_prompt<'p>(&self, prompt: &'p str) -> Cow<'p, str> {
         use crate::theme::StyledText;
 
         // Parse the plain text prompt to extract components
@@ -577,10 +768,17 @@ pub fn rl(
     // Generate available commands based on enabled experiments
     let available_commands = get_available_commands(os);
 
+    let mut completer = ChatCompleter::new(sender, receiver, available_commands.clone());
+    if let Ok(cwd) = os.env.current_dir() {
+        completer.mention_completer = WorkspacePathCompleter::new(cwd);
+    }
+    completer.shell_completer = ShellCompleter::new(os);
+
     let h = ChatHelper {
-        completer: ChatCompleter::new(sender, receiver, available_commands.clone()),
+        completer,
         hinter: ChatHinter::new(history_hints_enabled, history_path, available_commands),
         validator: MultiLineValidator,
+        shell_integration: ShellIntegration::new(&os.env, TerminalQuirks::current()),
     };
 
     let mut rl = Editor::with_config(config)?;
@@ -681,6 +879,83 @@ mod tests {
         assert!(completions.contains(&"/help".to_string()));
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_chat_completer_mention_path_completion() {
+        let (prompt_request_sender, _) = tokio::sync::broadcast::channel::<PromptQuery>(5);
+        let (_, prompt_response_receiver) = tokio::sync::broadcast::channel::<PromptQueryResult>(5);
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("alpha.rs"), "").unwrap();
+        std::fs::write(dir.path().join(".alpine"), "").unwrap();
+        std::fs::create_dir(dir.path().join("alps")).unwrap();
+        std::fs::write(dir.path().join("alps").join("everest.md"), "").unwrap();
+
+        let mock_os = crate::os::Os::new().await.unwrap();
+        let mut completer = ChatCompleter::new(
+            prompt_request_sender,
+            prompt_response_receiver,
+            get_available_commands(&mock_os),
+        );
+        completer.mention_completer = WorkspacePathCompleter::new(dir.path());
+
+        let empty_history = DefaultHistory::new();
+        let ctx = Context::new(&empty_history);
+
+        // Hidden files are left out and directories can be completed into
+        let line = "explain @al";
+        let (start, completions) = completer.complete(line, line.len(), &ctx).unwrap();
+        assert_eq!(start, 8);
+        assert_eq!(completions, vec!["@alpha.rs".to_string(), "@alps/".to_string()]);
+
+        let line = "explain @alps/";
+        let (_, completions) = completer.complete(line, line.len(), &ctx).unwrap();
+        assert_eq!(completions, vec!["@alps/everest.md".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_chat_completer_shell_command_completion() {
+        use rustyline::history::History;
+
+        let (prompt_request_sender, _) = tokio::sync::broadcast::channel::<PromptQuery>(5);
+        let (_, prompt_response_receiver) = tokio::sync::broadcast::channel::<PromptQueryResult>(5);
+        let mock_os = crate::os::Os::new().await.unwrap();
+        let completer = ChatCompleter::new(
+            prompt_request_sender,
+            prompt_response_receiver,
+            get_available_commands(&mock_os),
+        );
+
+        let mut history = DefaultHistory::new();
+        history.add("!cargo test --workspace").unwrap();
+        history.add("explain cargo features").unwrap();
+        let ctx = Context::new(&history);
+
+        let line = "!cargo t";
+        assert_eq!(
+            completer.complete(line, line.len(), &ctx).unwrap(),
+            (7, vec!["test".to_string()])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_chat_completer_fuzzy_command_completion() {
+        let (prompt_request_sender, _) = tokio::sync::broadcast::channel::<PromptQuery>(5);
+        let (_, prompt_response_receiver) = tokio::sync::broadcast::channel::<PromptQueryResult>(5);
+
+        let mock_os = crate::os::Os::new().await.unwrap();
+        let available_commands = get_available_commands(&mock_os);
+        let completer = ChatCompleter::new(prompt_request_sender, prompt_response_receiver, available_commands);
+        let line = "/ctxadd";
+
+        let empty_history = DefaultHistory::new();
+        let ctx = Context::new(&empty_history);
+
+        // No command starts with the word, so the best fuzzy match comes first
+        let (start, completions) = completer.complete(line, line.len(), &ctx).unwrap();
+        assert_eq!(start, 0);
+        assert_eq!(completions.first(), Some(&"/context add".to_string()));
+    }
+
     #[tokio::test]
     async fn test_chat_completer_no_completion() {
         let (prompt_request_sender, _) = tokio::sync::broadcast::channel::<PromptQuery>(5);
@@ -720,6 +995,7 @@ mod tests {
             ),
             hinter: ChatHinter::new(true, PathBuf::new(), available_commands),
             validator: MultiLineValidator,
+            shell_integration: None,
         };
 
         // Test basic prompt highlighting
@@ -744,6 +1020,7 @@ mod tests {
             ),
             hinter: ChatHinter::new(true, PathBuf::new(), available_commands),
             validator: MultiLineValidator,
+            shell_integration: None,
         };
 
         // Test warning prompt highlighting
@@ -771,6 +1048,7 @@ mod tests {
             ),
             hinter: ChatHinter::new(true, PathBuf::new(), available_commands),
             validator: MultiLineValidator,
+            shell_integration: None,
         };
 
         // Test profile prompt highlighting
@@ -798,6 +1076,7 @@ mod tests {
             ),
             hinter: ChatHinter::new(true, PathBuf::new(), available_commands),
             validator: MultiLineValidator,
+            shell_integration: None,
         };
 
         // Test profile + warning prompt highlighting
@@ -830,6 +1109,7 @@ mod tests {
             ),
             hinter: ChatHinter::new(true, PathBuf::new(), available_commands),
             validator: MultiLineValidator,
+            shell_integration: None,
         };
 
         // Test invalid prompt format (should return as-is)
@@ -854,6 +1134,7 @@ mod tests {
             ),
             hinter: ChatHinter::new(true, PathBuf::new(), available_commands),
             validator: MultiLineValidator,
+            shell_integration: None,
         };
 
         // Test tangent mode prompt highlighting - ↯ yellow, > magenta
@@ -880,6 +1161,7 @@ mod tests {
             ),
             hinter: ChatHinter::new(true, PathBuf::new(), available_commands),
             validator: MultiLineValidator,
+            shell_integration: None,
         };
 
         // Test tangent mode with warning - ↯ yellow, ! red, > magenta
@@ -911,6 +1193,7 @@ mod tests {
             ),
             hinter: ChatHinter::new(true, PathBuf::new(), available_commands),
             validator: MultiLineValidator,
+            shell_integration: None,
         };
 
         // Test profile with tangent mode - [dev] cyan, ↯ yellow, > magenta
@@ -959,6 +1242,55 @@ mod tests {
         assert_eq!(hint, None);
     }
 
+    #[tokio::test]
+    async fn test_chat_hinter_custom_command_hint() {
+        let mock_os = crate::os::Os::new().await.unwrap();
+        let available_commands = get_available_commands(&mock_os);
+        let mut hinter = ChatHinter::new(true, PathBuf::new(), available_commands);
+        hinter.custom_commands = vec!["/ticket".to_string()];
+
+        let line = "/tic";
+        let empty_history = DefaultHistory::new();
+        let ctx = Context::new(&empty_history);
+        assert_eq!(hinter.hint(line, line.len(), &ctx), Some("ket".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_chat_helper_slash_command_diagnostic_hint() {
+        let (prompt_request_sender, _) = tokio::sync::broadcast::channel::<PromptQuery>(5);
+        let (_, prompt_response_receiver) = tokio::sync::broadcast::channel::<PromptQueryResult>(5);
+
+        let mock_os = crate::os::Os::new().await.unwrap();
+        let available_commands = get_available_commands(&mock_os);
+        let mut helper = ChatHelper {
+            completer: ChatCompleter::new(
+                prompt_request_sender,
+                prompt_response_receiver,
+                available_commands.clone(),
+            ),
+            hinter: ChatHinter::new(true, PathBuf::new(), available_commands),
+            validator: MultiLineValidator,
+            shell_integration: None,
+        };
+        let empty_history = DefaultHistory::new();
+        let ctx = Context::new(&empty_history);
+
+        // Completion hints can still be accepted
+        let hint = helper.hint("/he", 3, &ctx).unwrap();
+        assert_eq!(hint.completion(), Some("lp"));
+
+        // Diagnostics are displayed but can't be accepted into the line
+        let line = "/contxt";
+        let hint = helper.hint(line, line.len(), &ctx).unwrap();
+        assert!(hint.display().contains("unknown command 'contxt'"));
+        assert_eq!(hint.completion(), None);
+
+        // User defined commands are not flagged
+        helper.set_custom_commands(vec!["/ticket".to_string()]);
+        let line = "/ticket ABC-123";
+        assert!(helper.hint(line, line.len(), &ctx).is_none());
+    }
+
     #[tokio::test]
     async fn test_chat_hinter_history_hint_disabled() {
         // Create a mock Os for testing
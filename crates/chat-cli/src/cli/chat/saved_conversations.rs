@@ -0,0 +1,207 @@
+//! Lists the saved conversations for `q chat --list` and `/history`, and picks one to resume.
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::PathBuf;
+
+use chrono::{
+    DateTime,
+    FixedOffset,
+};
+use dialoguer::FuzzySelect;
+use eyre::Result;
+
+use super::conversation::ConversationState;
+use super::title;
+use super::token_counter::{
+    CharCount,
+    CharCounter,
+    TokenCount,
+};
+use super::workspace::Workspace;
+use crate::os::Os;
+use crate::util::dialoguer_theme;
+use crate::util::time_format::TimeFormatter;
+
+/// Width of the title column
+const TITLE_WIDTH: usize = 40;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedConversation {
+    pub conversation_id: String,
+    /// Directory the conversation was saved for, where `q chat --resume` picks it up
+    pub path: PathBuf,
+    pub workspace: Workspace,
+    /// The generated title, or the first prompt when there is none
+    pub title: String,
+    /// Time of the last prompt
+    pub updated_at: Option<DateTime<FixedOffset>>,
+    /// Estimated size of the history
+    pub tokens: TokenCount,
+}
+
+impl SavedConversation {
+    /// Returns [None] for conversations without any messages, which can't be resumed.
+    pub async fn new(path: String, state: &ConversationState) -> Option<Self> {
+        let history = state.history();
+        if history.is_empty() {
+            return None;
+        }
+        let title = match state.title() {
+            Some(title) => title.to_string(),
+            None => history
+                .iter()
+                .find_map(|entry| entry.user.prompt())
+                .map(|prompt| title::shorten(prompt.lines().next().unwrap_or_default().trim(), title::MAX_TITLE_CHARS))
+                .unwrap_or_else(|| "(untitled)".to_string()),
+        };
+        let chars = history.iter().fold(CharCount::from(0), |acc, entry| {
+            acc + entry.user.char_count() + entry.assistant.char_count()
+        });
+        let path = PathBuf::from(path);
+        let workspace = match state.workspace() {
+            Some(workspace) => workspace.clone(),
+            None => Workspace::detect(&path).await,
+        };
+        Some(Self {
+            conversation_id: state.conversation_id().to_string(),
+            workspace,
+            path,
+            title,
+            updated_at: history.iter().rev().find_map(|entry| entry.user.timestamp),
+            tokens: TokenCount::from(chars),
+        })
+    }
+
+    fn row(&self, formatter: &TimeFormatter, home: Option<&str>) -> String {
+        let path = self.path.to_string_lossy();
+        let path = match home {
+            Some(home) if path.starts_with(home) => path.replacen(home, "~", 1),
+            _ => path.to_string(),
+        };
+        format!(
+            "{:<TITLE_WIDTH$}  {:>16}  {:>14}  {path}",
+            title::shorten(&self.title, TITLE_WIDTH),
+            self.updated_at
+                .map_or_else(|| "unknown".to_string(), |at| formatter.format(&at)),
+            format!("{} tokens", format_tokens(self.tokens.value())),
+        )
+    }
+}
+
+/// Returns the saved conversations in `workspace`, or all of them for [None], most recently used
+/// first.
+pub async fn load(os: &Os, workspace: Option<&Workspace>) -> Result<Vec<SavedConversation>> {
+    let mut conversations = Vec::new();
+    for (path, state) in os.database.get_all_conversations()? {
+        if let Some(conversation) = SavedConversation::new(path, &state).await {
+            if workspace.is_none_or(|workspace| conversation.workspace.id == workspace.id) {
+                conversations.push(conversation);
+            }
+        }
+    }
+    conversations.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    // A conversation resumed from another directory of the workspace is saved again for that
+    // directory, only the latest copy is listed
+    let mut seen = HashSet::new();
+    conversations.retain(|conversation| seen.insert(conversation.conversation_id.clone()));
+    Ok(conversations)
+}
+
+/// Returns the directory of the most recently used conversation in `workspace`.
+pub async fn latest_in_workspace(os: &Os, workspace: &Workspace) -> Option<PathBuf> {
+    load(os, Some(workspace))
+        .await
+        .ok()?
+        .into_iter()
+        .next()
+        .map(|conversation| conversation.path)
+}
+
+/// Prints the saved conversations of the current workspace, or all of them with `all`, and when
+/// `interactive` lets the user pick one with a fuzzy finder. Returns the directory of the picked
+/// conversation.
+pub async fn select(os: &Os, interactive: bool, all: bool) -> Result<Option<PathBuf>> {
+    let workspace = Workspace::detect(&os.env.current_dir()?).await;
+    let conversations = load(os, (!all).then_some(&workspace)).await?;
+    if conversations.is_empty() {
+        match all {
+            true => eprintln!("No saved conversations"),
+            false => eprintln!(
+                "No saved conversations in {}, pass --all to list the conversations of every workspace",
+                workspace.root.display()
+            ),
+        }
+        return Ok(None);
+    }
+
+    let rows = rows(os, &conversations);
+    if !interactive {
+        print_rows(&mut std::io::stdout(), &rows)?;
+        return Ok(None);
+    }
+
+    let selection = FuzzySelect::with_theme(&dialoguer_theme())
+        .with_prompt("Select a conversation to resume")
+        .items(&rows)
+        .default(0)
+        .interact_opt();
+    match selection {
+        Ok(index) => Ok(index.map(|index| conversations[index].path.clone())),
+        // Ctrl‑C -> Err(Interrupted)
+        Err(dialoguer::Error::IO(ref e)) if e.kind() == std::io::ErrorKind::Interrupted => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Returns the rows printed for `conversations`.
+pub fn rows(os: &Os, conversations: &[SavedConversation]) -> Vec<String> {
+    let formatter = TimeFormatter::from_settings(&os.database.settings);
+    let home = os.env.home().map(|home| home.to_string_lossy().to_string());
+    conversations
+        .iter()
+        .map(|conversation| conversation.row(&formatter, home.as_deref()))
+        .collect()
+}
+
+pub fn print_rows(output: &mut impl Write, rows: &[String]) -> Result<()> {
+    for row in rows {
+        writeln!(output, "{row}")?;
+    }
+    Ok(())
+}
+
+fn format_tokens(tokens: usize) -> String {
+    match tokens {
+        0..1000 => tokens.to_string(),
+        _ => format!("{:.1}k", tokens as f64 / 1000.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::time_format::Zone;
+
+    #[test]
+    fn test_format_tokens() {
+        assert_eq!(format_tokens(950), "950");
+        assert_eq!(format_tokens(12_340), "12.3k");
+    }
+
+    #[test]
+    fn test_row() {
+        let conversation = SavedConversation {
+            conversation_id: "id".to_string(),
+            path: PathBuf::from("/home/user/project"),
+            workspace: Workspace::new(PathBuf::from("/home/user/project"), None),
+            title: "Fix flaky login test".to_string(),
+            updated_at: None,
+            tokens: TokenCount::from(CharCount::from(4000)),
+        };
+        let row = conversation.row(&TimeFormatter::new(Zone::Local, true), Some("/home/user"));
+        assert!(row.starts_with("Fix flaky login test"));
+        assert!(row.ends_with("~/project"));
+        assert!(row.contains("unknown"));
+    }
+}
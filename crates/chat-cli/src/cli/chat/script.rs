@@ -0,0 +1,447 @@
+//! Scripted conversations, run with `q script`.
+//!
+//! A [Script] lists prompts that are sent one after the other in a non-interactive session, along
+//! with what to expect from each turn, written like the expectations of `q eval` scenarios. Tool
+//! uses that the script doesn't approve are denied rather than ending the session, so that scripts
+//! can also check how the model copes with a denial.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process::ExitCode;
+
+use agent::eval::{
+    ExpectedFile,
+    ExpectedToolCall,
+    json_contains,
+};
+use clap::Args;
+use crossterm::style::Stylize;
+use eyre::{
+    Result,
+    WrapErr,
+    bail,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::{
+    ChatArgs,
+    ChatSession,
+};
+use crate::os::Os;
+
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct ScriptArgs {
+    /// YAML file listing the prompts and what to expect from each turn
+    pub script: PathBuf,
+    /// Write the JSON report to this file instead of printing it after the conversation
+    #[arg(long)]
+    pub report: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Script {
+    /// Agent to run the conversation with, the default agent if omitted
+    #[serde(default)]
+    pub agent: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Tools that run without approval, written like `--trust-tools`. Other tool uses are denied.
+    #[serde(default)]
+    pub approve: Vec<String>,
+    /// Approves every tool use
+    #[serde(default)]
+    pub trust_all_tools: bool,
+    pub turns: Vec<ScriptTurn>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ScriptTurn {
+    pub prompt: String,
+    #[serde(default)]
+    pub expect: TurnExpectations,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TurnExpectations {
+    /// Tools that must be called, in this order. Other calls may happen in between.
+    #[serde(default)]
+    pub tool_calls: Vec<ExpectedToolCall>,
+    /// Names of tools that must not be called
+    #[serde(default)]
+    pub no_tool_calls: Vec<String>,
+    /// Names of tools that must be called and denied, because the script doesn't approve them
+    #[serde(default)]
+    pub denied_tool_calls: Vec<String>,
+    /// Text the responses of the model must contain
+    #[serde(default)]
+    pub response_contains: Vec<String>,
+    /// Text the responses of the model must not contain
+    #[serde(default)]
+    pub response_not_contains: Vec<String>,
+    /// Files after the turn, keyed by path relative to the current directory
+    #[serde(default)]
+    pub files: BTreeMap<String, ExpectedFile>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScriptReport {
+    script: PathBuf,
+    passed: bool,
+    /// Why the conversation ended before the last turn, if it did
+    error: Option<String>,
+    turns: Vec<TurnReport>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TurnReport {
+    prompt: String,
+    passed: bool,
+    /// Why the turn failed, empty when it passed
+    failures: Vec<String>,
+    response: String,
+    tool_calls: Vec<String>,
+    denied_tool_calls: Vec<String>,
+}
+
+/// The progress of a session through a script, see [ChatSession::script].
+#[derive(Debug)]
+pub struct ScriptRun {
+    turns: Vec<ScriptTurn>,
+    /// Number of turns whose prompt was sent
+    started: usize,
+    /// Tools denied during each started turn
+    denied: Vec<Vec<String>>,
+    /// Unmet file expectations of each ended turn, checked as soon as the turn ends since later
+    /// turns may change the files
+    file_failures: Vec<Vec<String>>,
+}
+
+impl ScriptRun {
+    fn new(turns: Vec<ScriptTurn>) -> Self {
+        Self {
+            turns,
+            started: 0,
+            denied: Vec::new(),
+            file_failures: Vec::new(),
+        }
+    }
+
+    /// Ends the current turn, if any, and returns the prompt of the next one.
+    pub async fn next_prompt(&mut self, os: &Os) -> Option<String> {
+        self.end_turn(os).await;
+        let prompt = self.turns.get(self.started)?.prompt.clone();
+        self.started += 1;
+        self.denied.push(Vec::new());
+        Some(prompt)
+    }
+
+    pub fn record_denial(&mut self, tool_name: &str) {
+        if let Some(denied) = self.denied.last_mut() {
+            denied.push(tool_name.to_string());
+        }
+    }
+
+    async fn end_turn(&mut self, os: &Os) {
+        if self.file_failures.len() < self.started {
+            let expected = &self.turns[self.file_failures.len()].expect.files;
+            self.file_failures.push(check_files(os, expected).await);
+        }
+    }
+}
+
+impl ScriptArgs {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        let script = load(os, &self.script).await?;
+        let mut stderr = std::io::stderr();
+
+        let mut session = ChatArgs {
+            agent: script.agent.clone(),
+            model: script.model.clone(),
+            trust_all_tools: script.trust_all_tools,
+            trust_tools: Some(script.approve.clone()),
+            no_interactive: true,
+            ..Default::default()
+        }
+        .into_session(os, None)
+        .await?;
+        session.script = Some(ScriptRun::new(script.turns));
+
+        let error = session.spawn(os).await.err().map(|err| err.to_string());
+        let mut run = session.script.take().expect("the script is set until the session ends");
+        run.end_turn(os).await;
+
+        let turns = turn_reports(&session, &run);
+        let report = ScriptReport {
+            script: self.script,
+            passed: error.is_none() && turns.iter().all(|turn| turn.passed),
+            error,
+            turns,
+        };
+
+        writeln!(stderr)?;
+        for (i, turn) in report.turns.iter().enumerate() {
+            match turn.passed {
+                true => writeln!(stderr, "{} turn {}", "PASS".green(), i + 1)?,
+                false => {
+                    writeln!(stderr, "{} turn {}", "FAIL".red(), i + 1)?;
+                    for failure in &turn.failures {
+                        writeln!(stderr, "    {failure}")?;
+                    }
+                },
+            }
+        }
+        if let Some(error) = &report.error {
+            writeln!(stderr, "{} {error}", "ERROR".red())?;
+        }
+
+        let json = serde_json::to_string_pretty(&report)?;
+        match &self.report {
+            Some(path) => os
+                .fs
+                .write(path, json)
+                .await
+                .wrap_err_with(|| format!("failed to write {}", path.display()))?,
+            None => println!("{json}"),
+        }
+
+        Ok(if report.passed {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        })
+    }
+}
+
+async fn load(os: &Os, path: &Path) -> Result<Script> {
+    let content = os
+        .fs
+        .read_to_string(path)
+        .await
+        .wrap_err_with(|| format!("failed to read {}", path.display()))?;
+    let script: Script =
+        serde_yaml_ng::from_str(&content).wrap_err_with(|| format!("failed to parse {}", path.display()))?;
+    if script.turns.is_empty() {
+        bail!("{} has no turns", path.display());
+    }
+    Ok(script)
+}
+
+/// What the model did during a turn, read from the conversation history.
+#[derive(Debug, Default)]
+struct TurnOutcome {
+    response: Vec<String>,
+    tool_calls: Vec<(String, serde_json::Value)>,
+}
+
+/// Splits the history of the session into the turns of the script: each turn starts at the entry
+/// with its prompt, and covers the tool uses and denials that follow.
+fn turn_outcomes(session: &ChatSession, prompts: &[&str]) -> Vec<TurnOutcome> {
+    let mut outcomes: Vec<TurnOutcome> = Vec::new();
+    for entry in session.conversation.history().iter() {
+        if prompts
+            .get(outcomes.len())
+            .is_some_and(|prompt| entry.user.prompt() == Some(*prompt))
+        {
+            outcomes.push(TurnOutcome::default());
+        }
+        let Some(outcome) = outcomes.last_mut() else {
+            continue;
+        };
+        if !entry.assistant.content().trim().is_empty() {
+            outcome.response.push(entry.assistant.content().to_string());
+        }
+        for tool_use in entry.assistant.tool_uses().unwrap_or_default() {
+            outcome
+                .tool_calls
+                .push((tool_use.orig_name.clone(), tool_use.orig_args.clone()));
+        }
+    }
+    outcomes
+}
+
+fn turn_reports(session: &ChatSession, run: &ScriptRun) -> Vec<TurnReport> {
+    let prompts = run.turns.iter().map(|turn| turn.prompt.as_str()).collect::<Vec<_>>();
+    let mut outcomes = turn_outcomes(session, &prompts).into_iter();
+
+    let mut reports = Vec::new();
+    for (i, turn) in run.turns.iter().enumerate() {
+        let mut report = TurnReport {
+            prompt: turn.prompt.clone(),
+            ..Default::default()
+        };
+        match (i < run.started, outcomes.next()) {
+            (true, Some(outcome)) => {
+                let denied = run.denied.get(i).cloned().unwrap_or_default();
+                report.failures = check(&turn.expect, &outcome, &denied);
+                report
+                    .failures
+                    .extend(run.file_failures.get(i).cloned().unwrap_or_default());
+                report.response = outcome.response.join("\n");
+                report.tool_calls = outcome.tool_calls.into_iter().map(|(name, _)| name).collect();
+                report.denied_tool_calls = denied;
+            },
+            (true, None) => report.failures.push("the model did not answer".to_string()),
+            (false, _) => report.failures.push("the turn did not run".to_string()),
+        }
+        report.passed = report.failures.is_empty();
+        reports.push(report);
+    }
+    reports
+}
+
+/// Checks the outcome of a turn against its expectations, except for the files, and returns the
+/// unmet ones.
+fn check(expect: &TurnExpectations, outcome: &TurnOutcome, denied: &[String]) -> Vec<String> {
+    let mut failures = Vec::new();
+
+    let mut remaining = outcome.tool_calls.iter();
+    for expected in &expect.tool_calls {
+        let found = remaining.any(|(name, input)| {
+            name == &expected.name
+                && expected
+                    .input
+                    .as_ref()
+                    .is_none_or(|expected| json_contains(input, expected))
+        });
+        if !found {
+            failures.push(match &expected.input {
+                Some(input) => format!("expected a call to {} with input {input}", expected.name),
+                None => format!("expected a call to {}", expected.name),
+            });
+            break;
+        }
+    }
+    for name in &expect.no_tool_calls {
+        if outcome.tool_calls.iter().any(|(called, _)| called == name) {
+            failures.push(format!("expected no calls to {name}"));
+        }
+    }
+    for name in &expect.denied_tool_calls {
+        if !denied.contains(name) {
+            failures.push(format!("expected a call to {name} to be denied"));
+        }
+    }
+
+    let response = outcome.response.join("\n");
+    for text in &expect.response_contains {
+        if !response.contains(text.as_str()) {
+            failures.push(format!("expected the response to contain {text:?}"));
+        }
+    }
+    for text in &expect.response_not_contains {
+        if response.contains(text.as_str()) {
+            failures.push(format!("expected the response not to contain {text:?}"));
+        }
+    }
+    failures
+}
+
+async fn check_files(os: &Os, files: &BTreeMap<String, ExpectedFile>) -> Vec<String> {
+    let mut failures = Vec::new();
+    let cwd = os.env.current_dir().unwrap_or_default();
+    for (path, expected) in files {
+        match os.fs.read_to_string(cwd.join(path)).await {
+            Ok(_) if !expected.exists => failures.push(format!("expected {path} not to exist")),
+            Ok(content) => {
+                for text in &expected.contains {
+                    if !content.contains(text.as_str()) {
+                        failures.push(format!("expected {path} to contain {text:?}"));
+                    }
+                }
+            },
+            Err(_) if expected.exists => failures.push(format!("expected {path} to exist")),
+            Err(_) => (),
+        }
+    }
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = r#"
+agent: reviewer
+approve: [fs_read]
+turns:
+  - prompt: Summarize README.md
+    expect:
+      toolCalls:
+        - name: fs_read
+          input: { path: README.md }
+      responseContains: [CLI]
+  - prompt: Now delete it
+    expect:
+      deniedToolCalls: [execute_bash]
+      files:
+        README.md: {}
+"#;
+
+    #[test]
+    fn test_parse_script() {
+        let script: Script = serde_yaml_ng::from_str(SCRIPT).unwrap();
+        assert_eq!(script.agent.as_deref(), Some("reviewer"));
+        assert_eq!(script.approve, vec!["fs_read"]);
+        assert!(!script.trust_all_tools);
+        assert_eq!(script.turns.len(), 2);
+        assert_eq!(script.turns[0].expect.tool_calls[0].name, "fs_read");
+        assert!(script.turns[1].expect.files["README.md"].exists);
+
+        assert!(serde_yaml_ng::from_str::<Script>("turns: []\nprompts: []").is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        let script: Script = serde_yaml_ng::from_str(SCRIPT).unwrap();
+        let outcome = TurnOutcome {
+            response: vec!["The README describes the CLI.".to_string()],
+            tool_calls: vec![(
+                "fs_read".to_string(),
+                serde_json::json!({ "path": "README.md", "mode": "Line" }),
+            )],
+        };
+        assert!(check(&script.turns[0].expect, &outcome, &[]).is_empty());
+
+        let outcome = TurnOutcome {
+            response: vec!["I can't delete it.".to_string()],
+            tool_calls: vec![(
+                "execute_bash".to_string(),
+                serde_json::json!({ "command": "rm README.md" }),
+            )],
+        };
+        assert_eq!(check(&script.turns[0].expect, &outcome, &[]), vec![
+            "expected a call to fs_read with input {\"path\":\"README.md\"}",
+            "expected the response to contain \"CLI\"",
+        ]);
+        assert!(check(&script.turns[1].expect, &outcome, &["execute_bash".to_string()]).is_empty());
+        assert_eq!(check(&script.turns[1].expect, &outcome, &[]), vec![
+            "expected a call to execute_bash to be denied"
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_check_files() {
+        let os = Os::new().await.unwrap();
+        let cwd = os.env.current_dir().unwrap();
+        os.fs.create_dir_all(&cwd).await.unwrap();
+        os.fs.write(cwd.join("notes.md"), "# Notes").await.unwrap();
+
+        let files: BTreeMap<String, ExpectedFile> =
+            serde_yaml_ng::from_str("notes.md: { contains: [Notes, Todo] }\nmissing.md: { exists: false }").unwrap();
+        assert_eq!(check_files(&os, &files).await, vec![
+            "expected notes.md to contain \"Todo\""
+        ]);
+    }
+}
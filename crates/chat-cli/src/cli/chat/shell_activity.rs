@@ -0,0 +1,491 @@
+//! Shares the user's recent shell activity with the model, so questions like "why did that fail?"
+//! work without pasting the command.
+//!
+//! Commands are read from the activity log written by the shell hooks described in
+//! `docs/shell-activity.md`, which records exit codes and working directories. Without the hooks,
+//! the shell's own history file is used, which only has the commands themselves.
+
+use std::io::SeekFrom;
+use std::path::PathBuf;
+
+use eyre::bail;
+use tokio::io::{
+    AsyncReadExt,
+    AsyncSeekExt,
+};
+use tracing::debug;
+
+use super::cli::capture::capture_terminal;
+use crate::database::settings::Setting;
+use crate::os::Os;
+use crate::util::paths::PathResolver;
+
+/// Default number of commands shared with the model
+pub const DEFAULT_COMMAND_LIMIT: usize = 10;
+
+/// Most bytes read from the end of a history file
+const MAX_READ_LEN: u64 = 64 * 1024;
+
+/// Lines of terminal output included when asking about a failed command
+const ERROR_OUTPUT_LINES: usize = 50;
+
+/// A command run in the user's shell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ShellCommand {
+    command: String,
+    exit_code: Option<i32>,
+    cwd: Option<String>,
+}
+
+/// Where shell commands are read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HistorySource {
+    /// Tab separated `timestamp, exit code, cwd, command` lines written by the shell hooks
+    ActivityLog(PathBuf),
+    Zsh(PathBuf),
+    Bash(PathBuf),
+    Fish(PathBuf),
+}
+
+impl HistorySource {
+    fn detect(os: &Os) -> Option<Self> {
+        if let Ok(log) = PathResolver::new(os).global().shell_activity_log() {
+            if os.fs.exists(&log) {
+                return Some(Self::ActivityLog(log));
+            }
+        }
+
+        let home = os.env.home()?;
+        let shell = os.env.get("SHELL").unwrap_or_default();
+        let histfile = os.env.get("HISTFILE").ok().map(PathBuf::from);
+        if shell.ends_with("zsh") {
+            Some(Self::Zsh(histfile.unwrap_or_else(|| home.join(".zsh_history"))))
+        } else if shell.ends_with("bash") {
+            Some(Self::Bash(histfile.unwrap_or_else(|| home.join(".bash_history"))))
+        } else if shell.ends_with("fish") {
+            Some(Self::Fish(home.join(".local/share/fish/fish_history")))
+        } else {
+            None
+        }
+    }
+
+    fn path(&self) -> &PathBuf {
+        match self {
+            Self::ActivityLog(path) | Self::Zsh(path) | Self::Bash(path) | Self::Fish(path) => path,
+        }
+    }
+
+    fn parse(&self, text: &str) -> Vec<ShellCommand> {
+        match self {
+            Self::ActivityLog(_) => parse_activity_log(text),
+            Self::Zsh(_) => parse_zsh_history(text),
+            Self::Bash(_) => parse_bash_history(text),
+            Self::Fish(_) => parse_fish_history(text),
+        }
+    }
+}
+
+/// Tracks how much of the user's shell history has already been shared, so each message only
+/// includes the commands run since the previous one.
+#[derive(Debug, Default)]
+pub struct ShellActivity {
+    /// The history file and its length when it was last read
+    last_read: Option<(PathBuf, u64)>,
+}
+
+impl ShellActivity {
+    /// Returns the shell commands run since the last call, formatted as context for the next user
+    /// message, or [None] if the feature is disabled or there is nothing new.
+    pub async fn recent(&mut self, os: &Os) -> Option<String> {
+        if !os
+            .database
+            .settings
+            .get_bool(Setting::ChatShellActivityContext)
+            .unwrap_or(false)
+        {
+            return None;
+        }
+        let limit = os
+            .database
+            .settings
+            .get_int_or(Setting::ChatShellActivityCommands, DEFAULT_COMMAND_LIMIT);
+
+        let source = HistorySource::detect(os)?;
+        let commands = match self.read_new(os, &source).await {
+            Ok(commands) => commands,
+            Err(err) => {
+                debug!(?err, path = ?source.path(), "failed to read shell history");
+                return None;
+            },
+        };
+        format_commands(&commands[commands.len().saturating_sub(limit)..])
+    }
+
+    async fn read_new(&mut self, os: &Os, source: &HistorySource) -> std::io::Result<Vec<ShellCommand>> {
+        let mut file = os.fs.open(source.path()).await?;
+        let len = file.metadata().await?.len();
+
+        // Start over if this is a different file or it was rewritten, as shells do when they
+        // deduplicate their history.
+        let resume_at = match &self.last_read {
+            Some((path, last_len)) if path == source.path() && *last_len <= len => *last_len,
+            _ => 0,
+        };
+        self.last_read = Some((source.path().clone(), len));
+
+        let start = resume_at.max(len.saturating_sub(MAX_READ_LEN));
+        file.seek(SeekFrom::Start(start)).await?;
+        let mut bytes = Vec::new();
+        file.take(len - start).read_to_end(&mut bytes).await?;
+
+        let mut text = String::from_utf8_lossy(&bytes).into_owned();
+        // Skipping ahead can start partway through an entry.
+        if start > resume_at {
+            text = text
+                .split_once('\n')
+                .map(|(_, rest)| rest.to_string())
+                .unwrap_or_default();
+        }
+
+        Ok(source.parse(&text))
+    }
+}
+
+/// Returns the most recent commands in the user's shell history, oldest first, for use in
+/// completions. Unlike [ShellActivity::recent], this doesn't depend on the privacy setting since
+/// nothing leaves the machine.
+pub fn history_commands(os: &Os) -> Vec<String> {
+    let Some(source) = HistorySource::detect(os) else {
+        return Vec::new();
+    };
+    let read_tail = || -> std::io::Result<String> {
+        use std::io::{
+            Read,
+            Seek,
+        };
+
+        let mut file = std::fs::File::open(os.fs.chroot_path(source.path()))?;
+        let len = file.metadata()?.len();
+        let start = len.saturating_sub(MAX_READ_LEN);
+        file.seek(SeekFrom::Start(start))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        let text = String::from_utf8_lossy(&bytes).into_owned();
+        Ok(match start {
+            0 => text,
+            _ => text
+                .split_once('\n')
+                .map(|(_, rest)| rest.to_string())
+                .unwrap_or_default(),
+        })
+    };
+    match read_tail() {
+        Ok(text) => source.parse(&text).into_iter().map(|c| c.command).collect(),
+        Err(err) => {
+            debug!(?err, path = ?source.path(), "failed to read shell history");
+            Vec::new()
+        },
+    }
+}
+
+/// The last command run in the user's shell, which failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedCommand {
+    pub command: String,
+    pub exit_code: i32,
+    pub cwd: Option<String>,
+    /// The output on screen, when the terminal can report it
+    pub output: Option<String>,
+}
+
+/// Returns the last command recorded in the shell activity log, or an error if it succeeded or
+/// nothing was recorded.
+pub async fn last_failed_command(os: &Os) -> eyre::Result<FailedCommand> {
+    let log = PathResolver::new(os).global().shell_activity_log()?;
+    if !os.fs.exists(&log) {
+        bail!(
+            "No shell activity log found at {}. Add the shell hooks from docs/shell-activity.md to record failed commands.",
+            log.display()
+        );
+    }
+
+    let mut activity = ShellActivity::default();
+    let commands = activity.read_new(os, &HistorySource::ActivityLog(log)).await?;
+    let Some(ShellCommand {
+        command,
+        exit_code: Some(exit_code),
+        cwd,
+    }) = commands.into_iter().next_back()
+    else {
+        bail!("No commands have been recorded in the shell activity log");
+    };
+    if exit_code == 0 {
+        bail!("The last command, `{command}`, succeeded");
+    }
+
+    Ok(FailedCommand {
+        command,
+        exit_code,
+        cwd,
+        output: capture_terminal(&os.env, ERROR_OUTPUT_LINES).await,
+    })
+}
+
+/// Builds the first message of a chat started from the shell's error hint, asking why the last
+/// command failed.
+///
+/// The message includes the command, its exit code and, when the terminal can report it, the
+/// output that is on screen.
+pub async fn explain_error_prompt(os: &Os) -> eyre::Result<String> {
+    let FailedCommand {
+        command,
+        exit_code,
+        cwd,
+        output,
+    } = last_failed_command(os).await?;
+
+    let mut prompt = format!("I ran `{command}`");
+    if let Some(cwd) = cwd {
+        prompt.push_str(&format!(" in {cwd}"));
+    }
+    prompt.push_str(&format!(" and it failed with exit code {exit_code}."));
+    if let Some(output) = output {
+        prompt.push_str(&format!("\n\nThe output in my terminal:\n```\n{output}\n```"));
+    }
+    prompt.push_str("\n\nExplain why it failed and how to fix it.");
+    Ok(prompt)
+}
+
+fn format_commands(commands: &[ShellCommand]) -> Option<String> {
+    if commands.is_empty() {
+        return None;
+    }
+
+    let mut out = String::from("Commands the user recently ran in their shell, oldest first:\n");
+    let mut cwd = None;
+    for command in commands {
+        if command.cwd.is_some() && command.cwd != cwd {
+            cwd = command.cwd.clone();
+            out.push_str(&format!("(in {})\n", cwd.as_deref().unwrap_or_default()));
+        }
+        out.push_str(&format!("$ {}", command.command));
+        match command.exit_code {
+            Some(0) | None => {},
+            Some(code) => out.push_str(&format!("  [exit code {code}]")),
+        }
+        out.push('\n');
+    }
+    Some(out)
+}
+
+fn parse_activity_log(text: &str) -> Vec<ShellCommand> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, '\t');
+            let _timestamp = fields.next()?;
+            let exit_code = fields.next()?.trim().parse().ok();
+            let cwd = fields.next()?.to_string();
+            let command = fields.next()?.trim();
+            (!command.is_empty()).then(|| ShellCommand {
+                command: command.to_string(),
+                exit_code,
+                cwd: Some(cwd),
+            })
+        })
+        .collect()
+}
+
+/// Parses zsh history, which prefixes each command with `: <start>:<duration>;` when
+/// `EXTENDED_HISTORY` is set and continues multiline commands with a trailing backslash.
+fn parse_zsh_history(text: &str) -> Vec<ShellCommand> {
+    let mut commands = Vec::new();
+    let mut current = String::new();
+    for line in text.lines() {
+        let line = if current.is_empty() {
+            line.strip_prefix(": ")
+                .and_then(|rest| rest.split_once(';'))
+                .map_or(line, |(_, command)| command)
+        } else {
+            line
+        };
+        match line.strip_suffix('\\') {
+            Some(partial) => {
+                current.push_str(partial);
+                current.push('\n');
+            },
+            None => {
+                current.push_str(line);
+                push_command(&mut commands, std::mem::take(&mut current));
+            },
+        }
+    }
+    commands
+}
+
+/// Parses bash history, skipping the `#<timestamp>` lines written when `HISTTIMEFORMAT` is set.
+fn parse_bash_history(text: &str) -> Vec<ShellCommand> {
+    let mut commands = Vec::new();
+    for line in text.lines() {
+        let is_timestamp = line
+            .strip_prefix('#')
+            .is_some_and(|ts| !ts.is_empty() && ts.bytes().all(|b| b.is_ascii_digit()));
+        if !is_timestamp {
+            push_command(&mut commands, line.to_string());
+        }
+    }
+    commands
+}
+
+/// Parses fish history, a YAML-like list of `- cmd: <command>` entries.
+fn parse_fish_history(text: &str) -> Vec<ShellCommand> {
+    let mut commands = Vec::new();
+    for line in text.lines() {
+        if let Some(command) = line.strip_prefix("- cmd: ") {
+            push_command(&mut commands, command.replace("\\n", "\n").replace("\\\\", "\\"));
+        }
+    }
+    commands
+}
+
+fn push_command(commands: &mut Vec<ShellCommand>, command: String) {
+    let command = command.trim();
+    if !command.is_empty() {
+        commands.push(ShellCommand {
+            command: command.to_string(),
+            exit_code: None,
+            cwd: None,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_activity_log() {
+        let log = "1700000000\t0\t/home/user/project\tcargo build\n\
+                   1700000010\t101\t/home/user/project\tcargo test\t--lib\n\
+                   malformed line\n";
+        assert_eq!(parse_activity_log(log), vec![
+            ShellCommand {
+                command: "cargo build".to_string(),
+                exit_code: Some(0),
+                cwd: Some("/home/user/project".to_string()),
+            },
+            ShellCommand {
+                command: "cargo test\t--lib".to_string(),
+                exit_code: Some(101),
+                cwd: Some("/home/user/project".to_string()),
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_parse_shell_history() {
+        let commands = |parsed: Vec<ShellCommand>| parsed.into_iter().map(|c| c.command).collect::<Vec<_>>();
+        assert_eq!(
+            commands(parse_zsh_history(
+                ": 1700000000:0;ls -la\n: 1700000005:2;for f in *; do\\\necho $f\\\ndone\ngit status\n"
+            )),
+            vec!["ls -la", "for f in *; do\necho $f\ndone", "git status"]
+        );
+        assert_eq!(
+            commands(parse_bash_history("#1700000000\nmake\n\n# a comment\nnpm test\n")),
+            vec!["make", "# a comment", "npm test"]
+        );
+        assert_eq!(
+            commands(parse_fish_history(
+                "- cmd: cd src\n  when: 1700000000\n- cmd: echo a\\nb\n  when: 1700000001\n  paths:\n    - src\n"
+            )),
+            vec!["cd src", "echo a\nb"]
+        );
+    }
+
+    #[test]
+    fn test_format_commands() {
+        let command = |command: &str, exit_code, cwd: &str| ShellCommand {
+            command: command.to_string(),
+            exit_code,
+            cwd: Some(cwd.to_string()),
+        };
+        assert_eq!(format_commands(&[]), None);
+        assert_eq!(
+            format_commands(&[
+                command("cd project", Some(0), "/home/user"),
+                command("cargo build", Some(101), "/home/user/project"),
+                command("cargo build -v", Some(101), "/home/user/project"),
+            ])
+            .unwrap(),
+            "Commands the user recently ran in their shell, oldest first:\n\
+             (in /home/user)\n\
+             $ cd project\n\
+             (in /home/user/project)\n\
+             $ cargo build  [exit code 101]\n\
+             $ cargo build -v  [exit code 101]\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_explain_error_prompt() {
+        let os = Os::new().await.unwrap();
+        assert!(explain_error_prompt(&os).await.is_err(), "no activity log");
+
+        let log = PathResolver::new(&os).global().shell_activity_log().unwrap();
+        os.fs.create_dir_all(log.parent().unwrap()).await.unwrap();
+        os.fs.write(&log, "1\t0\t/repo\tgit pull\n").await.unwrap();
+        let err = explain_error_prompt(&os).await.unwrap_err();
+        assert!(err.to_string().contains("succeeded"), "{err}");
+
+        os.fs
+            .write(&log, "1\t0\t/repo\tgit pull\n2\t2\t/repo\tmake test\n")
+            .await
+            .unwrap();
+        assert_eq!(
+            explain_error_prompt(&os).await.unwrap(),
+            "I ran `make test` in /repo and it failed with exit code 2.\n\nExplain why it failed and how to fix it."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recent_only_returns_new_commands() {
+        let mut os = Os::new().await.unwrap();
+        let log = PathResolver::new(&os).global().shell_activity_log().unwrap();
+        os.fs.create_dir_all(log.parent().unwrap()).await.unwrap();
+        os.fs
+            .write(&log, "1\t0\t/repo\tgit pull\n2\t1\t/repo\tmake\n")
+            .await
+            .unwrap();
+
+        let mut activity = ShellActivity::default();
+        assert_eq!(activity.recent(&os).await, None, "disabled by default");
+
+        os.database
+            .settings
+            .set(Setting::ChatShellActivityContext, true)
+            .await
+            .unwrap();
+        os.database
+            .settings
+            .set(Setting::ChatShellActivityCommands, 1)
+            .await
+            .unwrap();
+        assert_eq!(
+            activity.recent(&os).await.unwrap(),
+            "Commands the user recently ran in their shell, oldest first:\n(in /repo)\n$ make  [exit code 1]\n"
+        );
+        assert_eq!(activity.recent(&os).await, None);
+
+        os.fs
+            .write(
+                &log,
+                "1\t0\t/repo\tgit pull\n2\t1\t/repo\tmake\n3\t0\t/repo\tmake clean\n",
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            activity.recent(&os).await.unwrap(),
+            "Commands the user recently ran in their shell, oldest first:\n(in /repo)\n$ make clean\n"
+        );
+    }
+}
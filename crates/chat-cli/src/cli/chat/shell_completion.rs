@@ -0,0 +1,330 @@
+//! Completes the shell commands run with `!` in the chat prompt without any completion specs, by
+//! ranking candidates from the user's history, executables on `PATH`, git branches and directory
+//! contents.
+
+use std::collections::HashMap;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process::Command;
+use std::sync::OnceLock;
+
+use crate::os::Os;
+
+/// Most candidates returned for a single completion
+const MAX_CANDIDATES: usize = 50;
+
+/// Base scores of each candidate source. History adds to these, so a frequently used argument
+/// ranks above one that merely exists.
+const PATH_SCORE: f64 = 0.5;
+const GIT_BRANCH_SCORE: f64 = 0.8;
+const DIRECTORY_SCORE: f64 = 0.3;
+
+/// Git subcommands whose arguments are usually branches
+const GIT_BRANCH_SUBCOMMANDS: &[&str] = &[
+    "checkout",
+    "switch",
+    "merge",
+    "rebase",
+    "branch",
+    "diff",
+    "log",
+    "push",
+    "pull",
+    "cherry-pick",
+    "reset",
+    "worktree",
+];
+
+#[derive(Debug, Default)]
+pub struct ShellCompleter {
+    /// Directory that relative paths and git branches are resolved in
+    cwd: PathBuf,
+    home: Option<PathBuf>,
+    /// Value of `PATH`, scanned for executables on first use
+    path_var: Option<String>,
+    path_commands: OnceLock<Vec<String>>,
+    /// Commands from the user's shell history, oldest first
+    history: Vec<String>,
+}
+
+impl ShellCompleter {
+    pub fn new(os: &Os) -> Self {
+        Self {
+            cwd: os.env.current_dir().unwrap_or_default(),
+            home: os.env.home(),
+            path_var: os.env.get("PATH").ok(),
+            path_commands: OnceLock::new(),
+            history: super::shell_activity::history_commands(os),
+        }
+    }
+
+    pub fn set_cwd(&mut self, cwd: PathBuf) {
+        self.cwd = cwd;
+    }
+
+    /// Replaces the shell history, to pick up commands run since the completer was created.
+    pub fn set_history(&mut self, history: Vec<String>) {
+        self.history = history;
+    }
+
+    /// Completes the word ending at `pos` in the shell command `line`. `recent` are commands run
+    /// from the chat prompt, oldest first, which rank above the shell history.
+    pub fn complete<'a>(
+        &self,
+        line: &str,
+        pos: usize,
+        recent: impl IntoIterator<Item = &'a str>,
+    ) -> (usize, Vec<String>) {
+        let line = &line[..pos];
+        let start = line.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let word = &line[start..];
+        let preceding = line[..start].split_whitespace().collect::<Vec<_>>();
+
+        let mut scores: HashMap<String, f64> = HashMap::new();
+        let mut history = self.history.iter().map(String::as_str).collect::<Vec<_>>();
+        for command in recent {
+            history.push(command);
+        }
+        for (i, command) in history.iter().enumerate() {
+            let tokens = command.split_whitespace().collect::<Vec<_>>();
+            if tokens.len() > preceding.len() && tokens[..preceding.len()] == preceding[..] {
+                // Recent commands weigh up to twice as much as the oldest ones.
+                *scores.entry(tokens[preceding.len()].to_string()).or_default() +=
+                    1.0 + i as f64 / history.len() as f64;
+            }
+        }
+
+        let is_path = word.contains('/') || word.starts_with('.') || word.starts_with('~');
+        if preceding.is_empty() && !is_path {
+            for command in self.path_commands() {
+                add_score(&mut scores, command, PATH_SCORE);
+            }
+        }
+        if preceding.first() == Some(&"git")
+            && preceding.len() >= 2
+            && GIT_BRANCH_SUBCOMMANDS.contains(&preceding[1])
+            && !is_path
+        {
+            for branch in git_branches(&self.cwd) {
+                add_score(&mut scores, &branch, GIT_BRANCH_SCORE);
+            }
+        }
+        if !preceding.is_empty() || is_path {
+            for entry in self.directory_entries(word) {
+                add_score(&mut scores, &entry, DIRECTORY_SCORE);
+            }
+        }
+
+        let mut candidates = scores
+            .into_iter()
+            .filter(|(candidate, _)| candidate.starts_with(word) && candidate != word)
+            .collect::<Vec<_>>();
+        candidates.sort_by(|(a, a_score), (b, b_score)| b_score.total_cmp(a_score).then_with(|| a.cmp(b)));
+        candidates.truncate(MAX_CANDIDATES);
+        (start, candidates.into_iter().map(|(candidate, _)| candidate).collect())
+    }
+
+    fn path_commands(&self) -> &[String] {
+        self.path_commands.get_or_init(|| {
+            let mut commands = self
+                .path_var
+                .iter()
+                .flat_map(std::env::split_paths)
+                .filter_map(|dir| std::fs::read_dir(dir).ok())
+                .flatten()
+                .filter_map(|entry| {
+                    let entry = entry.ok()?;
+                    is_executable(&entry.path()).then(|| entry.file_name().to_string_lossy().into_owned())
+                })
+                .collect::<Vec<_>>();
+            commands.sort();
+            commands.dedup();
+            commands
+        })
+    }
+
+    /// Entries of the directory that `word` is in, written the same way as `word` so they can
+    /// replace it. Directories end with a slash and hidden entries are only included when `word`
+    /// names one.
+    fn directory_entries(&self, word: &str) -> Vec<String> {
+        let (dir_part, name_part) = word.rsplit_once('/').map_or(("", word), |(dir, name)| (dir, name));
+        let prefix = match word.rfind('/') {
+            Some(i) => &word[..=i],
+            None => "",
+        };
+        let dir = if word.contains('/') {
+            match dir_part.strip_prefix('~') {
+                Some(rest) => match &self.home {
+                    Some(home) => home.join(rest.trim_start_matches('/')),
+                    None => return Vec::new(),
+                },
+                None if dir_part.is_empty() => PathBuf::from("/"),
+                None => self.cwd.join(dir_part),
+            }
+        } else {
+            self.cwd.clone()
+        };
+
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if !name.starts_with(name_part) || (name.starts_with('.') && !name_part.starts_with('.')) {
+                    return None;
+                }
+                let suffix = if entry.path().is_dir() { "/" } else { "" };
+                Some(format!("{prefix}{name}{suffix}"))
+            })
+            .collect()
+    }
+}
+
+fn add_score(scores: &mut HashMap<String, f64>, candidate: &str, score: f64) {
+    *scores.entry(candidate.to_string()).or_default() += score;
+}
+
+/// Local and remote branches of the repository containing `cwd`, empty if it isn't one.
+fn git_branches(cwd: &Path) -> Vec<String> {
+    let Ok(output) = Command::new("git")
+        .arg("-C")
+        .arg(cwd)
+        .args(["for-each-ref", "--format=%(refname)", "refs/heads", "refs/remotes"])
+        .output()
+    else {
+        return Vec::new();
+    };
+    if !output.status.success() {
+        return Vec::new();
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|refname| !refname.ends_with("/HEAD"))
+        .filter_map(|refname| {
+            refname
+                .strip_prefix("refs/heads/")
+                .or_else(|| refname.strip_prefix("refs/remotes/"))
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn completer(cwd: &Path, history: &[&str]) -> ShellCompleter {
+        ShellCompleter {
+            cwd: cwd.to_path_buf(),
+            history: history.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_complete_ranks_history_by_frequency_and_recency() {
+        let dir = tempfile::tempdir().unwrap();
+        let completer = completer(dir.path(), &[
+            "cargo build",
+            "cargo test",
+            "cargo bench",
+            "cargo test --lib",
+        ]);
+        assert_eq!(
+            completer.complete("cargo ", 6, []),
+            (6, vec!["test".to_string(), "bench".to_string(), "build".to_string()])
+        );
+        // Commands run from the chat are the most recent.
+        assert_eq!(completer.complete("cargo b", 7, ["cargo build"]).1, vec![
+            "build".to_string(),
+            "bench".to_string()
+        ]);
+        assert_eq!(completer.complete("cargo test -", 12, []).1, vec!["--lib".to_string()]);
+    }
+
+    #[test]
+    fn test_complete_directory_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src").join("main.rs"), "").unwrap();
+        std::fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+        std::fs::write(dir.path().join(".env"), "").unwrap();
+        let completer = completer(dir.path(), &[]);
+
+        let (start, mut candidates) = completer.complete("cat ", 4, []);
+        candidates.sort();
+        assert_eq!(
+            (start, candidates),
+            (4, vec!["Cargo.toml".to_string(), "src/".to_string()])
+        );
+        assert_eq!(completer.complete("cat s", 5, []).1, vec!["src/".to_string()]);
+        assert_eq!(
+            completer.complete("cat src/m", 9, []),
+            (4, vec!["src/main.rs".to_string()])
+        );
+        assert_eq!(completer.complete("cat .e", 6, []).1, vec![".env".to_string()]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_complete_path_commands() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let bin = tempfile::tempdir().unwrap();
+        for (name, mode) in [("mytool", 0o755), ("mydata", 0o644)] {
+            let path = bin.path().join(name);
+            std::fs::write(&path, "").unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+        }
+        let completer = ShellCompleter {
+            path_var: Some(bin.path().to_string_lossy().into_owned()),
+            ..completer(bin.path(), &["mytop"])
+        };
+        assert_eq!(
+            completer.complete("my", 2, []),
+            (0, vec!["mytop".to_string(), "mytool".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_complete_git_branches() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .output()
+                .is_ok_and(|o| o.status.success())
+        };
+        if !git(&["init", "-b", "main"]) {
+            // git isn't available
+            return;
+        }
+        assert!(git(&["commit", "--allow-empty", "-m", "init"]));
+        assert!(git(&["branch", "feature/login"]));
+
+        let completer = completer(dir.path(), &[]);
+        assert_eq!(completer.complete("git checkout f", 14, []).1, vec![
+            "feature/login".to_string()
+        ]);
+        let (_, candidates) = completer.complete("git switch ", 11, []);
+        assert!(candidates.starts_with(&["feature/login".to_string(), "main".to_string()]));
+    }
+}
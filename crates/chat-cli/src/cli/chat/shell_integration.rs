@@ -0,0 +1,96 @@
+//! VS Code shell integration (`OSC 633`). Marking where the prompt ends and the input begins lets
+//! the integrated terminal track the cursor within the input, so its suggestions and ghost text
+//! follow the cursor instead of the column output last stopped at. Each chat turn also gets the
+//! command decorations and navigation that shell commands get.
+
+use std::cell::Cell;
+use std::path::Path;
+
+use crate::os::Env;
+use crate::util::terminal::TerminalQuirks;
+
+#[derive(Debug, Default)]
+pub struct ShellIntegration {
+    /// Nonce VS Code passes to verify that command lines come from the shell integration
+    nonce: Option<String>,
+    /// Whether a command was started and not finished yet
+    command_running: Cell<bool>,
+}
+
+impl ShellIntegration {
+    pub fn new(env: &Env, quirks: &TerminalQuirks) -> Option<Self> {
+        quirks.shell_integration.then(|| Self {
+            nonce: env.get("VSCODE_NONCE").ok().filter(|nonce| !nonce.is_empty()),
+            command_running: Cell::new(false),
+        })
+    }
+
+    /// Wraps the styled prompt in the sequences marking its start and end. The prompt is redrawn
+    /// on every edit, the previous command is only finished the first time.
+    pub fn prompt(&self, cwd: &Path, prompt: &str) -> String {
+        let mut result = String::new();
+        if self.command_running.replace(false) {
+            result.push_str("\x1b]633;D\x07");
+        }
+        result.push_str("\x1b]633;A\x07");
+        result.push_str(&format!("\x1b]633;P;Cwd={}\x07", escape(&cwd.to_string_lossy())));
+        result.push_str(prompt);
+        result.push_str("\x1b]633;B\x07");
+        result
+    }
+
+    /// Sequences reporting the submitted `line` and marking the start of its output.
+    pub fn command_started(&self, line: &str) -> String {
+        self.command_running.set(true);
+        let nonce = self.nonce.as_ref().map(|nonce| format!(";{nonce}")).unwrap_or_default();
+        format!("\x1b]633;E;{}{nonce}\x07\x1b]633;C\x07", escape(line))
+    }
+}
+
+/// Escapes a value the way VS Code expects, so it can't end the sequence or add parameters.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\x3b"),
+            c if (c as u32) < 0x20 || c == '\x7f' => escaped.push_str(&format!("\\x{:02x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("echo hi"), "echo hi");
+        assert_eq!(escape("a;b\\c\nd"), "a\\x3bb\\\\c\\x0ad");
+    }
+
+    #[test]
+    fn test_sequences() {
+        let vscode = TerminalQuirks::detect(&Env::from_slice(&[("TERM_PROGRAM", "vscode")]));
+        let env = Env::from_slice(&[("VSCODE_NONCE", "abc")]);
+        let integration = ShellIntegration::new(&env, &vscode).unwrap();
+
+        let cwd = Path::new("/work");
+        assert_eq!(
+            integration.prompt(cwd, "> "),
+            "\x1b]633;A\x07\x1b]633;P;Cwd=/work\x07> \x1b]633;B\x07"
+        );
+        assert_eq!(
+            integration.command_started("/help"),
+            "\x1b]633;E;/help;abc\x07\x1b]633;C\x07"
+        );
+        // Only the first redraw of the next prompt finishes the command.
+        assert!(integration.prompt(cwd, "> ").starts_with("\x1b]633;D\x07\x1b]633;A"));
+        assert!(integration.prompt(cwd, "> ").starts_with("\x1b]633;A"));
+
+        let iterm = TerminalQuirks::detect(&Env::from_slice(&[("TERM_PROGRAM", "iTerm.app")]));
+        assert!(ShellIntegration::new(&env, &iterm).is_none());
+    }
+}
@@ -212,75 +212,83 @@ pub fn select_command(os: &Os, context_manager: &ContextManager, tools: &[String
 
     match launch_skim_selector(&commands, "Select command: ", false)? {
         Some(selections) if !selections.is_empty() => {
-            let selected_command = &selections[0];
-
-            match CommandType::from_str(selected_command) {
-                Some(CommandType::ContextAdd(cmd)) => {
-                    // For context add commands, we need to select files
-                    match select_files_with_skim()? {
-                        Some(files) if !files.is_empty() => {
-                            // Construct the full command with selected files
-                            let mut cmd = cmd.clone();
-                            for file in files {
-                                cmd.push_str(&format!(" {}", file));
-                            }
-                            Ok(Some(cmd))
-                        },
-                        _ => Ok(Some(selected_command.clone())), /* User cancelled file selection, return just the
-                                                                  * command */
+            complete_command_selection(&selections[0], context_manager, tools)
+        },
+        _ => Ok(None), // User cancelled command selection
+    }
+}
+
+/// Prompts for the arguments of a selected command where possible, e.g. the files for
+/// `/context add`
+pub fn complete_command_selection(
+    selected_command: &str,
+    context_manager: &ContextManager,
+    tools: &[String],
+) -> Result<Option<String>> {
+    match CommandType::from_str(selected_command) {
+        Some(CommandType::ContextAdd(cmd)) => {
+            // For context add commands, we need to select files
+            match select_files_with_skim()? {
+                Some(files) if !files.is_empty() => {
+                    // Construct the full command with selected files
+                    let mut cmd = cmd.clone();
+                    for file in files {
+                        cmd.push_str(&format!(" {}", file));
                     }
+                    Ok(Some(cmd))
                 },
-                Some(CommandType::ContextRemove(cmd)) => {
-                    // For context rm commands, we need to select from existing context paths
-                    match select_context_paths_with_skim(context_manager)? {
-                        Some((paths, has_global)) if !paths.is_empty() => {
-                            // Construct the full command with selected paths
-                            let mut full_cmd = cmd.clone();
-                            if has_global {
-                                full_cmd.push_str(" --global");
-                            }
-                            for path in paths {
-                                full_cmd.push_str(&format!(" {}", path));
-                            }
-                            Ok(Some(full_cmd))
-                        },
-                        Some((_, _)) => Ok(Some(format!("{} (No paths selected)", cmd))),
-                        None => Ok(Some(selected_command.clone())), // User cancelled path selection
+                _ => Ok(Some(selected_command.to_string())), /* User cancelled file selection, return just the
+                                                              * command */
+            }
+        },
+        Some(CommandType::ContextRemove(cmd)) => {
+            // For context rm commands, we need to select from existing context paths
+            match select_context_paths_with_skim(context_manager)? {
+                Some((paths, has_global)) if !paths.is_empty() => {
+                    // Construct the full command with selected paths
+                    let mut full_cmd = cmd.clone();
+                    if has_global {
+                        full_cmd.push_str(" --global");
                     }
-                },
-                Some(CommandType::Tools(_)) => {
-                    let options = create_skim_options("Select tool: ", false)?;
-                    let item_reader = SkimItemReader::default();
-                    let items = item_reader.of_bufread(Cursor::new(tools.join("\n")));
-                    let selected_tool = match run_skim_with_options(&options, items)? {
-                        Some(items) if !items.is_empty() => Some(items[0].output().to_string()),
-                        _ => None,
-                    };
-
-                    match selected_tool {
-                        Some(tool) => Ok(Some(format!("{} {}", selected_command, tool))),
-                        None => Ok(Some(selected_command.clone())), /* User cancelled tool selection, return just the
-                                                                     * command */
+                    for path in paths {
+                        full_cmd.push_str(&format!(" {}", path));
                     }
+                    Ok(Some(full_cmd))
                 },
-                Some(cmd @ CommandType::Agent(_)) if cmd.needs_agent_selection() => {
-                    // For profile operations that need a profile name, show profile selector
-                    // As part of the agent implementation, we are disabling the ability to
-                    // switch profile after a session has started.
-                    // TODO: perhaps revive this after we have a decision on profile switching
-                    Ok(Some(selected_command.clone()))
-                },
-                Some(CommandType::Agent(_)) => {
-                    // For other profile operations (like create), just return the command
-                    Ok(Some(selected_command.clone()))
-                },
-                None => {
-                    // Command doesn't need additional parameters
-                    Ok(Some(selected_command.clone()))
-                },
+                Some((_, _)) => Ok(Some(format!("{} (No paths selected)", cmd))),
+                None => Ok(Some(selected_command.to_string())), // User cancelled path selection
             }
         },
-        _ => Ok(None), // User cancelled command selection
+        Some(CommandType::Tools(_)) => {
+            let options = create_skim_options("Select tool: ", false)?;
+            let item_reader = SkimItemReader::default();
+            let items = item_reader.of_bufread(Cursor::new(tools.join("\n")));
+            let selected_tool = match run_skim_with_options(&options, items)? {
+                Some(items) if !items.is_empty() => Some(items[0].output().to_string()),
+                _ => None,
+            };
+
+            match selected_tool {
+                Some(tool) => Ok(Some(format!("{} {}", selected_command, tool))),
+                None => Ok(Some(selected_command.to_string())), /* User cancelled tool selection, return just the
+                                                                 * command */
+            }
+        },
+        Some(cmd @ CommandType::Agent(_)) if cmd.needs_agent_selection() => {
+            // For profile operations that need a profile name, show profile selector
+            // As part of the agent implementation, we are disabling the ability to
+            // switch profile after a session has started.
+            // TODO: perhaps revive this after we have a decision on profile switching
+            Ok(Some(selected_command.to_string()))
+        },
+        Some(CommandType::Agent(_)) => {
+            // For other profile operations (like create), just return the command
+            Ok(Some(selected_command.to_string()))
+        },
+        None => {
+            // Command doesn't need additional parameters
+            Ok(Some(selected_command.to_string()))
+        },
     }
 }
 
@@ -0,0 +1,213 @@
+//! Detects context files that changed since a resumed conversation last sent them, so that the
+//! model can be told to prefer their current contents over what it saw earlier in the history.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use crossterm::style::Stylize;
+use crossterm::{
+    cursor,
+    execute,
+    style,
+};
+use sha2::{
+    Digest,
+    Sha256,
+};
+use tracing::warn;
+
+use super::{
+    ChatError,
+    ChatSession,
+};
+use crate::os::Os;
+use crate::theme::StyledText;
+
+/// Hash of each context file sent with the last request, keyed by path
+pub type ContextSnapshot = BTreeMap<String, String>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Modified,
+    Removed,
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Modified => write!(f, "modified"),
+            Change::Removed => write!(f, "removed"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleFile {
+    pub path: String,
+    pub change: Change,
+}
+
+/// Returns the snapshot of the `(path, content)` pairs of context files.
+pub fn snapshot(files: &[(String, String)]) -> ContextSnapshot {
+    files
+        .iter()
+        .map(|(path, content)| (path.clone(), hex::encode(Sha256::digest(content.as_bytes()))))
+        .collect()
+}
+
+/// Returns the files of `snapshot` that are missing from or differ in `current`. Files that were
+/// added since are sent with the next request like any other, so they are not stale.
+pub fn stale_files(snapshot: &ContextSnapshot, current: &[(String, String)]) -> Vec<StaleFile> {
+    let current = self::snapshot(current);
+    snapshot
+        .iter()
+        .filter_map(|(path, hash)| {
+            let change = match current.get(path) {
+                None => Change::Removed,
+                Some(current_hash) if current_hash != hash => Change::Modified,
+                Some(_) => return None,
+            };
+            Some(StaleFile {
+                path: path.clone(),
+                change,
+            })
+        })
+        .collect()
+}
+
+/// Context added to the next prompt so that the model doesn't rely on the earlier versions of
+/// `stale` in the history.
+pub fn refresh_note(stale: &[StaleFile]) -> String {
+    let mut note = String::from(
+        "These context files changed since this conversation was last active. The context sent with this \
+        message holds their current contents, which replace anything earlier in the conversation:\n",
+    );
+    for file in stale {
+        note.push_str(&format!("- {} ({})\n", file.path, file.change));
+    }
+    note
+}
+
+/// Warns about the context files of a resumed conversation that changed since it was saved, and
+/// offers to point the model at their current contents before the first request.
+pub async fn offer_refresh(os: &Os, session: &mut ChatSession) -> Result<(), ChatError> {
+    let Some(context_manager) = session.conversation.context_manager.as_ref() else {
+        return Ok(());
+    };
+    let current = match context_manager.get_context_files(os).await {
+        Ok(current) => current,
+        Err(err) => {
+            warn!(?err, "failed to read the context files to check for changes");
+            return Ok(());
+        },
+    };
+    let stale = stale_files(session.conversation.context_snapshot(), &current);
+    if stale.is_empty() {
+        return Ok(());
+    }
+
+    execute!(
+        session.stderr,
+        StyledText::warning_fg(),
+        style::Print("Context files changed since this conversation was saved:\n"),
+        StyledText::reset(),
+    )?;
+    for file in &stale {
+        execute!(
+            session.stderr,
+            style::Print(format!("  {} ", file.path)),
+            StyledText::secondary_fg(),
+            style::Print(format!("({})\n", file.change)),
+            StyledText::reset(),
+        )?;
+    }
+
+    let refresh = match session.interactive {
+        true => {
+            execute!(
+                session.stderr,
+                StyledText::secondary_fg(),
+                style::Print("\nRefresh them so the model uses their current contents? "),
+                style::Print("["),
+                StyledText::success_fg(),
+                style::Print("y"),
+                StyledText::secondary_fg(),
+                style::Print("/"),
+                StyledText::success_fg(),
+                style::Print("n"),
+                StyledText::secondary_fg(),
+                style::Print("]:\n\n"),
+                StyledText::reset(),
+                cursor::Show,
+            )?;
+            session
+                .read_user_input("> ".yellow().to_string().as_str(), true)?
+                .is_some_and(|input| ["y", "Y"].contains(&input.trim()))
+        },
+        // Nobody can answer, and telling the model is always safe
+        false => true,
+    };
+
+    if refresh {
+        let note = refresh_note(&stale);
+        match session.pending_additional_context.as_mut() {
+            Some(context) => {
+                context.push_str("\n\n");
+                context.push_str(&note);
+            },
+            None => session.pending_additional_context = Some(note),
+        }
+        execute!(
+            session.stderr,
+            StyledText::success_fg(),
+            style::Print("The next request uses the current contents of the changed files.\n\n"),
+            StyledText::reset(),
+        )?;
+    } else {
+        execute!(session.stderr, style::Print("\n"))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(files: &[(&str, &str)]) -> Vec<(String, String)> {
+        files
+            .iter()
+            .map(|(path, content)| (path.to_string(), content.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_stale_files() {
+        let saved = snapshot(&files(&[
+            ("README.md", "# App"),
+            ("AGENTS.md", "Use tabs"),
+            ("docs/old.md", "old"),
+        ]));
+        let current = files(&[("README.md", "# App"), ("AGENTS.md", "Use spaces"), ("new.md", "new")]);
+        assert_eq!(stale_files(&saved, &current), vec![
+            StaleFile {
+                path: "AGENTS.md".to_string(),
+                change: Change::Modified,
+            },
+            StaleFile {
+                path: "docs/old.md".to_string(),
+                change: Change::Removed,
+            },
+        ]);
+        assert!(stale_files(&ContextSnapshot::new(), &current).is_empty());
+    }
+
+    #[test]
+    fn test_refresh_note() {
+        let note = refresh_note(&[StaleFile {
+            path: "AGENTS.md".to_string(),
+            change: Change::Modified,
+        }]);
+        assert!(note.ends_with("- AGENTS.md (modified)\n"));
+    }
+}
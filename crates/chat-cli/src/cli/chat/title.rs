@@ -0,0 +1,149 @@
+//! Short titles for conversations, generated after the first turn and shown by `q chat --list`.
+
+use std::path::PathBuf;
+
+use eyre::{
+    Result,
+    bail,
+};
+use tokio::task::JoinHandle;
+use tracing::{
+    debug,
+    warn,
+};
+
+use super::conversation::ConversationState;
+use super::util::truncate_safe;
+use crate::database::settings::Setting;
+use crate::os::Os;
+
+/// Longest title kept, in characters
+pub const MAX_TITLE_CHARS: usize = 60;
+
+/// How much of the first prompt and response is sent to generate the title, in bytes
+const MAX_INPUT_BYTES: usize = 2000;
+
+/// Whether titles are generated, which can be turned off with `chat.generateTitles`.
+pub fn enabled(os: &Os) -> bool {
+    os.database
+        .settings
+        .get_bool(Setting::ChatGenerateTitles)
+        .unwrap_or(true)
+}
+
+/// Returns the first prompt of the conversation and the response to it, which the title is
+/// generated from.
+pub fn first_turn(conversation: &ConversationState) -> Option<(String, String)> {
+    let entry = conversation
+        .history()
+        .iter()
+        .find(|entry| entry.user.prompt().is_some())?;
+    let prompt = entry.user.prompt()?;
+    Some((
+        truncate_safe(prompt, MAX_INPUT_BYTES).to_string(),
+        truncate_safe(entry.assistant.content(), MAX_INPUT_BYTES).to_string(),
+    ))
+}
+
+/// Generates a title in the background, and stores it with the conversation saved for `cwd` so
+/// that it is kept even if the session ends before the title is applied to it.
+pub fn spawn(
+    os: &Os,
+    cwd: PathBuf,
+    conversation_id: String,
+    (prompt, response): (String, String),
+    model_id: Option<String>,
+) -> JoinHandle<Option<String>> {
+    let os = os.clone();
+    tokio::spawn(async move {
+        let model_id = os.database.settings.get_string(Setting::ChatTitleModel).or(model_id);
+        let title = match generate(&os, &prompt, &response, model_id.as_deref()).await {
+            Ok(title) => title,
+            Err(err) => {
+                warn!(?err, "failed to generate a conversation title");
+                return None;
+            },
+        };
+        debug!(?title, "generated conversation title");
+
+        let mut database = os.database.clone();
+        if let Ok(Some(mut saved)) = database.get_conversation_by_path(&cwd) {
+            if saved.conversation_id() == conversation_id && saved.title().is_none() {
+                saved.set_title(title.clone());
+                database.set_conversation_by_path(&cwd, &saved).ok();
+            }
+        }
+        Some(title)
+    })
+}
+
+async fn generate(os: &Os, prompt: &str, response: &str, model_id: Option<&str>) -> Result<String> {
+    let content = format!(
+        "Write a title of at most 6 words for the conversation below, like the subject of an email. \
+        Reply with the title only, without quotes or punctuation at the end.\n\n\
+        <user>\n{prompt}\n</user>\n<assistant>\n{response}\n</assistant>"
+    );
+    let reply = os
+        .client
+        .send_prompt(content, model_id.map(str::to_string))
+        .await?
+        .text()
+        .await?;
+    match clean(&reply) {
+        Some(title) => Ok(title),
+        None => bail!("the title is empty"),
+    }
+}
+
+/// Turns the reply of the model into a single line title.
+pub fn clean(reply: &str) -> Option<String> {
+    let line = reply.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let line = line.trim_start_matches(['#', '*']).trim_start();
+    let line = line.strip_prefix("Title:").unwrap_or(line);
+    let title = line
+        .trim()
+        .trim_matches(|c| matches!(c, '"' | '\'' | '`' | '*'))
+        .trim_end_matches(['.', '!'])
+        .trim();
+    if title.is_empty() {
+        return None;
+    }
+    Some(shorten(title, MAX_TITLE_CHARS))
+}
+
+/// Shortens `text` to `max_chars` characters, ending with an ellipsis when cut.
+pub fn shorten(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut shortened = text.chars().take(max_chars.saturating_sub(1)).collect::<String>();
+    shortened.truncate(shortened.trim_end().len());
+    shortened.push('…');
+    shortened
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean() {
+        assert_eq!(
+            clean("\"Fix flaky login test\"\n"),
+            Some("Fix flaky login test".to_string())
+        );
+        assert_eq!(
+            clean("Title: Rust lifetimes explained."),
+            Some("Rust lifetimes explained".to_string())
+        );
+        assert_eq!(clean("\n## **Deploy to ECS**\nmore"), Some("Deploy to ECS".to_string()));
+        assert_eq!(clean("  \n "), None);
+        assert_eq!(clean(&"word ".repeat(30)).unwrap().chars().count(), MAX_TITLE_CHARS);
+    }
+
+    #[test]
+    fn test_shorten() {
+        assert_eq!(shorten("short", 10), "short");
+        assert_eq!(shorten("a long sentence", 8), "a long…");
+    }
+}
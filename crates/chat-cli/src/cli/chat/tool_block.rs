@@ -0,0 +1,173 @@
+//! Executed tools shown as a single line in the transcript, expanded on request.
+//!
+//! Tool output (a build log, a file listing) can run to hundreds of lines and push the rest of the
+//! conversation out of view, so when `chat.collapseToolOutput` is enabled, it is held back and
+//! only a summary line is printed.
+
+use std::collections::VecDeque;
+use std::io::{
+    self,
+    Write,
+};
+
+use crossterm::{
+    execute,
+    style,
+};
+
+use super::message::ToolProvenance;
+use super::util::truncate_safe;
+use crate::theme::StyledText;
+
+/// Number of collapsed tools that can still be expanded
+const MAX_COLLAPSED_TOOLS: usize = 20;
+
+/// Number of bytes of each path shown on the summary line
+const MAX_PATH_LEN: usize = 60;
+
+/// An executed tool whose arguments and output were held back.
+#[derive(Debug, Clone)]
+pub struct CollapsedTool {
+    pub summary: String,
+    pub args: serde_json::Value,
+    pub output: String,
+}
+
+impl CollapsedTool {
+    pub fn new(provenance: &ToolProvenance, args: serde_json::Value, output: String) -> Self {
+        Self {
+            summary: summary_line(provenance),
+            args,
+            output,
+        }
+    }
+}
+
+/// Collapsed tools of the session, most recent last.
+#[derive(Debug, Default)]
+pub struct CollapsedTools {
+    tools: VecDeque<CollapsedTool>,
+}
+
+impl CollapsedTools {
+    pub fn push(&mut self, tool: CollapsedTool) {
+        if self.tools.len() == MAX_COLLAPSED_TOOLS {
+            self.tools.pop_front();
+        }
+        self.tools.push_back(tool);
+    }
+
+    /// Removes and returns the most recent collapsed tool, so that pressing Enter repeatedly walks
+    /// back through the tools of the last turn.
+    pub fn expand_last(&mut self) -> Option<CollapsedTool> {
+        self.tools.pop_back()
+    }
+}
+
+/// The line printed in place of a tool's output, e.g. `✓ fs_read src/main.rs 1.2s`.
+pub fn summary_line(provenance: &ToolProvenance) -> String {
+    let mut parts = vec![provenance.tool_name.clone()];
+    match provenance.paths.as_slice() {
+        [] => (),
+        [path] => parts.push(truncate_safe(path, MAX_PATH_LEN).to_string()),
+        [path, rest @ ..] => parts.push(format!("{} (+{} more)", truncate_safe(path, MAX_PATH_LEN), rest.len())),
+    }
+    if let Some(code) = provenance.exit_code.filter(|code| *code != 0) {
+        parts.push(format!("exit {code}"));
+    }
+    parts.push(format!("{:.1}s", provenance.duration_ms as f64 / 1000.0));
+    parts.join(" ")
+}
+
+/// Prints the summary line of a tool that was just collapsed.
+pub fn print_collapsed(output: &mut impl Write, tool: &CollapsedTool, checkpoint_tag: Option<&str>) -> io::Result<()> {
+    execute!(
+        output,
+        StyledText::success_fg(),
+        style::Print("✓ "),
+        StyledText::reset(),
+        style::Print(&tool.summary),
+    )?;
+    if let Some(tag) = checkpoint_tag {
+        execute!(
+            output,
+            StyledText::info_fg(),
+            style::Print(format!(" [{tag}]")),
+            StyledText::reset(),
+        )?;
+    }
+    execute!(
+        output,
+        StyledText::secondary_fg(),
+        style::Print("  (Enter on an empty prompt to expand)\n\n"),
+        StyledText::reset(),
+    )?;
+    Ok(())
+}
+
+/// Prints the arguments and output held back for a collapsed tool.
+pub fn print_expanded(output: &mut impl Write, tool: &CollapsedTool) -> io::Result<()> {
+    let args = serde_json::to_string_pretty(&tool.args).unwrap_or_else(|_| tool.args.to_string());
+    execute!(
+        output,
+        StyledText::success_fg(),
+        style::Print("▾ "),
+        StyledText::reset(),
+        style::Print(&tool.summary),
+        style::Print("\n"),
+        StyledText::secondary_fg(),
+        style::Print(args),
+        style::Print("\n"),
+        StyledText::reset(),
+        style::Print(tool.output.trim_end()),
+        style::Print("\n\n"),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_line() {
+        let provenance = ToolProvenance {
+            tool_name: "fs_read".to_string(),
+            duration_ms: 1200,
+            paths: vec!["src/main.rs".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(summary_line(&provenance), "fs_read src/main.rs 1.2s");
+
+        let provenance = ToolProvenance {
+            tool_name: "execute_bash".to_string(),
+            duration_ms: 3000,
+            exit_code: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(summary_line(&provenance), "execute_bash exit 2 3.0s");
+
+        let provenance = ToolProvenance {
+            tool_name: "fs_read".to_string(),
+            duration_ms: 40,
+            paths: vec!["a.rs".to_string(), "b.rs".to_string(), "c.rs".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(summary_line(&provenance), "fs_read a.rs (+2 more) 0.0s");
+    }
+
+    #[test]
+    fn test_collapsed_tools_expand_most_recent_first() {
+        let mut tools = CollapsedTools::default();
+        for i in 0..MAX_COLLAPSED_TOOLS + 1 {
+            tools.push(CollapsedTool {
+                summary: i.to_string(),
+                args: serde_json::Value::Null,
+                output: String::new(),
+            });
+        }
+        assert_eq!(tools.expand_last().unwrap().summary, MAX_COLLAPSED_TOOLS.to_string());
+        assert_eq!(tools.tools.len(), MAX_COLLAPSED_TOOLS - 1);
+        assert_eq!(tools.tools.front().unwrap().summary, "1");
+    }
+}
@@ -1,5 +1,6 @@
 use std::borrow::Borrow;
 use std::collections::{
+    BTreeMap,
     HashMap,
     HashSet,
 };
@@ -53,7 +54,11 @@ use tracing::{
     warn,
 };
 
-use super::tools::custom_tool::CustomToolConfig;
+use super::tools::custom_tool::{
+    CircuitBreaker,
+    CustomToolConfig,
+    TransportType,
+};
 use crate::api_client::model::{
     ToolResult,
     ToolResultContentBlock,
@@ -62,6 +67,7 @@ use crate::api_client::model::{
 use crate::cli::agent::{
     Agent,
     McpServerConfig,
+    OriginalToolName,
 };
 use crate::cli::chat::cli::prompts::GetPromptError;
 use crate::cli::chat::consts::DUMMY_TOOL_NAME;
@@ -70,13 +76,23 @@ use crate::cli::chat::server_messenger::{
     ServerMessengerBuilder,
     UpdateEventMessage,
 };
+use crate::cli::chat::tools::archive::{
+    ArchiveList,
+    ArchiveReadMember,
+};
 use crate::cli::chat::tools::custom_tool::CustomTool;
+use crate::cli::chat::tools::data_preview::DataPreview;
 use crate::cli::chat::tools::delegate::Delegate;
+use crate::cli::chat::tools::dependencies::{
+    DependencyList,
+    DependencyVersions,
+};
 use crate::cli::chat::tools::execute::ExecuteCommand;
 use crate::cli::chat::tools::fs_read::FsRead;
 use crate::cli::chat::tools::fs_write::FsWrite;
 use crate::cli::chat::tools::gh_issue::GhIssue;
 use crate::cli::chat::tools::introspect::Introspect;
+use crate::cli::chat::tools::kb_search::KbSearch;
 use crate::cli::chat::tools::knowledge::Knowledge;
 use crate::cli::chat::tools::thinking::Thinking;
 use crate::cli::chat::tools::todo::TodoList;
@@ -97,7 +113,10 @@ use crate::mcp_client::{
 use crate::os::Os;
 use crate::telemetry::TelemetryThread;
 use crate::theme::StyledText;
-use crate::util::MCP_SERVER_TOOL_DELIMITER;
+use crate::util::{
+    MCP_SERVER_TOOL_DELIMITER,
+    startup_profile,
+};
 
 const NAMESPACE_DELIMITER: &str = "___";
 // This applies for both mcp server and tool name
@@ -187,6 +206,7 @@ pub struct ToolManagerBuilder {
     pending_clients: Option<Arc<RwLock<HashSet<String>>>>,
     is_first_launch: bool,
     agent: Option<Arc<Mutex<Agent>>>,
+    tool_set: Option<Vec<String>>,
 }
 
 impl Default for ToolManagerBuilder {
@@ -204,6 +224,7 @@ impl Default for ToolManagerBuilder {
             pending_clients: Default::default(),
             is_first_launch: true,
             agent: Default::default(),
+            tool_set: Default::default(),
         }
     }
 }
@@ -226,6 +247,7 @@ impl From<&mut ToolManager> for ToolManagerBuilder {
             // if we are getting a builder from an instantiated tool manager this field would be
             // false
             is_first_launch: false,
+            tool_set: value.tool_set.clone(),
             ..Default::default()
         }
     }
@@ -279,8 +301,16 @@ impl ToolManagerBuilder {
         mut output: Box<dyn Write + Send + Sync + 'static>,
         interactive: bool,
     ) -> eyre::Result<ToolManager> {
-        let McpServerConfig { mcp_servers } = match &self.agent {
-            Some(agent) => agent.lock().await.mcp_servers.clone(),
+        let (McpServerConfig { mcp_servers }, agent_env, agent_name, egress) = match &self.agent {
+            Some(agent) => {
+                let agent = agent.lock().await;
+                (
+                    agent.mcp_servers.clone(),
+                    agent.resolved_env(os),
+                    agent.name.clone(),
+                    agent.egress.clone(),
+                )
+            },
             None => Default::default(),
         };
         debug_assert!(self.conversation_id.is_some());
@@ -291,12 +321,46 @@ impl ToolManagerBuilder {
             .into_iter()
             .partition(|(_, server_config)| !server_config.disabled);
 
+        // Servers inherit the env of the agent, with their own env taking precedence
+        let enabled_servers = enabled_servers
+            .into_iter()
+            .map(|(server_name, mut server_config)| {
+                if !agent_env.is_empty() {
+                    let env = server_config.env.get_or_insert_default();
+                    for (name, value) in &agent_env {
+                        env.entry(name.clone()).or_insert_with(|| value.clone());
+                    }
+                }
+                (server_name, server_config)
+            })
+            .filter(|(server_name, _)| {
+                // Servers the tool set picked with `/tools use` has no tools from are not started
+                self.tool_set
+                    .as_ref()
+                    .is_none_or(|tool_set| tool_set_includes_server(tool_set, server_name))
+            })
+            .collect::<Vec<_>>();
+
         // Prepare disabled servers for display
         let disabled_servers_display: Vec<String> = disabled_servers
             .iter()
             .map(|(server_name, _)| server_name.clone())
             .collect();
 
+        // Only remote servers can be held to the egress policy, local ones are processes the agent
+        // can't see into
+        let mut egress_denied = HashMap::new();
+        if let Some(egress) = &egress {
+            for (server_name, server_config) in &enabled_servers {
+                if !matches!(server_config.r#type, TransportType::Http) {
+                    continue;
+                }
+                if let Err(err) = egress.check(os, &agent_name, server_name, &server_config.url).await {
+                    egress_denied.insert(server_name.clone(), err);
+                }
+            }
+        }
+
         let pre_initialized = enabled_servers
             .iter()
             .filter(|(server_name, _)| {
@@ -315,6 +379,17 @@ impl ToolManagerBuilder {
                         style::Print(" (it is used to denote native tools)\n")
                     );
                     false
+                } else if let Some(err) = egress_denied.get(server_name) {
+                    let _ = queue!(
+                        output,
+                        StyledText::error_fg(),
+                        style::Print("✗ "),
+                        StyledText::info_fg(),
+                        style::Print(&server_name),
+                        StyledText::reset(),
+                        style::Print(format!(" was not started: {err}\n")),
+                    );
+                    false
                 } else {
                     true
                 }
@@ -397,6 +472,7 @@ impl ToolManagerBuilder {
                         server_name.clone(),
                         server_config,
                         messenger_builder.build_with_name(server_name),
+                        os.env.clone(),
                     ),
                 )
             })
@@ -459,6 +535,7 @@ impl ToolManagerBuilder {
             },
             messenger_builder: Some(messenger_builder),
             is_first_launch: self.is_first_launch,
+            tool_set: self.tool_set,
             ..Default::default()
         })
     }
@@ -499,6 +576,52 @@ enum ToolValidationViolation {
     DescriptionTooLong(String),
 }
 
+/// How a tool from an mcp server was changed on its way to the model, or why it was left out.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ToolAdjustment {
+    /// Renamed by the agent's `toolAliases`
+    Aliased(ModelToolName),
+    /// Renamed to comply with ^[a-zA-Z][a-zA-Z0-9_]*$
+    Sanitized(ModelToolName),
+    /// Description replaced by the agent's `toolDescriptions`
+    DescriptionOverridden,
+    /// Description cut to the maximum length
+    DescriptionTruncated,
+    /// Left out because the agent's `tools` doesn't include it
+    NotIncluded,
+    /// Left out because its name is longer than 64 characters
+    NameTooLong(ModelToolName),
+    /// Left out because it has no description
+    EmptyDescription,
+    /// Left out because another tool already has the name
+    Conflict { name: ModelToolName, kept: String },
+}
+
+impl ToolAdjustment {
+    /// Whether the tool is left out rather than changed.
+    pub fn is_filtered(&self) -> bool {
+        matches!(
+            self,
+            Self::NotIncluded | Self::NameTooLong(_) | Self::EmptyDescription | Self::Conflict { .. }
+        )
+    }
+}
+
+impl std::fmt::Display for ToolAdjustment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Aliased(name) => write!(f, "renamed to {name} by toolAliases"),
+            Self::Sanitized(name) => write!(f, "renamed to {name} to match ^[a-zA-Z][a-zA-Z0-9_]*$"),
+            Self::DescriptionOverridden => write!(f, "description replaced by toolDescriptions"),
+            Self::DescriptionTruncated => write!(f, "description truncated to 10004 characters"),
+            Self::NotIncluded => write!(f, "not included in the agent's tools"),
+            Self::NameTooLong(name) => write!(f, "name {name} is longer than 64 characters"),
+            Self::EmptyDescription => write!(f, "description is empty"),
+            Self::Conflict { name, kept } => write!(f, "name {name} is already used by {kept}, set an alias for it"),
+        }
+    }
+}
+
 #[derive(Clone, Default, Debug, Eq, PartialEq)]
 pub struct ToolInfo {
     pub server_name: String,
@@ -533,7 +656,14 @@ type ServerName = String;
 /// (which is a subset of the tools that are in the aforementioned vector)
 /// Note that [ToolSpec] is model facing and thus will have names that are model facing (i.e. model
 /// tool name).
-type NewToolSpecs = Arc<Mutex<HashMap<ServerName, (HashMap<ModelToolName, ToolInfo>, Vec<ToolSpec>)>>>;
+type NewToolSpecs = Arc<Mutex<HashMap<ServerName, ServerTools>>>;
+
+/// The tools of a server after [process_tool_specs], with the adjustments made to them.
+type ServerTools = (
+    HashMap<ModelToolName, ToolInfo>,
+    Vec<ToolSpec>,
+    Vec<(HostToolName, ToolAdjustment)>,
+);
 
 /// A pair of channels used for prompt list communication between the tool manager and chat helper.
 /// The sender broadcasts a list of available prompt names, while the receiver listens for
@@ -590,11 +720,28 @@ pub struct ToolManager {
     /// to ensure tool names comply with naming requirements.
     pub tn_map: HashMap<ModelToolName, ToolInfo>,
 
+    /// The latest tools of every server, kept so name conflicts between servers can be resolved
+    /// the same way regardless of the order the servers finish loading in.
+    mcp_tools: BTreeMap<ServerName, ServerTools>,
+
+    /// Tools of each server left out because their name is taken, as of the last
+    /// [ToolManager::update].
+    tool_conflicts: BTreeMap<ServerName, Vec<(HostToolName, ToolAdjustment)>>,
+
     /// A cache of tool's input schema for all of the available tools.
     /// This is mainly used to show the user what the tools look like from the perspective of the
     /// model.
     pub schema: HashMap<ModelToolName, ToolSpec>,
 
+    /// Tools turned off for the rest of the session with `/tools`. They stay in [Self::schema] so
+    /// they can be turned back on, but are not sent to the model.
+    pub disabled_tools: HashSet<ModelToolName>,
+
+    /// The tool set picked with `/tools use`, written like the tools field of an agent. Tools it
+    /// leaves out are added to [Self::disabled_tools] as they load, and servers it has no tools
+    /// from are not started.
+    pub tool_set: Option<Vec<String>>,
+
     is_interactive: bool,
 
     /// This serves as a record of the loading of mcp servers.
@@ -607,6 +754,9 @@ pub struct ToolManager {
     /// List of disabled MCP server names for display purposes
     disabled_servers: Vec<String>,
 
+    /// Whether tool calls to each server are let through, created on the first call to the server
+    circuit_breakers: HashMap<ServerName, Arc<CircuitBreaker>>,
+
     /// A builder for mcp clients to communicate with the orchestrator task
     /// We need to store this for when we switch agent - we need to be spawning messengers that are
     /// already listened to by the orchestrator task
@@ -627,10 +777,15 @@ impl Clone for ToolManager {
             has_new_stuff: self.has_new_stuff.clone(),
             new_tool_specs: self.new_tool_specs.clone(),
             tn_map: self.tn_map.clone(),
+            mcp_tools: self.mcp_tools.clone(),
+            tool_conflicts: self.tool_conflicts.clone(),
             schema: self.schema.clone(),
+            disabled_tools: self.disabled_tools.clone(),
+            tool_set: self.tool_set.clone(),
             is_interactive: self.is_interactive,
             mcp_load_record: self.mcp_load_record.clone(),
             disabled_servers: self.disabled_servers.clone(),
+            circuit_breakers: self.circuit_breakers.clone(),
             ..Default::default()
         }
     }
@@ -727,6 +882,9 @@ impl ToolManager {
             if !crate::cli::chat::tools::knowledge::Knowledge::is_enabled(os) {
                 tool_specs.remove("knowledge");
             }
+            if !crate::cli::chat::tools::kb_search::KbSearch::is_enabled(os) {
+                tool_specs.remove("kb_search");
+            }
             if !crate::cli::chat::tools::todo::TodoList::is_enabled(os) {
                 tool_specs.remove("todo_list");
             }
@@ -764,6 +922,16 @@ impl ToolManager {
 
             tool_specs
         };
+        if let Some(tool_set) = &self.tool_set {
+            self.disabled_tools.extend(
+                self.schema
+                    .values()
+                    .filter(|spec| {
+                        spec.name != DUMMY_TOOL_NAME && !tool_set_includes(tool_set, &spec.tool_origin, &spec.name)
+                    })
+                    .map(|spec| spec.name.clone()),
+            );
+        }
 
         // We need to cast it to erase the type otherwise the compiler will default to static
         // dispatch, which would result in an error of inconsistent match arm return type.
@@ -861,6 +1029,18 @@ impl ToolManager {
             status: ToolResultStatus::Error,
         };
 
+        // Tools turned off with /tools aren't sent to the model, but it may still remember them
+        if self.disabled_tools.contains(&value.name) {
+            return Err(ToolResult {
+                tool_use_id: value.id.clone(),
+                content: vec![ToolResultContentBlock::Text(format!(
+                    "The tool {} has been turned off by the user for this session. Do not use it again.",
+                    value.name
+                ))],
+                status: ToolResultStatus::Error,
+            });
+        }
+
         Ok(match value.name.as_str() {
             "fs_read" => Tool::FsRead(serde_json::from_value::<FsRead>(value.args).map_err(map_err)?),
             "fs_write" => Tool::FsWrite(serde_json::from_value::<FsWrite>(value.args).map_err(map_err)?),
@@ -877,9 +1057,21 @@ impl ToolManager {
             "introspect" => Tool::Introspect(serde_json::from_value::<Introspect>(value.args).map_err(map_err)?),
             "thinking" => Tool::Thinking(serde_json::from_value::<Thinking>(value.args).map_err(map_err)?),
             "knowledge" => Tool::Knowledge(serde_json::from_value::<Knowledge>(value.args).map_err(map_err)?),
+            "kb_search" => Tool::KbSearch(serde_json::from_value::<KbSearch>(value.args).map_err(map_err)?),
             "todo_list" => Tool::Todo(serde_json::from_value::<TodoList>(value.args).map_err(map_err)?),
             // Note that this name is NO LONGER namespaced with server_name{DELIMITER}tool_name
             "delegate" => Tool::Delegate(serde_json::from_value::<Delegate>(value.args).map_err(map_err)?),
+            "data_preview" => Tool::DataPreview(serde_json::from_value::<DataPreview>(value.args).map_err(map_err)?),
+            "archive_list" => Tool::ArchiveList(serde_json::from_value::<ArchiveList>(value.args).map_err(map_err)?),
+            "archive_read_member" => {
+                Tool::ArchiveReadMember(serde_json::from_value::<ArchiveReadMember>(value.args).map_err(map_err)?)
+            },
+            "dependency_list" => {
+                Tool::DependencyList(serde_json::from_value::<DependencyList>(value.args).map_err(map_err)?)
+            },
+            "dependency_versions" => {
+                Tool::DependencyVersions(serde_json::from_value::<DependencyVersions>(value.args).map_err(map_err)?)
+            },
             name => {
                 // Note: tn_map also has tools that underwent no transformation. In otherwords, if
                 // it is a valid tool name, we should get a hit.
@@ -915,11 +1107,46 @@ impl ToolManager {
                     status: ToolResultStatus::Error,
                 })?;
 
+                let breaker = match self.circuit_breakers.get(server_name) {
+                    Some(breaker) => breaker.clone(),
+                    None => {
+                        let policy = self
+                            .agent
+                            .lock()
+                            .await
+                            .mcp_servers
+                            .mcp_servers
+                            .get(server_name)
+                            .map(|config| config.retry.clone())
+                            .unwrap_or_default();
+                        let breaker = Arc::new(CircuitBreaker::new(
+                            server_name.clone(),
+                            policy,
+                            self.mcp_load_record.clone(),
+                        ));
+                        self.circuit_breakers.insert(server_name.clone(), breaker.clone());
+                        breaker
+                    },
+                };
+                // Tools of a degraded server are reported as unavailable without asking the user
+                // to approve a call that won't be made
+                if let Some(left) = breaker.unavailable_for() {
+                    return Err(ToolResult {
+                        tool_use_id: value.id,
+                        content: vec![ToolResultContentBlock::Text(format!(
+                            "The tools of server {server_name} are temporarily unavailable after repeated failures. Try again in {}s or use other tools.",
+                            left.as_secs().max(1)
+                        ))],
+                        status: ToolResultStatus::Error,
+                    });
+                }
+
                 Tool::Custom(CustomTool {
                     name: tool_name.to_owned(),
                     server_name: server_name.to_owned(),
                     client: running_service.clone(),
                     params: value.args.as_object().cloned(),
+                    breaker,
                 })
             },
         })
@@ -927,72 +1154,141 @@ impl ToolManager {
 
     /// Updates tool managers various states with new information
     pub async fn update(&mut self) {
-        // A hashmap of <tool name, tool spec>
-        let mut tool_specs = HashMap::<String, ToolSpec>::new();
-        let new_tools = {
-            let mut new_tool_specs = self.new_tool_specs.lock().await;
-            new_tool_specs.drain().fold(
-                HashMap::<ServerName, (HashMap<ModelToolName, ToolInfo>, Vec<ToolSpec>)>::new(),
-                |mut acc, (server_name, v)| {
-                    acc.insert(server_name, v);
-                    acc
-                },
-            )
-        };
+        let new_tools = self.new_tool_specs.lock().await.drain().collect::<Vec<_>>();
+        let updated_servers = new_tools.iter().map(|(name, _)| name.clone()).collect::<HashSet<_>>();
+        self.mcp_tools.extend(new_tools);
+
+        // Conflicts are resolved over the tools of every server at once so the outcome doesn't
+        // depend on which server loaded first:
+        // 1. Built-in tools keep their names
+        // 2. Names given by an alias are kept over names that are not
+        // 3. Otherwise the server whose name sorts first keeps the name
+        let native_tool_names = self
+            .schema
+            .values()
+            .filter(|spec| spec.tool_origin == ToolOrigin::Native)
+            .map(|spec| spec.name.clone())
+            .collect::<HashSet<_>>();
+        let mut candidates = self
+            .mcp_tools
+            .iter()
+            .flat_map(|(server_name, (tool_name_map, _, adjustments))| {
+                tool_name_map.iter().map(move |(model_tool_name, tool_info)| {
+                    let aliased = adjustments.iter().any(|(host_tool_name, adjustment)| {
+                        *host_tool_name == tool_info.host_tool_name && matches!(adjustment, ToolAdjustment::Aliased(_))
+                    });
+                    (!aliased, server_name, model_tool_name, tool_info)
+                })
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by(|a, b| (a.0, a.1, a.2).cmp(&(b.0, b.1, b.2)));
 
-        let mut updated_servers = HashSet::<ToolOrigin>::new();
-        let mut conflicts = HashMap::<ServerName, String>::new();
-        for (server_name, (tool_name_map, specs)) in new_tools {
-            // First we evict the tools that were already in the tn_map
-            self.tn_map.retain(|_, tool_info| tool_info.server_name != server_name);
-
-            // And update them with the new tools queried
-            // valid: tools that do not have conflicts in naming
-            let (valid, invalid) = tool_name_map
-                .into_iter()
-                .partition::<HashMap<ModelToolName, ToolInfo>, _>(|(model_tool_name, _)| {
-                    !self.tn_map.contains_key(model_tool_name)
-                });
-            // We reject tools that are conflicting with the existing tools by not including them
-            // in the tn_map. We would also want to report this error.
-            if !invalid.is_empty() {
-                let msg = invalid.into_iter().fold("The following tools are rejected because they conflict with existing tools in names. Avoid this via setting aliases for them: \n".to_string(), |mut acc, (model_tool_name, tool_info)| {
-                    acc.push_str(&format!(" - {} from {}\n", model_tool_name, tool_info.server_name));
-                    acc
-                });
-                conflicts.insert(server_name, msg);
-            }
-            if let Some(spec) = specs.first() {
-                updated_servers.insert(spec.tool_origin.clone());
-            }
-            // We want to filter for specs that are valid
-            // Note that [ToolSpec::name] is a model facing name (thus you should be comparing it
-            // with the keys of a tn_map)
-            for spec in specs.into_iter().filter(|spec| valid.contains_key(&spec.name)) {
-                tool_specs.insert(spec.name.clone(), spec);
+        let mut tn_map = HashMap::<ModelToolName, ToolInfo>::new();
+        let mut conflicts = BTreeMap::<ServerName, Vec<(HostToolName, ToolAdjustment)>>::new();
+        for (_, server_name, model_tool_name, tool_info) in candidates {
+            let kept = if native_tool_names.contains(model_tool_name) {
+                Some("a built-in tool".to_string())
+            } else {
+                tn_map.get(model_tool_name).map(|existing| {
+                    format!(
+                        "@{}{MCP_SERVER_TOOL_DELIMITER}{}",
+                        existing.server_name, existing.host_tool_name
+                    )
+                })
+            };
+            match kept {
+                Some(kept) => conflicts.entry(server_name.clone()).or_default().push((
+                    tool_info.host_tool_name.clone(),
+                    ToolAdjustment::Conflict {
+                        name: model_tool_name.clone(),
+                        kept,
+                    },
+                )),
+                None => {
+                    tn_map.insert(model_tool_name.clone(), tool_info.clone());
+                },
             }
-
-            self.tn_map.extend(valid);
         }
 
-        // Update schema
-        // As we are writing over the ensemble of tools in a given server, we will need to first
-        // remove everything that it has.
-        self.schema
-            .retain(|_tool_name, spec| !updated_servers.contains(&spec.tool_origin));
-        self.schema.extend(tool_specs);
+        // Only specs whose names were kept for their server make it into the schema. Note that
+        // [ToolSpec::name] is a model facing name (thus you should be comparing it with the keys of
+        // a tn_map)
+        self.schema.retain(|_, spec| spec.tool_origin == ToolOrigin::Native);
+        for (server_name, (_, specs, _)) in &self.mcp_tools {
+            for spec in specs {
+                if tn_map
+                    .get(&spec.name)
+                    .is_some_and(|info| info.server_name == *server_name)
+                {
+                    self.schema.insert(spec.name.clone(), spec.clone());
+                }
+            }
+        }
+        self.tn_map = tn_map;
+
+        // Servers that finish loading after a tool set was picked are held to it as well
+        if let Some(tool_set) = &self.tool_set {
+            self.disabled_tools.extend(
+                self.tn_map
+                    .iter()
+                    .filter(|(_, info)| {
+                        updated_servers.contains(&info.server_name)
+                            && !tool_set_includes(
+                                tool_set,
+                                &ToolOrigin::McpServer(info.server_name.clone()),
+                                &info.host_tool_name,
+                            )
+                    })
+                    .map(|(model_tool_name, _)| model_tool_name.clone()),
+            );
+        }
 
-        // if block here to avoid repeatedly asking for loc
-        if !conflicts.is_empty() {
+        // Conflicts are reported in the load record of the servers that just loaded, so they are
+        // shown once rather than on every update
+        let new_conflicts = conflicts
+            .iter()
+            .filter(|(server_name, _)| updated_servers.contains(*server_name))
+            .collect::<Vec<_>>();
+        if !new_conflicts.is_empty() {
             let mut record_lock = self.mcp_load_record.lock().await;
-            for (server_name, msg) in conflicts {
+            for (server_name, tools) in new_conflicts {
+                let msg = tools.iter().fold(
+                    "The following tools are rejected because they conflict with existing tools in names. Avoid this via setting aliases for them: \n".to_string(),
+                    |mut acc, (host_tool_name, adjustment)| {
+                        acc.push_str(&format!(" - {host_tool_name} ({adjustment})\n"));
+                        acc
+                    },
+                );
                 let record = LoadingRecord::err(msg);
                 record_lock
-                    .entry(server_name)
+                    .entry(server_name.clone())
                     .and_modify(|v| v.push(record.clone()))
                     .or_insert(vec![record]);
             }
         }
+        self.tool_conflicts = conflicts;
+    }
+
+    /// Every adjustment made to the tools of each server, including conflicts, sorted by server and
+    /// tool name.
+    pub fn tool_adjustments(&self) -> BTreeMap<ServerName, Vec<(HostToolName, ToolAdjustment)>> {
+        let mut report = BTreeMap::<ServerName, Vec<(HostToolName, ToolAdjustment)>>::new();
+        for (server_name, (_, _, adjustments)) in &self.mcp_tools {
+            report
+                .entry(server_name.clone())
+                .or_default()
+                .extend(adjustments.iter().cloned());
+        }
+        for (server_name, conflicts) in &self.tool_conflicts {
+            report
+                .entry(server_name.clone())
+                .or_default()
+                .extend(conflicts.iter().cloned());
+        }
+        for adjustments in report.values_mut() {
+            adjustments.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+        report
     }
 
     pub async fn list_prompts(&self) -> Result<HashMap<String, Vec<PromptBundle>>, GetPromptError> {
@@ -1139,6 +1435,19 @@ impl ToolManager {
     pub async fn pending_clients(&self) -> Vec<String> {
         self.pending_clients.read().await.iter().cloned().collect::<Vec<_>>()
     }
+
+    /// Notifies the running MCP servers that the working directory changed, so that they can
+    /// request the new roots
+    pub async fn notify_roots_changed(&mut self) {
+        for (server_name, client) in self.clients.iter_mut() {
+            let Ok(running_service) = client.get_running_service().await else {
+                continue;
+            };
+            if let Err(e) = running_service.notify_roots_list_changed().await {
+                warn!("Failed to notify {server_name} of changed roots: {e}");
+            }
+        }
+    }
 }
 
 type DisplayTaskJoinHandle = JoinHandle<Result<(), eyre::Report>>;
@@ -1405,6 +1714,7 @@ fn spawn_orchestrator_task(
                     let time_taken = loading_servers
                         .remove(&server_name)
                         .map_or("0.0".to_owned(), |init_time| {
+                            startup_profile::record(format!("mcp server {server_name}"), init_time);
                             let time_taken = (std::time::Instant::now() - init_time).as_secs_f64().abs();
                             format!("{:.2}", time_taken)
                         });
@@ -1419,7 +1729,7 @@ fn spawn_orchestrator_task(
                         Err(_) => vec![],
                     };
 
-                    let (tool_filter, alias_list) = {
+                    let (tool_filter, alias_list, description_list) = {
                         let agent_lock = agent.lock().await;
 
                         // We will assume all tools are allowed if the tool list consists of 1
@@ -1449,20 +1759,10 @@ fn spawn_orchestrator_task(
                             }
                         };
 
-                        let server_prefix = format!("@{server_name}");
-                        let alias_list = agent_lock.tool_aliases.iter().fold(
-                            HashMap::<HostToolName, ModelToolName>::new(),
-                            |mut acc, (full_path, model_tool_name)| {
-                                if full_path.starts_with(&server_prefix) {
-                                    if let Some((_, host_tool_name)) = full_path.split_once(MCP_SERVER_TOOL_DELIMITER) {
-                                        acc.insert(host_tool_name.to_string(), model_tool_name.clone());
-                                    }
-                                }
-                                acc
-                            },
-                        );
+                        let alias_list = server_tool_overrides(&agent_lock.tool_aliases, &server_name);
+                        let description_list = server_tool_overrides(&agent_lock.tool_descriptions, &server_name);
 
-                        (tool_filter, alias_list)
+                        (tool_filter, alias_list, description_list)
                     };
 
                     match result {
@@ -1479,7 +1779,7 @@ fn spawn_orchestrator_task(
                                 return;
                             }
 
-                            let mut specs = result
+                            let (mut specs, excluded) = result
                                 .tools
                                 .into_iter()
                                 .map(|v| ToolSpec {
@@ -1488,7 +1788,10 @@ fn spawn_orchestrator_task(
                                     input_schema: crate::cli::chat::tools::InputSchema(v.schema_as_json_value()),
                                     tool_origin: ToolOrigin::Native,
                                 })
-                                .filter(|spec| tool_filter.should_include(&spec.name))
+                                .partition::<Vec<_>, _>(|spec| tool_filter.should_include(&spec.name));
+                            let mut adjustments = excluded
+                                .into_iter()
+                                .map(|spec| (spec.name, ToolAdjustment::NotIncluded))
                                 .collect::<Vec<_>>();
                             let mut sanitized_mapping = HashMap::<ModelToolName, ToolInfo>::new();
                             let process_result = process_tool_specs(
@@ -1498,6 +1801,8 @@ fn spawn_orchestrator_task(
                                 &mut specs,
                                 &mut sanitized_mapping,
                                 &alias_list,
+                                &description_list,
+                                &mut adjustments,
                                 regex,
                                 telemetry_clone,
                                 &result_tools,
@@ -1529,7 +1834,7 @@ fn spawn_orchestrator_task(
                             new_tool_specs
                                 .lock()
                                 .await
-                                .insert(server_name.clone(), (sanitized_mapping, specs));
+                                .insert(server_name.clone(), (sanitized_mapping, specs, adjustments));
                             has_new_stuff.store(true, Ordering::Release);
                             // Maintain a record of the server load:
                             let mut buf_writer = BufWriter::new(&mut *record_temp_buf);
@@ -1759,6 +2064,8 @@ async fn process_tool_specs(
     specs: &mut Vec<ToolSpec>,
     tn_map: &mut HashMap<ModelToolName, ToolInfo>,
     alias_list: &HashMap<HostToolName, ModelToolName>,
+    description_list: &HashMap<HostToolName, String>,
+    adjustments: &mut Vec<(HostToolName, ToolAdjustment)>,
     regex: &Regex,
     telemetry: &TelemetryThread,
     result_tools: &[String],
@@ -1782,29 +2089,55 @@ async fn process_tool_specs(
         None
     };
 
+    // Aliased tools are named first so they keep their names over tools whose names collide with
+    // them, and the rest in order of name so sanitized names are the same on every load.
+    specs.sort_by(|a, b| {
+        (!alias_list.contains_key(&a.name), &a.name).cmp(&(!alias_list.contains_key(&b.name), &b.name))
+    });
+
     for spec in specs.iter_mut() {
-        let model_tool_name = alias_list.get(&spec.name).cloned().unwrap_or({
-            if !regex.is_match(&spec.name) {
+        let model_tool_name = match alias_list.get(&spec.name) {
+            Some(alias) => {
+                if *alias != spec.name {
+                    adjustments.push((spec.name.clone(), ToolAdjustment::Aliased(alias.clone())));
+                }
+                alias.clone()
+            },
+            None if !regex.is_match(&spec.name) => {
                 let mut sn = sanitize_name(spec.name.clone(), regex, &mut hasher);
                 while tn_map.contains_key(&sn) {
                     sn.push('1');
                 }
+                adjustments.push((spec.name.clone(), ToolAdjustment::Sanitized(sn.clone())));
                 sn
-            } else {
-                spec.name.clone()
-            }
-        });
+            },
+            None => spec.name.clone(),
+        };
+        if let Some(description) = description_list.get(&spec.name) {
+            spec.description = description.clone();
+            adjustments.push((spec.name.clone(), ToolAdjustment::DescriptionOverridden));
+        }
         if model_tool_name.len() > 64 {
             out_of_spec_tool_names.push(ToolValidationViolation::TooLong(spec.name.clone()));
+            adjustments.push((spec.name.clone(), ToolAdjustment::NameTooLong(model_tool_name)));
             continue;
         } else if spec.description.is_empty() {
             out_of_spec_tool_names.push(ToolValidationViolation::EmptyDescription(spec.name.clone()));
+            adjustments.push((spec.name.clone(), ToolAdjustment::EmptyDescription));
+            continue;
+        }
+        if let Some(existing) = tn_map.get(&model_tool_name) {
+            adjustments.push((spec.name.clone(), ToolAdjustment::Conflict {
+                name: model_tool_name,
+                kept: format!("@{server_name}{MCP_SERVER_TOOL_DELIMITER}{}", existing.host_tool_name),
+            }));
             continue;
         }
 
         if spec.description.len() > 10_004 {
             spec.description.truncate(10_004);
             out_of_spec_tool_names.push(ToolValidationViolation::DescriptionTooLong(spec.name.clone()));
+            adjustments.push((spec.name.clone(), ToolAdjustment::DescriptionTruncated));
         }
 
         tn_map.insert(model_tool_name.clone(), ToolInfo {
@@ -1873,6 +2206,50 @@ async fn process_tool_specs(
     }
 }
 
+/// The entries of `overrides` (such as the agent's tool aliases) for the tools of `server_name`,
+/// keyed by the tool name without the server prefix.
+fn server_tool_overrides(
+    overrides: &HashMap<OriginalToolName, String>,
+    server_name: &str,
+) -> HashMap<HostToolName, String> {
+    overrides
+        .iter()
+        .filter_map(|(full_path, value)| {
+            let (server, host_tool_name) = full_path.strip_prefix('@')?.split_once(MCP_SERVER_TOOL_DELIMITER)?;
+            (server == server_name).then(|| (host_tool_name.to_string(), value.clone()))
+        })
+        .collect()
+}
+
+/// Whether a tool set, written like the tools field of an agent, includes the tool.
+pub fn tool_set_includes(tool_set: &[String], origin: &ToolOrigin, host_tool_name: &str) -> bool {
+    let (source_ref, config_ref) = match origin {
+        ToolOrigin::Native => (
+            "@builtin".to_string(),
+            format!("@builtin{MCP_SERVER_TOOL_DELIMITER}{host_tool_name}"),
+        ),
+        ToolOrigin::McpServer(server_name) => (
+            format!("@{server_name}"),
+            format!("@{server_name}{MCP_SERVER_TOOL_DELIMITER}{host_tool_name}"),
+        ),
+    };
+    tool_set.iter().any(|entry| {
+        entry == "*"
+            || *entry == source_ref
+            || *entry == config_ref
+            || (*origin == ToolOrigin::Native && entry == host_tool_name)
+    })
+}
+
+/// Whether a tool set, written like the tools field of an agent, includes any tool of the server.
+pub fn tool_set_includes_server(tool_set: &[String], server_name: &str) -> bool {
+    let source_ref = format!("@{server_name}");
+    let tool_prefix = format!("{source_ref}{MCP_SERVER_TOOL_DELIMITER}");
+    tool_set
+        .iter()
+        .any(|entry| entry == "*" || *entry == source_ref || entry.starts_with(&tool_prefix))
+}
+
 fn sanitize_name(orig: String, regex: &regex::Regex, hasher: &mut impl Hasher) -> String {
     if regex.is_match(&orig) && !orig.contains(NAMESPACE_DELIMITER) {
         return orig;
@@ -2202,4 +2579,127 @@ mod tests {
         );
         assert_eq!(result, Some(expected_map));
     }
+
+    fn server_tools(server_name: &str, tools: &[(&str, &str)], aliased: &[&str]) -> ServerTools {
+        let mut tool_name_map = HashMap::new();
+        let mut specs = Vec::new();
+        let mut adjustments = Vec::new();
+        for (host_tool_name, model_tool_name) in tools {
+            tool_name_map.insert(model_tool_name.to_string(), ToolInfo {
+                server_name: server_name.to_string(),
+                host_tool_name: host_tool_name.to_string(),
+            });
+            specs.push(ToolSpec {
+                name: model_tool_name.to_string(),
+                description: "a tool".to_string(),
+                input_schema: crate::cli::chat::tools::InputSchema(serde_json::json!({})),
+                tool_origin: ToolOrigin::McpServer(server_name.to_string()),
+            });
+            if aliased.contains(host_tool_name) {
+                adjustments.push((
+                    host_tool_name.to_string(),
+                    ToolAdjustment::Aliased(model_tool_name.to_string()),
+                ));
+            }
+        }
+        (tool_name_map, specs, adjustments)
+    }
+
+    #[tokio::test]
+    async fn test_update_resolves_conflicts_deterministically() {
+        let mut manager = ToolManager::default();
+        manager.schema.insert("fs_read".to_string(), ToolSpec {
+            name: "fs_read".to_string(),
+            description: "built-in".to_string(),
+            input_schema: crate::cli::chat::tools::InputSchema(serde_json::json!({})),
+            tool_origin: ToolOrigin::Native,
+        });
+
+        // The later server loads first, and aliases its tool to the name the other one uses
+        manager.new_tool_specs.lock().await.insert(
+            "zeta".to_string(),
+            server_tools("zeta", &[("get", "get"), ("find", "search")], &["find"]),
+        );
+        manager.update().await;
+        manager.new_tool_specs.lock().await.insert(
+            "alpha".to_string(),
+            server_tools(
+                "alpha",
+                &[("get", "get"), ("search", "search"), ("fs_read", "fs_read")],
+                &[],
+            ),
+        );
+        manager.update().await;
+
+        assert_eq!(manager.tn_map["get"].server_name, "alpha");
+        assert_eq!(manager.tn_map["search"].server_name, "zeta");
+        assert!(manager.tn_map.get("fs_read").is_none());
+        assert_eq!(manager.schema["fs_read"].tool_origin, ToolOrigin::Native);
+        assert_eq!(
+            manager.schema["get"].tool_origin,
+            ToolOrigin::McpServer("alpha".to_string())
+        );
+
+        let adjustments = manager.tool_adjustments();
+        let alpha = adjustments["alpha"]
+            .iter()
+            .map(|(name, adjustment)| (name.as_str(), adjustment.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(alpha, vec![
+            (
+                "fs_read",
+                "name fs_read is already used by a built-in tool, set an alias for it".to_string()
+            ),
+            (
+                "search",
+                "name search is already used by @zeta/find, set an alias for it".to_string()
+            ),
+        ]);
+        assert!(matches!(adjustments["zeta"].as_slice(), [
+            (_, ToolAdjustment::Aliased(_)),
+            (_, ToolAdjustment::Conflict { .. })
+        ]));
+        assert!(!adjustments["zeta"][0].1.is_filtered());
+        assert!(adjustments["zeta"][1].1.is_filtered());
+    }
+
+    #[test]
+    fn test_server_tool_overrides() {
+        let overrides: HashMap<OriginalToolName, String> = serde_json::from_value(serde_json::json!({
+            "@git/git_status": "status",
+            "@git-extra/git_log": "log",
+            "fs_read": "read",
+        }))
+        .unwrap();
+        assert_eq!(
+            server_tool_overrides(&overrides, "git"),
+            HashMap::from([("git_status".to_string(), "status".to_string())])
+        );
+        assert!(server_tool_overrides(&overrides, "fs").is_empty());
+    }
+
+    #[test]
+    fn test_tool_set_includes_server() {
+        let set = |entries: &[&str]| entries.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert!(tool_set_includes_server(&set(&["*"]), "git"));
+        assert!(tool_set_includes_server(&set(&["fs_read", "@git"]), "git"));
+        assert!(tool_set_includes_server(&set(&["@git/git_log"]), "git"));
+        assert!(!tool_set_includes_server(&set(&["@builtin", "@git-extra"]), "git"));
+        assert!(!tool_set_includes_server(&set(&["@git-extra/git_log"]), "git"));
+    }
+
+    #[tokio::test]
+    async fn test_update_applies_tool_set_to_servers_loaded_later() {
+        let mut manager = ToolManager {
+            tool_set: Some(vec!["@builtin".to_string(), "@git/git_log".to_string()]),
+            ..Default::default()
+        };
+        manager.new_tool_specs.lock().await.insert(
+            "git".to_string(),
+            server_tools("git", &[("git_log", "git_log"), ("git_status", "git_status")], &[]),
+        );
+        manager.update().await;
+
+        assert_eq!(manager.disabled_tools, HashSet::from(["git_status".to_string()]));
+    }
 }
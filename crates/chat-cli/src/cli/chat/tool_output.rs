@@ -0,0 +1,360 @@
+//! Reduces tool results that are too large to be added to the conversation as is.
+//!
+//! Without this, a single large result (a verbose build log, a huge file) can take up most of the
+//! context window and force the conversation to be compacted.
+
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+use eyre::{
+    Result,
+    bail,
+};
+use regex::Regex;
+use tracing::warn;
+
+use super::token_counter::TokenCounter;
+use super::tools::{
+    InvokeOutput,
+    OutputKind,
+};
+use super::util::truncate_safe;
+use crate::database::settings::Setting;
+use crate::os::Os;
+
+/// Default size above which tool results are reduced, in tokens
+pub const DEFAULT_TOKEN_THRESHOLD: usize = 25_000;
+
+/// The most a result is shrunk to before being summarized, relative to the threshold, so that
+/// the summarization request itself stays cheap.
+const SUMMARY_INPUT_THRESHOLD_MULTIPLIER: usize = 4;
+
+/// Lines that likely report a problem, which the [ReductionStrategy::Extract] strategy keeps
+static DIAGNOSTIC_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\b(error|errors|warning|warnings|warn|fail|failed|failure|fatal|panic|panicked|exception|traceback)\b",
+    )
+    .expect("diagnostic line regex must be valid")
+});
+
+/// Number of lines kept around each line matching [DIAGNOSTIC_LINE]
+const DIAGNOSTIC_CONTEXT_LINES: usize = 1;
+
+/// How tool results above the threshold are reduced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReductionStrategy {
+    /// Keep the beginning and the end of the result
+    #[default]
+    Truncate,
+    /// Keep the lines reporting errors or warnings
+    Extract,
+    /// Replace the result with a summary written by a model
+    Summarize,
+}
+
+impl FromStr for ReductionStrategy {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "truncate" => Ok(Self::Truncate),
+            "extract" => Ok(Self::Extract),
+            "summarize" => Ok(Self::Summarize),
+            other => bail!("unknown tool output strategy '{other}', expected one of: truncate, extract, summarize"),
+        }
+    }
+}
+
+impl Display for ReductionStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Truncate => "truncated",
+            Self::Extract => "filtered to errors and warnings",
+            Self::Summarize => "summarized",
+        })
+    }
+}
+
+/// A tool result that was reduced to fit under the threshold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReducedOutput {
+    pub text: String,
+    /// The strategy that was actually applied, which differs from the configured one when it
+    /// failed or had nothing to work with.
+    pub strategy: ReductionStrategy,
+    pub original_tokens: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolOutputReducer {
+    pub token_threshold: usize,
+    pub strategy: ReductionStrategy,
+    /// Model used by [ReductionStrategy::Summarize], defaults to the model of the conversation
+    pub summary_model: Option<String>,
+}
+
+impl Default for ToolOutputReducer {
+    fn default() -> Self {
+        Self {
+            token_threshold: DEFAULT_TOKEN_THRESHOLD,
+            strategy: ReductionStrategy::default(),
+            summary_model: None,
+        }
+    }
+}
+
+impl ToolOutputReducer {
+    pub fn from_settings(os: &Os) -> Self {
+        let settings = &os.database.settings;
+        let strategy = settings
+            .get_string(Setting::ChatToolOutputStrategy)
+            .map(|strategy| {
+                strategy.parse().unwrap_or_else(|err| {
+                    warn!(?err, "invalid tool output strategy, using the default");
+                    ReductionStrategy::default()
+                })
+            })
+            .unwrap_or_default();
+
+        Self {
+            token_threshold: settings.get_int_or(Setting::ChatToolOutputTokenThreshold, DEFAULT_TOKEN_THRESHOLD),
+            strategy,
+            summary_model: settings.get_string(Setting::ChatToolOutputSummaryModel),
+        }
+    }
+
+    /// Reduces `output` if it's above the threshold, returning [None] if it can be used as is.
+    ///
+    /// `model_id` is the model of the conversation, used to summarize when no summary model is
+    /// configured.
+    pub async fn reduce(
+        &self,
+        os: &Os,
+        tool_name: &str,
+        output: &InvokeOutput,
+        model_id: Option<&str>,
+    ) -> Option<ReducedOutput> {
+        if !matches!(output.output, OutputKind::Text(_) | OutputKind::Json(_)) {
+            return None;
+        }
+        let text = output.as_str();
+        let original_tokens = TokenCounter::count_tokens(&text);
+        if original_tokens <= self.token_threshold {
+            return None;
+        }
+
+        let max_bytes = TokenCounter::token_to_chars(self.token_threshold);
+        let (text, strategy) = match self.strategy {
+            ReductionStrategy::Truncate => (truncate_middle(&text, max_bytes), ReductionStrategy::Truncate),
+            ReductionStrategy::Extract => match extract_diagnostics(&text, max_bytes) {
+                Some(extracted) => (extracted, ReductionStrategy::Extract),
+                None => (truncate_middle(&text, max_bytes), ReductionStrategy::Truncate),
+            },
+            ReductionStrategy::Summarize => {
+                let model_id = self.summary_model.as_deref().or(model_id);
+                let input = truncate_middle(&text, max_bytes * SUMMARY_INPUT_THRESHOLD_MULTIPLIER);
+                match summarize(os, tool_name, &input, model_id).await {
+                    Ok(summary) => (truncate_middle(&summary, max_bytes), ReductionStrategy::Summarize),
+                    Err(err) => {
+                        warn!(?err, "failed to summarize tool output, truncating it instead");
+                        (truncate_middle(&text, max_bytes), ReductionStrategy::Truncate)
+                    },
+                }
+            },
+        };
+
+        Some(ReducedOutput {
+            text,
+            strategy,
+            original_tokens,
+        })
+    }
+}
+
+/// Keeps the beginning and the end of `text`, cut on line boundaries where possible, so that the
+/// result is at most about `max_bytes` long.
+pub fn truncate_middle(text: &str, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text.to_string();
+    }
+
+    let half = max_bytes / 2;
+    let head = truncate_safe(text, half);
+    let head = head.rfind('\n').map_or(head, |i| &head[..i]);
+
+    let mut tail_start = text.len() - half;
+    while !text.is_char_boundary(tail_start) {
+        tail_start += 1;
+    }
+    let tail = &text[tail_start..];
+    let tail = tail.find('\n').map_or(tail, |i| &tail[i + 1..]);
+
+    let omitted = &text[head.len()..text.len() - tail.len()];
+    format!(
+        "{head}\n\n[... {} lines ({} bytes) omitted ...]\n\n{tail}",
+        omitted.lines().count(),
+        omitted.len()
+    )
+}
+
+/// Keeps the lines of `text` that report errors or warnings, along with the lines around them.
+/// Returns [None] if there are no such lines.
+pub fn extract_diagnostics(text: &str, max_bytes: usize) -> Option<String> {
+    let lines = text.lines().collect::<Vec<_>>();
+    let mut keep = vec![false; lines.len()];
+    let mut matches = 0;
+    for (i, line) in lines.iter().enumerate() {
+        if DIAGNOSTIC_LINE.is_match(line) {
+            matches += 1;
+            let start = i.saturating_sub(DIAGNOSTIC_CONTEXT_LINES);
+            let end = (i + DIAGNOSTIC_CONTEXT_LINES).min(lines.len() - 1);
+            keep[start..=end].fill(true);
+        }
+    }
+    if matches == 0 {
+        return None;
+    }
+
+    let mut extracted = format!(
+        "Kept {matches} of {} lines reporting errors or warnings, with {DIAGNOSTIC_CONTEXT_LINES} line(s) of context:\n",
+        lines.len()
+    );
+    let mut previous = None;
+    for (i, line) in lines.iter().enumerate().filter(|(i, _)| keep[*i]) {
+        if previous.is_some_and(|previous| previous + 1 != i) {
+            extracted.push_str("...\n");
+        }
+        extracted.push_str(line);
+        extracted.push('\n');
+        previous = Some(i);
+    }
+    Some(truncate_middle(&extracted, max_bytes))
+}
+
+async fn summarize(os: &Os, tool_name: &str, text: &str, model_id: Option<&str>) -> Result<String> {
+    let content = format!(
+        "The following is the output of the `{tool_name}` tool, which is too large to use as is. \
+        Summarize it for another assistant that needs it to continue its task. Keep errors, \
+        warnings, file paths, identifiers, and numbers verbatim, and say what was left out. \
+        Reply with the summary only.\n\n<output>\n{text}\n</output>"
+    );
+    let summary = os
+        .client
+        .send_prompt(content, model_id.map(str::to_string))
+        .await?
+        .text()
+        .await?;
+    if summary.trim().is_empty() {
+        bail!("the summary is empty");
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbered_lines(n: usize) -> String {
+        (0..n).map(|i| format!("line {i}\n")).collect()
+    }
+
+    fn reducer(strategy: ReductionStrategy) -> ToolOutputReducer {
+        ToolOutputReducer {
+            token_threshold: 100,
+            strategy,
+            summary_model: None,
+        }
+    }
+
+    fn text_output(text: String) -> InvokeOutput {
+        InvokeOutput {
+            output: OutputKind::Text(text),
+        }
+    }
+
+    #[test]
+    fn test_parse_strategy() {
+        assert_eq!(
+            "Summarize".parse::<ReductionStrategy>().unwrap(),
+            ReductionStrategy::Summarize
+        );
+        assert_eq!(
+            "extract".parse::<ReductionStrategy>().unwrap(),
+            ReductionStrategy::Extract
+        );
+        assert!("shrink".parse::<ReductionStrategy>().is_err());
+    }
+
+    #[test]
+    fn test_truncate_middle() {
+        let text = numbered_lines(1000);
+        let truncated = truncate_middle(&text, 200);
+        assert!(truncated.len() < 300);
+        assert!(truncated.starts_with("line 0\n"));
+        assert!(truncated.ends_with("line 999\n"));
+        assert!(truncated.contains("lines ("));
+
+        assert_eq!(truncate_middle("short", 200), "short");
+        // Never splits a character.
+        assert!(truncate_middle(&"é".repeat(100), 51).len() <= 51 + 50);
+    }
+
+    #[test]
+    fn test_extract_diagnostics() {
+        let mut text = numbered_lines(500);
+        text.push_str("error[E0308]: mismatched types\n");
+        text.push_str(&numbered_lines(500));
+        text.push_str("warning: unused variable\n");
+
+        let extracted = extract_diagnostics(&text, 10_000).unwrap();
+        assert!(extracted.starts_with("Kept 2 of 1002 lines"));
+        assert!(extracted.contains("line 499\nerror[E0308]: mismatched types\nline 0\n...\n"));
+        assert!(extracted.ends_with("line 499\nwarning: unused variable\n"));
+
+        assert!(extract_diagnostics(&numbered_lines(10), 10_000).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reduce() {
+        let os = Os::new().await.unwrap();
+        let small = text_output("small".to_string());
+        assert!(
+            reducer(ReductionStrategy::Truncate)
+                .reduce(&os, "tool", &small, None)
+                .await
+                .is_none()
+        );
+
+        let large = text_output(numbered_lines(1000));
+        let reduced = reducer(ReductionStrategy::Truncate)
+            .reduce(&os, "tool", &large, None)
+            .await
+            .unwrap();
+        assert_eq!(reduced.strategy, ReductionStrategy::Truncate);
+        assert!(reduced.text.len() < large.as_str().len());
+        assert!(reduced.original_tokens > 100);
+
+        // Nothing to extract falls back to truncating.
+        let reduced = reducer(ReductionStrategy::Extract)
+            .reduce(&os, "tool", &large, None)
+            .await
+            .unwrap();
+        assert_eq!(reduced.strategy, ReductionStrategy::Truncate);
+    }
+
+    #[tokio::test]
+    async fn test_reduce_summarize() {
+        let mut os = Os::new().await.unwrap();
+        os.client
+            .set_mock_output(serde_json::json!([["1000 numbered lines, no errors"]]));
+
+        let large = text_output(numbered_lines(1000));
+        let reduced = reducer(ReductionStrategy::Summarize)
+            .reduce(&os, "tool", &large, Some("model"))
+            .await
+            .unwrap();
+        assert_eq!(reduced.strategy, ReductionStrategy::Summarize);
+        assert_eq!(reduced.text, "1000 numbered lines, no errors");
+    }
+}
@@ -0,0 +1,445 @@
+use std::fs::File;
+use std::io::{
+    Read,
+    Write,
+};
+use std::path::Path;
+
+use crossterm::queue;
+use crossterm::style::{
+    self,
+};
+use eyre::{
+    Result,
+    bail,
+};
+use flate2::read::GzDecoder;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::{
+    InvokeOutput,
+    MAX_TOOL_RESPONSE_SIZE,
+    OutputKind,
+    sanitize_path_tool_arg,
+};
+use crate::cli::agent::{
+    Agent,
+    PermissionEvalResult,
+};
+use crate::cli::chat::sanitize_unicode_tags;
+use crate::cli::chat::util::binary;
+use crate::os::Os;
+use crate::theme::StyledText;
+
+/// Entries listed beyond this are counted but not returned.
+const MAX_LISTED_ENTRIES: usize = 1000;
+/// Bytes of a binary member shown as a hex dump.
+const BINARY_PREVIEW_LEN: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum ArchiveFormat {
+    Zip,
+    Tar,
+    #[serde(rename = "tar.gz")]
+    TarGz,
+}
+
+impl ArchiveFormat {
+    /// Detects the format from the magic bytes at the start of the file, so that zip based formats
+    /// such as jar, whl and vsix work regardless of their extension.
+    fn detect(path: &Path) -> Result<Self> {
+        let mut sample = Vec::new();
+        File::open(path)?
+            .take(binary::SNIFF_LEN as u64)
+            .read_to_end(&mut sample)?;
+        match binary::detect_file_type(&sample).map(|t| t.description) {
+            Some("Zip archive") => Ok(Self::Zip),
+            Some("tar archive") => Ok(Self::Tar),
+            Some("gzip compressed data") => Ok(Self::TarGz),
+            _ => bail!("'{}' is not a zip, tar or gzipped tar archive", path.display()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ArchiveEntry {
+    name: String,
+    size: u64,
+    is_dir: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ArchiveListing {
+    format: ArchiveFormat,
+    entry_count: usize,
+    total_size: u64,
+    entries: Vec<ArchiveEntry>,
+    truncated: bool,
+}
+
+/// Lists the entries of a zip or tar archive without extracting it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ArchiveList {
+    pub path: String,
+    /// Only list entries whose names start with this prefix
+    #[serde(default)]
+    pub prefix: Option<String>,
+}
+
+impl ArchiveList {
+    pub async fn validate(&mut self, os: &Os) -> Result<()> {
+        validate_archive_path(os, &self.path).await
+    }
+
+    pub fn queue_description(&self, output: &mut impl Write) -> Result<()> {
+        queue!(
+            output,
+            style::Print("Listing archive: "),
+            StyledText::success_fg(),
+            style::Print(&self.path),
+            StyledText::reset(),
+        )?;
+        if let Some(prefix) = &self.prefix {
+            queue!(
+                output,
+                style::Print(" under "),
+                StyledText::success_fg(),
+                style::Print(prefix),
+                StyledText::reset(),
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn eval_perm(&self, os: &Os, agent: &Agent) -> PermissionEvalResult {
+        super::eval_read_only_perm(os, agent, "archive_list", &self.path)
+    }
+
+    pub async fn invoke(&self, os: &Os, updates: &mut impl Write) -> Result<InvokeOutput> {
+        let path = sanitize_path_tool_arg(os, &self.path);
+        let format = ArchiveFormat::detect(&path)?;
+        let prefix = self.prefix.clone().unwrap_or_default();
+
+        let file = File::open(&path)?;
+        let listing = tokio::task::spawn_blocking(move || -> Result<ArchiveListing> {
+            let mut listing = ArchiveListing {
+                format,
+                entry_count: 0,
+                total_size: 0,
+                entries: Vec::new(),
+                truncated: false,
+            };
+            let mut add = |entry: ArchiveEntry| {
+                if !entry.name.starts_with(&prefix) {
+                    return;
+                }
+                listing.entry_count += 1;
+                listing.total_size += entry.size;
+                if listing.entries.len() < MAX_LISTED_ENTRIES {
+                    listing.entries.push(entry);
+                } else {
+                    listing.truncated = true;
+                }
+            };
+
+            match format {
+                ArchiveFormat::Zip => {
+                    let mut archive = zip::ZipArchive::new(file)?;
+                    for i in 0..archive.len() {
+                        // Raw access reads the central directory without decompressing anything.
+                        let entry = archive.by_index_raw(i)?;
+                        add(ArchiveEntry {
+                            name: entry.name().to_string(),
+                            size: entry.size(),
+                            is_dir: entry.is_dir(),
+                        });
+                    }
+                },
+                ArchiveFormat::Tar | ArchiveFormat::TarGz => {
+                    let mut archive = tar_archive(file, format);
+                    for entry in archive.entries()? {
+                        let entry = entry?;
+                        add(ArchiveEntry {
+                            name: entry.path()?.to_string_lossy().into_owned(),
+                            size: entry.header().size()?,
+                            is_dir: entry.header().entry_type().is_dir(),
+                        });
+                    }
+                },
+            }
+            Ok(listing)
+        })
+        .await??;
+
+        super::queue_function_result(
+            &format!("Found {} entries in {}", listing.entry_count, path.display()),
+            updates,
+            false,
+            false,
+        )?;
+
+        Ok(InvokeOutput {
+            output: OutputKind::Text(serde_json::to_string(&listing)?),
+        })
+    }
+}
+
+/// Reads a single member of a zip or tar archive into memory, without extracting it to disk.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ArchiveReadMember {
+    pub path: String,
+    /// Name of the member as returned by archive_list
+    pub member: String,
+}
+
+impl ArchiveReadMember {
+    pub async fn validate(&mut self, os: &Os) -> Result<()> {
+        validate_archive_path(os, &self.path).await?;
+        if self.member.is_empty() {
+            bail!("member cannot be empty");
+        }
+        Ok(())
+    }
+
+    pub fn queue_description(&self, output: &mut impl Write) -> Result<()> {
+        queue!(
+            output,
+            style::Print("Reading "),
+            StyledText::success_fg(),
+            style::Print(&self.member),
+            StyledText::reset(),
+            style::Print(" from archive: "),
+            StyledText::success_fg(),
+            style::Print(&self.path),
+            StyledText::reset(),
+        )?;
+        Ok(())
+    }
+
+    pub fn eval_perm(&self, os: &Os, agent: &Agent) -> PermissionEvalResult {
+        super::eval_read_only_perm(os, agent, "archive_read_member", &self.path)
+    }
+
+    pub async fn invoke(&self, os: &Os, updates: &mut impl Write) -> Result<InvokeOutput> {
+        let path = sanitize_path_tool_arg(os, &self.path);
+        let format = ArchiveFormat::detect(&path)?;
+        let member = self.member.clone();
+
+        let file = File::open(&path)?;
+        let read = tokio::task::spawn_blocking(move || -> Result<Option<(u64, Vec<u8>)>> {
+            match format {
+                ArchiveFormat::Zip => {
+                    let mut archive = zip::ZipArchive::new(file)?;
+                    let Some(index) = archive.index_for_name(&member) else {
+                        return Ok(None);
+                    };
+                    let entry = archive.by_index(index)?;
+                    Ok(Some((entry.size(), read_capped(entry)?)))
+                },
+                ArchiveFormat::Tar | ArchiveFormat::TarGz => {
+                    let mut archive = tar_archive(file, format);
+                    for entry in archive.entries()? {
+                        let entry = entry?;
+                        if entry.path()?.to_string_lossy() == member {
+                            return Ok(Some((entry.header().size()?, read_capped(entry)?)));
+                        }
+                    }
+                    Ok(None)
+                },
+            }
+        })
+        .await??;
+
+        let Some((size, bytes)) = read else {
+            bail!(
+                "'{}' is not a member of {}. Use archive_list to see the available members.",
+                self.member,
+                self.path
+            );
+        };
+
+        let contents = if binary::is_binary(&bytes) {
+            let description = binary::detect_file_type(&bytes).map_or("unrecognized binary data", |t| t.description);
+            let shown = bytes.len().min(BINARY_PREVIEW_LEN);
+            format!(
+                "'{}' is a binary file ({description}, {size} bytes). The first {shown} bytes are:\n{}",
+                self.member,
+                binary::hex_dump(&bytes[..shown], 0)
+            )
+        } else {
+            let mut text = sanitize_unicode_tags(&String::from_utf8_lossy(&bytes));
+            if (bytes.len() as u64) < size {
+                text.push_str(&format!(
+                    "\n\n[... truncated, showing {} of {size} bytes ...]",
+                    bytes.len()
+                ));
+            }
+            text
+        };
+
+        super::queue_function_result(
+            &format!("Successfully read {} bytes of {}", bytes.len(), self.member),
+            updates,
+            false,
+            false,
+        )?;
+
+        Ok(InvokeOutput {
+            output: OutputKind::Text(contents),
+        })
+    }
+}
+
+async fn validate_archive_path(os: &Os, path: &str) -> Result<()> {
+    let sanitized = sanitize_path_tool_arg(os, path);
+    if !sanitized.exists() {
+        bail!("'{}' does not exist", path);
+    }
+    if !os.fs.symlink_metadata(&sanitized).await?.is_file() {
+        bail!("'{}' is not a file", path);
+    }
+    ArchiveFormat::detect(&sanitized)?;
+    Ok(())
+}
+
+fn tar_archive(file: File, format: ArchiveFormat) -> tar::Archive<Box<dyn Read + Send>> {
+    let reader: Box<dyn Read + Send> = match format {
+        ArchiveFormat::TarGz => Box::new(GzDecoder::new(file)),
+        _ => Box::new(file),
+    };
+    tar::Archive::new(reader)
+}
+
+/// Reads at most [MAX_TOOL_RESPONSE_SIZE] bytes, so that a highly compressed member can't exhaust
+/// memory no matter what size the archive claims it has.
+fn read_capped(reader: impl Read) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.take(MAX_TOOL_RESPONSE_SIZE as u64).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use zip::ZipWriter;
+    use zip::write::SimpleFileOptions;
+
+    use super::*;
+
+    fn text(output: Result<InvokeOutput>) -> String {
+        match output.unwrap().output {
+            OutputKind::Text(text) => text,
+            _ => panic!("expected text output"),
+        }
+    }
+
+    fn write_zip(os: &Os, path: &str) {
+        let file = File::create(os.fs.chroot_path(path)).unwrap();
+        let mut writer = ZipWriter::new(file);
+        writer.add_directory("src/", SimpleFileOptions::default()).unwrap();
+        writer.start_file("src/main.rs", SimpleFileOptions::default()).unwrap();
+        writer.write_all(b"fn main() {}\n").unwrap();
+        writer.start_file("bin/tool", SimpleFileOptions::default()).unwrap();
+        writer.write_all(b"\x7fELF\x02\x01\x01\x00\x00\x00").unwrap();
+        writer.finish().unwrap();
+    }
+
+    fn write_tar_gz(os: &Os, path: &str) {
+        let file = File::create(os.fs.chroot_path(path)).unwrap();
+        let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+        let contents = "hello from tar\n".repeat(50_000);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "pkg/README.md", contents.as_bytes())
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_archive_list() {
+        let os = Os::new().await.unwrap();
+        let mut stdout = std::io::stdout();
+        write_zip(&os, "/artifact.jar");
+        write_tar_gz(&os, "/package.tgz");
+
+        let mut list = serde_json::from_value::<ArchiveList>(serde_json::json!({ "path": "/artifact.jar" })).unwrap();
+        list.validate(&os).await.unwrap();
+        let text = text(list.invoke(&os, &mut stdout).await);
+        let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(value["format"], "zip");
+        assert_eq!(value["entry_count"], 3);
+        assert_eq!(
+            value["entries"][0],
+            serde_json::json!({ "name": "src/", "size": 0, "is_dir": true })
+        );
+        assert_eq!(value["truncated"], false);
+
+        let list =
+            serde_json::from_value::<ArchiveList>(serde_json::json!({ "path": "/artifact.jar", "prefix": "bin/" }))
+                .unwrap();
+        let text = text(list.invoke(&os, &mut stdout).await);
+        let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(value["entry_count"], 1);
+        assert_eq!(value["entries"][0]["name"], "bin/tool");
+
+        let list = serde_json::from_value::<ArchiveList>(serde_json::json!({ "path": "/package.tgz" })).unwrap();
+        let text = text(list.invoke(&os, &mut stdout).await);
+        let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(value["format"], "tar.gz");
+        assert_eq!(value["entries"][0]["name"], "pkg/README.md");
+        assert_eq!(value["entries"][0]["size"], 750_000);
+    }
+
+    #[tokio::test]
+    async fn test_archive_read_member() {
+        let os = Os::new().await.unwrap();
+        let mut stdout = std::io::stdout();
+        write_zip(&os, "/artifact.zip");
+        write_tar_gz(&os, "/package.tar.gz");
+
+        let read = |path: &str, member: &str| {
+            serde_json::from_value::<ArchiveReadMember>(serde_json::json!({ "path": path, "member": member })).unwrap()
+        };
+
+        let text = text(read("/artifact.zip", "src/main.rs").invoke(&os, &mut stdout).await);
+        assert_eq!(text, "fn main() {}\n");
+
+        let text = text(read("/artifact.zip", "bin/tool").invoke(&os, &mut stdout).await);
+        assert!(text.contains("ELF executable, 10 bytes"), "{text}");
+        assert!(text.contains("00000000: 7f45 4c46"), "{text}");
+
+        // Large members are capped rather than read in full.
+        let text = text(read("/package.tar.gz", "pkg/README.md").invoke(&os, &mut stdout).await);
+        assert!(text.starts_with("hello from tar\n"));
+        assert!(
+            text.ends_with(&format!(
+                "[... truncated, showing {MAX_TOOL_RESPONSE_SIZE} of 750000 bytes ...]"
+            )),
+            "{}",
+            &text[text.len() - 100..]
+        );
+
+        assert!(
+            read("/artifact.zip", "missing.txt")
+                .invoke(&os, &mut stdout)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_rejects_non_archives() {
+        let os = Os::new().await.unwrap();
+        os.fs.write("/notes.zip", "not really a zip").await.unwrap();
+        let mut list = serde_json::from_value::<ArchiveList>(serde_json::json!({ "path": "/notes.zip" })).unwrap();
+        assert!(list.validate(&os).await.is_err());
+    }
+}
@@ -1,18 +1,25 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::io::Write;
+use std::sync::Arc;
+use std::time::{
+    Duration,
+    Instant,
+};
 
 use crossterm::{
     queue,
     style,
 };
 use eyre::Result;
+use rmcp::ServiceError;
 use rmcp::model::CallToolRequestParam;
 use schemars::JsonSchema;
 use serde::{
     Deserialize,
     Serialize,
 };
+use tokio::sync::Mutex;
 use tracing::warn;
 
 use super::InvokeOutput;
@@ -22,6 +29,7 @@ use crate::cli::agent::{
 };
 use crate::cli::chat::CONTINUATION_LINE;
 use crate::cli::chat::token_counter::TokenCounter;
+use crate::cli::chat::tool_manager::LoadingRecord;
 use crate::mcp_client::{
     RunningService,
     oauth_util,
@@ -87,6 +95,9 @@ pub struct CustomToolConfig {
     /// A boolean flag to denote whether or not to load this mcp server
     #[serde(default)]
     pub disabled: bool,
+    /// How failed tool calls are retried, and when the server is treated as unavailable
+    #[serde(default)]
+    pub retry: RetryPolicy,
     /// A flag to denote whether this is a server from the legacy mcp.json
     #[serde(skip)]
     pub is_from_legacy_mcp_json: bool,
@@ -103,6 +114,147 @@ pub fn default_timeout() -> u64 {
     120 * 1000
 }
 
+/// Retries for tool calls that fail to reach the server, and a circuit breaker that stops calling a
+/// server after repeated failures. Only transport errors and timeouts count as failures, see
+/// [is_transport_error].
+#[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetryPolicy {
+    /// Attempts for each tool call, including the first, so retries are off unless this is raised.
+    /// Calls that reach the server and return an error are not retried. Note that a retried call
+    /// may have already taken effect
+    pub max_attempts: u32,
+    /// Wait before the first retry in ms, doubled for every retry after it
+    pub backoff_ms: u64,
+    /// Tool calls in a row that have to fail before the server is treated as unavailable
+    pub failure_threshold: u32,
+    /// Time in ms the server is treated as unavailable before a call is tried again
+    pub cooldown_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff_ms: 500,
+            failure_threshold: 3,
+            cooldown_ms: 30 * 1000,
+        }
+    }
+}
+
+/// Whether tool calls to a server are let through, shared by every tool of the server.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    server_name: String,
+    policy: RetryPolicy,
+    state: std::sync::Mutex<CircuitState>,
+    /// Where opening and closing the circuit is recorded for `/mcp`
+    load_record: Arc<Mutex<HashMap<String, Vec<LoadingRecord>>>>,
+}
+
+#[derive(Debug, Default)]
+struct CircuitState {
+    consecutive_failures: u32,
+    /// When the circuit is open, the time a call is let through again
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(
+        server_name: String,
+        policy: RetryPolicy,
+        load_record: Arc<Mutex<HashMap<String, Vec<LoadingRecord>>>>,
+    ) -> Self {
+        Self {
+            server_name,
+            policy,
+            state: Default::default(),
+            load_record,
+        }
+    }
+
+    /// The time left before calls are let through, if the circuit is open.
+    pub fn unavailable_for(&self) -> Option<Duration> {
+        self.unavailable_for_at(Instant::now())
+    }
+
+    fn unavailable_for_at(&self, now: Instant) -> Option<Duration> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state
+            .open_until
+            .and_then(|open_until| open_until.checked_duration_since(now))
+            .filter(|left| !left.is_zero())
+    }
+
+    /// Wait before the given retry, counting from 1.
+    fn backoff(&self, retry: u32) -> Duration {
+        Duration::from_millis(
+            self.policy
+                .backoff_ms
+                .saturating_mul(1 << retry.saturating_sub(1).min(16)),
+        )
+    }
+
+    /// Records that a call reached the server, reporting it when this makes the server available
+    /// again.
+    async fn record_reached(&self, updates: &mut impl Write) -> Result<()> {
+        if self.record_success() {
+            queue!(
+                updates,
+                StyledText::success_fg(),
+                style::Print(format!("Server {} has recovered\n", self.server_name)),
+                StyledText::reset(),
+            )?;
+            self.record(LoadingRecord::success(
+                "Tool calls are succeeding again, its tools are available".to_string(),
+            ))
+            .await;
+        }
+        Ok(())
+    }
+
+    /// Returns true if this closed the circuit.
+    fn record_success(&self) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let was_open = state.open_until.take().is_some();
+        state.consecutive_failures = 0;
+        was_open
+    }
+
+    /// Returns true if this opened the circuit. Once the cooldown is over the circuit lets a call
+    /// through, and opens again right away if that call fails too.
+    fn record_failure_at(&self, now: Instant) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.policy.failure_threshold.max(1) {
+            let was_open = state.open_until.is_some();
+            state.open_until = Some(now + Duration::from_millis(self.policy.cooldown_ms));
+            !was_open
+        } else {
+            false
+        }
+    }
+
+    async fn record(&self, record: LoadingRecord) {
+        self.load_record
+            .lock()
+            .await
+            .entry(self.server_name.clone())
+            .or_default()
+            .push(record);
+    }
+}
+
+/// Whether a tool call failed to reach the server or to get an answer in time, the only errors
+/// that are retried and that count towards the circuit breaker.
+fn is_transport_error(err: &ServiceError) -> bool {
+    matches!(
+        err,
+        ServiceError::TransportSend(_) | ServiceError::TransportClosed | ServiceError::Timeout { .. }
+    )
+}
+
 /// Represents a custom tool that can be invoked through the Model Context Protocol (MCP).
 #[derive(Clone, Debug)]
 pub struct CustomTool {
@@ -118,6 +270,8 @@ pub struct CustomTool {
     /// Optional parameters to pass to the tool when invoking the method.
     /// Structured as a JSON value to accommodate various parameter types and structures.
     pub params: Option<serde_json::Map<String, serde_json::Value>>,
+    /// Retries failed calls and keeps track of whether the server is available
+    pub breaker: Arc<CircuitBreaker>,
 }
 
 impl CustomTool {
@@ -126,13 +280,73 @@ impl CustomTool {
         format!("@{}{}{}", self.server_name, MCP_SERVER_TOOL_DELIMITER, self.name)
     }
 
-    pub async fn invoke(&self, _os: &Os, _updates: &mut impl Write) -> Result<InvokeOutput> {
+    pub async fn invoke(&self, _os: &Os, updates: &mut impl Write) -> Result<InvokeOutput> {
+        if let Some(left) = self.breaker.unavailable_for() {
+            eyre::bail!(
+                "The tools of server {} are temporarily unavailable after repeated failures. Try again in {}s or use other tools.",
+                self.server_name,
+                left.as_secs().max(1)
+            );
+        }
+
         let params = CallToolRequestParam {
             name: Cow::from(self.name.clone()),
             arguments: self.params.clone(),
         };
 
-        let resp = self.client.call_tool(params.clone()).await?;
+        let max_attempts = self.breaker.policy.max_attempts.max(1);
+        let mut attempt = 1;
+        let resp = loop {
+            match self.client.call_tool(params.clone()).await {
+                Ok(resp) => {
+                    self.breaker.record_reached(updates).await?;
+                    break resp;
+                },
+                // The server answered with an error, or the call was cancelled. Neither means the
+                // server can't be reached, and retrying would run a call the server rejected again.
+                Err(e) if !is_transport_error(&e) => {
+                    if matches!(e, ServiceError::McpError(_)) {
+                        self.breaker.record_reached(updates).await?;
+                    }
+                    return Err(e.into());
+                },
+                Err(e) if attempt < max_attempts => {
+                    let backoff = self.breaker.backoff(attempt);
+                    warn!("Tool call for {} failed on attempt {attempt}: {e}", self.name);
+                    queue!(
+                        updates,
+                        StyledText::warning_fg(),
+                        style::Print(format!(
+                            "Call to {} failed: {e}. Retrying in {}ms ({} of {max_attempts})\n",
+                            self.name,
+                            backoff.as_millis(),
+                            attempt + 1
+                        )),
+                        StyledText::reset(),
+                    )?;
+                    updates.flush()?;
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                },
+                Err(e) => {
+                    if self.breaker.record_failure_at(Instant::now()) {
+                        let msg = format!(
+                            "Tool calls failed {} times in a row, its tools are unavailable for {}s",
+                            self.breaker.policy.failure_threshold.max(1),
+                            self.breaker.policy.cooldown_ms / 1000
+                        );
+                        queue!(
+                            updates,
+                            StyledText::warning_fg(),
+                            style::Print(format!("Server {} is degraded. {msg}\n", self.server_name)),
+                            StyledText::reset(),
+                        )?;
+                        self.breaker.record(LoadingRecord::warn(msg)).await;
+                    }
+                    return Err(e.into());
+                },
+            }
+        };
 
         if resp.is_error.is_none_or(|v| !v) {
             Ok(InvokeOutput {
@@ -196,3 +410,78 @@ impl CustomTool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(policy: RetryPolicy) -> CircuitBreaker {
+        CircuitBreaker::new("git".to_string(), policy, Default::default())
+    }
+
+    #[test]
+    fn test_retry_policy_defaults() {
+        let config = serde_json::from_value::<CustomToolConfig>(serde_json::json!({
+            "command": "git-mcp",
+            "retry": { "maxAttempts": 3 }
+        }))
+        .unwrap();
+        assert_eq!(config.retry, RetryPolicy {
+            max_attempts: 3,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_retries_are_opt_in() {
+        let config = serde_json::from_value::<CustomToolConfig>(serde_json::json!({ "command": "git-mcp" })).unwrap();
+        assert_eq!(config.retry.max_attempts, 1);
+    }
+
+    #[test]
+    fn test_is_transport_error() {
+        assert!(is_transport_error(&ServiceError::TransportClosed));
+        assert!(is_transport_error(&ServiceError::Timeout {
+            timeout: Duration::from_secs(1)
+        }));
+        assert!(!is_transport_error(&ServiceError::McpError(
+            rmcp::model::ErrorData::invalid_params("missing argument", None)
+        )));
+        assert!(!is_transport_error(&ServiceError::UnexpectedResponse));
+        assert!(!is_transport_error(&ServiceError::Cancelled { reason: None }));
+    }
+
+    #[test]
+    fn test_backoff_doubles() {
+        let breaker = breaker(RetryPolicy::default());
+        assert_eq!(breaker.backoff(1), Duration::from_millis(500));
+        assert_eq!(breaker.backoff(2), Duration::from_millis(1000));
+        assert_eq!(breaker.backoff(3), Duration::from_millis(2000));
+    }
+
+    #[test]
+    fn test_circuit_opens_and_recovers() {
+        let breaker = breaker(RetryPolicy {
+            failure_threshold: 2,
+            cooldown_ms: 1000,
+            ..Default::default()
+        });
+        let now = Instant::now();
+
+        assert!(!breaker.record_failure_at(now));
+        assert_eq!(breaker.unavailable_for_at(now), None);
+        assert!(breaker.record_failure_at(now));
+        assert_eq!(breaker.unavailable_for_at(now), Some(Duration::from_millis(1000)));
+
+        // After the cooldown a call is let through, and failing again opens the circuit without
+        // reporting it a second time
+        let later = now + Duration::from_millis(1000);
+        assert_eq!(breaker.unavailable_for_at(later), None);
+        assert!(!breaker.record_failure_at(later));
+        assert!(breaker.unavailable_for_at(later).is_some());
+
+        assert!(breaker.record_success());
+        assert_eq!(breaker.unavailable_for_at(later), None);
+        assert!(!breaker.record_success());
+    }
+}
@@ -0,0 +1,545 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use crossterm::queue;
+use crossterm::style::{
+    self,
+};
+use disk_cache::CacheKey;
+use eyre::{
+    Result,
+    bail,
+};
+use parquet::file::reader::{
+    FileReader,
+    SerializedFileReader,
+};
+use parquet::record::Field;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::Value;
+
+use super::{
+    InvokeOutput,
+    OutputKind,
+    sanitize_path_tool_arg,
+};
+use crate::cli::agent::{
+    Agent,
+    PermissionEvalResult,
+};
+use crate::cli::chat::util::truncate_safe_in_place;
+use crate::os::Os;
+use crate::theme::StyledText;
+
+/// Rows shown from each end of the file when the model does not ask for a specific amount.
+const DEFAULT_SAMPLE_ROWS: usize = 5;
+const MAX_SAMPLE_ROWS: usize = 50;
+/// Column statistics are computed over at most this many rows.
+const MAX_STATS_ROWS: usize = 1_000_000;
+/// Longer cell values are truncated in the sampled rows.
+const MAX_CELL_LENGTH: usize = 200;
+
+/// Summarizes a tabular data file without reading its contents into the conversation.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DataPreview {
+    pub path: String,
+    /// Number of rows to sample from both the start and the end of the file
+    #[serde(default)]
+    pub rows: Option<usize>,
+    /// Field delimiter for delimited text files, defaulting to a tab for `.tsv` and a comma
+    /// otherwise
+    #[serde(default)]
+    pub delimiter: Option<char>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum DataFormat {
+    Csv,
+    Tsv,
+    Parquet,
+}
+
+impl DataFormat {
+    fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "csv" => Some(Self::Csv),
+            "tsv" | "tab" => Some(Self::Tsv),
+            "parquet" | "pq" => Some(Self::Parquet),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Preview {
+    format: DataFormat,
+    row_count: usize,
+    columns: Vec<ColumnSummary>,
+    head: Vec<Vec<Value>>,
+    tail: Vec<Vec<Value>>,
+    /// Set when column statistics only cover the first rows of the file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats_row_limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct ColumnSummary {
+    name: String,
+    #[serde(rename = "type")]
+    data_type: String,
+    null_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mean: Option<f64>,
+}
+
+/// A cell reduced to what column statistics need.
+enum Cell {
+    Null,
+    Number(f64),
+    Text,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ColumnStats {
+    nulls: usize,
+    numbers: usize,
+    texts: usize,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl ColumnStats {
+    fn add(&mut self, cell: Cell) {
+        match cell {
+            Cell::Null => self.nulls += 1,
+            Cell::Text => self.texts += 1,
+            Cell::Number(n) => {
+                self.numbers += 1;
+                self.sum += n;
+                self.min = Some(self.min.map_or(n, |min| min.min(n)));
+                self.max = Some(self.max.map_or(n, |max| max.max(n)));
+            },
+        }
+    }
+
+    /// Summarizes the column, only reporting numeric statistics when every value is a number.
+    /// `data_type` is inferred from the values when not given.
+    fn summarize(self, name: String, data_type: Option<String>) -> ColumnSummary {
+        let numeric = self.numbers > 0 && self.texts == 0;
+        let data_type = data_type.unwrap_or_else(|| {
+            match (self.numbers, self.texts) {
+                (0, 0) => "empty",
+                (_, 0) => "number",
+                _ => "string",
+            }
+            .to_string()
+        });
+        ColumnSummary {
+            name,
+            data_type,
+            null_count: self.nulls,
+            min: self.min.filter(|_| numeric),
+            max: self.max.filter(|_| numeric),
+            mean: numeric.then(|| self.sum / self.numbers as f64),
+        }
+    }
+}
+
+/// Keeps the first and last `limit` rows seen, without overlap between the two.
+struct Sampler {
+    limit: usize,
+    head: Vec<Vec<Value>>,
+    tail: VecDeque<Vec<Value>>,
+}
+
+impl Sampler {
+    fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            head: Vec::new(),
+            tail: VecDeque::new(),
+        }
+    }
+
+    fn push(&mut self, row: Vec<Value>) {
+        if self.head.len() < self.limit {
+            self.head.push(row);
+        } else {
+            self.tail.push_back(row);
+            if self.tail.len() > self.limit {
+                self.tail.pop_front();
+            }
+        }
+    }
+}
+
+impl DataPreview {
+    pub async fn validate(&mut self, os: &Os) -> Result<()> {
+        let path = sanitize_path_tool_arg(os, &self.path);
+        if !path.exists() {
+            bail!("'{}' does not exist", self.path);
+        }
+        if !os.fs.symlink_metadata(&path).await?.is_file() {
+            bail!("'{}' is not a file", self.path);
+        }
+        if DataFormat::from_path(&path).is_none() {
+            bail!("'{}' is not a CSV, TSV or Parquet file", self.path);
+        }
+        if self.sample_rows() > MAX_SAMPLE_ROWS {
+            bail!("At most {MAX_SAMPLE_ROWS} rows can be sampled from each end of the file");
+        }
+        Ok(())
+    }
+
+    pub fn queue_description(&self, output: &mut impl Write) -> Result<()> {
+        queue!(
+            output,
+            style::Print("Previewing data file: "),
+            StyledText::success_fg(),
+            style::Print(&self.path),
+            StyledText::reset(),
+        )?;
+        Ok(())
+    }
+
+    pub fn eval_perm(&self, os: &Os, agent: &Agent) -> PermissionEvalResult {
+        super::eval_read_only_perm(os, agent, "data_preview", &self.path)
+    }
+
+    /// Key identifying this preview within `scope`, which changes whenever the file is modified.
+    pub async fn cache_key(&self, os: &Os, scope: &str) -> Option<CacheKey> {
+        let path = sanitize_path_tool_arg(os, &self.path);
+        let metadata = tokio::fs::metadata(&path).await.ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(CacheKey::new([
+            "data_preview".to_string(),
+            scope.to_string(),
+            path.to_string_lossy().into_owned(),
+            format!("{:?}", self.rows),
+            format!("{:?}", self.delimiter),
+            modified.as_nanos().to_string(),
+            metadata.len().to_string(),
+        ]))
+    }
+
+    pub async fn invoke(&self, os: &Os, updates: &mut impl Write) -> Result<InvokeOutput> {
+        let path = sanitize_path_tool_arg(os, &self.path);
+        let Some(format) = DataFormat::from_path(&path) else {
+            bail!("'{}' is not a CSV, TSV or Parquet file", self.path);
+        };
+        let delimiter = match (self.delimiter, format) {
+            (Some(c), _) if c.is_ascii() => c as u8,
+            (Some(c), _) => bail!("delimiter must be an ASCII character, got '{c}'"),
+            (None, DataFormat::Tsv) => b'\t',
+            (None, _) => b',',
+        };
+        let rows = self.sample_rows();
+
+        let preview_path = path.clone();
+        let preview = tokio::task::spawn_blocking(move || match format {
+            DataFormat::Csv | DataFormat::Tsv => preview_delimited(&preview_path, format, delimiter, rows),
+            DataFormat::Parquet => preview_parquet(&preview_path, rows),
+        })
+        .await??;
+
+        super::queue_function_result(
+            &format!(
+                "Previewed {} rows and {} columns in {}",
+                preview.row_count,
+                preview.columns.len(),
+                path.display()
+            ),
+            updates,
+            false,
+            false,
+        )?;
+
+        Ok(InvokeOutput {
+            output: OutputKind::Text(serde_json::to_string(&preview)?),
+        })
+    }
+
+    fn sample_rows(&self) -> usize {
+        self.rows.unwrap_or(DEFAULT_SAMPLE_ROWS)
+    }
+}
+
+fn preview_delimited(path: &Path, format: DataFormat, delimiter: u8, rows: usize) -> Result<Preview> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_path(path)?;
+    let names = reader
+        .byte_headers()?
+        .iter()
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect::<Vec<_>>();
+
+    let mut stats = vec![ColumnStats::default(); names.len()];
+    let mut sampler = Sampler::new(rows);
+    let mut row_count = 0;
+    for record in reader.byte_records() {
+        let record = record?;
+        row_count += 1;
+        let cells = record
+            .iter()
+            .map(|cell| String::from_utf8_lossy(cell))
+            .collect::<Vec<_>>();
+        if row_count <= MAX_STATS_ROWS {
+            for (stats, cell) in stats.iter_mut().zip(&cells) {
+                stats.add(match cell.trim() {
+                    "" => Cell::Null,
+                    cell => match cell.parse::<f64>() {
+                        Ok(n) if n.is_finite() => Cell::Number(n),
+                        _ => Cell::Text,
+                    },
+                });
+            }
+        }
+        sampler.push(cells.iter().map(|cell| sample_text(cell)).collect());
+    }
+
+    Ok(Preview {
+        format,
+        row_count,
+        columns: names
+            .into_iter()
+            .zip(stats)
+            .map(|(name, stats)| stats.summarize(name, None))
+            .collect(),
+        head: sampler.head,
+        tail: sampler.tail.into(),
+        stats_row_limit: (row_count > MAX_STATS_ROWS).then_some(MAX_STATS_ROWS),
+    })
+}
+
+fn preview_parquet(path: &Path, rows: usize) -> Result<Preview> {
+    let reader = SerializedFileReader::new(File::open(path)?)?;
+    let metadata = reader.metadata();
+    let row_count = usize::try_from(metadata.file_metadata().num_rows())?;
+    let fields = metadata
+        .file_metadata()
+        .schema_descr()
+        .root_schema()
+        .get_fields()
+        .to_vec();
+
+    let mut stats = vec![ColumnStats::default(); fields.len()];
+    let mut sampler = Sampler::new(rows);
+    for row in reader.get_row_iter(None)?.take(MAX_STATS_ROWS) {
+        let row = row?;
+        let mut cells = Vec::with_capacity(fields.len());
+        for ((_, field), stats) in row.get_column_iter().zip(stats.iter_mut()) {
+            stats.add(parquet_cell(field));
+            cells.push(sample_json(field.to_json_value()));
+        }
+        sampler.push(cells);
+    }
+
+    // Statistics stop short of the end of large files, so read the tail from the last row groups.
+    if row_count > MAX_STATS_ROWS {
+        sampler.tail.clear();
+        let mut first_group = metadata.num_row_groups();
+        let mut tail_rows = 0;
+        while first_group > 0 && tail_rows < rows {
+            first_group -= 1;
+            tail_rows += usize::try_from(metadata.row_group(first_group).num_rows())?;
+        }
+        for i in first_group..metadata.num_row_groups() {
+            let group = reader.get_row_group(i)?;
+            for row in group.get_row_iter(None)? {
+                let row = row?;
+                sampler.tail.push_back(
+                    row.get_column_iter()
+                        .map(|(_, field)| sample_json(field.to_json_value()))
+                        .collect(),
+                );
+                if sampler.tail.len() > rows {
+                    sampler.tail.pop_front();
+                }
+            }
+        }
+    }
+
+    Ok(Preview {
+        format: DataFormat::Parquet,
+        row_count,
+        columns: fields
+            .iter()
+            .zip(stats)
+            .map(|(field, stats)| {
+                let data_type = if !field.is_primitive() {
+                    "group".to_string()
+                } else {
+                    match field.get_basic_info().logical_type() {
+                        Some(logical_type) => format!("{logical_type:?}"),
+                        None => field.get_physical_type().to_string(),
+                    }
+                };
+                stats.summarize(field.name().to_string(), Some(data_type))
+            })
+            .collect(),
+        head: sampler.head,
+        tail: sampler.tail.into(),
+        stats_row_limit: (row_count > MAX_STATS_ROWS).then_some(MAX_STATS_ROWS),
+    })
+}
+
+fn parquet_cell(field: &Field) -> Cell {
+    match field {
+        Field::Null => Cell::Null,
+        Field::Byte(n) => Cell::Number(f64::from(*n)),
+        Field::Short(n) => Cell::Number(f64::from(*n)),
+        Field::Int(n) => Cell::Number(f64::from(*n)),
+        Field::Long(n) => Cell::Number(*n as f64),
+        Field::UByte(n) => Cell::Number(f64::from(*n)),
+        Field::UShort(n) => Cell::Number(f64::from(*n)),
+        Field::UInt(n) => Cell::Number(f64::from(*n)),
+        Field::ULong(n) => Cell::Number(*n as f64),
+        Field::Float(n) => Cell::Number(f64::from(*n)),
+        Field::Double(n) => Cell::Number(*n),
+        _ => Cell::Text,
+    }
+}
+
+fn sample_text(text: &str) -> Value {
+    let mut text = text.to_string();
+    truncate_safe_in_place(&mut text, MAX_CELL_LENGTH, "...");
+    Value::String(text)
+}
+
+fn sample_json(value: Value) -> Value {
+    match value {
+        Value::String(text) => sample_text(&text),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parquet::data_type::{
+        ByteArray,
+        ByteArrayType,
+        Int64Type,
+    };
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+
+    use super::*;
+
+    async fn preview(os: &Os, args: Value) -> Value {
+        let mut tool = serde_json::from_value::<DataPreview>(args).unwrap();
+        tool.validate(os).await.unwrap();
+        match tool.invoke(os, &mut std::io::stdout()).await.unwrap().output {
+            OutputKind::Text(text) => serde_json::from_str(&text).unwrap(),
+            _ => panic!("expected text output"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_preview_csv() {
+        let os = Os::new().await.unwrap();
+        let mut csv = String::from("id,name,score\n");
+        for i in 1..=20 {
+            csv.push_str(&format!(
+                "{i},user{i},{}\n",
+                if i == 3 { String::new() } else { i.to_string() }
+            ));
+        }
+        os.fs.write("/data.csv", csv).await.unwrap();
+
+        let value = preview(&os, serde_json::json!({ "path": "/data.csv", "rows": 2 })).await;
+        assert_eq!(value["format"], "csv");
+        assert_eq!(value["row_count"], 20);
+        assert_eq!(
+            value["head"],
+            serde_json::json!([["1", "user1", "1"], ["2", "user2", "2"]])
+        );
+        assert_eq!(
+            value["tail"],
+            serde_json::json!([["19", "user19", "19"], ["20", "user20", "20"]])
+        );
+        assert_eq!(
+            value["columns"][0],
+            serde_json::json!({ "name": "id", "type": "number", "null_count": 0, "min": 1.0, "max": 20.0, "mean": 10.5 })
+        );
+        assert_eq!(
+            value["columns"][1],
+            serde_json::json!({ "name": "name", "type": "string", "null_count": 0 })
+        );
+        assert_eq!(value["columns"][2]["null_count"], 1);
+        assert!(value.get("stats_row_limit").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_preview_tsv_small_file() {
+        let os = Os::new().await.unwrap();
+        os.fs.write("/data.tsv", "a\tb\n1\tx,y\n").await.unwrap();
+
+        let value = preview(&os, serde_json::json!({ "path": "/data.tsv" })).await;
+        assert_eq!(value["row_count"], 1);
+        assert_eq!(value["head"], serde_json::json!([["1", "x,y"]]));
+        assert_eq!(value["tail"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_preview_parquet() {
+        let os = Os::new().await.unwrap();
+        let path = sanitize_path_tool_arg(&os, "/data.parquet");
+        let schema = Arc::new(
+            parse_message_type("message schema { REQUIRED INT64 id; OPTIONAL BYTE_ARRAY name (UTF8); }").unwrap(),
+        );
+        let mut writer = SerializedFileWriter::new(File::create(&path).unwrap(), schema, Default::default()).unwrap();
+        let mut row_group = writer.next_row_group().unwrap();
+        let mut column = row_group.next_column().unwrap().unwrap();
+        column.typed::<Int64Type>().write_batch(&[1, 2, 3], None, None).unwrap();
+        column.close().unwrap();
+        let mut column = row_group.next_column().unwrap().unwrap();
+        column
+            .typed::<ByteArrayType>()
+            .write_batch(&[ByteArray::from("a"), ByteArray::from("b")], Some(&[1, 1, 0]), None)
+            .unwrap();
+        column.close().unwrap();
+        row_group.close().unwrap();
+        writer.close().unwrap();
+
+        let value = preview(&os, serde_json::json!({ "path": "/data.parquet", "rows": 1 })).await;
+        assert_eq!(value["format"], "parquet");
+        assert_eq!(value["row_count"], 3);
+        assert_eq!(value["head"], serde_json::json!([[1, "a"]]));
+        assert_eq!(value["tail"], serde_json::json!([[3, null]]));
+        assert_eq!(value["columns"][0]["type"], "INT64");
+        assert_eq!(value["columns"][0]["mean"], 2.0);
+        assert_eq!(value["columns"][1]["null_count"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_validate() {
+        let os = Os::new().await.unwrap();
+        os.fs.write("/notes.txt", "a,b\n").await.unwrap();
+        os.fs.write("/data.csv", "a,b\n").await.unwrap();
+
+        let mut tool = serde_json::from_value::<DataPreview>(serde_json::json!({ "path": "/notes.txt" })).unwrap();
+        assert!(tool.validate(&os).await.is_err());
+        let mut tool =
+            serde_json::from_value::<DataPreview>(serde_json::json!({ "path": "/data.csv", "rows": 1000 })).unwrap();
+        assert!(tool.validate(&os).await.is_err());
+        let mut tool = serde_json::from_value::<DataPreview>(serde_json::json!({ "path": "/missing.csv" })).unwrap();
+        assert!(tool.validate(&os).await.is_err());
+    }
+}
@@ -350,6 +350,9 @@ pub async fn spawn_agent_process(os: &Os, agent: &str, task: &str) -> Result<Age
     cmd.stderr(std::process::Stdio::piped());
     cmd.stdin(std::process::Stdio::null()); // No user input
     cmd.envs(get_all_env_vars());
+    if let Some(dir) = os.env.changed_dir() {
+        cmd.current_dir(dir);
+    }
 
     #[cfg(not(windows))]
     cmd.process_group(0);
@@ -368,7 +371,10 @@ pub async fn spawn_agent_process(os: &Os, agent: &str, task: &str) -> Result<Age
         output: String::new(),
         user_notified: false,
         summary: None,
-        cwd: std::env::current_dir().map_or_else(|_| "Unknown".to_string(), |p| p.to_string_lossy().to_string()),
+        cwd: os
+            .env
+            .current_dir()
+            .map_or_else(|_| "Unknown".to_string(), |p| p.to_string_lossy().to_string()),
     };
 
     save_agent_execution(os, &execution).await?;
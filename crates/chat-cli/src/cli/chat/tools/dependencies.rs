@@ -0,0 +1,649 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use crossterm::queue;
+use crossterm::style::{
+    self,
+};
+use eyre::{
+    Result,
+    bail,
+};
+use futures::future::join_all;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::Value;
+
+use super::{
+    InvokeOutput,
+    OutputKind,
+    sanitize_path_tool_arg,
+};
+use crate::cli::agent::{
+    Agent,
+    PermissionEvalResult,
+};
+use crate::os::Os;
+use crate::theme::StyledText;
+use crate::util::tool_permission_checker::is_tool_in_allowlist;
+
+/// Manifests that are recognized, in the order they are looked for in a directory.
+const MANIFESTS: [&str; 3] = ["Cargo.toml", "package.json", "requirements.txt"];
+/// Packages that can be looked up in one call.
+const MAX_PACKAGES: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ecosystem {
+    Cargo,
+    Npm,
+    Pypi,
+}
+
+impl Ecosystem {
+    fn from_manifest(path: &Path) -> Option<Self> {
+        match path.file_name()?.to_str()? {
+            "Cargo.toml" => Some(Self::Cargo),
+            "package.json" => Some(Self::Npm),
+            name if name.starts_with("requirements") && name.ends_with(".txt") => Some(Self::Pypi),
+            _ => None,
+        }
+    }
+
+    fn lockfile(&self) -> Option<&'static str> {
+        match self {
+            Self::Cargo => Some("Cargo.lock"),
+            Self::Npm => Some("package-lock.json"),
+            // Requirements files pin versions themselves
+            Self::Pypi => None,
+        }
+    }
+
+    fn registry_url(&self, package: &str) -> String {
+        match self {
+            Self::Cargo => format!("https://crates.io/api/v1/crates/{package}"),
+            Self::Npm => format!("https://registry.npmjs.org/{package}/latest"),
+            Self::Pypi => format!("https://pypi.org/pypi/{package}/json"),
+        }
+    }
+}
+
+/// Lists the dependencies declared in a manifest along with the versions locked for them.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DependencyList {
+    /// Path to a Cargo.toml, package.json or requirements.txt, or a directory containing one
+    pub path: String,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct Dependency {
+    name: String,
+    /// Section the dependency is declared in, such as `dev-dependencies`
+    kind: String,
+    /// Version requirement as written in the manifest, or the source for path and git dependencies
+    requirement: String,
+    /// Versions in the lockfile, more than one when several are in the dependency graph
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    locked: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct DependencyListing {
+    manifest: PathBuf,
+    ecosystem: Ecosystem,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lockfile: Option<PathBuf>,
+    dependencies: Vec<Dependency>,
+}
+
+impl DependencyList {
+    pub async fn validate(&mut self, os: &Os) -> Result<()> {
+        let path = sanitize_path_tool_arg(os, &self.path);
+        if !path.exists() {
+            bail!("'{}' does not exist", self.path);
+        }
+        if find_manifest(&path).is_none() {
+            bail!("'{}' is not a Cargo.toml, package.json or requirements.txt", self.path);
+        }
+        Ok(())
+    }
+
+    pub fn queue_description(&self, output: &mut impl Write) -> Result<()> {
+        queue!(
+            output,
+            style::Print("Listing dependencies in: "),
+            StyledText::success_fg(),
+            style::Print(&self.path),
+            StyledText::reset(),
+        )?;
+        Ok(())
+    }
+
+    pub fn eval_perm(&self, os: &Os, agent: &Agent) -> PermissionEvalResult {
+        super::eval_read_only_perm(os, agent, "dependency_list", &self.path)
+    }
+
+    pub async fn invoke(&self, os: &Os, updates: &mut impl Write) -> Result<InvokeOutput> {
+        let path = sanitize_path_tool_arg(os, &self.path);
+        let Some((manifest, ecosystem)) = find_manifest(&path) else {
+            bail!("'{}' is not a Cargo.toml, package.json or requirements.txt", self.path);
+        };
+        let contents = tokio::fs::read_to_string(&manifest).await?;
+        let mut dependencies = match ecosystem {
+            Ecosystem::Cargo => parse_cargo_manifest(&contents)?,
+            Ecosystem::Npm => parse_package_json(&contents)?,
+            Ecosystem::Pypi => parse_requirements(&contents),
+        };
+
+        // Lockfiles of workspaces are next to the workspace manifest rather than the member's
+        let lockfile = ecosystem.lockfile().and_then(|name| {
+            manifest
+                .ancestors()
+                .skip(1)
+                .map(|dir| dir.join(name))
+                .find(|path| path.exists())
+        });
+        if let Some(lockfile) = &lockfile {
+            let contents = tokio::fs::read_to_string(lockfile).await?;
+            let locked = match ecosystem {
+                Ecosystem::Cargo => parse_cargo_lock(&contents)?,
+                Ecosystem::Npm => parse_package_lock(&contents)?,
+                Ecosystem::Pypi => BTreeMap::new(),
+            };
+            for dependency in &mut dependencies {
+                if let Some(versions) = locked.get(&dependency.name) {
+                    dependency.locked = versions.clone();
+                }
+            }
+        }
+
+        super::queue_function_result(
+            &format!("Found {} dependencies in {}", dependencies.len(), manifest.display()),
+            updates,
+            false,
+            false,
+        )?;
+
+        Ok(InvokeOutput {
+            output: OutputKind::Text(serde_json::to_string(&DependencyListing {
+                manifest,
+                ecosystem,
+                lockfile,
+                dependencies,
+            })?),
+        })
+    }
+}
+
+/// Looks up the latest published version of packages and where to find their changelogs.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DependencyVersions {
+    pub ecosystem: Ecosystem,
+    pub packages: Vec<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct PackageVersion {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latest: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repository: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    changelog: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl DependencyVersions {
+    pub async fn validate(&mut self, _os: &Os) -> Result<()> {
+        if self.packages.is_empty() {
+            bail!("No packages were provided");
+        }
+        if self.packages.len() > MAX_PACKAGES {
+            bail!("At most {MAX_PACKAGES} packages can be looked up at once");
+        }
+        // Names are interpolated into registry URLs
+        if let Some(name) = self.packages.iter().find(|name| {
+            name.is_empty()
+                || name.contains("..")
+                || name.starts_with(['.', '/'])
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@' | '/'))
+        }) {
+            bail!("'{name}' is not a valid package name");
+        }
+        Ok(())
+    }
+
+    pub fn queue_description(&self, output: &mut impl Write) -> Result<()> {
+        queue!(
+            output,
+            style::Print("Looking up the latest versions of: "),
+            StyledText::success_fg(),
+            style::Print(self.packages.join(", ")),
+            StyledText::reset(),
+        )?;
+        Ok(())
+    }
+
+    /// URLs the packages are looked up at, which the egress policy of the agent applies to.
+    pub fn registry_urls(&self) -> Vec<String> {
+        self.packages
+            .iter()
+            .map(|name| self.ecosystem.registry_url(name))
+            .collect()
+    }
+
+    pub fn eval_perm(&self, _os: &Os, agent: &Agent) -> PermissionEvalResult {
+        if is_tool_in_allowlist(&agent.allowed_tools, "dependency_versions", None) {
+            return PermissionEvalResult::Allow;
+        }
+        // Only public package metadata is read, but the package names are sent to the registry
+        PermissionEvalResult::Ask
+    }
+
+    pub async fn invoke(&self, _os: &Os, updates: &mut impl Write) -> Result<InvokeOutput> {
+        let client = crate::request::new_client()?;
+        let versions = join_all(self.packages.iter().map(|name| {
+            let client = client.clone();
+            async move {
+                let result = async {
+                    let response = client
+                        .get(self.ecosystem.registry_url(name))
+                        .send()
+                        .await?
+                        .error_for_status()?;
+                    Ok::<_, eyre::Report>(response.json::<Value>().await?)
+                }
+                .await;
+                match result {
+                    Ok(metadata) => parse_registry_metadata(self.ecosystem, name, &metadata),
+                    Err(err) => PackageVersion {
+                        name: name.clone(),
+                        error: Some(err.to_string()),
+                        ..Default::default()
+                    },
+                }
+            }
+        }))
+        .await;
+
+        let found = versions.iter().filter(|v| v.latest.is_some()).count();
+        super::queue_function_result(
+            &format!("Found the latest versions of {found} of {} packages", versions.len()),
+            updates,
+            found < versions.len(),
+            false,
+        )?;
+
+        Ok(InvokeOutput {
+            output: OutputKind::Text(serde_json::to_string(&versions)?),
+        })
+    }
+}
+
+/// Finds the manifest at `path`, which is either the manifest or a directory containing one.
+fn find_manifest(path: &Path) -> Option<(PathBuf, Ecosystem)> {
+    if path.is_dir() {
+        return MANIFESTS
+            .iter()
+            .map(|name| path.join(name))
+            .find(|path| path.is_file())
+            .and_then(|path| Ecosystem::from_manifest(&path).map(|e| (path, e)));
+    }
+    Ecosystem::from_manifest(path).map(|e| (path.to_path_buf(), e))
+}
+
+fn parse_cargo_manifest(contents: &str) -> Result<Vec<Dependency>> {
+    let manifest: toml::Table = toml::from_str(contents)?;
+    let mut tables = Vec::new();
+    for kind in ["dependencies", "dev-dependencies", "build-dependencies"] {
+        if let Some(table) = manifest.get(kind).and_then(|t| t.as_table()) {
+            tables.push((kind.to_string(), table));
+        }
+    }
+    if let Some(table) = manifest
+        .get("workspace")
+        .and_then(|w| w.get("dependencies"))
+        .and_then(|t| t.as_table())
+    {
+        tables.push(("workspace.dependencies".to_string(), table));
+    }
+
+    let mut dependencies = Vec::new();
+    for (kind, table) in tables {
+        for (name, spec) in table {
+            let requirement = match spec {
+                toml::Value::String(version) => version.clone(),
+                toml::Value::Table(spec) => {
+                    if let Some(version) = spec.get("version").and_then(|v| v.as_str()) {
+                        version.to_string()
+                    } else if spec.get("workspace").and_then(|w| w.as_bool()) == Some(true) {
+                        "workspace".to_string()
+                    } else if let Some(path) = spec.get("path").and_then(|p| p.as_str()) {
+                        format!("path:{path}")
+                    } else if let Some(git) = spec.get("git").and_then(|g| g.as_str()) {
+                        format!("git:{git}")
+                    } else {
+                        "*".to_string()
+                    }
+                },
+                _ => continue,
+            };
+            // Renamed dependencies are locked under the name of the package
+            let name = match spec.get("package").and_then(|p| p.as_str()) {
+                Some(package) => package.to_string(),
+                None => name.clone(),
+            };
+            dependencies.push(Dependency {
+                name,
+                kind: kind.clone(),
+                requirement,
+                locked: Vec::new(),
+            });
+        }
+    }
+    Ok(dependencies)
+}
+
+fn parse_cargo_lock(contents: &str) -> Result<BTreeMap<String, Vec<String>>> {
+    let lock: toml::Table = toml::from_str(contents)?;
+    let mut locked: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for package in lock.get("package").and_then(|p| p.as_array()).into_iter().flatten() {
+        if let (Some(name), Some(version)) = (
+            package.get("name").and_then(|n| n.as_str()),
+            package.get("version").and_then(|v| v.as_str()),
+        ) {
+            locked.entry(name.to_string()).or_default().push(version.to_string());
+        }
+    }
+    Ok(locked)
+}
+
+fn parse_package_json(contents: &str) -> Result<Vec<Dependency>> {
+    let manifest: Value = serde_json::from_str(contents)?;
+    let mut dependencies = Vec::new();
+    for kind in [
+        "dependencies",
+        "devDependencies",
+        "peerDependencies",
+        "optionalDependencies",
+    ] {
+        for (name, requirement) in manifest[kind].as_object().into_iter().flatten() {
+            dependencies.push(Dependency {
+                name: name.clone(),
+                kind: kind.to_string(),
+                requirement: requirement.as_str().unwrap_or_default().to_string(),
+                locked: Vec::new(),
+            });
+        }
+    }
+    Ok(dependencies)
+}
+
+fn parse_package_lock(contents: &str) -> Result<BTreeMap<String, Vec<String>>> {
+    let lock: Value = serde_json::from_str(contents)?;
+    let mut locked: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (path, package) in lock["packages"].as_object().into_iter().flatten() {
+        // Nested copies are under node_modules/a/node_modules/b
+        let Some((_, name)) = path.rsplit_once("node_modules/") else {
+            continue;
+        };
+        if let Some(version) = package["version"].as_str() {
+            let versions = locked.entry(name.to_string()).or_default();
+            if !versions.iter().any(|v| v == version) {
+                versions.push(version.to_string());
+            }
+        }
+    }
+    Ok(locked)
+}
+
+fn parse_requirements(contents: &str) -> Vec<Dependency> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let line = line.split(" #").next().unwrap_or_default().trim();
+            // Options such as -r other.txt, -e ./local and --index-url
+            if line.is_empty() || line.starts_with('#') || line.starts_with('-') {
+                return None;
+            }
+            let line = line.split(';').next().unwrap_or_default().trim();
+            let end = line
+                .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+                .unwrap_or(line.len());
+            let (name, rest) = line.split_at(end);
+            if name.is_empty() {
+                return None;
+            }
+            // Drop extras such as requests[socks]
+            let rest = match rest.strip_prefix('[') {
+                Some(rest) => rest.split_once(']').map_or("", |(_, rest)| rest),
+                None => rest,
+            };
+            let requirement = rest.trim();
+            let locked = requirement
+                .strip_prefix("==")
+                .map(|version| vec![version.trim().to_string()])
+                .unwrap_or_default();
+            Some(Dependency {
+                name: name.to_string(),
+                kind: "requirements".to_string(),
+                requirement: if requirement.is_empty() { "*" } else { requirement }.to_string(),
+                locked,
+            })
+        })
+        .collect()
+}
+
+fn parse_registry_metadata(ecosystem: Ecosystem, name: &str, metadata: &Value) -> PackageVersion {
+    let str_at = |pointer: &str| metadata.pointer(pointer).and_then(|v| v.as_str()).map(str::to_string);
+    let (latest, repository, changelog) = match ecosystem {
+        Ecosystem::Cargo => (
+            str_at("/crate/max_stable_version").or_else(|| str_at("/crate/max_version")),
+            str_at("/crate/repository"),
+            None,
+        ),
+        Ecosystem::Npm => (
+            str_at("/version"),
+            str_at("/repository/url").or_else(|| str_at("/repository")).map(|url| {
+                url.trim_start_matches("git+")
+                    .trim_end_matches(".git")
+                    .replace("git://", "https://")
+            }),
+            None,
+        ),
+        Ecosystem::Pypi => {
+            let urls = metadata.pointer("/info/project_urls").and_then(|u| u.as_object());
+            let find_url = |keys: &[&str]| {
+                urls.into_iter()
+                    .flatten()
+                    .find(|(key, _)| keys.iter().any(|k| key.eq_ignore_ascii_case(k)))
+                    .and_then(|(_, url)| url.as_str().map(str::to_string))
+            };
+            (
+                str_at("/info/version"),
+                find_url(&["Source", "Source Code", "Repository", "Code"]),
+                find_url(&["Changelog", "Changes", "Release Notes", "History"]),
+            )
+        },
+    };
+    // GitHub repositories publish release notes under /releases when there is no changelog link
+    let changelog = changelog.or_else(|| {
+        repository
+            .as_ref()
+            .filter(|url| url.starts_with("https://github.com/"))
+            .map(|url| format!("{}/releases", url.trim_end_matches('/')))
+    });
+    PackageVersion {
+        name: name.to_string(),
+        latest,
+        repository,
+        changelog,
+        error: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(output: Result<InvokeOutput>) -> String {
+        match output.unwrap().output {
+            OutputKind::Text(text) => text,
+            _ => panic!("expected text output"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dependency_list_cargo() {
+        let os = Os::new().await.unwrap();
+        let mut stdout = std::io::stdout();
+        os.fs.create_dir_all("/project/app").await.unwrap();
+        os.fs
+            .write(
+                "/project/app/Cargo.toml",
+                r#"
+                [package]
+                name = "app"
+
+                [dependencies]
+                serde = { version = "1.0", features = ["derive"] }
+                tokio = { workspace = true }
+                json = { package = "serde_json", version = "1" }
+                local = { path = "../local" }
+
+                [dev-dependencies]
+                insta = "1.34"
+                "#,
+            )
+            .await
+            .unwrap();
+        os.fs
+            .write(
+                "/project/Cargo.lock",
+                r#"
+                [[package]]
+                name = "serde"
+                version = "1.0.190"
+
+                [[package]]
+                name = "serde_json"
+                version = "1.0.108"
+                "#,
+            )
+            .await
+            .unwrap();
+
+        let mut list = serde_json::from_value::<DependencyList>(serde_json::json!({ "path": "/project/app" })).unwrap();
+        list.validate(&os).await.unwrap();
+        let value: Value = serde_json::from_str(&text(list.invoke(&os, &mut stdout).await)).unwrap();
+        assert_eq!(value["ecosystem"], "cargo");
+        assert!(value["lockfile"].as_str().unwrap().ends_with("Cargo.lock"));
+        let dependencies = value["dependencies"].as_array().unwrap();
+        assert_eq!(
+            dependencies[0],
+            serde_json::json!({ "name": "serde_json", "kind": "dependencies", "requirement": "1", "locked": ["1.0.108"] })
+        );
+        assert_eq!(dependencies[1]["requirement"], "path:../local");
+        assert_eq!(dependencies[2]["locked"], serde_json::json!(["1.0.190"]));
+        assert_eq!(dependencies[3]["requirement"], "workspace");
+        assert_eq!(dependencies[4]["kind"], "dev-dependencies");
+    }
+
+    #[test]
+    fn test_parse_package_json_and_lock() {
+        let dependencies = parse_package_json(
+            r#"{ "dependencies": { "react": "^18.2.0" }, "devDependencies": { "typescript": "~5.3.0" } }"#,
+        )
+        .unwrap();
+        assert_eq!(dependencies[1].name, "typescript");
+        assert_eq!(dependencies[1].kind, "devDependencies");
+
+        let locked = parse_package_lock(
+            r#"{ "packages": {
+                "": { "name": "app" },
+                "node_modules/react": { "version": "18.2.0" },
+                "node_modules/a/node_modules/react": { "version": "17.0.2" }
+            } }"#,
+        )
+        .unwrap();
+        assert_eq!(locked["react"], vec!["18.2.0", "17.0.2"]);
+    }
+
+    #[test]
+    fn test_parse_requirements() {
+        let dependencies = parse_requirements(
+            "# comment\n-r base.txt\nrequests[socks]==2.31.0  # pinned\nDjango>=4.2,<5\nnumpy ; python_version > '3.8'\n",
+        );
+        assert_eq!(dependencies, vec![
+            Dependency {
+                name: "requests".to_string(),
+                kind: "requirements".to_string(),
+                requirement: "==2.31.0".to_string(),
+                locked: vec!["2.31.0".to_string()],
+            },
+            Dependency {
+                name: "Django".to_string(),
+                kind: "requirements".to_string(),
+                requirement: ">=4.2,<5".to_string(),
+                locked: Vec::new(),
+            },
+            Dependency {
+                name: "numpy".to_string(),
+                kind: "requirements".to_string(),
+                requirement: "*".to_string(),
+                locked: Vec::new(),
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_parse_registry_metadata() {
+        let version = parse_registry_metadata(
+            Ecosystem::Npm,
+            "react",
+            &serde_json::json!({
+                "version": "18.3.1",
+                "repository": { "type": "git", "url": "git+https://github.com/facebook/react.git" }
+            }),
+        );
+        assert_eq!(version.latest.as_deref(), Some("18.3.1"));
+        assert_eq!(version.repository.as_deref(), Some("https://github.com/facebook/react"));
+        assert_eq!(
+            version.changelog.as_deref(),
+            Some("https://github.com/facebook/react/releases")
+        );
+
+        let version = parse_registry_metadata(
+            Ecosystem::Pypi,
+            "requests",
+            &serde_json::json!({ "info": {
+                "version": "2.32.3",
+                "project_urls": { "Changelog": "https://github.com/psf/requests/blob/main/HISTORY.md" }
+            } }),
+        );
+        assert_eq!(
+            version.changelog.as_deref(),
+            Some("https://github.com/psf/requests/blob/main/HISTORY.md")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dependency_versions_validate() {
+        let os = Os::new().await.unwrap();
+        let mut versions = DependencyVersions {
+            ecosystem: Ecosystem::Cargo,
+            packages: vec!["serde".to_string(), "../../etc".to_string()],
+        };
+        assert!(versions.validate(&os).await.is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use std::io::Write;
+use std::path::PathBuf;
 
 use crossterm::queue;
 use crossterm::style::{
@@ -7,7 +8,10 @@ use crossterm::style::{
 use eyre::Result;
 use regex::Regex;
 use serde::Deserialize;
-use tracing::error;
+use tracing::{
+    error,
+    warn,
+};
 
 use super::env_vars_with_user_agent;
 use crate::cli::agent::{
@@ -36,6 +40,13 @@ mod unix;
 #[cfg(not(windows))]
 pub use unix::*;
 
+/// Commands that delete the files given as arguments, see [ExecuteCommand::removed_paths]
+const REMOVE_COMMANDS: &[&str] = &["rm", "unlink", "shred"];
+
+/// Most files backed up before a command removes them, so that `rm -rf` of a large directory
+/// doesn't copy all of it
+const MAX_REMOVED_PATHS: usize = 1000;
+
 // Common readonly commands that are safe to execute without user confirmation
 pub const READONLY_COMMANDS: &[&str] = &[
     "ls", "cat", "echo", "pwd", "which", "head", "tail", "find", "grep", "dir", "type",
@@ -147,8 +158,66 @@ impl ExecuteCommand {
         false
     }
 
-    pub async fn invoke(&self, os: &Os, output: &mut impl Write) -> Result<InvokeOutput> {
-        let output = run_command(os, &self.command, MAX_TOOL_RESPONSE_SIZE / 3, Some(output)).await?;
+    /// Returns the files that the command removes with `rm` and the like, including those in
+    /// directories removed with `rm -r`, so that they can be backed up before it runs.
+    ///
+    /// This only sees plain arguments: files removed through globs, variables or subshells, or by
+    /// other programs, aren't found.
+    pub async fn removed_paths(&self, os: &Os) -> Vec<PathBuf> {
+        let Some(args) = shlex::split(&self.command) else {
+            return Vec::new();
+        };
+
+        let cwd = os.env.current_dir().unwrap_or_default();
+        let mut targets = Vec::new();
+        for segment in args.split(|arg| ["|", "||", "&&", ";", "&"].contains(&arg.as_str())) {
+            let mut tokens = segment.iter().skip_while(|token| token.as_str() == "sudo");
+            let Some(program) = tokens.next() else {
+                continue;
+            };
+            let program = program.rsplit('/').next().unwrap_or(program);
+            if !REMOVE_COMMANDS.contains(&program) {
+                continue;
+            }
+            let mut options_ended = false;
+            for token in tokens {
+                match token.as_str() {
+                    "--" if !options_ended => options_ended = true,
+                    flag if flag.starts_with('-') && !options_ended => {},
+                    path => targets.push(cwd.join(super::sanitize_path_tool_arg(os, path))),
+                }
+            }
+        }
+
+        let mut removed = Vec::new();
+        while let Some(path) = targets.pop() {
+            if removed.len() >= MAX_REMOVED_PATHS {
+                warn!(
+                    "not backing up more than {MAX_REMOVED_PATHS} files removed by {}",
+                    self.command
+                );
+                break;
+            }
+            let Ok(metadata) = os.fs.symlink_metadata(&path).await else {
+                continue;
+            };
+            if metadata.is_file() {
+                removed.push(path);
+            } else if metadata.is_dir() {
+                let Ok(mut entries) = os.fs.read_dir(&path).await else {
+                    continue;
+                };
+                while let Ok(Some(entry)) = entries.next_entry().await {
+                    targets.push(path.join(entry.file_name()));
+                }
+            }
+        }
+        removed
+    }
+
+    pub async fn invoke(&self, os: &Os, output: &mut impl Write, agent: Option<&Agent>) -> Result<InvokeOutput> {
+        let env = agent.map(|agent| agent.resolved_env(os)).unwrap_or_default();
+        let output = run_command(os, &self.command, MAX_TOOL_RESPONSE_SIZE / 3, env, Some(output)).await?;
         let clean_stdout = sanitize_unicode_tags(&output.stdout);
         let clean_stderr = sanitize_unicode_tags(&output.stderr);
 
@@ -747,4 +816,44 @@ mod tests {
         assert!(user_agent_value.contains("ExistingValue"));
         assert!(user_agent_value.contains(USER_AGENT_APP_NAME));
     }
+
+    #[tokio::test]
+    async fn test_removed_paths() {
+        let os = Os::new().await.unwrap();
+        os.fs.create_dir_all("/project/dir/nested").await.unwrap();
+        for path in [
+            "/project/a.txt",
+            "/project/-b.txt",
+            "/project/dir/c.txt",
+            "/project/dir/nested/d.txt",
+        ] {
+            os.fs.write(path, "content").await.unwrap();
+        }
+
+        let removed = |command: &str| {
+            let command = ExecuteCommand {
+                command: command.to_string(),
+                summary: None,
+            };
+            let os = os.clone();
+            async move {
+                let mut paths = command.removed_paths(&os).await;
+                paths.sort();
+                paths
+            }
+        };
+
+        assert_eq!(removed("rm /project/a.txt").await, vec![
+            os.fs.chroot_path("/project/a.txt")
+        ]);
+        assert_eq!(removed("rm -f -- /project/-b.txt").await, vec![
+            os.fs.chroot_path("/project/-b.txt")
+        ]);
+        assert_eq!(removed("ls /project && sudo rm -rf /project/dir").await, vec![
+            os.fs.chroot_path("/project/dir/c.txt"),
+            os.fs.chroot_path("/project/dir/nested/d.txt"),
+        ]);
+        assert!(removed("cat /project/a.txt").await.is_empty());
+        assert!(removed("rm /project/missing.txt").await.is_empty());
+    }
 }
@@ -1,4 +1,7 @@
-use std::collections::VecDeque;
+use std::collections::{
+    HashMap,
+    VecDeque,
+};
 use std::io::Write;
 use std::process::Stdio;
 
@@ -22,6 +25,7 @@ use crate::util::env_var::get_chat_shell;
 /// # Arguments
 /// * `command` - The command to run
 /// * `max_result_size` - max size of output streams, truncating if required
+/// * `env` - environment variables set on top of the inherited environment, e.g. from the agent
 /// * `updates` - output stream to push informational messages about the progress
 /// # Returns
 /// A [`CommandResult`]
@@ -29,21 +33,27 @@ pub async fn run_command<W: Write>(
     os: &Os,
     command: &str,
     max_result_size: usize,
+    env: HashMap<String, String>,
     mut updates: Option<W>,
 ) -> Result<CommandResult> {
     let shell = get_chat_shell();
 
     // Set up environment variables with user agent metadata for CloudTrail tracking
-    let env_vars = env_vars_with_user_agent(os);
+    let mut env_vars = env_vars_with_user_agent(os);
+    env_vars.extend(env);
 
     // We need to maintain a handle on stderr and stdout, but pipe it to the terminal as well
-    let mut child = tokio::process::Command::new(shell)
-        .arg("-c")
+    let mut cmd = tokio::process::Command::new(shell);
+    cmd.arg("-c")
         .arg(command)
         .envs(env_vars)
         .stdin(Stdio::inherit())
         .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(dir) = os.env.changed_dir() {
+        cmd.current_dir(dir);
+    }
+    let mut child = cmd
         .spawn()
         .wrap_err_with(|| format!("Unable to spawn command '{}'", command))?;
 
@@ -144,7 +154,7 @@ mod tests {
         });
         let out = serde_json::from_value::<ExecuteCommand>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, None)
             .await
             .unwrap();
 
@@ -162,7 +172,7 @@ mod tests {
         });
         let out = serde_json::from_value::<ExecuteCommand>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, None)
             .await
             .unwrap();
 
@@ -180,7 +190,7 @@ mod tests {
         });
         let out = serde_json::from_value::<ExecuteCommand>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, None)
             .await
             .unwrap();
         if let OutputKind::Json(json) = out.output {
@@ -1,11 +1,17 @@
 use std::collections::VecDeque;
 use std::fs::Metadata;
-use std::io::Write;
+use std::io::{
+    SeekFrom,
+    Write,
+};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
 
 use crossterm::queue;
 use crossterm::style::{
     self,
 };
+use disk_cache::CacheKey;
 use eyre::{
     Result,
     bail,
@@ -16,6 +22,13 @@ use serde::{
     Serialize,
 };
 use syntect::util::LinesWithEndings;
+use tokio::io::{
+    AsyncBufRead,
+    AsyncBufReadExt,
+    AsyncReadExt,
+    AsyncSeekExt,
+    BufReader,
+};
 use tracing::{
     debug,
     error,
@@ -34,6 +47,11 @@ use crate::cli::agent::{
     PermissionEvalResult,
 };
 use crate::cli::chat::tools::display_purpose;
+use crate::cli::chat::util::binary;
+use crate::cli::chat::util::documents::{
+    self,
+    DocumentKind,
+};
 use crate::cli::chat::util::images::{
     handle_images_from_paths,
     is_supported_image_type,
@@ -62,6 +80,7 @@ pub enum FsReadOperation {
     Directory(FsDirectory),
     Search(FsSearch),
     Image(FsImage),
+    Bytes(FsBytes),
 }
 
 impl FsRead {
@@ -180,7 +199,8 @@ impl FsRead {
                         match op {
                             FsReadOperation::Line(FsLine { path, .. })
                             | FsReadOperation::Directory(FsDirectory { path, .. })
-                            | FsReadOperation::Search(FsSearch { path, .. }) => {
+                            | FsReadOperation::Search(FsSearch { path, .. })
+                            | FsReadOperation::Bytes(FsBytes { path, .. }) => {
                                 let Ok(path) = paths::canonicalizes_path(os, path) else {
                                     ask = true;
                                     continue;
@@ -266,6 +286,50 @@ impl FsRead {
         }
     }
 
+    /// Key identifying the result of these operations within `scope`, which changes whenever a
+    /// file being read is modified. Directory listings and images are never cached.
+    pub async fn cache_key(&self, os: &Os, scope: &str) -> Option<CacheKey> {
+        let mut parts = vec!["fs_read".to_string(), scope.to_string()];
+        for op in &self.operations {
+            let path = match op {
+                FsReadOperation::Line(fs_line) => {
+                    parts.extend([
+                        "Line".to_string(),
+                        format!("{:?}", fs_line.start_line),
+                        format!("{:?}", fs_line.end_line),
+                    ]);
+                    &fs_line.path
+                },
+                FsReadOperation::Search(fs_search) => {
+                    parts.extend([
+                        "Search".to_string(),
+                        fs_search.pattern.clone(),
+                        format!("{:?}", fs_search.context_lines),
+                    ]);
+                    &fs_search.path
+                },
+                FsReadOperation::Bytes(fs_bytes) => {
+                    parts.extend([
+                        "Bytes".to_string(),
+                        format!("{:?}", fs_bytes.offset),
+                        format!("{:?}", fs_bytes.length),
+                    ]);
+                    &fs_bytes.path
+                },
+                FsReadOperation::Directory(_) | FsReadOperation::Image(_) => return None,
+            };
+            let path = sanitize_path_tool_arg(os, path);
+            let metadata = tokio::fs::metadata(&path).await.ok()?;
+            let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+            parts.extend([
+                path.to_string_lossy().into_owned(),
+                modified.as_nanos().to_string(),
+                metadata.len().to_string(),
+            ]);
+        }
+        Some(CacheKey::new(parts))
+    }
+
     pub async fn invoke(&self, os: &Os, updates: &mut impl Write) -> Result<InvokeOutput> {
         if self.operations.len() == 1 {
             // Single operation - return result directly
@@ -364,6 +428,7 @@ impl FsReadOperation {
             FsReadOperation::Directory(fs_directory) => fs_directory.validate(os).await,
             FsReadOperation::Search(fs_search) => fs_search.validate(os).await,
             FsReadOperation::Image(fs_image) => fs_image.validate(os).await,
+            FsReadOperation::Bytes(fs_bytes) => fs_bytes.validate(os).await,
         }
     }
 
@@ -373,6 +438,7 @@ impl FsReadOperation {
             FsReadOperation::Directory(fs_directory) => fs_directory.queue_description(updates),
             FsReadOperation::Search(fs_search) => fs_search.queue_description(updates),
             FsReadOperation::Image(fs_image) => fs_image.queue_description(updates),
+            FsReadOperation::Bytes(fs_bytes) => fs_bytes.queue_description(updates),
         }
     }
 
@@ -382,6 +448,7 @@ impl FsReadOperation {
             FsReadOperation::Directory(fs_directory) => fs_directory.invoke(os, updates).await,
             FsReadOperation::Search(fs_search) => fs_search.invoke(os, updates).await,
             FsReadOperation::Image(fs_image) => fs_image.invoke(updates).await,
+            FsReadOperation::Bytes(fs_bytes) => fs_bytes.invoke(os, updates).await,
         }
     }
 }
@@ -460,9 +527,18 @@ impl FsLine {
 
     pub async fn queue_description(&self, os: &Os, updates: &mut impl Write) -> Result<()> {
         let path = sanitize_path_tool_arg(os, &self.path);
-        let file_bytes = os.fs.read(&path).await?;
-        let file_content = String::from_utf8_lossy(&file_bytes);
-        let line_count = file_content.lines().count();
+        if let Some(kind) = DocumentKind::from_path(&path) {
+            queue!(
+                updates,
+                style::Print(format!("Extracting text from {} document: ", kind.name())),
+                StyledText::success_fg(),
+                style::Print(&self.path),
+                StyledText::reset(),
+            )?;
+            return Ok(());
+        }
+
+        let line_count = count_lines(BufReader::new(os.fs.open(&path).await?)).await?;
         queue!(
             updates,
             style::Print("Reading file: "),
@@ -501,10 +577,18 @@ impl FsLine {
     pub async fn invoke(&self, os: &Os, updates: &mut impl Write) -> Result<InvokeOutput> {
         let path = sanitize_path_tool_arg(os, &self.path);
         debug!(?path, "Reading");
-        let file_bytes = os.fs.read(&path).await?;
-        let file_content = String::from_utf8_lossy(&file_bytes);
-        let file_content = sanitize_unicode_tags(&file_content);
-        let line_count = file_content.lines().count();
+        let document = documents::extract_text(os, &path).await?;
+        if document.is_none() {
+            let sample = binary::sniff(os, &path).await?;
+            if binary::is_binary(&sample) {
+                return self.invoke_binary(os, &path, &sample, updates).await;
+            }
+        }
+
+        let line_count = match &document {
+            Some(text) => count_lines(text.as_bytes()).await?,
+            None => count_lines(BufReader::new(os.fs.open(&path).await?)).await?,
+        };
         let (start, end) = (
             convert_negative_index(line_count, self.start_line()),
             convert_negative_index(line_count, self.end_line()),
@@ -522,15 +606,10 @@ impl FsLine {
             );
         }
 
-        // The range should be inclusive on both ends.
-        let file_contents = file_content
-            .lines()
-            .skip(start)
-            .take(end - start + 1)
-            .collect::<Vec<_>>()
-            .join("\n");
-
-        let byte_count = file_contents.len();
+        let (file_contents, byte_count) = match &document {
+            Some(text) => read_lines(text.as_bytes(), start, end).await?,
+            None => read_lines(BufReader::new(os.fs.open(&path).await?), start, end).await?,
+        };
         if byte_count > MAX_TOOL_RESPONSE_SIZE {
             bail!(
                 "This tool only supports reading {MAX_TOOL_RESPONSE_SIZE} bytes at a
@@ -554,6 +633,48 @@ time. You tried to read {byte_count} bytes. Try executing with fewer lines speci
         })
     }
 
+    /// Returns metadata about a binary file in place of its contents, or the file itself if it is
+    /// an image the model can view.
+    async fn invoke_binary(
+        &self,
+        os: &Os,
+        path: &Path,
+        sample: &[u8],
+        updates: &mut impl Write,
+    ) -> Result<InvokeOutput> {
+        let file_type = binary::detect_file_type(sample);
+        if let Some(path_str) = path.to_str() {
+            if file_type.is_some_and(|t| t.image) && is_supported_image_type(path_str) {
+                let images = handle_images_from_paths(updates, &[path_str.to_string()]);
+                if !images.is_empty() {
+                    super::queue_function_result("Successfully read image", updates, false, false)?;
+                    return Ok(InvokeOutput {
+                        output: OutputKind::Images(images),
+                    });
+                }
+            }
+        }
+
+        let size = os.fs.symlink_metadata(path).await?.len();
+        let description = describe_binary(sample);
+        super::queue_function_result(
+            &format!(
+                "{} is a binary file ({description}), returning its metadata",
+                path.display()
+            ),
+            updates,
+            false,
+            false,
+        )?;
+
+        Ok(InvokeOutput {
+            output: OutputKind::Text(format!(
+                "'{}' is a binary file, so its contents were not read.\nType: {description}\nSize: {size} bytes\nUse the Bytes mode to read a range of its raw bytes as a hex dump.",
+                self.path
+            )),
+        })
+    }
+
     fn start_line(&self) -> i32 {
         self.start_line.unwrap_or(Self::DEFAULT_START_LINE)
     }
@@ -582,9 +703,17 @@ impl FsSearch {
         if !path.exists() {
             bail!("File not found: {}", relative_path);
         }
-        if !os.fs.symlink_metadata(path).await?.is_file() {
+        if !os.fs.symlink_metadata(&path).await?.is_file() {
             bail!("Path is not a file: {}", relative_path);
         }
+        let sample = binary::sniff(os, &path).await?;
+        if binary::is_binary(&sample) && DocumentKind::from_path(&path).is_none() {
+            bail!(
+                "Cannot search binary file: {} ({}). Use the Bytes mode to inspect it instead.",
+                relative_path,
+                describe_binary(&sample)
+            );
+        }
         if self.pattern.is_empty() {
             bail!("Search pattern cannot be empty");
         }
@@ -610,8 +739,10 @@ impl FsSearch {
         let file_path = sanitize_path_tool_arg(os, &self.path);
         let pattern = &self.pattern;
 
-        let file_bytes = os.fs.read(&file_path).await?;
-        let file_content = String::from_utf8_lossy(&file_bytes);
+        let file_content = match documents::extract_text(os, &file_path).await? {
+            Some(text) => text,
+            None => String::from_utf8_lossy(&os.fs.read(&file_path).await?).into_owned(),
+        };
         let file_content = sanitize_unicode_tags(&file_content);
         let lines: Vec<&str> = LinesWithEndings::from(&file_content).collect();
 
@@ -665,6 +796,102 @@ impl FsSearch {
     }
 }
 
+/// Read a range of raw bytes from a file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FsBytes {
+    pub path: String,
+    pub offset: Option<u64>,
+    pub length: Option<u64>,
+}
+
+impl FsBytes {
+    const DEFAULT_LENGTH: u64 = 4096;
+    const DEFAULT_OFFSET: u64 = 0;
+    /// Hex dumps take a little over four times as many bytes as the data they show.
+    const MAX_LENGTH: u64 = (MAX_TOOL_RESPONSE_SIZE / 5) as u64;
+
+    pub async fn validate(&mut self, os: &Os) -> Result<()> {
+        let path = sanitize_path_tool_arg(os, &self.path);
+        if !path.exists() {
+            bail!("'{}' does not exist", self.path);
+        }
+        let is_file = os.fs.symlink_metadata(&path).await?.is_file();
+        if !is_file {
+            bail!("'{}' is not a file", self.path);
+        }
+        match self.length() {
+            0 => bail!("length must be greater than 0"),
+            length if length > Self::MAX_LENGTH => bail!(
+                "This tool only supports reading {} bytes at a time. You tried to read {length} bytes.",
+                Self::MAX_LENGTH
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn queue_description(&self, updates: &mut impl Write) -> Result<()> {
+        queue!(
+            updates,
+            style::Print("Reading bytes: "),
+            StyledText::success_fg(),
+            style::Print(&self.path),
+            StyledText::reset(),
+            style::Print(", "),
+            StyledText::success_fg(),
+            style::Print(self.length()),
+            StyledText::reset(),
+            style::Print(" bytes from offset "),
+            StyledText::success_fg(),
+            style::Print(self.offset()),
+            StyledText::reset(),
+        )?;
+        Ok(())
+    }
+
+    pub async fn invoke(&self, os: &Os, updates: &mut impl Write) -> Result<InvokeOutput> {
+        let path = sanitize_path_tool_arg(os, &self.path);
+        let size = os.fs.symlink_metadata(&path).await?.len();
+        let offset = self.offset();
+        if offset > size {
+            bail!("offset: {offset} is past the end of the file, which is {size} bytes long");
+        }
+
+        let sample = binary::sniff(os, &path).await?;
+        let mut file = os.fs.open(&path).await?;
+        file.seek(SeekFrom::Start(offset)).await?;
+        let mut bytes = Vec::new();
+        file.take(self.length()).read_to_end(&mut bytes).await?;
+
+        let (description, contents) = if binary::is_binary(&sample) {
+            (describe_binary(&sample), binary::hex_dump(&bytes, offset))
+        } else {
+            ("text", sanitize_unicode_tags(&String::from_utf8_lossy(&bytes)))
+        };
+
+        super::queue_function_result(
+            &format!("Successfully read {} bytes from {}", bytes.len(), &path.display()),
+            updates,
+            false,
+            false,
+        )?;
+
+        Ok(InvokeOutput {
+            output: OutputKind::Text(format!(
+                "Bytes {offset}-{} of {size} ({description}):\n{contents}",
+                offset + bytes.len() as u64
+            )),
+        })
+    }
+
+    fn offset(&self) -> u64 {
+        self.offset.unwrap_or(Self::DEFAULT_OFFSET)
+    }
+
+    fn length(&self) -> u64 {
+        self.length.unwrap_or(Self::DEFAULT_LENGTH)
+    }
+}
+
 /// List directory contents.
 #[derive(Debug, Clone, Deserialize)]
 pub struct FsDirectory {
@@ -808,6 +1035,67 @@ impl FsDirectory {
     }
 }
 
+/// Counts lines the same way as [str::lines], without reading the whole file into memory.
+async fn count_lines(mut reader: impl AsyncBufRead + Unpin) -> Result<usize> {
+    let mut count = 0;
+    let mut last_byte = None;
+    loop {
+        let buf = reader.fill_buf().await?;
+        let Some(&last) = buf.last() else {
+            break;
+        };
+        count += buf.iter().filter(|&&b| b == b'\n').count();
+        last_byte = Some(last);
+        let len = buf.len();
+        reader.consume(len);
+    }
+    if last_byte.is_some_and(|b| b != b'\n') {
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Reads the lines from `start` to `end` (both inclusive and zero based) one at a time, so that
+/// only the requested range of a large file is ever held in memory.
+///
+/// Returns the lines joined by newlines along with the size of the full range in bytes. Lines past
+/// [MAX_TOOL_RESPONSE_SIZE] are counted but not kept.
+async fn read_lines(mut reader: impl AsyncBufRead + Unpin, start: usize, end: usize) -> Result<(String, usize)> {
+    let mut line = Vec::new();
+    let mut contents = String::new();
+    let mut byte_count = 0;
+    let mut index = 0;
+    while index <= end && reader.read_until(b'\n', &mut line).await? > 0 {
+        if index >= start {
+            trim_line_ending(&mut line);
+            let text = sanitize_unicode_tags(&String::from_utf8_lossy(&line));
+            let separator = if index > start { "\n" } else { "" };
+            byte_count += separator.len() + text.len();
+            if byte_count <= MAX_TOOL_RESPONSE_SIZE {
+                contents.push_str(separator);
+                contents.push_str(&text);
+            }
+        }
+        line.clear();
+        index += 1;
+    }
+    Ok((contents, byte_count))
+}
+
+/// Strips the line ending left by [AsyncBufReadExt::read_until], matching [str::lines].
+fn trim_line_ending(line: &mut Vec<u8>) {
+    if line.last() == Some(&b'\n') {
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+    }
+}
+
+fn describe_binary(sample: &[u8]) -> &'static str {
+    binary::detect_file_type(sample).map_or("unrecognized binary data", |t| t.description)
+}
+
 /// Converts negative 1-based indices to positive 0-based indices.
 fn convert_negative_index(line_count: usize, i: i32) -> usize {
     if i <= 0 {
@@ -912,6 +1200,10 @@ mod tests {
             "operations": [{ "image_paths": ["/img1.png", "/img2.jpg"], "mode": "Image" }]
         }))
         .unwrap();
+        serde_json::from_value::<FsRead>(serde_json::json!({
+            "operations": [{ "path": "/program", "mode": "Bytes", "offset": 1024, "length": 256 }]
+        }))
+        .unwrap();
 
         // Test mixed batch operations
         serde_json::from_value::<FsRead>(serde_json::json!({
@@ -926,6 +1218,30 @@ mod tests {
         .unwrap();
     }
 
+    #[tokio::test]
+    async fn test_fs_read_cache_key() {
+        let os = setup_test_directory().await;
+        let read = |v: serde_json::Value| serde_json::from_value::<FsRead>(v).unwrap();
+        let line =
+            read(serde_json::json!({ "operations": [{ "path": TEST_FILE_PATH, "mode": "Line", "end_line": 2 }] }));
+
+        let key = line.cache_key(&os, "turn").await.unwrap();
+        assert_eq!(line.cache_key(&os, "turn").await, Some(key.clone()));
+        assert_ne!(line.cache_key(&os, "other turn").await, Some(key.clone()));
+        let other_lines =
+            read(serde_json::json!({ "operations": [{ "path": TEST_FILE_PATH, "mode": "Line", "end_line": 3 }] }));
+        assert_ne!(other_lines.cache_key(&os, "turn").await, Some(key.clone()));
+
+        // Modifying the file invalidates the key.
+        os.fs.write(TEST_FILE_PATH, "modified\n").await.unwrap();
+        assert_ne!(line.cache_key(&os, "turn").await, Some(key));
+
+        let directory = read(serde_json::json!({ "operations": [{ "path": "/", "mode": "Directory" }] }));
+        assert_eq!(directory.cache_key(&os, "turn").await, None);
+        let missing = read(serde_json::json!({ "operations": [{ "path": "/missing.txt", "mode": "Line" }] }));
+        assert_eq!(missing.cache_key(&os, "turn").await, None);
+    }
+
     #[tokio::test]
     async fn test_fs_read_line_invoke() {
         let os = setup_test_directory().await;
@@ -1337,6 +1653,165 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_fs_read_binary_file_returns_metadata() {
+        let os = Os::new().await.unwrap();
+        let mut stdout = std::io::stdout();
+
+        let mut elf_data = b"\x7fELF\x02\x01\x01\x00".to_vec();
+        elf_data.extend_from_slice(&[0u8; 56]);
+        os.fs.write("/program", &elf_data).await.unwrap();
+
+        let v = serde_json::json!({ "operations": [{ "path": "/program", "mode": "Line" }] });
+        let output = serde_json::from_value::<FsRead>(v)
+            .unwrap()
+            .invoke(&os, &mut stdout)
+            .await
+            .unwrap();
+
+        if let OutputKind::Text(text) = output.output {
+            assert!(
+                text.contains("ELF executable"),
+                "should report the detected type: {text}"
+            );
+            assert!(text.contains("64 bytes"), "should report the file size: {text}");
+            assert!(!text.contains('\0'), "should not include the raw contents");
+        } else {
+            panic!("expected text output");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fs_read_binary_image_returns_image() {
+        let os = Os::new().await.unwrap();
+        let mut stdout = std::io::stdout();
+
+        let mut png_data = b"\x89PNG\r\n\x1a\n".to_vec();
+        png_data.extend_from_slice(&[0u8; 32]);
+        os.fs.write("/image.png", &png_data).await.unwrap();
+
+        let v = serde_json::json!({ "operations": [{ "path": "/image.png", "mode": "Line" }] });
+        let output = serde_json::from_value::<FsRead>(v)
+            .unwrap()
+            .invoke(&os, &mut stdout)
+            .await
+            .unwrap();
+
+        if let OutputKind::Images(images) = output.output {
+            assert_eq!(images.len(), 1);
+            assert_eq!(images[0].1.filename, "image.png");
+        } else {
+            panic!("expected image output");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fs_read_bytes_invoke() {
+        let os = setup_test_directory().await;
+        let mut stdout = std::io::stdout();
+        os.fs.write("/data.bin", b"\x00\x01\x02\x03abcdefgh").await.unwrap();
+
+        let v =
+            serde_json::json!({ "operations": [{ "path": "/data.bin", "mode": "Bytes", "offset": 2, "length": 4 }] });
+        let output = serde_json::from_value::<FsRead>(v)
+            .unwrap()
+            .invoke(&os, &mut stdout)
+            .await
+            .unwrap();
+        if let OutputKind::Text(text) = output.output {
+            assert_eq!(
+                text,
+                "Bytes 2-6 of 12 (unrecognized binary data):\n00000002: 0203 6162                                ..ab\n"
+            );
+        } else {
+            panic!("expected text output");
+        }
+
+        // Text files are returned as text, and reads past the end are cut short.
+        let v = serde_json::json!({ "operations": [{ "path": TEST_FILE_PATH, "mode": "Bytes", "offset": 3, "length": 100_000 }] });
+        let mut fs_read = serde_json::from_value::<FsRead>(v).unwrap();
+        assert!(fs_read.validate(&os).await.is_err(), "length should be capped");
+        let v = serde_json::json!({ "operations": [{ "path": TEST_FILE_PATH, "mode": "Bytes", "offset": 3 }] });
+        let output = serde_json::from_value::<FsRead>(v)
+            .unwrap()
+            .invoke(&os, &mut stdout)
+            .await
+            .unwrap();
+        if let OutputKind::Text(text) = output.output {
+            let expected = format!(
+                "Bytes 3-{len} of {len} (text):\n{}",
+                &TEST_FILE_CONTENTS[3..],
+                len = TEST_FILE_CONTENTS.len()
+            );
+            assert_eq!(text, expected);
+        } else {
+            panic!("expected text output");
+        }
+
+        let v = serde_json::json!({ "operations": [{ "path": "/data.bin", "mode": "Bytes", "offset": 13 }] });
+        assert!(
+            serde_json::from_value::<FsRead>(v)
+                .unwrap()
+                .invoke(&os, &mut stdout)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fs_read_notebook() {
+        let os = Os::new().await.unwrap();
+        let mut stdout = std::io::stdout();
+        let notebook = serde_json::json!({
+            "metadata": { "language_info": { "name": "python" } },
+            "cells": [
+                { "cell_type": "markdown", "source": "# Results" },
+                { "cell_type": "code", "source": "print('hello')", "outputs": [{ "output_type": "stream", "text": "hello\n" }] }
+            ]
+        });
+        os.fs.write("/analysis.ipynb", notebook.to_string()).await.unwrap();
+
+        let v = serde_json::json!({ "operations": [{ "path": "/analysis.ipynb", "mode": "Line", "start_line": 3, "end_line": 5 }] });
+        let output = serde_json::from_value::<FsRead>(v)
+            .unwrap()
+            .invoke(&os, &mut stdout)
+            .await
+            .unwrap();
+        if let OutputKind::Text(text) = output.output {
+            assert_eq!(text, "```python\nprint('hello')\n```");
+        } else {
+            panic!("expected text output");
+        }
+
+        let v = serde_json::json!({ "operations": [{ "path": "/analysis.ipynb", "mode": "Search", "pattern": "results" }] });
+        let output = serde_json::from_value::<FsRead>(v)
+            .unwrap()
+            .invoke(&os, &mut stdout)
+            .await
+            .unwrap();
+        if let OutputKind::Text(value) = output.output {
+            let matches: Vec<SearchMatch> = serde_json::from_str(&value).unwrap();
+            assert_eq!(matches.len(), 1);
+            assert_eq!(matches[0].line_number, 1);
+        } else {
+            panic!("expected Text output");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fs_search_binary_file() {
+        let os = Os::new().await.unwrap();
+        os.fs.write("/archive.gz", b"\x1f\x8b\x08\x00hello").await.unwrap();
+
+        let v = serde_json::json!({ "operations": [{ "path": "/archive.gz", "mode": "Search", "pattern": "hello" }] });
+        let err = serde_json::from_value::<FsRead>(v)
+            .unwrap()
+            .validate(&os)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("gzip compressed data"), "{err}");
+    }
+
     #[tokio::test]
     async fn test_fs_read_batch_mixed_operations() {
         let os = setup_test_directory().await;
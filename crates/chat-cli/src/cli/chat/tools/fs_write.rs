@@ -85,6 +85,11 @@ pub enum FsWrite {
         new_str: String,
         summary: Option<String>,
     },
+    /// Deleted files are moved to the trash of the OS, and a copy is kept in the backups directory,
+    /// unless the agent allows hard deletes, see [allows_hard_delete]. Deletes always ask for
+    /// confirmation, see [FsWrite::eval_perm].
+    #[serde(rename = "delete")]
+    Delete { path: String, summary: Option<String> },
 }
 
 impl FsWrite {
@@ -94,14 +99,20 @@ impl FsWrite {
             FsWrite::StrReplace { path, .. } => path.as_str(),
             FsWrite::Insert { path, .. } => path.as_str(),
             FsWrite::Append { path, .. } => path.as_str(),
+            FsWrite::Delete { path, .. } => path.as_str(),
         })
     }
 
+    pub fn is_delete(&self) -> bool {
+        matches!(self, FsWrite::Delete { .. })
+    }
+
     pub async fn invoke(
         &self,
         os: &Os,
         output: &mut impl Write,
         line_tracker: &mut HashMap<String, FileLineTracker>,
+        agent: Option<&Agent>,
     ) -> Result<InvokeOutput> {
         let cwd = os.env.current_dir()?;
         let path = self.path(os);
@@ -192,6 +203,22 @@ impl FsWrite {
                 file.push_str(new_str);
                 write_to_file(os, &path, file).await?;
             },
+            FsWrite::Delete { .. } => {
+                queue!(
+                    output,
+                    style::Print("Deleting: "),
+                    StyledText::success_fg(),
+                    style::Print(format_path(cwd, &path)),
+                    StyledText::reset(),
+                    style::Print("\n"),
+                )?;
+
+                if agent.is_some_and(allows_hard_delete) {
+                    os.fs.remove_file(&path).await?;
+                } else {
+                    os.fs.move_to_trash(&path).await?;
+                }
+            },
         };
 
         self.update_line_tracker_after_invoke(os, line_tracker).await?;
@@ -248,11 +275,20 @@ impl FsWrite {
             0
         };
 
+        // Calculate actual lines added and removed by analyzing the diff
+        let (lines_added, lines_removed) = match self {
+            // The file is gone, so every line it had before was removed
+            FsWrite::Delete { .. } => (
+                0,
+                line_tracker
+                    .get(path.to_string_lossy().as_ref())
+                    .map_or(0, |tracker| tracker.before_fswrite_lines),
+            ),
+            _ => self.calculate_diff_lines(os).await?,
+        };
+
         let tracker = line_tracker.entry(path.to_string_lossy().to_string()).or_default();
         tracker.after_fswrite_lines = after_lines;
-
-        // Calculate actual lines added and removed by analyzing the diff
-        let (lines_added, lines_removed) = self.calculate_diff_lines(os).await?;
         tracker.lines_added_by_agent = lines_added;
         tracker.lines_removed_by_agent = lines_removed;
 
@@ -296,6 +332,7 @@ impl FsWrite {
                 let lines_added = new_str.lines().count();
                 (lines_added, 0)
             },
+            FsWrite::Delete { .. } => (0, 0),
         };
 
         Ok(result)
@@ -381,6 +418,18 @@ impl FsWrite {
                 // Display summary as purpose if available after the diff
                 super::display_purpose(self.get_summary(), output)?;
 
+                Ok(())
+            },
+            FsWrite::Delete { .. } => {
+                queue!(
+                    output,
+                    StyledText::warning_fg(),
+                    style::Print("This file will be deleted\n"),
+                    StyledText::reset(),
+                )?;
+
+                super::display_purpose(self.get_summary(), output)?;
+
                 Ok(())
             },
         }
@@ -407,6 +456,17 @@ impl FsWrite {
                     bail!("Content to append must not be empty")
                 };
             },
+            FsWrite::Delete { path, .. } => {
+                let path = sanitize_path_tool_arg(os, path);
+                if !os
+                    .fs
+                    .symlink_metadata(&path)
+                    .await
+                    .is_ok_and(|metadata| metadata.is_file())
+                {
+                    bail!("The provided path must be an existing file in order to delete it")
+                }
+            },
         }
 
         Ok(())
@@ -419,6 +479,7 @@ impl FsWrite {
             FsWrite::StrReplace { path, .. } => path,
             FsWrite::Insert { path, .. } => path,
             FsWrite::Append { path, .. } => path,
+            FsWrite::Delete { path, .. } => path,
         };
         // Sanitize the path to handle tilde expansion
         let path = sanitize_path_tool_arg(os, path);
@@ -460,6 +521,7 @@ impl FsWrite {
             FsWrite::StrReplace { summary, .. } => summary.as_ref(),
             FsWrite::Insert { summary, .. } => summary.as_ref(),
             FsWrite::Append { summary, .. } => summary.as_ref(),
+            FsWrite::Delete { summary, .. } => summary.as_ref(),
         }
     }
 
@@ -525,7 +587,8 @@ impl FsWrite {
                             Self::Create { path, .. }
                             | Self::Insert { path, .. }
                             | Self::Append { path, .. }
-                            | Self::StrReplace { path, .. } => {
+                            | Self::StrReplace { path, .. }
+                            | Self::Delete { path, .. } => {
                                 let Ok(path) = paths::canonicalizes_path(os, path) else {
                                     return PermissionEvalResult::Ask;
                                 };
@@ -538,6 +601,10 @@ impl FsWrite {
                                             .collect::<Vec<_>>()
                                     });
                                 }
+                                // Deleting always asks, whatever the paths allowed for writing
+                                if self.is_delete() {
+                                    return PermissionEvalResult::Ask;
+                                }
                                 if is_in_allowlist || allow_set.is_match(path.as_ref() as &str) {
                                     return PermissionEvalResult::Allow;
                                 }
@@ -557,12 +624,23 @@ impl FsWrite {
                     },
                 }
             },
-            None if is_in_allowlist => PermissionEvalResult::Allow,
+            None if is_in_allowlist && !self.is_delete() => PermissionEvalResult::Allow,
             _ => PermissionEvalResult::Ask,
         }
     }
 }
 
+/// Whether the agent lets `fs_write` delete files without keeping a copy in the backups directory,
+/// set with `toolsSettings.fs_write.allowHardDelete`.
+pub fn allows_hard_delete(agent: &Agent) -> bool {
+    agent
+        .tools_settings
+        .get("fs_write")
+        .and_then(|settings| settings.get("allowHardDelete"))
+        .and_then(serde_json::Value::as_bool)
+        .unwrap_or_default()
+}
+
 /// Writes `content` to `path`, adding a newline if necessary.
 async fn write_to_file(os: &Os, path: impl AsRef<Path>, mut content: String) -> Result<()> {
     let path_ref = path.as_ref();
@@ -962,7 +1040,7 @@ mod tests {
         });
         serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout, &mut line_tracker)
+            .invoke(&os, &mut stdout, &mut line_tracker, None)
             .await
             .unwrap();
 
@@ -979,7 +1057,7 @@ mod tests {
         });
         serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout, &mut line_tracker)
+            .invoke(&os, &mut stdout, &mut line_tracker, None)
             .await
             .unwrap();
 
@@ -997,7 +1075,7 @@ mod tests {
         });
         serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout, &mut line_tracker)
+            .invoke(&os, &mut stdout, &mut line_tracker, None)
             .await
             .unwrap();
 
@@ -1023,7 +1101,7 @@ mod tests {
         assert!(
             serde_json::from_value::<FsWrite>(v)
                 .unwrap()
-                .invoke(&os, &mut stdout, &mut line_tracker)
+                .invoke(&os, &mut stdout, &mut line_tracker, None)
                 .await
                 .is_err()
         );
@@ -1038,7 +1116,7 @@ mod tests {
         assert!(
             serde_json::from_value::<FsWrite>(v)
                 .unwrap()
-                .invoke(&os, &mut stdout, &mut line_tracker)
+                .invoke(&os, &mut stdout, &mut line_tracker, None)
                 .await
                 .is_err()
         );
@@ -1052,7 +1130,7 @@ mod tests {
         });
         serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout, &mut line_tracker)
+            .invoke(&os, &mut stdout, &mut line_tracker, None)
             .await
             .unwrap();
         assert_eq!(
@@ -1083,7 +1161,7 @@ mod tests {
         });
         serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout, &mut line_tracker)
+            .invoke(&os, &mut stdout, &mut line_tracker, None)
             .await
             .unwrap();
         let actual = os.fs.read_to_string(TEST_FILE_PATH).await.unwrap();
@@ -1116,7 +1194,7 @@ mod tests {
 
         serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout, &mut line_tracker)
+            .invoke(&os, &mut stdout, &mut line_tracker, None)
             .await
             .unwrap();
         let actual = os.fs.read_to_string(TEST_FILE_PATH).await.unwrap();
@@ -1154,7 +1232,7 @@ mod tests {
         });
         serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout, &mut line_tracker)
+            .invoke(&os, &mut stdout, &mut line_tracker, None)
             .await
             .unwrap();
         let actual = os.fs.read_to_string(test_file_path).await.unwrap();
@@ -1169,7 +1247,7 @@ mod tests {
         });
         serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout, &mut line_tracker)
+            .invoke(&os, &mut stdout, &mut line_tracker, None)
             .await
             .unwrap();
         let actual = os.fs.read_to_string(test_file_path).await.unwrap();
@@ -1192,7 +1270,7 @@ mod tests {
 
         serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout, &mut line_tracker)
+            .invoke(&os, &mut stdout, &mut line_tracker, None)
             .await
             .unwrap();
 
@@ -1214,12 +1292,81 @@ mod tests {
 
         let result = serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout, &mut line_tracker)
+            .invoke(&os, &mut stdout, &mut line_tracker, None)
             .await;
 
         assert!(result.is_err(), "Appending to non-existent file should fail");
     }
 
+    #[tokio::test]
+    async fn test_fs_write_tool_delete() {
+        let os = setup_test_directory().await;
+        let mut stdout = std::io::stdout();
+        let mut line_tracker = HashMap::new();
+
+        let fw = serde_json::from_value::<FsWrite>(serde_json::json!({
+            "path": TEST_FILE_PATH,
+            "command": "delete",
+        }))
+        .unwrap();
+        fw.invoke(&os, &mut stdout, &mut line_tracker, None).await.unwrap();
+
+        // The file went to the trash rather than being unlinked
+        assert!(!os.fs.exists(TEST_FILE_PATH));
+        assert_eq!(
+            os.fs.read_to_string("/.Trash/test_file.txt").await.unwrap(),
+            TEST_FILE_CONTENTS
+        );
+        let tracker = line_tracker.get(fw.path(&os).to_string_lossy().as_ref()).unwrap();
+        assert_eq!(tracker.lines_removed_by_agent, TEST_FILE_CONTENTS.lines().count());
+        assert_eq!(tracker.after_fswrite_lines, 0);
+
+        // Hard deletes skip the trash
+        os.fs.write(TEST_FILE_PATH, TEST_FILE_CONTENTS).await.unwrap();
+        os.fs.remove_file("/.Trash/test_file.txt").await.unwrap();
+        let mut agent = Agent::default();
+        agent.tools_settings.insert(
+            ToolSettingTarget("fs_write".to_string()),
+            serde_json::json!({ "allowHardDelete": true }),
+        );
+        fw.invoke(&os, &mut stdout, &mut line_tracker, Some(&agent))
+            .await
+            .unwrap();
+        assert!(!os.fs.exists(TEST_FILE_PATH));
+        assert!(!os.fs.exists("/.Trash/test_file.txt"));
+    }
+
+    #[tokio::test]
+    async fn test_fs_write_tool_delete_validate() {
+        let os = setup_test_directory().await;
+
+        let mut fw = serde_json::from_value::<FsWrite>(serde_json::json!({
+            "path": TEST_FILE_PATH,
+            "command": "delete",
+        }))
+        .unwrap();
+        assert!(fw.validate(&os).await.is_ok());
+
+        let mut fw = serde_json::from_value::<FsWrite>(serde_json::json!({
+            "path": "/",
+            "command": "delete",
+        }))
+        .unwrap();
+        assert!(fw.validate(&os).await.is_err());
+    }
+
+    #[test]
+    fn test_allows_hard_delete() {
+        let mut agent = Agent::default();
+        assert!(!allows_hard_delete(&agent));
+
+        agent.tools_settings.insert(
+            ToolSettingTarget("fs_write".to_string()),
+            serde_json::json!({ "allowHardDelete": true }),
+        );
+        assert!(allows_hard_delete(&agent));
+    }
+
     #[test]
     fn test_lines_with_context() {
         let content = "Hello\nWorld!\nhow\nare\nyou\ntoday?";
@@ -1268,7 +1415,7 @@ mod tests {
 
         let result = serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout, &mut line_tracker)
+            .invoke(&os, &mut stdout, &mut line_tracker, None)
             .await;
 
         match &result {
@@ -1303,7 +1450,7 @@ mod tests {
 
         let result = serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout, &mut line_tracker)
+            .invoke(&os, &mut stdout, &mut line_tracker, None)
             .await;
 
         assert!(result.is_ok(), "Writing to ~/nested/path/file.txt should succeed");
@@ -1460,6 +1607,19 @@ mod tests {
 
         let res = tool_exact_allowed_dir.eval_perm(&os, &agent);
         assert!(matches!(res, PermissionEvalResult::Allow));
+
+        // Deleting asks even in allowed paths, and with fs_write in the allowed tools
+        let tool_delete = serde_json::from_value::<FsWrite>(serde_json::json!({
+            "path": "/some/allow/path/some_file.txt",
+            "command": "delete",
+        }))
+        .unwrap();
+        let res = tool_delete.eval_perm(&os, &agent);
+        assert!(matches!(res, PermissionEvalResult::Ask));
+
+        agent.tools_settings.clear();
+        let res = tool_delete.eval_perm(&os, &agent);
+        assert!(matches!(res, PermissionEvalResult::Ask));
     }
 
     #[tokio::test]
@@ -1480,7 +1640,7 @@ mod tests {
 
         serde_json::from_value::<FsWrite>(create_command)
             .unwrap()
-            .invoke(&os, &mut stdout, &mut line_tracker)
+            .invoke(&os, &mut stdout, &mut line_tracker, None)
             .await
             .unwrap();
 
@@ -1509,7 +1669,7 @@ mod tests {
 
         serde_json::from_value::<FsWrite>(append_command)
             .unwrap()
-            .invoke(&os, &mut stdout, &mut line_tracker)
+            .invoke(&os, &mut stdout, &mut line_tracker, None)
             .await
             .unwrap();
 
@@ -1531,7 +1691,7 @@ mod tests {
 
         serde_json::from_value::<FsWrite>(insert_command)
             .unwrap()
-            .invoke(&os, &mut stdout, &mut line_tracker)
+            .invoke(&os, &mut stdout, &mut line_tracker, None)
             .await
             .unwrap();
 
@@ -1553,7 +1713,7 @@ mod tests {
 
         serde_json::from_value::<FsWrite>(replace_command)
             .unwrap()
-            .invoke(&os, &mut stdout, &mut line_tracker)
+            .invoke(&os, &mut stdout, &mut line_tracker, None)
             .await
             .unwrap();
 
@@ -0,0 +1,133 @@
+use std::io::Write;
+
+use crossterm::queue;
+use crossterm::style::{
+    self,
+};
+use eyre::Result;
+use serde::Deserialize;
+
+use super::knowledge::Knowledge;
+use super::{
+    InvokeOutput,
+    OutputKind,
+};
+use crate::os::Os;
+use crate::theme::StyledText;
+use crate::util::knowledge_store::{
+    KnowledgeStore,
+    scoped_knowledge_bases,
+};
+
+/// Number of results returned when the model does not ask for a specific amount
+const DEFAULT_RESULT_LIMIT: usize = 5;
+
+/// Read-only retrieval over the active agent's knowledge bases. Unlike [Knowledge], this tool
+/// cannot modify the knowledge base, so it is trusted by default.
+///
+/// Searches are restricted to the knowledge bases listed under
+/// `toolsSettings.knowledge.knowledgeBases` in the agent config, if any.
+#[derive(Debug, Clone, Deserialize)]
+pub struct KbSearch {
+    /// The semantic search query
+    pub query: String,
+    /// Name of a single knowledge base to search
+    #[serde(default)]
+    pub knowledge_base: Option<String>,
+    /// Maximum number of results to return
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl KbSearch {
+    /// The kb_search tool shares its feature flag with the knowledge tool
+    pub fn is_enabled(os: &Os) -> bool {
+        Knowledge::is_enabled(os)
+    }
+
+    pub fn queue_description(&self, output: &mut impl Write) -> Result<()> {
+        queue!(
+            output,
+            style::Print("Searching knowledge base for: "),
+            StyledText::success_fg(),
+            style::Print(&self.query),
+            StyledText::reset(),
+        )?;
+
+        if let Some(knowledge_base) = &self.knowledge_base {
+            queue!(
+                output,
+                style::Print(" in "),
+                StyledText::success_fg(),
+                style::Print(knowledge_base),
+                StyledText::reset(),
+            )?;
+        }
+
+        queue!(output, style::Print("\n"))?;
+        Ok(())
+    }
+
+    pub async fn validate(&mut self, _os: &Os) -> Result<()> {
+        if self.query.trim().is_empty() {
+            eyre::bail!("Search query must not be empty");
+        }
+        if self.limit == Some(0) {
+            eyre::bail!("limit must be greater than 0");
+        }
+        Ok(())
+    }
+
+    pub async fn invoke(
+        &self,
+        os: &Os,
+        _updates: &mut impl Write,
+        agent: Option<&crate::cli::Agent>,
+    ) -> Result<InvokeOutput> {
+        let scoped = scoped_knowledge_bases(agent);
+        let names = match &self.knowledge_base {
+            Some(name) if !scoped.is_empty() && !scoped.contains(name) => {
+                return Ok(InvokeOutput {
+                    output: OutputKind::Text(format!(
+                        "Knowledge base '{}' is not available to this agent. Available knowledge bases: {}",
+                        name,
+                        scoped.join(", ")
+                    )),
+                });
+            },
+            Some(name) => vec![name.clone()],
+            None => scoped,
+        };
+
+        let async_knowledge_store = KnowledgeStore::get_async_instance(os, agent)
+            .await
+            .map_err(|e| eyre::eyre!("Failed to access knowledge base: {}", e))?;
+        let store = async_knowledge_store.lock().await;
+
+        let results = store
+            .search_scoped(&self.query, &names)
+            .await
+            .map_err(|e| eyre::eyre!("{}", e))?;
+
+        let limit = self.limit.unwrap_or(DEFAULT_RESULT_LIMIT);
+        let output = if results.is_empty() {
+            format!("No matching entries found for query: \"{}\"", self.query)
+        } else {
+            let mut output = format!("Search results for \"{}\":\n\n", self.query);
+            for result in results.iter().take(limit) {
+                let Some(text) = result.text() else {
+                    continue;
+                };
+                if let Some(path) = result.point.payload.get("path").and_then(|v| v.as_str()) {
+                    output.push_str(&format!("Source: {}\n", path));
+                }
+                output.push_str(&format!("{}\n\n", text));
+            }
+            output
+        };
+
+        Ok(InvokeOutput {
+            output: OutputKind::Text(output),
+        })
+    }
+}
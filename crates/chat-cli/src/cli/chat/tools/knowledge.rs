@@ -22,7 +22,11 @@ use crate::cli::experiment::experiment_manager::{
 };
 use crate::os::Os;
 use crate::theme::StyledText;
-use crate::util::knowledge_store::KnowledgeStore;
+use crate::util::knowledge_store::{
+    KnowledgeStore,
+    is_url,
+    scoped_knowledge_bases,
+};
 use crate::util::tool_permission_checker::is_tool_in_allowlist;
 
 /// The Knowledge tool allows storing and retrieving information across chat sessions.
@@ -95,8 +99,8 @@ impl Knowledge {
     pub async fn validate(&mut self, os: &Os) -> Result<()> {
         match self {
             Knowledge::Add(add) => {
-                // Check if value is intended to be a path (doesn't contain newlines)
-                if !add.value.contains('\n') {
+                // Check if value is intended to be a path (doesn't contain newlines or point to a url)
+                if !add.value.contains('\n') && !is_url(&add.value) {
                     let path = crate::cli::chat::tools::sanitize_path_tool_arg(os, &add.value);
                     if !path.exists() {
                         eyre::bail!("Path '{}' does not exist", add.value);
@@ -331,14 +335,14 @@ impl Knowledge {
                     add.value.clone()
                 };
 
-                match store
-                    .add(
-                        &add.name,
-                        &value_to_use,
-                        crate::util::knowledge_store::AddOptions::with_db_defaults(os),
-                    )
-                    .await
-                {
+                let options = crate::util::knowledge_store::AddOptions::with_db_defaults(os);
+                let added = if is_url(&add.value) {
+                    store.add_url(&add.name, &add.value, options).await
+                } else {
+                    store.add(&add.name, &value_to_use, options).await
+                };
+
+                match added {
                     Ok(context_id) => format!(
                         "Added '{}' to knowledge base with ID: {}. Track active jobs in '/knowledge status' with provided id.",
                         add.name, context_id
@@ -425,7 +429,10 @@ impl Knowledge {
                 .await
                 .unwrap_or_else(|e| format!("Failed to clear knowledge base: {}", e)),
             Knowledge::Search(search) => {
-                let results = store.search(&search.query, search.context_id.as_deref()).await;
+                let results = match search.context_id.as_deref() {
+                    Some(context_id) => store.search(&search.query, Some(context_id)).await,
+                    None => store.search_scoped(&search.query, &scoped_knowledge_bases(agent)).await,
+                };
                 match results {
                     Ok(results) => {
                         if results.is_empty() {
@@ -1,10 +1,14 @@
+pub mod archive;
 pub mod custom_tool;
+pub mod data_preview;
 pub mod delegate;
+pub mod dependencies;
 pub mod execute;
 pub mod fs_read;
 pub mod fs_write;
 pub mod gh_issue;
 pub mod introspect;
+pub mod kb_search;
 pub mod knowledge;
 pub mod thinking;
 pub mod todo;
@@ -21,6 +25,10 @@ use std::path::{
     PathBuf,
 };
 
+use archive::{
+    ArchiveList,
+    ArchiveReadMember,
+};
 use chat_cli_ui::conduit::{
     ControlEnd,
     DestinationStdout,
@@ -34,13 +42,23 @@ use crossterm::style::{
     self,
 };
 use custom_tool::CustomTool;
+use data_preview::DataPreview;
 use delegate::Delegate;
+use dependencies::{
+    DependencyList,
+    DependencyVersions,
+};
+use disk_cache::CacheKey;
 use execute::ExecuteCommand;
 use eyre::Result;
-use fs_read::FsRead;
+use fs_read::{
+    FsRead,
+    FsReadOperation,
+};
 use fs_write::FsWrite;
 use gh_issue::GhIssue;
 use introspect::Introspect;
+use kb_search::KbSearch;
 use knowledge::Knowledge;
 use serde::{
     Deserialize,
@@ -69,9 +87,11 @@ use crate::theme::{
     StyledText,
     theme,
 };
+use crate::util::paths;
+use crate::util::tool_permission_checker::is_tool_in_allowlist;
 
 pub const DEFAULT_APPROVE: [&str; 0] = [];
-pub const NATIVE_TOOLS: [&str; 9] = [
+pub const NATIVE_TOOLS: [&str; 15] = [
     "fs_read",
     "fs_write",
     #[cfg(windows)]
@@ -81,9 +101,15 @@ pub const NATIVE_TOOLS: [&str; 9] = [
     "use_aws",
     "gh_issue",
     "knowledge",
+    "kb_search",
     "thinking",
     "todo_list",
     "delegate",
+    "data_preview",
+    "archive_list",
+    "archive_read_member",
+    "dependency_list",
+    "dependency_versions",
 ];
 
 /// Represents an executable tool use.
@@ -98,9 +124,15 @@ pub enum Tool {
     GhIssue(GhIssue),
     Introspect(Introspect),
     Knowledge(Knowledge),
+    KbSearch(KbSearch),
     Thinking(Thinking),
     Todo(TodoList),
     Delegate(Delegate),
+    DataPreview(DataPreview),
+    ArchiveList(ArchiveList),
+    ArchiveReadMember(ArchiveReadMember),
+    DependencyList(DependencyList),
+    DependencyVersions(DependencyVersions),
 }
 
 impl Tool {
@@ -118,13 +150,56 @@ impl Tool {
             Tool::GhIssue(_) => "gh_issue",
             Tool::Introspect(_) => "introspect",
             Tool::Knowledge(_) => "knowledge",
+            Tool::KbSearch(_) => "kb_search",
             Tool::Thinking(_) => "thinking (prerelease)",
             Tool::Todo(_) => "todo_list",
             Tool::Delegate(_) => "delegate",
+            Tool::DataPreview(_) => "data_preview",
+            Tool::ArchiveList(_) => "archive_list",
+            Tool::ArchiveReadMember(_) => "archive_read_member",
+            Tool::DependencyList(_) => "dependency_list",
+            Tool::DependencyVersions(_) => "dependency_versions",
         }
         .to_owned()
     }
 
+    /// Paths the tool reads or writes, as the model gave them
+    pub fn paths(&self) -> Vec<String> {
+        match self {
+            Tool::FsRead(fs_read) => fs_read
+                .operations
+                .iter()
+                .flat_map(|op| match op {
+                    FsReadOperation::Line(op) => vec![op.path.clone()],
+                    FsReadOperation::Directory(op) => vec![op.path.clone()],
+                    FsReadOperation::Search(op) => vec![op.path.clone()],
+                    FsReadOperation::Image(op) => op.image_paths.clone(),
+                    FsReadOperation::Bytes(op) => vec![op.path.clone()],
+                })
+                .collect(),
+            Tool::FsWrite(
+                FsWrite::Create { path, .. }
+                | FsWrite::StrReplace { path, .. }
+                | FsWrite::Insert { path, .. }
+                | FsWrite::Append { path, .. }
+                | FsWrite::Delete { path, .. },
+            ) => vec![path.clone()],
+            Tool::DataPreview(data_preview) => vec![data_preview.path.clone()],
+            Tool::ArchiveList(archive_list) => vec![archive_list.path.clone()],
+            Tool::ArchiveReadMember(read_member) => vec![read_member.path.clone()],
+            Tool::DependencyList(list) => vec![list.path.clone()],
+            _ => Vec::new(),
+        }
+    }
+
+    /// The exit code of the command the tool ran, if it runs one
+    pub fn exit_code(&self, output: &InvokeOutput) -> Option<i32> {
+        match (self, &output.output) {
+            (Tool::ExecuteCommand(_), OutputKind::Json(json)) => json.get("exit_status")?.as_str()?.parse().ok(),
+            _ => None,
+        }
+    }
+
     /// Whether or not the tool should prompt the user to accept before [Self::invoke] is called.
     pub fn requires_acceptance(&self, os: &Os, agent: &Agent) -> PermissionEvalResult {
         match self {
@@ -138,7 +213,24 @@ impl Tool {
             Tool::Thinking(_) => PermissionEvalResult::Allow,
             Tool::Todo(_) => PermissionEvalResult::Allow,
             Tool::Knowledge(knowledge) => knowledge.eval_perm(os, agent),
+            Tool::KbSearch(_) => PermissionEvalResult::Allow,
             Tool::Delegate(_) => PermissionEvalResult::Allow, // Allow delegate tool
+            Tool::DataPreview(data_preview) => data_preview.eval_perm(os, agent),
+            Tool::ArchiveList(archive_list) => archive_list.eval_perm(os, agent),
+            Tool::ArchiveReadMember(read_member) => read_member.eval_perm(os, agent),
+            Tool::DependencyList(list) => list.eval_perm(os, agent),
+            Tool::DependencyVersions(versions) => versions.eval_perm(os, agent),
+        }
+    }
+
+    /// URLs the tool would connect to, which the egress policy of the agent is checked against.
+    /// Empty for tools that don't reach the network, and for tools whose connections can't be
+    /// known ahead of time, like shell commands.
+    pub fn egress_urls(&self) -> Vec<String> {
+        match self {
+            Tool::UseAws(use_aws) => vec![use_aws.endpoint_url()],
+            Tool::DependencyVersions(versions) => versions.registry_urls(),
+            _ => Vec::new(),
         }
     }
 
@@ -153,16 +245,22 @@ impl Tool {
         let active_agent = agents.get_active();
         match self {
             Tool::FsRead(fs_read) => fs_read.invoke(os, stdout).await,
-            Tool::FsWrite(fs_write) => fs_write.invoke(os, stdout, line_tracker).await,
-            Tool::ExecuteCommand(execute_command) => execute_command.invoke(os, stdout).await,
+            Tool::FsWrite(fs_write) => fs_write.invoke(os, stdout, line_tracker, active_agent).await,
+            Tool::ExecuteCommand(execute_command) => execute_command.invoke(os, stdout, active_agent).await,
             Tool::UseAws(use_aws) => use_aws.invoke(os, stdout).await,
             Tool::Custom(custom_tool) => custom_tool.invoke(os, stdout).await,
             Tool::GhIssue(gh_issue) => gh_issue.invoke(os, stdout).await,
             Tool::Introspect(introspect) => introspect.invoke(os, stdout).await,
             Tool::Knowledge(knowledge) => knowledge.invoke(os, stdout, active_agent).await,
+            Tool::KbSearch(kb_search) => kb_search.invoke(os, stdout, active_agent).await,
             Tool::Thinking(think) => think.invoke(stdout).await,
             Tool::Todo(todo) => todo.invoke(os, stdout).await,
             Tool::Delegate(delegate) => delegate.invoke(os, stdout, agents).await,
+            Tool::DataPreview(data_preview) => data_preview.invoke(os, stdout).await,
+            Tool::ArchiveList(archive_list) => archive_list.invoke(os, stdout).await,
+            Tool::ArchiveReadMember(read_member) => read_member.invoke(os, stdout).await,
+            Tool::DependencyList(list) => list.invoke(os, stdout).await,
+            Tool::DependencyVersions(versions) => versions.invoke(os, stdout).await,
         }
     }
 
@@ -180,9 +278,15 @@ impl Tool {
                 Tool::GhIssue(gh_issue) => gh_issue.queue_description(&mut buf),
                 Tool::Introspect(_) => Introspect::queue_description(&mut buf),
                 Tool::Knowledge(knowledge) => knowledge.queue_description(os, &mut buf).await,
+                Tool::KbSearch(kb_search) => kb_search.queue_description(&mut buf),
                 Tool::Thinking(thinking) => thinking.queue_description(&mut buf),
                 Tool::Todo(_) => Ok(()),
                 Tool::Delegate(delegate) => delegate.queue_description(&mut buf),
+                Tool::DataPreview(data_preview) => data_preview.queue_description(&mut buf),
+                Tool::ArchiveList(archive_list) => archive_list.queue_description(&mut buf),
+                Tool::ArchiveReadMember(read_member) => read_member.queue_description(&mut buf),
+                Tool::DependencyList(list) => list.queue_description(&mut buf),
+                Tool::DependencyVersions(versions) => versions.queue_description(&mut buf),
             }?;
 
             let tool_call_args = ToolCallArgs {
@@ -205,9 +309,15 @@ impl Tool {
                 Tool::GhIssue(gh_issue) => gh_issue.queue_description(output),
                 Tool::Introspect(_) => Introspect::queue_description(output),
                 Tool::Knowledge(knowledge) => knowledge.queue_description(os, output).await,
+                Tool::KbSearch(kb_search) => kb_search.queue_description(output),
                 Tool::Thinking(thinking) => thinking.queue_description(output),
                 Tool::Todo(_) => Ok(()),
                 Tool::Delegate(delegate) => delegate.queue_description(output),
+                Tool::DataPreview(data_preview) => data_preview.queue_description(output),
+                Tool::ArchiveList(archive_list) => archive_list.queue_description(output),
+                Tool::ArchiveReadMember(read_member) => read_member.queue_description(output),
+                Tool::DependencyList(list) => list.queue_description(output),
+                Tool::DependencyVersions(versions) => versions.queue_description(output),
             }?;
         };
 
@@ -225,9 +335,25 @@ impl Tool {
             Tool::GhIssue(gh_issue) => gh_issue.validate(os).await,
             Tool::Introspect(introspect) => introspect.validate(os).await,
             Tool::Knowledge(knowledge) => knowledge.validate(os).await,
+            Tool::KbSearch(kb_search) => kb_search.validate(os).await,
             Tool::Thinking(think) => think.validate(os).await,
             Tool::Todo(todo) => todo.validate(os).await,
             Tool::Delegate(_) => Ok(()), // No validation needed for delegate tool
+            Tool::DataPreview(data_preview) => data_preview.validate(os).await,
+            Tool::ArchiveList(archive_list) => archive_list.validate(os).await,
+            Tool::ArchiveReadMember(read_member) => read_member.validate(os).await,
+            Tool::DependencyList(list) => list.validate(os).await,
+            Tool::DependencyVersions(versions) => versions.validate(os).await,
+        }
+    }
+
+    /// Key under which the result of this tool use is cached for reuse within `scope`, [None] for
+    /// tools whose results can't be reused.
+    pub async fn cache_key(&self, os: &Os, scope: &str) -> Option<CacheKey> {
+        match self {
+            Tool::FsRead(fs_read) => fs_read.cache_key(os, scope).await,
+            Tool::DataPreview(data_preview) => data_preview.cache_key(os, scope).await,
+            _ => None,
         }
     }
 
@@ -375,6 +501,22 @@ impl Default for OutputKind {
     }
 }
 
+/// Permission check for tools that only read the file at `path`. Like fs_read without any tool
+/// settings, they are trusted within the current working directory.
+pub fn eval_read_only_perm(os: &Os, agent: &Agent, tool_name: &str, path: &str) -> PermissionEvalResult {
+    if is_tool_in_allowlist(&agent.allowed_tools, tool_name, None) {
+        return PermissionEvalResult::Allow;
+    }
+    let (Ok(cwd), Ok(path)) = (os.env.current_dir(), paths::canonicalizes_path(os, path)) else {
+        return PermissionEvalResult::Ask;
+    };
+    if Path::new(&path).starts_with(cwd) {
+        PermissionEvalResult::Allow
+    } else {
+        PermissionEvalResult::Ask
+    }
+}
+
 /// Performs tilde expansion and other required sanitization modifications for handling tool use
 /// path arguments.
 ///
@@ -394,6 +536,13 @@ pub fn sanitize_path_tool_arg(os: &Os, path: impl AsRef<Path>) -> PathBuf {
     for p in path {
         res.push(p);
     }
+    // Relative paths are relative to the working directory of the session, which `/cd` can move
+    // away from the one of the process
+    if res.is_relative() {
+        if let Some(dir) = os.env.changed_dir() {
+            res = dir.join(res);
+        }
+    }
     // For testing scenarios, we need to make sure paths are appropriately handled in chroot test
     // file systems since they are passed directly from the model.
     os.fs.chroot_path(res)
@@ -447,6 +596,7 @@ fn format_path(cwd: impl AsRef<Path>, path: impl AsRef<Path>) -> String {
 fn supports_truecolor() -> bool {
     // Simple override to disable truecolor since shell_color doesn't use Context.
     !crate::util::env_var::is_truecolor_disabled()
+        && crate::util::terminal::TerminalQuirks::current().truecolor
         && shell_color::get_color_support().contains(shell_color::ColorSupport::TERM24BIT)
 }
 
@@ -35,6 +35,9 @@ use crate::theme::StyledText;
 pub struct Task {
     pub task_description: String,
     pub completed: bool,
+    /// Whether the last attempt at the task failed, such as a migration step whose tests failed
+    #[serde(default)]
+    pub failed: bool,
 }
 
 /// Contains all state to be serialized and deserialized into a todo list
@@ -82,7 +85,7 @@ impl TodoListState {
     pub fn display_list(&self, output: &mut impl Write) -> Result<()> {
         queue!(output, style::Print("TODO:\n".yellow()))?;
         for (index, task) in self.tasks.iter().enumerate() {
-            queue_next_without_newline(output, task.task_description.clone(), task.completed)?;
+            queue_next_without_newline(output, task)?;
             if index < self.tasks.len() - 1 {
                 queue!(output, style::Print("\n"))?;
             }
@@ -91,21 +94,30 @@ impl TodoListState {
     }
 }
 
-/// Displays a single empty or marked off to-do list task depending on
+/// Displays a single empty, failed or marked off to-do list task depending on
 /// the completion status
-fn queue_next_without_newline(output: &mut impl Write, task: String, completed: bool) -> Result<()> {
-    if completed {
+fn queue_next_without_newline(output: &mut impl Write, task: &Task) -> Result<()> {
+    let description = task.task_description.clone();
+    if task.completed {
         queue!(
             output,
             StyledText::success_fg(),
             style::Print("[x] "),
             style::SetAttribute(style::Attribute::Italic),
             StyledText::secondary_fg(),
-            style::Print(task),
+            style::Print(description),
             style::SetAttribute(style::Attribute::NoItalic),
         )?;
+    } else if task.failed {
+        queue!(
+            output,
+            StyledText::error_fg(),
+            style::Print("[!] "),
+            StyledText::reset(),
+            style::Print(description),
+        )?;
     } else {
-        queue!(output, StyledText::reset(), style::Print(format!("[ ] {task}")),)?;
+        queue!(output, StyledText::reset(), style::Print(format!("[ ] {description}")),)?;
     }
     Ok(())
 }
@@ -252,6 +264,7 @@ impl TodoList {
                     todo_tasks.push(Task {
                         task_description: task_description.clone(),
                         completed: false,
+                        failed: false,
                     });
                 }
 
@@ -277,6 +290,7 @@ impl TodoList {
 
                 for i in completed_indices.iter() {
                     state.tasks[*i].completed = true;
+                    state.tasks[*i].failed = false;
                 }
 
                 state.context.push(context_update.clone());
@@ -321,6 +335,7 @@ impl TodoList {
                     let new_task = Task {
                         task_description: task_description.clone(),
                         completed: false,
+                        failed: false,
                     };
                     state.tasks.insert(*i, new_task);
                 }
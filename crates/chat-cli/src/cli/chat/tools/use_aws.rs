@@ -50,6 +50,17 @@ impl UseAws {
         !READONLY_OPS.iter().any(|op| self.operation_name.starts_with(op))
     }
 
+    /// URL of the service endpoint that the AWS CLI connects to, which the egress policy of the
+    /// agent applies to. The endpoint prefix of most services matches their CLI name, and the
+    /// policy can allow `*.amazonaws.com` to cover the ones that don't.
+    pub fn endpoint_url(&self) -> String {
+        let domain = match self.region.starts_with("cn-") {
+            true => "amazonaws.com.cn",
+            false => "amazonaws.com",
+        };
+        format!("https://{}.{}.{domain}", self.service_name, self.region)
+    }
+
     pub async fn invoke(&self, os: &Os, _updates: impl Write) -> Result<InvokeOutput> {
         let mut command = tokio::process::Command::new("aws");
 
@@ -57,6 +68,9 @@ impl UseAws {
         let env_vars = env_vars_with_user_agent(os);
 
         command.envs(env_vars).arg("--region").arg(&self.region);
+        if let Some(dir) = os.env.changed_dir() {
+            command.current_dir(dir);
+        }
         if let Some(profile_name) = self.profile_name.as_deref() {
             command.arg("--profile").arg(profile_name);
         }
@@ -229,6 +243,22 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_endpoint_url() {
+        let cmd = use_aws! {{
+            "service_name": "dynamodb",
+            "operation_name": "list-tables",
+            "region": "eu-west-1"
+        }};
+        assert_eq!(cmd.endpoint_url(), "https://dynamodb.eu-west-1.amazonaws.com");
+        let cmd = use_aws! {{
+            "service_name": "s3",
+            "operation_name": "list-buckets",
+            "region": "cn-north-1"
+        }};
+        assert_eq!(cmd.endpoint_url(), "https://s3.cn-north-1.amazonaws.com.cn");
+    }
+
     #[test]
     fn test_requires_acceptance() {
         let cmd = use_aws! {{
@@ -0,0 +1,125 @@
+//! Limits on the work the model does to answer a single prompt, set per agent with
+//! `responseBudgetTokens` and `maxToolIterations`. The model is told about them in the context so
+//! it can scope its answers, and is asked to answer with what it has once they are spent.
+
+use crate::cli::agent::Agent;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TurnBudget {
+    /// Tokens the model may generate while answering a prompt, tool uses included
+    pub response_tokens: Option<u32>,
+    /// Rounds of tool uses the model may make while answering a prompt
+    pub max_tool_iterations: Option<u32>,
+}
+
+/// What the model did so far to answer the current prompt.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TurnUsage {
+    /// Estimated tokens of the responses
+    pub response_tokens: usize,
+    /// Responses that asked for tool uses
+    pub tool_iterations: u32,
+    /// Whether the model was already asked to answer without tools
+    pub wrap_up_requested: bool,
+}
+
+impl TurnBudget {
+    pub fn from_agent(agent: &Agent) -> Self {
+        Self {
+            response_tokens: agent.response_budget_tokens,
+            max_tool_iterations: agent.max_tool_iterations,
+        }
+    }
+
+    /// Describes the budget to the model, or [None] without one.
+    pub fn hint(&self) -> Option<String> {
+        let mut limits = Vec::new();
+        if let Some(tokens) = self.response_tokens {
+            limits.push(format!("about {tokens} tokens of output, tool uses included"));
+        }
+        match self.max_tool_iterations {
+            Some(0) => limits.push("no tool uses".to_string()),
+            Some(1) => limits.push("a single round of tool uses".to_string()),
+            Some(rounds) => limits.push(format!("at most {rounds} rounds of tool uses")),
+            None => (),
+        }
+        if limits.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "Your budget for answering each of my prompts is {}. Scope your answers to fit it: be brief and \
+            direct when it is small, and thorough when it is large. If you go over it, I will ask you to answer \
+            with what you have so far.\n",
+            limits.join(" and ")
+        ))
+    }
+
+    /// Returns why `usage` is over the budget, if it is.
+    pub fn exceeded(&self, usage: &TurnUsage) -> Option<String> {
+        if let Some(rounds) = self.max_tool_iterations {
+            if usage.tool_iterations > rounds {
+                return Some(format!(
+                    "Reached the limit of {rounds} rounds of tool uses for this prompt"
+                ));
+            }
+        }
+        if let Some(tokens) = self.response_tokens {
+            if usage.response_tokens > tokens as usize {
+                return Some(format!(
+                    "Reached the budget of {tokens} response tokens for this prompt"
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// The prompt sent in place of the results of the tool uses that went over the budget.
+pub fn wrap_up_prompt(reason: &str) -> String {
+    format!("{reason}, so the tools were not run. Answer now with what you have so far, without using any tools.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hint() {
+        assert_eq!(TurnBudget::default().hint(), None);
+        let budget = TurnBudget {
+            response_tokens: Some(500),
+            max_tool_iterations: Some(1),
+        };
+        assert!(
+            budget
+                .hint()
+                .unwrap()
+                .contains("is about 500 tokens of output, tool uses included and a single round of tool uses.")
+        );
+        let budget = TurnBudget {
+            response_tokens: None,
+            max_tool_iterations: Some(0),
+        };
+        assert!(budget.hint().unwrap().contains("is no tool uses."));
+    }
+
+    #[test]
+    fn test_exceeded() {
+        let budget = TurnBudget {
+            response_tokens: Some(1000),
+            max_tool_iterations: Some(2),
+        };
+        let mut usage = TurnUsage {
+            response_tokens: 400,
+            tool_iterations: 2,
+            wrap_up_requested: false,
+        };
+        assert_eq!(budget.exceeded(&usage), None);
+        usage.tool_iterations = 3;
+        assert!(budget.exceeded(&usage).unwrap().contains("2 rounds of tool uses"));
+        usage.tool_iterations = 1;
+        usage.response_tokens = 1200;
+        assert!(budget.exceeded(&usage).unwrap().contains("1000 response tokens"));
+        assert_eq!(TurnBudget::default().exceeded(&usage), None);
+    }
+}
@@ -0,0 +1,289 @@
+//! Files changed by the agent during a user turn.
+//!
+//! The content of a file is recorded before it is first written in a turn, and compared to what is
+//! on disk once the turn ends so that the user can review everything that changed in one place.
+
+use std::collections::BTreeMap;
+use std::io::{
+    self,
+    Write,
+};
+use std::path::PathBuf;
+
+use crossterm::{
+    queue,
+    style,
+};
+use similar::{
+    ChangeTag,
+    TextDiff,
+};
+
+use crate::os::Os;
+use crate::theme::StyledText;
+
+/// Number of unchanged lines shown around each change by `/diff`
+const DIFF_CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Deleted,
+}
+
+/// A file changed during a turn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    /// Path of the file, relative to the working directory when possible
+    pub display_path: String,
+    /// Content before the turn, [None] if the file didn't exist
+    pub before: Option<String>,
+    /// Content after the turn, [None] if the file no longer exists
+    pub after: Option<String>,
+}
+
+impl FileChange {
+    pub fn kind(&self) -> ChangeKind {
+        match (&self.before, &self.after) {
+            (None, _) => ChangeKind::Created,
+            (Some(_), None) => ChangeKind::Deleted,
+            (Some(_), Some(_)) => ChangeKind::Modified,
+        }
+    }
+
+    /// Returns the number of lines added and removed.
+    pub fn line_counts(&self) -> (usize, usize) {
+        let before = self.before.as_deref().unwrap_or_default();
+        let after = self.after.as_deref().unwrap_or_default();
+        let diff = TextDiff::from_lines(before, after);
+        diff.iter_all_changes()
+            .fold((0, 0), |(added, removed), change| match change.tag() {
+                ChangeTag::Insert => (added + 1, removed),
+                ChangeTag::Delete => (added, removed + 1),
+                ChangeTag::Equal => (added, removed),
+            })
+    }
+
+    /// Returns the change as a unified diff.
+    pub fn unified_diff(&self) -> String {
+        let before = self.before.as_deref().unwrap_or_default();
+        let after = self.after.as_deref().unwrap_or_default();
+        let old_header = match self.before {
+            Some(_) => format!("a/{}", self.display_path),
+            None => "/dev/null".to_string(),
+        };
+        let new_header = match self.after {
+            Some(_) => format!("b/{}", self.display_path),
+            None => "/dev/null".to_string(),
+        };
+        TextDiff::from_lines(before, after)
+            .unified_diff()
+            .context_radius(DIFF_CONTEXT_LINES)
+            .header(&old_header, &new_header)
+            .to_string()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TurnChanges {
+    /// Content of the files written during the current turn, as it was before the first write
+    originals: BTreeMap<PathBuf, Option<String>>,
+    /// Changes made during the last completed turn
+    last_turn: Vec<FileChange>,
+}
+
+impl TurnChanges {
+    /// Records the content of `path` unless it was already written earlier in the turn. Must be
+    /// called before the file is written.
+    pub async fn record(&mut self, os: &Os, path: PathBuf) {
+        if !self.originals.contains_key(&path) {
+            let content = os.fs.read_to_string(&path).await.ok();
+            self.originals.insert(path, content);
+        }
+    }
+
+    /// Compares the files written during the turn to their content before it, and returns the
+    /// files that changed.
+    pub async fn finish_turn(&mut self, os: &Os) -> &[FileChange] {
+        let cwd = os.env.current_dir().ok();
+        let mut changes = Vec::new();
+        for (path, before) in std::mem::take(&mut self.originals) {
+            let after = os.fs.read_to_string(&path).await.ok();
+            if before == after {
+                continue;
+            }
+            let display_path = cwd
+                .as_ref()
+                .and_then(|cwd| path.strip_prefix(cwd).ok())
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .to_string();
+            changes.push(FileChange {
+                display_path,
+                before,
+                after,
+            });
+        }
+        self.last_turn = changes;
+        &self.last_turn
+    }
+
+    pub fn last_turn(&self) -> &[FileChange] {
+        &self.last_turn
+    }
+
+    /// Returns the change of the last turn for `file`, matched against the end of the path so that
+    /// a file name is enough when it is unambiguous.
+    pub fn find(&self, file: &str) -> Option<&FileChange> {
+        let file = file.trim_start_matches("./");
+        self.last_turn
+            .iter()
+            .find(|change| change.display_path == file)
+            .or_else(|| {
+                let mut matches = self.last_turn.iter().filter(|change| {
+                    change
+                        .display_path
+                        .strip_suffix(file)
+                        .is_some_and(|prefix| prefix.ends_with(std::path::MAIN_SEPARATOR))
+                });
+                matches.next().filter(|_| matches.next().is_none())
+            })
+    }
+}
+
+/// Prints one line per changed file, with the number of lines added and removed.
+pub fn print_summary(output: &mut impl Write, changes: &[FileChange]) -> io::Result<()> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let path_width = changes.iter().map(|c| c.display_path.len()).max().unwrap_or_default();
+    let noun = if changes.len() == 1 { "file" } else { "files" };
+    queue!(
+        output,
+        style::SetAttribute(style::Attribute::Bold),
+        style::Print(format!("Changed {} {noun} this turn:\n", changes.len())),
+        StyledText::reset_attributes(),
+    )?;
+    for change in changes {
+        let (marker, label) = match change.kind() {
+            ChangeKind::Created => ("+", "created"),
+            ChangeKind::Modified => ("~", "modified"),
+            ChangeKind::Deleted => ("-", "deleted"),
+        };
+        let (added, removed) = change.line_counts();
+        queue!(
+            output,
+            style::Print(format!("  {marker} {:<path_width$}  ", change.display_path)),
+            StyledText::secondary_fg(),
+            style::Print(format!("{label:<8} ")),
+            StyledText::success_fg(),
+            style::Print(format!("+{added} ")),
+            StyledText::error_fg(),
+            style::Print(format!("-{removed}\n")),
+            StyledText::reset(),
+        )?;
+    }
+    queue!(
+        output,
+        StyledText::secondary_fg(),
+        style::Print("Use /diff <file> to view a change.\n\n"),
+        StyledText::reset(),
+    )?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Prints a change as a colored unified diff.
+pub fn print_diff(output: &mut impl Write, change: &FileChange) -> io::Result<()> {
+    for line in change.unified_diff().lines() {
+        let color = if line.starts_with("+++") || line.starts_with("---") {
+            Some(StyledText::secondary_fg())
+        } else if line.starts_with('+') {
+            Some(StyledText::success_fg())
+        } else if line.starts_with('-') {
+            Some(StyledText::error_fg())
+        } else if line.starts_with("@@") {
+            Some(StyledText::info_fg())
+        } else {
+            None
+        };
+        if let Some(color) = color {
+            queue!(output, color)?;
+        }
+        queue!(output, style::Print(line), StyledText::reset(), style::Print("\n"))?;
+    }
+    queue!(output, style::Print("\n"))?;
+    output.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_finish_turn() {
+        let os = Os::new().await.unwrap();
+        let cwd = os.env.current_dir().unwrap();
+        os.fs.create_dir_all(&cwd).await.unwrap();
+        os.fs.write(cwd.join("unchanged.txt"), "same\n").await.unwrap();
+        os.fs.write(cwd.join("edited.txt"), "one\ntwo\n").await.unwrap();
+
+        let mut changes = TurnChanges::default();
+        for file in ["unchanged.txt", "edited.txt", "new.txt"] {
+            changes.record(&os, cwd.join(file)).await;
+        }
+        os.fs.write(cwd.join("edited.txt"), "one\n2\nthree\n").await.unwrap();
+        os.fs.write(cwd.join("new.txt"), "hello\n").await.unwrap();
+        // Writing again in the same turn keeps the original content
+        changes.record(&os, cwd.join("edited.txt")).await;
+
+        let finished = changes.finish_turn(&os).await;
+        assert_eq!(finished.len(), 2);
+        assert_eq!(finished[0].display_path, "edited.txt");
+        assert_eq!(finished[0].kind(), ChangeKind::Modified);
+        assert_eq!(finished[0].line_counts(), (2, 1));
+        assert_eq!(finished[1].display_path, "new.txt");
+        assert_eq!(finished[1].kind(), ChangeKind::Created);
+        assert_eq!(finished[1].line_counts(), (1, 0));
+
+        // The next turn starts from scratch
+        assert!(changes.finish_turn(&os).await.is_empty());
+    }
+
+    #[test]
+    fn test_find() {
+        let change = |path: &str| FileChange {
+            display_path: path.to_string(),
+            before: None,
+            after: Some(String::new()),
+        };
+        let changes = TurnChanges {
+            last_turn: vec![
+                change("src/main.rs"),
+                change("src/cli/mod.rs"),
+                change("src/lib/mod.rs"),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(changes.find("./src/main.rs").unwrap().display_path, "src/main.rs");
+        assert_eq!(changes.find("main.rs").unwrap().display_path, "src/main.rs");
+        assert_eq!(changes.find("cli/mod.rs").unwrap().display_path, "src/cli/mod.rs");
+        assert!(changes.find("mod.rs").is_none());
+        assert!(changes.find("ain.rs").is_none());
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let change = FileChange {
+            display_path: "a.txt".to_string(),
+            before: Some("one\n".to_string()),
+            after: Some("two\n".to_string()),
+        };
+        assert_eq!(
+            change.unified_diff(),
+            "--- a/a.txt\n+++ b/a.txt\n@@ -1 +1 @@\n-one\n+two\n"
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use std::fmt::Write as _;
+use std::path::Path;
+
+use tokio::io::AsyncReadExt;
+
+use crate::os::Os;
+
+/// Number of bytes read from the start of a file to decide whether it is binary.
+pub const SNIFF_LEN: usize = 8192;
+
+/// Bytes shown per line of a hex dump.
+const HEX_DUMP_WIDTH: usize = 16;
+
+/// A file type recognized from the magic bytes at the start of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileType {
+    pub description: &'static str,
+    /// Whether the file is an image that can be sent to the model as an image block.
+    pub image: bool,
+}
+
+impl FileType {
+    const fn new(description: &'static str) -> Self {
+        Self {
+            description,
+            image: false,
+        }
+    }
+
+    const fn image(description: &'static str) -> Self {
+        Self {
+            description,
+            image: true,
+        }
+    }
+}
+
+/// Signatures as `(offset, magic, type)`, checked in order.
+const SIGNATURES: &[(usize, &[u8], FileType)] = &[
+    (0, b"\x89PNG\r\n\x1a\n", FileType::image("PNG image")),
+    (0, b"\xff\xd8\xff", FileType::image("JPEG image")),
+    (0, b"GIF87a", FileType::image("GIF image")),
+    (0, b"GIF89a", FileType::image("GIF image")),
+    (8, b"WEBP", FileType::image("WebP image")),
+    (0, b"%PDF-", FileType::new("PDF document")),
+    (0, b"PK\x03\x04", FileType::new("Zip archive")),
+    (0, b"\x1f\x8b", FileType::new("gzip compressed data")),
+    (0, b"BZh", FileType::new("bzip2 compressed data")),
+    (0, b"\xfd7zXZ\x00", FileType::new("xz compressed data")),
+    (0, b"\x28\xb5\x2f\xfd", FileType::new("Zstandard compressed data")),
+    (0, b"7z\xbc\xaf\x27\x1c", FileType::new("7-zip archive")),
+    (257, b"ustar", FileType::new("tar archive")),
+    (0, b"\x7fELF", FileType::new("ELF executable")),
+    (0, b"\xfe\xed\xfa\xce", FileType::new("Mach-O executable")),
+    (0, b"\xfe\xed\xfa\xcf", FileType::new("Mach-O executable")),
+    (0, b"\xce\xfa\xed\xfe", FileType::new("Mach-O executable")),
+    (0, b"\xcf\xfa\xed\xfe", FileType::new("Mach-O executable")),
+    (
+        0,
+        b"\xca\xfe\xba\xbe",
+        FileType::new("Mach-O universal binary or Java class file"),
+    ),
+    (0, b"MZ", FileType::new("PE/DOS executable")),
+    (0, b"\x00asm", FileType::new("WebAssembly module")),
+    (0, b"SQLite format 3\x00", FileType::new("SQLite database")),
+    (0, b"PAR1", FileType::new("Parquet file")),
+];
+
+/// Identifies the type of a file from the first bytes of its contents.
+pub fn detect_file_type(bytes: &[u8]) -> Option<FileType> {
+    SIGNATURES.iter().find_map(|(offset, magic, file_type)| {
+        bytes
+            .get(*offset..offset + magic.len())
+            .is_some_and(|b| b == *magic)
+            .then_some(*file_type)
+    })
+}
+
+/// Whether `sample`, taken from the start of a file, looks like binary rather than text.
+///
+/// Bytes that are merely invalid UTF-8 (e.g. Latin-1 text) do not count, since those files still
+/// read fine with lossy decoding. Only NUL bytes, a high ratio of control characters or a known
+/// binary signature do.
+pub fn is_binary(sample: &[u8]) -> bool {
+    if sample.is_empty() {
+        return false;
+    }
+    if detect_file_type(sample).is_some() || sample.contains(&0) {
+        return true;
+    }
+    let control = sample
+        .iter()
+        .filter(|&&b| b.is_ascii_control() && !matches!(b, b'\t' | b'\n' | b'\r' | b'\x0c' | b'\x1b'))
+        .count();
+    control * 10 > sample.len()
+}
+
+/// Reads up to [SNIFF_LEN] bytes from the start of the file at `path`.
+pub async fn sniff(os: &Os, path: impl AsRef<Path>) -> std::io::Result<Vec<u8>> {
+    let file = os.fs.open(path).await?;
+    let mut sample = Vec::with_capacity(SNIFF_LEN);
+    file.take(SNIFF_LEN as u64).read_to_end(&mut sample).await?;
+    Ok(sample)
+}
+
+/// Formats `bytes` like `xxd`, labelling each line with its position relative to `offset`.
+pub fn hex_dump(bytes: &[u8], offset: u64) -> String {
+    let mut out = String::new();
+    for (i, chunk) in bytes.chunks(HEX_DUMP_WIDTH).enumerate() {
+        let _ = write!(out, "{:08x}: ", offset + (i * HEX_DUMP_WIDTH) as u64);
+        for j in 0..HEX_DUMP_WIDTH {
+            match chunk.get(j) {
+                Some(b) => {
+                    let _ = write!(out, "{b:02x}");
+                },
+                None => out.push_str("  "),
+            }
+            if j % 2 == 1 {
+                out.push(' ');
+            }
+        }
+        out.push(' ');
+        out.extend(chunk.iter().map(|b| {
+            if b.is_ascii_graphic() || *b == b' ' {
+                *b as char
+            } else {
+                '.'
+            }
+        }));
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_file_type() {
+        assert_eq!(
+            detect_file_type(b"\x89PNG\r\n\x1a\n\x00\x00"),
+            Some(FileType::image("PNG image"))
+        );
+        assert_eq!(
+            detect_file_type(b"RIFF\x00\x00\x00\x00WEBPVP8 "),
+            Some(FileType::image("WebP image"))
+        );
+        assert_eq!(
+            detect_file_type(b"\x7fELF\x02\x01\x01"),
+            Some(FileType::new("ELF executable"))
+        );
+        assert_eq!(detect_file_type(b"fn main() {}"), None);
+        assert_eq!(detect_file_type(b""), None);
+    }
+
+    #[test]
+    fn test_is_binary() {
+        assert!(!is_binary(b""));
+        assert!(!is_binary(b"hello\tworld\r\n"));
+        assert!(!is_binary(b"\x1b[31mred\x1b[0m\n"));
+        // Invalid UTF-8 alone is still treated as text.
+        assert!(!is_binary(&[0xff, 0xfe, 0xfd, 0xfc]));
+        assert!(!is_binary(b"caf\xe9"));
+        assert!(is_binary(b"text\x00more text"));
+        assert!(is_binary(b"\x01\x02\x03\x04abc"));
+        assert!(is_binary(b"%PDF-1.7\n"));
+    }
+
+    #[test]
+    fn test_hex_dump() {
+        assert_eq!(
+            hex_dump(b"Hello, world!\n\x00\x01\xff", 16),
+            "00000010: 4865 6c6c 6f2c 2077 6f72 6c64 210a 0001  Hello, world!...\n\
+             00000020: ff                                       .\n"
+        );
+        assert_eq!(hex_dump(b"", 0), "");
+    }
+}
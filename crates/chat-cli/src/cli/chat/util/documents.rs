@@ -0,0 +1,325 @@
+use std::io::{
+    Cursor,
+    Read,
+};
+use std::path::Path;
+
+use eyre::{
+    Result,
+    WrapErr,
+    eyre,
+};
+use serde_json::Value;
+
+use crate::os::Os;
+
+/// Document formats whose text can be extracted for the model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentKind {
+    Pdf,
+    Docx,
+    Notebook,
+}
+
+impl DocumentKind {
+    /// Identifies a document from the extension of `path`.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "pdf" => Some(Self::Pdf),
+            "docx" => Some(Self::Docx),
+            "ipynb" => Some(Self::Notebook),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Pdf => "PDF",
+            Self::Docx => "DOCX",
+            Self::Notebook => "Jupyter notebook",
+        }
+    }
+}
+
+/// Extracts the text of the document at `path` as markdown, or returns `None` if the file is not a
+/// supported document.
+pub async fn extract_text(os: &Os, path: impl AsRef<Path>) -> Result<Option<String>> {
+    let path = path.as_ref();
+    let Some(kind) = DocumentKind::from_path(path) else {
+        return Ok(None);
+    };
+
+    let bytes = os.fs.read(path).await?;
+    let text = match kind {
+        DocumentKind::Pdf => tokio::task::spawn_blocking(move || pdf_extract::extract_text_from_mem(&bytes))
+            .await
+            .map_err(Into::into)
+            .and_then(|result| result.map_err(|e| eyre!(e.to_string()))),
+        DocumentKind::Docx => docx_to_markdown(&bytes),
+        DocumentKind::Notebook => notebook_to_markdown(&bytes),
+    }
+    .wrap_err_with(|| format!("Failed to extract text from {} {}", kind.name(), path.display()))?;
+
+    Ok(Some(text))
+}
+
+/// Converts the main body of a DOCX file to markdown, keeping headings, list items and paragraph
+/// breaks.
+fn docx_to_markdown(bytes: &[u8]) -> Result<String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+    let mut xml = String::new();
+    archive.by_name("word/document.xml")?.read_to_string(&mut xml)?;
+
+    let mut paragraphs = Vec::new();
+    let mut paragraph = String::new();
+    let mut prefix = String::new();
+    let mut in_text = false;
+    let mut rest = xml.as_str();
+    while let Some(start) = rest.find('<') {
+        if in_text {
+            paragraph.push_str(&unescape_xml(&rest[..start]));
+        }
+        let Some(len) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = &rest[start + 1..start + len];
+        rest = &rest[start + len + 1..];
+
+        match tag.trim_end_matches('/').split_whitespace().next().unwrap_or_default() {
+            "w:p" => prefix.clear(),
+            "w:pStyle" => {
+                if let Some(level) = heading_level(tag) {
+                    prefix = format!("{} ", "#".repeat(level));
+                }
+            },
+            "w:numPr" => prefix = "- ".to_string(),
+            "w:t" => in_text = !tag.ends_with('/'),
+            "/w:t" => in_text = false,
+            "w:tab" => paragraph.push('\t'),
+            "w:br" | "w:cr" => paragraph.push('\n'),
+            "/w:p" => {
+                let text = paragraph.trim();
+                if !text.is_empty() {
+                    paragraphs.push(format!("{prefix}{text}"));
+                }
+                paragraph.clear();
+            },
+            _ => {},
+        }
+    }
+
+    Ok(paragraphs.join("\n\n"))
+}
+
+/// Returns the heading level of a `w:pStyle` tag, treating the document title as a top level
+/// heading.
+fn heading_level(tag: &str) -> Option<usize> {
+    let value = tag.split("w:val=\"").nth(1)?.split('"').next()?;
+    if value == "Title" {
+        return Some(1);
+    }
+    value.strip_prefix("Heading")?.parse().ok()
+}
+
+fn unescape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map_or_else(
+                    || entity.strip_prefix('#')?.parse().ok(),
+                    |hex| u32::from_str_radix(hex, 16).ok(),
+                )
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            },
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Converts a Jupyter notebook to markdown: markdown cells as is, code cells as fenced blocks, and
+/// each code cell followed by its text outputs.
+fn notebook_to_markdown(bytes: &[u8]) -> Result<String> {
+    let notebook: Value = serde_json::from_slice(bytes)?;
+    let language = notebook
+        .pointer("/metadata/language_info/name")
+        .or_else(|| notebook.pointer("/metadata/kernelspec/language"))
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let cells = notebook
+        .get("cells")
+        .and_then(Value::as_array)
+        .ok_or_else(|| eyre!("notebook has no cells"))?;
+
+    let mut sections = Vec::new();
+    for cell in cells {
+        let source = multiline_text(cell.get("source"));
+        match cell.get("cell_type").and_then(Value::as_str) {
+            Some("code") => {
+                sections.push(format!("```{language}\n{}\n```", source.trim_end()));
+                let outputs = cell.get("outputs").and_then(Value::as_array).into_iter().flatten();
+                for output in outputs.filter_map(notebook_output_text) {
+                    sections.push(format!("Output:\n```\n{}\n```", output.trim_end()));
+                }
+            },
+            _ if source.trim().is_empty() => {},
+            _ => sections.push(source.trim_end().to_string()),
+        }
+    }
+
+    Ok(sections.join("\n\n"))
+}
+
+fn notebook_output_text(output: &Value) -> Option<String> {
+    match output.get("output_type")?.as_str()? {
+        "stream" => Some(multiline_text(output.get("text"))),
+        "execute_result" | "display_data" => {
+            let data = output.get("data")?;
+            match data.get("text/plain") {
+                Some(text) => Some(multiline_text(Some(text))),
+                None if data.as_object()?.keys().any(|k| k.starts_with("image/")) => {
+                    Some("[image output omitted]".to_string())
+                },
+                None => None,
+            }
+        },
+        "error" => Some(format!(
+            "{}: {}",
+            output.get("ename")?.as_str()?,
+            output.get("evalue").and_then(Value::as_str).unwrap_or_default()
+        )),
+        _ => None,
+    }
+}
+
+/// Notebook text fields are either a single string or a list of lines.
+fn multiline_text(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::ZipWriter;
+    use zip::write::SimpleFileOptions;
+
+    use super::*;
+
+    /// Builds a minimal DOCX file whose body is `body`.
+    fn docx_with_body(body: &str) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        writer
+            .start_file("word/document.xml", SimpleFileOptions::default())
+            .unwrap();
+        write!(
+            writer,
+            r#"<?xml version="1.0" encoding="UTF-8"?><w:document><w:body>{body}</w:body></w:document>"#
+        )
+        .unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_document_kind_from_path() {
+        assert_eq!(DocumentKind::from_path("/report.PDF"), Some(DocumentKind::Pdf));
+        assert_eq!(DocumentKind::from_path("design.docx"), Some(DocumentKind::Docx));
+        assert_eq!(DocumentKind::from_path("analysis.ipynb"), Some(DocumentKind::Notebook));
+        assert_eq!(DocumentKind::from_path("notes.md"), None);
+        assert_eq!(DocumentKind::from_path("Makefile"), None);
+    }
+
+    #[test]
+    fn test_docx_to_markdown() {
+        let docx = docx_with_body(concat!(
+            r#"<w:p><w:pPr><w:pStyle w:val="Heading2"/></w:pPr><w:r><w:t>Design</w:t></w:r></w:p>"#,
+            r#"<w:p><w:r><w:t xml:space="preserve">Fish &amp; chips </w:t></w:r><w:r><w:t>&#x263A;</w:t></w:r></w:p>"#,
+            r#"<w:p></w:p>"#,
+            r#"<w:p><w:pPr><w:numPr><w:ilvl w:val="0"/></w:numPr></w:pPr><w:r><w:t>item</w:t><w:tab/><w:t>one</w:t></w:r></w:p>"#,
+        ));
+        assert_eq!(
+            docx_to_markdown(&docx).unwrap(),
+            "## Design\n\nFish & chips ☺\n\n- item\tone"
+        );
+        assert!(docx_to_markdown(b"not a zip").is_err());
+    }
+
+    #[test]
+    fn test_unescape_xml() {
+        assert_eq!(unescape_xml("a &lt;b&gt; &quot;c&quot; &#65;&#x42;"), "a <b> \"c\" AB");
+        assert_eq!(unescape_xml("AT&T & &bogus; &"), "AT&T & &bogus; &");
+    }
+
+    #[test]
+    fn test_notebook_to_markdown() {
+        let notebook = serde_json::json!({
+            "metadata": { "language_info": { "name": "python" } },
+            "cells": [
+                { "cell_type": "markdown", "source": ["# Analysis\n", "Some notes"] },
+                {
+                    "cell_type": "code",
+                    "source": "print(1 + 1)\n",
+                    "outputs": [
+                        { "output_type": "stream", "name": "stdout", "text": ["2\n"] },
+                        { "output_type": "display_data", "data": { "image/png": "iVBORw0KGgo=" } },
+                        { "output_type": "error", "ename": "ValueError", "evalue": "bad", "traceback": [] }
+                    ]
+                },
+                { "cell_type": "markdown", "source": [] }
+            ]
+        });
+        assert_eq!(
+            notebook_to_markdown(notebook.to_string().as_bytes()).unwrap(),
+            "# Analysis\nSome notes\n\n```python\nprint(1 + 1)\n```\n\nOutput:\n```\n2\n```\n\n\
+             Output:\n```\n[image output omitted]\n```\n\nOutput:\n```\nValueError: bad\n```"
+        );
+        assert!(notebook_to_markdown(b"{}").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_extract_text() {
+        let os = Os::new().await.unwrap();
+        os.fs.write("/notes.md", "plain").await.unwrap();
+        os.fs
+            .write("/design.docx", docx_with_body("<w:p><w:r><w:t>Hello</w:t></w:r></w:p>"))
+            .await
+            .unwrap();
+        os.fs.write("/broken.pdf", "not a pdf").await.unwrap();
+
+        assert_eq!(extract_text(&os, "/notes.md").await.unwrap(), None);
+        assert_eq!(
+            extract_text(&os, "/design.docx").await.unwrap(),
+            Some("Hello".to_string())
+        );
+        let err = extract_text(&os, "/broken.pdf").await.unwrap_err();
+        assert!(err.to_string().contains("Failed to extract text from PDF"), "{err}");
+    }
+}
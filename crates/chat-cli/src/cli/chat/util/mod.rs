@@ -1,4 +1,6 @@
+pub mod binary;
 pub mod clipboard;
+pub mod documents;
 pub mod images;
 pub mod issue;
 #[cfg(test)]
@@ -16,7 +18,7 @@ use eyre::Result;
 
 use super::ChatError;
 use super::token_counter::TokenCounter;
-use crate::util::env_var::get_term;
+use crate::util::terminal::TerminalQuirks;
 
 pub fn truncate_safe(s: &str, max_bytes: usize) -> &str {
     if s.len() <= max_bytes {
@@ -103,58 +105,21 @@ pub fn sanitize_unicode_tags(text: &str) -> String {
     out
 }
 
-/// Play the terminal bell notification sound
+/// Play the terminal bell and show a desktop notification where the terminal supports one
 pub fn play_notification_bell(requires_confirmation: bool) {
     // Don't play bell for tools that don't require confirmation
     if !requires_confirmation {
         return;
     }
 
-    // Check if we should play the bell based on terminal type
-    if should_play_bell() {
+    let quirks = TerminalQuirks::current();
+    if quirks.bell {
         print!("\x07"); // ASCII bell character
-        std::io::stdout().flush().unwrap();
     }
-}
-
-/// Determine if we should play the bell based on terminal type
-fn should_play_bell() -> bool {
-    // Get the TERM environment variable
-    if let Some(term) = get_term() {
-        // List of terminals known to handle bell character well
-        let bell_compatible_terms = [
-            "xterm",
-            "xterm-256color",
-            "screen",
-            "screen-256color",
-            "tmux",
-            "tmux-256color",
-            "rxvt",
-            "rxvt-unicode",
-            "linux",
-            "konsole",
-            "gnome",
-            "gnome-256color",
-            "alacritty",
-            "iterm2",
-            "eat-truecolor",
-            "eat-256color",
-            "eat-color",
-        ];
-
-        // Check if the current terminal is in the compatible list
-        for compatible_term in bell_compatible_terms.iter() {
-            if term.starts_with(compatible_term) {
-                return true;
-            }
-        }
-
-        // For other terminals, don't play the bell
-        return false;
+    if let Some(notification) = quirks.notification {
+        print!("{}", notification.format("Amazon Q is waiting for your input"));
     }
-
-    // If TERM is not set, default to not playing the bell
-    false
+    std::io::stdout().flush().unwrap();
 }
 
 /// This is a simple greedy algorithm that drops the largest files first
@@ -0,0 +1,94 @@
+//! The workspace a conversation belongs to, so that saved conversations can be listed and resumed
+//! from anywhere in the same repository.
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sha2::{
+    Digest,
+    Sha256,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Workspace {
+    /// Top level of the git repository, or the directory itself outside of one
+    pub root: PathBuf,
+    /// URL of the `origin` remote of the repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote: Option<String>,
+    /// Hash of the remote and the root. Two conversations are in the same workspace when their ids
+    /// are equal, so separate clones of a repository are separate workspaces.
+    pub id: String,
+}
+
+impl Workspace {
+    /// Returns the workspace containing `dir`.
+    pub async fn detect(dir: &Path) -> Self {
+        let (root, remote) = tokio::join!(
+            git(dir, &["rev-parse", "--show-toplevel"]),
+            git(dir, &["config", "--get", "remote.origin.url"])
+        );
+        let root = root.map(PathBuf::from).unwrap_or_else(|| dir.to_path_buf());
+        Self::new(root, remote)
+    }
+
+    pub fn new(root: PathBuf, remote: Option<String>) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(remote.as_deref().unwrap_or_default());
+        hasher.update([0]);
+        hasher.update(root.to_string_lossy().as_bytes());
+        let id = hex::encode(&hasher.finalize()[..8]);
+        Self { root, remote, id }
+    }
+}
+
+/// Runs git in `dir` and returns its trimmed output, or [None] if it fails.
+async fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let output = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!output.is_empty()).then_some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workspace_id() {
+        let a = Workspace::new(
+            PathBuf::from("/src/app"),
+            Some("git@github.com:org/app.git".to_string()),
+        );
+        let clone = Workspace::new(
+            PathBuf::from("/tmp/app"),
+            Some("git@github.com:org/app.git".to_string()),
+        );
+        assert_eq!(a.id, Workspace::new(a.root.clone(), a.remote.clone()).id);
+        assert_ne!(a.id, clone.id);
+        assert_ne!(a.id, Workspace::new(a.root.clone(), None).id);
+    }
+
+    #[tokio::test]
+    async fn test_detect_outside_git_repo() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = Workspace::detect(dir.path()).await;
+        assert_eq!(workspace.root, dir.path());
+        assert_eq!(workspace.remote, None);
+    }
+}
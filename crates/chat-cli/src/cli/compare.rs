@@ -0,0 +1,264 @@
+use std::collections::HashSet;
+use std::process::ExitCode;
+use std::time::Instant;
+
+use clap::Args;
+use eyre::{
+    Result,
+    bail,
+};
+use futures::future::join_all;
+use serde::Serialize;
+use unicode_width::{
+    UnicodeWidthChar,
+    UnicodeWidthStr,
+};
+
+use super::OutputFormat;
+use crate::cli::chat::token_counter::TokenCounter;
+use crate::os::Os;
+
+/// Narrowest a column is wrapped to, columns scroll past the terminal width below it
+const MIN_COLUMN_WIDTH: usize = 30;
+/// Separator between the columns of two models
+const COLUMN_SEPARATOR: &str = " │ ";
+
+/// Sends the same prompt to several models concurrently and shows their responses side by side,
+/// with the latency and length of each.
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct CompareArgs {
+    /// Comma-separated ids of the models to compare, e.g. claude-sonnet-4,claude-3.7-sonnet
+    #[arg(long, required = true, value_delimiter = ',')]
+    pub models: Vec<String>,
+    /// The prompt sent to every model
+    pub prompt: Vec<String>,
+    /// Format of the output
+    #[arg(long, short, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+impl CompareArgs {
+    pub async fn execute(self, os: &Os) -> Result<ExitCode> {
+        let prompt = self.prompt.join(" ");
+        if prompt.trim().is_empty() {
+            bail!("Provide a prompt to compare the models with");
+        }
+        let mut seen = HashSet::new();
+        let models = self
+            .models
+            .iter()
+            .map(|model| model.trim())
+            .filter(|model| !model.is_empty() && seen.insert(*model))
+            .collect::<Vec<_>>();
+        if models.is_empty() {
+            bail!("Provide at least one model to compare, e.g. `--models claude-sonnet-4,claude-3.7-sonnet`");
+        }
+
+        let responses = join_all(models.iter().map(|model| ModelResponse::request(os, &prompt, model))).await;
+        self.format.print(
+            || {
+                let width = crossterm::terminal::size().map_or(120, |(width, _)| width as usize);
+                render_columns(&responses, width)
+            },
+            || &responses,
+        );
+
+        Ok(match responses.iter().all(|response| response.error.is_some()) {
+            true => ExitCode::FAILURE,
+            false => ExitCode::SUCCESS,
+        })
+    }
+}
+
+/// The response of a model to the compared prompt.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelResponse {
+    model: String,
+    response: Option<String>,
+    error: Option<String>,
+    /// Time until the first chunk of the response was received
+    time_to_first_chunk_ms: Option<u64>,
+    /// Time until the response completed or failed
+    latency_ms: u64,
+    /// Estimated number of tokens of the response
+    output_tokens: usize,
+}
+
+impl ModelResponse {
+    async fn request(os: &Os, prompt: &str, model: &str) -> Self {
+        let start = Instant::now();
+        let mut first_chunk = None;
+        let mut text = String::new();
+        let result = async {
+            let mut response = os
+                .client
+                .send_prompt(prompt.to_string(), Some(model.to_string()))
+                .await?;
+            while let Some(content) = response.recv_text().await? {
+                first_chunk.get_or_insert_with(|| start.elapsed());
+                text.push_str(&content);
+            }
+            Ok::<_, eyre::Report>(())
+        }
+        .await;
+
+        Self {
+            model: model.to_string(),
+            output_tokens: TokenCounter::count_tokens(&text),
+            response: result.is_ok().then(|| text.trim().to_string()),
+            error: result.err().map(|err| err.to_string()),
+            time_to_first_chunk_ms: first_chunk.map(|elapsed| elapsed.as_millis() as u64),
+            latency_ms: start.elapsed().as_millis() as u64,
+        }
+    }
+
+    fn stats(&self) -> String {
+        let mut stats = Vec::new();
+        if let Some(ms) = self.time_to_first_chunk_ms {
+            stats.push(format!("first chunk {}", format_ms(ms)));
+        }
+        stats.push(format!("total {}", format_ms(self.latency_ms)));
+        stats.push(format!("~{} tokens", self.output_tokens));
+        stats.join(", ")
+    }
+}
+
+fn format_ms(ms: u64) -> String {
+    format!("{:.1}s", ms as f64 / 1000.0)
+}
+
+/// Lays the responses out in a column each, splitting `width` between them.
+fn render_columns(responses: &[ModelResponse], width: usize) -> String {
+    let separators = COLUMN_SEPARATOR.width() * responses.len().saturating_sub(1);
+    let column_width = (width.saturating_sub(separators) / responses.len().max(1)).max(MIN_COLUMN_WIDTH);
+
+    let columns = responses
+        .iter()
+        .map(|response| {
+            let mut lines = wrap(&response.model, column_width);
+            lines.push("─".repeat(column_width));
+            lines.extend(match (&response.response, &response.error) {
+                (_, Some(err)) => wrap(&format!("error: {err}"), column_width),
+                (Some(text), None) => wrap(text, column_width),
+                (None, None) => Vec::new(),
+            });
+            lines
+        })
+        .collect::<Vec<_>>();
+    let footers = responses
+        .iter()
+        .map(|response| wrap(&response.stats(), column_width))
+        .collect::<Vec<_>>();
+
+    let mut rendered = join_rows(&columns, column_width);
+    rendered.push_str(&join_rows(
+        &vec![vec!["─".repeat(column_width)]; responses.len()],
+        column_width,
+    ));
+    rendered.push_str(&join_rows(&footers, column_width));
+    rendered.trim_end().to_string()
+}
+
+/// Joins the lines of each column side by side, padding the shorter columns.
+fn join_rows(columns: &[Vec<String>], column_width: usize) -> String {
+    let rows = columns.iter().map(Vec::len).max().unwrap_or_default();
+    let mut rendered = String::new();
+    for row in 0..rows {
+        let line = columns
+            .iter()
+            .map(|column| {
+                let cell = column.get(row).map(String::as_str).unwrap_or_default();
+                format!("{cell}{}", " ".repeat(column_width.saturating_sub(cell.width())))
+            })
+            .collect::<Vec<_>>()
+            .join(COLUMN_SEPARATOR);
+        rendered.push_str(line.trim_end());
+        rendered.push('\n');
+    }
+    rendered
+}
+
+/// Wraps `text` at word boundaries to lines of at most `width` columns, splitting words that
+/// don't fit on a line of their own.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let needed = match line.is_empty() {
+                true => word.width(),
+                false => line.width() + 1 + word.width(),
+            };
+            if needed <= width {
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(word);
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            for c in word.chars() {
+                if line.width() + c.width().unwrap_or_default() > width {
+                    lines.push(std::mem::take(&mut line));
+                }
+                line.push(c);
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(model: &str, response: Result<&str, &str>) -> ModelResponse {
+        ModelResponse {
+            model: model.to_string(),
+            response: response.ok().map(str::to_string),
+            error: response.err().map(str::to_string),
+            time_to_first_chunk_ms: response.is_ok().then_some(800),
+            latency_ms: 2400,
+            output_tokens: 10,
+        }
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("the quick brown fox", 10), vec!["the quick", "brown fox"]);
+        assert_eq!(wrap("abcdefghij klm", 4), vec!["abcd", "efgh", "ij", "klm"]);
+        assert_eq!(wrap("first\n\nsecond", 10), vec!["first", "", "second"]);
+    }
+
+    #[test]
+    fn test_render_columns() {
+        let rendered = render_columns(
+            &[
+                response("fast", Ok("a short answer")),
+                response("slow", Err("throttled")),
+            ],
+            63,
+        );
+        let lines = rendered.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], format!("{:<30} │ slow", "fast"));
+        assert_eq!(lines[2], format!("{:<30} │ error: throttled", "a short answer"));
+        assert_eq!(
+            lines[4],
+            format!("{:<30} │ total 2.4s, ~10 tokens", "first chunk 0.8s, total 2.4s,")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_request() {
+        let mut os = Os::new().await.unwrap();
+        os.client.set_mock_output(serde_json::json!([["Hello", " world\n"]]));
+        let response = ModelResponse::request(&os, "hi", "claude-sonnet-4").await;
+        assert_eq!(response.response.as_deref(), Some("Hello world"));
+        assert_eq!(response.error, None);
+        assert!(response.time_to_first_chunk_ms.is_some());
+    }
+}
@@ -0,0 +1,88 @@
+use std::io::Write;
+use std::process::ExitCode;
+
+use clap::Subcommand;
+use eyre::Result;
+
+use crate::os::Os;
+use crate::util::completion_specs::{
+    self,
+    SpecStore,
+    UpdateOutcome,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum CompletionSpecsSubcommand {
+    /// Download the latest autocomplete specs
+    Update {
+        /// Download the specs even if the installed ones are up to date
+        #[arg(long)]
+        force: bool,
+    },
+    /// Show the installed autocomplete specs and check their integrity
+    Status,
+}
+
+impl CompletionSpecsSubcommand {
+    pub async fn execute(self, os: &Os) -> Result<ExitCode> {
+        let mut stderr = std::io::stderr();
+        let store = SpecStore::open(os)?;
+        match self {
+            Self::Update { force } => {
+                let url = completion_specs::registry_url(os);
+                match store.update(os, &url, force).await? {
+                    UpdateOutcome::UpToDate { version } => {
+                        writeln!(stderr, "Autocomplete specs are up to date ({version})")?;
+                    },
+                    UpdateOutcome::Updated { from, to, specs } => {
+                        let from = from.map(|v| format!(" from {v}")).unwrap_or_default();
+                        writeln!(stderr, "Updated autocomplete specs{from} to {to} ({specs} specs)")?;
+                    },
+                }
+            },
+            Self::Status => {
+                let Some(manifest) = store.manifest(os).await? else {
+                    writeln!(
+                        stderr,
+                        "No autocomplete specs are installed, run `completion-specs update` to download them"
+                    )?;
+                    return Ok(ExitCode::FAILURE);
+                };
+                let checked_at = time::OffsetDateTime::from_unix_timestamp(manifest.checked_at)?;
+                writeln!(stderr, "Version:      {}", manifest.version)?;
+                writeln!(stderr, "Specs:        {}", manifest.files.len())?;
+                writeln!(stderr, "Location:     {}", store.specs_dir().display())?;
+                writeln!(stderr, "Last checked: {checked_at}")?;
+
+                let verification = store.verify(os, &manifest).await?;
+                if verification.is_ok() {
+                    writeln!(stderr, "Integrity:    ok")?;
+                } else {
+                    writeln!(
+                        stderr,
+                        "Integrity:    {} missing, {} modified, run `completion-specs update --force` to repair",
+                        verification.missing.len(),
+                        verification.corrupt.len()
+                    )?;
+                    return Ok(ExitCode::FAILURE);
+                }
+            },
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_status_without_specs() {
+        let os = Os::new().await.unwrap();
+        assert_eq!(
+            CompletionSpecsSubcommand::Status.execute(&os).await.unwrap(),
+            ExitCode::FAILURE
+        );
+    }
+}
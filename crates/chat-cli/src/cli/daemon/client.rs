@@ -0,0 +1,144 @@
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+use eyre::{
+    Result,
+    bail,
+};
+use tokio::io::BufReader;
+use tokio::net::UnixStream;
+use tokio::net::unix::{
+    OwnedReadHalf,
+    OwnedWriteHalf,
+};
+
+use super::framing::{
+    DEFAULT_MAX_MESSAGE_LEN,
+    max_message_len,
+    read_message,
+    write_message,
+};
+use super::service::ServiceManager;
+use super::{
+    Request,
+    Response,
+};
+use crate::os::Os;
+use crate::util::paths;
+
+/// How long to wait for the daemon to start or stop listening
+const START_TIMEOUT: Duration = Duration::from_secs(3);
+
+pub struct Client {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+    max_message_len: usize,
+}
+
+impl Client {
+    pub async fn connect(path: &Path) -> std::io::Result<Self> {
+        let (reader, writer) = UnixStream::connect(path).await?.into_split();
+        Ok(Self {
+            reader: BufReader::new(reader),
+            writer,
+            max_message_len: DEFAULT_MAX_MESSAGE_LEN,
+        })
+    }
+
+    pub fn with_max_message_len(mut self, max_message_len: usize) -> Self {
+        self.max_message_len = max_message_len;
+        self
+    }
+
+    pub async fn send(&mut self, request: &Request) -> Result<Response> {
+        write_message(&mut self.writer, request, self.max_message_len).await?;
+        match read_message(&mut self.reader, self.max_message_len).await? {
+            Some(response) => Ok(response),
+            None => bail!("The daemon closed the connection"),
+        }
+    }
+}
+
+/// Sends `request` to the daemon, starting it first if it isn't running.
+pub async fn send(os: &Os, request: &Request) -> Result<Response> {
+    let path = paths::daemon_socket_path()?;
+    let client = match Client::connect(&path).await {
+        Ok(client) => client,
+        Err(_) => {
+            spawn(os)?;
+            wait_until_listening(&path).await?
+        },
+    };
+    client
+        .with_max_message_len(max_message_len(&os.database.settings))
+        .send(request)
+        .await
+}
+
+/// Pings the daemon if it is running, without starting it.
+pub async fn ping() -> Result<Option<Response>> {
+    match Client::connect(&paths::daemon_socket_path()?).await {
+        Ok(mut client) => Ok(Some(client.send(&Request::Ping).await?)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Starts the daemon, through the service manager if it is installed there. Returns the pid of
+/// the daemon if it was already running.
+pub async fn start(os: &Os) -> Result<Option<u32>> {
+    if let Some(Response::Pong { pid, .. }) = ping().await? {
+        return Ok(Some(pid));
+    }
+    match ServiceManager::current().filter(|service| service.is_installed(os)) {
+        Some(service) => service.start().await?,
+        None => spawn(os)?,
+    }
+    wait_until_listening(&paths::daemon_socket_path()?).await?;
+    Ok(None)
+}
+
+/// Stops the daemon, returning false if it wasn't running.
+pub async fn stop() -> Result<bool> {
+    let path = paths::daemon_socket_path()?;
+    let Ok(mut client) = Client::connect(&path).await else {
+        return Ok(false);
+    };
+    client.send(&Request::Shutdown).await?;
+
+    let started = tokio::time::Instant::now();
+    while Client::connect(&path).await.is_ok() {
+        if started.elapsed() > START_TIMEOUT {
+            bail!("The daemon didn't stop");
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    Ok(true)
+}
+
+fn spawn(os: &Os) -> Result<()> {
+    let mut command = std::process::Command::new(os.env.current_exe()?);
+    command
+        .args(["daemon", "run"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0);
+    command.spawn()?;
+    Ok(())
+}
+
+async fn wait_until_listening(path: &Path) -> Result<Client> {
+    let started = tokio::time::Instant::now();
+    loop {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        match Client::connect(path).await {
+            Ok(client) => return Ok(client),
+            Err(err) if started.elapsed() > START_TIMEOUT => {
+                bail!("The daemon didn't start listening on {}: {err}", path.display())
+            },
+            Err(_) => (),
+        }
+    }
+}
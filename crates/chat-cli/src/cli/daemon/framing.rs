@@ -0,0 +1,288 @@
+//! Framing of the messages exchanged with the daemon: one JSON object per line.
+//!
+//! Messages longer than [MAX_CHUNK_LEN] are split into chunks, one per line. Every chunk but the
+//! last starts with [CONTINUATION], and the last one with [LAST_CHUNK], so that a chunk whose
+//! payload happens to start with either is never mistaken for the end of the message. JSON never
+//! starts with either marker, so peers that don't chunk can still exchange short messages.
+//!
+//! Reads are bounded on both ends, so a peer that never sends a newline, sends one huge line, or
+//! keeps sending chunks can't make the other side buffer without limit.
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tokio::io::{
+    AsyncBufRead,
+    AsyncBufReadExt,
+    AsyncReadExt,
+    AsyncWrite,
+    AsyncWriteExt,
+};
+
+use crate::database::settings::{
+    Setting,
+    Settings,
+};
+
+/// Longest line that is read. Longer messages are sent in chunks.
+pub const MAX_CHUNK_LEN: usize = 64 * 1024;
+
+/// Default of the `daemon.maxMessageSize` setting
+pub const DEFAULT_MAX_MESSAGE_LEN: usize = 8 * 1024 * 1024;
+
+/// Starts every chunk of a message but the last
+const CONTINUATION: u8 = b'+';
+
+/// Starts the last chunk of a message that was split into chunks
+const LAST_CHUNK: u8 = b'=';
+
+#[derive(Debug, thiserror::Error)]
+pub enum FrameError {
+    #[error("Messages are limited to {limit} bytes")]
+    TooLong { limit: usize },
+    #[error("The connection closed in the middle of a message")]
+    Truncated,
+    #[error("A chunk of a message is missing its marker")]
+    MissingChunkMarker,
+    #[error("Invalid message: {0}")]
+    Invalid(#[from] serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// The largest message to send or receive, from the `daemon.maxMessageSize` setting
+pub fn max_message_len(settings: &Settings) -> usize {
+    settings
+        .get_int(Setting::DaemonMaxMessageSize)
+        .and_then(|len| usize::try_from(len).ok())
+        .filter(|len| *len > 0)
+        .unwrap_or(DEFAULT_MAX_MESSAGE_LEN)
+}
+
+/// Reads the next message of at most `limit` bytes, or [None] if the peer closed the connection
+/// between messages.
+///
+/// After an error the rest of the stream can't be trusted to start at a message boundary, so the
+/// connection should be closed.
+pub async fn read_message<T, R>(reader: &mut R, limit: usize) -> Result<Option<T>, FrameError>
+where
+    T: DeserializeOwned,
+    R: AsyncBufRead + Unpin,
+{
+    let mut message = Vec::new();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        // A byte more for the continuation marker, the newline is read on top
+        let line_limit = MAX_CHUNK_LEN.min(limit - message.len()) + 1;
+        let len = (&mut *reader)
+            .take(line_limit as u64 + 1)
+            .read_until(b'\n', &mut line)
+            .await?;
+        if len == 0 && message.is_empty() {
+            return Ok(None);
+        }
+        if line.last() != Some(&b'\n') {
+            return Err(if len > line_limit {
+                FrameError::TooLong { limit }
+            } else {
+                FrameError::Truncated
+            });
+        }
+        line.pop();
+
+        let (chunk, is_last) = match line.split_first() {
+            Some((&CONTINUATION, chunk)) => (chunk, false),
+            Some((&LAST_CHUNK, chunk)) if !message.is_empty() => (chunk, true),
+            // Once a message was started, only its chunks can follow
+            _ if !message.is_empty() => return Err(FrameError::MissingChunkMarker),
+            _ => (line.as_slice(), true),
+        };
+        if message.len() + chunk.len() > limit {
+            return Err(FrameError::TooLong { limit });
+        }
+        message.extend_from_slice(chunk);
+        if is_last {
+            return Ok(Some(serde_json::from_slice(&message)?));
+        }
+    }
+}
+
+/// Writes `message`, failing without writing anything if it is longer than `limit` bytes.
+pub async fn write_message<T, W>(writer: &mut W, message: &T, limit: usize) -> Result<(), FrameError>
+where
+    T: Serialize,
+    W: AsyncWrite + Unpin,
+{
+    // JSON escapes newlines in strings, so chunks never contain one.
+    let json = serde_json::to_vec(message)?;
+    if json.len() > limit {
+        return Err(FrameError::TooLong { limit });
+    }
+
+    let mut framed = Vec::with_capacity(json.len() + 2 * json.len().div_ceil(MAX_CHUNK_LEN));
+    let is_chunked = json.len() > MAX_CHUNK_LEN;
+    let mut chunks = json.chunks(MAX_CHUNK_LEN).peekable();
+    while let Some(chunk) = chunks.next() {
+        if chunks.peek().is_some() {
+            framed.push(CONTINUATION);
+        } else if is_chunked {
+            framed.push(LAST_CHUNK);
+        }
+        framed.extend_from_slice(chunk);
+        framed.push(b'\n');
+    }
+    writer.write_all(&framed).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::cli::daemon::Request;
+
+    fn block_on<F: std::future::Future>(fut: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(fut)
+    }
+
+    /// Reads messages until the end of the stream or the first error
+    fn read_all(bytes: Vec<u8>, limit: usize) -> (Vec<Request>, Option<FrameError>, u64) {
+        block_on(async {
+            let mut reader = Cursor::new(bytes);
+            let mut messages = Vec::new();
+            loop {
+                match read_message(&mut reader, limit).await {
+                    Ok(Some(message)) => messages.push(message),
+                    Ok(None) => return (messages, None, reader.position()),
+                    Err(err) => return (messages, Some(err), reader.position()),
+                }
+            }
+        })
+    }
+
+    fn encode(requests: &[Request]) -> Vec<u8> {
+        block_on(async {
+            let mut bytes = Vec::new();
+            for request in requests {
+                write_message(&mut bytes, request, DEFAULT_MAX_MESSAGE_LEN)
+                    .await
+                    .unwrap();
+            }
+            bytes
+        })
+    }
+
+    fn complete(line: String) -> Request {
+        Request::Complete {
+            line,
+            cursor: 0,
+            cwd: PathBuf::from("/"),
+        }
+    }
+
+    fn request() -> impl Strategy<Value = Request> {
+        prop_oneof![
+            Just(Request::Ping),
+            Just(Request::Shutdown),
+            (any::<String>(), any::<usize>(), "[a-z/ \n]{0,20}").prop_map(|(line, cursor, cwd)| Request::Complete {
+                line,
+                cursor,
+                cwd: PathBuf::from(cwd),
+            }),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(requests in prop::collection::vec(request(), 0..8)) {
+            let (read, err, _) = read_all(encode(&requests), DEFAULT_MAX_MESSAGE_LEN);
+            prop_assert!(err.is_none(), "{err:?}");
+            prop_assert_eq!(read, requests);
+        }
+
+        #[test]
+        fn test_truncated_frames(request in request(), cut in any::<prop::sample::Index>()) {
+            let bytes = encode(std::slice::from_ref(&request));
+            // Cut anywhere but after the final newline
+            let cut = 1 + cut.index(bytes.len() - 1);
+            let (read, err, _) = read_all(bytes[..cut].to_vec(), DEFAULT_MAX_MESSAGE_LEN);
+            prop_assert!(read.is_empty());
+            prop_assert!(matches!(err, Some(FrameError::Truncated)), "{err:?}");
+        }
+
+        #[test]
+        fn test_oversized_frames(len in 258..4096_usize, byte in any::<u8>()) {
+            let limit = 256;
+            let (read, err, consumed) = read_all(vec![byte; len], limit);
+            prop_assert!(read.is_empty());
+            // Newlines in the line make it a series of short, invalid messages instead.
+            if byte != b'\n' {
+                prop_assert!(matches!(err, Some(FrameError::TooLong { limit: 256 })), "{err:?}");
+                prop_assert!(consumed <= limit as u64 + 2);
+            }
+        }
+
+        #[test]
+        fn test_garbage(bytes in prop::collection::vec(any::<u8>(), 0..4096)) {
+            let limit = 256;
+            let len = bytes.len() as u64;
+            let (_, err, consumed) = read_all(bytes, limit);
+            prop_assert!(consumed <= len);
+            if err.is_none() {
+                prop_assert_eq!(consumed, len);
+            }
+        }
+    }
+
+    #[test]
+    fn test_chunked_messages() {
+        let requests = [complete("a".repeat(3 * MAX_CHUNK_LEN + 10)), Request::Ping];
+        let bytes = encode(&requests);
+        assert_eq!(bytes.iter().filter(|b| **b == b'\n').count(), 5);
+        assert_eq!(bytes.iter().filter(|b| **b == CONTINUATION).count(), 3);
+        assert_eq!(bytes.iter().filter(|b| **b == LAST_CHUNK).count(), 1);
+        let (read, err, _) = read_all(bytes.clone(), DEFAULT_MAX_MESSAGE_LEN);
+        assert!(err.is_none());
+        assert_eq!(read, requests);
+
+        // The reader stops buffering once the message exceeds its limit.
+        let limit = MAX_CHUNK_LEN + 100;
+        let (read, err, consumed) = read_all(bytes, limit);
+        assert!(read.is_empty());
+        assert!(matches!(err, Some(FrameError::TooLong { .. })));
+        assert!(consumed <= limit as u64 + 4);
+    }
+
+    #[test]
+    fn test_last_chunk_starting_with_continuation() {
+        // Every chunk of the line, the last one included, starts with a `+`
+        let requests = [complete("+".repeat(2 * MAX_CHUNK_LEN)), Request::Ping];
+        let bytes = encode(&requests);
+        let (read, err, _) = read_all(bytes, DEFAULT_MAX_MESSAGE_LEN);
+        assert!(err.is_none(), "{err:?}");
+        assert_eq!(read, requests);
+
+        // A message can't be completed by a line that isn't one of its chunks
+        let mut bytes = encode(&[complete("a".repeat(2 * MAX_CHUNK_LEN))]);
+        let last_chunk = bytes[..bytes.len() - 1].iter().rposition(|b| *b == b'\n').unwrap() + 1;
+        bytes.remove(last_chunk);
+        let (read, err, _) = read_all(bytes, DEFAULT_MAX_MESSAGE_LEN);
+        assert!(read.is_empty());
+        assert!(matches!(err, Some(FrameError::MissingChunkMarker)), "{err:?}");
+    }
+
+    #[test]
+    fn test_write_too_long() {
+        let mut bytes = Vec::new();
+        let result = block_on(write_message(&mut bytes, &complete("a".repeat(100)), 64));
+        assert!(matches!(result, Err(FrameError::TooLong { limit: 64 })));
+        assert!(bytes.is_empty());
+    }
+}
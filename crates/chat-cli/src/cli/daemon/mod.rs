@@ -0,0 +1,285 @@
+//! A small background process that hosts the socket endpoints of the shell integrations and runs
+//! periodic work, so they work from the CLI alone. It is started on demand by the first client and
+//! exits after being idle, unless a service manager starts it at login.
+
+#[cfg(unix)]
+mod client;
+#[cfg(unix)]
+mod framing;
+#[cfg(unix)]
+mod server;
+#[cfg(unix)]
+pub mod service;
+
+#[cfg(unix)]
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Subcommand;
+#[cfg(unix)]
+pub use client::stop;
+use eyre::Result;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::os::Os;
+use crate::util::paths;
+#[cfg(unix)]
+use crate::util::time_format::TimeFormatter;
+
+/// Requests sent to the daemon, one JSON object per line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Request {
+    Ping,
+    /// Complete the word ending at `cursor` in the shell command `line`
+    Complete {
+        line: String,
+        cursor: usize,
+        cwd: PathBuf,
+    },
+    /// The installed autocomplete spec of `command`, e.g. `git` or `aws/s3`
+    CompletionSpec {
+        command: String,
+    },
+    Shutdown,
+}
+
+/// Responses to [Request]s, one JSON object per line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Response {
+    Pong {
+        pid: u32,
+        version: String,
+        /// Unix timestamp the daemon started at
+        started_at: i64,
+        schedules: Vec<ScheduleStatus>,
+    },
+    Completions {
+        start: usize,
+        candidates: Vec<String>,
+    },
+    CompletionSpec {
+        /// Version of the installed spec package
+        version: String,
+        /// The compiled spec, or [None] when there is no spec for the command
+        spec: Option<String>,
+    },
+    Ok,
+    Error {
+        message: String,
+    },
+}
+
+/// Work the daemon runs periodically
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleStatus {
+    pub name: String,
+    /// Unix timestamp of the last run, if it ran yet
+    pub last_run: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum DaemonSubcommand {
+    /// Start the daemon in the background
+    Start,
+    /// Stop the daemon
+    Stop,
+    /// Show whether the daemon is running and what it does
+    Status,
+    /// Restart the daemon, e.g. to pick up a new version
+    Restart,
+    /// Print the daemon's log
+    Logs {
+        /// Number of lines to print
+        #[arg(long, short = 'n', default_value_t = 50)]
+        lines: usize,
+        /// Keep printing lines as they are logged
+        #[arg(long, short)]
+        follow: bool,
+    },
+    /// Run the daemon in the foreground
+    #[command(hide = true)]
+    Run {
+        /// Keep running without connections, for service managers that start the daemon at login
+        #[arg(long)]
+        no_idle_timeout: bool,
+    },
+    /// Complete a shell command line, printing one candidate per line
+    Complete {
+        /// The command line to complete
+        line: String,
+        /// Byte offset of the cursor in the line, defaults to the end
+        #[arg(long)]
+        cursor: Option<usize>,
+    },
+}
+
+impl DaemonSubcommand {
+    #[cfg(unix)]
+    pub async fn execute(self, os: &Os) -> Result<ExitCode> {
+        let mut stderr = std::io::stderr();
+        match self {
+            Self::Start => match client::start(os).await? {
+                Some(pid) => writeln!(stderr, "The daemon is already running (pid {pid})")?,
+                None => writeln!(stderr, "Started the daemon")?,
+            },
+            Self::Stop => match client::stop().await? {
+                true => writeln!(stderr, "Stopped the daemon")?,
+                false => writeln!(stderr, "The daemon is not running")?,
+            },
+            Self::Status => return status(os, &mut stderr).await,
+            Self::Restart => {
+                client::stop().await?;
+                client::start(os).await?;
+                writeln!(stderr, "Restarted the daemon")?;
+            },
+            Self::Logs { lines, follow } => logs(lines, follow).await?,
+            Self::Run { no_idle_timeout } => server::run(os, no_idle_timeout).await?,
+            Self::Complete { line, cursor } => {
+                let cursor = cursor.unwrap_or(line.len()).min(line.len());
+                if !line.is_char_boundary(cursor) {
+                    eyre::bail!("The cursor must be at a character boundary");
+                }
+                let request = Request::Complete {
+                    line,
+                    cursor,
+                    cwd: os.env.current_dir()?,
+                };
+                match client::send(os, &request).await? {
+                    Response::Completions { candidates, .. } => {
+                        let mut stdout = std::io::stdout().lock();
+                        for candidate in candidates {
+                            writeln!(stdout, "{candidate}")?;
+                        }
+                    },
+                    Response::Error { message } => eyre::bail!(message),
+                    response => eyre::bail!("Unexpected response from the daemon: {response:?}"),
+                }
+            },
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+
+    #[cfg(not(unix))]
+    pub async fn execute(self, _os: &Os) -> Result<ExitCode> {
+        eyre::bail!("The daemon is not supported on this platform")
+    }
+}
+
+#[cfg(unix)]
+async fn status(os: &Os, stderr: &mut impl Write) -> Result<ExitCode> {
+    let service = service::ServiceManager::current().filter(|service| service.is_installed(os));
+    let managed = match service {
+        Some(service) => service.to_string(),
+        None => "no, run `integrations install daemon` to start it at login".to_string(),
+    };
+    let Some(Response::Pong {
+        pid,
+        version,
+        started_at,
+        schedules,
+    }) = client::ping().await?
+    else {
+        writeln!(stderr, "Status:     not running")?;
+        writeln!(stderr, "At login:   {managed}")?;
+        return Ok(ExitCode::FAILURE);
+    };
+
+    let formatter = TimeFormatter::from_settings(&os.database.settings);
+    let format_time = |timestamp: i64| {
+        chrono::DateTime::from_timestamp(timestamp, 0).map_or_else(|| timestamp.to_string(), |t| formatter.format(&t))
+    };
+    writeln!(stderr, "Status:     running (pid {pid})")?;
+    writeln!(stderr, "Version:    {version}")?;
+    writeln!(stderr, "Started:    {}", format_time(started_at))?;
+    writeln!(stderr, "Socket:     {}", paths::daemon_socket_path()?.display())?;
+    writeln!(stderr, "At login:   {managed}")?;
+    for schedule in schedules {
+        let last_run = schedule.last_run.map_or_else(|| "not run yet".to_string(), format_time);
+        writeln!(stderr, "Schedule:   {} (last run {last_run})", schedule.name)?;
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Prints the last `lines` lines of the daemon's log, then the lines logged later if `follow`.
+#[cfg(unix)]
+async fn logs(lines: usize, follow: bool) -> Result<()> {
+    use tokio::io::{
+        AsyncReadExt,
+        AsyncSeekExt,
+    };
+
+    let path = log_path()?;
+    let mut file = match tokio::fs::File::open(&path).await {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            eyre::bail!(
+                "The daemon hasn't logged anything yet, {} doesn't exist",
+                path.display()
+            )
+        },
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).await?;
+    let text = String::from_utf8_lossy(&bytes);
+    let mut stdout = std::io::stdout();
+    let skip = text.lines().count().saturating_sub(lines);
+    for line in text.lines().skip(skip) {
+        writeln!(stdout, "{line}")?;
+    }
+    if !follow {
+        return Ok(());
+    }
+
+    let mut position = file.stream_position().await?;
+    loop {
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        let len = tokio::fs::metadata(&path).await?.len();
+        if len < position {
+            // The log was truncated
+            file.seek(std::io::SeekFrom::Start(0)).await?;
+        }
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes).await?;
+        position = file.stream_position().await?;
+        stdout.write_all(&bytes)?;
+        stdout.flush()?;
+    }
+}
+
+/// File the daemon logs to
+pub fn log_path() -> Result<PathBuf> {
+    Ok(paths::logs_dir()?.join("qdaemon.log"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protocol() {
+        let request = Request::Complete {
+            line: "git checkout ma".into(),
+            cursor: 15,
+            cwd: PathBuf::from("/repo"),
+        };
+        let json = serde_json::to_string(&request).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"complete","line":"git checkout ma","cursor":15,"cwd":"/repo"}"#
+        );
+        assert_eq!(serde_json::from_str::<Request>(&json).unwrap(), request);
+        assert_eq!(
+            serde_json::from_str::<Request>(r#"{"type":"ping"}"#).unwrap(),
+            Request::Ping
+        );
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::BTreeMap;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::{
+    Arc,
+    Mutex,
+};
+use std::time::Duration;
+
+use eyre::{
+    Result,
+    bail,
+};
+use tokio::io::BufReader;
+use tokio::net::{
+    UnixListener,
+    UnixStream,
+};
+use tokio::sync::mpsc;
+use tracing::{
+    debug,
+    info,
+    warn,
+};
+
+use super::framing::{
+    FrameError,
+    max_message_len,
+    read_message,
+    write_message,
+};
+use super::{
+    Request,
+    Response,
+    ScheduleStatus,
+};
+use crate::cli::chat::shell_activity::history_commands;
+use crate::cli::chat::shell_completion::ShellCompleter;
+use crate::os::Os;
+use crate::util::completion_specs::SpecStore;
+use crate::util::{
+    completion_specs,
+    paths,
+};
+
+/// How long the daemon waits for a connection before exiting
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// How often the scheduled work runs. Each schedule decides for itself whether there is anything
+/// to do, e.g. the completion specs are only downloaded once they are stale.
+const SCHEDULE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+struct State {
+    started_at: i64,
+    completer: Mutex<ShellCompleter>,
+    /// Unix timestamp of the last run of each schedule
+    schedules: Mutex<BTreeMap<&'static str, Option<i64>>>,
+    shutdown: mpsc::Sender<()>,
+}
+
+pub async fn run(os: &Os, no_idle_timeout: bool) -> Result<()> {
+    let path = paths::daemon_socket_path()?;
+    let listener = bind(&path).await?;
+    info!(?path, pid = std::process::id(), "daemon listening");
+    let idle_timeout = (!no_idle_timeout).then_some(IDLE_TIMEOUT);
+    let result = serve(os, listener, idle_timeout).await;
+    if let Err(err) = std::fs::remove_file(&path) {
+        warn!(?err, ?path, "failed to remove the daemon socket");
+    }
+    result
+}
+
+async fn bind(path: &Path) -> Result<UnixListener> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if path.exists() {
+        if UnixStream::connect(path).await.is_ok() {
+            bail!("The daemon is already running");
+        }
+        // Left behind by a daemon that didn't exit cleanly
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+/// Accepts connections until a [Request::Shutdown] or no connection arrives for `idle_timeout`.
+async fn serve(os: &Os, listener: UnixListener, idle_timeout: Option<Duration>) -> Result<()> {
+    let (shutdown, mut shutdown_rx) = mpsc::channel(1);
+    let state = Arc::new(State {
+        started_at: time::OffsetDateTime::now_utc().unix_timestamp(),
+        completer: Mutex::new(ShellCompleter::new(os)),
+        schedules: Mutex::new(BTreeMap::from([(COMPLETION_SPECS_SCHEDULE, None)])),
+        shutdown,
+    });
+    let schedules = tokio::spawn(run_schedules(state.clone(), os.clone()));
+    let result = accept_connections(os, &state, listener, idle_timeout, &mut shutdown_rx).await;
+    schedules.abort();
+    result
+}
+
+async fn accept_connections(
+    os: &Os,
+    state: &Arc<State>,
+    listener: UnixListener,
+    idle_timeout: Option<Duration>,
+    shutdown_rx: &mut mpsc::Receiver<()>,
+) -> Result<()> {
+    let idle = |timeout: Option<Duration>| async move {
+        match timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => std::future::pending().await,
+        }
+    };
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    tokio::spawn(handle_connection(state.clone(), os.clone(), stream));
+                },
+                Err(err) => return Err(err.into()),
+            },
+            _ = idle(idle_timeout) => {
+                info!("daemon was idle for {idle_timeout:?}, exiting");
+                return Ok(());
+            },
+            _ = shutdown_rx.recv() => {
+                info!("daemon shutting down");
+                return Ok(());
+            },
+        }
+    }
+}
+
+const COMPLETION_SPECS_SCHEDULE: &str = "completion-specs";
+
+async fn run_schedules(state: Arc<State>, os: Os) {
+    let mut interval = tokio::time::interval(SCHEDULE_INTERVAL);
+    loop {
+        interval.tick().await;
+        completion_specs::update_if_stale(&os).await;
+        let now = time::OffsetDateTime::now_utc().unix_timestamp();
+        let mut schedules = state.schedules.lock().unwrap_or_else(|err| err.into_inner());
+        schedules.insert(COMPLETION_SPECS_SCHEDULE, Some(now));
+    }
+}
+
+async fn handle_connection(state: Arc<State>, os: Os, stream: UnixStream) {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    let limit = max_message_len(&os.database.settings);
+    loop {
+        // After an error reading the request, the rest of the stream can't be trusted to start at a
+        // request boundary, so the connection is closed once the error is sent.
+        let (response, close) = match read_message::<Request, _>(&mut reader, limit).await {
+            Ok(None) => return,
+            Ok(Some(request)) => (handle_request(&state, os.clone(), request).await, false),
+            Err(err @ FrameError::TooLong { .. }) => (
+                Response::Error {
+                    message: err.to_string(),
+                },
+                true,
+            ),
+            Err(FrameError::Invalid(err)) => (
+                Response::Error {
+                    message: format!("Invalid request: {err}"),
+                },
+                true,
+            ),
+            Err(err) => {
+                debug!(?err, "failed to read a daemon request");
+                return;
+            },
+        };
+
+        match write_message(&mut writer, &response, limit).await {
+            Ok(()) if !close => (),
+            // The client would wait forever for a response that isn't sent, e.g. the completions
+            // of a huge directory, so it gets an error instead.
+            Err(err @ FrameError::TooLong { .. }) => {
+                let response = Response::Error {
+                    message: format!("The response is too long. {err}"),
+                };
+                if write_message(&mut writer, &response, limit).await.is_err() {
+                    return;
+                }
+            },
+            _ => return,
+        }
+    }
+}
+
+async fn handle_request(state: &Arc<State>, os: Os, request: Request) -> Response {
+    match request {
+        Request::Ping => {
+            let schedules = state.schedules.lock().unwrap_or_else(|err| err.into_inner());
+            Response::Pong {
+                pid: std::process::id(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                started_at: state.started_at,
+                schedules: schedules
+                    .iter()
+                    .map(|(name, last_run)| ScheduleStatus {
+                        name: name.to_string(),
+                        last_run: *last_run,
+                    })
+                    .collect(),
+            }
+        },
+        Request::Complete { line, cursor, cwd } => {
+            if cursor > line.len() || !line.is_char_boundary(cursor) {
+                return Response::Error {
+                    message: format!("Cursor {cursor} is not a character boundary of the line"),
+                };
+            }
+            let state = state.clone();
+            // Completing reads files and directories and runs git, which blocks.
+            let completed = tokio::task::spawn_blocking(move || {
+                let history = history_commands(&os);
+                let mut completer = state.completer.lock().unwrap_or_else(|err| err.into_inner());
+                completer.set_cwd(cwd);
+                completer.set_history(history);
+                completer.complete(&line, cursor, [])
+            })
+            .await;
+            match completed {
+                Ok((start, candidates)) => Response::Completions { start, candidates },
+                Err(err) => Response::Error {
+                    message: format!("Completion failed: {err}"),
+                },
+            }
+        },
+        Request::CompletionSpec { command } => match completion_spec(&os, &command).await {
+            Ok(response) => response,
+            Err(err) => Response::Error {
+                message: format!("Failed to read the completion spec of {command}: {err}"),
+            },
+        },
+        Request::Shutdown => {
+            let _ = state.shutdown.try_send(());
+            Response::Ok
+        },
+    }
+}
+
+/// Reads the spec of `command` from the specs kept up to date by the daemon's schedule.
+async fn completion_spec(os: &Os, command: &str) -> Result<Response> {
+    let store = SpecStore::open(os)?;
+    let Some(manifest) = store.manifest(os).await? else {
+        bail!("No completion specs are installed, install them with `q completion-specs update`");
+    };
+    let spec = store.read_spec(os, &manifest, command).await?;
+    Ok(Response::CompletionSpec {
+        version: manifest.version,
+        spec: spec.map(|spec| String::from_utf8_lossy(&spec).into_owned()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_serve() {
+        let os = Os::new().await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("daemon.sock");
+        std::fs::write(dir.path().join("notes.txt"), "").unwrap();
+        let listener = bind(&path).await.unwrap();
+        let server = tokio::spawn({
+            let os = os.clone();
+            async move { serve(&os, listener, None).await }
+        });
+
+        let mut client = super::super::client::Client::connect(&path).await.unwrap();
+        assert!(matches!(
+            client.send(&Request::Ping).await.unwrap(),
+            Response::Pong { pid, schedules, .. }
+                if pid == std::process::id() && schedules[0].name == "completion-specs"
+        ));
+        assert_eq!(
+            client
+                .send(&Request::Complete {
+                    line: "cat no".into(),
+                    cursor: 6,
+                    cwd: dir.path().to_path_buf(),
+                })
+                .await
+                .unwrap(),
+            Response::Completions {
+                start: 4,
+                candidates: vec!["notes.txt".into()]
+            }
+        );
+        assert!(matches!(
+            client
+                .send(&Request::Complete {
+                    line: "cat".into(),
+                    cursor: 10,
+                    cwd: dir.path().to_path_buf(),
+                })
+                .await
+                .unwrap(),
+            Response::Error { .. }
+        ));
+        // The connection stays usable after a request is refused
+        assert!(matches!(
+            client
+                .send(&Request::CompletionSpec { command: "git".into() })
+                .await
+                .unwrap(),
+            Response::Error { message } if message.contains("No completion specs are installed")
+        ));
+
+        // A second daemon refuses to replace the running one.
+        assert!(bind(&path).await.is_err());
+
+        let mut client = super::super::client::Client::connect(&path).await.unwrap();
+        assert_eq!(client.send(&Request::Shutdown).await.unwrap(), Response::Ok);
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_serve_exits_when_idle() {
+        let os = Os::new().await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let listener = bind(&dir.path().join("daemon.sock")).await.unwrap();
+        serve(&os, listener, Some(Duration::from_millis(10))).await.unwrap();
+    }
+}
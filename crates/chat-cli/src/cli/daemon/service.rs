@@ -0,0 +1,234 @@
+//! Registers the daemon with the platform's service manager so it starts at login and is kept
+//! running, instead of being started on demand and exiting when idle.
+
+use std::fmt::Display;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use eyre::{
+    Result,
+    bail,
+};
+
+use crate::os::Os;
+use crate::util::paths::home_dir;
+
+const LAUNCHD_LABEL: &str = "com.amazon.q.daemon";
+const SYSTEMD_UNIT: &str = "q-daemon.service";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceManager {
+    Launchd,
+    Systemd,
+}
+
+impl ServiceManager {
+    /// The service manager of the platform, if it is supported.
+    pub fn current() -> Option<Self> {
+        if cfg!(target_os = "macos") {
+            Some(Self::Launchd)
+        } else if cfg!(target_os = "linux") {
+            Some(Self::Systemd)
+        } else {
+            None
+        }
+    }
+
+    /// Path of the launchd agent or systemd user unit.
+    pub fn unit_path(&self, os: &Os) -> Result<PathBuf> {
+        Ok(match self {
+            Self::Launchd => home_dir(os)?
+                .join("Library/LaunchAgents")
+                .join(format!("{LAUNCHD_LABEL}.plist")),
+            Self::Systemd => {
+                let config_dir = match os.env.get("XDG_CONFIG_HOME") {
+                    Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+                    _ => home_dir(os)?.join(".config"),
+                };
+                config_dir.join("systemd/user").join(SYSTEMD_UNIT)
+            },
+        })
+    }
+
+    pub fn is_installed(&self, os: &Os) -> bool {
+        self.unit_path(os).is_ok_and(|path| os.fs.exists(path))
+    }
+
+    /// Contents of the unit running `exe` as the daemon.
+    pub fn unit(&self, exe: &Path) -> String {
+        let exe = exe.to_string_lossy();
+        match self {
+            Self::Launchd => {
+                let exe = exe.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+                format!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{LAUNCHD_LABEL}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{exe}</string>
+        <string>daemon</string>
+        <string>run</string>
+        <string>--no-idle-timeout</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+    <key>ProcessType</key>
+    <string>Background</string>
+</dict>
+</plist>
+"#
+                )
+            },
+            Self::Systemd => {
+                let exe = exe.replace('\\', "\\\\").replace('"', "\\\"").replace('%', "%%");
+                format!(
+                    r#"[Unit]
+Description=Amazon Q daemon
+
+[Service]
+ExecStart="{exe}" daemon run --no-idle-timeout
+Restart=on-failure
+RestartSec=10
+
+[Install]
+WantedBy=default.target
+"#
+                )
+            },
+        }
+    }
+
+    /// Writes the unit for the current executable and starts it.
+    pub async fn install(&self, os: &Os) -> Result<PathBuf> {
+        let path = self.unit_path(os)?;
+        if let Some(parent) = path.parent() {
+            os.fs.create_dir_all(parent).await?;
+        }
+        os.fs.write(&path, self.unit(&os.env.current_exe()?)).await?;
+
+        match self {
+            Self::Launchd => {
+                let path = path.to_string_lossy();
+                // Unload a previous version of the agent, which fails if there is none.
+                run(&["launchctl", "bootout", &launchd_domain(), &path]).await.ok();
+                run(&["launchctl", "bootstrap", &launchd_domain(), &path]).await?;
+            },
+            Self::Systemd => {
+                run(&["systemctl", "--user", "daemon-reload"]).await?;
+                run(&["systemctl", "--user", "enable", "--now", SYSTEMD_UNIT]).await?;
+            },
+        }
+        Ok(path)
+    }
+
+    /// Stops and removes the unit, returning false if it wasn't installed.
+    pub async fn uninstall(&self, os: &Os) -> Result<bool> {
+        if !self.is_installed(os) {
+            return Ok(false);
+        }
+        let path = self.unit_path(os)?;
+        match self {
+            Self::Launchd => {
+                run(&["launchctl", "bootout", &launchd_domain(), &path.to_string_lossy()])
+                    .await
+                    .ok();
+            },
+            Self::Systemd => {
+                run(&["systemctl", "--user", "disable", "--now", SYSTEMD_UNIT])
+                    .await
+                    .ok();
+            },
+        }
+        os.fs.remove_file(&path).await?;
+        if *self == Self::Systemd {
+            run(&["systemctl", "--user", "daemon-reload"]).await?;
+        }
+        Ok(true)
+    }
+
+    /// Starts the installed unit.
+    pub async fn start(&self) -> Result<()> {
+        match self {
+            Self::Launchd => {
+                run(&[
+                    "launchctl",
+                    "kickstart",
+                    &format!("{}/{LAUNCHD_LABEL}", launchd_domain()),
+                ])
+                .await
+            },
+            Self::Systemd => run(&["systemctl", "--user", "start", SYSTEMD_UNIT]).await,
+        }
+    }
+}
+
+impl Display for ServiceManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Launchd => write!(f, "launchd"),
+            Self::Systemd => write!(f, "systemd"),
+        }
+    }
+}
+
+fn launchd_domain() -> String {
+    format!("gui/{}", nix::unistd::getuid())
+}
+
+async fn run(args: &[&str]) -> Result<()> {
+    let output = tokio::process::Command::new(args[0]).args(&args[1..]).output().await?;
+    if !output.status.success() {
+        bail!(
+            "`{}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_launchd_unit() {
+        let unit = ServiceManager::Launchd.unit(Path::new("/Applications/Q & A/q"));
+        assert!(unit.contains("<string>com.amazon.q.daemon</string>"));
+        assert!(unit.contains("<string>/Applications/Q &amp; A/q</string>"));
+        assert!(unit.contains("<string>--no-idle-timeout</string>"));
+    }
+
+    #[test]
+    fn test_systemd_unit() {
+        let unit = ServiceManager::Systemd.unit(Path::new("/home/me/100% \"q\"/q"));
+        assert!(unit.contains(r#"ExecStart="/home/me/100%% \"q\"/q" daemon run --no-idle-timeout"#));
+        assert!(unit.contains("WantedBy=default.target"));
+    }
+
+    #[tokio::test]
+    async fn test_unit_path() {
+        let os = Os::new().await.unwrap();
+        let home = home_dir(&os).unwrap();
+        assert_eq!(
+            ServiceManager::Systemd.unit_path(&os).unwrap(),
+            home.join(".config/systemd/user/q-daemon.service")
+        );
+        assert_eq!(
+            ServiceManager::Launchd.unit_path(&os).unwrap(),
+            home.join("Library/LaunchAgents/com.amazon.q.daemon.plist")
+        );
+        assert!(!ServiceManager::Systemd.is_installed(&os));
+    }
+}
@@ -1,4 +1,27 @@
-use clap::ValueEnum;
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use agent::journal::{
+    Journal,
+    Replay,
+};
+use agent::protocol::{
+    AgentEvent,
+    InternalEvent,
+};
+use clap::{
+    Subcommand,
+    ValueEnum,
+};
+use eyre::{
+    Result,
+    bail,
+};
+use tokio::io::{
+    AsyncBufReadExt,
+    BufReader,
+};
 
 #[derive(Debug, ValueEnum, Clone, PartialEq, Eq)]
 pub enum Build {
@@ -58,9 +81,6 @@ pub enum TISAction {
     Deselect,
 }
 
-#[cfg(target_os = "macos")]
-use std::path::PathBuf;
-
 #[cfg(target_os = "macos")]
 #[derive(Debug, clap::Subcommand, Clone, PartialEq, Eq)]
 pub enum InputMethodDebugAction {
@@ -80,3 +100,120 @@ pub enum InputMethodDebugAction {
         action: TISAction,
     },
 }
+
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum DebugSubcommand {
+    /// Replay a recorded conversation journal and inspect the agent's state
+    Replay {
+        /// Path to the journal
+        file: PathBuf,
+        /// Stop after this turn, starting from 1. Defaults to the last turn.
+        #[arg(long)]
+        turn: Option<usize>,
+    },
+}
+
+impl DebugSubcommand {
+    pub async fn execute(self) -> Result<ExitCode> {
+        match self {
+            Self::Replay { file, turn } => replay(file, turn).await,
+        }
+    }
+}
+
+const INSPECTOR_HELP: &str = "Commands:
+  messages       Print the conversation history
+  snapshot       Print the agent's snapshot as JSON
+  events [TURN]  Print the events of a replayed turn, defaults to the last
+  requests       Print the requests sent to the model in the replayed turns
+  next           Replay the next turn
+  help           Show this help
+  quit           Exit the inspector";
+
+async fn replay(file: PathBuf, turn: Option<usize>) -> Result<ExitCode> {
+    let mut stderr = std::io::stderr();
+    let journal = Journal::load(&file).await?;
+    let stop_at = turn.unwrap_or(journal.turns.len());
+    if stop_at > journal.turns.len() {
+        bail!("The journal has {} turns", journal.turns.len());
+    }
+
+    let mut replay = Replay::new(journal).await?;
+    while replay.replayed_turns() < stop_at {
+        replay_turn(&mut replay).await?;
+    }
+
+    writeln!(stderr, "{INSPECTOR_HELP}")?;
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    loop {
+        write!(stderr, "\n> ")?;
+        stderr.flush()?;
+        let Some(line) = lines.next_line().await? else {
+            break;
+        };
+        let mut args = line.split_whitespace();
+        match args.next() {
+            Some("messages") => {
+                for message in replay.agent().create_snapshot().await?.conversation_state.messages {
+                    println!("{}", serde_json::to_string(&message)?);
+                }
+            },
+            Some("snapshot") => {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&replay.agent().create_snapshot().await?)?
+                );
+            },
+            Some("events") => {
+                let index = match args.next().map(str::parse::<usize>) {
+                    Some(Ok(turn)) => turn.checked_sub(1),
+                    Some(Err(_)) => {
+                        writeln!(stderr, "Expected a turn number")?;
+                        continue;
+                    },
+                    None => replay.replayed_turns().checked_sub(1),
+                };
+                match index.and_then(|index| replay.events().get(index)) {
+                    Some(events) => {
+                        for event in events {
+                            println!("{}", serde_json::to_string(event)?);
+                        }
+                    },
+                    None => writeln!(stderr, "That turn was not replayed")?,
+                }
+            },
+            Some("requests") => {
+                for event in replay.events().iter().flatten() {
+                    if let AgentEvent::Internal(InternalEvent::RequestSent(request)) = event {
+                        println!("{}", serde_json::to_string(request)?);
+                    }
+                }
+            },
+            Some("next") => {
+                if replay.replayed_turns() == replay.journal().turns.len() {
+                    writeln!(stderr, "Every turn was replayed")?;
+                } else {
+                    replay_turn(&mut replay).await?;
+                }
+            },
+            Some("help") => writeln!(stderr, "{INSPECTOR_HELP}")?,
+            Some("quit" | "exit") => break,
+            Some(command) => writeln!(stderr, "Unknown command {command}, run `help` to list the commands")?,
+            None => (),
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn replay_turn(replay: &mut Replay) -> Result<()> {
+    let turn = replay.replayed_turns() + 1;
+    let reason = replay.replay_turn().await?;
+    let events = replay.events().last().map_or(0, Vec::len);
+    writeln!(
+        std::io::stderr(),
+        "Replayed turn {turn} of {}: {events} events, stopped with {reason:?}",
+        replay.journal().turns.len()
+    )?;
+    Ok(())
+}
@@ -22,7 +22,10 @@ use spinners::{
 
 use super::OutputFormat;
 use crate::os::Os;
-use crate::os::diagnostics::Diagnostics;
+use crate::os::diagnostics::{
+    Diagnostics,
+    ShellDiagnostics,
+};
 
 #[derive(Clone, Debug, Args, PartialEq, Eq)]
 pub struct DiagnosticArgs {
@@ -32,6 +35,10 @@ pub struct DiagnosticArgs {
     /// Force limited diagnostic output
     #[arg(long)]
     force: bool,
+    /// Show how the shell was launched and how its environment differs from login and interactive
+    /// shells
+    #[arg(long)]
+    shell: bool,
 }
 
 impl DiagnosticArgs {
@@ -51,14 +58,28 @@ impl DiagnosticArgs {
             })?;
         }
 
-        let diagnostics = Diagnostics::new(&os.env).await;
+        let stop_spinner = |spinner: Option<Spinner>| -> Result<()> {
+            if let Some(mut sp) = spinner {
+                sp.stop();
+                execute!(std::io::stdout(), Clear(ClearType::CurrentLine), cursor::Show)?;
+                println!();
+            }
+            Ok(())
+        };
 
-        if let Some(mut sp) = spinner {
-            sp.stop();
-            execute!(std::io::stdout(), Clear(ClearType::CurrentLine), cursor::Show)?;
-            println!();
+        if self.shell {
+            let diagnostics = ShellDiagnostics::new(&os.env).await;
+            stop_spinner(spinner)?;
+            self.format.print(
+                || diagnostics.user_readable().expect("Failed to run user_readable()"),
+                || &diagnostics,
+            );
+            return Ok(ExitCode::SUCCESS);
         }
 
+        let diagnostics = Diagnostics::new(&os.env).await;
+        stop_spinner(spinner)?;
+
         self.format.print(
             || diagnostics.user_readable().expect("Failed to run user_readable()"),
             || &diagnostics,
@@ -0,0 +1,211 @@
+//! Classes of failures with stable exit codes, so scripts can branch on why a command failed
+//! instead of matching the error message.
+
+use std::error::Error;
+use std::process::ExitCode;
+
+use anstream::eprintln;
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::api_client::ApiClientError;
+use crate::api_client::error::{
+    ConverseStreamError,
+    ConverseStreamErrorKind,
+};
+use crate::auth::AuthError;
+use crate::cli::chat::ChatError;
+use crate::theme::StyledText;
+
+/// Format of the error printed when a command fails, see `--error-format`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ErrorFormat {
+    /// Prints the error message
+    #[default]
+    Text,
+    /// Prints the class, exit code and message of the error as a JSON object
+    Json,
+}
+
+/// Why a command failed. The exit code of each class is part of the CLI's interface, so existing
+/// codes must not change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    /// Any failure without a class of its own
+    General,
+    /// Not logged in, or the credentials were rejected
+    Auth,
+    /// Too many requests, or the usage limit was reached
+    Throttled,
+    /// The conversation doesn't fit in the context window of the model
+    ContextOverflow,
+    /// A tool use needed an approval that couldn't be given, as with `--no-interactive`
+    ToolDenied,
+    /// The user interrupted the command
+    Interrupted,
+}
+
+impl ErrorClass {
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Self::General => 1,
+            // 2 is left to usage errors, which clap reports before the command runs
+            Self::Auth => 3,
+            Self::Throttled => 4,
+            Self::ContextOverflow => 5,
+            Self::ToolDenied => 6,
+            // The code shells use for commands ended by SIGINT
+            Self::Interrupted => 130,
+        }
+    }
+
+    pub fn of(err: &eyre::Report) -> Self {
+        err.chain().find_map(classify).unwrap_or(Self::General)
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ErrorReport {
+    class: ErrorClass,
+    exit_code: u8,
+    message: String,
+}
+
+/// Prints `err` to stderr and returns the exit code of its class.
+pub fn report(err: &eyre::Report, format: ErrorFormat, verbose: bool) -> ExitCode {
+    let class = ErrorClass::of(err);
+    match format {
+        ErrorFormat::Text if verbose => eprintln!("{} {err:?}", StyledText::error("error:")),
+        ErrorFormat::Text => eprintln!("{} {err}", StyledText::error("error:")),
+        ErrorFormat::Json => {
+            let report = ErrorReport {
+                class,
+                exit_code: class.exit_code(),
+                message: err.to_string(),
+            };
+            eprintln!("{}", serde_json::to_string(&report).unwrap_or_default());
+        },
+    }
+    ExitCode::from(class.exit_code())
+}
+
+/// Classifies `err` itself, or [None] to go on with its source.
+fn classify(err: &(dyn Error + 'static)) -> Option<ErrorClass> {
+    if let Some(err) = err.downcast_ref::<ChatError>() {
+        // The errors wrapped by these variants aren't their source, so they are classified here
+        return match err {
+            ChatError::Auth(_) => Some(ErrorClass::Auth),
+            ChatError::NonInteractiveToolApproval => Some(ErrorClass::ToolDenied),
+            ChatError::Interrupted { .. } => Some(ErrorClass::Interrupted),
+            ChatError::CompactHistoryFailure => Some(ErrorClass::ContextOverflow),
+            ChatError::Client(err) => classify_chain(err.as_ref()),
+            ChatError::SendMessage(err) => classify_chain(err.as_ref()),
+            ChatError::ResponseStream(err) => classify_chain(err.as_ref()),
+            ChatError::Std(err) => classify_chain(err),
+            _ => None,
+        };
+    }
+    if err.is::<AuthError>() {
+        return Some(ErrorClass::Auth);
+    }
+    if let Some(err) = err.downcast_ref::<ConverseStreamError>() {
+        return match err.kind {
+            ConverseStreamErrorKind::Throttling | ConverseStreamErrorKind::MonthlyLimitReached => {
+                Some(ErrorClass::Throttled)
+            },
+            ConverseStreamErrorKind::ContextWindowOverflow => Some(ErrorClass::ContextOverflow),
+            _ => from_status_code(err.status_code),
+        };
+    }
+    if let Some(err) = err.downcast_ref::<ApiClientError>() {
+        return match err {
+            ApiClientError::AuthError(_) | ApiClientError::Credentials(_) => Some(ErrorClass::Auth),
+            _ => from_status_code(err.status_code()),
+        };
+    }
+    if let Some(err) = err.downcast_ref::<std::io::Error>() {
+        if err.kind() == std::io::ErrorKind::Interrupted {
+            return Some(ErrorClass::Interrupted);
+        }
+    }
+    None
+}
+
+fn classify_chain(err: &(dyn Error + 'static)) -> Option<ErrorClass> {
+    std::iter::successors(Some(err), |err| err.source()).find_map(classify)
+}
+
+fn from_status_code(status_code: Option<u16>) -> Option<ErrorClass> {
+    match status_code {
+        Some(401 | 403) => Some(ErrorClass::Auth),
+        Some(429) => Some(ErrorClass::Throttled),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eyre::WrapErr;
+
+    use super::*;
+    use crate::api_client::error::ConverseStreamSdkError;
+
+    fn converse_stream_error(kind: ConverseStreamErrorKind) -> ConverseStreamError {
+        ConverseStreamError::new(kind, None::<ConverseStreamSdkError>)
+    }
+
+    #[test]
+    fn test_error_class() {
+        let err = eyre::eyre!("Failed to open the file");
+        assert_eq!(ErrorClass::of(&err), ErrorClass::General);
+
+        let err = eyre::Report::new(AuthError::NoToken).wrap_err("You are not logged in");
+        assert_eq!(ErrorClass::of(&err), ErrorClass::Auth);
+
+        let err = eyre::Report::new(ChatError::NonInteractiveToolApproval);
+        assert_eq!(ErrorClass::of(&err), ErrorClass::ToolDenied);
+
+        let err = eyre::Report::new(ChatError::Client(Box::new(ApiClientError::ConverseStream(
+            converse_stream_error(ConverseStreamErrorKind::Throttling),
+        ))));
+        assert_eq!(ErrorClass::of(&err), ErrorClass::Throttled);
+
+        let err = eyre::Report::new(ApiClientError::ConverseStream(converse_stream_error(
+            ConverseStreamErrorKind::ContextWindowOverflow,
+        )));
+        assert_eq!(ErrorClass::of(&err), ErrorClass::ContextOverflow);
+
+        let err = eyre::Report::new(
+            converse_stream_error(ConverseStreamErrorKind::Unknown {
+                reason_code: "AccessDenied".to_string(),
+            })
+            .set_status_code(Some(403)),
+        );
+        assert_eq!(ErrorClass::of(&err), ErrorClass::Auth);
+
+        let err = Err::<(), _>(std::io::Error::from(std::io::ErrorKind::Interrupted))
+            .wrap_err("Failed to read the prompt")
+            .unwrap_err();
+        assert_eq!(ErrorClass::of(&err), ErrorClass::Interrupted);
+    }
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let classes = [
+            ErrorClass::General,
+            ErrorClass::Auth,
+            ErrorClass::Throttled,
+            ErrorClass::ContextOverflow,
+            ErrorClass::ToolDenied,
+            ErrorClass::Interrupted,
+        ];
+        let codes = classes
+            .iter()
+            .map(|class| class.exit_code())
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(codes.len(), classes.len());
+        assert!(!codes.contains(&2));
+    }
+}
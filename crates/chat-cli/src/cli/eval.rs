@@ -0,0 +1,239 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use agent::agent_loop::model::Model;
+use agent::eval::{
+    Scenario,
+    ScenarioReport,
+    Variant,
+    VariantComparison,
+};
+use clap::Args;
+use crossterm::style::Stylize;
+use eyre::Result;
+use serde::Serialize;
+
+use super::OutputFormat;
+use crate::agent::rts::model_providers;
+use crate::os::Os;
+
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct EvalArgs {
+    /// Scenario files, or directories of scenarios
+    #[arg(required = true)]
+    pub paths: Vec<PathBuf>,
+    /// Run against the model instead of the recorded responses of the scenarios
+    #[arg(long)]
+    pub live: bool,
+    /// Model to run live scenarios against
+    #[arg(long, requires = "live")]
+    pub model: Option<String>,
+    /// YAML file of agent config or system prompt variants to run every scenario with, and
+    /// compare
+    #[arg(long)]
+    pub variants: Option<PathBuf>,
+    /// Format of the output
+    #[arg(long, short, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EvalReport {
+    passed: usize,
+    failed: usize,
+    /// Scenarios without recorded responses, which only run with `--live`
+    skipped: Vec<PathBuf>,
+    scenarios: Vec<ScenarioReport>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VariantsReport {
+    comparison: VariantComparison,
+    /// Report of each variant, in the order of the comparison
+    reports: Vec<EvalReport>,
+}
+
+impl EvalArgs {
+    pub async fn execute(self, os: &Os) -> Result<ExitCode> {
+        let mut scenarios = Vec::new();
+        for path in &self.paths {
+            scenarios.extend(Scenario::load_all(path).await?);
+        }
+
+        let Some(variants_path) = &self.variants else {
+            let report = self.run(os, &scenarios, None).await?;
+            self.format.print(
+                || {
+                    format!(
+                        "\n{} passed, {} failed, {} skipped",
+                        report.passed,
+                        report.failed,
+                        report.skipped.len()
+                    )
+                },
+                || &report,
+            );
+            return Ok(exit_code(report.failed));
+        };
+
+        let variants = Variant::load_all(variants_path).await?;
+        let mut reports = Vec::new();
+        for variant in &variants {
+            writeln!(std::io::stderr(), "\n{} {}", "VARIANT".bold(), variant.name)?;
+            reports.push(self.run(os, &scenarios, Some(variant)).await?);
+        }
+        let results = variants
+            .iter()
+            .zip(&reports)
+            .map(|(variant, report)| (variant.name.clone(), report.scenarios.clone()))
+            .collect::<Vec<_>>();
+        let report = VariantsReport {
+            comparison: VariantComparison::new(&results),
+            reports,
+        };
+        self.format
+            .print(|| format!("\n{}", render_comparison(&report.comparison)), || &report);
+        Ok(exit_code(report.reports.iter().map(|report| report.failed).sum()))
+    }
+
+    /// Runs `scenarios`, with the agent config of `variant` if given, reporting each outcome as it
+    /// completes.
+    async fn run(&self, os: &Os, scenarios: &[(PathBuf, Scenario)], variant: Option<&Variant>) -> Result<EvalReport> {
+        let mut stderr = std::io::stderr();
+        let mut report = EvalReport {
+            passed: 0,
+            failed: 0,
+            skipped: Vec::new(),
+            scenarios: Vec::new(),
+        };
+        for (path, scenario) in scenarios {
+            let scenario = match variant {
+                Some(variant) => variant.apply(scenario),
+                None => scenario.clone(),
+            };
+            let model: Arc<dyn Model> = if self.live {
+                let mut agent_config = scenario.agent_config.clone().unwrap_or_default();
+                if let Some(model_id) = &self.model {
+                    agent_config.set_model_id(model_id.clone());
+                }
+                model_providers(os.client.clone()).create(agent_config.model(), None)?
+            } else {
+                match scenario.fixture_model().await? {
+                    Some(model) => Arc::new(model),
+                    None => {
+                        writeln!(stderr, "{} {} (no recorded responses)", "SKIP".yellow(), scenario.name)?;
+                        report.skipped.push(path.clone());
+                        continue;
+                    },
+                }
+            };
+
+            let result = scenario.run(path, model).await?;
+            if result.passed {
+                report.passed += 1;
+                writeln!(stderr, "{} {} ({}ms)", "PASS".green(), result.name, result.duration_ms)?;
+            } else {
+                report.failed += 1;
+                writeln!(stderr, "{} {} ({})", "FAIL".red(), result.name, path.display())?;
+                for failure in &result.failures {
+                    writeln!(stderr, "    {failure}")?;
+                }
+            }
+            report.scenarios.push(result);
+        }
+        Ok(report)
+    }
+}
+
+fn exit_code(failed: usize) -> ExitCode {
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Renders a table of the results of each variant, followed by the scenarios whose outcome
+/// depends on the variant.
+fn render_comparison(comparison: &VariantComparison) -> String {
+    let name_width = comparison
+        .variants
+        .iter()
+        .map(|variant| variant.name.len())
+        .chain(["variant".len()])
+        .max()
+        .unwrap_or_default();
+    let mut rendered = format!(
+        "{:<name_width$}  {:>7}  {:>7}  {:>8}  {:>12}  {:>13}\n",
+        "variant", "passed", "success", "requests", "input tokens", "output tokens"
+    );
+    for variant in &comparison.variants {
+        rendered.push_str(&format!(
+            "{:<name_width$}  {:>7}  {:>6.0}%  {:>8.1}  {:>12}  {:>13}\n",
+            variant.name,
+            format!("{}/{}", variant.passed, variant.passed + variant.failed),
+            variant.success_rate * 100.0,
+            variant.mean_requests,
+            variant.usage.input_tokens,
+            variant.usage.output_tokens,
+        ));
+    }
+
+    if !comparison.diverging.is_empty() {
+        rendered.push_str("\nScenarios that pass with some variants only:\n");
+        for scenario in &comparison.diverging {
+            rendered.push_str(&format!(
+                "  {} ({}): passed with {}\n",
+                scenario.name,
+                scenario.path.display(),
+                scenario.passed_with.join(", ")
+            ));
+        }
+    }
+    rendered.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use agent::agent_loop::types::TokenUsage;
+    use agent::eval::{
+        DivergingScenario,
+        VariantSummary,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_render_comparison() {
+        let summary = |name: &str, passed: usize, input_tokens: u64| VariantSummary {
+            name: name.to_string(),
+            passed,
+            failed: 4 - passed,
+            success_rate: passed as f64 / 4.0,
+            mean_requests: 2.5,
+            usage: TokenUsage {
+                input_tokens,
+                output_tokens: 100,
+                ..Default::default()
+            },
+        };
+        let rendered = render_comparison(&VariantComparison {
+            variants: vec![summary("baseline", 3, 1000), summary("concise", 4, 800)],
+            diverging: vec![DivergingScenario {
+                name: "edits a file".to_string(),
+                path: "evals/edit.yaml".into(),
+                passed_with: vec!["concise".to_string()],
+            }],
+        });
+        let lines = rendered.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[1],
+            "baseline      3/4      75%       2.5          1000            100"
+        );
+        assert_eq!(lines[5], "  edits a file (evals/edit.yaml): passed with concise");
+    }
+}
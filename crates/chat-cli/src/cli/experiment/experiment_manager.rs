@@ -42,7 +42,7 @@ pub struct Experiment {
 static AVAILABLE_EXPERIMENTS: &[Experiment] = &[
     Experiment {
         experiment_name: ExperimentName::Knowledge,
-        description: "Enables persistent context storage and retrieval across chat sessions (/knowledge)",
+        description: "Enables persistent context storage and retrieval across chat sessions (/knowledge, /kb)",
         setting_key: Setting::EnabledKnowledge,
         enabled: true,
         commands: &[
@@ -56,6 +56,8 @@ static AVAILABLE_EXPERIMENTS: &[Experiment] = &[
             "/knowledge update",
             "/knowledge status",
             "/knowledge cancel",
+            "/kb",
+            "/kb search",
         ],
     },
     Experiment {
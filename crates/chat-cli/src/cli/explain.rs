@@ -0,0 +1,449 @@
+use std::io::{
+    ErrorKind,
+    IsTerminal,
+};
+use std::process::{
+    ExitCode,
+    Stdio,
+};
+use std::time::Duration;
+
+use clap::Args;
+use crossterm::{
+    execute,
+    style,
+};
+use dialoguer::Confirm;
+use eyre::{
+    Result,
+    bail,
+};
+use tokio::process::Command;
+use tracing::debug;
+
+use super::ask::stream_answer;
+use crate::database::settings::Setting;
+use crate::os::Os;
+use crate::theme::StyledText;
+use crate::util::dialoguer_theme;
+
+/// How long a man page or --help output may take to generate
+const DOC_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Lines from the top of the documentation sent for each program, which cover its name and
+/// synopsis
+const HEADER_LINES: usize = 12;
+
+/// Lines sent for each flag found in the documentation
+const FLAG_LINES: usize = 8;
+
+/// Most of the documentation sent for each program, in bytes
+const MAX_DOC_BYTES: usize = 8000;
+
+/// Tokens that separate the commands of a pipeline or list
+const SEPARATORS: [&str; 5] = ["|", "||", "&&", ";", "&"];
+
+/// Commands that run the command after them
+const WRAPPERS: [&str; 6] = ["sudo", "env", "time", "nohup", "exec", "command"];
+
+/// Explains a shell command flag by flag, grounded on the man pages found on this machine, or on
+/// the --help output of programs the user agreed to run.
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct ExplainArgs {
+    /// The command to explain, e.g. "tar -xzvf foo.tgz"
+    #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+    pub command: Vec<String>,
+    /// Model to explain with, defaults to the chat.defaultModel setting
+    #[arg(long)]
+    pub model: Option<String>,
+}
+
+impl ExplainArgs {
+    pub async fn execute(self, os: &Os) -> Result<ExitCode> {
+        let command = self.command.join(" ");
+        let Some(segments) = segments(&command) else {
+            bail!("The command has unbalanced quotes");
+        };
+
+        let mut docs = Vec::new();
+        let mut undocumented = Vec::new();
+        for segment in &segments {
+            let Some((program, subcommand)) = program(segment) else {
+                continue;
+            };
+            if docs.iter().any(|doc: &Documentation| doc.program == program) || undocumented.contains(&program) {
+                continue;
+            }
+            let doc = match Documentation::man_page(&program, subcommand.as_deref()).await {
+                Some(doc) => Some(doc),
+                None if confirm_help(&program)? => Documentation::help_output(&program, subcommand.as_deref()).await,
+                None => None,
+            };
+            match doc {
+                Some(doc) => docs.push(doc),
+                None => undocumented.push(program),
+            }
+        }
+
+        let mut stderr = std::io::stderr();
+        if !docs.is_empty() {
+            let sources = docs.iter().map(|doc| doc.source.as_str()).collect::<Vec<_>>();
+            execute!(
+                stderr,
+                StyledText::secondary_fg(),
+                style::Print(format!("Using {}\n", sources.join(", "))),
+                StyledText::reset(),
+            )?;
+        }
+        if !undocumented.is_empty() {
+            execute!(
+                stderr,
+                StyledText::warning_fg(),
+                style::Print(format!(
+                    "No documentation found for {}, its explanation isn't grounded on local docs\n",
+                    undocumented.join(", ")
+                )),
+                StyledText::reset(),
+            )?;
+        }
+
+        let excerpts = docs
+            .iter()
+            .map(|doc| {
+                let command_flags = segments
+                    .iter()
+                    .filter(|segment| program(segment).is_some_and(|(program, _)| program == doc.program))
+                    .flat_map(|segment| flags(segment))
+                    .collect::<Vec<_>>();
+                (doc.source.as_str(), excerpt(&doc.text, &command_flags))
+            })
+            .collect::<Vec<_>>();
+
+        let model_id = self
+            .model
+            .or_else(|| os.database.settings.get_string(Setting::ChatDefaultModel));
+        stream_answer(os, build_prompt(&command, &excerpts), model_id, &mut std::io::stdout()).await?;
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// The documentation of a program found on this machine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Documentation {
+    program: String,
+    /// Where the text comes from, e.g. `man tar`
+    source: String,
+    text: String,
+}
+
+impl Documentation {
+    /// Looks for the man page of `program`, or of `program-subcommand` for commands like `git
+    /// commit`.
+    async fn man_page(program: &str, subcommand: Option<&str>) -> Option<Self> {
+        let mut man_pages = Vec::new();
+        if let Some(subcommand) = subcommand {
+            man_pages.push(format!("{program}-{subcommand}"));
+        }
+        man_pages.push(program.to_string());
+
+        for page in man_pages {
+            if let Some(text) = run("man", &[&page], true).await {
+                return Some(Self {
+                    program: program.to_string(),
+                    source: format!("man {page}"),
+                    text,
+                });
+            }
+        }
+        None
+    }
+
+    /// Runs `program` with --help, or `program subcommand --help`, and returns the output.
+    ///
+    /// This runs the program, which does whatever it normally does if it doesn't handle --help,
+    /// so it is only called once the user agreed, see [confirm_help]. Only programs found on the
+    /// PATH are run, not paths to scripts.
+    async fn help_output(program: &str, subcommand: Option<&str>) -> Option<Self> {
+        let mut help_commands = Vec::new();
+        if let Some(subcommand) = subcommand {
+            help_commands.push(vec![subcommand, "--help"]);
+        }
+        help_commands.push(vec!["--help"]);
+
+        for args in help_commands {
+            if let Some(text) = run(program, &args, false).await {
+                return Some(Self {
+                    program: program.to_string(),
+                    source: format!("{program} {}", args.join(" ")),
+                    text,
+                });
+            }
+        }
+        None
+    }
+}
+
+/// Asks the user whether `program` may be run with --help, since a program that doesn't handle
+/// the flag would do whatever it normally does. Nothing is run without a terminal to ask on.
+fn confirm_help(program: &str) -> Result<bool> {
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        return Ok(false);
+    }
+    let answer = Confirm::with_theme(&dialoguer_theme())
+        .with_prompt(format!(
+            "No man page for {program}. Run `{program} --help` to read its usage? \
+             Programs that don't support --help run as usual"
+        ))
+        .default(false)
+        .interact_opt();
+    match answer {
+        Ok(answer) => Ok(answer == Some(true)),
+        Err(dialoguer::Error::IO(ref e)) if e.kind() == ErrorKind::Interrupted => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Runs `program` and returns its output without formatting, or [None] if it fails, prints nothing
+/// or takes longer than [DOC_TIMEOUT]. Many programs print their --help output to stderr or exit
+/// with an error after printing it, so only `require_success` commands must succeed.
+async fn run(program: &str, args: &[&str], require_success: bool) -> Option<String> {
+    let output = Command::new(program)
+        .args(args)
+        .env("MANPAGER", "cat")
+        .env("PAGER", "cat")
+        .env("MANWIDTH", "100")
+        .env("GROFF_NO_SGR", "1")
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = match tokio::time::timeout(DOC_TIMEOUT, output).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(err)) => {
+            debug!(?err, program, "failed to run");
+            return None;
+        },
+        Err(_) => {
+            debug!(program, ?args, "timed out");
+            return None;
+        },
+    };
+    if require_success && !output.status.success() {
+        return None;
+    }
+    let bytes = match output.stdout.is_empty() {
+        true => output.stderr,
+        false => output.stdout,
+    };
+    let text = strip_overstrike(&String::from_utf8_lossy(&bytes));
+    (!text.trim().is_empty()).then_some(text)
+}
+
+/// Removes the backspace sequences that man pages use for bold and underlined text.
+fn strip_overstrike(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\u{8}' => {
+                out.pop();
+            },
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Splits `command` into the commands of its pipelines and lists, or [None] if it has unbalanced
+/// quotes.
+fn segments(command: &str) -> Option<Vec<Vec<String>>> {
+    let tokens = shlex::split(command)?;
+    Some(
+        tokens
+            .split(|token| SEPARATORS.contains(&token.as_str()))
+            .filter(|segment| !segment.is_empty())
+            .map(<[String]>::to_vec)
+            .collect(),
+    )
+}
+
+/// Returns the program a command runs, skipping variable assignments and wrappers like `sudo`,
+/// and the word after it that may be a subcommand. Paths to scripts are ignored since they have no
+/// documentation to find.
+fn program(segment: &[String]) -> Option<(String, Option<String>)> {
+    let mut tokens = segment
+        .iter()
+        .skip_while(|token| (token.contains('=') && !token.starts_with('-')) || WRAPPERS.contains(&token.as_str()));
+    let program = tokens.next()?;
+    if program.contains('/') || program.starts_with('-') {
+        return None;
+    }
+    let subcommand = tokens
+        .next()
+        .filter(|token| token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') && !token.starts_with('-'))
+        .cloned();
+    Some((program.clone(), subcommand))
+}
+
+/// Returns the flags of a command, e.g. `--file` for `--file=foo.tgz`. Single dash flags are
+/// kept whole, since some programs use them for long options like `find -name`, and
+/// [excerpt] splits them into letters when they aren't documented whole.
+fn flags(segment: &[String]) -> Vec<String> {
+    segment
+        .iter()
+        .filter(|token| token.starts_with('-') && token.len() > 1 && *token != "--")
+        .map(|token| token.split('=').next().unwrap_or(token).to_string())
+        .collect()
+}
+
+/// Returns the top of `doc` and the paragraphs documenting each of `flags`.
+fn excerpt(doc: &str, flags: &[String]) -> String {
+    let lines = doc.lines().collect::<Vec<_>>();
+    let mut out = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .take(HEADER_LINES)
+        .map(|line| format!("{line}\n"))
+        .collect::<String>();
+
+    let mut documented = Vec::new();
+    for flag in flags {
+        let mut found = flag_paragraph(&lines, flag);
+        // Combined short flags, e.g. -xzvf
+        if found.is_none() && !flag.starts_with("--") && flag.len() > 2 {
+            let paragraphs = flag[1..]
+                .chars()
+                .filter_map(|c| flag_paragraph(&lines, &format!("-{c}")))
+                .collect::<Vec<_>>();
+            found = (!paragraphs.is_empty()).then(|| paragraphs.join(""));
+        }
+        if let Some(paragraph) = found {
+            if !documented.contains(&paragraph) {
+                documented.push(paragraph);
+            }
+        }
+    }
+    for paragraph in documented {
+        if out.len() + paragraph.len() > MAX_DOC_BYTES {
+            break;
+        }
+        out.push_str("...\n");
+        out.push_str(&paragraph);
+    }
+    out
+}
+
+/// Returns the line that starts documenting `flag`, and the lines after it that are indented
+/// further.
+fn flag_paragraph(lines: &[&str], flag: &str) -> Option<String> {
+    let start = lines.iter().position(|line| {
+        line.trim_start().strip_prefix(flag).is_some_and(|rest| {
+            rest.chars()
+                .next()
+                .is_none_or(|c| !c.is_ascii_alphanumeric() && c != '-')
+        })
+    })?;
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let flag_indent = indent(lines[start]);
+    let mut paragraph = format!("{}\n", lines[start]);
+    for line in lines[start + 1..].iter().take(FLAG_LINES - 1) {
+        if !line.trim().is_empty() && indent(line) <= flag_indent {
+            break;
+        }
+        paragraph.push_str(line);
+        paragraph.push('\n');
+    }
+    Some(paragraph)
+}
+
+fn build_prompt(command: &str, excerpts: &[(&str, String)]) -> String {
+    let mut prompt = format!(
+        "Explain this shell command:\n```\n{command}\n```\n\n\
+        Go through it token by token, one line each, in the form `token`: meaning. Group combined short flags \
+        like -xzvf by letter. Base the meaning of each flag on the documentation excerpts below, which come \
+        from this machine. When a flag isn't covered by them, say so instead of guessing. Finish with one \
+        sentence on what the whole command does.\n"
+    );
+    for (source, excerpt) in excerpts {
+        prompt.push_str(&format!(
+            "\n<documentation source=\"{source}\">\n{excerpt}</documentation>\n"
+        ));
+    }
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TAR_HELP: &str = "\
+Usage: tar [OPTION...] [FILE]...
+GNU 'tar' saves many files together into a single tape or disk archive.
+
+ Main operation mode:
+  -x, --extract, --get       extract files from an archive
+  -t, --list                 list the contents of an archive
+
+ Compression options:
+  -z, --gzip, --gunzip, --ungzip   filter the archive through gzip
+  -f, --file=ARCHIVE         use archive file or device ARCHIVE
+  -v, --verbose              verbosely list files processed
+";
+
+    fn strings(tokens: &[&str]) -> Vec<String> {
+        tokens.iter().map(|token| token.to_string()).collect()
+    }
+
+    #[test]
+    fn test_segments_and_program() {
+        let segments = segments("LC_ALL=C sudo tar -xzvf foo.tgz | git log --oneline; ./build.sh").unwrap();
+        assert_eq!(segments.len(), 3);
+        assert_eq!(program(&segments[0]), Some(("tar".to_string(), None)));
+        assert_eq!(
+            program(&segments[1]),
+            Some(("git".to_string(), Some("log".to_string())))
+        );
+        assert_eq!(program(&segments[2]), None);
+        assert_eq!(segments("echo 'unbalanced"), None);
+    }
+
+    #[test]
+    fn test_flags() {
+        assert_eq!(
+            flags(&strings(&["tar", "-xzvf", "--file=foo.tgz", "-", "--", "bar"])),
+            strings(&["-xzvf", "--file"])
+        );
+    }
+
+    #[test]
+    fn test_strip_overstrike() {
+        assert_eq!(
+            strip_overstrike("N\u{8}NA\u{8}AM\u{8}ME\u{8}E _\u{8}t_\u{8}a_\u{8}r"),
+            "NAME tar"
+        );
+    }
+
+    #[test]
+    fn test_excerpt() {
+        let excerpt = excerpt(TAR_HELP, &strings(&["-xzvf", "--verbose", "-q"]));
+        assert!(excerpt.starts_with("Usage: tar"));
+        assert!(excerpt.contains("extract files from an archive"));
+        assert!(excerpt.contains("filter the archive through gzip"));
+        assert!(excerpt.contains("use archive file"));
+        assert_eq!(
+            excerpt.matches("verbosely list").count(),
+            2,
+            "once in the header, once as a flag"
+        );
+        assert_eq!(excerpt.matches("...\n").count(), 1, "-q is not documented");
+    }
+
+    #[test]
+    fn test_flag_paragraph() {
+        let man = "OPTIONS\n     -name pattern\n             True if the last component matches.\n\n             More detail.\n     -newer file\n             True if newer.\n";
+        let lines = man.lines().collect::<Vec<_>>();
+        assert_eq!(
+            flag_paragraph(&lines, "-name").unwrap(),
+            "     -name pattern\n             True if the last component matches.\n\n             More detail.\n"
+        );
+        assert_eq!(flag_paragraph(&lines, "-n"), None);
+    }
+}
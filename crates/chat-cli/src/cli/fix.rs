@@ -0,0 +1,148 @@
+use std::io::{
+    IsTerminal,
+    Write,
+};
+use std::process::ExitCode;
+
+use clap::Args;
+use crossterm::{
+    execute,
+    style,
+};
+use dialoguer::Confirm;
+use eyre::{
+    Result,
+    bail,
+};
+
+use super::chat::shell_activity::{
+    FailedCommand,
+    last_failed_command,
+};
+use super::suggest_command::extract_command;
+use crate::os::Os;
+use crate::theme::StyledText;
+use crate::util::dialoguer_theme;
+
+/// Proposes a corrected version of the last command, which failed, and prints it to stdout once
+/// accepted so shell keybindings can insert it into the command line.
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct FixArgs {
+    /// Print the corrected command without asking
+    #[arg(long, short)]
+    pub yes: bool,
+    /// Shell to write the command for, defaults to $SHELL
+    #[arg(long)]
+    pub shell: Option<String>,
+}
+
+impl FixArgs {
+    pub async fn execute(self, os: &Os) -> Result<ExitCode> {
+        let failed = last_failed_command(os).await?;
+        let shell = self
+            .shell
+            .or_else(|| os.env.get("SHELL").ok())
+            .and_then(|shell| shell.rsplit('/').next().map(str::to_string))
+            .unwrap_or_else(|| "sh".to_string());
+
+        let mut stderr = std::io::stderr();
+        execute!(
+            stderr,
+            StyledText::secondary_fg(),
+            style::Print(format!(
+                "Fixing `{}` (exit code {})…\n",
+                failed.command, failed.exit_code
+            )),
+            StyledText::reset(),
+        )?;
+        let command = generate_fix(os, &failed, &shell).await?;
+        if command == failed.command.trim() {
+            bail!("No fix was found for `{}`", failed.command);
+        }
+
+        if !self.yes && stderr.is_terminal() {
+            // A single key accepts: Enter or y, while n, Esc or Ctrl‑C cancel.
+            let accepted = Confirm::with_theme(&dialoguer_theme())
+                .with_prompt(format!("Use `{command}`?"))
+                .default(true)
+                .wait_for_newline(false)
+                .interact_opt();
+            match accepted {
+                Ok(Some(true)) => {},
+                Ok(_) => return Ok(ExitCode::FAILURE),
+                Err(dialoguer::Error::IO(ref e)) if e.kind() == std::io::ErrorKind::Interrupted => {
+                    return Ok(ExitCode::FAILURE);
+                },
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let mut stdout = std::io::stdout();
+        writeln!(stdout, "{command}")?;
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+fn build_prompt(failed: &FailedCommand, shell: &str) -> String {
+    let mut prompt = format!("This {shell} command failed with exit code {}", failed.exit_code);
+    if let Some(cwd) = &failed.cwd {
+        prompt.push_str(&format!(" in {cwd}"));
+    }
+    prompt.push_str(&format!(":\n{}\n", failed.command));
+    if let Some(output) = &failed.output {
+        prompt.push_str(&format!("\nThe output in the terminal:\n```\n{output}\n```\n"));
+    }
+    prompt.push_str(&format!(
+        "\nWrite the corrected command for {}, fixing typos, wrong flags or missing arguments. Keep what the \
+        user meant to do. Reply with the command only, without explanation or markdown. If the command is \
+        right and failed for another reason, reply with it unchanged.",
+        std::env::consts::OS
+    ));
+    prompt
+}
+
+async fn generate_fix(os: &Os, failed: &FailedCommand, shell: &str) -> Result<String> {
+    let text = os
+        .client
+        .send_prompt(build_prompt(failed, shell), None)
+        .await?
+        .text()
+        .await?;
+    let command = extract_command(&text);
+    if command.is_empty() {
+        bail!("No command was generated");
+    }
+    Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failed() -> FailedCommand {
+        FailedCommand {
+            command: "git psuh origin main".to_string(),
+            exit_code: 1,
+            cwd: Some("/repo".to_string()),
+            output: Some("git: 'psuh' is not a git command.".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_build_prompt() {
+        let prompt = build_prompt(&failed(), "zsh");
+        assert!(prompt.starts_with("This zsh command failed with exit code 1 in /repo:\ngit psuh origin main\n"));
+        assert!(prompt.contains("```\ngit: 'psuh' is not a git command.\n```"));
+    }
+
+    #[tokio::test]
+    async fn test_generate_fix() {
+        let mut os = Os::new().await.unwrap();
+        os.client
+            .set_mock_output(serde_json::json!([["$ git push", " origin main\n"]]));
+        assert_eq!(
+            generate_fix(&os, &failed(), "bash").await.unwrap(),
+            "git push origin main"
+        );
+    }
+}
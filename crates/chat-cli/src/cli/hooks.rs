@@ -0,0 +1,254 @@
+use std::io::Write;
+use std::process::ExitCode;
+
+use clap::Subcommand;
+use crossterm::{
+    queue,
+    style,
+};
+use eyre::{
+    Result,
+    bail,
+};
+use serde_json::json;
+
+use super::agent::context_templates::ContextTemplates;
+use super::agent::hook::{
+    Hook,
+    HookTrigger,
+};
+use super::agent::resolve_env;
+use crate::cli::Agent;
+use crate::cli::chat::cli::hooks::{
+    HookExecutor,
+    ToolContext,
+    format_hook_context,
+    hook_input,
+    hook_matches_tool,
+    pre_tool_use_block_message,
+};
+use crate::os::Os;
+use crate::theme::StyledText;
+
+const TRIGGERS: [HookTrigger; 5] = [
+    HookTrigger::AgentSpawn,
+    HookTrigger::UserPromptSubmit,
+    HookTrigger::PreToolUse,
+    HookTrigger::PostToolUse,
+    HookTrigger::Stop,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum HooksSubcommand {
+    /// Run the hooks of an agent with a made up payload, and show what each hook received, what it
+    /// printed and how the agent would act on it
+    Test {
+        /// Name of the agent
+        agent: String,
+        /// Only run the hooks of this trigger, e.g. preToolUse
+        #[arg(long, value_parser = parse_trigger)]
+        trigger: Option<HookTrigger>,
+        /// Name of the tool passed to preToolUse and postToolUse hooks, e.g. fs_write or
+        /// @git/git_status. Those hooks are skipped without it
+        #[arg(long)]
+        tool: Option<String>,
+        /// JSON input of the tool
+        #[arg(long, default_value = "{}")]
+        tool_input: String,
+        /// Prompt passed to agentSpawn and userPromptSubmit hooks
+        #[arg(long, default_value = "Hello")]
+        prompt: String,
+    },
+}
+
+fn parse_trigger(value: &str) -> Result<HookTrigger, String> {
+    serde_json::from_value(json!(value)).map_err(|_| {
+        let names = TRIGGERS.map(|t| t.to_string());
+        format!("expected one of {}", names.join(", "))
+    })
+}
+
+impl HooksSubcommand {
+    pub async fn execute(self, os: &Os) -> Result<ExitCode> {
+        let Self::Test {
+            agent,
+            trigger,
+            tool,
+            tool_input,
+            prompt,
+        } = self;
+        let mut stderr = std::io::stderr();
+        let tool_input = match serde_json::from_str::<serde_json::Value>(&tool_input) {
+            Ok(tool_input) => tool_input,
+            Err(e) => bail!("--tool-input is not valid JSON: {e}"),
+        };
+        let (agent, path) = Agent::get_agent_by_name(os, &agent).await?;
+
+        let executor = HookExecutor {
+            env: resolve_env(&agent.env, os),
+            ..Default::default()
+        };
+        let cwd = os.env.current_dir()?.to_string_lossy().to_string();
+
+        let mut ran = 0;
+        let mut failed = 0;
+        for trigger in TRIGGERS.into_iter().filter(|t| trigger.is_none_or(|only| only == *t)) {
+            for hook in agent.hooks.get(&trigger).into_iter().flatten() {
+                queue!(
+                    stderr,
+                    StyledText::brand_fg(),
+                    style::Print(format!("▸ {trigger} ")),
+                    StyledText::reset(),
+                    style::Print(&hook.command),
+                    StyledText::secondary_fg(),
+                    style::Print(match &hook.matcher {
+                        Some(matcher) => format!(" (matcher: {matcher})\n"),
+                        None => "\n".to_string(),
+                    }),
+                    StyledText::reset(),
+                )?;
+
+                let is_tool_hook = matches!(trigger, HookTrigger::PreToolUse | HookTrigger::PostToolUse);
+                let tool_context = match (&tool, is_tool_hook) {
+                    (None, true) => {
+                        print_field(&mut stderr, "skipped", "pass --tool to run tool hooks")?;
+                        continue;
+                    },
+                    (Some(tool), true) => {
+                        if !hook_matches_tool(hook, tool) {
+                            print_field(&mut stderr, "skipped", &format!("the matcher doesn't match {tool}"))?;
+                            continue;
+                        }
+                        Some(ToolContext {
+                            tool_name: tool.clone(),
+                            tool_input: tool_input.clone(),
+                            tool_response: (trigger == HookTrigger::PostToolUse)
+                                .then(|| json!({ "success": true, "result": [] })),
+                        })
+                    },
+                    (_, false) => None,
+                };
+                let hook_prompt = matches!(trigger, HookTrigger::AgentSpawn | HookTrigger::UserPromptSubmit)
+                    .then_some(prompt.as_str());
+
+                let stdin = hook_input(trigger, &cwd, hook_prompt, tool_context.clone());
+                print_field(&mut stderr, "stdin", &serde_json::to_string_pretty(&stdin)?)?;
+
+                ran += 1;
+                let (_, result, duration) = executor
+                    .run_hook((trigger, hook.clone()), &cwd, hook_prompt, tool_context)
+                    .await;
+                match result {
+                    Ok((exit_code, output)) => {
+                        print_field(
+                            &mut stderr,
+                            "exit code",
+                            &format!("{exit_code} after {:.2} s", duration.as_secs_f32()),
+                        )?;
+                        print_field(
+                            &mut stderr,
+                            if exit_code == 0 { "stdout" } else { "stderr" },
+                            output.trim_end(),
+                        )?;
+                        print_field(
+                            &mut stderr,
+                            "result",
+                            &interpret(trigger, hook, exit_code, &output, &agent.context_templates),
+                        )?;
+                    },
+                    Err(err) => {
+                        failed += 1;
+                        print_field(&mut stderr, "error", &err.to_string())?;
+                        print_field(&mut stderr, "result", "Shown as an error, the hook is ignored")?;
+                    },
+                }
+                writeln!(stderr)?;
+            }
+        }
+
+        if ran == 0 {
+            writeln!(stderr, "No hooks to run in {}", path.display())?;
+        }
+        stderr.flush()?;
+
+        Ok(match failed {
+            0 => ExitCode::SUCCESS,
+            _ => ExitCode::FAILURE,
+        })
+    }
+}
+
+fn print_field(output: &mut impl Write, name: &str, value: &str) -> Result<()> {
+    queue!(
+        output,
+        StyledText::secondary_fg(),
+        style::Print(format!("  {name}:")),
+        StyledText::reset(),
+    )?;
+    match value.lines().count() {
+        0 => writeln!(output, " (empty)")?,
+        1 => writeln!(output, " {value}")?,
+        _ => {
+            writeln!(output)?;
+            for line in value.lines() {
+                writeln!(output, "    {line}")?;
+            }
+        },
+    }
+    Ok(())
+}
+
+/// Describes what the agent does with the result of a hook, using the same handling as the chat
+/// session for the context and blocking hooks.
+fn interpret(trigger: HookTrigger, hook: &Hook, exit_code: i32, output: &str, templates: &ContextTemplates) -> String {
+    match trigger {
+        HookTrigger::AgentSpawn | HookTrigger::UserPromptSubmit => {
+            let results = [((trigger, hook.clone()), (exit_code, output.to_string()))];
+            match format_hook_context(&results, trigger, templates) {
+                Some(context) if trigger == HookTrigger::AgentSpawn => {
+                    format!("Added to the context of the conversation:\n{context}")
+                },
+                Some(context) => format!("Added to the context of the prompt:\n{context}"),
+                None if exit_code != 0 => "Shown as an error, nothing is added to the context".to_string(),
+                None => "Nothing is added to the context, the hook printed nothing".to_string(),
+            }
+        },
+        HookTrigger::PreToolUse => match pre_tool_use_block_message(exit_code, output) {
+            Some(message) => format!("The tool is blocked and the model receives:\n{message}"),
+            None if exit_code == 0 => "The tool runs, the output is not shown".to_string(),
+            None => "Shown as an error, the tool still runs".to_string(),
+        },
+        HookTrigger::PostToolUse | HookTrigger::Stop if exit_code == 0 => "Nothing, the output is not used".to_string(),
+        HookTrigger::PostToolUse | HookTrigger::Stop => "Shown as an error".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_trigger() {
+        assert_eq!(parse_trigger("preToolUse"), Ok(HookTrigger::PreToolUse));
+        assert!(parse_trigger("pre_tool_use").unwrap_err().contains("agentSpawn"));
+    }
+
+    #[test]
+    fn test_interpret() {
+        let templates = ContextTemplates::default();
+        let hook = Hook::new("git branch --show-current".to_string(), Default::default());
+
+        // What the session adds to the context and sends to the model is shown verbatim
+        let results = [((HookTrigger::AgentSpawn, hook.clone()), (0, "branch: main".to_string()))];
+        let context = format_hook_context(&results, HookTrigger::AgentSpawn, &templates).unwrap();
+        assert!(interpret(HookTrigger::AgentSpawn, &hook, 0, "branch: main", &templates).ends_with(&context));
+        let blocked = pre_tool_use_block_message(2, "no writes to main").unwrap();
+        assert!(interpret(HookTrigger::PreToolUse, &hook, 2, "no writes to main", &templates).ends_with(&blocked));
+
+        assert!(interpret(HookTrigger::PreToolUse, &hook, 1, "", &templates).contains("still runs"));
+        assert!(interpret(HookTrigger::UserPromptSubmit, &hook, 0, "", &templates).starts_with("Nothing"));
+        assert!(
+            interpret(HookTrigger::UserPromptSubmit, &hook, 1, "oops", &templates).starts_with("Shown as an error")
+        );
+    }
+}
@@ -0,0 +1,162 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{
+    Args,
+    ValueEnum,
+};
+use crossterm::style::Stylize;
+use eyre::{
+    Result,
+    bail,
+};
+
+use crate::cli::chat::ChatArgs;
+use crate::os::Os;
+use crate::util::paths::PathResolver;
+
+/// Project templates the scaffolding agent knows how to generate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Template {
+    /// A Rust command line tool built with clap
+    RustCli,
+    /// An AWS Lambda function in Python deployed with AWS SAM
+    Lambda,
+    /// An AWS CDK app in TypeScript
+    CdkApp,
+}
+
+impl Template {
+    /// Description of the project, read from `init_templates/`
+    fn instructions(&self) -> &'static str {
+        match self {
+            Template::RustCli => include_str!("init_templates/rust_cli.md"),
+            Template::Lambda => include_str!("init_templates/lambda.md"),
+            Template::CdkApp => include_str!("init_templates/cdk_app.md"),
+        }
+        .trim()
+    }
+}
+
+/// Starts a chat session with an agent that scaffolds a new project from a template. The agent
+/// proposes a plan before writing anything, and every file it writes is previewed for approval.
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct InitArgs {
+    /// Template to generate the project from
+    #[arg(value_enum)]
+    pub template: Template,
+    /// Directory to create the project in, the current directory by default
+    pub directory: Option<PathBuf>,
+    /// Name of the project, the name of the directory by default
+    #[arg(long)]
+    pub name: Option<String>,
+    /// Model to use
+    #[arg(long)]
+    pub model: Option<String>,
+}
+
+impl InitArgs {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        let mut stderr = std::io::stderr();
+
+        if let Some(directory) = &self.directory {
+            os.fs.create_dir_all(directory).await?;
+            os.env.set_current_dir(directory)?;
+        }
+        let cwd = os.env.current_dir()?;
+        let name = match self.name {
+            Some(name) => name,
+            None => match cwd.file_name() {
+                Some(name) => name.to_string_lossy().into_owned(),
+                None => bail!("Choose a name for the project with --name"),
+            },
+        };
+
+        let mut entries = os.fs.read_dir(&cwd).await?;
+        let mut is_empty = true;
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_name() != ".git" {
+                is_empty = false;
+                break;
+            }
+        }
+        if !is_empty {
+            writeln!(
+                stderr,
+                "{} {} is not empty, the plan will account for the files already in it\n",
+                "Note:".yellow(),
+                cwd.display()
+            )?;
+        }
+
+        // The conversation is saved for the directory after every response, so the one there before
+        // the session tells whether the session got as far as a response.
+        let previous_id = os
+            .database
+            .get_conversation_by_path(&cwd)
+            .ok()
+            .flatten()
+            .map(|conversation| conversation.conversation_id().to_string());
+
+        let exit_code = ChatArgs {
+            model: self.model,
+            input: Some(scaffold_prompt(self.template, &name, is_empty)),
+            // Every tool use is approved, even for tools the default agent trusts
+            trust_tools: Some(Vec::new()),
+            ..Default::default()
+        }
+        .execute(os)
+        .await?;
+
+        let conversation = os.database.get_conversation_by_path(&cwd).ok().flatten();
+        if let Some(conversation) = conversation.filter(|c| Some(c.conversation_id()) != previous_id.as_deref()) {
+            let path = PathResolver::new(os).workspace().init_conversation()?;
+            if let Some(parent) = path.parent() {
+                os.fs.create_dir_all(parent).await?;
+            }
+            os.fs.write(&path, serde_json::to_string_pretty(&conversation)?).await?;
+            writeln!(
+                stderr,
+                "\nSaved the conversation that generated the project to {}",
+                path.display()
+            )?;
+        }
+
+        Ok(exit_code)
+    }
+}
+
+fn scaffold_prompt(template: Template, name: &str, is_empty: bool) -> String {
+    let directory = if is_empty {
+        "The current directory is empty."
+    } else {
+        "The current directory already has files in it. Don't overwrite any of them unless I ask you to."
+    };
+    format!(
+        "Scaffold a new project named {name} in the current directory.\n\n\
+        The project should be: {}\n\n\
+        {directory}\n\n\
+        Work in two phases. First, without writing anything, reply with a plan that lists every file you \
+        will create with a one line summary of each, and any commands you will run. Then stop and wait \
+        for me to approve or change the plan. Only after I approve, write the files one at a time and \
+        run the commands. Finish by telling me how to build and run the project.",
+        template.instructions()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scaffold_prompt() {
+        let prompt = scaffold_prompt(Template::RustCli, "greeter", true);
+        assert!(prompt.contains("named greeter"));
+        assert!(prompt.contains(Template::RustCli.instructions()));
+        assert!(prompt.contains("wait for me to approve"));
+
+        let prompt = scaffold_prompt(Template::CdkApp, "infra", false);
+        assert!(prompt.contains("Don't overwrite"));
+    }
+}
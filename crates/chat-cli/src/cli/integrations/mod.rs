@@ -0,0 +1,161 @@
+pub mod shell;
+
+use std::io::Write;
+use std::process::ExitCode;
+
+use clap::{
+    Subcommand,
+    ValueEnum,
+};
+use eyre::Result;
+
+use crate::os::Os;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Integration {
+    /// Start the daemon at login with launchd or systemd
+    Daemon,
+    /// Activity log, error hints, command suggestions and completions in the shell configuration
+    Shell,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum IntegrationsSubcommand {
+    /// Install an integration, or update it if it is already installed
+    Install {
+        integration: Integration,
+        /// Shell to install the shell integration for, defaults to $SHELL
+        #[arg(long, value_enum)]
+        shell: Option<shell::Shell>,
+    },
+    /// Uninstall an integration
+    Uninstall {
+        integration: Integration,
+        /// Shell to uninstall the shell integration from, defaults to $SHELL
+        #[arg(long, value_enum)]
+        shell: Option<shell::Shell>,
+    },
+    /// Show which integrations are installed
+    Status,
+}
+
+impl IntegrationsSubcommand {
+    #[cfg(unix)]
+    pub async fn execute(self, os: &Os) -> Result<ExitCode> {
+        use crossterm::style::Stylize;
+
+        use self::shell::{
+            Change,
+            Shell,
+            Status,
+        };
+        use super::daemon::service::ServiceManager;
+        use crate::theme::StyledText;
+        use crate::util::CLI_BINARY_NAME;
+
+        let mut stderr = std::io::stderr();
+        let service_manager = || match ServiceManager::current() {
+            Some(service) => Ok(service),
+            None => Err(eyre::eyre!(
+                "Service managers other than launchd and systemd are not supported"
+            )),
+        };
+        let current_shell = |shell: Option<Shell>| match shell.or_else(|| Shell::current(os)) {
+            Some(shell) => Ok(shell),
+            None => Err(eyre::eyre!(
+                "Choose a shell with --shell, $SHELL is not zsh, bash or fish"
+            )),
+        };
+
+        match self {
+            Self::Install {
+                integration: Integration::Daemon,
+                ..
+            } => {
+                let service = service_manager()?;
+                // The daemon started by the service manager can't listen while one started on
+                // demand is running.
+                super::daemon::stop().await?;
+                let path = service.install(os).await?;
+                writeln!(stderr, "Installed the daemon with {service} at {}", path.display())?;
+            },
+            Self::Install {
+                integration: Integration::Shell,
+                shell,
+            } => {
+                let shell = current_shell(shell)?;
+                let path = shell.config_path(os)?;
+                match shell::install(os, shell).await? {
+                    Change::Installed => writeln!(
+                        stderr,
+                        "Installed the {shell} integration in {}, open a new shell to use it",
+                        path.display()
+                    )?,
+                    Change::Updated => writeln!(
+                        stderr,
+                        "Updated the {shell} integration in {}, open a new shell to use it",
+                        path.display()
+                    )?,
+                    Change::Unchanged => {
+                        writeln!(stderr, "The {shell} integration in {} is up to date", path.display())?;
+                    },
+                }
+            },
+            Self::Uninstall {
+                integration: Integration::Daemon,
+                ..
+            } => {
+                let service = service_manager()?;
+                match service.uninstall(os).await? {
+                    true => writeln!(stderr, "Uninstalled the daemon from {service}")?,
+                    false => writeln!(stderr, "The daemon isn't installed with {service}")?,
+                }
+            },
+            Self::Uninstall {
+                integration: Integration::Shell,
+                shell,
+            } => {
+                let shell = current_shell(shell)?;
+                let path = shell.config_path(os)?;
+                match shell::uninstall(os, shell).await? {
+                    true => writeln!(stderr, "Removed the {shell} integration from {}", path.display())?,
+                    false => writeln!(stderr, "The {shell} integration isn't in {}", path.display())?,
+                }
+            },
+            Self::Status => {
+                let status = match ServiceManager::current() {
+                    Some(service) if service.is_installed(os) => format!("installed with {service}"),
+                    _ => "not installed".to_string(),
+                };
+                writeln!(stderr, "daemon: {status}")?;
+                for shell in Shell::value_variants() {
+                    let path = shell.config_path(os)?;
+                    let status = match shell::status(os, *shell).await? {
+                        Status::NotInstalled => "not installed".to_string(),
+                        Status::Installed => format!("installed in {}", path.display()),
+                        Status::Outdated => format!(
+                            "{} in {}, update it with {}",
+                            "outdated".yellow(),
+                            path.display(),
+                            StyledText::command(&format!(
+                                "{CLI_BINARY_NAME} integrations install shell --shell {shell}"
+                            ))
+                        ),
+                        Status::Modified => format!(
+                            "{} in {}, reinstall it to undo the changes",
+                            "modified".yellow(),
+                            path.display()
+                        ),
+                    };
+                    writeln!(stderr, "shell ({shell}): {status}")?;
+                }
+            },
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+
+    #[cfg(not(unix))]
+    pub async fn execute(self, _os: &Os) -> Result<ExitCode> {
+        eyre::bail!("Integrations are not supported on this platform")
+    }
+}
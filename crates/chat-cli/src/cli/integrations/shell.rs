@@ -0,0 +1,365 @@
+//! Installs the shell snippets from the docs (activity log, error hints, command suggestions and
+//! completions) into the shell's configuration file, between markers so they can be updated and
+//! removed without touching the rest of the file.
+
+use std::fmt::Display;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use eyre::{
+    Result,
+    bail,
+};
+
+use crate::os::Os;
+use crate::util::paths::{
+    PathResolver,
+    home_dir,
+};
+
+/// Version of the snippets, bumped whenever they change so `status` can tell outdated blocks from
+/// edited ones.
+const VERSION: u32 = 1;
+
+const BEGIN_PREFIX: &str = "# >>> q shell integration";
+const END_MARKER: &str = "# <<< q shell integration <<<";
+
+const ZSH_SNIPPET: &str = r#"__q_log_command() {
+  local exit_code=$?
+  printf '%s\t%s\t%s\t%s\n' "$(date +%s)" "$exit_code" "$PWD" "$(fc -ln -1)" >> ~/.aws/amazonq/shell_activity.log
+}
+precmd_functions+=(__q_log_command)
+
+__q_error_hint() {
+  local exit_code=$(tail -n 1 ~/.aws/amazonq/shell_activity.log 2>/dev/null | cut -f 2)
+  [[ -n $exit_code && $exit_code != 0 ]] && print -P "%F{8}Press Ctrl+G to ask Q about this error%f"
+}
+precmd_functions+=(__q_error_hint)
+
+__q_explain_error() {
+  zle -I
+  q chat --explain-error </dev/tty
+  zle reset-prompt
+}
+zle -N __q_explain_error
+bindkey '^G' __q_explain_error
+
+__q_suggest_command() {
+  zle -I
+  local command
+  command=$(q suggest-command </dev/tty) && LBUFFER+=$command
+  zle reset-prompt
+}
+zle -N __q_suggest_command
+bindkey '^Xq' __q_suggest_command
+
+if (( $+functions[compdef] )); then
+  _q_complete() {
+    local -a candidates
+    candidates=("${(@f)$(q daemon complete --cursor ${#LBUFFER} -- "$BUFFER" 2>/dev/null)}")
+    (( ${#candidates[@]} )) && [[ -n $candidates[1] ]] && compadd -Q -- "${candidates[@]}"
+  }
+  compdef _q_complete -default-
+fi
+"#;
+
+const BASH_SNIPPET: &str = r#"if [[ $- == *i* ]]; then
+  __q_log_command() {
+    local exit_code=$?
+    printf '%s\t%s\t%s\t%s\n' "$(date +%s)" "$exit_code" "$PWD" "$(HISTTIMEFORMAT= history 1 | sed 's/^ *[0-9]* *//')" >> ~/.aws/amazonq/shell_activity.log
+  }
+  PROMPT_COMMAND="__q_log_command${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+
+  __q_error_hint() {
+    local exit_code=$(tail -n 1 ~/.aws/amazonq/shell_activity.log 2>/dev/null | cut -f 2)
+    [[ -n $exit_code && $exit_code != 0 ]] && printf '\e[90mPress Ctrl+G to ask Q about this error\e[0m\n'
+  }
+  PROMPT_COMMAND="${PROMPT_COMMAND:+$PROMPT_COMMAND;}__q_error_hint"
+  bind -x '"\C-g": q chat --explain-error'
+
+  __q_suggest_command() {
+    local command
+    command=$(q suggest-command </dev/tty) || return
+    READLINE_LINE="${READLINE_LINE:0:READLINE_POINT}$command${READLINE_LINE:READLINE_POINT}"
+    READLINE_POINT=$((READLINE_POINT + ${#command}))
+  }
+  bind -x '"\C-xq": __q_suggest_command'
+
+  _q_complete() {
+    mapfile -t COMPREPLY < <(q daemon complete -- "${COMP_LINE:0:COMP_POINT}" 2>/dev/null)
+  }
+  complete -D -o default -F _q_complete
+fi
+"#;
+
+const FISH_SNIPPET: &str = r#"if status is-interactive
+    function __q_log_command --on-event fish_postexec
+        printf '%s\t%s\t%s\t%s\n' (date +%s) $status $PWD (string join ' ' $argv) >> ~/.aws/amazonq/shell_activity.log
+    end
+
+    function __q_error_hint --on-event fish_postexec
+        test $status -ne 0; and set_color brblack; and echo "Press Ctrl+G to ask Q about this error"; and set_color normal
+    end
+    bind \cg 'q chat --explain-error; commandline -f repaint'
+
+    function __q_suggest_command
+        set -l command (q suggest-command </dev/tty | string collect); or return
+        commandline -i -- $command
+        commandline -f repaint
+    end
+    bind \cxq __q_suggest_command
+end
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Zsh,
+    Bash,
+    Fish,
+}
+
+impl Shell {
+    /// The shell in `$SHELL`, if it is supported.
+    pub fn current(os: &Os) -> Option<Self> {
+        let shell = os.env.get("SHELL").ok()?;
+        match shell.rsplit('/').next()? {
+            "zsh" => Some(Self::Zsh),
+            "bash" => Some(Self::Bash),
+            "fish" => Some(Self::Fish),
+            _ => None,
+        }
+    }
+
+    /// Configuration file read by interactive shells.
+    pub fn config_path(&self, os: &Os) -> Result<PathBuf> {
+        Ok(match self {
+            Self::Zsh => match os.env.get("ZDOTDIR") {
+                Ok(dir) if !dir.is_empty() => PathBuf::from(dir).join(".zshrc"),
+                _ => home_dir(os)?.join(".zshrc"),
+            },
+            Self::Bash => home_dir(os)?.join(".bashrc"),
+            Self::Fish => {
+                let config_dir = match os.env.get("XDG_CONFIG_HOME") {
+                    Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+                    _ => home_dir(os)?.join(".config"),
+                };
+                config_dir.join("fish/config.fish")
+            },
+        })
+    }
+
+    /// The snippets between their markers.
+    pub fn block(&self) -> String {
+        let snippet = match self {
+            Self::Zsh => ZSH_SNIPPET,
+            Self::Bash => BASH_SNIPPET,
+            Self::Fish => FISH_SNIPPET,
+        };
+        format!(
+            "{BEGIN_PREFIX} v{VERSION} >>>\n\
+            # Managed by `q integrations install shell`, changes here are lost when it is updated.\n\
+            {snippet}{END_MARKER}\n"
+        )
+    }
+}
+
+impl Display for Shell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Zsh => "zsh",
+            Self::Bash => "bash",
+            Self::Fish => "fish",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    NotInstalled,
+    Installed,
+    /// Installed by an older version of the CLI
+    Outdated,
+    /// Installed by this version of the CLI and edited since
+    Modified,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Installed,
+    Updated,
+    Unchanged,
+}
+
+pub async fn status(os: &Os, shell: Shell) -> Result<Status> {
+    let path = shell.config_path(os)?;
+    if !os.fs.exists(&path) {
+        return Ok(Status::NotInstalled);
+    }
+    Ok(status_of(&os.fs.read_to_string(&path).await?, shell))
+}
+
+/// Adds the integration to the shell's configuration file, or replaces the one already there.
+pub async fn install(os: &Os, shell: Shell) -> Result<Change> {
+    let path = shell.config_path(os)?;
+    let contents = match os.fs.exists(&path) {
+        true => os.fs.read_to_string(&path).await?,
+        false => String::new(),
+    };
+    if find_block(&contents).is_none() && contents.contains(BEGIN_PREFIX) {
+        bail!(
+            "{} has the start of the shell integration but not its end, remove it and try again",
+            path.display()
+        );
+    }
+
+    let change = match status_of(&contents, shell) {
+        Status::Installed => return Ok(Change::Unchanged),
+        Status::NotInstalled => Change::Installed,
+        Status::Outdated | Status::Modified => Change::Updated,
+    };
+    // The hooks append to the activity log, which fails every prompt if its directory is missing.
+    if let Some(parent) = PathResolver::new(os).global().shell_activity_log()?.parent() {
+        os.fs.create_dir_all(parent).await?;
+    }
+    if let Some(parent) = path.parent() {
+        os.fs.create_dir_all(parent).await?;
+    }
+    os.fs.write(&path, with_block(&contents, &shell.block())).await?;
+    Ok(change)
+}
+
+/// Removes the integration from the shell's configuration file, returning whether it was there.
+pub async fn uninstall(os: &Os, shell: Shell) -> Result<bool> {
+    let path = shell.config_path(os)?;
+    if !os.fs.exists(&path) {
+        return Ok(false);
+    }
+    match without_block(&os.fs.read_to_string(&path).await?) {
+        Some(contents) => {
+            os.fs.write(&path, contents).await?;
+            Ok(true)
+        },
+        None => Ok(false),
+    }
+}
+
+/// Byte range of the integration in `contents` including its markers, and the version in its
+/// first marker.
+fn find_block(contents: &str) -> Option<(Range<usize>, Option<u32>)> {
+    let mut offset = 0;
+    let mut begin = None;
+    for line in contents.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        match begin {
+            None => {
+                if let Some(rest) = trimmed.strip_prefix(BEGIN_PREFIX) {
+                    let version = rest
+                        .trim_end_matches(">>>")
+                        .trim()
+                        .strip_prefix('v')
+                        .and_then(|v| v.parse().ok());
+                    begin = Some((offset, version));
+                }
+            },
+            Some((start, version)) if trimmed == END_MARKER => return Some((start..offset + line.len(), version)),
+            Some(_) => {},
+        }
+        offset += line.len();
+    }
+    None
+}
+
+fn status_of(contents: &str, shell: Shell) -> Status {
+    match find_block(contents) {
+        None => Status::NotInstalled,
+        Some((range, _)) if contents[range.clone()].trim_end() == shell.block().trim_end() => Status::Installed,
+        Some((_, Some(version))) if version >= VERSION => Status::Modified,
+        Some(_) => Status::Outdated,
+    }
+}
+
+/// `contents` with `block` in place of the integration, or appended after a blank line.
+fn with_block(contents: &str, block: &str) -> String {
+    let mut result = contents.to_string();
+    match find_block(contents) {
+        Some((range, _)) => result.replace_range(range, block),
+        None => {
+            if !result.is_empty() {
+                if !result.ends_with('\n') {
+                    result.push('\n');
+                }
+                result.push('\n');
+            }
+            result.push_str(block);
+        },
+    }
+    result
+}
+
+/// `contents` without the integration, or [None] if it isn't there.
+fn without_block(contents: &str) -> Option<String> {
+    let (range, _) = find_block(contents)?;
+    let mut before = &contents[..range.start];
+    let after = &contents[range.end..];
+    // Drop the blank line added before an appended block.
+    if after.is_empty() && before.ends_with("\n\n") {
+        before = &before[..before.len() - 1];
+    }
+    Some(format!("{before}{after}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RC: &str = "export EDITOR=vim\nalias ll='ls -l'";
+
+    #[test]
+    fn test_install_is_idempotent() {
+        let installed = with_block(RC, &Shell::Zsh.block());
+        assert!(installed.starts_with("export EDITOR=vim\nalias ll='ls -l'\n\n# >>> q shell integration v1 >>>\n"));
+        assert!(installed.ends_with("# <<< q shell integration <<<\n"));
+        assert_eq!(status_of(&installed, Shell::Zsh), Status::Installed);
+        assert_eq!(with_block(&installed, &Shell::Zsh.block()), installed);
+        assert_eq!(status_of(RC, Shell::Zsh), Status::NotInstalled);
+    }
+
+    #[test]
+    fn test_uninstall_restores_file() {
+        let installed = with_block(RC, &Shell::Bash.block());
+        assert_eq!(without_block(&installed).unwrap(), format!("{RC}\n"));
+        assert_eq!(without_block(RC), None);
+
+        let surrounded = format!("before\n{}after\n", Shell::Fish.block());
+        assert_eq!(without_block(&surrounded).unwrap(), "before\nafter\n");
+    }
+
+    #[test]
+    fn test_outdated_and_modified() {
+        let outdated = format!("{RC}\n# >>> q shell integration v0 >>>\nold\n{END_MARKER}\nexport A=1\n");
+        assert_eq!(status_of(&outdated, Shell::Zsh), Status::Outdated);
+        let updated = with_block(&outdated, &Shell::Zsh.block());
+        assert!(updated.ends_with(&format!("{END_MARKER}\nexport A=1\n")));
+        assert!(!updated.contains("old\n"));
+        assert_eq!(status_of(&updated, Shell::Zsh), Status::Installed);
+
+        let modified = updated.replace("bindkey '^G'", "bindkey '^H'");
+        assert_eq!(status_of(&modified, Shell::Zsh), Status::Modified);
+    }
+
+    #[tokio::test]
+    async fn test_install_and_uninstall() {
+        let os = Os::new().await.unwrap();
+        let path = Shell::Bash.config_path(&os).unwrap();
+        os.fs.create_dir_all(path.parent().unwrap()).await.unwrap();
+        os.fs.write(&path, RC).await.unwrap();
+
+        assert_eq!(install(&os, Shell::Bash).await.unwrap(), Change::Installed);
+        assert_eq!(install(&os, Shell::Bash).await.unwrap(), Change::Unchanged);
+        assert_eq!(status(&os, Shell::Bash).await.unwrap(), Status::Installed);
+        assert!(uninstall(&os, Shell::Bash).await.unwrap());
+        assert!(!uninstall(&os, Shell::Bash).await.unwrap());
+        assert_eq!(os.fs.read_to_string(&path).await.unwrap(), format!("{RC}\n"));
+    }
+}
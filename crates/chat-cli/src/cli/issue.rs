@@ -1,21 +1,58 @@
+use std::path::PathBuf;
 use std::process::ExitCode;
 
+use anstream::eprintln;
 use clap::Args;
 use eyre::Result;
 
+use crate::cli::chat::cli::share::Redactor;
 use crate::os::Os;
+use crate::os::diagnostics::Diagnostics;
+use crate::util::paths::logs_dir;
+
+/// Lines at the end of the chat log written to a bundle on disk
+const BUNDLE_LOG_LINES: usize = 500;
+/// Lines at the end of the chat log added to the issue, short since GitHub limits the length of
+/// the URL it is pre-filled from
+const ISSUE_LOG_LINES: usize = 30;
+
+/// Terminal variables that are not part of the [Diagnostics] environment variables
+const TERMINAL_VARS: &[&str] = &["TERM_PROGRAM", "TERM_PROGRAM_VERSION", "COLORTERM", "TMUX", "ZELLIJ"];
 
 #[derive(Clone, Debug, Args, PartialEq, Eq)]
 pub struct IssueArgs {
-    /// Force issue creation
+    /// Force issue creation, or overwrite the file given with --output
     #[arg(long, short = 'f')]
     force: bool,
+    /// Write the diagnostics bundle to a file instead of opening an issue
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+    /// Leave the recent logs out of the diagnostics
+    #[arg(long)]
+    no_logs: bool,
     /// Issue description
     description: Vec<String>,
 }
 
 impl IssueArgs {
     pub async fn execute(&self, os: &Os) -> Result<ExitCode> {
+        let bundle = Bundle::collect(os, !self.no_logs).await;
+
+        if let Some(path) = &self.output {
+            if os.fs.exists(path) && !self.force {
+                eyre::bail!("{} already exists, use --force to overwrite it", path.display());
+            }
+            let diagnostics = Diagnostics::new(&os.env).await.user_readable()?;
+            os.fs
+                .write(path, format!("{diagnostics}\n{}", bundle.render(BUNDLE_LOG_LINES)))
+                .await?;
+            eprintln!(
+                "Wrote the diagnostics to {}, review them before attaching them to an issue",
+                path.display()
+            );
+            return Ok(ExitCode::SUCCESS);
+        }
+
         let joined_description = self.description.join(" ").trim().to_owned();
 
         let issue_title = match joined_description.len() {
@@ -30,7 +67,7 @@ impl IssueArgs {
             expected_behavior: None,
             actual_behavior: None,
             steps_to_reproduce: None,
-            additional_environment: None,
+            additional_environment: Some(bundle.render(ISSUE_LOG_LINES)),
         }
         .create_url(os)
         .await;
@@ -38,3 +75,137 @@ impl IssueArgs {
         Ok(ExitCode::SUCCESS)
     }
 }
+
+/// Diagnostics gathered for an issue in addition to the [Diagnostics] of `q doctor`.
+#[derive(Debug, Default)]
+struct Bundle {
+    terminal: Vec<(String, String)>,
+    integrations: Vec<(String, String)>,
+    /// The end of the chat log, redacted
+    logs: Vec<String>,
+}
+
+impl Bundle {
+    async fn collect(os: &Os, include_logs: bool) -> Self {
+        let terminal = TERMINAL_VARS
+            .iter()
+            .filter_map(|var| os.env.get(var).ok().map(|value| (var.to_string(), value)))
+            .collect();
+
+        let logs = match include_logs {
+            true => match logs_dir() {
+                Ok(dir) => os
+                    .fs
+                    .read_to_string(dir.join("qchat.log"))
+                    .await
+                    .map(|log| tail(&log, BUNDLE_LOG_LINES, &mut Redactor::from_env(os)))
+                    .unwrap_or_default(),
+                Err(_) => Vec::new(),
+            },
+            false => Vec::new(),
+        };
+
+        Self {
+            terminal,
+            integrations: integrations(os).await,
+            logs,
+        }
+    }
+
+    /// Formats the bundle like [Diagnostics::user_readable], with the last `log_lines` lines of
+    /// the log.
+    fn render(&self, log_lines: usize) -> String {
+        let mut rendered = String::new();
+        for (section, entries) in [("terminal", &self.terminal), ("integrations", &self.integrations)] {
+            if entries.is_empty() {
+                continue;
+            }
+            rendered.push_str(&format!("[{section}]\n"));
+            for (key, value) in entries {
+                rendered.push_str(&format!("{key} = {value:?}\n"));
+            }
+            rendered.push('\n');
+        }
+        if !self.logs.is_empty() {
+            let logs = &self.logs[self.logs.len().saturating_sub(log_lines)..];
+            rendered.push_str(&format!(
+                "[logs]\n```\n{}\n```\n",
+                logs.join("\n").replace("```", r"\```")
+            ));
+        }
+        rendered
+    }
+}
+
+/// The status of the daemon and shell integrations, as shown by `q integrations status`.
+#[cfg(unix)]
+async fn integrations(os: &Os) -> Vec<(String, String)> {
+    use clap::ValueEnum;
+
+    use super::daemon::service::ServiceManager;
+    use super::integrations::shell::{
+        self,
+        Shell,
+        Status,
+    };
+
+    let daemon = match ServiceManager::current() {
+        Some(service) if service.is_installed(os) => format!("installed with {service}"),
+        _ => "not installed".to_string(),
+    };
+    let mut integrations = vec![("daemon".to_string(), daemon)];
+    for shell in Shell::value_variants() {
+        let status = match shell::status(os, *shell).await {
+            Ok(Status::NotInstalled) => "not installed".to_string(),
+            Ok(Status::Installed) => "installed".to_string(),
+            Ok(Status::Outdated) => "outdated".to_string(),
+            Ok(Status::Modified) => "modified".to_string(),
+            Err(err) => format!("error: {err}"),
+        };
+        integrations.push((format!("shell-{shell}"), status));
+    }
+    integrations
+}
+
+#[cfg(not(unix))]
+async fn integrations(_os: &Os) -> Vec<(String, String)> {
+    Vec::new()
+}
+
+/// The last `lines` lines of `log`, redacted.
+fn tail(log: &str, lines: usize, redactor: &mut Redactor) -> Vec<String> {
+    let all = log.lines().collect::<Vec<_>>();
+    all[all.len().saturating_sub(lines)..]
+        .iter()
+        .map(|line| redactor.redact(line))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tail_redacts() {
+        let mut redactor = Redactor::new(Some("/home/alice".to_string()), ["hunter2-hunter2".to_string()]);
+        let log = "first\nreading /home/alice/.aws/config\ntoken hunter2-hunter2 expired\n";
+        assert_eq!(tail(log, 2, &mut redactor), vec![
+            "reading ~/.aws/config".to_string(),
+            "token [REDACTED] expired".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn test_render() {
+        let bundle = Bundle {
+            terminal: vec![("TERM_PROGRAM".to_string(), "iTerm.app".to_string())],
+            integrations: vec![],
+            logs: (1..=5).map(|i| format!("line {i}")).collect(),
+        };
+        assert_eq!(
+            bundle.render(2),
+            "[terminal]\nTERM_PROGRAM = \"iTerm.app\"\n\n[logs]\n```\nline 4\nline 5\n```\n"
+        );
+        assert_eq!(Bundle::default().render(2), "");
+    }
+}
@@ -0,0 +1,278 @@
+use std::io::Write;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::{
+    Args,
+    Subcommand,
+};
+use crossterm::{
+    execute,
+    style,
+};
+use eyre::{
+    Result,
+    bail,
+};
+
+use super::agent::Agent;
+use crate::cli::chat::tools::sanitize_path_tool_arg;
+use crate::cli::experiment::experiment_manager::{
+    ExperimentManager,
+    ExperimentName,
+};
+use crate::os::Os;
+use crate::theme::StyledText;
+use crate::util::knowledge_store::{
+    AddOptions,
+    KnowledgeStore,
+    is_url,
+    scoped_knowledge_bases,
+};
+
+/// How often to poll the status of background indexing when waiting for it to finish
+const INDEXING_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct KnowledgeArgs {
+    /// The agent whose knowledge base should be used. Defaults to the built-in default agent.
+    #[arg(long, global = true)]
+    pub agent: Option<String>,
+    #[command(subcommand)]
+    pub subcommand: KnowledgeSubcommand,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum KnowledgeSubcommand {
+    /// Index a file, directory, or http(s) url into the knowledge base
+    Add {
+        /// Path to a file or directory, or an http(s) url of a document to download
+        path: String,
+        /// Name for the knowledge base entry. Defaults to the file name or url.
+        #[arg(long, short = 'n')]
+        name: Option<String>,
+        /// Include patterns (e.g., `**/*.ts`, `**/*.md`)
+        #[arg(long, action = clap::ArgAction::Append)]
+        include: Vec<String>,
+        /// Exclude patterns (e.g., `node_modules/**`, `target/**`)
+        #[arg(long, action = clap::ArgAction::Append)]
+        exclude: Vec<String>,
+        /// Index type to use (Fast, Best)
+        #[arg(long)]
+        index_type: Option<String>,
+        /// Return immediately instead of waiting for indexing to finish
+        #[arg(long)]
+        no_wait: bool,
+    },
+    /// List the knowledge base entries
+    #[command(alias = "list")]
+    Show,
+    /// Search the knowledge base
+    Search {
+        /// The search query
+        #[arg(required = true)]
+        query: Vec<String>,
+        /// Name of a knowledge base entry to restrict the search to
+        #[arg(long, short = 'n')]
+        name: Option<String>,
+        /// Maximum number of results to print
+        #[arg(long, default_value_t = 5)]
+        limit: usize,
+    },
+    /// Remove a knowledge base entry by name or path
+    #[command(alias = "rm")]
+    Remove {
+        /// Name or path of the entry to remove
+        name_or_path: String,
+    },
+}
+
+impl KnowledgeArgs {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        if !ExperimentManager::is_enabled(os, ExperimentName::Knowledge) {
+            bail!(
+                "Knowledge base is disabled. Enable it with: {}",
+                StyledText::command("q settings chat.enableKnowledge true")
+            );
+        }
+
+        let agent = match &self.agent {
+            Some(name) => Some(Agent::get_agent_by_name(os, name).await?.0),
+            None => None,
+        };
+
+        let store = KnowledgeStore::get_async_instance(os, agent.as_ref())
+            .await
+            .map_err(|e| eyre::eyre!("Error accessing knowledge base: {}", e))?;
+        let mut store = store.lock().await;
+        let mut stderr = std::io::stderr();
+
+        match self.subcommand {
+            KnowledgeSubcommand::Add {
+                path,
+                name,
+                include,
+                exclude,
+                index_type,
+                no_wait,
+            } => {
+                let defaults = AddOptions::with_db_defaults(os);
+                let options = AddOptions::new()
+                    .with_include_patterns(if include.is_empty() {
+                        defaults.include_patterns
+                    } else {
+                        include
+                    })
+                    .with_exclude_patterns(if exclude.is_empty() {
+                        defaults.exclude_patterns
+                    } else {
+                        exclude
+                    })
+                    .with_embedding_type(index_type.or(defaults.embedding_type));
+
+                let message = if is_url(&path) {
+                    let name = name.unwrap_or_else(|| path.clone());
+                    store.add_url(&name, &path, options).await
+                } else {
+                    let sanitized = sanitize_path_tool_arg(os, &path);
+                    if !sanitized.exists() {
+                        bail!("Path '{}' does not exist", path);
+                    }
+                    let name = name.unwrap_or_else(|| default_entry_name(&sanitized));
+                    store.add(&name, &sanitized.to_string_lossy(), options).await
+                }
+                .map_err(|e| eyre::eyre!(e))?;
+                writeln!(stderr, "{message}")?;
+
+                if !no_wait {
+                    wait_for_operations(&store, &mut stderr).await?;
+                }
+            },
+            KnowledgeSubcommand::Show => {
+                let contexts = store.get_all().await.map_err(|e| eyre::eyre!(e))?;
+                let scoped = scoped_knowledge_bases(agent.as_ref());
+                if contexts.is_empty() {
+                    writeln!(stderr, "No knowledge base entries found")?;
+                }
+                for context in contexts {
+                    execute!(
+                        stderr,
+                        style::SetAttribute(style::Attribute::Bold),
+                        style::Print(&context.name),
+                        StyledText::reset_attributes(),
+                        StyledText::secondary_fg(),
+                        style::Print(format!(" ({})", &context.id[..8])),
+                        StyledText::reset(),
+                    )?;
+                    if !scoped.is_empty() && !scoped.contains(&context.name) {
+                        execute!(
+                            stderr,
+                            StyledText::warning_fg(),
+                            style::Print(" (not searchable by this agent)"),
+                            StyledText::reset(),
+                        )?;
+                    }
+                    writeln!(stderr)?;
+                    if let Some(source_path) = &context.source_path {
+                        writeln!(stderr, "   {source_path}")?;
+                    }
+                    writeln!(
+                        stderr,
+                        "   {} items • {} • {}",
+                        context.item_count,
+                        context.embedding_type.description(),
+                        context.updated_at.format("%Y-%m-%d %H:%M")
+                    )?;
+                }
+            },
+            KnowledgeSubcommand::Search { query, name, limit } => {
+                let query = query.join(" ");
+                let names = match name {
+                    Some(name) => vec![name],
+                    None => scoped_knowledge_bases(agent.as_ref()),
+                };
+                let results = store.search_scoped(&query, &names).await?;
+                if results.is_empty() {
+                    writeln!(stderr, "No matching entries found for query: \"{query}\"")?;
+                }
+                for result in results.iter().take(limit) {
+                    let Some(text) = result.text() else {
+                        continue;
+                    };
+                    if let Some(path) = result.point.payload.get("path").and_then(|v| v.as_str()) {
+                        execute!(
+                            stderr,
+                            StyledText::secondary_fg(),
+                            style::Print(format!("{path}\n")),
+                            StyledText::reset()
+                        )?;
+                    }
+                    println!("{}\n", text.trim());
+                }
+            },
+            KnowledgeSubcommand::Remove { name_or_path } => {
+                let sanitized = sanitize_path_tool_arg(os, &name_or_path);
+                if store.remove_by_path(&sanitized.to_string_lossy()).await.is_err() {
+                    store
+                        .remove_by_name(&name_or_path)
+                        .await
+                        .map_err(|_e| eyre::eyre!("Entry not found in knowledge base: {}", name_or_path))?;
+                }
+                writeln!(stderr, "Removed knowledge base entry '{name_or_path}'")?;
+            },
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Name an entry after the last component of its path, e.g. `docs/runbooks` -> `runbooks`
+fn default_entry_name(path: &std::path::Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string_lossy().to_string())
+}
+
+/// Indexing runs in the background, so a one-shot command needs to wait for it to finish before
+/// the process exits.
+async fn wait_for_operations(store: &KnowledgeStore, output: &mut impl Write) -> Result<()> {
+    loop {
+        let status = store.get_status_data().await.map_err(|e| eyre::eyre!(e))?;
+        let pending = status
+            .operations
+            .iter()
+            .filter(|op| !op.is_failed && !op.is_cancelled)
+            .collect::<Vec<_>>();
+
+        if let Some(failed) = status.operations.iter().find(|op| op.is_failed) {
+            bail!("Indexing failed: {}", failed.message);
+        }
+        if pending.is_empty() {
+            writeln!(output, "\r✅ Indexing complete")?;
+            return Ok(());
+        }
+
+        let (current, total) = pending
+            .iter()
+            .fold((0, 0), |(current, total), op| (current + op.current, total + op.total));
+        if total > 0 {
+            write!(output, "\r⏳ Indexing {}%", (current * 100 / total).min(100))?;
+        } else {
+            write!(output, "\r⏳ Indexing...")?;
+        }
+        output.flush()?;
+
+        tokio::time::sleep(INDEXING_POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_entry_name() {
+        assert_eq!(default_entry_name(std::path::Path::new("/docs/runbooks")), "runbooks");
+        assert_eq!(default_entry_name(std::path::Path::new("/")), "/");
+    }
+}
@@ -0,0 +1,386 @@
+use std::io::Write;
+use std::process::ExitCode;
+
+use clap::Args;
+use crossterm::style::Stylize;
+use eyre::{
+    Result,
+    bail,
+    eyre,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::cli::chat::ChatArgs;
+use crate::cli::chat::tools::todo::{
+    Task,
+    TodoListState,
+    generate_new_todo_id,
+};
+use crate::os::Os;
+use crate::util::paths::PathResolver;
+
+/// Plans a migration as a list of steps saved to a todo list, then carries out the steps one at a
+/// time, running the tests after each. Progress is saved, so running `q migrate` again resumes
+/// from the first step that isn't complete.
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct MigrateArgs {
+    /// Plan a new migration toward this goal, such as "Java 8 to Java 17"
+    #[arg(long, value_name = "GOAL")]
+    pub plan: Option<String>,
+    /// Command that verifies each step, such as "cargo test". Saved with the plan
+    #[arg(long, value_name = "COMMAND")]
+    pub test: Option<String>,
+    /// Migration to resume, the most recent unfinished one by default
+    #[arg(long, conflicts_with = "plan")]
+    pub id: Option<String>,
+    /// Show the progress of the migrations in this directory
+    #[arg(long, conflicts_with_all = ["plan", "id"])]
+    pub status: bool,
+    /// Allows the agent to use any tool without asking for confirmation, and runs the steps
+    /// without stopping for input
+    #[arg(short = 'a', long)]
+    pub trust_all_tools: bool,
+}
+
+/// A migration in progress. The steps themselves are the tasks of the todo list with the same id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Migration {
+    id: String,
+    goal: String,
+    test_command: Option<String>,
+}
+
+impl Migration {
+    async fn load(os: &Os, id: &str) -> Result<Self> {
+        let path = PathResolver::new(os)
+            .workspace()
+            .migrations_dir()?
+            .join(format!("{id}.json"));
+        let contents = os
+            .fs
+            .read_to_string(&path)
+            .await
+            .map_err(|_err| eyre!("No migration exists with the id {id}"))?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    async fn load_all(os: &Os) -> Result<Vec<Self>> {
+        let dir = PathResolver::new(os).workspace().migrations_dir()?;
+        let mut migrations: Vec<Self> = Vec::new();
+        if !os.fs.exists(&dir) {
+            return Ok(migrations);
+        }
+        let mut entries = os.fs.read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            match os
+                .fs
+                .read_to_string(entry.path())
+                .await
+                .map(|c| serde_json::from_str(&c))
+            {
+                Ok(Ok(migration)) => migrations.push(migration),
+                _ => tracing::warn!(path = ?entry.path(), "skipping unreadable migration"),
+            }
+        }
+        // Ids are creation timestamps
+        migrations.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(migrations)
+    }
+
+    async fn save(&self, os: &Os) -> Result<()> {
+        let dir = PathResolver::new(os).workspace().migrations_dir()?;
+        os.fs.create_dir_all(&dir).await?;
+        os.fs
+            .write(
+                dir.join(format!("{}.json", self.id)),
+                serde_json::to_string_pretty(self)?,
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+impl MigrateArgs {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        let mut stderr = std::io::stderr();
+
+        if self.status {
+            let migrations = Migration::load_all(os).await?;
+            if migrations.is_empty() {
+                writeln!(
+                    stderr,
+                    "No migrations in this directory. Start one with q migrate --plan <goal>"
+                )?;
+            }
+            for migration in migrations {
+                let todo = TodoListState::load(os, &migration.id).await?;
+                let completed = todo.tasks.iter().filter(|t| t.completed).count();
+                writeln!(
+                    stderr,
+                    "{} {} ({completed}/{})",
+                    migration.id.as_str().dark_grey(),
+                    migration.goal.as_str().bold(),
+                    todo.tasks.len()
+                )?;
+                let mut stdout = std::io::stdout();
+                todo.display_list(&mut stdout)?;
+                writeln!(stdout, "\n")?;
+            }
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        let (mut migration, mut todo) = match (&self.plan, &self.id) {
+            (Some(goal), _) => {
+                writeln!(stderr, "Planning the migration...")?;
+                let steps = plan_steps(os, goal).await?;
+                let id = generate_new_todo_id();
+                let todo = TodoListState {
+                    tasks: steps
+                        .into_iter()
+                        .map(|task_description| Task {
+                            task_description,
+                            completed: false,
+                            failed: false,
+                        })
+                        .collect(),
+                    description: format!("Migration: {goal}"),
+                    context: Vec::new(),
+                    modified_files: Vec::new(),
+                    id: id.clone(),
+                };
+                todo.save(os, &id).await?;
+                let migration = Migration {
+                    id,
+                    goal: goal.clone(),
+                    test_command: self.test.clone(),
+                };
+                migration.save(os).await?;
+                writeln!(
+                    stderr,
+                    "Saved the plan as migration {}. Run q migrate to resume it later.\n",
+                    migration.id
+                )?;
+                (migration, todo)
+            },
+            (None, Some(id)) => {
+                let migration = Migration::load(os, id).await?;
+                let todo = TodoListState::load(os, id).await?;
+                (migration, todo)
+            },
+            (None, None) => {
+                let mut unfinished = Vec::new();
+                for migration in Migration::load_all(os).await? {
+                    let todo = TodoListState::load(os, &migration.id).await?;
+                    if !todo.tasks.iter().all(|t| t.completed) {
+                        unfinished.push((migration, todo));
+                    }
+                }
+                match unfinished.pop() {
+                    Some(latest) => latest,
+                    None => bail!("No unfinished migrations in this directory. Start one with q migrate --plan <goal>"),
+                }
+            },
+        };
+        if self.plan.is_none() && self.test.is_some() {
+            migration.test_command = self.test.clone();
+            migration.save(os).await?;
+        }
+
+        todo.display_list(&mut stderr)?;
+        writeln!(stderr, "\n")?;
+
+        while let Some(index) = todo.tasks.iter().position(|t| !t.completed) {
+            let step = todo.tasks[index].task_description.clone();
+            writeln!(
+                stderr,
+                "{} {}",
+                format!("Step {}/{}:", index + 1, todo.tasks.len()).bold(),
+                step
+            )?;
+            if !self.trust_all_tools {
+                writeln!(
+                    stderr,
+                    "{}",
+                    "Exit the chat with /quit once the step is done.".dark_grey()
+                )?;
+            }
+
+            ChatArgs {
+                input: Some(step_prompt(&migration, &todo, index)),
+                trust_all_tools: self.trust_all_tools,
+                no_interactive: self.trust_all_tools,
+                ..Default::default()
+            }
+            .execute(os)
+            .await?;
+
+            let passed = match &migration.test_command {
+                Some(command) => {
+                    writeln!(stderr, "\nVerifying the step with {}", command.as_str().bold())?;
+                    run_test_command(command).await?
+                },
+                None => true,
+            };
+            todo.tasks[index].completed = passed;
+            todo.tasks[index].failed = !passed;
+            if passed {
+                todo.context.push(format!("Completed step {}: {step}", index + 1));
+            }
+            todo.save(os, &migration.id).await?;
+
+            if !passed {
+                writeln!(
+                    stderr,
+                    "\n{} Step {} failed verification. Fix it and run q migrate to retry it.",
+                    "✗".red().bold(),
+                    index + 1
+                )?;
+                return Ok(ExitCode::FAILURE);
+            }
+            writeln!(stderr, "{} Step {} complete\n", "✓".green().bold(), index + 1)?;
+        }
+
+        writeln!(stderr, "{} Migration complete: {}", "✓".green().bold(), migration.goal)?;
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+async fn plan_steps(os: &Os, goal: &str) -> Result<Vec<String>> {
+    let cwd = os
+        .env
+        .current_dir()
+        .map(|d| d.display().to_string())
+        .unwrap_or_default();
+    let content = format!(
+        "Plan a migration of the project in {cwd}: {goal}\n\n\
+        Break it into small steps that can each be done and tested on their own, in the order they \
+        should be done. Reply with a numbered list of the steps only, one line each, without \
+        explanation or markdown."
+    );
+    let text = os.client.send_prompt(content, None).await?.text().await?;
+    let steps = parse_steps(&text);
+    if steps.is_empty() {
+        bail!("No steps were planned for the migration");
+    }
+    Ok(steps)
+}
+
+/// Returns the items of a numbered or bulleted list, ignoring any other lines of the response.
+fn parse_steps(response: &str) -> Vec<String> {
+    response
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            let item = if digits > 0 {
+                line[digits..].strip_prefix(['.', ')'])?
+            } else {
+                line.strip_prefix(['-', '*'])?
+            };
+            let item = item.trim();
+            (!item.is_empty()).then(|| item.to_string())
+        })
+        .collect()
+}
+
+fn step_prompt(migration: &Migration, todo: &TodoListState, index: usize) -> String {
+    let plan = todo
+        .tasks
+        .iter()
+        .enumerate()
+        .map(|(i, task)| {
+            let status = if task.completed { "done" } else { "to do" };
+            format!("{}. [{status}] {}", i + 1, task.task_description)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let retry = if todo.tasks[index].failed {
+        " A previous attempt at this step failed verification, so check what was already changed first."
+    } else {
+        ""
+    };
+    let verification = match &migration.test_command {
+        Some(command) => format!(" When you're done, `{command}` will be run to verify the step."),
+        None => String::new(),
+    };
+    format!(
+        "We are migrating this project: {}\n\nThe plan is:\n{plan}\n\n\
+        Carry out step {} only: {}.{retry} Don't start on later steps.{verification}",
+        migration.goal,
+        index + 1,
+        todo.tasks[index].task_description,
+    )
+}
+
+async fn run_test_command(command: &str) -> Result<bool> {
+    #[cfg(windows)]
+    let mut cmd = tokio::process::Command::new("cmd");
+    #[cfg(windows)]
+    cmd.args(["/C", command]);
+    #[cfg(not(windows))]
+    let mut cmd = tokio::process::Command::new("bash");
+    #[cfg(not(windows))]
+    cmd.args(["-c", command]);
+
+    Ok(cmd.status().await?.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_steps() {
+        assert_eq!(
+            parse_steps(
+                "Here is the plan:\n1. Update the build to Java 17\n2) Replace javax imports\n- Run the tests\n\n"
+            ),
+            vec!["Update the build to Java 17", "Replace javax imports", "Run the tests"]
+        );
+        assert!(parse_steps("2024 was a good year").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_plan_steps() {
+        let mut os = Os::new().await.unwrap();
+        os.client
+            .set_mock_output(serde_json::json!([["1. Bump the toolchain\n", "2. Fix deprecations"]]));
+        assert_eq!(plan_steps(&os, "Java 8 to 17").await.unwrap(), vec![
+            "Bump the toolchain",
+            "Fix deprecations"
+        ]);
+    }
+
+    #[test]
+    fn test_step_prompt() {
+        let migration = Migration {
+            id: "1".to_string(),
+            goal: "Python 2 to 3".to_string(),
+            test_command: Some("pytest".to_string()),
+        };
+        let todo = TodoListState {
+            tasks: vec![
+                Task {
+                    task_description: "Run 2to3".to_string(),
+                    completed: true,
+                    failed: false,
+                },
+                Task {
+                    task_description: "Fix string handling".to_string(),
+                    completed: false,
+                    failed: true,
+                },
+            ],
+            ..Default::default()
+        };
+        let prompt = step_prompt(&migration, &todo, 1);
+        assert!(prompt.contains("1. [done] Run 2to3\n2. [to do] Fix string handling"));
+        assert!(prompt.contains("Carry out step 2 only: Fix string handling."));
+        assert!(prompt.contains("previous attempt"));
+        assert!(prompt.contains("`pytest`"));
+    }
+}
@@ -3,15 +3,38 @@ use crate::util::env_var::{
     get_aws_region,
     is_log_stdout_enabled,
 };
+mod about;
 mod agent;
+mod ask;
+mod backups;
+mod bench;
+mod cache;
+mod changelog;
 pub mod chat;
+mod compare;
+mod completion_specs;
+mod daemon;
 mod debug;
 mod diagnostics;
+pub mod error_class;
+mod eval;
 pub mod experiment;
+mod explain;
 pub mod feed;
+mod fix;
+mod hooks;
+mod init;
+mod integrations;
 mod issue;
+mod knowledge;
 mod mcp;
+mod migrate;
+mod scan;
+mod secrets;
+mod serve;
 mod settings;
+mod suggest_command;
+mod telemetry;
 mod user;
 
 use std::fmt::Display;
@@ -21,6 +44,7 @@ use std::io::{
 };
 use std::process::ExitCode;
 
+use about::AboutArgs;
 use agent::AgentArgs;
 pub use agent::{
     Agent,
@@ -47,8 +71,35 @@ use tracing::{
     debug,
 };
 
+use crate::cli::ask::AskArgs;
+use crate::cli::backups::BackupsSubcommand;
+use crate::cli::bench::BenchArgs;
+use crate::cli::cache::CacheSubcommand;
+use crate::cli::changelog::ChangelogArgs;
 use crate::cli::chat::ChatArgs;
+use crate::cli::chat::script::ScriptArgs;
+use crate::cli::compare::CompareArgs;
+use crate::cli::completion_specs::CompletionSpecsSubcommand;
+use crate::cli::daemon::DaemonSubcommand;
+use crate::cli::debug::DebugSubcommand;
+use crate::cli::error_class::ErrorFormat;
+use crate::cli::eval::EvalArgs;
+use crate::cli::explain::ExplainArgs;
+use crate::cli::fix::FixArgs;
+use crate::cli::hooks::HooksSubcommand;
+use crate::cli::init::InitArgs;
+use crate::cli::integrations::IntegrationsSubcommand;
+use crate::cli::knowledge::KnowledgeArgs;
 use crate::cli::mcp::McpSubcommand;
+use crate::cli::migrate::MigrateArgs;
+use crate::cli::scan::ScanArgs;
+use crate::cli::secrets::SecretsSubcommand;
+use crate::cli::serve::ServeArgs;
+use crate::cli::suggest_command::SuggestCommandArgs;
+use crate::cli::telemetry::{
+    StatsArgs,
+    TelemetrySubcommand,
+};
 use crate::cli::user::{
     LoginArgs,
     WhoamiArgs,
@@ -59,6 +110,7 @@ use crate::logging::{
 };
 use crate::os::Os;
 use crate::util::paths::logs_dir;
+use crate::util::startup_profile::stage;
 use crate::util::{
     CLI_BINARY_NAME,
     GOV_REGIONS,
@@ -99,6 +151,12 @@ pub enum RootSubcommand {
     Agent(AgentArgs),
     /// AI assistant in your terminal
     Chat(ChatArgs),
+    /// Answer a quick question with a single request, without tools or a conversation
+    Ask(AskArgs),
+    /// Explain a shell command flag by flag, based on the man pages on this machine
+    Explain(ExplainArgs),
+    /// Send the same prompt to several models concurrently and show their responses side by side
+    Compare(CompareArgs),
     /// Log in to Amazon Q
     Login(LoginArgs),
     /// Log out of Amazon Q
@@ -111,9 +169,9 @@ pub enum RootSubcommand {
     #[command(alias("setting"))]
     Settings(settings::SettingsArgs),
     /// Run diagnostic tests
-    #[command(alias("diagnostics"))]
+    #[command(alias("diagnostics"), alias("doctor"))]
     Diagnostic(diagnostics::DiagnosticArgs),
-    /// Create a new Github issue
+    /// Create a new Github issue pre-filled with diagnostics, or write them to a file
     Issue(issue::IssueArgs),
     /// Version
     #[command(hide = true)]
@@ -123,9 +181,65 @@ pub enum RootSubcommand {
         #[arg(long, num_args = 0..=1, default_missing_value = "")]
         changelog: Option<String>,
     },
+    /// Show how the installed binary was built, including its dependencies with --sbom
+    About(AboutArgs),
     /// Model Context Protocol (MCP)
     #[command(subcommand)]
     Mcp(McpSubcommand),
+    /// (Beta) Manage knowledge bases. Requires "q settings chat.enableKnowledge true"
+    #[command(alias("kb"))]
+    Knowledge(KnowledgeArgs),
+    /// Manage the cache of tool results and MCP tool lists
+    #[command(subcommand)]
+    Cache(CacheSubcommand),
+    /// Restore files from the backups taken before the agent overwrote them
+    #[command(subcommand)]
+    Backups(BackupsSubcommand),
+    /// Store credentials for tools in the OS keychain instead of config files
+    #[command(subcommand)]
+    Secrets(SecretsSubcommand),
+    /// Debug the hooks of an agent without running a conversation
+    #[command(subcommand)]
+    Hooks(HooksSubcommand),
+    /// Manage the autocomplete specs used for command completions
+    #[command(subcommand)]
+    CompletionSpecs(CompletionSpecsSubcommand),
+    /// Generate a shell command from a description and print it without running it
+    SuggestCommand(SuggestCommandArgs),
+    /// Propose a fix for the last shell command, which failed, and print it once accepted
+    Fix(FixArgs),
+    /// Background process hosting the shell integrations
+    #[command(subcommand)]
+    Daemon(DaemonSubcommand),
+    /// Install integrations with the system, such as starting the daemon at login
+    #[command(subcommand)]
+    Integrations(IntegrationsSubcommand),
+    /// Choose which telemetry is collected and whether it is sent
+    #[command(subcommand)]
+    Telemetry(TelemetrySubcommand),
+    /// Show the telemetry events recorded on this machine
+    Stats(StatsArgs),
+    /// Scaffold a new project from a template with an agent
+    Init(InitArgs),
+    /// Plan a migration as steps and carry them out one at a time, verifying each with tests
+    Migrate(MigrateArgs),
+    /// Run security scanners and fix their findings with an agent
+    Scan(ScanArgs),
+    /// Write release notes from the git history, optionally adding them to CHANGELOG.md
+    Changelog(ChangelogArgs),
+    /// Run agent behavior scenarios and report which pass
+    Eval(EvalArgs),
+    /// Run the prompts of a script without user input, and report whether each turn met its
+    /// expectations
+    Script(ScriptArgs),
+    /// Serve conversations with the agent over a local HTTP API
+    Serve(ServeArgs),
+    /// Time the work the agent repeats on every turn, optionally against a baseline
+    #[command(hide = true)]
+    Bench(BenchArgs),
+    /// Tools for debugging the agent
+    #[command(subcommand, hide = true)]
+    Debug(DebugSubcommand),
 }
 
 impl RootSubcommand {
@@ -137,16 +251,31 @@ impl RootSubcommand {
     }
 
     pub fn requires_auth(&self) -> bool {
-        matches!(self, Self::Chat(_) | Self::Profile)
+        matches!(
+            self,
+            Self::Chat(_)
+                | Self::Ask(_)
+                | Self::Explain(_)
+                | Self::Compare(_)
+                | Self::Profile
+                | Self::SuggestCommand(_)
+                | Self::Fix(_)
+                | Self::Init(_)
+                | Self::Changelog(_)
+                | Self::Serve(_)
+                | Self::Script(_)
+        ) || matches!(self, Self::Migrate(args) if !args.status)
+            || matches!(self, Self::Scan(args) if !args.no_chat)
+            || matches!(self, Self::Eval(args) if args.live)
     }
 
     pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
         // Check for auth on subcommands that require it.
-        if self.requires_auth() && !crate::auth::is_logged_in(&mut os.database).await {
-            bail!(
+        if self.requires_auth() && !stage("auth check", crate::auth::is_logged_in(&mut os.database)).await {
+            return Err(eyre::Report::new(crate::auth::AuthError::NoToken).wrap_err(format!(
                 "You are not logged in, please log in with {}",
                 StyledText::command(&format!("{CLI_BINARY_NAME} login"))
-            );
+            )));
         }
 
         // Daily heartbeat check
@@ -172,8 +301,33 @@ impl RootSubcommand {
             Self::Settings(settings_args) => settings_args.execute(os).await,
             Self::Issue(args) => args.execute(os).await,
             Self::Version { changelog } => Cli::print_version(changelog),
+            Self::About(args) => args.execute().await,
             Self::Chat(args) => args.execute(os).await,
+            Self::Ask(args) => args.execute(os).await,
+            Self::Explain(args) => args.execute(os).await,
+            Self::Compare(args) => args.execute(os).await,
             Self::Mcp(args) => args.execute(os, &mut std::io::stderr()).await,
+            Self::Knowledge(args) => args.execute(os).await,
+            Self::Cache(subcommand) => subcommand.execute(os).await,
+            Self::Backups(subcommand) => subcommand.execute(os).await,
+            Self::Secrets(subcommand) => subcommand.execute(os).await,
+            Self::Hooks(subcommand) => subcommand.execute(os).await,
+            Self::CompletionSpecs(subcommand) => subcommand.execute(os).await,
+            Self::SuggestCommand(args) => args.execute(os).await,
+            Self::Fix(args) => args.execute(os).await,
+            Self::Daemon(subcommand) => subcommand.execute(os).await,
+            Self::Integrations(subcommand) => subcommand.execute(os).await,
+            Self::Telemetry(subcommand) => subcommand.execute(os).await,
+            Self::Stats(args) => args.execute(os).await,
+            Self::Init(args) => args.execute(os).await,
+            Self::Migrate(args) => args.execute(os).await,
+            Self::Scan(args) => args.execute(os).await,
+            Self::Changelog(args) => args.execute(os).await,
+            Self::Eval(args) => args.execute(os).await,
+            Self::Script(args) => args.execute(os).await,
+            Self::Serve(args) => args.execute(os).await,
+            Self::Bench(args) => args.execute().await,
+            Self::Debug(subcommand) => subcommand.execute().await,
         }
     }
 }
@@ -189,6 +343,9 @@ impl Display for RootSubcommand {
         let name = match self {
             Self::Agent(_) => "agent",
             Self::Chat(_) => "chat",
+            Self::Ask(_) => "ask",
+            Self::Explain(_) => "explain",
+            Self::Compare(_) => "compare",
             Self::Login(_) => "login",
             Self::Logout => "logout",
             Self::Whoami(_) => "whoami",
@@ -197,7 +354,29 @@ impl Display for RootSubcommand {
             Self::Diagnostic(_) => "diagnostic",
             Self::Issue(_) => "issue",
             Self::Version { .. } => "version",
+            Self::About(_) => "about",
             Self::Mcp(_) => "mcp",
+            Self::Knowledge(_) => "knowledge",
+            Self::Cache(_) => "cache",
+            Self::Backups(_) => "backups",
+            Self::Secrets(_) => "secrets",
+            Self::Hooks(_) => "hooks",
+            Self::CompletionSpecs(_) => "completion-specs",
+            Self::SuggestCommand(_) => "suggest-command",
+            Self::Fix(_) => "fix",
+            Self::Daemon(_) => "daemon",
+            Self::Integrations(_) => "integrations",
+            Self::Telemetry(_) => "telemetry",
+            Self::Stats(_) => "stats",
+            Self::Init(_) => "init",
+            Self::Migrate(_) => "migrate",
+            Self::Scan(_) => "scan",
+            Self::Changelog(_) => "changelog",
+            Self::Eval(_) => "eval",
+            Self::Script(_) => "script",
+            Self::Serve(_) => "serve",
+            Self::Bench(_) => "bench",
+            Self::Debug(_) => "debug",
         };
 
         write!(f, "{name}")
@@ -215,6 +394,9 @@ pub struct Cli {
     /// Print help for all subcommands
     #[arg(long)]
     help_all: bool,
+    /// Format of the error printed when a command fails
+    #[arg(long, value_enum, default_value_t, global = true)]
+    pub error_format: ErrorFormat,
 }
 
 impl Cli {
@@ -237,7 +419,14 @@ impl Cli {
             },
             log_to_stdout: is_log_stdout_enabled() || self.verbose > 0,
             log_file_path: match subcommand {
-                RootSubcommand::Chat { .. } => Some(logs_dir().expect("home dir must be set").join("qchat.log")),
+                RootSubcommand::Chat { .. }
+                | RootSubcommand::Init(_)
+                | RootSubcommand::Migrate(_)
+                | RootSubcommand::Scan(_)
+                | RootSubcommand::Changelog(_) => Some(logs_dir().expect("home dir must be set").join("qchat.log")),
+                RootSubcommand::Daemon(DaemonSubcommand::Run { .. }) => {
+                    Some(daemon::log_path().expect("home dir must be set"))
+                },
                 _ => None,
             },
             delete_old_log_file: false,
@@ -341,6 +530,8 @@ impl Cli {
 
 #[cfg(test)]
 mod test {
+    use std::path::PathBuf;
+
     use chat::WrapMode::{
         Always,
         Auto,
@@ -363,12 +554,14 @@ mod test {
             subcommand: None,
             verbose: 1,
             help_all: false,
+            error_format: ErrorFormat::Text,
         });
 
         assert_eq!(Cli::parse_from([CHAT_BINARY_NAME, "-vvv"]), Cli {
             subcommand: None,
             verbose: 3,
             help_all: false,
+            error_format: ErrorFormat::Text,
         });
 
         assert_eq!(Cli::parse_from([CHAT_BINARY_NAME, "--help-all"]), Cli {
@@ -387,9 +580,14 @@ mod test {
                 trust_tools: None,
                 no_interactive: false,
                 wrap: None,
+                profile_startup: false,
+                list: false,
+                all: false,
+                explain_error: false,
             })),
             verbose: 2,
             help_all: false,
+            error_format: ErrorFormat::Text,
         });
     }
 
@@ -414,6 +612,375 @@ mod test {
         });
     }
 
+    #[test]
+    fn test_about_sbom() {
+        assert_parse!(
+            ["about", "--sbom", "--format", "json"],
+            RootSubcommand::About(AboutArgs {
+                sbom: true,
+                format: OutputFormat::Json,
+            })
+        );
+    }
+
+    #[test]
+    fn test_knowledge_add_url() {
+        assert_parse!(
+            ["kb", "add", "https://example.com/runbook.md", "--agent", "oncall"],
+            RootSubcommand::Knowledge(KnowledgeArgs {
+                agent: Some("oncall".to_string()),
+                subcommand: knowledge::KnowledgeSubcommand::Add {
+                    path: "https://example.com/runbook.md".to_string(),
+                    name: None,
+                    include: vec![],
+                    exclude: vec![],
+                    index_type: None,
+                    no_wait: false,
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_cache_clear() {
+        assert_parse!(["cache", "clear"], RootSubcommand::Cache(CacheSubcommand::Clear));
+    }
+
+    #[test]
+    fn test_backups_restore() {
+        assert_parse!(
+            [
+                "backups",
+                "restore",
+                "conv/20261016T101500.000Z",
+                "--file",
+                "src/main.rs"
+            ],
+            RootSubcommand::Backups(BackupsSubcommand::Restore {
+                id: "conv/20261016T101500.000Z".to_string(),
+                file: Some(std::path::PathBuf::from("src/main.rs")),
+            })
+        );
+    }
+
+    #[test]
+    fn test_backups_prune() {
+        assert_parse!(
+            ["backups", "prune", "--max-size-mb", "200"],
+            RootSubcommand::Backups(BackupsSubcommand::Prune {
+                max_age_days: None,
+                max_size_mb: Some(200),
+            })
+        );
+    }
+
+    #[test]
+    fn test_secrets_rm() {
+        assert_parse!(
+            ["secrets", "rm", "github-token"],
+            RootSubcommand::Secrets(SecretsSubcommand::Rm {
+                name: "github-token".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_hooks_test() {
+        assert_parse!(
+            [
+                "hooks",
+                "test",
+                "reviewer",
+                "--trigger",
+                "preToolUse",
+                "--tool",
+                "fs_write"
+            ],
+            RootSubcommand::Hooks(HooksSubcommand::Test {
+                agent: "reviewer".to_string(),
+                trigger: Some(crate::cli::agent::hook::HookTrigger::PreToolUse),
+                tool: Some("fs_write".to_string()),
+                tool_input: "{}".to_string(),
+                prompt: "Hello".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_completion_specs() {
+        assert_parse!(
+            ["completion-specs", "update", "--force"],
+            RootSubcommand::CompletionSpecs(CompletionSpecsSubcommand::Update { force: true })
+        );
+        assert_parse!(
+            ["completion-specs", "status"],
+            RootSubcommand::CompletionSpecs(CompletionSpecsSubcommand::Status)
+        );
+    }
+
+    #[test]
+    fn test_script() {
+        assert_parse!(
+            ["script", "review.yaml", "--report", "report.json"],
+            RootSubcommand::Script(ScriptArgs {
+                script: PathBuf::from("review.yaml"),
+                report: Some(PathBuf::from("report.json")),
+            })
+        );
+        assert_parse!(
+            ["chat", "exec"],
+            RootSubcommand::Chat(ChatArgs {
+                input: Some("exec".to_string()),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_ask() {
+        assert_parse!(
+            ["ask", "--model", "claude-haiku", "which", "tar", "flag"],
+            RootSubcommand::Ask(AskArgs {
+                question: vec!["which".into(), "tar".into(), "flag".into()],
+                model: Some("claude-haiku".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_parse!(
+            ["compare", "--models", "claude-sonnet-4,claude-haiku", "explain monads"],
+            RootSubcommand::Compare(CompareArgs {
+                models: vec!["claude-sonnet-4".to_string(), "claude-haiku".to_string()],
+                prompt: vec!["explain monads".into()],
+                format: OutputFormat::Plain,
+            })
+        );
+    }
+
+    #[test]
+    fn test_explain() {
+        assert_parse!(
+            ["explain", "tar", "-xzvf", "foo.tgz"],
+            RootSubcommand::Explain(ExplainArgs {
+                command: vec!["tar".into(), "-xzvf".into(), "foo.tgz".into()],
+                model: None,
+            })
+        );
+        assert_parse!(
+            ["explain", "--model", "claude-haiku", "ls -la"],
+            RootSubcommand::Explain(ExplainArgs {
+                command: vec!["ls -la".into()],
+                model: Some("claude-haiku".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_suggest_command() {
+        assert_parse!(
+            ["suggest-command", "--shell", "fish", "list", "files", "by", "size"],
+            RootSubcommand::SuggestCommand(SuggestCommandArgs {
+                request: vec!["list".into(), "files".into(), "by".into(), "size".into()],
+                shell: Some("fish".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_fix() {
+        assert_parse!(
+            ["fix", "-y", "--shell", "zsh"],
+            RootSubcommand::Fix(FixArgs {
+                yes: true,
+                shell: Some("zsh".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_daemon() {
+        assert_parse!(
+            ["daemon", "run"],
+            RootSubcommand::Daemon(DaemonSubcommand::Run { no_idle_timeout: false })
+        );
+        assert_parse!(
+            ["daemon", "logs", "-n", "10", "--follow"],
+            RootSubcommand::Daemon(DaemonSubcommand::Logs {
+                lines: 10,
+                follow: true
+            })
+        );
+        assert_parse!(["daemon", "restart"], RootSubcommand::Daemon(DaemonSubcommand::Restart));
+        assert_parse!(
+            ["daemon", "complete", "--cursor", "3", "git checkout"],
+            RootSubcommand::Daemon(DaemonSubcommand::Complete {
+                line: "git checkout".into(),
+                cursor: Some(3),
+            })
+        );
+    }
+
+    #[test]
+    fn test_integrations() {
+        assert_parse!(
+            ["integrations", "install", "daemon"],
+            RootSubcommand::Integrations(IntegrationsSubcommand::Install {
+                integration: integrations::Integration::Daemon,
+                shell: None,
+            })
+        );
+        assert_parse!(
+            ["integrations", "uninstall", "shell", "--shell", "fish"],
+            RootSubcommand::Integrations(IntegrationsSubcommand::Uninstall {
+                integration: integrations::Integration::Shell,
+                shell: Some(integrations::shell::Shell::Fish),
+            })
+        );
+    }
+
+    #[test]
+    fn test_telemetry() {
+        assert_parse!(
+            ["telemetry", "disable", "performance"],
+            RootSubcommand::Telemetry(TelemetrySubcommand::Disable {
+                category: Some(crate::telemetry::TelemetryCategory::Performance)
+            })
+        );
+        assert_parse!(
+            ["telemetry", "enable"],
+            RootSubcommand::Telemetry(TelemetrySubcommand::Enable { category: None })
+        );
+    }
+
+    #[test]
+    fn test_init() {
+        assert_parse!(
+            ["init", "rust-cli", "greeter"],
+            RootSubcommand::Init(InitArgs {
+                template: init::Template::RustCli,
+                directory: Some("greeter".into()),
+                name: None,
+                model: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_migrate() {
+        assert_parse!(
+            ["migrate", "--plan", "Java 8 to 17", "--test", "mvn test"],
+            RootSubcommand::Migrate(MigrateArgs {
+                plan: Some("Java 8 to 17".to_string()),
+                test: Some("mvn test".to_string()),
+                id: None,
+                status: false,
+                trust_all_tools: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_scan() {
+        assert_parse!(
+            ["scan", "--tool", "semgrep,cargo-audit", "--no-chat"],
+            RootSubcommand::Scan(ScanArgs {
+                tool: vec![
+                    crate::util::security_scan::Scanner::Semgrep,
+                    crate::util::security_scan::Scanner::CargoAudit
+                ],
+                no_chat: true,
+                format: OutputFormat::Plain,
+            })
+        );
+    }
+
+    #[test]
+    fn test_changelog() {
+        assert_parse!(
+            ["changelog", "--from", "v1.2.0", "--update"],
+            RootSubcommand::Changelog(ChangelogArgs {
+                from: "v1.2.0".to_string(),
+                to: "HEAD".to_string(),
+                template: None,
+                update: true,
+                model: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_eval() {
+        assert_parse!(
+            ["eval", "evals", "--live", "--model", "claude-sonnet-4"],
+            RootSubcommand::Eval(EvalArgs {
+                paths: vec!["evals".into()],
+                live: true,
+                model: Some("claude-sonnet-4".to_string()),
+                variants: None,
+                format: OutputFormat::Plain,
+            })
+        );
+        assert_parse!(
+            ["eval", "evals", "--variants", "variants.yaml"],
+            RootSubcommand::Eval(EvalArgs {
+                paths: vec!["evals".into()],
+                live: false,
+                model: None,
+                variants: Some("variants.yaml".into()),
+                format: OutputFormat::Plain,
+            })
+        );
+    }
+
+    #[test]
+    fn test_serve() {
+        assert_parse!(
+            ["serve", "--listen", "127.0.0.1:9000"],
+            RootSubcommand::Serve(ServeArgs {
+                listen: "127.0.0.1:9000".parse().unwrap(),
+                model: None,
+                openai_compat: false,
+                journal_dir: None,
+            })
+        );
+        assert_parse!(
+            ["serve", "--journal-dir", "journals"],
+            RootSubcommand::Serve(ServeArgs {
+                listen: "127.0.0.1:8400".parse().unwrap(),
+                model: None,
+                openai_compat: false,
+                journal_dir: Some("journals".into()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_bench() {
+        assert_parse!(
+            ["bench", "--iterations", "10", "--baseline", "bench.json"],
+            RootSubcommand::Bench(BenchArgs {
+                iterations: 10,
+                messages: ::agent::bench::CONVERSATION_LEN,
+                baseline: Some("bench.json".into()),
+                max_regression: 20.0,
+                format: OutputFormat::Plain,
+            })
+        );
+    }
+
+    #[test]
+    fn test_debug_replay() {
+        assert_parse!(
+            ["debug", "replay", "journal.json", "--turn", "2"],
+            RootSubcommand::Debug(DebugSubcommand::Replay {
+                file: "journal.json".into(),
+                turn: Some(2),
+            })
+        );
+    }
+
     #[test]
     fn test_chat_with_context_profile() {
         assert_parse!(
@@ -427,6 +994,10 @@ mod test {
                 trust_tools: None,
                 no_interactive: false,
                 wrap: None,
+                profile_startup: false,
+                list: false,
+                all: false,
+                explain_error: false,
             })
         );
     }
@@ -444,6 +1015,10 @@ mod test {
                 trust_tools: None,
                 no_interactive: false,
                 wrap: None,
+                profile_startup: false,
+                list: false,
+                all: false,
+                explain_error: false,
             })
         );
     }
@@ -461,6 +1036,10 @@ mod test {
                 trust_tools: None,
                 no_interactive: false,
                 wrap: None,
+                profile_startup: false,
+                list: false,
+                all: false,
+                explain_error: false,
             })
         );
     }
@@ -478,6 +1057,10 @@ mod test {
                 trust_tools: None,
                 no_interactive: true,
                 wrap: None,
+                profile_startup: false,
+                list: false,
+                all: false,
+                explain_error: false,
             })
         );
         assert_parse!(
@@ -491,6 +1074,10 @@ mod test {
                 trust_tools: None,
                 no_interactive: true,
                 wrap: None,
+                profile_startup: false,
+                list: false,
+                all: false,
+                explain_error: false,
             })
         );
     }
@@ -508,6 +1095,53 @@ mod test {
                 trust_tools: None,
                 no_interactive: false,
                 wrap: None,
+                profile_startup: false,
+                list: false,
+                all: false,
+                explain_error: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_chat_explain_error() {
+        assert_parse!(
+            ["chat", "--explain-error"],
+            RootSubcommand::Chat(ChatArgs {
+                resume: false,
+                input: None,
+                agent: None,
+                model: None,
+                trust_all_tools: false,
+                trust_tools: None,
+                no_interactive: false,
+                wrap: None,
+                profile_startup: false,
+                list: false,
+                all: false,
+                explain_error: true,
+            })
+        );
+        assert!(Cli::try_parse_from([CHAT_BINARY_NAME, "chat", "--explain-error", "Hello"]).is_err());
+    }
+
+    #[test]
+    fn test_chat_list() {
+        assert_parse!(
+            ["chat", "--list"],
+            RootSubcommand::Chat(ChatArgs {
+                resume: false,
+                input: None,
+                agent: None,
+                model: None,
+                trust_all_tools: false,
+                trust_tools: None,
+                no_interactive: false,
+                wrap: None,
+                profile_startup: false,
+                list: true,
+                all: false,
+                explain_error: false,
             })
         );
     }
@@ -525,6 +1159,10 @@ mod test {
                 trust_tools: Some(vec!["".to_string()]),
                 no_interactive: false,
                 wrap: None,
+                profile_startup: false,
+                list: false,
+                all: false,
+                explain_error: false,
             })
         );
     }
@@ -542,6 +1180,10 @@ mod test {
                 trust_tools: Some(vec!["fs_read".to_string(), "fs_write".to_string()]),
                 no_interactive: false,
                 wrap: None,
+                profile_startup: false,
+                list: false,
+                all: false,
+                explain_error: false,
             })
         );
     }
@@ -559,6 +1201,10 @@ mod test {
                 trust_tools: None,
                 no_interactive: false,
                 wrap: Some(Never),
+                profile_startup: false,
+                list: false,
+                all: false,
+                explain_error: false,
             })
         );
         assert_parse!(
@@ -572,6 +1218,10 @@ mod test {
                 trust_tools: None,
                 no_interactive: false,
                 wrap: Some(Always),
+                profile_startup: false,
+                list: false,
+                all: false,
+                explain_error: false,
             })
         );
         assert_parse!(
@@ -585,6 +1235,10 @@ mod test {
                 trust_tools: None,
                 no_interactive: false,
                 wrap: Some(Auto),
+                profile_startup: false,
+                list: false,
+                all: false,
+                explain_error: false,
             })
         );
     }
@@ -0,0 +1,148 @@
+use std::io::{
+    IsTerminal,
+    Write,
+};
+use std::process::ExitCode;
+
+use clap::Args;
+use crossterm::style::Stylize;
+use eyre::{
+    Result,
+    bail,
+};
+
+use super::OutputFormat;
+use crate::cli::chat::ChatArgs;
+use crate::os::Os;
+use crate::util::security_scan::{
+    Finding,
+    ScanReport,
+    Scanner,
+    Severity,
+};
+
+/// Runs the security scanners installed on the system over the current directory, then starts a
+/// chat session with the findings so they can be fixed one at a time with `/fix <id>`.
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct ScanArgs {
+    /// Scanners to run, every installed scanner that applies to the project by default
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub tool: Vec<Scanner>,
+    /// Print the findings without starting a chat session
+    #[arg(long)]
+    pub no_chat: bool,
+    /// Format of the findings
+    #[arg(long, short, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+impl ScanArgs {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        let mut stderr = std::io::stderr();
+        let explicit = !self.tool.is_empty();
+        let report = ScanReport::run(os, &self.tool, |scanner| {
+            if explicit {
+                let _ = writeln!(stderr, "{} {scanner} is not installed", "Skipped:".yellow());
+            }
+        })
+        .await?;
+        if report.scanners.is_empty() {
+            bail!("No scanners are installed. Install semgrep, bandit or cargo-audit to scan this project");
+        }
+        report.save(os).await?;
+
+        self.format.print(
+            || {
+                let scanners = report.scanners.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+                let mut text = format!(
+                    "Scanned with {}: {} findings\n",
+                    scanners.join(", "),
+                    report.findings.len()
+                );
+                for finding in &report.findings {
+                    text.push_str(&format_finding(finding));
+                }
+                text
+            },
+            || &report,
+        );
+
+        if report.findings.is_empty() {
+            return Ok(ExitCode::SUCCESS);
+        }
+        if self.no_chat || self.format != OutputFormat::Plain || !std::io::stdin().is_terminal() {
+            return Ok(ExitCode::FAILURE);
+        }
+
+        ChatArgs {
+            input: Some(findings_prompt(&report)),
+            ..Default::default()
+        }
+        .execute(os)
+        .await
+    }
+}
+
+fn format_finding(finding: &Finding) -> String {
+    let severity = match finding.severity {
+        Severity::High => "high".red().bold(),
+        Severity::Medium => "medium".yellow(),
+        Severity::Low => "low".dark_grey(),
+    };
+    format!(
+        "{} {severity} {} {}\n   {}\n",
+        finding.id.as_str().bold(),
+        finding.location().unwrap_or_default(),
+        finding.rule.as_str().dark_grey(),
+        finding.message
+    )
+}
+
+/// First message of the session, so the agent knows the findings without fixing anything yet.
+fn findings_prompt(report: &ScanReport) -> String {
+    let findings = report
+        .findings
+        .iter()
+        .map(|f| {
+            format!(
+                "- {} [{}] {} {}: {}",
+                f.id,
+                f.severity,
+                f.location().unwrap_or_default(),
+                f.rule,
+                f.message
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "A security scan of this project reported these findings:\n{findings}\n\n\
+        Don't change any code yet. Summarize the findings in a few sentences, grouping related ones, and \
+        suggest which to fix first. I will ask for fixes with /fix <id>."
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_findings_prompt() {
+        let report = ScanReport {
+            scanned_at: String::new(),
+            scanners: vec![Scanner::Semgrep],
+            findings: vec![Finding {
+                id: "F1".to_string(),
+                scanner: Scanner::Semgrep,
+                rule: "eval-detected".to_string(),
+                severity: Severity::High,
+                path: Some("app.py".to_string()),
+                line: Some(3),
+                message: "Detected eval".to_string(),
+            }],
+        };
+        let prompt = findings_prompt(&report);
+        assert!(prompt.contains("- F1 [high] app.py:3 eval-detected: Detected eval"));
+        assert!(prompt.contains("/fix <id>"));
+    }
+}
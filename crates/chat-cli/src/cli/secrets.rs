@@ -0,0 +1,82 @@
+use std::io::{
+    IsTerminal,
+    Read,
+    Write,
+};
+use std::process::ExitCode;
+
+use clap::Subcommand;
+use dialoguer::console::Term;
+use eyre::{
+    Result,
+    bail,
+};
+
+use crate::os::Os;
+use crate::util::secrets;
+
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum SecretsSubcommand {
+    /// Store a secret, read from the terminal without echoing it or from stdin. Reference it in
+    /// agent and MCP server env values and headers with ${secret:NAME}. Secrets are encrypted in
+    /// the login keychain on macOS, and stored unencrypted in the CLI's local database elsewhere
+    Set {
+        /// Name of the secret
+        name: String,
+    },
+    /// Print the value of a secret
+    Get {
+        /// Name of the secret
+        name: String,
+    },
+    /// Delete a secret
+    #[command(alias("delete"))]
+    Rm {
+        /// Name of the secret
+        name: String,
+    },
+}
+
+impl SecretsSubcommand {
+    pub async fn execute(self, os: &Os) -> Result<ExitCode> {
+        let mut stderr = std::io::stderr();
+        match self {
+            Self::Set { name } => {
+                secrets::validate_name(&name)?;
+                let value = if std::io::stdin().is_terminal() {
+                    write!(stderr, "Value of {name}: ")?;
+                    stderr.flush()?;
+                    Term::stderr().read_secure_line()?
+                } else {
+                    let mut value = String::new();
+                    std::io::stdin().read_to_string(&mut value)?;
+                    value.trim_end_matches(['\n', '\r']).to_string()
+                };
+                if value.is_empty() {
+                    bail!("The value of a secret can't be empty");
+                }
+                secrets::set(&os.database, &name, &value)?;
+                writeln!(stderr, "Stored {name}, reference it with ${{secret:{name}}}")?;
+                if !secrets::ENCRYPTED_AT_REST {
+                    writeln!(
+                        stderr,
+                        "Secrets are stored unencrypted in the CLI's local database on this platform, where any \
+                         process running as your user can read them"
+                    )?;
+                }
+            },
+            Self::Get { name } => match secrets::get(&os.database, &name)? {
+                Some(value) => println!("{value}"),
+                None => bail!("No secret named '{name}'"),
+            },
+            Self::Rm { name } => {
+                if !secrets::delete(&os.database, &name)? {
+                    bail!("No secret named '{name}'");
+                }
+                writeln!(stderr, "Deleted {name}")?;
+            },
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
@@ -0,0 +1,708 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{
+    Arc,
+    Weak,
+};
+use std::time::Duration;
+
+use agent::agent_config::definitions::AgentConfig;
+use agent::agent_loop::model_provider::ModelProviderRegistry;
+use agent::protocol::{
+    AgentError,
+    SendApprovalResultArgs,
+    SendPromptArgs,
+};
+use bytes::Bytes;
+use http::{
+    Method,
+    Request,
+    Response,
+    StatusCode,
+    header,
+};
+use http_body_util::combinators::UnsyncBoxBody;
+use http_body_util::{
+    BodyExt,
+    Full,
+    LengthLimitError,
+    Limited,
+    StreamBody,
+};
+use hyper::body::{
+    Body,
+    Frame,
+    Incoming,
+};
+use hyper::service::Service;
+use serde::de::DeserializeOwned;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tokio::sync::{
+    RwLock,
+    watch,
+};
+use tracing::error;
+use uuid::Uuid;
+
+use super::conversation::Conversation;
+use super::openai;
+
+/// Largest request body accepted, enough for prompts with images
+const MAX_BODY_LEN: usize = 16 * 1024 * 1024;
+
+/// Interval of the comments sent on idle event streams, so clients and proxies keep them open
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+pub type ApiResponse = Response<UnsyncBoxBody<Bytes, Infallible>>;
+
+pub struct State {
+    token: String,
+    pub(super) default_model: Option<String>,
+    /// Creates the model of each conversation from its agent config
+    pub(super) models: ModelProviderRegistry,
+    /// Models of the OpenAI compatible endpoints, which are only served when this is set
+    pub(super) openai_models: Option<Vec<String>>,
+    /// Directory the journals of the conversations are recorded into, if they are
+    journal_dir: Option<PathBuf>,
+    /// Whether the agents may launch MCP servers, false when an administrator disabled MCP
+    pub(super) mcp_enabled: bool,
+    conversations: RwLock<HashMap<Uuid, Arc<Conversation>>>,
+}
+
+impl State {
+    pub fn new(token: String, default_model: Option<String>, models: ModelProviderRegistry) -> Self {
+        Self {
+            token,
+            default_model,
+            models,
+            openai_models: None,
+            journal_dir: None,
+            mcp_enabled: true,
+            conversations: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Serves the OpenAI compatible endpoints, letting requests choose from `models`.
+    pub fn with_openai_compat(mut self, models: Vec<String>) -> Self {
+        self.openai_models = Some(models);
+        self
+    }
+
+    /// Keeps the agents from launching MCP servers, as required by the user's profile.
+    pub fn with_mcp_disabled(mut self) -> Self {
+        self.mcp_enabled = false;
+        self
+    }
+
+    /// Records the journal of each conversation into `dir`, named after the conversation id.
+    pub fn with_journal_dir(mut self, dir: PathBuf) -> Self {
+        self.journal_dir = Some(dir);
+        self
+    }
+
+    async fn conversation(&self, id: &str) -> Result<Arc<Conversation>, ApiError> {
+        let conversations = self.conversations.read().await;
+        Uuid::parse_str(id)
+            .ok()
+            .and_then(|id| conversations.get(&id).cloned())
+            .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("no conversation with id {id}")))
+    }
+}
+
+#[derive(Clone)]
+pub struct ApiService {
+    pub state: Arc<State>,
+}
+
+impl Service<Request<Incoming>> for ApiService {
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<ApiResponse, Infallible>> + Send>>;
+    type Response = ApiResponse;
+
+    fn call(&self, req: Request<Incoming>) -> Self::Future {
+        let state = Arc::clone(&self.state);
+        Box::pin(async move { Ok(handle(state, req).await) })
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct ApiError {
+    pub(super) status: StatusCode,
+    pub(super) message: String,
+}
+
+impl ApiError {
+    pub(super) fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<AgentError> for ApiError {
+    fn from(err: AgentError) -> Self {
+        let status = match err {
+            AgentError::NotIdle => StatusCode::CONFLICT,
+            AgentError::Channel => StatusCode::GONE,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        Self::new(status, err.to_string())
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct CreateConversation {
+    model: Option<String>,
+    agent_config: Option<AgentConfig>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConversationSummary {
+    id: Uuid,
+    model: Option<String>,
+    created_at: i64,
+}
+
+impl From<&Conversation> for ConversationSummary {
+    fn from(conversation: &Conversation) -> Self {
+        Self {
+            id: conversation.id,
+            model: conversation.model.clone(),
+            created_at: conversation.created_at,
+        }
+    }
+}
+
+/// A prompt, either as text alone or with the content chunks of the agent protocol
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Prompt {
+    Text { text: String },
+    Args(SendPromptArgs),
+}
+
+impl From<Prompt> for SendPromptArgs {
+    fn from(prompt: Prompt) -> Self {
+        match prompt {
+            Prompt::Text { text } => SendPromptArgs {
+                content: vec![text.into()],
+                should_continue_turn: None,
+            },
+            Prompt::Args(args) => args,
+        }
+    }
+}
+
+/// Handles a request, whose body is generic so the routes can be tested without a connection.
+pub async fn handle<B>(state: Arc<State>, req: Request<B>) -> ApiResponse
+where
+    B: Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    match route(state, req).await {
+        Ok(response) => response,
+        Err(err) => json(err.status, &serde_json::json!({ "error": err.message })),
+    }
+}
+
+async fn route<B>(state: Arc<State>, req: Request<B>) -> Result<ApiResponse, ApiError>
+where
+    B: Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let path = req.uri().path().trim_end_matches('/').to_string();
+    let segments = path.split('/').skip(1).collect::<Vec<_>>();
+    let method = req.method().clone();
+
+    if let (&Method::GET, ["v1", "health"]) = (&method, segments.as_slice()) {
+        return Ok(json(StatusCode::OK, &serde_json::json!({ "status": "ok" })));
+    }
+    authorize(&state, &req)?;
+
+    match (&method, segments.as_slice()) {
+        (&Method::GET, ["v1", "conversations"]) => {
+            let conversations = state.conversations.read().await;
+            let mut summaries = conversations
+                .values()
+                .map(|c| ConversationSummary::from(c.as_ref()))
+                .collect::<Vec<_>>();
+            summaries.sort_by_key(|summary| summary.created_at);
+            Ok(json(StatusCode::OK, &summaries))
+        },
+        (&Method::POST, ["v1", "conversations"]) => {
+            let body = read_body(req).await?;
+            let args = if body.is_empty() {
+                CreateConversation::default()
+            } else {
+                parse_json::<CreateConversation>(&body)?
+            };
+            let id = Uuid::new_v4();
+            let model_name = args.model.or_else(|| state.default_model.clone());
+            let journal = state.journal_dir.as_ref().map(|dir| dir.join(format!("{id}.json")));
+            let agent_config = args.agent_config.unwrap_or_default();
+            let conversation = Conversation::new(id, model_name, &state, agent_config, journal)
+                .await
+                .map_err(|err| {
+                    error!(?err, "failed to create a conversation");
+                    ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("{err:#}"))
+                })?;
+            let summary = ConversationSummary::from(&conversation);
+            state.conversations.write().await.insert(id, Arc::new(conversation));
+            Ok(json(StatusCode::CREATED, &summary))
+        },
+        (&Method::GET, ["v1", "conversations", id]) => {
+            let snapshot = state.conversation(id).await?.agent.create_snapshot().await?;
+            Ok(json(StatusCode::OK, &snapshot))
+        },
+        (&Method::DELETE, ["v1", "conversations", id]) => {
+            let conversation = state.conversation(id).await?;
+            state.conversations.write().await.remove(&conversation.id);
+            conversation.agent.cancel().await.ok();
+            Ok(empty(StatusCode::NO_CONTENT))
+        },
+        (&Method::POST, ["v1", "conversations", id, "prompts"]) => {
+            let conversation = state.conversation(id).await?;
+            let prompt = parse_json::<Prompt>(&read_body(req).await?)?;
+            conversation.agent.send_prompt(prompt.into()).await?;
+            Ok(empty(StatusCode::ACCEPTED))
+        },
+        (&Method::POST, ["v1", "conversations", id, "approvals"]) => {
+            let conversation = state.conversation(id).await?;
+            let args = parse_json::<SendApprovalResultArgs>(&read_body(req).await?)?;
+            conversation.agent.send_tool_use_approval_result(args).await?;
+            Ok(empty(StatusCode::NO_CONTENT))
+        },
+        (&Method::POST, ["v1", "conversations", id, "cancel"]) => {
+            state.conversation(id).await?.agent.cancel().await?;
+            Ok(empty(StatusCode::NO_CONTENT))
+        },
+        (&Method::GET, ["v1", "conversations", id, "events"]) => {
+            let conversation = state.conversation(id).await?;
+            // Browsers send the id of the last event they received when they reconnect.
+            let after = query_param(&req, "after")
+                .or_else(|| req.headers().get("last-event-id").and_then(|v| v.to_str().ok()))
+                .map(|after| {
+                    after
+                        .parse::<u64>()
+                        .ok()
+                        .filter(|after| *after <= conversation.events.latest())
+                        .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, format!("no event with id {after}")))
+                })
+                .transpose()?;
+            Ok(events(&conversation, after))
+        },
+        (&Method::POST, ["v1", "chat", "completions"]) if state.openai_models.is_some() => {
+            let response = match parse_json(&read_body(req).await?) {
+                Ok(request) => openai::chat_completions(&state, request).await,
+                Err(err) => Err(err),
+            };
+            Ok(response.unwrap_or_else(openai::error_response))
+        },
+        (&Method::GET, ["v1", "models"]) if state.openai_models.is_some() => Ok(openai::models(&state)),
+        _ => Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            format!("no endpoint for {method} {path}"),
+        )),
+    }
+}
+
+/// Checks the bearer token, which is also accepted in the `access_token` query parameter for
+/// clients that can't set headers, such as a browser's `EventSource`.
+fn authorize<B>(state: &State, req: &Request<B>) -> Result<(), ApiError> {
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or_else(|| query_param(req, "access_token"));
+    match token {
+        Some(token) if constant_time_eq(token.as_bytes(), state.token.as_bytes()) => Ok(()),
+        _ => Err(ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            "missing or invalid bearer token",
+        )),
+    }
+}
+
+fn query_param<'a, B>(req: &'a Request<B>, name: &str) -> Option<&'a str> {
+    req.uri()
+        .query()?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find_map(|(key, value)| (key == name).then_some(value))
+}
+
+/// Compares without returning early, so the time taken doesn't reveal how much of a guessed token
+/// is right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Streams the events of the conversation as server-sent events, each with its id in the event
+/// log. The stream starts after the event with the id `after`, or with the next event.
+///
+/// Events that were dropped from the log before they could be sent are reported by a `gap` event
+/// with the number of events missed.
+fn events(conversation: &Arc<Conversation>, after: Option<u64>) -> ApiResponse {
+    let cursor = after.unwrap_or_else(|| conversation.events.latest());
+    let latest = conversation.events.subscribe();
+    // The stream only holds a weak reference, so it ends once the conversation is deleted.
+    let conversation = Arc::downgrade(conversation);
+    let stream = futures::stream::unfold(
+        (conversation, latest, cursor),
+        |(conversation, mut latest, cursor): (Weak<Conversation>, watch::Receiver<u64>, u64)| async move {
+            loop {
+                let events = conversation.upgrade()?.events.after(cursor);
+                if let (Some(first), Some(last)) = (events.first(), events.last()) {
+                    let mut data = String::new();
+                    if first.id > cursor + 1 {
+                        data.push_str(&format!(
+                            "event: gap\ndata: {{\"missed\":{}}}\n\n",
+                            first.id - cursor - 1
+                        ));
+                    }
+                    let cursor = last.id;
+                    for event in events {
+                        let json = serde_json::to_string(&event.event).expect("events serialize");
+                        data.push_str(&format!("id: {}\ndata: {json}\n\n", event.id));
+                    }
+                    let frame = Frame::data(Bytes::from(data));
+                    return Some((Ok::<_, Infallible>(frame), (conversation, latest, cursor)));
+                }
+
+                match tokio::time::timeout(KEEP_ALIVE_INTERVAL, latest.changed()).await {
+                    Ok(Ok(())) => continue,
+                    Ok(Err(_)) => return None,
+                    Err(_) => {
+                        let frame = Frame::data(Bytes::from_static(b": keep-alive\n\n"));
+                        return Some((Ok(frame), (conversation, latest, cursor)));
+                    },
+                }
+            }
+        },
+    );
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/event-stream")
+        .header(header::CACHE_CONTROL, "no-cache")
+        .body(StreamBody::new(stream).boxed_unsync())
+        .expect("valid builder will not panic")
+}
+
+async fn read_body<B>(req: Request<B>) -> Result<Bytes, ApiError>
+where
+    B: Body<Data = Bytes>,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    match Limited::new(req.into_body(), MAX_BODY_LEN).collect().await {
+        Ok(body) => Ok(body.to_bytes()),
+        Err(err) if err.downcast_ref::<LengthLimitError>().is_some() => Err(ApiError::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("request bodies are limited to {MAX_BODY_LEN} bytes"),
+        )),
+        Err(err) => Err(ApiError::new(StatusCode::BAD_REQUEST, err.to_string())),
+    }
+}
+
+fn parse_json<T: DeserializeOwned>(body: &[u8]) -> Result<T, ApiError> {
+    serde_json::from_slice(body).map_err(|err| ApiError::new(StatusCode::BAD_REQUEST, format!("invalid body: {err}")))
+}
+
+pub(super) fn json(status: StatusCode, value: &impl Serialize) -> ApiResponse {
+    let body = serde_json::to_vec(value).expect("responses serialize");
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(body)).boxed_unsync())
+        .expect("valid builder will not panic")
+}
+
+fn empty(status: StatusCode) -> ApiResponse {
+    Response::builder()
+        .status(status)
+        .body(Full::new(Bytes::new()).boxed_unsync())
+        .expect("valid builder will not panic")
+}
+
+#[cfg(test)]
+mod tests {
+    use agent::agent_loop::model::MockModel;
+    use agent::agent_loop::protocol::StreamResult;
+    use agent::agent_loop::types::{
+        ContentBlockDelta,
+        ContentBlockDeltaEvent,
+        MessageStartEvent,
+        MessageStopEvent,
+        Role,
+        StopReason,
+        StreamEvent,
+    };
+    use agent::journal::Journal;
+    use agent::protocol::AgentEvent;
+
+    use super::*;
+
+    const TOKEN: &str = "test-token";
+
+    fn state() -> Arc<State> {
+        let mut models = ModelProviderRegistry::new();
+        models.register("mock", MockModel::new());
+        Arc::new(State::new(TOKEN.to_string(), Some("default-model".to_string()), models))
+    }
+
+    async fn send(state: &Arc<State>, method: Method, path: &str, token: Option<&str>, body: &str) -> ApiResponse {
+        let mut builder = Request::builder().method(method).uri(path);
+        if let Some(token) = token {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let req = builder.body(Full::new(Bytes::from(body.to_string()))).unwrap();
+        handle(Arc::clone(state), req).await
+    }
+
+    async fn body_json(response: ApiResponse) -> serde_json::Value {
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_auth() {
+        let state = state();
+        let response = send(&state, Method::GET, "/v1/health", None, "").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        for token in [None, Some("wrong-token"), Some("test-toke")] {
+            let response = send(&state, Method::GET, "/v1/conversations", token, "").await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+        let response = send(&state, Method::GET, "/v1/conversations", Some(TOKEN), "").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_json(response).await, serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_openai_compat_routes() {
+        let state = state();
+        let response = send(&state, Method::GET, "/v1/models", Some(TOKEN), "").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let state = Arc::new(
+            Arc::into_inner(state)
+                .unwrap()
+                .with_openai_compat(vec!["model-1".to_string()]),
+        );
+        let response = send(&state, Method::GET, "/v1/models", Some(TOKEN), "").await;
+        assert_eq!(body_json(response).await["data"][0]["id"], "model-1");
+
+        let body = r#"{"model":"model-1","messages":[{"role":"assistant","content":"Hi"}]}"#;
+        let response = send(&state, Method::POST, "/v1/chat/completions", Some(TOKEN), body).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(body_json(response).await["error"]["message"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_conversations() {
+        let state = state();
+        let response = send(&state, Method::POST, "/v1/conversations", Some(TOKEN), "").await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let created = body_json(response).await;
+        assert_eq!(created["model"], "default-model");
+        let id = created["id"].as_str().unwrap().to_string();
+
+        let response = send(&state, Method::GET, "/v1/conversations/", Some(TOKEN), "").await;
+        assert_eq!(body_json(response).await[0]["id"], id.as_str());
+
+        let path = format!("/v1/conversations/{id}");
+        let response = send(&state, Method::GET, &path, Some(TOKEN), "").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send(&state, Method::POST, &format!("{path}/prompts"), Some(TOKEN), "{}").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        for unknown in [
+            "/v1/conversations/not-a-uuid",
+            &format!("/v1/conversations/{}", Uuid::new_v4()),
+        ] {
+            let response = send(&state, Method::GET, unknown, Some(TOKEN), "").await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+
+        let response = send(&state, Method::DELETE, &path, Some(TOKEN), "").await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let response = send(&state, Method::GET, &path, Some(TOKEN), "").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    /// Creates a conversation and waits for its agent to initialize, so the events the tests push
+    /// aren't interleaved with the agent's.
+    async fn create_conversation(state: &Arc<State>) -> (String, Arc<Conversation>) {
+        let response = send(state, Method::POST, "/v1/conversations", Some(TOKEN), "").await;
+        let id = body_json(response).await["id"].as_str().unwrap().to_string();
+        let conversation = state.conversation(&id).await.unwrap();
+        let mut latest = conversation.events.subscribe();
+        tokio::time::timeout(Duration::from_secs(5), latest.wait_for(|id| *id > 0))
+            .await
+            .unwrap()
+            .unwrap();
+        (id, conversation)
+    }
+
+    #[tokio::test]
+    async fn test_journal() {
+        let response = vec![
+            StreamResult::Ok(StreamEvent::MessageStart(MessageStartEvent { role: Role::Assistant })),
+            StreamResult::Ok(StreamEvent::ContentBlockDelta(ContentBlockDeltaEvent {
+                delta: ContentBlockDelta::Text("Hi".to_string()),
+                content_block_index: None,
+            })),
+            StreamResult::Ok(StreamEvent::MessageStop(MessageStopEvent {
+                stop_reason: StopReason::EndTurn,
+            })),
+        ];
+        let mut models = ModelProviderRegistry::new();
+        models.register("mock", MockModel::new().with_response(response));
+        let dir = tempfile::tempdir().unwrap();
+        let state = Arc::new(
+            State::new(TOKEN.to_string(), Some("default-model".to_string()), models)
+                .with_journal_dir(dir.path().to_path_buf()),
+        );
+        let (id, conversation) = create_conversation(&state).await;
+
+        let path = format!("/v1/conversations/{id}/prompts");
+        let response = send(&state, Method::POST, &path, Some(TOKEN), r#"{"text":"Hello"}"#).await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        // The agent saves the journal before it emits the event stopping the turn.
+        let mut latest = conversation.events.subscribe();
+        let stopped = |_: &u64| {
+            conversation
+                .events
+                .after(0)
+                .iter()
+                .any(|logged| matches!(logged.event, AgentEvent::Stop(_)))
+        };
+        tokio::time::timeout(Duration::from_secs(5), latest.wait_for(stopped))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let journal = Journal::load(dir.path().join(format!("{id}.json"))).await.unwrap();
+        assert_eq!(journal.turns.len(), 1);
+        assert_eq!(journal.turns[0].responses.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_events() {
+        let state = state();
+        let (id, conversation) = create_conversation(&state).await;
+        let latest = conversation.events.latest();
+
+        let path = format!("/v1/conversations/{id}/events");
+        let response = send(&state, Method::GET, &path, Some(TOKEN), "").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/event-stream");
+        conversation.events.push(AgentEvent::Initialized);
+        drop(conversation);
+
+        let mut body = response.into_body();
+        let frame = body.frame().await.unwrap().unwrap().into_data().unwrap();
+        let frame = String::from_utf8(frame.to_vec()).unwrap();
+        assert_eq!(
+            frame,
+            format!("id: {}\ndata: {{\"kind\":\"initialized\"}}\n\n", latest + 1)
+        );
+
+        // Deleting the conversation ends the stream.
+        send(
+            &state,
+            Method::DELETE,
+            &format!("/v1/conversations/{id}"),
+            Some(TOKEN),
+            "",
+        )
+        .await;
+        assert!(
+            tokio::time::timeout(Duration::from_secs(5), body.frame())
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    async fn first_frame(response: ApiResponse) -> String {
+        let frame = response
+            .into_body()
+            .frame()
+            .await
+            .unwrap()
+            .unwrap()
+            .into_data()
+            .unwrap();
+        String::from_utf8(frame.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_resume_events() {
+        let state = state();
+        let (id, conversation) = create_conversation(&state).await;
+        let resume_from = conversation.events.latest();
+        for _ in 0..2 {
+            conversation.events.push(AgentEvent::Initialized);
+        }
+        let path = format!("/v1/conversations/{id}/events");
+
+        let response = send(
+            &state,
+            Method::GET,
+            &format!("{path}?after={resume_from}"),
+            Some(TOKEN),
+            "",
+        )
+        .await;
+        let frame = first_frame(response).await;
+        assert!(frame.starts_with(&format!("id: {}\n", resume_from + 1)), "{frame}");
+        assert!(frame.contains(&format!("id: {}\n", resume_from + 2)), "{frame}");
+
+        // Browsers authenticate with a query parameter and resume with a header.
+        let req = Request::builder()
+            .uri(format!("{path}?access_token={TOKEN}"))
+            .header("last-event-id", (resume_from + 1).to_string())
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        let frame = first_frame(handle(Arc::clone(&state), req).await).await;
+        assert!(frame.starts_with(&format!("id: {}\n", resume_from + 2)), "{frame}");
+
+        for invalid in ["not-a-number", "100000"] {
+            let response = send(&state, Method::GET, &format!("{path}?after={invalid}"), Some(TOKEN), "").await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+
+        // Events dropped from the log are reported before the rest.
+        for _ in 0..5000 {
+            conversation.events.push(AgentEvent::Initialized);
+        }
+        let response = send(
+            &state,
+            Method::GET,
+            &format!("{path}?after={resume_from}"),
+            Some(TOKEN),
+            "",
+        )
+        .await;
+        let frame = first_frame(response).await;
+        let missed = conversation.events.after(resume_from)[0].id - resume_from - 1;
+        assert!(
+            frame.starts_with(&format!("event: gap\ndata: {{\"missed\":{missed}}}\n\n")),
+            "{frame}"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{
+    Arc,
+    Mutex,
+};
+
+use agent::agent_config::definitions::AgentConfig;
+use agent::mcp::McpManager;
+use agent::protocol::AgentEvent;
+use agent::types::AgentSnapshot;
+use agent::{
+    Agent,
+    AgentHandle,
+};
+use eyre::Result;
+use serde::Serialize;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use super::api::State;
+
+/// Most events kept per conversation. Older events are dropped from the log.
+const EVENT_LOG_CAPACITY: usize = 4096;
+
+/// An agent hosted by the server, and the log of the events it emitted
+#[derive(Debug)]
+pub struct Conversation {
+    pub id: Uuid,
+    pub model: Option<String>,
+    /// Unix timestamp the conversation was created at
+    pub created_at: i64,
+    /// Only sends requests, the events are received by `relay`
+    pub agent: AgentHandle,
+    pub events: Arc<EventLog>,
+    relay: JoinHandle<()>,
+}
+
+impl Conversation {
+    pub async fn new(
+        id: Uuid,
+        model_name: Option<String>,
+        state: &State,
+        mut agent_config: AgentConfig,
+        journal: Option<PathBuf>,
+    ) -> Result<Self> {
+        if let Some(model_id) = &model_name {
+            agent_config.set_model_id(model_id.clone());
+        }
+        let model = state.models.create(agent_config.model(), None)?;
+        let mut snapshot = AgentSnapshot::new_empty(agent_config);
+        snapshot.settings.mcp_enabled = state.mcp_enabled;
+        let mut agent = Agent::new(snapshot, model, McpManager::new().spawn()).await?;
+        if let Some(path) = journal {
+            agent.record_journal(path);
+        }
+        let mut agent = agent.spawn();
+        let mut receiver = agent.take_events();
+        let events = Arc::new(EventLog::new(EVENT_LOG_CAPACITY));
+        let relay = tokio::spawn({
+            let events = Arc::clone(&events);
+            async move {
+                while let Ok(event) = receiver.recv().await {
+                    events.push(event);
+                }
+            }
+        });
+
+        Ok(Self {
+            id,
+            model: model_name,
+            created_at: time::OffsetDateTime::now_utc().unix_timestamp(),
+            agent,
+            events,
+            relay,
+        })
+    }
+}
+
+impl Drop for Conversation {
+    fn drop(&mut self) {
+        self.relay.abort();
+    }
+}
+
+/// An event and its id, which increases by one with every event of a conversation
+#[derive(Debug, Clone, Serialize)]
+pub struct LoggedEvent {
+    pub id: u64,
+    pub event: AgentEvent,
+}
+
+/// The latest events of a conversation, so streams can catch up on what they missed
+#[derive(Debug)]
+pub struct EventLog {
+    events: Mutex<VecDeque<LoggedEvent>>,
+    capacity: usize,
+    /// Id of the latest event, 0 before the first
+    latest: watch::Sender<u64>,
+}
+
+impl EventLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: Mutex::new(VecDeque::new()),
+            capacity,
+            latest: watch::Sender::new(0),
+        }
+    }
+
+    pub fn push(&self, event: AgentEvent) {
+        let Ok(mut events) = self.events.lock() else {
+            return;
+        };
+        let id = *self.latest.borrow() + 1;
+        events.push_back(LoggedEvent { id, event });
+        if events.len() > self.capacity {
+            events.pop_front();
+        }
+        drop(events);
+        self.latest.send_replace(id);
+    }
+
+    /// The logged events with an id greater than `after`, oldest first
+    pub fn after(&self, after: u64) -> Vec<LoggedEvent> {
+        let Ok(events) = self.events.lock() else {
+            return Vec::new();
+        };
+        let start = events.partition_point(|event| event.id <= after);
+        events.range(start..).cloned().collect()
+    }
+
+    pub fn latest(&self) -> u64 {
+        *self.latest.borrow()
+    }
+
+    /// Notifies of every new event through the id of the latest one
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.latest.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_log() {
+        let log = EventLog::new(3);
+        assert_eq!(log.latest(), 0);
+        assert!(log.after(0).is_empty());
+
+        for _ in 0..5 {
+            log.push(AgentEvent::Initialized);
+        }
+        assert_eq!(log.latest(), 5);
+        let ids = |events: Vec<LoggedEvent>| events.into_iter().map(|e| e.id).collect::<Vec<_>>();
+        assert_eq!(ids(log.after(0)), vec![3, 4, 5]);
+        assert_eq!(ids(log.after(4)), vec![5]);
+        assert!(log.after(5).is_empty());
+    }
+}
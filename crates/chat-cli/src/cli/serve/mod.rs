@@ -0,0 +1,141 @@
+//! A local HTTP+JSON API over the agent runtime, for web UIs, scripts in other languages and QA
+//! tooling. The API is documented in `docs/serve.md`.
+
+mod api;
+mod conversation;
+mod openai;
+
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use clap::Args;
+use eyre::{
+    Result,
+    WrapErr,
+    bail,
+};
+use hyper::server::conn::http1;
+use hyper_util::rt::TokioIo;
+use rand::Rng;
+use tokio::net::TcpListener;
+use tokio::signal::ctrl_c;
+use tracing::{
+    debug,
+    error,
+    warn,
+};
+
+use crate::agent::rts::model_providers;
+use crate::os::Os;
+
+/// Environment variable with the token clients authenticate with. A random token is generated
+/// when it isn't set.
+const TOKEN_ENV_VAR: &str = "Q_SERVE_TOKEN";
+
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct ServeArgs {
+    /// Loopback address and port to listen on
+    #[arg(long, default_value = "127.0.0.1:8400")]
+    pub listen: SocketAddr,
+    /// Model of the conversations that don't request one
+    #[arg(long)]
+    pub model: Option<String>,
+    /// Also serve OpenAI compatible `/v1/chat/completions` and `/v1/models` endpoints
+    #[arg(long)]
+    pub openai_compat: bool,
+    /// Record each conversation into `<DIR>/<ID>.json`, which `q debug replay` can replay
+    #[arg(long, value_name = "DIR")]
+    pub journal_dir: Option<PathBuf>,
+}
+
+impl ServeArgs {
+    pub async fn execute(self, os: &Os) -> Result<ExitCode> {
+        // Tokens are sent in plain text, so only clients on this machine may connect.
+        if !self.listen.ip().is_loopback() {
+            bail!("{} is not a loopback address", self.listen.ip());
+        }
+
+        let mut stderr = std::io::stderr();
+        let token = match os.env.get(TOKEN_ENV_VAR) {
+            Ok(token) if !token.is_empty() => token,
+            _ => {
+                let token = URL_SAFE_NO_PAD.encode(rand::rng().random::<[u8; 32]>());
+                writeln!(stderr, "Token: {token}")?;
+                token
+            },
+        };
+
+        let listener = TcpListener::bind(self.listen)
+            .await
+            .wrap_err_with(|| format!("failed to listen on {}", self.listen))?;
+        writeln!(stderr, "Listening on http://{}", listener.local_addr()?)?;
+
+        let mut state = api::State::new(token, self.model, model_providers(os.client.clone()));
+        match os.client.is_mcp_enabled().await {
+            Ok(true) => (),
+            Ok(false) => {
+                writeln!(stderr, "MCP functionality has been disabled by your administrator")?;
+                state = state.with_mcp_disabled();
+            },
+            Err(err) => warn!(?err, "Failed to check MCP configuration, defaulting to enabled"),
+        }
+        if self.openai_compat {
+            let models = match os.client.list_available_models_cached().await {
+                Ok(result) => result.models.iter().map(|model| model.model_id().to_string()).collect(),
+                Err(err) => {
+                    warn!(?err, "failed to list the available models");
+                    Vec::new()
+                },
+            };
+            state = state.with_openai_compat(models);
+            writeln!(
+                stderr,
+                "Serving OpenAI compatible endpoints at http://{}/v1",
+                listener.local_addr()?
+            )?;
+        }
+        if let Some(dir) = self.journal_dir {
+            os.fs
+                .create_dir_all(&dir)
+                .await
+                .wrap_err_with(|| format!("failed to create {}", dir.display()))?;
+            state = state.with_journal_dir(dir);
+        }
+        let state = Arc::new(state);
+
+        loop {
+            let stream = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, addr)) => {
+                        debug!(?addr, "accepted a connection");
+                        stream
+                    },
+                    Err(err) => {
+                        error!(?err, "failed to accept a connection");
+                        continue;
+                    },
+                },
+                _ = ctrl_c() => break,
+            };
+
+            let service = api::ApiService {
+                state: Arc::clone(&state),
+            };
+            tokio::spawn(async move {
+                if let Err(err) = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    debug!(?err, "error serving a connection");
+                }
+            });
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
@@ -0,0 +1,454 @@
+//! An OpenAI compatible facade, so tools built for `/v1/chat/completions` can talk to the agent.
+//!
+//! Every request carries the whole conversation, which is restored into a new agent that runs
+//! without tools. The agent's streamed text becomes the deltas of the completion.
+
+use std::collections::HashSet;
+use std::convert::Infallible;
+
+use agent::agent_config::definitions::{
+    AgentConfig,
+    AgentConfigV2025_08_22,
+    InferenceParams,
+};
+use agent::agent_loop::types::{
+    ContentBlock,
+    Message,
+    Role,
+};
+use agent::mcp::McpManager;
+use agent::protocol::{
+    AgentEvent,
+    AgentStopReason,
+    ApprovalResult,
+    ContentChunk,
+    SendApprovalResultArgs,
+    SendPromptArgs,
+    UpdateEvent,
+};
+use agent::types::AgentSnapshot;
+use agent::{
+    Agent,
+    AgentHandle,
+};
+use bytes::Bytes;
+use futures::StreamExt;
+use http::{
+    Response,
+    StatusCode,
+    header,
+};
+use http_body_util::{
+    BodyExt,
+    StreamBody,
+};
+use hyper::body::Frame;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{
+    debug,
+    error,
+};
+use uuid::Uuid;
+
+use super::api::{
+    ApiError,
+    ApiResponse,
+    State,
+    json,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct ChatCompletionRequest {
+    model: String,
+    messages: Vec<ChatMessage>,
+    #[serde(default)]
+    stream: bool,
+    /// Applied when the server's model supports them
+    #[serde(default)]
+    temperature: Option<f32>,
+    #[serde(default)]
+    top_p: Option<f32>,
+    #[serde(default, alias = "max_completion_tokens")]
+    max_tokens: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatMessage {
+    role: String,
+    #[serde(default)]
+    content: Option<MessageContent>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentPart {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: Option<String>,
+}
+
+impl MessageContent {
+    fn into_text(self) -> Result<String, ApiError> {
+        match self {
+            MessageContent::Text(text) => Ok(text),
+            MessageContent::Parts(parts) => parts
+                .into_iter()
+                .map(|part| match (part.kind.as_str(), part.text) {
+                    ("text", Some(text)) => Ok(text),
+                    (kind, _) => Err(ApiError::new(
+                        StatusCode::BAD_REQUEST,
+                        format!("content parts of type {kind} aren't supported"),
+                    )),
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map(|texts| texts.join("\n")),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ChatCompletion {
+    id: String,
+    object: &'static str,
+    created: i64,
+    model: String,
+    choices: Vec<Choice>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum Choice {
+    Message {
+        index: u32,
+        message: ChoiceMessage,
+        finish_reason: &'static str,
+    },
+    Delta {
+        index: u32,
+        delta: ChoiceDelta,
+        finish_reason: Option<&'static str>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct ChoiceMessage {
+    role: &'static str,
+    content: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct ChoiceDelta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+}
+
+/// What the agent produced next
+#[derive(Debug)]
+enum Delta {
+    Content(String),
+    /// The turn ended, with the `finish_reason` of the completion
+    Finish(&'static str),
+    Error(String),
+}
+
+pub async fn chat_completions(state: &State, request: ChatCompletionRequest) -> Result<ApiResponse, ApiError> {
+    let (system_prompt, history, prompt) = into_conversation(request.messages)?;
+    // Clients often send a fixed model name, which falls back to the server's model.
+    let model_id = state
+        .openai_models
+        .as_ref()
+        .is_some_and(|models| models.contains(&request.model))
+        .then(|| request.model.clone())
+        .or_else(|| state.default_model.clone());
+
+    let mut agent_config = AgentConfig::V2025_08_22(AgentConfigV2025_08_22 {
+        system_prompt,
+        tools: Vec::new(),
+        resources: Vec::new(),
+        allowed_tools: HashSet::new(),
+        ..Default::default()
+    });
+    if let Some(model_id) = model_id {
+        agent_config.set_model_id(model_id);
+    }
+    let model = state.models.create(agent_config.model(), None).map_err(|err| {
+        error!(?err, "failed to create a model");
+        ApiError::new(StatusCode::BAD_REQUEST, err.to_string())
+    })?;
+    let mut snapshot = AgentSnapshot::new_empty(agent_config);
+    snapshot.settings.mcp_enabled = state.mcp_enabled;
+    snapshot.conversation_state.messages = history;
+    let inference_params = InferenceParams {
+        temperature: request.temperature,
+        top_p: request.top_p,
+        max_tokens: request.max_tokens,
+    };
+    // Clients often send parameters by default, so they are dropped rather than refused when the
+    // backend doesn't take them
+    if model.supports_inference_params() {
+        snapshot.conversation_state.inference_params = inference_params;
+    } else if !inference_params.is_empty() {
+        debug!(%inference_params, "ignoring inference parameters, which the backend doesn't support");
+    }
+    let id = Uuid::new_v4();
+    let agent = Agent::new(snapshot, model, McpManager::new().spawn())
+        .await
+        .map_err(|err| {
+            error!(?err, "failed to create an agent");
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("{err:#}"))
+        })?
+        .spawn();
+    agent
+        .send_prompt(SendPromptArgs {
+            content: vec![prompt.into()],
+            should_continue_turn: None,
+        })
+        .await?;
+
+    let completion = ChatCompletion {
+        id: format!("chatcmpl-{id}"),
+        object: "chat.completion",
+        created: time::OffsetDateTime::now_utc().unix_timestamp(),
+        model: request.model,
+        choices: Vec::new(),
+    };
+    if request.stream {
+        Ok(stream_completion(agent, completion))
+    } else {
+        complete(agent, completion).await
+    }
+}
+
+/// Lists the models the requests can choose from
+pub fn models(state: &State) -> ApiResponse {
+    let data = state
+        .openai_models
+        .iter()
+        .flatten()
+        .map(|id| serde_json::json!({ "id": id, "object": "model", "created": 0, "owned_by": "amazon-q" }))
+        .collect::<Vec<_>>();
+    json(StatusCode::OK, &serde_json::json!({ "object": "list", "data": data }))
+}
+
+/// Formats errors like OpenAI's API, whose clients look for `error.message`.
+pub fn error_response(err: ApiError) -> ApiResponse {
+    let kind = if err.status.is_client_error() {
+        "invalid_request_error"
+    } else {
+        "api_error"
+    };
+    json(
+        err.status,
+        &serde_json::json!({ "error": { "message": err.message, "type": kind } }),
+    )
+}
+
+/// Splits the messages into the system prompt, the history and the prompt, which is the text of
+/// the last message and must be from the user. Consecutive messages from the same role are joined.
+fn into_conversation(messages: Vec<ChatMessage>) -> Result<(Option<String>, Vec<Message>, String), ApiError> {
+    let mut system = Vec::new();
+    let mut turns: Vec<(Role, String)> = Vec::new();
+    for message in messages {
+        let text = message
+            .content
+            .map(MessageContent::into_text)
+            .transpose()?
+            .unwrap_or_default();
+        let role = match message.role.as_str() {
+            "system" | "developer" => {
+                system.push(text);
+                continue;
+            },
+            "user" => Role::User,
+            "assistant" => Role::Assistant,
+            other => {
+                return Err(ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("messages with the role {other} aren't supported, conversations run without tools"),
+                ));
+            },
+        };
+        match turns.last_mut() {
+            Some((last, joined)) if *last == role => {
+                joined.push('\n');
+                joined.push_str(&text);
+            },
+            _ => turns.push((role, text)),
+        }
+    }
+
+    let prompt = match turns.pop() {
+        Some((Role::User, prompt)) => prompt,
+        _ => {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "the last message must be from the user",
+            ));
+        },
+    };
+    let history = turns
+        .into_iter()
+        .map(|(role, text)| Message::new(role, vec![ContentBlock::Text(text)], None))
+        .collect();
+    let system_prompt = (!system.is_empty()).then(|| system.join("\n"));
+    Ok((system_prompt, history, prompt))
+}
+
+async fn next_delta(agent: &mut AgentHandle) -> Delta {
+    loop {
+        match agent.recv().await {
+            Ok(AgentEvent::Update(UpdateEvent::AgentContent(ContentChunk::Text(text)))) => return Delta::Content(text),
+            // The agent has no tools, but deny anything it asks for rather than waiting forever.
+            Ok(AgentEvent::ApprovalRequest { id, .. }) => {
+                let result = ApprovalResult::Deny {
+                    reason: Some("tools are unavailable".to_string()),
+                };
+                agent
+                    .send_tool_use_approval_result(SendApprovalResultArgs { id, result })
+                    .await
+                    .ok();
+            },
+            Ok(AgentEvent::Stop(AgentStopReason::EndTurn)) => return Delta::Finish("stop"),
+            Ok(AgentEvent::Stop(AgentStopReason::MaxTurnRequests)) => return Delta::Finish("length"),
+            Ok(AgentEvent::Stop(AgentStopReason::Cancelled)) => {
+                return Delta::Error("the turn was cancelled".to_string());
+            },
+            Ok(AgentEvent::Stop(AgentStopReason::Error(err))) => return Delta::Error(err.to_string()),
+            Ok(_) => {},
+            Err(err) => return Delta::Error(err.to_string()),
+        }
+    }
+}
+
+async fn complete(mut agent: AgentHandle, mut completion: ChatCompletion) -> Result<ApiResponse, ApiError> {
+    let mut content = String::new();
+    let finish_reason = loop {
+        match next_delta(&mut agent).await {
+            Delta::Content(text) => content.push_str(&text),
+            Delta::Finish(reason) => break reason,
+            Delta::Error(message) => return Err(ApiError::new(StatusCode::BAD_GATEWAY, message)),
+        }
+    };
+    completion.choices.push(Choice::Message {
+        index: 0,
+        message: ChoiceMessage {
+            role: "assistant",
+            content,
+        },
+        finish_reason,
+    });
+    Ok(json(StatusCode::OK, &completion))
+}
+
+/// Streams the completion as server-sent chunks, ending with `[DONE]`. The agent runs on a task
+/// that stops once the client disconnects.
+fn stream_completion(mut agent: AgentHandle, completion: ChatCompletion) -> ApiResponse {
+    let (tx, rx) = mpsc::channel::<String>(16);
+    tokio::spawn(async move {
+        let chunk = |delta: ChoiceDelta, finish_reason: Option<&'static str>| {
+            let chunk = ChatCompletion {
+                id: completion.id.clone(),
+                object: "chat.completion.chunk",
+                created: completion.created,
+                model: completion.model.clone(),
+                choices: vec![Choice::Delta {
+                    index: 0,
+                    delta,
+                    finish_reason,
+                }],
+            };
+            serde_json::to_string(&chunk).expect("chunks serialize")
+        };
+
+        let role = ChoiceDelta {
+            role: Some("assistant"),
+            ..Default::default()
+        };
+        if tx.send(chunk(role, None)).await.is_err() {
+            return;
+        }
+        loop {
+            let data = match next_delta(&mut agent).await {
+                Delta::Content(text) => chunk(
+                    ChoiceDelta {
+                        content: Some(text),
+                        ..Default::default()
+                    },
+                    None,
+                ),
+                Delta::Finish(reason) => {
+                    tx.send(chunk(ChoiceDelta::default(), Some(reason))).await.ok();
+                    break;
+                },
+                Delta::Error(message) => {
+                    let error = serde_json::json!({ "error": { "message": message, "type": "api_error" } });
+                    tx.send(error.to_string()).await.ok();
+                    break;
+                },
+            };
+            if tx.send(data).await.is_err() {
+                return;
+            }
+        }
+        tx.send("[DONE]".to_string()).await.ok();
+    });
+
+    let stream =
+        ReceiverStream::new(rx).map(|data| Ok::<_, Infallible>(Frame::data(Bytes::from(format!("data: {data}\n\n")))));
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/event-stream")
+        .header(header::CACHE_CONTROL, "no-cache")
+        .body(StreamBody::new(stream).boxed_unsync())
+        .expect("valid builder will not panic")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(value: serde_json::Value) -> Vec<ChatMessage> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_into_conversation() {
+        let (system_prompt, history, prompt) = into_conversation(messages(serde_json::json!([
+            { "role": "system", "content": "Be brief." },
+            { "role": "user", "content": "Hi" },
+            { "role": "assistant", "content": "Hello!" },
+            { "role": "user", "content": "What is 2 + 2?" },
+            { "role": "user", "content": [{ "type": "text", "text": "Answer in words." }] },
+        ])))
+        .unwrap();
+        assert_eq!(system_prompt.as_deref(), Some("Be brief."));
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].role, Role::User);
+        assert_eq!(history[1].role, Role::Assistant);
+        assert_eq!(prompt, "What is 2 + 2?\nAnswer in words.");
+
+        for invalid in [
+            serde_json::json!([{ "role": "assistant", "content": "Hello!" }]),
+            serde_json::json!([{ "role": "tool", "content": "42" }, { "role": "user", "content": "Hi" }]),
+            serde_json::json!([{ "role": "user", "content": [{ "type": "image_url" }] }]),
+            serde_json::json!([]),
+        ] {
+            let err = into_conversation(messages(invalid)).unwrap_err();
+            assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        }
+    }
+}
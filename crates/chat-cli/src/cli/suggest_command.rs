@@ -0,0 +1,129 @@
+use std::io::{
+    BufRead,
+    IsTerminal,
+    Write,
+};
+use std::process::ExitCode;
+
+use clap::Args;
+use eyre::{
+    Result,
+    bail,
+};
+
+use crate::os::Os;
+
+/// Generates a shell command from a description and prints it to stdout without running it, so
+/// shell keybindings can insert it into the command line.
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct SuggestCommandArgs {
+    /// Description of the command, read from the terminal if omitted
+    pub request: Vec<String>,
+    /// Shell to write the command for, defaults to $SHELL
+    #[arg(long)]
+    pub shell: Option<String>,
+}
+
+impl SuggestCommandArgs {
+    pub async fn execute(self, os: &Os) -> Result<ExitCode> {
+        let mut request = self.request.join(" ");
+        if request.trim().is_empty() {
+            request = read_request()?;
+        }
+        if request.trim().is_empty() {
+            return Ok(ExitCode::FAILURE);
+        }
+
+        let shell = self
+            .shell
+            .or_else(|| os.env.get("SHELL").ok())
+            .and_then(|shell| shell.rsplit('/').next().map(str::to_string))
+            .unwrap_or_else(|| "sh".to_string());
+        let command = generate_command(os, request.trim(), &shell).await?;
+
+        let mut stdout = std::io::stdout();
+        writeln!(stdout, "{command}")?;
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Prompts for the description on the terminal. The prompt goes to stderr since stdout is
+/// captured by the shell keybinding.
+fn read_request() -> Result<String> {
+    let mut stderr = std::io::stderr();
+    if !std::io::stdin().is_terminal() {
+        bail!("Describe the command to generate, e.g. `suggest-command list files by size`");
+    }
+    write!(stderr, "Describe the command: ")?;
+    stderr.flush()?;
+    let mut request = String::new();
+    std::io::stdin().lock().read_line(&mut request)?;
+    Ok(request)
+}
+
+async fn generate_command(os: &Os, request: &str, shell: &str) -> Result<String> {
+    let cwd = os
+        .env
+        .current_dir()
+        .map(|d| d.display().to_string())
+        .unwrap_or_default();
+    let content = format!(
+        "Write a single {shell} command for {} that does the following: {request}\n\n\
+        The current directory is {cwd}. Reply with the command only, without explanation or \
+        markdown. Prefer one line, and chain steps with && or pipes when needed.",
+        std::env::consts::OS,
+    );
+    let text = os.client.send_prompt(content, None).await?.text().await?;
+    let command = extract_command(&text);
+    if command.is_empty() {
+        bail!("No command was generated");
+    }
+    Ok(command)
+}
+
+/// Returns the command in a model response, removing any code fence and `$ ` prompt the model
+/// added despite being asked not to.
+pub fn extract_command(response: &str) -> String {
+    let response = response.trim();
+    let body = match response.split_once("```") {
+        Some((_, rest)) => {
+            let rest = rest.split_once("```").map_or(rest, |(block, _)| block);
+            // Drop the language of the fence, e.g. ```bash
+            rest.split_once('\n').map_or(rest, |(_, code)| code)
+        },
+        None => response,
+    };
+    let body = body.trim();
+    body.strip_prefix("$ ").unwrap_or(body).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_command() {
+        assert_eq!(extract_command("ls -lS\n"), "ls -lS");
+        assert_eq!(extract_command("$ du -sh *"), "du -sh *");
+        assert_eq!(
+            extract_command("Here you go:\n```bash\nfind . -name '*.rs' | xargs wc -l\n```\nThis counts lines."),
+            "find . -name '*.rs' | xargs wc -l"
+        );
+        assert_eq!(
+            extract_command("```\nfor f in *; do\n  echo $f\ndone\n```"),
+            "for f in *; do\n  echo $f\ndone"
+        );
+        assert_eq!(extract_command("  "), "");
+    }
+
+    #[tokio::test]
+    async fn test_generate_command() {
+        let mut os = Os::new().await.unwrap();
+        os.client
+            .set_mock_output(serde_json::json!([["```sh\n", "git log --oneline -5\n```"]]));
+        assert_eq!(
+            generate_command(&os, "show the last 5 commits", "zsh").await.unwrap(),
+            "git log --oneline -5"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use std::io::Write;
+use std::process::ExitCode;
+
+use clap::{
+    Args,
+    Subcommand,
+};
+use eyre::Result;
+use strum::IntoEnumIterator;
+
+use super::OutputFormat;
+use crate::database::settings::Setting;
+use crate::os::Os;
+use crate::telemetry::{
+    TelemetryCategory,
+    TelemetryConsent,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum TelemetrySubcommand {
+    /// Show which telemetry is collected and whether it is sent
+    Status {
+        /// Format of the output
+        #[arg(long, short, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Enable telemetry, or one category of it
+    Enable { category: Option<TelemetryCategory> },
+    /// Disable telemetry, or one category of it
+    Disable { category: Option<TelemetryCategory> },
+}
+
+impl TelemetrySubcommand {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        let mut stderr = std::io::stderr();
+        match self {
+            Self::Status { format } => {
+                let consent = TelemetryConsent::from_settings(&os.database.settings);
+                format.print(|| status_text(&consent), || consent.clone());
+            },
+            Self::Enable { category } | Self::Disable { category } => {
+                let enable = matches!(self, Self::Enable { .. });
+                let setting = category.map_or(Setting::TelemetryEnabled, TelemetryCategory::setting);
+                os.database.settings.set(setting, enable).await?;
+
+                let state = if enable { "Enabled" } else { "Disabled" };
+                match category {
+                    Some(category) => writeln!(stderr, "{state} {category} telemetry")?,
+                    None => writeln!(stderr, "{state} telemetry")?,
+                }
+                let consent = TelemetryConsent::from_settings(&os.database.settings);
+                if enable && consent.disabled_by_env {
+                    writeln!(stderr, "Telemetry stays disabled while Q_DISABLE_TELEMETRY is set")?;
+                } else if enable && category.is_some() && !consent.enabled {
+                    writeln!(stderr, "Run `telemetry enable` to collect it, telemetry is disabled")?;
+                }
+            },
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+fn status_text(consent: &TelemetryConsent) -> String {
+    let mode = if consent.disabled_by_env {
+        "disabled by Q_DISABLE_TELEMETRY"
+    } else if !consent.enabled {
+        "disabled"
+    } else if consent.local_only {
+        "local only, recorded for `stats` but never sent"
+    } else {
+        "enabled"
+    };
+    let mut text = format!("{:<13}{mode}", "Telemetry:");
+    for category in TelemetryCategory::iter() {
+        let state = if consent.collects(category) { "collected" } else { "off" };
+        text.push_str(&format!("\n{:<13}{state}", format!("{category}:")));
+    }
+    if consent.enabled && !consent.local_only {
+        text.push_str("\n\nRun `settings telemetry.localOnly true` to keep telemetry on this machine");
+    }
+    text
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct StatsArgs {
+    /// Forget the recorded events
+    #[arg(long)]
+    reset: bool,
+    /// Format of the output
+    #[arg(long, short, value_enum, default_value_t)]
+    format: OutputFormat,
+}
+
+impl StatsArgs {
+    pub async fn execute(self, os: &Os) -> Result<ExitCode> {
+        if self.reset {
+            os.database.reset_telemetry_local_stats()?;
+            writeln!(std::io::stderr(), "Forgot the recorded telemetry events")?;
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        let stats = os.database.get_telemetry_local_stats()?.unwrap_or_default();
+        self.format.print(
+            || {
+                let Some(since) = stats.since else {
+                    return "No telemetry events were recorded yet".to_string();
+                };
+                let mut text = format!("Telemetry events recorded since {}\n", format_time(since));
+                for category in TelemetryCategory::iter() {
+                    let mut events = stats
+                        .events
+                        .iter()
+                        .filter(|(_, event)| event.category == category)
+                        .peekable();
+                    if events.peek().is_none() {
+                        continue;
+                    }
+                    text.push_str(&format!("\n{category}\n"));
+                    for (name, event) in events {
+                        text.push_str(&format!(
+                            "  {name:<28}{:>8}   last {}\n",
+                            event.count,
+                            format_time(event.last_recorded)
+                        ));
+                    }
+                }
+                text.trim_end().to_string()
+            },
+            || stats.clone(),
+        );
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+fn format_time(timestamp: i64) -> String {
+    time::OffsetDateTime::from_unix_timestamp(timestamp).map_or_else(|_| timestamp.to_string(), |t| t.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_enable_disable() {
+        let mut os = Os::new().await.unwrap();
+        TelemetrySubcommand::Disable {
+            category: Some(TelemetryCategory::Usage),
+        }
+        .execute(&mut os)
+        .await
+        .unwrap();
+        assert_eq!(os.database.settings.get_bool(Setting::TelemetryUsage), Some(false));
+
+        TelemetrySubcommand::Disable { category: None }
+            .execute(&mut os)
+            .await
+            .unwrap();
+        let consent = TelemetryConsent::from_settings(&os.database.settings);
+        assert!(!consent.collects(TelemetryCategory::Errors));
+        assert!(status_text(&consent).starts_with("Telemetry:   disabled"));
+    }
+}
@@ -113,6 +113,18 @@ pub mod ui_text {
         ));
         help.push('\n');
 
+        // Command palette tip
+        help.push_str(&format!(
+            "{}         {}",
+            StyledText::primary("Ctrl(^) + p"),
+            StyledText::secondary("Command palette for commands, files, prompts, and agents")
+        ));
+        help.push_str(&format!(
+            "\n                    {}",
+            StyledText::secondary("Change the keybind using: q settings chat.commandPaletteKey x")
+        ));
+        help.push('\n');
+
         // Tangent mode tip
         help.push_str(&format!(
             "{}         {}",
@@ -203,6 +215,9 @@ Notes:
         format!("By default, {} will ask for your permission to use certain tools. You can control which tools you
 trust so that no confirmation is required.
 
+Run /tools on its own to turn tools on or off, cycle them between asking, trusted for this session
+and trusted by the agent, and save the changes to your agent.
+
 Refer to the documentation for how to configure tools with your agent: https://github.com/aws/amazon-q-developer-cli/blob/main/docs/agent-format.md#tools-field", super::PRODUCT_NAME)
     }
 
@@ -31,10 +31,12 @@ use tracing::{
     error,
     info,
     trace,
+    warn,
 };
 use uuid::Uuid;
 
 use crate::cli::ConversationState;
+use crate::telemetry::LocalStats;
 use crate::util::env_var::is_integ_test;
 use crate::util::paths::{
     DirectoryError,
@@ -63,6 +65,10 @@ const IDC_REGION_KEY: &str = "auth.idc.region";
 const CUSTOMIZATION_STATE_KEY: &str = "api.selectedCustomization";
 const PROFILE_MIGRATION_KEY: &str = "profile.Migrated";
 const HEARTBEAT_DATE_KEY: &str = "telemetry.lastHeartbeatDate";
+const TELEMETRY_LOCAL_STATS_KEY: &str = "telemetry.localStats";
+const TOOL_SECRET_KEY_PREFIX: &str = "toolSecret.";
+const MCP_OAUTH_TOKEN_KEY_PREFIX: &str = "mcpOauthToken.";
+const MCP_OAUTH_REGISTRATION_KEY_PREFIX: &str = "mcpOauthRegistration.";
 
 const MIGRATIONS: &[Migration] = migrations![
     "000_migration_table",
@@ -366,6 +372,21 @@ impl Database {
         Ok(())
     }
 
+    /// Get the telemetry events recorded on this machine.
+    pub fn get_telemetry_local_stats(&self) -> Result<Option<LocalStats>, DatabaseError> {
+        self.get_json_entry(Table::State, TELEMETRY_LOCAL_STATS_KEY)
+    }
+
+    /// Set the telemetry events recorded on this machine.
+    pub fn set_telemetry_local_stats(&self, stats: &LocalStats) -> Result<usize, DatabaseError> {
+        self.set_json_entry(Table::State, TELEMETRY_LOCAL_STATS_KEY, stats)
+    }
+
+    /// Forget the telemetry events recorded on this machine.
+    pub fn reset_telemetry_local_stats(&self) -> Result<(), DatabaseError> {
+        self.delete_entry(Table::State, TELEMETRY_LOCAL_STATS_KEY)
+    }
+
     // /// Get the model id used for last conversation state.
     // pub fn get_last_used_model_id(&self) -> Result<Option<String>, DatabaseError> {
     //     self.get_json_entry::<String>(Table::State, LAST_USED_MODEL_ID)
@@ -410,6 +431,21 @@ impl Database {
         self.set_json_entry(Table::Conversations, path, state)
     }
 
+    /// Get every saved chat conversation along with the path it was saved for. Conversations that
+    /// can't be read are skipped.
+    pub fn get_all_conversations(&self) -> Result<Vec<(String, ConversationState)>, DatabaseError> {
+        Ok(self
+            .all_entries(Table::Conversations)?
+            .into_iter()
+            .filter_map(|(path, value)| {
+                let state = serde_json::from_str(value.as_str()?)
+                    .map_err(|err| warn!(?err, %path, "failed to read saved conversation"))
+                    .ok()?;
+                Some((path, state))
+            })
+            .collect())
+    }
+
     pub async fn get_secret(&self, key: &str) -> Result<Option<Secret>, DatabaseError> {
         trace!(key, "getting secret");
         Ok(self.get_entry::<String>(Table::Auth, key)?.map(Into::into))
@@ -426,6 +462,51 @@ impl Database {
         self.delete_entry(Table::Auth, key)
     }
 
+    /// Gets a secret set with `q secrets set`, on platforms where it isn't kept in the keychain.
+    pub fn get_tool_secret(&self, name: &str) -> Result<Option<String>, DatabaseError> {
+        self.get_entry(Table::Auth, format!("{TOOL_SECRET_KEY_PREFIX}{name}"))
+    }
+
+    pub fn set_tool_secret(&self, name: &str, value: &str) -> Result<(), DatabaseError> {
+        self.set_entry(Table::Auth, format!("{TOOL_SECRET_KEY_PREFIX}{name}"), value)?;
+        Ok(())
+    }
+
+    pub fn delete_tool_secret(&self, name: &str) -> Result<(), DatabaseError> {
+        self.delete_entry(Table::Auth, format!("{TOOL_SECRET_KEY_PREFIX}{name}"))
+    }
+
+    /// Gets the OAuth token of a remote MCP server as JSON, by the key computed from its url.
+    pub fn get_mcp_oauth_token(&self, key: &str) -> Result<Option<String>, DatabaseError> {
+        self.get_entry(Table::Auth, format!("{MCP_OAUTH_TOKEN_KEY_PREFIX}{key}"))
+    }
+
+    pub fn set_mcp_oauth_token(&self, key: &str, token: &str) -> Result<(), DatabaseError> {
+        self.set_entry(Table::Auth, format!("{MCP_OAUTH_TOKEN_KEY_PREFIX}{key}"), token)?;
+        Ok(())
+    }
+
+    /// Deletes the OAuth token of a remote MCP server, so that the user authorizes it again on
+    /// the next connection. The client registration is kept.
+    pub fn delete_mcp_oauth_token(&self, key: &str) -> Result<(), DatabaseError> {
+        self.delete_entry(Table::Auth, format!("{MCP_OAUTH_TOKEN_KEY_PREFIX}{key}"))
+    }
+
+    /// Gets the OAuth client registration of a remote MCP server as JSON, by the key computed
+    /// from its url.
+    pub fn get_mcp_oauth_registration(&self, key: &str) -> Result<Option<String>, DatabaseError> {
+        self.get_entry(Table::Auth, format!("{MCP_OAUTH_REGISTRATION_KEY_PREFIX}{key}"))
+    }
+
+    pub fn set_mcp_oauth_registration(&self, key: &str, registration: &str) -> Result<(), DatabaseError> {
+        self.set_entry(
+            Table::Auth,
+            format!("{MCP_OAUTH_REGISTRATION_KEY_PREFIX}{key}"),
+            registration,
+        )?;
+        Ok(())
+    }
+
     // Private functions. Do not expose.
 
     fn migrate(self) -> Result<Self, DatabaseError> {
@@ -642,6 +723,25 @@ mod tests {
         assert!(db.get_entry::<bool>(Table::State, "bool").unwrap().is_some());
     }
 
+    #[tokio::test]
+    async fn test_mcp_oauth_entries() {
+        let db = Database::new().await.unwrap();
+
+        db.set_mcp_oauth_token("server", "{\"access_token\":\"a\"}").unwrap();
+        db.set_mcp_oauth_registration("server", "{\"client_id\":\"c\"}")
+            .unwrap();
+        assert_eq!(
+            db.get_mcp_oauth_token("server").unwrap().as_deref(),
+            Some("{\"access_token\":\"a\"}")
+        );
+        assert!(db.get_mcp_oauth_token("other").unwrap().is_none());
+
+        // Deleting the token keeps the registration
+        db.delete_mcp_oauth_token("server").unwrap();
+        assert!(db.get_mcp_oauth_token("server").unwrap().is_none());
+        assert!(db.get_mcp_oauth_registration("server").unwrap().is_some());
+    }
+
     #[tokio::test]
     #[ignore = "not on ci"]
     async fn test_set_password() {
@@ -20,6 +20,14 @@ use crate::util::paths::GlobalPaths;
 pub enum Setting {
     #[strum(message = "Enable/disable telemetry collection (boolean)")]
     TelemetryEnabled,
+    #[strum(message = "Collect error and failure events (boolean)")]
+    TelemetryErrors,
+    #[strum(message = "Collect feature usage events (boolean)")]
+    TelemetryUsage,
+    #[strum(message = "Collect latency and response timing events (boolean)")]
+    TelemetryPerformance,
+    #[strum(message = "Record telemetry locally for `q stats` without sending it (boolean)")]
+    TelemetryLocalOnly,
     #[strum(message = "Legacy client identifier for telemetry (string)")]
     OldClientId,
     #[strum(message = "Share content with CodeWhisperer service (boolean)")]
@@ -42,6 +50,8 @@ pub enum Setting {
     KnowledgeIndexType,
     #[strum(message = "Key binding for fuzzy search command (single character)")]
     SkimCommandKey,
+    #[strum(message = "Key binding for the command palette (single character)")]
+    CommandPaletteKey,
     #[strum(message = "Key binding for autocompletion hint acceptance (single character)")]
     AutocompletionKey,
     #[strum(message = "Enable tangent mode feature (boolean)")]
@@ -61,6 +71,16 @@ pub enum Setting {
     ChatEditMode,
     #[strum(message = "Enable desktop notifications (boolean)")]
     ChatEnableNotifications,
+    #[strum(message = "Max frames per second when rendering streamed responses, 0 for no limit (number)")]
+    ChatRenderMaxFps,
+    #[strum(message = "Show executed tools as one line, expanded by pressing Enter on an empty prompt (boolean)")]
+    ChatCollapseToolOutput,
+    #[strum(
+        message = "Scan prompts and context for secrets and personal data before sending: off, warn or block (string)"
+    )]
+    ChatContentScan,
+    #[strum(message = "Globs of context files flagged by chat.contentScan, e.g. [\"**/.env\"] (array)")]
+    ChatContentScanSensitiveGlobs,
     #[strum(message = "CodeWhisperer service endpoint URL (string)")]
     ApiCodeWhispererService,
     #[strum(message = "Q service endpoint URL (string)")]
@@ -81,8 +101,36 @@ pub enum Setting {
     ChatDefaultAgent,
     #[strum(message = "Disable automatic conversation summarization (boolean)")]
     ChatDisableAutoCompaction,
+    #[strum(message = "Size in tokens above which tool results are reduced (number)")]
+    ChatToolOutputTokenThreshold,
+    #[strum(message = "How to reduce large tool results: truncate, extract, or summarize (string)")]
+    ChatToolOutputStrategy,
+    #[strum(message = "Model used to summarize large tool results, defaults to the conversation's model (string)")]
+    ChatToolOutputSummaryModel,
+    #[strum(message = "Generate a short title for each conversation after its first turn (boolean)")]
+    ChatGenerateTitles,
+    #[strum(message = "Model used to generate conversation titles, defaults to the conversation's model (string)")]
+    ChatTitleModel,
     #[strum(message = "Show conversation history hints (boolean)")]
     ChatEnableHistoryHints,
+    #[strum(message = "Share your recent shell commands and their exit codes with the model (boolean)")]
+    ChatShellActivityContext,
+    #[strum(message = "Maximum number of recent shell commands shared with the model (number)")]
+    ChatShellActivityCommands,
+    #[strum(message = "Show recent timestamps relative to now, or always as dates: relative or absolute (string)")]
+    ChatTimestampFormat,
+    #[strum(message = "Timezone timestamps are shown in: local, utc, or an offset such as +05:30 (string)")]
+    ChatTimezone,
+    #[strum(message = "Registry URL of the autocomplete spec package (string)")]
+    CompletionSpecsRegistryUrl,
+    #[strum(message = "Hours between checks for autocomplete spec updates (number)")]
+    CompletionSpecsUpdateInterval,
+    #[strum(message = "Largest message in bytes exchanged with the daemon (number)")]
+    DaemonMaxMessageSize,
+    #[strum(message = "Days after which file backups are removed, 0 to keep them (number)")]
+    BackupsMaxAgeDays,
+    #[strum(message = "Megabytes file backups may take before the oldest are removed, 0 for no limit (number)")]
+    BackupsMaxSizeMb,
     #[strum(message = "Enable the todo list feature (boolean)")]
     EnabledTodoList,
     #[strum(message = "Enable the checkpoint feature (boolean)")]
@@ -97,6 +145,10 @@ impl AsRef<str> for Setting {
     fn as_ref(&self) -> &'static str {
         match self {
             Self::TelemetryEnabled => "telemetry.enabled",
+            Self::TelemetryErrors => "telemetry.errors",
+            Self::TelemetryUsage => "telemetry.usage",
+            Self::TelemetryPerformance => "telemetry.performance",
+            Self::TelemetryLocalOnly => "telemetry.localOnly",
             Self::OldClientId => "telemetryClientId",
             Self::ShareCodeWhispererContent => "codeWhisperer.shareCodeWhispererContentWithAWS",
             Self::EnabledThinking => "chat.enableThinking",
@@ -108,6 +160,7 @@ impl AsRef<str> for Setting {
             Self::KnowledgeChunkOverlap => "knowledge.chunkOverlap",
             Self::KnowledgeIndexType => "knowledge.indexType",
             Self::SkimCommandKey => "chat.skimCommandKey",
+            Self::CommandPaletteKey => "chat.commandPaletteKey",
             Self::AutocompletionKey => "chat.autocompletionKey",
             Self::EnabledTangentMode => "chat.enableTangentMode",
             Self::TangentModeKey => "chat.tangentModeKey",
@@ -118,6 +171,10 @@ impl AsRef<str> for Setting {
             Self::ApiTimeout => "api.timeout",
             Self::ChatEditMode => "chat.editMode",
             Self::ChatEnableNotifications => "chat.enableNotifications",
+            Self::ChatRenderMaxFps => "chat.renderMaxFps",
+            Self::ChatCollapseToolOutput => "chat.collapseToolOutput",
+            Self::ChatContentScan => "chat.contentScan",
+            Self::ChatContentScanSensitiveGlobs => "chat.contentScanSensitiveGlobs",
             Self::ApiCodeWhispererService => "api.codewhisperer.service",
             Self::ApiQService => "api.q.service",
             Self::McpInitTimeout => "mcp.initTimeout",
@@ -127,7 +184,21 @@ impl AsRef<str> for Setting {
             Self::ChatDisableMarkdownRendering => "chat.disableMarkdownRendering",
             Self::ChatDefaultAgent => "chat.defaultAgent",
             Self::ChatDisableAutoCompaction => "chat.disableAutoCompaction",
+            Self::ChatToolOutputTokenThreshold => "chat.toolOutputTokenThreshold",
+            Self::ChatToolOutputStrategy => "chat.toolOutputStrategy",
+            Self::ChatToolOutputSummaryModel => "chat.toolOutputSummaryModel",
+            Self::ChatGenerateTitles => "chat.generateTitles",
+            Self::ChatTitleModel => "chat.titleModel",
             Self::ChatEnableHistoryHints => "chat.enableHistoryHints",
+            Self::ChatShellActivityContext => "chat.shellActivityContext",
+            Self::ChatShellActivityCommands => "chat.shellActivityCommands",
+            Self::ChatTimestampFormat => "chat.timestampFormat",
+            Self::ChatTimezone => "chat.timezone",
+            Self::CompletionSpecsRegistryUrl => "completionSpecs.registryUrl",
+            Self::CompletionSpecsUpdateInterval => "completionSpecs.updateIntervalHours",
+            Self::DaemonMaxMessageSize => "daemon.maxMessageSize",
+            Self::BackupsMaxAgeDays => "backups.maxAgeDays",
+            Self::BackupsMaxSizeMb => "backups.maxSizeMb",
             Self::EnabledTodoList => "chat.enableTodoList",
             Self::EnabledCheckpoint => "chat.enableCheckpoint",
             Self::EnabledContextUsageIndicator => "chat.enableContextUsageIndicator",
@@ -149,6 +220,10 @@ impl TryFrom<&str> for Setting {
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         match value {
             "telemetry.enabled" => Ok(Self::TelemetryEnabled),
+            "telemetry.errors" => Ok(Self::TelemetryErrors),
+            "telemetry.usage" => Ok(Self::TelemetryUsage),
+            "telemetry.performance" => Ok(Self::TelemetryPerformance),
+            "telemetry.localOnly" => Ok(Self::TelemetryLocalOnly),
             "telemetryClientId" => Ok(Self::OldClientId),
             "codeWhisperer.shareCodeWhispererContentWithAWS" => Ok(Self::ShareCodeWhispererContent),
             "chat.enableThinking" => Ok(Self::EnabledThinking),
@@ -160,6 +235,7 @@ impl TryFrom<&str> for Setting {
             "knowledge.chunkOverlap" => Ok(Self::KnowledgeChunkOverlap),
             "knowledge.indexType" => Ok(Self::KnowledgeIndexType),
             "chat.skimCommandKey" => Ok(Self::SkimCommandKey),
+            "chat.commandPaletteKey" => Ok(Self::CommandPaletteKey),
             "chat.autocompletionKey" => Ok(Self::AutocompletionKey),
             "chat.enableTangentMode" => Ok(Self::EnabledTangentMode),
             "chat.tangentModeKey" => Ok(Self::TangentModeKey),
@@ -169,6 +245,10 @@ impl TryFrom<&str> for Setting {
             "api.timeout" => Ok(Self::ApiTimeout),
             "chat.editMode" => Ok(Self::ChatEditMode),
             "chat.enableNotifications" => Ok(Self::ChatEnableNotifications),
+            "chat.renderMaxFps" => Ok(Self::ChatRenderMaxFps),
+            "chat.collapseToolOutput" => Ok(Self::ChatCollapseToolOutput),
+            "chat.contentScan" => Ok(Self::ChatContentScan),
+            "chat.contentScanSensitiveGlobs" => Ok(Self::ChatContentScanSensitiveGlobs),
             "api.codewhisperer.service" => Ok(Self::ApiCodeWhispererService),
             "api.q.service" => Ok(Self::ApiQService),
             "mcp.initTimeout" => Ok(Self::McpInitTimeout),
@@ -178,7 +258,21 @@ impl TryFrom<&str> for Setting {
             "chat.disableMarkdownRendering" => Ok(Self::ChatDisableMarkdownRendering),
             "chat.defaultAgent" => Ok(Self::ChatDefaultAgent),
             "chat.disableAutoCompaction" => Ok(Self::ChatDisableAutoCompaction),
+            "chat.toolOutputTokenThreshold" => Ok(Self::ChatToolOutputTokenThreshold),
+            "chat.toolOutputStrategy" => Ok(Self::ChatToolOutputStrategy),
+            "chat.toolOutputSummaryModel" => Ok(Self::ChatToolOutputSummaryModel),
+            "chat.generateTitles" => Ok(Self::ChatGenerateTitles),
+            "chat.titleModel" => Ok(Self::ChatTitleModel),
             "chat.enableHistoryHints" => Ok(Self::ChatEnableHistoryHints),
+            "chat.shellActivityContext" => Ok(Self::ChatShellActivityContext),
+            "chat.shellActivityCommands" => Ok(Self::ChatShellActivityCommands),
+            "chat.timestampFormat" => Ok(Self::ChatTimestampFormat),
+            "chat.timezone" => Ok(Self::ChatTimezone),
+            "completionSpecs.registryUrl" => Ok(Self::CompletionSpecsRegistryUrl),
+            "completionSpecs.updateIntervalHours" => Ok(Self::CompletionSpecsUpdateInterval),
+            "daemon.maxMessageSize" => Ok(Self::DaemonMaxMessageSize),
+            "backups.maxAgeDays" => Ok(Self::BackupsMaxAgeDays),
+            "backups.maxSizeMb" => Ok(Self::BackupsMaxSizeMb),
             "chat.enableTodoList" => Ok(Self::EnabledTodoList),
             "chat.enableCheckpoint" => Ok(Self::EnabledCheckpoint),
             "chat.enableContextUsageIndicator" => Ok(Self::EnabledContextUsageIndicator),
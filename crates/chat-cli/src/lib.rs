@@ -2,6 +2,7 @@
 //! This lib.rs is only here for testing purposes.
 //! `test_mcp_server/test_server.rs` is declared as a separate binary and would need a way to
 //! reference types defined inside of this crate, hence the export.
+pub mod agent;
 pub mod api_client;
 pub mod auth;
 pub mod aws_common;
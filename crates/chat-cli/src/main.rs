@@ -15,17 +15,16 @@ mod util;
 
 use std::process::ExitCode;
 
-use anstream::eprintln;
 use clap::Parser;
 use eyre::Result;
 use logging::get_log_level_max;
-use theme::StyledText;
 use tracing::metadata::LevelFilter;
 
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
 fn main() -> Result<ExitCode> {
+    util::startup_profile::init();
     color_eyre::install()?;
 
     let parsed = match cli::Cli::try_parse() {
@@ -37,19 +36,16 @@ fn main() -> Result<ExitCode> {
     };
 
     let verbose = parsed.verbose > 0;
+    let error_format = parsed.error_format;
     let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
     let result = runtime.block_on(parsed.execute());
 
     match result {
         Ok(exit_code) => Ok(exit_code),
-        Err(err) => {
-            if verbose || get_log_level_max() > LevelFilter::INFO {
-                eprintln!("{} {err:?}", StyledText::error("error:"));
-            } else {
-                eprintln!("{} {err}", StyledText::error("error:"));
-            }
-
-            Ok(ExitCode::FAILURE)
-        },
+        Err(err) => Ok(cli::error_class::report(
+            &err,
+            error_format,
+            verbose || get_log_level_max() > LevelFilter::INFO,
+        )),
     }
 }
@@ -1,11 +1,17 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::process::Stdio;
+use std::sync::Arc;
 
+use disk_cache::{
+    CacheKey,
+    DiskCache,
+};
 use regex::Regex;
 use rmcp::model::{
     CallToolRequestParam,
     CallToolResult,
+    ClientCapabilities,
     ClientResult,
     ErrorCode,
     GetPromptRequestParam,
@@ -13,12 +19,17 @@ use rmcp::model::{
     Implementation,
     InitializeRequestParam,
     ListPromptsResult,
+    ListRootsResult,
     ListToolsResult,
     LoggingLevel,
     LoggingMessageNotificationParam,
     PaginatedRequestParam,
+    Root,
+    RootsCapabilities,
+    ServerInfo,
     ServerNotification,
     ServerRequest,
+    Tool,
 };
 use rmcp::service::{
     ClientInitializeError,
@@ -45,6 +56,7 @@ use tokio::task::JoinHandle;
 use tracing::{
     error,
     info,
+    warn,
 };
 
 use super::messenger::Messenger;
@@ -58,8 +70,15 @@ use crate::cli::chat::tools::custom_tool::{
     CustomToolConfig,
     TransportType,
 };
-use crate::os::Os;
+use crate::os::{
+    Env,
+    Os,
+};
 use crate::util::env_var::get_all_env_vars;
+use crate::util::{
+    cache,
+    secrets,
+};
 
 /// Fetches all pages of specified resources from a server
 macro_rules! paginated_fetch {
@@ -72,6 +91,7 @@ macro_rules! paginated_fetch {
         service: $service:expr,
         messenger: $messenger:expr,
         server_name: $server_name:expr
+        $(, on_fetched: $on_fetched:expr)?
     ) => {
         {
             let mut cursor = None::<String>;
@@ -99,6 +119,7 @@ macro_rules! paginated_fetch {
 
             if let Ok(final_result) = &mut final_result {
                 final_result.$result_field.append(&mut content);
+                $( ($on_fetched)(&final_result.$result_field); )?
             }
 
             if let Err(e) = $messenger.$messenger_method(final_result, Some($service)).await {
@@ -261,6 +282,54 @@ impl RunningService {
     decorate_with_auth_retry!(CallToolRequestParam, call_tool, CallToolResult);
 
     decorate_with_auth_retry!(GetPromptRequestParam, get_prompt, GetPromptResult);
+
+    /// Lets the server know that the roots, i.e. the working directory, changed
+    pub async fn notify_roots_list_changed(&self) -> Result<(), rmcp::ServiceError> {
+        match &self.inner_service {
+            InnerService::Original(rs) => rs.notify_roots_list_changed().await,
+            InnerService::Peer(peer) => peer.notify_roots_list_changed().await,
+        }
+    }
+}
+
+/// Tool lists of servers, cached across sessions so that they aren't listed again on every launch.
+/// A cached list is reused as long as the server runs with the same config and reports the same
+/// name and version when initialized, and is replaced whenever the server reports that its tools
+/// changed.
+#[derive(Clone, Debug)]
+struct ToolListCache {
+    cache: Arc<DiskCache>,
+    /// The config of the server as loaded, before env vars and secrets are substituted in it
+    config: String,
+}
+
+impl ToolListCache {
+    fn new(cache: Arc<DiskCache>, config: &CustomToolConfig) -> Self {
+        Self {
+            cache,
+            config: serde_json::to_string(config).unwrap_or_default(),
+        }
+    }
+
+    fn key(&self, server_name: &str, server_info: &ServerInfo) -> CacheKey {
+        CacheKey::new([
+            "mcp_tool_list",
+            server_name,
+            &self.config,
+            &server_info.server_info.name,
+            &server_info.server_info.version,
+        ])
+    }
+
+    fn get(&self, server_name: &str, server_info: &ServerInfo) -> Option<Vec<Tool>> {
+        self.cache.get(&self.key(server_name, server_info))
+    }
+
+    fn insert(&self, server_name: &str, server_info: &ServerInfo, tools: &[Tool]) {
+        if let Err(err) = self.cache.insert(&self.key(server_name, server_info), &tools) {
+            warn!(target: "mcp", "{server_name}: failed to cache the tool list: {err}");
+        }
+    }
 }
 
 /// This struct implements the [Service] trait from rmcp. It is within this trait the logic of
@@ -271,23 +340,37 @@ pub struct McpClientService {
     pub config: CustomToolConfig,
     server_name: String,
     messenger: ServerMessenger,
+    /// Environment of the chat session, whose working directory is the root exposed to the server
+    env: Env,
+    /// Set once the client is initialized, unless the cache couldn't be opened
+    tool_list_cache: Option<ToolListCache>,
 }
 
 impl McpClientService {
-    pub fn new(server_name: String, config: CustomToolConfig, messenger: ServerMessenger) -> Self {
+    pub fn new(server_name: String, config: CustomToolConfig, messenger: ServerMessenger, env: Env) -> Self {
         Self {
             server_name,
             config,
             messenger,
+            env,
+            tool_list_cache: None,
         }
     }
 
-    pub async fn init(self, os: &Os) -> Result<InitializedMcpClient, McpClientError> {
+    pub async fn init(mut self, os: &Os) -> Result<InitializedMcpClient, McpClientError> {
         let os_clone = os.clone();
+        self.tool_list_cache = match cache::open(os) {
+            Ok(cache) => Some(ToolListCache::new(Arc::new(cache), &self.config)),
+            Err(err) => {
+                warn!(target: "mcp", "{}: tool lists won't be cached: {err}", self.server_name);
+                None
+            },
+        };
 
         let handle: JoinHandle<Result<RunningService, McpClientError>> = tokio::spawn(async move {
             let messenger_clone = self.messenger.clone();
             let server_name = self.server_name.clone();
+            let tool_list_cache = self.tool_list_cache.clone();
 
             let (service, child_stderr, auth_dropguard) = match self.into_service(&os_clone, &messenger_clone).await {
                 Ok((service, stderr, auth_dg)) => (service, stderr, auth_dg),
@@ -335,7 +418,20 @@ impl McpClientService {
                 let result: Result<(), Box<dyn std::error::Error + Send + Sync>> = async {
                     let init_result = service_clone.peer_info();
                     if let Some(init_result) = init_result {
-                        if init_result.capabilities.tools.is_some() {
+                        let cached_tools = tool_list_cache
+                            .as_ref()
+                            .filter(|_| init_result.capabilities.tools.is_some())
+                            .and_then(|cache| cache.get(&server_name, init_result));
+                        if let Some(tools) = cached_tools {
+                            info!(target: "mcp", "{server_name}: using the cached list of {} tools", tools.len());
+                            let result = Ok(ListToolsResult::with_all_items(tools));
+                            if let Err(e) = messenger_clone
+                                .send_tools_list_result(result, Some(service_clone.clone()))
+                                .await
+                            {
+                                error!(target: "mcp", "Cached tools failed to send for server {server_name}: {e}");
+                            }
+                        } else if init_result.capabilities.tools.is_some() {
                             paginated_fetch! {
                                 final_result_type: ListToolsResult,
                                 content_type: rmcp::model::Tool,
@@ -344,7 +440,12 @@ impl McpClientService {
                                 messenger_method: send_tools_list_result,
                                 service: service_clone.clone(),
                                 messenger: messenger_clone,
-                                server_name: server_name
+                                server_name: server_name,
+                                on_fetched: |tools: &[Tool]| {
+                                    if let Some(cache) = &tool_list_cache {
+                                        cache.insert(&server_name, init_result, tools);
+                                    }
+                                }
                             };
                         }
 
@@ -425,11 +526,14 @@ impl McpClientService {
                 let expanded_cmd = shellexpand::full_with_context(command_as_str, home_dir, context)?;
 
                 let command = Command::new(expanded_cmd.as_ref() as &str).configure(|cmd| {
+                    // The configured env has to be applied last to take precedence over the
+                    // inherited one
+                    cmd.envs(get_all_env_vars()).args(args);
                     if let Some(envs) = config_envs {
                         process_env_vars(envs, &os.env);
+                        secrets::interpolate_values(&os.database, envs);
                         cmd.envs(envs);
                     }
-                    cmd.envs(get_all_env_vars()).args(args);
 
                     #[cfg(not(windows))]
                     cmd.process_group(0);
@@ -456,11 +560,12 @@ impl McpClientService {
                     ..
                 } = &self.config;
 
-                // Process environment variables in headers
+                // Process environment variables and secrets in headers
                 let mut processed_headers = headers.clone();
                 for (_, value) in processed_headers.iter_mut() {
                     *value = substitute_env_vars(value, &os.env);
                 }
+                secrets::interpolate_values(&os.database, &mut processed_headers);
 
                 let http_service_builder =
                     HttpServiceBuilder::new(url, os, url, *timeout, scopes, &processed_headers, oauth, messenger);
@@ -502,6 +607,7 @@ impl McpClientService {
 
     async fn on_tool_list_changed(&self, context: NotificationContext<RoleClient>) {
         let NotificationContext { peer, .. } = context;
+        let server_info = peer.peer_info().cloned();
 
         paginated_fetch! {
             final_result_type: ListToolsResult,
@@ -511,7 +617,12 @@ impl McpClientService {
             messenger_method: send_tools_list_result,
             service: peer,
             messenger: self.messenger,
-            server_name: self.server_name
+            server_name: self.server_name,
+            on_fetched: |tools: &[Tool]| {
+                if let (Some(cache), Some(server_info)) = (&self.tool_list_cache, &server_info) {
+                    cache.insert(&self.server_name, server_info, tools);
+                }
+            }
         };
     }
 
@@ -542,9 +653,9 @@ impl Service<RoleClient> for McpClientService {
             ServerRequest::CreateMessageRequest(_) => Err(rmcp::ErrorData::method_not_found::<
                 rmcp::model::CreateMessageRequestMethod,
             >()),
-            ServerRequest::ListRootsRequest(_) => {
-                Err(rmcp::ErrorData::method_not_found::<rmcp::model::ListRootsRequestMethod>())
-            },
+            ServerRequest::ListRootsRequest(_) => Ok(ClientResult::ListRootsResult(ListRootsResult {
+                roots: current_roots(&self.env),
+            })),
             ServerRequest::CreateElicitationRequest(_) => Err(rmcp::ErrorData::method_not_found::<
                 rmcp::model::ElicitationCreateRequestMethod,
             >()),
@@ -574,7 +685,12 @@ impl Service<RoleClient> for McpClientService {
     fn get_info(&self) -> <RoleClient as rmcp::service::ServiceRole>::Info {
         InitializeRequestParam {
             protocol_version: Default::default(),
-            capabilities: Default::default(),
+            capabilities: ClientCapabilities {
+                roots: Some(RootsCapabilities {
+                    list_changed: Some(true),
+                }),
+                ..Default::default()
+            },
             client_info: Implementation {
                 name: "Q DEV CLI".to_string(),
                 version: "1.0.0".to_string(),
@@ -584,6 +700,22 @@ impl Service<RoleClient> for McpClientService {
     }
 }
 
+/// The roots exposed to servers, which is the working directory of the chat session. It is read
+/// on every request since it can be changed with `/cd`.
+fn current_roots(env: &Env) -> Vec<Root> {
+    let Ok(cwd) = env.current_dir() else {
+        return Vec::new();
+    };
+    let Ok(uri) = url::Url::from_directory_path(&cwd) else {
+        return Vec::new();
+    };
+
+    vec![Root {
+        uri: uri.to_string(),
+        name: cwd.file_name().map(|name| name.to_string_lossy().to_string()),
+    }]
+}
+
 /// InitializedMcpClient is the return of [McpClientService::init].
 /// This is necessitated by the fact that [Service::serve], the command to spawn the process, is
 /// async and does not resolve immediately. This delay can be significant and causes long perceived
@@ -706,4 +838,31 @@ mod tests {
         assert_eq!(processed_headers.get("X-API-Key").unwrap(), "secret_key_456");
         assert_eq!(processed_headers.get("Content-Type").unwrap(), "application/json");
     }
+
+    #[test]
+    fn test_tool_list_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let disk_cache = Arc::new(DiskCache::open(dir.path(), Default::default()).unwrap());
+        let config = |command: &str| -> CustomToolConfig {
+            serde_json::from_value(serde_json::json!({ "command": command })).unwrap()
+        };
+        let server_info = |version: &str| {
+            let mut info = ServerInfo::default();
+            info.server_info.name = "server".to_string();
+            info.server_info.version = version.to_string();
+            info
+        };
+        let tools = vec![Tool::new("search", "Searches the docs", serde_json::Map::new())];
+
+        let cache = ToolListCache::new(disk_cache.clone(), &config("server"));
+        assert!(cache.get("docs", &server_info("1.0.0")).is_none());
+        cache.insert("docs", &server_info("1.0.0"), &tools);
+        assert_eq!(cache.get("docs", &server_info("1.0.0")), Some(tools));
+
+        // Lists are not reused once the server is updated or configured differently
+        assert!(cache.get("docs", &server_info("1.1.0")).is_none());
+        assert!(cache.get("other", &server_info("1.0.0")).is_none());
+        let reconfigured = ToolListCache::new(disk_cache, &config("server --readonly"));
+        assert!(reconfigured.get("docs", &server_info("1.0.0")).is_none());
+    }
 }
@@ -1,9 +1,9 @@
 use std::collections::HashMap;
 use std::net::SocketAddr;
-use std::path::PathBuf;
 use std::pin::Pin;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use http::{
     HeaderMap,
@@ -47,15 +47,23 @@ use sha2::{
     Sha256,
 };
 use tokio::sync::oneshot::Sender;
-use tokio_util::sync::CancellationToken;
+use tokio_util::sync::{
+    CancellationToken,
+    DropGuard,
+};
 use tracing::{
     debug,
     error,
     info,
+    warn,
 };
 use url::Url;
 
 use super::messenger::Messenger;
+use crate::database::{
+    Database,
+    DatabaseError,
+};
 use crate::os::Os;
 use crate::util::paths::{
     DirectoryError,
@@ -81,6 +89,8 @@ pub enum OauthUtilError {
     #[error(transparent)]
     Directory(#[from] DirectoryError),
     #[error(transparent)]
+    Database(#[from] DatabaseError),
+    #[error(transparent)]
     Reqwest(#[from] reqwest::Error),
     #[error("{0}")]
     Http(String),
@@ -136,36 +146,116 @@ impl From<OAuthClientConfig> for Registration {
     }
 }
 
+/// An OAuth token as stored in the database, along with when it was obtained so that it can be
+/// refreshed before it expires.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredToken {
+    pub token: OAuthTokenResponse,
+    /// Unix timestamp in seconds. [None] for tokens moved from the files they used to be stored
+    /// in, which didn't record it.
+    #[serde(default)]
+    pub obtained_at: Option<i64>,
+}
+
+impl StoredToken {
+    fn new(token: OAuthTokenResponse) -> Self {
+        Self {
+            token,
+            obtained_at: Some(chrono::Utc::now().timestamp()),
+        }
+    }
+
+    /// Parses a stored token, or a bare token as written to the legacy credential files.
+    fn parse(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str::<Self>(json).or_else(|_| {
+            Ok(Self {
+                token: serde_json::from_str(json)?,
+                obtained_at: None,
+            })
+        })
+    }
+
+    /// Time from `now` until the token should be refreshed, once 80% of its lifetime has passed.
+    /// [None] if the token doesn't expire.
+    fn refresh_delay(&self, now: i64) -> Option<Duration> {
+        // The lifetime is only accessible through a trait of the private oauth2 dependency of rmcp
+        let expires_in = serde_json::to_value(&self.token).ok()?.get("expires_in")?.as_u64()?;
+        let elapsed = self.obtained_at.map_or(0, |at| now.saturating_sub(at).max(0) as u64);
+        Some(Duration::from_secs((expires_in * 4 / 5).saturating_sub(elapsed)))
+    }
+}
+
 /// A wrapper that manages an authenticated MCP client.
 ///
 /// This struct wraps an `AuthClient` and provides access to OAuth credentials
 /// for MCP server connections that require authentication. The credentials
-/// are managed separately from this wrapper's lifecycle.
+/// are stored in the database, under the key computed from the url of the server.
 #[derive(Clone, Debug)]
 pub struct AuthClientWrapper {
-    pub cred_full_path: PathBuf,
+    /// See [compute_key]
+    pub key: String,
+    pub database: Database,
     pub auth_client: AuthClient<Client>,
+    /// Stops refreshing the token automatically once the last clone of the wrapper is dropped
+    refresh_guard: Option<Arc<DropGuard>>,
 }
 
 impl AuthClientWrapper {
-    pub fn new(cred_full_path: PathBuf, auth_client: AuthClient<Client>) -> Self {
+    pub fn new(key: String, database: Database, auth_client: AuthClient<Client>) -> Self {
         Self {
-            cred_full_path,
+            key,
+            database,
             auth_client,
+            refresh_guard: None,
         }
     }
 
     /// Refreshes token in memory using the registration read from when the auth client was
     /// spawned. This also persists the retrieved token
-    pub async fn refresh_token(&self) -> Result<(), OauthUtilError> {
+    pub async fn refresh_token(&self) -> Result<StoredToken, OauthUtilError> {
         let cred = self.auth_client.auth_manager.lock().await.refresh_token().await?;
-        let parent_path = self.cred_full_path.parent().ok_or(OauthUtilError::MalformDirectory)?;
-        tokio::fs::create_dir_all(parent_path).await?;
+        let token = StoredToken::new(cred);
+        self.database
+            .set_mcp_oauth_token(&self.key, &serde_json::to_string(&token)?)?;
+        Ok(token)
+    }
 
-        let cred_as_bytes = serde_json::to_string_pretty(&cred)?;
-        tokio::fs::write(&self.cred_full_path, &cred_as_bytes).await?;
+    /// Refreshes the stored token in the background shortly before it expires, for as long as a
+    /// clone of the returned wrapper is alive. Requests that fail because of an expired token are
+    /// still retried after a refresh, e.g. when the system slept past the expiry.
+    pub fn with_automatic_refresh(mut self) -> Self {
+        let token = match self.database.get_mcp_oauth_token(&self.key) {
+            Ok(Some(token)) => StoredToken::parse(&token).ok(),
+            _ => None,
+        };
+        let Some(mut delay) = token.and_then(|token| token.refresh_delay(chrono::Utc::now().timestamp())) else {
+            return self;
+        };
 
-        Ok(())
+        let cancellation_token = CancellationToken::new();
+        let refresher = self.clone();
+        let cancelled = cancellation_token.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = cancelled.cancelled() => break,
+                    _ = tokio::time::sleep(delay) => {},
+                }
+                match refresher.refresh_token().await {
+                    Ok(token) => match token.refresh_delay(chrono::Utc::now().timestamp()) {
+                        Some(next_delay) => delay = next_delay,
+                        None => break,
+                    },
+                    Err(err) => {
+                        warn!(?err, "## mcp: failed to refresh the token in the background");
+                        break;
+                    },
+                }
+            }
+        });
+        self.refresh_guard = Some(Arc::new(cancellation_token.drop_guard()));
+        self
     }
 }
 
@@ -240,11 +330,8 @@ impl<'a> HttpServiceBuilder<'a> {
         } = self;
 
         let mut state = HttpServiceBuilderState::AttemptConnection(TransportType::Http, false);
-        let cred_dir = PathResolver::new(os).global().mcp_auth_dir()?;
         let url = Url::from_str(url)?;
         let key = compute_key(&url);
-        let cred_full_path = cred_dir.join(format!("{key}.token.json"));
-        let reg_full_path = cred_dir.join(format!("{key}.registration.json"));
         let mut auth_client = None::<AuthClient<Client>>;
 
         let mut client_builder = reqwest::ClientBuilder::new().timeout(std::time::Duration::from_millis(timeout));
@@ -292,16 +379,8 @@ impl<'a> HttpServiceBuilder<'a> {
                         let ac = match auth_client {
                             Some(ref auth_client) => auth_client.clone(),
                             None => {
-                                let am = get_auth_manager(
-                                    url.clone(),
-                                    cred_full_path.clone(),
-                                    reg_full_path.clone(),
-                                    scopes,
-                                    oauth_config,
-                                    messenger,
-                                    os,
-                                )
-                                .await?;
+                                let am =
+                                    get_auth_manager(url.clone(), &key, scopes, oauth_config, messenger, os).await?;
 
                                 let ac = AuthClient::new(reqwest_client.clone(), am);
                                 auth_client.replace(ac.clone());
@@ -322,7 +401,8 @@ impl<'a> HttpServiceBuilder<'a> {
 
                                 match service.clone().into_dyn().serve(transport).await {
                                     Ok(service) => {
-                                        let auth_client_wrapper = AuthClientWrapper::new(cred_full_path, ac);
+                                        let auth_client_wrapper = AuthClientWrapper::new(key, os.database.clone(), ac)
+                                            .with_automatic_refresh();
                                         return Ok((service, Some(auth_client_wrapper)));
                                     },
                                     Err(e) => {
@@ -354,7 +434,8 @@ impl<'a> HttpServiceBuilder<'a> {
 
                                 match service.clone().into_dyn().serve(transport).await {
                                     Ok(service) => {
-                                        let auth_client_wrapper = AuthClientWrapper::new(cred_full_path, ac);
+                                        let auth_client_wrapper = AuthClientWrapper::new(key, os.database.clone(), ac)
+                                            .with_automatic_refresh();
                                         return Ok((service, Some(auth_client_wrapper)));
                                     },
                                     Err(e) => {
@@ -423,7 +504,8 @@ impl<'a> HttpServiceBuilder<'a> {
                 },
                 HttpServiceBuilderState::FailedBecauseTokenMightBeExpired => {
                     let auth_client_ref = auth_client.as_ref().ok_or(OauthUtilError::MissingAuthClient)?;
-                    let auth_client_wrapper = AuthClientWrapper::new(cred_full_path.clone(), auth_client_ref.clone());
+                    let auth_client_wrapper =
+                        AuthClientWrapper::new(key.clone(), os.database.clone(), auth_client_ref.clone());
                     let refresh_res = auth_client_wrapper.refresh_token().await;
 
                     if let Err(e) = refresh_res {
@@ -433,9 +515,7 @@ impl<'a> HttpServiceBuilder<'a> {
                         // case we would need to have user go through the auth flow
                         // again. We do this by deleting the cred
                         // and discarding the client to trigger a full auth flow
-                        if cred_full_path.is_file() {
-                            tokio::fs::remove_file(&cred_full_path).await?;
-                        }
+                        os.database.delete_mcp_oauth_token(&key)?;
 
                         // we'll also need to remove the auth client to force a reauth when we go
                         // back to attempt the first step again
@@ -456,23 +536,18 @@ impl<'a> HttpServiceBuilder<'a> {
 
 async fn get_auth_manager(
     url: Url,
-    cred_full_path: PathBuf,
-    reg_full_path: PathBuf,
+    key: &str,
     scopes: &[String],
     oauth_config: &Option<crate::cli::chat::tools::custom_tool::OAuthConfig>,
     messenger: &dyn Messenger,
     os: &Os,
 ) -> Result<AuthorizationManager, OauthUtilError> {
-    let cred_as_bytes = tokio::fs::read(&cred_full_path).await;
-    let reg_as_bytes = tokio::fs::read(&reg_full_path).await;
+    let (token, reg) = load_credentials(os, key).await?;
     let mut oauth_state = OAuthState::new(url, None).await?;
 
-    match (cred_as_bytes, reg_as_bytes) {
-        (Ok(cred_as_bytes), Ok(reg_as_bytes)) => {
-            let token = serde_json::from_slice::<OAuthTokenResponse>(&cred_as_bytes)?;
-            let reg = serde_json::from_slice::<Registration>(&reg_as_bytes)?;
-
-            oauth_state.set_credentials(&reg.client_id, token).await?;
+    match (token, reg) {
+        (Some(token), Some(reg)) => {
+            oauth_state.set_credentials(&reg.client_id, token.token).await?;
 
             debug!("## mcp: credentials set with cache");
 
@@ -498,23 +573,50 @@ async fn get_auth_manager(
                     .collect::<Vec<_>>(),
                 redirect_uri,
             };
-            let reg_as_str = serde_json::to_string_pretty(&reg)?;
-            let reg_parent_path = reg_full_path.parent().ok_or(OauthUtilError::MalformDirectory)?;
-            tokio::fs::create_dir_all(reg_parent_path).await?;
-            tokio::fs::write(reg_full_path, &reg_as_str).await?;
+            os.database
+                .set_mcp_oauth_registration(key, &serde_json::to_string(&reg)?)?;
 
             let credentials = credentials.ok_or(OauthUtilError::MissingCredentials)?;
-
-            let cred_parent_path = cred_full_path.parent().ok_or(OauthUtilError::MalformDirectory)?;
-            tokio::fs::create_dir_all(cred_parent_path).await?;
-            let reg_as_str = serde_json::to_string_pretty(&credentials)?;
-            tokio::fs::write(cred_full_path, &reg_as_str).await?;
+            os.database
+                .set_mcp_oauth_token(key, &serde_json::to_string(&StoredToken::new(credentials))?)?;
 
             Ok(am)
         },
     }
 }
 
+/// Reads the stored OAuth token and client registration of the remote MCP server with the given
+/// key. Those still in the files they used to be stored in are moved to the database.
+async fn load_credentials(os: &Os, key: &str) -> Result<(Option<StoredToken>, Option<Registration>), OauthUtilError> {
+    let mut token = os.database.get_mcp_oauth_token(key)?;
+    let mut reg = os.database.get_mcp_oauth_registration(key)?;
+
+    if token.is_none() || reg.is_none() {
+        let legacy_dir = PathResolver::new(os).global().mcp_auth_dir()?;
+        let token_path = legacy_dir.join(format!("{key}.token.json"));
+        let reg_path = legacy_dir.join(format!("{key}.registration.json"));
+        if token.is_none() {
+            if let Ok(legacy) = tokio::fs::read_to_string(&token_path).await {
+                os.database.set_mcp_oauth_token(key, &legacy)?;
+                let _ = tokio::fs::remove_file(&token_path).await;
+                token = Some(legacy);
+            }
+        }
+        if reg.is_none() {
+            if let Ok(legacy) = tokio::fs::read_to_string(&reg_path).await {
+                os.database.set_mcp_oauth_registration(key, &legacy)?;
+                let _ = tokio::fs::remove_file(&reg_path).await;
+                reg = Some(legacy);
+            }
+        }
+    }
+
+    Ok((
+        token.map(|token| StoredToken::parse(&token)).transpose()?,
+        reg.map(|reg| serde_json::from_str(&reg)).transpose()?,
+    ))
+}
+
 async fn get_auth_manager_impl(
     mut oauth_state: OAuthState,
     scopes: &[String],
@@ -738,3 +840,31 @@ async fn make_svc(
 
     Ok((actual_addr, dg))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stored_token_refresh_delay() {
+        let legacy = r#"{"access_token":"a","token_type":"bearer","expires_in":3600,"refresh_token":"r"}"#;
+        let token = StoredToken::parse(legacy).unwrap();
+        assert_eq!(token.obtained_at, None);
+        assert_eq!(token.refresh_delay(1000), Some(Duration::from_secs(2880)));
+
+        let token = StoredToken::parse(
+            &serde_json::to_string(&StoredToken {
+                obtained_at: Some(1000),
+                ..token
+            })
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(token.obtained_at, Some(1000));
+        assert_eq!(token.refresh_delay(1600), Some(Duration::from_secs(2280)));
+        assert_eq!(token.refresh_delay(10000), Some(Duration::ZERO));
+
+        let token = StoredToken::parse(r#"{"access_token":"a","token_type":"bearer"}"#).unwrap();
+        assert_eq!(token.refresh_delay(1000), None);
+    }
+}
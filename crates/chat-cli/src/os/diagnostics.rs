@@ -1,11 +1,17 @@
 #![allow(clippy::ref_option_ref)]
-use std::collections::BTreeMap;
+use std::collections::{
+    BTreeMap,
+    BTreeSet,
+};
+use std::time::Duration;
 
 use serde::Serialize;
 use sysinfo::{
     CpuRefreshKind,
     MemoryRefreshKind,
+    ProcessRefreshKind,
     RefreshKind,
+    UpdateKind,
 };
 use time::OffsetDateTime;
 use time::format_description::well_known::Rfc3339;
@@ -222,6 +228,275 @@ impl Diagnostics {
     }
 }
 
+/// How long to wait for a login or interactive shell to print its environment, since slow or
+/// prompting startup files shouldn't hang the diagnostics.
+const SHELL_ENV_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Printed before the environment so output from the shell's startup files can be skipped.
+const SHELL_ENV_MARKER: &str = "__Q_SHELL_ENV__";
+
+/// Variables that differ between any two shells and say nothing about their configuration.
+const SHELL_ENV_IGNORED: &[&str] = &["_", "SHLVL", "PWD", "OLDPWD", "COLUMNS", "LINES", "PS1", "PS2"];
+
+/// How the shell running the CLI was launched, read from its command line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ShellLaunch {
+    pub command: Vec<String>,
+    pub login: bool,
+    /// Arguments other than the login flags
+    pub extra_args: Vec<String>,
+    /// Name of the process that started the shell, such as the terminal or qterm
+    pub launched_by: Option<String>,
+}
+
+impl ShellLaunch {
+    fn new() -> Option<ShellLaunch> {
+        let system = sysinfo::System::new_with_specifics(
+            RefreshKind::nothing().with_processes(ProcessRefreshKind::nothing().with_cmd(UpdateKind::Always)),
+        );
+        let shell = system.process(system.process(sysinfo::get_current_pid().ok()?)?.parent()?)?;
+        let username = format!("/{}", whoami::username());
+        let command = shell
+            .cmd()
+            .iter()
+            .map(|arg| arg.to_string_lossy().replace(&username, "/USER"))
+            .collect::<Vec<_>>();
+        let launched_by = shell
+            .parent()
+            .and_then(|pid| system.process(pid))
+            .map(|process| process.name().to_string_lossy().into_owned());
+        Some(Self::from_command(command, launched_by))
+    }
+
+    fn from_command(command: Vec<String>, launched_by: Option<String>) -> ShellLaunch {
+        // Login shells are started with a leading dash in their name, or with -l or --login.
+        let is_login_flag =
+            |arg: &str| arg == "--login" || (arg.starts_with('-') && !arg.starts_with("--") && arg[1..].contains('l'));
+        let login = command.first().is_some_and(|arg0| arg0.starts_with('-'))
+            || command.iter().skip(1).any(|arg| is_login_flag(arg));
+        let extra_args = command
+            .iter()
+            .skip(1)
+            .filter(|arg| !is_login_flag(arg))
+            .cloned()
+            .collect();
+
+        ShellLaunch {
+            command,
+            login,
+            extra_args,
+            launched_by,
+        }
+    }
+}
+
+/// Differences between the environment the CLI runs in and the one of a freshly started shell.
+/// Only variable names are reported since values may hold secrets, except for `PATH` entries.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct EnvComparison {
+    /// Set in the fresh shell but not in this one
+    pub missing: Vec<String>,
+    /// Set in this shell but not in the fresh one
+    pub extra: Vec<String>,
+    /// Set in both with different values
+    pub different: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub path_missing: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub path_extra: Vec<String>,
+}
+
+impl EnvComparison {
+    fn new(current: &BTreeMap<String, String>, fresh: &BTreeMap<String, String>) -> EnvComparison {
+        let compared = |key: &String| {
+            !SHELL_ENV_IGNORED.contains(&key.as_str())
+                && !crate::util::consts::env_var::ALL.contains(&key.as_str())
+                && !key.starts_with("Q_")
+                && !key.starts_with("QTERM_")
+        };
+        let mut comparison = EnvComparison::default();
+        for (key, value) in fresh.iter().filter(|(key, _)| compared(key)) {
+            match current.get(key) {
+                None => comparison.missing.push(key.clone()),
+                Some(current_value) if current_value != value => comparison.different.push(key.clone()),
+                Some(_) => {},
+            }
+        }
+        comparison.extra = current
+            .keys()
+            .filter(|key| compared(key) && !fresh.contains_key(*key))
+            .cloned()
+            .collect();
+
+        if let (Some(current_path), Some(fresh_path)) = (current.get("PATH"), fresh.get("PATH")) {
+            let current_entries = current_path.split(':').collect::<BTreeSet<_>>();
+            let fresh_entries = fresh_path.split(':').collect::<BTreeSet<_>>();
+            let username = format!("/{}", whoami::username());
+            let sanitize = |entry: &&str| entry.replace(&username, "/USER");
+            comparison.path_missing = fresh_path
+                .split(':')
+                .filter(|entry| !current_entries.contains(entry))
+                .map(|entry| sanitize(&entry))
+                .collect();
+            comparison.path_extra = current_path
+                .split(':')
+                .filter(|entry| !fresh_entries.contains(entry))
+                .map(|entry| sanitize(&entry))
+                .collect();
+        }
+        comparison
+    }
+
+    fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.different.is_empty()
+    }
+}
+
+/// Explains why commands may behave differently in the shell the CLI runs in, such as one started
+/// by qterm, than in a plain terminal.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ShellDiagnostics {
+    pub shell: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub launch: Option<ShellLaunch>,
+    /// Variables set by qterm and the shell integration
+    pub q_env_vars: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compared_to_login_shell: Option<EnvComparison>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compared_to_interactive_shell: Option<EnvComparison>,
+    pub hints: Vec<String>,
+}
+
+impl ShellDiagnostics {
+    pub async fn new(env: &Env) -> ShellDiagnostics {
+        let shell = env.get("SHELL").ok().filter(|shell| !shell.is_empty());
+        let current = std::env::vars().collect::<BTreeMap<_, _>>();
+        let username = format!("/{}", whoami::username());
+        let q_env_vars = current
+            .iter()
+            .filter(|(key, _)| {
+                crate::util::consts::env_var::ALL.contains(&key.as_str())
+                    && (key.starts_with("Q_") || key.starts_with("QTERM_") || key.as_str() == "PROCESS_LAUNCHED_BY_Q")
+            })
+            .map(|(key, value)| (key.clone(), value.replace(&username, "/USER")))
+            .collect();
+
+        let (login_env, interactive_env) = match &shell {
+            Some(shell) => tokio::join!(capture_shell_env(shell, "-lc"), capture_shell_env(shell, "-ic")),
+            None => (None, None),
+        };
+        let mut diagnostics = ShellDiagnostics {
+            shell,
+            launch: ShellLaunch::new(),
+            q_env_vars,
+            compared_to_login_shell: login_env.map(|fresh| EnvComparison::new(&current, &fresh)),
+            compared_to_interactive_shell: interactive_env.map(|fresh| EnvComparison::new(&current, &fresh)),
+            hints: Vec::new(),
+        };
+        diagnostics.hints = diagnostics.hints();
+        diagnostics
+    }
+
+    fn hints(&self) -> Vec<String> {
+        let mut hints = Vec::new();
+        if self.shell.is_none() {
+            hints.push("$SHELL isn't set, so the shell's environment couldn't be compared".to_string());
+        }
+        if self.q_env_vars.contains_key("Q_TERM") || self.q_env_vars.contains_key("QTERM_SESSION_ID") {
+            hints.push("This shell runs inside qterm, compare with `q doctor --shell` in a plain terminal".to_string());
+        }
+        let login = self.launch.as_ref().map(|launch| launch.login);
+        if let (Some(false), Some(comparison)) = (login, &self.compared_to_login_shell) {
+            if !comparison.missing.is_empty() || !comparison.path_missing.is_empty() {
+                hints.push(
+                    "This shell isn't a login shell, so it didn't read files such as ~/.zprofile, \
+                     ~/.bash_profile or the login section of config.fish, which set the missing variables"
+                        .to_string(),
+                );
+            }
+        }
+        if let Some(launch) = self.launch.as_ref().filter(|launch| !launch.extra_args.is_empty()) {
+            hints.push(format!(
+                "The shell was started with extra arguments {}, which may skip startup files",
+                launch.extra_args.join(" ")
+            ));
+        }
+        if let Some(comparison) = &self.compared_to_interactive_shell {
+            if !comparison.different.is_empty() {
+                hints.push(
+                    "Variables changed since the shell started, by the shell integration or commands run in it, \
+                     differ from a freshly started interactive shell"
+                        .to_string(),
+                );
+            }
+        }
+        let all_match = [&self.compared_to_login_shell, &self.compared_to_interactive_shell]
+            .iter()
+            .all(|comparison| comparison.as_ref().is_some_and(EnvComparison::is_empty));
+        if all_match {
+            hints.push("The environment matches freshly started login and interactive shells".to_string());
+        }
+        hints
+    }
+
+    pub fn user_readable(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(&self)
+    }
+}
+
+/// The environment printed by `shell` started with `flag`, or [None] if it failed or timed out.
+async fn capture_shell_env(shell: &str, flag: &str) -> Option<BTreeMap<String, String>> {
+    let output = tokio::time::timeout(
+        SHELL_ENV_TIMEOUT,
+        tokio::process::Command::new(shell)
+            .arg(flag)
+            .arg(format!("echo {SHELL_ENV_MARKER}; env"))
+            .stdin(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .ok()?
+    .ok()?;
+    output
+        .status
+        .success()
+        .then(|| parse_env(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses the output of `env` after [SHELL_ENV_MARKER], joining the lines of multi-line values.
+fn parse_env(output: &str) -> BTreeMap<String, String> {
+    let mut vars = BTreeMap::new();
+    let mut last_key: Option<String> = None;
+    let lines = output
+        .lines()
+        .skip_while(|line| line.trim() != SHELL_ENV_MARKER)
+        .skip(1);
+    for line in lines {
+        let var = line
+            .split_once('=')
+            .filter(|(key, _)| !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+        match (var, &last_key) {
+            (Some((key, value)), _) => {
+                vars.insert(key.to_string(), value.to_string());
+                last_key = Some(key.to_string());
+            },
+            (None, Some(key)) => {
+                if let Some(value) = vars.get_mut(key) {
+                    value.push('\n');
+                    value.push_str(line);
+                }
+            },
+            (None, None) => {},
+        }
+    }
+    vars
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -233,4 +508,101 @@ mod tests {
         let toml = diagnostics.user_readable().unwrap();
         assert!(!toml.is_empty());
     }
+
+    #[test]
+    fn test_shell_launch() {
+        let launch = ShellLaunch::from_command(vec!["-zsh".to_string()], Some("Terminal".to_string()));
+        assert!(launch.login);
+        assert!(launch.extra_args.is_empty());
+
+        let launch = ShellLaunch::from_command(
+            vec!["/bin/bash".to_string(), "-il".to_string(), "--norc".to_string()],
+            None,
+        );
+        assert!(launch.login);
+        assert_eq!(launch.extra_args, vec!["--norc".to_string()]);
+
+        let launch = ShellLaunch::from_command(vec!["zsh".to_string(), "-i".to_string()], None);
+        assert!(!launch.login);
+    }
+
+    #[test]
+    fn test_hints() {
+        let comparison = EnvComparison {
+            missing: vec!["GOPATH".to_string()],
+            different: vec!["PS1".to_string()],
+            ..Default::default()
+        };
+        let diagnostics = ShellDiagnostics {
+            shell: Some("/bin/zsh".to_string()),
+            launch: Some(ShellLaunch::from_command(
+                vec!["zsh".to_string(), "-i".to_string()],
+                None,
+            )),
+            q_env_vars: BTreeMap::new(),
+            compared_to_login_shell: Some(comparison.clone()),
+            compared_to_interactive_shell: Some(comparison),
+            hints: Vec::new(),
+        };
+        assert_eq!(diagnostics.hints(), vec![
+            "This shell isn't a login shell, so it didn't read files such as ~/.zprofile, ~/.bash_profile or the \
+             login section of config.fish, which set the missing variables"
+                .to_string(),
+            "Variables changed since the shell started, by the shell integration or commands run in it, differ \
+             from a freshly started interactive shell"
+                .to_string(),
+        ]);
+
+        let diagnostics = ShellDiagnostics {
+            shell: None,
+            launch: None,
+            q_env_vars: BTreeMap::new(),
+            compared_to_login_shell: Some(EnvComparison::default()),
+            compared_to_interactive_shell: Some(EnvComparison::default()),
+            hints: Vec::new(),
+        };
+        assert_eq!(diagnostics.hints(), vec![
+            "$SHELL isn't set, so the shell's environment couldn't be compared".to_string(),
+            "The environment matches freshly started login and interactive shells".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_env() {
+        let output = "Welcome!\n__Q_SHELL_ENV__\nHOME=/home/me\nMULTI=a\nb\nPATH=/usr/bin:/bin\n";
+        let vars = parse_env(output);
+        assert_eq!(vars.len(), 3);
+        assert_eq!(vars["MULTI"], "a\nb");
+        assert_eq!(vars["PATH"], "/usr/bin:/bin");
+        assert!(parse_env("HOME=/home/me\n").is_empty());
+    }
+
+    #[test]
+    fn test_env_comparison() {
+        let vars = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let current = vars(&[
+            ("PATH", "/usr/bin:/opt/q"),
+            ("EXTRA", "1"),
+            ("SHLVL", "3"),
+            ("LANG", "C"),
+        ]);
+        let login = vars(&[
+            ("PATH", "/opt/homebrew/bin:/usr/bin"),
+            ("JAVA_HOME", "/jdk"),
+            ("SHLVL", "1"),
+            ("LANG", "C"),
+        ]);
+        let comparison = EnvComparison::new(&current, &login);
+        assert_eq!(comparison.missing, vec!["JAVA_HOME".to_string()]);
+        assert_eq!(comparison.extra, vec!["EXTRA".to_string()]);
+        assert_eq!(comparison.different, vec!["PATH".to_string()]);
+        assert_eq!(comparison.path_missing, vec!["/opt/homebrew/bin".to_string()]);
+        assert_eq!(comparison.path_extra, vec!["/opt/q".to_string()]);
+        assert!(EnvComparison::new(&current, &current).is_empty());
+    }
 }
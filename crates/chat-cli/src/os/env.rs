@@ -31,7 +31,8 @@ mod inner {
 
     #[derive(Debug, Clone)]
     pub(super) enum Inner {
-        Real,
+        /// Holds the working directory of the session once it is changed
+        Real(Arc<Mutex<Option<PathBuf>>>),
         Fake(Arc<Mutex<Fake>>),
     }
 
@@ -55,7 +56,7 @@ impl Env {
                 false => Env::from_slice(&[("HOME", ACTIVE_USER_HOME), ("USER", "testuser"), ("PATH", "")]),
             }
         } else {
-            Env(inner::Inner::Real)
+            Env(inner::Inner::Real(Default::default()))
         }
     }
 
@@ -73,7 +74,7 @@ impl Env {
     pub fn get<K: AsRef<str>>(&self, key: K) -> Result<String, VarError> {
         use inner::Inner;
         match &self.0 {
-            Inner::Real => env::var(key.as_ref()),
+            Inner::Real(_) => env::var(key.as_ref()),
             Inner::Fake(fake) => fake
                 .lock()
                 .unwrap()
@@ -87,7 +88,7 @@ impl Env {
     pub fn get_os<K: AsRef<OsStr>>(&self, key: K) -> Option<OsString> {
         use inner::Inner;
         match &self.0 {
-            Inner::Real => env::var_os(key.as_ref()),
+            Inner::Real(_) => env::var_os(key.as_ref()),
             Inner::Fake(fake) => fake
                 .lock()
                 .unwrap()
@@ -108,7 +109,7 @@ impl Env {
         unsafe {
             use inner::Inner;
             match &self.0 {
-                Inner::Real => std::env::set_var(key, value),
+                Inner::Real(_) => std::env::set_var(key, value),
                 Inner::Fake(fake) => {
                     fake.lock().unwrap().vars.insert(
                         key.as_ref().to_str().expect("key must be valid str").to_string(),
@@ -121,7 +122,7 @@ impl Env {
 
     pub fn home(&self) -> Option<PathBuf> {
         match &self.0 {
-            inner::Inner::Real => dirs::home_dir(),
+            inner::Inner::Real(_) => dirs::home_dir(),
             inner::Inner::Fake(fake) => fake.lock().unwrap().vars.get("HOME").map(PathBuf::from),
         }
     }
@@ -129,11 +130,48 @@ impl Env {
     pub fn current_dir(&self) -> Result<PathBuf, io::Error> {
         use inner::Inner;
         match &self.0 {
-            Inner::Real => std::env::current_dir(),
+            Inner::Real(cwd) => match cwd.lock().unwrap().clone() {
+                Some(cwd) => Ok(cwd),
+                None => std::env::current_dir(),
+            },
             Inner::Fake(fake) => Ok(fake.lock().unwrap().cwd.clone()),
         }
     }
 
+    /// Changes the working directory of the session, which [Env::current_dir] returns from then
+    /// on. The working directory of the process stays the same, so commands spawned for the
+    /// session have to be run in [Env::changed_dir].
+    pub fn set_current_dir(&self, path: impl AsRef<std::path::Path>) -> Result<(), io::Error> {
+        use inner::Inner;
+        match &self.0 {
+            Inner::Real(cwd) => {
+                let path = self.current_dir()?.join(path);
+                if !path.is_dir() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("{} is not a directory", path.display()),
+                    ));
+                }
+                *cwd.lock().unwrap() = Some(path);
+                Ok(())
+            },
+            Inner::Fake(fake) => {
+                fake.lock().unwrap().cwd = path.as_ref().to_path_buf();
+                Ok(())
+            },
+        }
+    }
+
+    /// The working directory of the session when it was changed with [Env::set_current_dir] and no
+    /// longer matches the one of the process.
+    pub fn changed_dir(&self) -> Option<PathBuf> {
+        use inner::Inner;
+        match &self.0 {
+            Inner::Real(cwd) => cwd.lock().unwrap().clone(),
+            Inner::Fake(_) => None,
+        }
+    }
+
     pub fn set_current_dir_for_test(&self, path: PathBuf) {
         use inner::Inner;
         if let Inner::Fake(fake) = &self.0 {
@@ -144,7 +182,7 @@ impl Env {
     pub fn current_exe(&self) -> Result<PathBuf, io::Error> {
         use inner::Inner;
         match &self.0 {
-            Inner::Real => std::env::current_exe(),
+            Inner::Real(_) => std::env::current_exe(),
             Inner::Fake(fake) => Ok(fake.lock().unwrap().current_exe.clone()),
         }
     }
@@ -212,4 +250,22 @@ mod tests {
         let env = Env::from_slice(&[]);
         assert_eq!(env.current_dir().unwrap(), PathBuf::from("/"));
     }
+
+    #[test]
+    fn test_set_current_dir_keeps_process_dir() {
+        let process_dir = std::env::current_dir().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let env = Env(inner::Inner::Real(Default::default()));
+        assert_eq!(env.current_dir().unwrap(), process_dir);
+        assert_eq!(env.changed_dir(), None);
+
+        env.set_current_dir(dir.path()).unwrap();
+        assert_eq!(env.current_dir().unwrap(), dir.path());
+        assert_eq!(env.changed_dir().as_deref(), Some(dir.path()));
+        assert_eq!(std::env::current_dir().unwrap(), process_dir);
+
+        // Clones share the session directory, like the clones of Os do
+        assert_eq!(env.clone().current_dir().unwrap(), dir.path());
+        assert!(env.set_current_dir(dir.path().join("missing")).is_err());
+    }
 }
@@ -12,6 +12,7 @@ use std::sync::{
 
 use tempfile::TempDir;
 use tokio::fs;
+use tokio::io::AsyncWriteExt as _;
 
 pub const WINDOWS_USER_HOME: &str = "C:\\Users\\testuser";
 pub const UNIX_USER_HOME: &str = "/home/testuser";
@@ -232,6 +233,28 @@ impl Fs {
         }
     }
 
+    /// Appends `contents` to the end of a file, creating it if it doesn't exist.
+    pub async fn append_file(&self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+        async fn append_real(path: &Path, contents: &[u8]) -> io::Result<()> {
+            let mut file = fs::OpenOptions::new().create(true).append(true).open(path).await?;
+            file.write_all(contents).await
+        }
+
+        match self {
+            Self::Real => append_real(path.as_ref(), contents.as_ref()).await,
+            Self::Chroot(root) => append_real(&append(root.path(), path), contents.as_ref()).await,
+            Self::Fake(map) => {
+                let Ok(mut lock) = map.lock() else {
+                    return Err(io::Error::other("poisoned lock"));
+                };
+                lock.entry(path.as_ref().to_owned())
+                    .or_default()
+                    .extend_from_slice(contents.as_ref());
+                Ok(())
+            },
+        }
+    }
+
     /// Removes a file from the filesystem.
     ///
     /// Note that there is no guarantee that the file is immediately deleted (e.g.
@@ -247,6 +270,28 @@ impl Fs {
         }
     }
 
+    /// Moves a file or directory to the trash of the OS, from where the user can restore it.
+    ///
+    /// With [Fs::Chroot] the trash is the `.Trash` directory at the root, so that tests don't
+    /// fill the trash of the user running them.
+    pub async fn move_to_trash(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        match self {
+            Self::Real => {
+                let path = path.as_ref().to_path_buf();
+                tokio::task::spawn_blocking(move || trash::delete(path))
+                    .await?
+                    .map_err(io::Error::other)
+            },
+            Self::Chroot(root) => {
+                let trash = root.path().join(".Trash");
+                fs::create_dir_all(&trash).await?;
+                let name = path.as_ref().file_name().ok_or(io::ErrorKind::InvalidInput)?;
+                fs::rename(append(root.path(), &path), trash.join(name)).await
+            },
+            Self::Fake(_) => panic!("unimplemented"),
+        }
+    }
+
     /// Removes a directory at this path, after removing all its contents. Use carefully!
     ///
     /// This is a proxy to [`tokio::fs::remove_dir_all`].
@@ -468,6 +513,10 @@ mod tests {
         fs.write(dir.join("write"), b"write").await.unwrap();
         assert_eq!(fs.read(dir.join("write")).await.unwrap(), b"write");
         assert_eq!(fs.read_to_string(dir.join("write")).await.unwrap(), "write");
+        fs.append_file(dir.join("write"), b" more").await.unwrap();
+        fs.append_file(dir.join("append"), b"append").await.unwrap();
+        assert_eq!(fs.read_to_string(dir.join("write")).await.unwrap(), "write more");
+        assert_eq!(fs.read_to_string(dir.join("append")).await.unwrap(), "append");
     }
 
     #[tokio::test]
@@ -480,6 +529,10 @@ mod tests {
         fs.write(dir.path().join("write"), b"write").await.unwrap();
         assert_eq!(fs.read(dir.path().join("write")).await.unwrap(), b"write");
         assert_eq!(fs.read_to_string(dir.path().join("write")).await.unwrap(), "write");
+        fs.append_file(dir.path().join("write"), b" more").await.unwrap();
+        fs.append_file(dir.path().join("append"), b"append").await.unwrap();
+        assert_eq!(fs.read_to_string(dir.path().join("write")).await.unwrap(), "write more");
+        assert_eq!(fs.read_to_string(dir.path().join("append")).await.unwrap(), "append");
     }
 
     macro_rules! test_append_cases {
@@ -13,6 +13,7 @@ pub use sysinfo::SysInfo;
 use crate::api_client::ApiClient;
 use crate::database::Database;
 use crate::telemetry::TelemetryThread;
+use crate::util::startup_profile::stage;
 
 const WINDOWS_USER_HOME: &str = "C:\\Users\\testuser";
 const UNIX_USER_HOME: &str = "/home/testuser";
@@ -44,9 +45,9 @@ impl Os {
     pub async fn new() -> Result<Self> {
         let env = Env::new();
         let fs = Fs::new();
-        let mut database = Database::new().await?;
-        let client = ApiClient::new(&env, &fs, &mut database, None).await?;
-        let telemetry = TelemetryThread::new(&env, &fs, &mut database).await?;
+        let mut database = stage("settings load", Database::new()).await?;
+        let client = stage("api client init", ApiClient::new(&env, &fs, &mut database, None)).await?;
+        let telemetry = stage("telemetry init", TelemetryThread::new(&env, &fs, &mut database)).await?;
 
         Ok(Self {
             env,
@@ -3,6 +3,7 @@ use std::sync::{
     Arc,
     LazyLock,
 };
+use std::time::Duration;
 
 use reqwest::Client;
 use rustls::{
@@ -28,11 +29,23 @@ pub enum RequestError {
     UrlParseError(#[from] ParseError),
 }
 
+/// How long idle connections are kept open for reuse, long enough to span the time a user takes
+/// to write their next prompt
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Interval of keep-alive pings on idle connections so that they aren't dropped by proxies and
+/// load balancers between turns
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
 pub fn new_client() -> Result<Client, RequestError> {
     Ok(Client::builder()
         .use_preconfigured_tls(client_config())
         .user_agent(USER_AGENT.chars().filter(|c| c.is_ascii_graphic()).collect::<String>())
         .cookie_store(true)
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .tcp_keepalive(KEEP_ALIVE_INTERVAL)
+        .http2_keep_alive_interval(KEEP_ALIVE_INTERVAL)
+        .http2_keep_alive_while_idle(true)
         .build()?)
 }
 
@@ -54,11 +67,14 @@ fn client_config() -> ClientConfig {
         .cloned()
         .unwrap_or_else(|| Arc::new(rustls::crypto::ring::default_provider()));
 
-    ClientConfig::builder_with_provider(provider)
+    let mut config = ClientConfig::builder_with_provider(provider)
         .with_protocol_versions(rustls::DEFAULT_VERSIONS)
         .expect("Failed to set supported TLS versions")
         .with_root_certificates(create_default_root_cert_store())
-        .with_no_client_auth()
+        .with_no_client_auth();
+    // reqwest doesn't set ALPN on preconfigured TLS, without it HTTP/2 is never negotiated.
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    config
 }
 
 static USER_AGENT: LazyLock<String> = LazyLock::new(|| {
@@ -83,6 +99,14 @@ mod tests {
         new_client().unwrap();
     }
 
+    #[test]
+    fn test_client_config_negotiates_http2() {
+        assert_eq!(client_config().alpn_protocols, vec![
+            b"h2".to_vec(),
+            b"http/1.1".to_vec()
+        ]);
+    }
+
     #[tokio::test]
     async fn request_test() {
         let mut server = mockito::Server::new_async().await;
@@ -0,0 +1,192 @@
+//! Which telemetry is collected, and whether it leaves the machine.
+
+use std::collections::BTreeMap;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::core::EventType;
+use crate::database::settings::{
+    Setting,
+    Settings,
+};
+
+/// Kinds of telemetry that can be enabled and disabled independently
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    clap::ValueEnum,
+    strum::Display,
+    strum::EnumIter,
+)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "lowercase")]
+pub enum TelemetryCategory {
+    /// Failed requests, authentication errors and other failures
+    Errors,
+    /// Which commands, tools and features are used
+    Usage,
+    /// Response latency and turn durations
+    Performance,
+}
+
+impl TelemetryCategory {
+    pub fn setting(self) -> Setting {
+        match self {
+            Self::Errors => Setting::TelemetryErrors,
+            Self::Usage => Setting::TelemetryUsage,
+            Self::Performance => Setting::TelemetryPerformance,
+        }
+    }
+}
+
+impl EventType {
+    pub fn category(&self) -> TelemetryCategory {
+        match self {
+            Self::AuthFailed { .. } | Self::RefreshCredentials { .. } | Self::MessageResponseError { .. } => {
+                TelemetryCategory::Errors
+            },
+            Self::ChatAddedMessage { .. } | Self::RecordUserTurnCompletion { .. } => TelemetryCategory::Performance,
+            Self::UserLoggedIn {}
+            | Self::CliSubcommandExecuted { .. }
+            | Self::ChatSlashCommandExecuted { .. }
+            | Self::ChatStart { .. }
+            | Self::ChatEnd { .. }
+            | Self::TangentModeSession { .. }
+            | Self::ChatFeedback { .. }
+            | Self::ToolUseSuggested { .. }
+            | Self::AgentContribution { .. }
+            | Self::McpServerInit { .. }
+            | Self::AgentConfigInit { .. }
+            | Self::DidSelectProfile { .. }
+            | Self::ProfileState { .. }
+            | Self::DailyHeartbeat {} => TelemetryCategory::Usage,
+        }
+    }
+}
+
+/// The telemetry the user agreed to, from `telemetry.*` settings and `Q_DISABLE_TELEMETRY`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TelemetryConsent {
+    /// `telemetry.enabled`, which turns off every category
+    pub enabled: bool,
+    /// Whether `Q_DISABLE_TELEMETRY` is set, which overrides the settings
+    pub disabled_by_env: bool,
+    /// Record telemetry for `q stats` without sending it
+    pub local_only: bool,
+    pub categories: BTreeMap<TelemetryCategory, bool>,
+}
+
+impl TelemetryConsent {
+    pub fn from_settings(settings: &Settings) -> Self {
+        use strum::IntoEnumIterator;
+
+        Self {
+            enabled: settings.get_bool(Setting::TelemetryEnabled).unwrap_or(true),
+            disabled_by_env: crate::util::env_var::is_telemetry_disabled(),
+            local_only: settings.get_bool(Setting::TelemetryLocalOnly).unwrap_or(false),
+            categories: TelemetryCategory::iter()
+                .map(|category| (category, settings.get_bool(category.setting()).unwrap_or(true)))
+                .collect(),
+        }
+    }
+
+    /// Whether events of `category` are collected, locally or to be sent.
+    pub fn collects(&self, category: TelemetryCategory) -> bool {
+        self.enabled && !self.disabled_by_env && self.categories.get(&category).copied().unwrap_or(true)
+    }
+
+    /// Whether collected events are sent.
+    pub fn transmits(&self) -> bool {
+        self.enabled && !self.disabled_by_env && !self.local_only
+    }
+}
+
+/// Counts of the telemetry events recorded on this machine, shown by `q stats`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalStats {
+    /// Unix timestamp of the first recorded event
+    pub since: Option<i64>,
+    pub events: BTreeMap<String, EventStats>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventStats {
+    pub category: TelemetryCategory,
+    pub count: u64,
+    /// Unix timestamp of the last recorded event
+    pub last_recorded: i64,
+}
+
+impl LocalStats {
+    pub fn record(&mut self, event: &EventType, now: i64) {
+        self.since.get_or_insert(now);
+        let name: &'static str = event.into();
+        let stats = self.events.entry(name.to_string()).or_insert(EventStats {
+            category: event.category(),
+            count: 0,
+            last_recorded: now,
+        });
+        stats.count += 1;
+        stats.last_recorded = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_consent() {
+        let mut settings = Settings::new().await.unwrap();
+        let consent = TelemetryConsent::from_settings(&settings);
+        assert!(consent.collects(TelemetryCategory::Usage));
+        assert!(consent.transmits());
+
+        settings.set(Setting::TelemetryPerformance, false).await.unwrap();
+        settings.set(Setting::TelemetryLocalOnly, true).await.unwrap();
+        let consent = TelemetryConsent::from_settings(&settings);
+        assert!(consent.collects(TelemetryCategory::Errors));
+        assert!(!consent.collects(TelemetryCategory::Performance));
+        assert!(!consent.transmits());
+
+        settings.set(Setting::TelemetryEnabled, false).await.unwrap();
+        let consent = TelemetryConsent::from_settings(&settings);
+        assert!(!consent.collects(TelemetryCategory::Errors));
+    }
+
+    #[test]
+    fn test_local_stats() {
+        let mut stats = LocalStats::default();
+        stats.record(&EventType::DailyHeartbeat {}, 10);
+        stats.record(&EventType::DailyHeartbeat {}, 20);
+        stats.record(
+            &EventType::AuthFailed {
+                auth_method: "idc".into(),
+                oauth_flow: "pkce".into(),
+                error_type: "timeout".into(),
+                error_code: None,
+            },
+            30,
+        );
+        assert_eq!(stats.since, Some(10));
+        assert_eq!(stats.events["dailyHeartbeat"], EventStats {
+            category: TelemetryCategory::Usage,
+            count: 2,
+            last_recorded: 20,
+        });
+        assert_eq!(stats.events["authFailed"].category, TelemetryCategory::Errors);
+    }
+}
@@ -519,6 +519,25 @@ impl Event {
                 }
                 .into_metric_datum(),
             ),
+            EventType::ChatFeedback {
+                conversation_id,
+                sentiment,
+                ..
+            } => Some(
+                CodewhispererterminalChatSlashCommandExecuted {
+                    create_time: self.created_time,
+                    value: None,
+                    credential_start_url: self.credential_start_url.map(Into::into),
+                    sso_region: self.sso_region.map(Into::into),
+                    amazonq_conversation_id: Some(conversation_id.into()),
+                    codewhispererterminal_chat_slash_command: Some("feedback".to_string().into()),
+                    codewhispererterminal_chat_slash_subcommand: Some(sentiment.to_string().into()),
+                    result: Some(TelemetryResult::Succeeded.to_string().into()),
+                    reason: None,
+                    codewhispererterminal_in_cloudshell: None,
+                }
+                .into_metric_datum(),
+            ),
             EventType::DailyHeartbeat {} => Some(
                 AmazonqcliDailyHeartbeat {
                     create_time: self.created_time,
@@ -576,6 +595,14 @@ pub struct ChatAddedMessageParams {
     pub message_meta_tags: Vec<MessageMetaTag>,
 }
 
+/// How the user rated a response with `/feedback`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Display, serde::Serialize, serde::Deserialize)]
+#[strum(serialize_all = "lowercase")]
+pub enum FeedbackSentiment {
+    Positive,
+    Negative,
+}
+
 /// Optional fields for tangent mode session telemetry event.
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Default)]
 pub struct TangentModeSessionArgs {
@@ -608,9 +635,10 @@ pub struct AgentConfigInitArgs {
     pub launched_agent: String,
 }
 
-#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, strum::IntoStaticStr)]
 #[serde(rename_all = "camelCase")]
 #[serde(tag = "type")]
+#[strum(serialize_all = "camelCase")]
 pub enum EventType {
     UserLoggedIn {},
     AuthFailed {
@@ -723,6 +751,12 @@ pub enum EventType {
         message_id: Option<String>,
         context_file_length: Option<usize>,
     },
+    ChatFeedback {
+        conversation_id: String,
+        sentiment: FeedbackSentiment,
+        /// The comment of the user, followed by the conversation if they agreed to share it
+        comment: Option<String>,
+    },
     DailyHeartbeat {},
 }
 
@@ -1,4 +1,5 @@
 pub mod cognito;
+pub mod consent;
 pub mod core;
 pub mod definitions;
 pub mod endpoint;
@@ -7,6 +8,7 @@ mod install_method;
 use core::{
     AgentConfigInitArgs,
     ChatAddedMessageParams,
+    FeedbackSentiment,
     RecordUserTurnCompletionArgs,
     TangentModeSessionArgs,
     ToolUseEventBuilder,
@@ -27,13 +29,22 @@ use amzn_toolkit_telemetry_client::config::{
     Region,
 };
 use amzn_toolkit_telemetry_client::error::DisplayErrorContext;
-use amzn_toolkit_telemetry_client::types::AwsProduct;
+use amzn_toolkit_telemetry_client::types::{
+    AwsProduct,
+    MetadataEntry,
+    Sentiment,
+};
 use amzn_toolkit_telemetry_client::{
     Client as ToolkitTelemetryClient,
     Config,
 };
 use aws_credential_types::provider::SharedCredentialsProvider;
 use cognito::CognitoProvider;
+pub use consent::{
+    LocalStats,
+    TelemetryCategory,
+    TelemetryConsent,
+};
 use endpoint::StaticEndpoint;
 pub use install_method::{
     InstallMethod,
@@ -112,6 +123,8 @@ impl From<ApiClientError> for TelemetryError {
 }
 
 const PRODUCT: &str = "CodeWhisperer";
+/// Longest comment accepted with feedback, in characters
+const MAX_FEEDBACK_CHARS: usize = 2000;
 const PRODUCT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// A IDE toolkit telemetry stage
@@ -359,6 +372,23 @@ impl TelemetryThread {
         Ok(self.tx.send(telemetry_event)?)
     }
 
+    /// Sends a rating of a response. `comment` is sent as is, so it must already be redacted.
+    pub async fn send_chat_feedback(
+        &self,
+        database: &Database,
+        conversation_id: String,
+        sentiment: FeedbackSentiment,
+        comment: Option<String>,
+    ) -> Result<(), TelemetryError> {
+        let mut telemetry_event = Event::new(EventType::ChatFeedback {
+            conversation_id,
+            sentiment,
+            comment,
+        });
+        set_event_metadata(database, &mut telemetry_event).await;
+        Ok(self.tx.send(telemetry_event)?)
+    }
+
     pub async fn send_tool_use_suggested(
         &self,
         database: &Database,
@@ -507,15 +537,16 @@ async fn set_event_metadata(database: &Database, event: &mut Event) {
 struct TelemetryClient {
     client_id: Uuid,
     telemetry_enabled: bool,
+    consent: TelemetryConsent,
+    database: Database,
     codewhisperer_client: Option<ApiClient>,
     toolkit_telemetry_client: Option<ToolkitTelemetryClient>,
 }
 
 impl TelemetryClient {
     async fn new(env: &Env, fs: &Fs, database: &mut Database) -> Result<Self, TelemetryError> {
-        let telemetry_enabled = !cfg!(test)
-            && !crate::util::env_var::is_telemetry_disabled()
-            && database.settings.get_bool(Setting::TelemetryEnabled).unwrap_or(true);
+        let consent = TelemetryConsent::from_settings(&database.settings);
+        let telemetry_enabled = !cfg!(test) && consent.transmits();
 
         // If telemetry is disabled we do not emit using toolkit_telemetry
         let toolkit_telemetry_client = if telemetry_enabled {
@@ -574,25 +605,60 @@ impl TelemetryClient {
         Ok(Self {
             client_id: client_id(env, database, telemetry_enabled)?,
             telemetry_enabled,
+            consent,
+            database: database.clone(),
             toolkit_telemetry_client,
             codewhisperer_client,
         })
     }
 
-    /// Sends a telemetry event to both the CW and toolkit API's. If the clients do not exist, then
-    /// telemetry is not sent.
+    /// Records a telemetry event for `q stats` and sends it to both the CW and toolkit API's. If
+    /// the clients do not exist, then telemetry is not sent.
     ///
     /// See [TelemetryClient::new] for which conditions the clients are created for.
     async fn send_event(&self, event: Event) {
-        self.send_cw_telemetry_event(&event).await;
-        self.send_telemetry_toolkit_metric(event).await;
+        let collected = self.consent.collects(event.ty.category());
+        if collected {
+            self.record_local_stats(&event);
+        }
+        if self.consent.local_only {
+            trace!("not sending telemetry - local only mode");
+            return;
+        }
+
+        self.send_cw_telemetry_event(&event, collected).await;
+        if collected {
+            self.send_toolkit_feedback(&event).await;
+            self.send_telemetry_toolkit_metric(event).await;
+        }
+    }
+
+    fn record_local_stats(&self, event: &Event) {
+        let mut stats = match self.database.get_telemetry_local_stats() {
+            Ok(stats) => stats.unwrap_or_default(),
+            Err(err) => {
+                error!(%err, "Failed to read local telemetry stats");
+                return;
+            },
+        };
+        let now = event
+            .created_time
+            .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+            .map_or(0, |duration| duration.as_secs() as i64);
+        stats.record(&event.ty, now);
+        if let Err(err) = self.database.set_telemetry_local_stats(&stats) {
+            error!(%err, "Failed to record local telemetry stats");
+        }
     }
 
-    async fn send_cw_telemetry_event(&self, event: &Event) {
+    /// Sends the CW counterpart of `event`, if any. CW events are sent with an opt out preference
+    /// when the event's category isn't `collected`.
+    async fn send_cw_telemetry_event(&self, event: &Event, collected: bool) {
         let Some(codewhisperer_client) = self.codewhisperer_client.clone() else {
             trace!("not sending cw metric - client does not exist");
             return;
         };
+        let telemetry_enabled = self.telemetry_enabled && collected;
 
         match &event.ty {
             EventType::ChatAddedMessage {
@@ -631,14 +697,9 @@ impl TelemetryClient {
                 };
 
                 let event = TelemetryEvent::ChatAddMessageEvent(chat_add_message_event);
-                debug!(
-                    ?event,
-                    ?user_context,
-                    telemetry_enabled = self.telemetry_enabled,
-                    "Sending cw telemetry event"
-                );
+                debug!(?event, ?user_context, telemetry_enabled, "Sending cw telemetry event");
                 if let Err(err) = codewhisperer_client
-                    .send_telemetry_event(event, user_context, self.telemetry_enabled, model.to_owned())
+                    .send_telemetry_event(event, user_context, telemetry_enabled, model.to_owned())
                     .await
                 {
                     error!(err =% DisplayErrorContext(err), "Failed to send cw telemetry event");
@@ -667,14 +728,9 @@ impl TelemetryClient {
                 };
 
                 let event = TelemetryEvent::ChatInteractWithMessageEvent(chat_interact_event);
-                debug!(
-                    ?event,
-                    ?user_context,
-                    telemetry_enabled = self.telemetry_enabled,
-                    "Sending cw telemetry event"
-                );
+                debug!(?event, ?user_context, telemetry_enabled, "Sending cw telemetry event");
                 if let Err(err) = codewhisperer_client
-                    .send_telemetry_event(event, user_context, self.telemetry_enabled, None)
+                    .send_telemetry_event(event, user_context, telemetry_enabled, None)
                     .await
                 {
                     error!(err =% DisplayErrorContext(err), "Failed to send cw telemetry event");
@@ -718,6 +774,55 @@ impl TelemetryClient {
         }
     }
 
+    /// Posts the feedback of a [EventType::ChatFeedback], which is sent in addition to its metric.
+    async fn send_toolkit_feedback(&self, event: &Event) {
+        let EventType::ChatFeedback {
+            conversation_id,
+            sentiment,
+            comment,
+        } = &event.ty
+        else {
+            return;
+        };
+        let Some(toolkit_telemetry_client) = self.toolkit_telemetry_client.clone() else {
+            trace!("not sending toolkit feedback - client does not exist");
+            return;
+        };
+
+        let sentiment = match sentiment {
+            FeedbackSentiment::Positive => Sentiment::Positive,
+            FeedbackSentiment::Negative => Sentiment::Negative,
+        };
+        let comment = comment
+            .as_deref()
+            .map(|comment| match comment.char_indices().nth(MAX_FEEDBACK_CHARS) {
+                Some((end, _)) => &comment[..end],
+                None => comment,
+            });
+
+        debug!(?sentiment, "Sending toolkit feedback");
+        if let Err(err) = toolkit_telemetry_client
+            .post_feedback()
+            .aws_product(AwsProduct::CodewhispererTerminal)
+            .aws_product_version(env!("CARGO_PKG_VERSION"))
+            .os(std::env::consts::OS)
+            .os_version(os_version().map(|v| v.to_string()).unwrap_or_default())
+            .metadata(
+                MetadataEntry::builder()
+                    .key("amazonqConversationId")
+                    .value(conversation_id)
+                    .build(),
+            )
+            .sentiment(sentiment)
+            .set_comment(comment.map(str::to_string))
+            .send()
+            .await
+            .map_err(DisplayErrorContext)
+        {
+            error!(%err, "Failed to post toolkit feedback");
+        }
+    }
+
     fn user_context(&self) -> Option<UserContext> {
         let operating_system = match std::env::consts::OS {
             "linux" => OperatingSystem::Linux,
@@ -0,0 +1,357 @@
+//! Copies of files taken before the agent overwrites them.
+//!
+//! Every `fs_write` first copies the file it is about to change into
+//! `~/.aws/amazonq/backups/<conversation id>/<timestamp>/`, alongside a manifest recording where
+//! each copy came from, so that changes can be reverted with `q backups restore` whether or not the
+//! files are tracked by git. Backups older than `backups.maxAgeDays`, and the oldest ones once all
+//! of them take more than `backups.maxSizeMb`, are removed whenever a new one is taken.
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use eyre::{
+    Result,
+    bail,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tracing::warn;
+
+use crate::database::settings::Setting;
+use crate::os::Os;
+use crate::util::paths::PathResolver;
+
+const MANIFEST_FILE: &str = "manifest.json";
+const DEFAULT_MAX_AGE_DAYS: usize = 30;
+const DEFAULT_MAX_SIZE_MB: usize = 1024;
+
+/// A file copied into a backup.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupEntry {
+    /// Name of the copy within the backup directory
+    pub index: usize,
+    /// Path of the original file
+    pub path: PathBuf,
+    /// Whether the file existed, restoring a file that didn't removes it
+    pub existed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupManifest {
+    pub conversation_id: String,
+    pub created_at: String,
+    pub files: Vec<BackupEntry>,
+}
+
+/// A backup on disk, identified by `<conversation id>/<timestamp>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backup {
+    pub id: String,
+    pub dir: PathBuf,
+    pub manifest: BackupManifest,
+}
+
+/// Limits on the backups that are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Retention {
+    /// Backups older than this are removed
+    pub max_age: Option<chrono::Duration>,
+    /// The oldest backups are removed while all of them take more bytes than this
+    pub max_size: Option<u64>,
+}
+
+impl Retention {
+    /// Limits in days and megabytes, 0 for no limit.
+    pub fn new(max_age_days: usize, max_size_mb: usize) -> Self {
+        Self {
+            max_age: (max_age_days > 0).then(|| chrono::Duration::days(max_age_days as i64)),
+            max_size: (max_size_mb > 0).then(|| max_size_mb as u64 * 1024 * 1024),
+        }
+    }
+
+    /// The limits set with `backups.maxAgeDays` and `backups.maxSizeMb`.
+    pub fn from_settings(os: &Os) -> Self {
+        let settings = &os.database.settings;
+        Self::new(
+            settings.get_int_or(Setting::BackupsMaxAgeDays, DEFAULT_MAX_AGE_DAYS),
+            settings.get_int_or(Setting::BackupsMaxSizeMb, DEFAULT_MAX_SIZE_MB),
+        )
+    }
+}
+
+/// Copies `paths` into a new backup of the conversation and returns its id.
+pub async fn create(os: &Os, conversation_id: &str, paths: &[PathBuf]) -> Result<String> {
+    let conversation_dir = PathResolver::new(os).global().backups_dir()?.join(conversation_id);
+    os.fs.create_dir_all(&conversation_dir).await?;
+
+    // Backups taken within the same millisecond get a suffix.
+    let now = chrono::Utc::now();
+    let timestamp = now.format("%Y%m%dT%H%M%S%.3fZ").to_string();
+    let mut name = timestamp.clone();
+    let mut attempt = 1;
+    let dir = loop {
+        let dir = conversation_dir.join(&name);
+        match os.fs.create_dir(&dir).await {
+            Ok(()) => break dir,
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+                name = format!("{timestamp}-{attempt}");
+                attempt += 1;
+            },
+            Err(err) => return Err(err.into()),
+        }
+    };
+
+    let mut files = Vec::with_capacity(paths.len());
+    for (index, path) in paths.iter().enumerate() {
+        let existed = os.fs.exists(path);
+        if existed {
+            os.fs.copy(path, dir.join(index.to_string())).await?;
+        }
+        files.push(BackupEntry {
+            index,
+            path: path.clone(),
+            existed,
+        });
+    }
+    let manifest = BackupManifest {
+        conversation_id: conversation_id.to_string(),
+        created_at: now.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        files,
+    };
+    os.fs
+        .write(dir.join(MANIFEST_FILE), serde_json::to_vec_pretty(&manifest)?)
+        .await?;
+
+    // The backup is taken by now, failing to remove old ones must not stop the write
+    if let Err(err) = prune(os, &Retention::from_settings(os)).await {
+        warn!(?err, "failed to prune backups");
+    }
+
+    Ok(format!("{conversation_id}/{name}"))
+}
+
+/// Removes the backups that are over the limits of `retention`, oldest first, and returns them. The
+/// newest backup is always kept.
+pub async fn prune(os: &Os, retention: &Retention) -> Result<Vec<Backup>> {
+    let backups = list(os, None).await?;
+    let mut sizes = Vec::with_capacity(backups.len());
+    for backup in &backups {
+        sizes.push(dir_size(os, &backup.dir).await?);
+    }
+    let mut total_size = sizes.iter().sum::<u64>();
+
+    let now = chrono::Utc::now();
+    let prunable = backups.len().saturating_sub(1);
+    let mut removed = Vec::new();
+    for (backup, size) in backups.into_iter().zip(sizes).take(prunable) {
+        let expired = retention.max_age.is_some_and(|max_age| {
+            chrono::DateTime::parse_from_rfc3339(&backup.manifest.created_at)
+                .is_ok_and(|created_at| now.signed_duration_since(created_at.with_timezone(&chrono::Utc)) > max_age)
+        });
+        let oversized = retention.max_size.is_some_and(|max_size| total_size > max_size);
+        if expired || oversized {
+            os.fs.remove_dir_all(&backup.dir).await?;
+            total_size -= size;
+            removed.push(backup);
+        }
+    }
+    Ok(removed)
+}
+
+/// Returns every backup, oldest first, optionally only those of one conversation.
+pub async fn list(os: &Os, conversation_id: Option<&str>) -> Result<Vec<Backup>> {
+    let root = PathResolver::new(os).global().backups_dir()?;
+    let conversations = match conversation_id {
+        Some(id) => vec![id.to_string()],
+        None => dir_names(os, &root).await?,
+    };
+
+    let mut backups = Vec::new();
+    for conversation in conversations {
+        for name in dir_names(os, &root.join(&conversation)).await? {
+            let dir = root.join(&conversation).join(&name);
+            let Ok(content) = os.fs.read(dir.join(MANIFEST_FILE)).await else {
+                continue;
+            };
+            let Ok(manifest) = serde_json::from_slice::<BackupManifest>(&content) else {
+                continue;
+            };
+            backups.push(Backup {
+                id: format!("{conversation}/{name}"),
+                dir,
+                manifest,
+            });
+        }
+    }
+    backups.sort_by(|a, b| a.manifest.created_at.cmp(&b.manifest.created_at).then(a.id.cmp(&b.id)));
+    Ok(backups)
+}
+
+/// Finds a backup by id, or by timestamp alone when it is unique.
+pub async fn find(os: &Os, id: &str) -> Result<Backup> {
+    let matching = list(os, None)
+        .await?
+        .into_iter()
+        .filter(|backup| backup.id == id || backup.id.rsplit('/').next() == Some(id))
+        .collect::<Vec<_>>();
+    match <[Backup; 1]>::try_from(matching) {
+        Ok([backup]) => Ok(backup),
+        Err(matching) if matching.is_empty() => bail!("No backup named '{id}'"),
+        Err(_) => bail!("'{id}' matches several backups, use the full id '<conversation id>/<timestamp>'"),
+    }
+}
+
+/// Puts the files of a backup back where they were, or only `only` if given, and returns the paths
+/// that were restored.
+pub async fn restore(os: &Os, backup: &Backup, only: Option<&Path>) -> Result<Vec<PathBuf>> {
+    let entries = backup
+        .manifest
+        .files
+        .iter()
+        .filter(|entry| only.is_none_or(|only| entry.path == only))
+        .collect::<Vec<_>>();
+    if entries.is_empty() {
+        if let Some(only) = only {
+            bail!("Backup '{}' has no copy of {}", backup.id, only.display());
+        }
+    }
+
+    let mut restored = Vec::with_capacity(entries.len());
+    for entry in entries {
+        if entry.existed {
+            if let Some(parent) = entry.path.parent() {
+                os.fs.create_dir_all(parent).await?;
+            }
+            os.fs
+                .copy(backup.dir.join(entry.index.to_string()), &entry.path)
+                .await?;
+        } else if os.fs.exists(&entry.path) {
+            os.fs.remove_file(&entry.path).await?;
+        }
+        restored.push(entry.path.clone());
+    }
+    Ok(restored)
+}
+
+async fn dir_size(os: &Os, dir: &Path) -> Result<u64> {
+    let mut read_dir = os.fs.read_dir(dir).await?;
+    let mut size = 0;
+    while let Some(entry) = read_dir.next_entry().await? {
+        size += entry.metadata().await?.len();
+    }
+    Ok(size)
+}
+
+async fn dir_names(os: &Os, dir: &Path) -> Result<Vec<String>> {
+    let mut read_dir = match os.fs.read_dir(dir).await {
+        Ok(read_dir) => read_dir,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+    let mut names = Vec::new();
+    while let Some(entry) = read_dir.next_entry().await? {
+        if entry.file_type().await?.is_dir() {
+            names.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_create_and_restore() {
+        let os = Os::new().await.unwrap();
+        os.fs.create_dir_all("/project").await.unwrap();
+        let existing = PathBuf::from("/project/existing.txt");
+        let new = PathBuf::from("/project/new.txt");
+        os.fs.write(&existing, "original").await.unwrap();
+
+        let id = create(&os, "conv", &[existing.clone(), new.clone()]).await.unwrap();
+        assert!(id.starts_with("conv/"));
+        os.fs.write(&existing, "changed").await.unwrap();
+        os.fs.write(&new, "created").await.unwrap();
+
+        let backup = find(&os, &id).await.unwrap();
+        assert_eq!(backup.manifest.files.len(), 2);
+        assert!(!backup.manifest.files[1].existed);
+
+        let restored = restore(&os, &backup, Some(&existing)).await.unwrap();
+        assert_eq!(restored, vec![existing.clone()]);
+        assert_eq!(os.fs.read_to_string(&existing).await.unwrap(), "original");
+        assert!(os.fs.exists(&new));
+
+        restore(&os, &backup, None).await.unwrap();
+        assert!(!os.fs.exists(&new));
+        assert!(
+            restore(&os, &backup, Some(Path::new("/project/other.txt")))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_and_find() {
+        let os = Os::new().await.unwrap();
+        assert!(list(&os, None).await.unwrap().is_empty());
+
+        let first = create(&os, "a", &[PathBuf::from("/x")]).await.unwrap();
+        let second = create(&os, "a", &[PathBuf::from("/x")]).await.unwrap();
+        let third = create(&os, "b", &[PathBuf::from("/y")]).await.unwrap();
+        assert_ne!(first, second);
+
+        let ids = list(&os, None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|b| b.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![first.clone(), second.clone(), third.clone()]);
+        assert_eq!(list(&os, Some("b")).await.unwrap().len(), 1);
+
+        let timestamp = third.rsplit('/').next().unwrap();
+        assert_eq!(find(&os, timestamp).await.unwrap().id, third);
+        assert!(find(&os, "missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_prune() {
+        let mut os = Os::new().await.unwrap();
+        // Keep every backup while they are taken
+        os.database.settings.set(Setting::BackupsMaxAgeDays, 0).await.unwrap();
+        os.database.settings.set(Setting::BackupsMaxSizeMb, 0).await.unwrap();
+        os.fs.write("/big", vec![0; 1024 * 1024]).await.unwrap();
+
+        let old = create(&os, "a", &[PathBuf::from("/big")]).await.unwrap();
+        let mut backup = find(&os, &old).await.unwrap();
+        backup.manifest.created_at = "2020-01-01T00:00:00.000Z".to_string();
+        os.fs
+            .write(
+                backup.dir.join(MANIFEST_FILE),
+                serde_json::to_vec(&backup.manifest).unwrap(),
+            )
+            .await
+            .unwrap();
+        let big = create(&os, "a", &[PathBuf::from("/big")]).await.unwrap();
+        let small = create(&os, "b", &[PathBuf::from("/missing")]).await.unwrap();
+
+        let ids = |backups: Vec<Backup>| backups.into_iter().map(|b| b.id).collect::<Vec<_>>();
+        assert!(prune(&os, &Retention::new(0, 0)).await.unwrap().is_empty());
+        assert_eq!(ids(prune(&os, &Retention::new(30, 0)).await.unwrap()), vec![old]);
+        assert_eq!(ids(prune(&os, &Retention::new(0, 1)).await.unwrap()), vec![big]);
+
+        // The newest backup is kept even when it is over the limits
+        os.fs.write("/big", vec![0; 2 * 1024 * 1024]).await.unwrap();
+        let newest = create(&os, "b", &[PathBuf::from("/big")]).await.unwrap();
+        assert_eq!(ids(prune(&os, &Retention::new(0, 1)).await.unwrap()), vec![small]);
+        assert_eq!(ids(list(&os, None).await.unwrap()), vec![newest]);
+    }
+}
@@ -0,0 +1,46 @@
+use disk_cache::{
+    CacheConfig,
+    DiskCache,
+};
+use eyre::Result;
+
+use crate::os::Os;
+use crate::util::paths::PathResolver;
+
+/// Opens the cache shared by every chat session, stored under the global cache directory.
+pub fn open(os: &Os) -> Result<DiskCache> {
+    let dir = PathResolver::new(os).global().cache_dir()?;
+    Ok(DiskCache::open(dir, CacheConfig::default())?)
+}
+
+/// Formats a size in bytes for display, e.g. `1.5 MB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KB", "MB", "GB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next in &UNITS[1..] {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+    format!("{size:.1} {unit}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(100 * 1024 * 1024), "100.0 MB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+}
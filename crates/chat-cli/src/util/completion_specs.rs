@@ -0,0 +1,475 @@
+//! Downloads and caches the autocomplete specs published in the `@withfig/autocomplete` npm
+//! package, so completions don't depend on another process keeping them up to date.
+//!
+//! Specs are stored under `~/.aws/amazonq/completion-specs/specs` next to a manifest recording
+//! the package version and a SHA-256 digest of every spec, which [SpecStore::verify] checks.
+
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{
+    Component,
+    Path,
+    PathBuf,
+};
+use std::time::Duration;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use eyre::{
+    Result,
+    bail,
+    eyre,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sha2::{
+    Digest,
+    Sha256,
+    Sha512,
+};
+use tracing::{
+    debug,
+    info,
+};
+
+use crate::database::settings::Setting;
+use crate::os::Os;
+use crate::util::paths::PathResolver;
+
+/// Registry metadata of the latest published version of the spec package
+pub const DEFAULT_REGISTRY_URL: &str = "https://registry.npmjs.org/@withfig/autocomplete/latest";
+
+/// How often installed specs are refreshed, in hours
+pub const DEFAULT_UPDATE_INTERVAL_HOURS: usize = 24;
+
+/// Directory of the compiled specs inside the package tarball
+const PACKAGE_SPEC_DIR: &str = "package/build";
+
+const MANIFEST_FILE: &str = "manifest.json";
+const SPECS_DIR: &str = "specs";
+const STAGING_DIR: &str = "specs.new";
+
+/// The installed version of the specs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub version: String,
+    /// Unix timestamp of the last successful update check
+    pub checked_at: i64,
+    /// Subresource integrity string of the downloaded tarball
+    pub integrity: String,
+    /// SHA-256 digest of each spec, keyed by its path relative to the specs directory
+    pub files: BTreeMap<String, String>,
+}
+
+/// Registry metadata for a published package version.
+#[derive(Debug, Deserialize)]
+struct PackageMetadata {
+    version: String,
+    dist: PackageDist,
+}
+
+#[derive(Debug, Deserialize)]
+struct PackageDist {
+    tarball: String,
+    integrity: String,
+}
+
+/// Result of [SpecStore::update].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateOutcome {
+    UpToDate {
+        version: String,
+    },
+    Updated {
+        from: Option<String>,
+        to: String,
+        specs: usize,
+    },
+}
+
+/// Result of [SpecStore::verify].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Verification {
+    /// Specs listed in the manifest that don't exist
+    pub missing: Vec<String>,
+    /// Specs whose contents don't match the manifest
+    pub corrupt: Vec<String>,
+}
+
+impl Verification {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.corrupt.is_empty()
+    }
+}
+
+/// The local copy of the completion specs.
+#[derive(Debug, Clone)]
+pub struct SpecStore {
+    dir: PathBuf,
+}
+
+impl SpecStore {
+    pub fn open(os: &Os) -> Result<Self> {
+        Ok(Self {
+            dir: PathResolver::new(os).global().completion_specs_dir()?,
+        })
+    }
+
+    /// Directory containing the specs, one `<command>.js` file per command.
+    pub fn specs_dir(&self) -> PathBuf {
+        self.dir.join(SPECS_DIR)
+    }
+
+    pub async fn manifest(&self, os: &Os) -> Result<Option<Manifest>> {
+        let path = self.dir.join(MANIFEST_FILE);
+        if !os.fs.exists(&path) {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_slice(&os.fs.read(&path).await?)?))
+    }
+
+    /// Checks every installed spec against the digest in the manifest.
+    pub async fn verify(&self, os: &Os, manifest: &Manifest) -> Result<Verification> {
+        let mut verification = Verification::default();
+        let specs_dir = self.specs_dir();
+        for (name, digest) in &manifest.files {
+            match os.fs.read(specs_dir.join(name)).await {
+                Ok(contents) if sha256_hex(&contents) == *digest => {},
+                Ok(_) => verification.corrupt.push(name.clone()),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => verification.missing.push(name.clone()),
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(verification)
+    }
+
+    /// Reads the spec of `command`, e.g. `git` or `aws/s3`, checking it against the digest in the
+    /// manifest. Returns [None] when there is no spec for the command.
+    pub async fn read_spec(&self, os: &Os, manifest: &Manifest, command: &str) -> Result<Option<Vec<u8>>> {
+        // Only the files listed in the manifest are read, so `command` can't point outside of
+        // the specs directory.
+        let name = format!("{command}.js");
+        let Some(digest) = manifest.files.get(&name) else {
+            return Ok(None);
+        };
+        let contents = os.fs.read(self.specs_dir().join(&name)).await?;
+        if sha256_hex(&contents) != *digest {
+            bail!("The completion spec {name} is corrupt, reinstall it with `completion-specs update --force`");
+        }
+        Ok(Some(contents))
+    }
+
+    /// Downloads the latest specs from the registry at `registry_url` unless the installed ones
+    /// are already the latest version and intact.
+    pub async fn update(&self, os: &Os, registry_url: &str, force: bool) -> Result<UpdateOutcome> {
+        let client = crate::request::new_client()?;
+        let metadata: PackageMetadata = client
+            .get(registry_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let installed = self.manifest(os).await?;
+        if let Some(mut manifest) = installed.clone() {
+            if !force && manifest.version == metadata.version && self.verify(os, &manifest).await?.is_ok() {
+                manifest.checked_at = now();
+                self.write_manifest(os, &manifest).await?;
+                return Ok(UpdateOutcome::UpToDate {
+                    version: manifest.version,
+                });
+            }
+        }
+
+        let tarball = client
+            .get(&metadata.dist.tarball)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        verify_integrity(&tarball, &metadata.dist.integrity)?;
+        let specs = tokio::task::spawn_blocking(move || extract_specs(&tarball)).await??;
+        if specs.is_empty() {
+            bail!("The package {} has no completion specs", metadata.version);
+        }
+
+        // Write the new specs next to the installed ones and swap them in once complete, so a
+        // failed update leaves the previous specs in place.
+        let staging = self.dir.join(STAGING_DIR);
+        if os.fs.exists(&staging) {
+            os.fs.remove_dir_all(&staging).await?;
+        }
+        let mut files = BTreeMap::new();
+        for (name, contents) in &specs {
+            let path = staging.join(name);
+            if let Some(parent) = path.parent() {
+                os.fs.create_dir_all(parent).await?;
+            }
+            os.fs.write(&path, contents).await?;
+            files.insert(name.clone(), sha256_hex(contents));
+        }
+        let specs_dir = self.specs_dir();
+        if os.fs.exists(&specs_dir) {
+            os.fs.remove_dir_all(&specs_dir).await?;
+        }
+        os.fs.rename(&staging, &specs_dir).await?;
+
+        self.write_manifest(os, &Manifest {
+            version: metadata.version.clone(),
+            checked_at: now(),
+            integrity: metadata.dist.integrity,
+            files,
+        })
+        .await?;
+
+        Ok(UpdateOutcome::Updated {
+            from: installed.map(|m| m.version),
+            to: metadata.version,
+            specs: specs.len(),
+        })
+    }
+
+    async fn write_manifest(&self, os: &Os, manifest: &Manifest) -> Result<()> {
+        os.fs.create_dir_all(&self.dir).await?;
+        os.fs
+            .write(self.dir.join(MANIFEST_FILE), serde_json::to_vec_pretty(manifest)?)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Updates the installed specs if they haven't been checked within the configured interval.
+///
+/// Nothing is downloaded until the specs have been installed once with `completion-specs update`.
+pub async fn update_if_stale(os: &Os) {
+    match try_update_if_stale(os).await {
+        Ok(Some(outcome)) => info!(?outcome, "checked for completion spec updates"),
+        Ok(None) => {},
+        Err(err) => debug!(?err, "failed to update completion specs"),
+    }
+}
+
+async fn try_update_if_stale(os: &Os) -> Result<Option<UpdateOutcome>> {
+    let store = SpecStore::open(os)?;
+    let Some(manifest) = store.manifest(os).await? else {
+        return Ok(None);
+    };
+    let interval_hours = os
+        .database
+        .settings
+        .get_int_or(Setting::CompletionSpecsUpdateInterval, DEFAULT_UPDATE_INTERVAL_HOURS);
+    let interval = Duration::from_secs(interval_hours as u64 * 60 * 60);
+    if now().saturating_sub(manifest.checked_at) < interval.as_secs() as i64 {
+        return Ok(None);
+    }
+    store.update(os, &registry_url(os), false).await.map(Some)
+}
+
+/// The configured registry URL of the spec package.
+pub fn registry_url(os: &Os) -> String {
+    os.database
+        .settings
+        .get_string(Setting::CompletionSpecsRegistryUrl)
+        .unwrap_or_else(|| DEFAULT_REGISTRY_URL.to_string())
+}
+
+/// Checks `bytes` against a subresource integrity string such as `sha512-<base64 digest>`.
+fn verify_integrity(bytes: &[u8], integrity: &str) -> Result<()> {
+    let Some((algorithm, expected)) = integrity.split_once('-') else {
+        bail!("Invalid integrity string: {integrity}");
+    };
+    let actual = match algorithm {
+        "sha512" => STANDARD.encode(Sha512::digest(bytes)),
+        "sha256" => STANDARD.encode(Sha256::digest(bytes)),
+        _ => bail!("Unsupported integrity algorithm: {algorithm}"),
+    };
+    if actual != expected {
+        bail!("The downloaded completion specs failed the integrity check");
+    }
+    Ok(())
+}
+
+/// Reads the specs from a gzipped package tarball, returning their paths relative to the
+/// package's spec directory.
+fn extract_specs(tarball: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(tarball));
+    let mut specs = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.into_owned();
+        let Ok(relative) = path.strip_prefix(PACKAGE_SPEC_DIR) else {
+            continue;
+        };
+        if relative.extension().is_none_or(|ext| ext != "js") || !is_plain_relative(relative) {
+            continue;
+        }
+        let name = relative
+            .to_str()
+            .ok_or_else(|| eyre!("Invalid spec path: {}", relative.display()))?
+            .to_string();
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents)?;
+        specs.push((name, contents));
+    }
+    Ok(specs)
+}
+
+/// Whether `path` stays inside the directory it is joined to.
+fn is_plain_relative(path: &Path) -> bool {
+    path.components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+fn now() -> i64 {
+    time::OffsetDateTime::now_utc().unix_timestamp()
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+
+    use super::*;
+
+    /// Builds a package tarball containing `files`.
+    fn package_tarball(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (path, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, contents.as_bytes()).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn sri(bytes: &[u8]) -> String {
+        format!("sha512-{}", STANDARD.encode(Sha512::digest(bytes)))
+    }
+
+    #[test]
+    fn test_verify_integrity() {
+        assert!(verify_integrity(b"specs", &sri(b"specs")).is_ok());
+        assert!(verify_integrity(b"tampered", &sri(b"specs")).is_err());
+        assert!(verify_integrity(b"specs", "md5-abc").is_err());
+        assert!(verify_integrity(b"specs", "garbage").is_err());
+    }
+
+    #[test]
+    fn test_extract_specs() {
+        let tarball = package_tarball(&[
+            ("package/package.json", "{}"),
+            ("package/build/git.js", "var git = {}"),
+            ("package/build/aws/s3.js", "var s3 = {}"),
+            ("package/build/README.md", "docs"),
+        ]);
+        let mut specs = extract_specs(&tarball).unwrap();
+        specs.sort();
+        assert_eq!(specs, vec![
+            ("aws/s3.js".to_string(), b"var s3 = {}".to_vec()),
+            ("git.js".to_string(), b"var git = {}".to_vec()),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_update_and_verify() {
+        let os = Os::new().await.unwrap();
+        let store = SpecStore::open(&os).unwrap();
+        assert_eq!(store.manifest(&os).await.unwrap(), None);
+
+        let mut server = mockito::Server::new_async().await;
+        let tarball = package_tarball(&[("package/build/git.js", "var git = {}")]);
+        let metadata = serde_json::json!({
+            "version": "2.700.0",
+            "dist": { "tarball": format!("{}/autocomplete.tgz", server.url()), "integrity": sri(&tarball) }
+        });
+        let registry = server
+            .mock("GET", "/latest")
+            .with_body(metadata.to_string())
+            .expect(2)
+            .create_async()
+            .await;
+        let download = server
+            .mock("GET", "/autocomplete.tgz")
+            .with_body(&tarball)
+            .expect(1)
+            .create_async()
+            .await;
+        let registry_url = format!("{}/latest", server.url());
+
+        assert_eq!(
+            store.update(&os, &registry_url, false).await.unwrap(),
+            UpdateOutcome::Updated {
+                from: None,
+                to: "2.700.0".to_string(),
+                specs: 1
+            }
+        );
+        assert_eq!(
+            os.fs.read_to_string(store.specs_dir().join("git.js")).await.unwrap(),
+            "var git = {}"
+        );
+        assert_eq!(
+            store.update(&os, &registry_url, false).await.unwrap(),
+            UpdateOutcome::UpToDate {
+                version: "2.700.0".to_string()
+            }
+        );
+        registry.assert_async().await;
+        download.assert_async().await;
+
+        let manifest = store.manifest(&os).await.unwrap().unwrap();
+        assert!(store.verify(&os, &manifest).await.unwrap().is_ok());
+        assert_eq!(
+            store.read_spec(&os, &manifest, "git").await.unwrap(),
+            Some(b"var git = {}".to_vec())
+        );
+        assert_eq!(store.read_spec(&os, &manifest, "../manifest").await.unwrap(), None);
+        os.fs.write(store.specs_dir().join("git.js"), "tampered").await.unwrap();
+        assert_eq!(store.verify(&os, &manifest).await.unwrap(), Verification {
+            missing: vec![],
+            corrupt: vec!["git.js".to_string()],
+        });
+        assert!(store.read_spec(&os, &manifest, "git").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_update_rejects_tampered_tarball() {
+        let os = Os::new().await.unwrap();
+        let store = SpecStore::open(&os).unwrap();
+        let mut server = mockito::Server::new_async().await;
+        let metadata = serde_json::json!({
+            "version": "2.700.0",
+            "dist": { "tarball": format!("{}/autocomplete.tgz", server.url()), "integrity": sri(b"original") }
+        });
+        server
+            .mock("GET", "/latest")
+            .with_body(metadata.to_string())
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/autocomplete.tgz")
+            .with_body(package_tarball(&[("package/build/git.js", "var git = {}")]))
+            .create_async()
+            .await;
+
+        let err = store
+            .update(&os, &format!("{}/latest", server.url()), false)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("integrity"), "{err}");
+        assert_eq!(store.manifest(&os).await.unwrap(), None);
+    }
+}
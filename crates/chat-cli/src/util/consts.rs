@@ -15,6 +15,19 @@ pub mod build {
 
     /// The datetime in rfc3339 format of the current build
     pub const DATETIME: Option<&str> = option_env!("AMAZON_Q_BUILD_DATETIME");
+
+    /// How the release is signed, unset for unsigned builds. Signing happens after the build, so
+    /// `scripts/build.py` passes it ahead of time
+    pub const SIGNING_IDENTITY: Option<&str> = option_env!("AMAZON_Q_BUILD_SIGNING_IDENTITY");
+
+    /// The target triple the binary was compiled for
+    pub const TARGET: &str = env!("AMAZON_Q_BUILD_TARGET");
+
+    /// The cargo profile the binary was compiled with, `debug` or `release`
+    pub const PROFILE: &str = env!("AMAZON_Q_BUILD_PROFILE");
+
+    /// The packages compiled into the binary, as read from Cargo.lock by the build script
+    pub const DEPENDENCIES_JSON: &str = include_str!(concat!(env!("OUT_DIR"), "/dependencies.json"));
 }
 
 pub mod env_var {
@@ -70,11 +70,6 @@ pub fn try_get_editor() -> Result<String, std::env::VarError> {
     Env::new().get(EDITOR)
 }
 
-/// Get terminal type
-pub fn get_term() -> Option<String> {
-    Env::new().get(TERM).ok()
-}
-
 /// Get AWS region
 pub fn get_aws_region() -> Result<String, std::env::VarError> {
     Env::new().get(AWS_REGION)
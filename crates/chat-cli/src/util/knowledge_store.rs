@@ -12,6 +12,10 @@ use semantic_search_client::types::{
     AddContextRequest,
     SearchResult,
 };
+use sha2::{
+    Digest,
+    Sha256,
+};
 use tokio::sync::Mutex;
 use uuid::Uuid;
 
@@ -48,6 +52,64 @@ fn agent_knowledge_dir(os: &Os, agent: Option<&crate::cli::Agent>) -> Result<Pat
     Ok(PathResolver::new(os).global().knowledge_bases_dir()?.join(unique_id))
 }
 
+/// Directory (relative to an agent's knowledge base directory) where remote documents are
+/// downloaded to before being indexed
+const DOWNLOADS_DIR: &str = "downloads";
+
+/// Whether the value supplied to `add` refers to a remote document rather than a local path
+pub fn is_url(value: &str) -> bool {
+    value.starts_with("http://") || value.starts_with("https://")
+}
+
+/// Names of the knowledge bases the agent is scoped to, as configured under
+/// `toolsSettings.knowledge.knowledgeBases`. An empty list means the agent can search all of them.
+pub fn scoped_knowledge_bases(agent: Option<&crate::cli::Agent>) -> Vec<String> {
+    agent
+        .and_then(|agent| agent.tools_settings.get("knowledge"))
+        .and_then(|settings| settings.get("knowledgeBases"))
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default()
+}
+
+/// Derive a stable file name for a downloaded document so that re-adding the same url overwrites
+/// the previous download. The extension is taken from the url path if it has one, otherwise from
+/// the content type of the response.
+fn download_file_name(url: &str, content_type: Option<&str>) -> String {
+    // SHA-256 rather than the std hasher, whose output may change between Rust releases
+    let url_hash = hex::encode(&Sha256::digest(url.as_bytes())[..8]);
+
+    let from_path = url::Url::parse(url).ok().and_then(|parsed| {
+        parsed
+            .path_segments()
+            .and_then(|mut segments| segments.next_back().map(|s| s.to_string()))
+            .and_then(|segment| {
+                std::path::Path::new(&segment)
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .map(|ext| ext.to_lowercase())
+            })
+    });
+
+    let extension = from_path.unwrap_or_else(|| {
+        let mime = content_type.and_then(|ct| ct.split(';').next()).map(str::trim);
+        match mime {
+            Some("text/markdown" | "text/x-markdown") => "md",
+            Some("text/html") => "html",
+            Some("application/json") => "json",
+            Some("application/pdf") => "pdf",
+            _ => "txt",
+        }
+        .to_string()
+    });
+
+    format!("{url_hash}.{extension}")
+}
+
 /// Configuration for adding knowledge contexts
 #[derive(Default)]
 pub struct AddOptions {
@@ -366,6 +428,43 @@ impl KnowledgeStore {
         }
     }
 
+    /// Download a remote document into the agent's knowledge base directory and index it
+    pub async fn add_url(&mut self, name: &str, url: &str, options: AddOptions) -> Result<String, String> {
+        let downloads_dir = self.agent_dir.join(DOWNLOADS_DIR);
+        tokio::fs::create_dir_all(&downloads_dir)
+            .await
+            .map_err(|e| format!("Failed to create download directory: {}", e))?;
+
+        let client = crate::request::new_client().map_err(|e| format!("Failed to create http client: {}", e))?;
+        let response = client
+            .get(url)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|e| format!("❌ Failed to fetch {}: {}", url, e))?;
+
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| format!("❌ Failed to read response from {}: {}", url, e))?;
+
+        let file_path = downloads_dir.join(download_file_name(url, content_type.as_deref()));
+        tokio::fs::write(&file_path, &body)
+            .await
+            .map_err(|e| format!("Failed to save {}: {}", url, e))?;
+
+        let options = AddOptions {
+            description: options.description.or_else(|| Some(format!("Downloaded from {}", url))),
+            ..options
+        };
+        self.add(name, &file_path.to_string_lossy(), options).await
+    }
+
     /// Get all contexts from agent client
     pub async fn get_all(&self) -> Result<Vec<KnowledgeContext>, String> {
         Ok(self.agent_client.get_contexts().await)
@@ -401,6 +500,25 @@ impl KnowledgeStore {
         }
     }
 
+    /// Search restricted to the knowledge bases with the given names. An empty list of names
+    /// searches all knowledge bases.
+    pub async fn search_scoped(&self, query: &str, names: &[String]) -> Result<Vec<SearchResult>, KnowledgeError> {
+        if names.is_empty() {
+            return self.search(query, None).await;
+        }
+
+        let mut flattened = Vec::new();
+        for context in self.agent_client.get_contexts().await {
+            if names.contains(&context.name) {
+                flattened.extend(self.search(query, Some(&context.id)).await?);
+            }
+        }
+
+        flattened.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap_or(std::cmp::Ordering::Equal));
+
+        Ok(flattened)
+    }
+
     /// Get status data
     pub async fn get_status_data(&self) -> Result<semantic_search_client::SystemStatus, String> {
         self.agent_client.get_status_data().await.map_err(|e| e.to_string())
@@ -624,4 +742,44 @@ mod tests {
         // Verify directory structure
         assert!(base_dir.to_string_lossy().contains("knowledge_bases"));
     }
+
+    #[test]
+    fn test_is_url() {
+        assert!(is_url("https://example.com/runbook.md"));
+        assert!(is_url("http://localhost:8080"));
+        assert!(!is_url("./docs/runbook.md"));
+        assert!(!is_url("/home/user/https"));
+    }
+
+    #[test]
+    fn test_download_file_name() {
+        let md = download_file_name("https://example.com/docs/runbook.MD", None);
+        assert_eq!(md, "7be0d083d593710b.md");
+
+        let html = download_file_name("https://example.com/docs/", Some("text/html; charset=utf-8"));
+        assert!(html.ends_with(".html"));
+
+        let fallback = download_file_name("https://example.com/docs", None);
+        assert!(fallback.ends_with(".txt"));
+
+        // The same url should always map onto the same file
+        assert_eq!(md, download_file_name("https://example.com/docs/runbook.MD", None));
+    }
+
+    #[test]
+    fn test_scoped_knowledge_bases() {
+        assert!(scoped_knowledge_bases(None).is_empty());
+
+        let agent = crate::cli::Agent::default();
+        assert!(scoped_knowledge_bases(Some(&agent)).is_empty());
+
+        let agent: crate::cli::Agent = serde_json::from_value(serde_json::json!({
+            "name": "oncall",
+            "toolsSettings": {
+                "knowledge": { "knowledgeBases": ["runbooks", "oncall"] }
+            }
+        }))
+        .unwrap();
+        assert_eq!(scoped_knowledge_bases(Some(&agent)), vec!["runbooks", "oncall"]);
+    }
 }
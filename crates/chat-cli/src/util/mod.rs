@@ -1,3 +1,6 @@
+pub mod backups;
+pub mod cache;
+pub mod completion_specs;
 pub mod consts;
 pub mod editor;
 pub mod env_var;
@@ -6,10 +9,16 @@ pub mod knowledge_store;
 pub mod open;
 pub mod paths;
 pub mod pattern_matching;
+pub mod secrets;
+pub mod security_scan;
+pub mod sleep_monitor;
 pub mod spinner;
+pub mod startup_profile;
 pub mod system_info;
+pub mod terminal;
 #[cfg(test)]
 pub mod test;
+pub mod time_format;
 pub mod tool_permission_checker;
 pub mod ui;
 
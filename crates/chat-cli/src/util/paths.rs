@@ -47,8 +47,12 @@ pub mod workspace {
     pub const PROMPTS_DIR: &str = ".amazonq/prompts";
     pub const MCP_CONFIG: &str = ".amazonq/mcp.json";
     pub const TODO_LISTS_DIR: &str = ".amazonq/cli-todo-lists";
+    pub const MIGRATIONS_DIR: &str = ".amazonq/cli-migrations";
     pub const SUBAGENTS_DIR: &str = ".amazonq/.subagents";
     pub const RULES_PATTERN: &str = ".amazonq/rules/**/*.md";
+    pub const INIT_CONVERSATION: &str = ".amazonq/init-conversation.json";
+    pub const SCAN_FINDINGS: &str = ".amazonq/scan-findings.json";
+    pub const CHANGELOG_TEMPLATE: &str = ".amazonq/changelog-template.md";
 
     // Default documentation files for agent resources
     pub const DEFAULT_AGENT_RESOURCES: &[&str] = &["file://AmazonQ.md", "file://AGENTS.md", "file://README.md"];
@@ -64,6 +68,11 @@ pub mod global {
     pub const GLOBAL_CONTEXT: &str = ".aws/amazonq/global_context.json";
     pub const PROFILES_DIR: &str = ".aws/amazonq/profiles";
     pub const KNOWLEDGE_BASES_DIR: &str = ".aws/amazonq/knowledge_bases";
+    pub const CACHE_DIR: &str = ".aws/amazonq/cache";
+    pub const SHELL_ACTIVITY_LOG: &str = ".aws/amazonq/shell_activity.log";
+    pub const COMPLETION_SPECS_DIR: &str = ".aws/amazonq/completion-specs";
+    pub const BACKUPS_DIR: &str = ".aws/amazonq/backups";
+    pub const EGRESS_AUDIT_LOG: &str = ".aws/amazonq/egress_audit.jsonl";
 }
 
 type Result<T, E = DirectoryError> = std::result::Result<T, E>;
@@ -139,6 +148,12 @@ pub fn runtime_dir() -> Result<PathBuf> {
     dir.ok_or(DirectoryError::NoRuntimeDirectory)
 }
 
+/// Socket the daemon listens on
+#[cfg(unix)]
+pub fn daemon_socket_path() -> Result<PathBuf> {
+    Ok(runtime_dir()?.join("qdaemon.sock"))
+}
+
 /// The directory to all the logs
 pub fn logs_dir() -> Result<PathBuf> {
     cfg_if::cfg_if! {
@@ -245,10 +260,26 @@ impl<'a> WorkspacePaths<'a> {
         Ok(self.os.env.current_dir()?.join(workspace::TODO_LISTS_DIR))
     }
 
+    pub fn migrations_dir(&self) -> Result<PathBuf> {
+        Ok(self.os.env.current_dir()?.join(workspace::MIGRATIONS_DIR))
+    }
+
     pub fn subagents_dir(&self) -> Result<PathBuf> {
         Ok(self.os.env.current_dir()?.join(workspace::SUBAGENTS_DIR))
     }
 
+    pub fn init_conversation(&self) -> Result<PathBuf> {
+        Ok(self.os.env.current_dir()?.join(workspace::INIT_CONVERSATION))
+    }
+
+    pub fn scan_findings(&self) -> Result<PathBuf> {
+        Ok(self.os.env.current_dir()?.join(workspace::SCAN_FINDINGS))
+    }
+
+    pub fn changelog_template(&self) -> Result<PathBuf> {
+        Ok(self.os.env.current_dir()?.join(workspace::CHANGELOG_TEMPLATE))
+    }
+
     pub async fn ensure_subagents_dir(&self) -> Result<PathBuf> {
         let dir = self.subagents_dir()?;
         if !dir.exists() {
@@ -296,6 +327,26 @@ impl<'a> GlobalPaths<'a> {
         Ok(home_dir(self.os)?.join(global::KNOWLEDGE_BASES_DIR))
     }
 
+    pub fn cache_dir(&self) -> Result<PathBuf> {
+        Ok(home_dir(self.os)?.join(global::CACHE_DIR))
+    }
+
+    pub fn shell_activity_log(&self) -> Result<PathBuf> {
+        Ok(home_dir(self.os)?.join(global::SHELL_ACTIVITY_LOG))
+    }
+
+    pub fn completion_specs_dir(&self) -> Result<PathBuf> {
+        Ok(home_dir(self.os)?.join(global::COMPLETION_SPECS_DIR))
+    }
+
+    pub fn backups_dir(&self) -> Result<PathBuf> {
+        Ok(home_dir(self.os)?.join(global::BACKUPS_DIR))
+    }
+
+    pub fn egress_audit_log(&self) -> Result<PathBuf> {
+        Ok(home_dir(self.os)?.join(global::EGRESS_AUDIT_LOG))
+    }
+
     pub async fn ensure_agents_dir(&self) -> Result<PathBuf> {
         let dir = self.agents_dir()?;
         if !dir.exists() {
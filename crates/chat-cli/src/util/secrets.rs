@@ -0,0 +1,207 @@
+//! Named secrets for tool credentials, set with `q secrets set`.
+//!
+//! Secrets are kept in the login keychain on macOS. On Linux and Windows they are stored
+//! unencrypted in the `auth_kv` table of the CLI's local database, like the CLI's own login
+//! tokens, so any process running as the user can read them. Config values reference them with
+//! `${secret:name}` so that tokens don't have to be written into agent or MCP config files.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use regex::{
+    Captures,
+    Regex,
+};
+use thiserror::Error;
+use tracing::warn;
+
+use crate::database::{
+    Database,
+    DatabaseError,
+};
+
+/// Whether secrets are encrypted at rest, see the module docs.
+pub const ENCRYPTED_AT_REST: bool = cfg!(target_os = "macos");
+
+static SECRET_REFERENCE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\$\{secret:([^}]*)\}").expect("valid pattern"));
+
+#[derive(Debug, Error)]
+pub enum SecretError {
+    #[error("'{0}' is not a valid secret name, use letters, digits, '_', '-' and '.'")]
+    InvalidName(String),
+    #[error(transparent)]
+    Database(#[from] DatabaseError),
+    #[cfg(target_os = "macos")]
+    #[error("keychain error: {0}")]
+    Keychain(#[from] security_framework::base::Error),
+}
+
+pub fn validate_name(name: &str) -> Result<(), SecretError> {
+    if !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    {
+        Ok(())
+    } else {
+        Err(SecretError::InvalidName(name.to_string()))
+    }
+}
+
+pub fn get(database: &Database, name: &str) -> Result<Option<String>, SecretError> {
+    validate_name(name)?;
+    store::get(database, name)
+}
+
+pub fn set(database: &Database, name: &str, value: &str) -> Result<(), SecretError> {
+    validate_name(name)?;
+    store::set(database, name, value)
+}
+
+/// Deletes a secret and returns whether it existed.
+pub fn delete(database: &Database, name: &str) -> Result<bool, SecretError> {
+    validate_name(name)?;
+    if store::get(database, name)?.is_none() {
+        return Ok(false);
+    }
+    store::delete(database, name)?;
+    Ok(true)
+}
+
+/// Replaces `${secret:name}` references with the value of the secret. References to secrets that
+/// can't be read are left as is.
+pub fn interpolate(database: &Database, input: &str) -> String {
+    SECRET_REFERENCE
+        .replace_all(input, |caps: &Captures<'_>| match get(database, &caps[1]) {
+            Ok(Some(value)) => value,
+            Ok(None) => {
+                warn!(
+                    "secret '{}' is referenced but not set, set it with q secrets set",
+                    &caps[1]
+                );
+                caps[0].to_string()
+            },
+            Err(err) => {
+                warn!("failed to read secret '{}': {err}", &caps[1]);
+                caps[0].to_string()
+            },
+        })
+        .into_owned()
+}
+
+/// Applies [interpolate] to every value of `values`.
+pub fn interpolate_values(database: &Database, values: &mut HashMap<String, String>) {
+    for value in values.values_mut() {
+        if value.contains("${secret:") {
+            *value = interpolate(database, value);
+        }
+    }
+}
+
+// Tests use the database on every platform, since the login keychain isn't available on CI.
+#[cfg(not(all(target_os = "macos", not(test))))]
+use database_store as store;
+#[cfg(all(target_os = "macos", not(test)))]
+use keychain as store;
+
+#[cfg(target_os = "macos")]
+mod keychain {
+    use security_framework::passwords::{
+        delete_generic_password,
+        get_generic_password,
+        set_generic_password,
+    };
+
+    use super::SecretError;
+    use crate::database::Database;
+
+    #[cfg(not(test))]
+    const SERVICE: &str = "amazon-q-cli-secrets";
+    #[cfg(test)]
+    const SERVICE: &str = "amazon-q-cli-secrets-test";
+    /// `errSecItemNotFound`
+    const ITEM_NOT_FOUND: i32 = -25300;
+
+    pub fn get(_: &Database, name: &str) -> Result<Option<String>, SecretError> {
+        match get_generic_password(SERVICE, name) {
+            Ok(value) => Ok(Some(String::from_utf8_lossy(&value).into_owned())),
+            Err(err) if err.code() == ITEM_NOT_FOUND => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn set(_: &Database, name: &str, value: &str) -> Result<(), SecretError> {
+        Ok(set_generic_password(SERVICE, name, value.as_bytes())?)
+    }
+
+    pub fn delete(_: &Database, name: &str) -> Result<(), SecretError> {
+        Ok(delete_generic_password(SERVICE, name)?)
+    }
+}
+
+#[cfg(not(all(target_os = "macos", not(test))))]
+mod database_store {
+    use super::SecretError;
+    use crate::database::Database;
+
+    pub fn get(database: &Database, name: &str) -> Result<Option<String>, SecretError> {
+        Ok(database.get_tool_secret(name)?)
+    }
+
+    pub fn set(database: &Database, name: &str, value: &str) -> Result<(), SecretError> {
+        Ok(database.set_tool_secret(name, value)?)
+    }
+
+    pub fn delete(database: &Database, name: &str) -> Result<(), SecretError> {
+        Ok(database.delete_tool_secret(name)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::os::Os;
+
+    #[tokio::test]
+    async fn test_set_get_delete() {
+        let os = Os::new().await.unwrap();
+        let db = &os.database;
+        assert_eq!(get(db, "github-token").unwrap(), None);
+        set(db, "github-token", "ghp_123").unwrap();
+        assert_eq!(get(db, "github-token").unwrap().as_deref(), Some("ghp_123"));
+        assert!(delete(db, "github-token").unwrap());
+        assert!(!delete(db, "github-token").unwrap());
+        assert!(matches!(set(db, "bad name", "x"), Err(SecretError::InvalidName(_))));
+    }
+
+    #[tokio::test]
+    async fn test_interpolate() {
+        let os = Os::new().await.unwrap();
+        let db = &os.database;
+        set(db, "token", "abc").unwrap();
+        assert_eq!(interpolate(db, "Bearer ${secret:token}"), "Bearer abc");
+        assert_eq!(interpolate(db, "${secret:missing}"), "${secret:missing}");
+        assert_eq!(interpolate(db, "${env:HOME}"), "${env:HOME}");
+
+        let mut values = HashMap::from([("A".to_string(), "${secret:token}-${secret:token}".to_string())]);
+        interpolate_values(db, &mut values);
+        assert_eq!(values["A"], "abc-abc");
+    }
+
+    #[cfg(target_os = "macos")]
+    #[tokio::test]
+    #[ignore = "uses the login keychain, not on ci"]
+    async fn test_keychain() {
+        let os = Os::new().await.unwrap();
+        let db = &os.database;
+        let _ = keychain::delete(db, "github-token");
+        assert_eq!(keychain::get(db, "github-token").unwrap(), None);
+        keychain::set(db, "github-token", "ghp_123").unwrap();
+        assert_eq!(keychain::get(db, "github-token").unwrap().as_deref(), Some("ghp_123"));
+        keychain::set(db, "github-token", "ghp_456").unwrap();
+        assert_eq!(keychain::get(db, "github-token").unwrap().as_deref(), Some("ghp_456"));
+        keychain::delete(db, "github-token").unwrap();
+        assert_eq!(keychain::get(db, "github-token").unwrap(), None);
+    }
+}
@@ -0,0 +1,385 @@
+//! Runs security scanners installed on the system and normalizes their findings, so `q scan` and
+//! the `/fix` slash command can refer to findings by a short id regardless of which scanner
+//! reported them.
+//!
+//! The last scan is saved to `.amazonq/scan-findings.json` in the scanned directory.
+
+use std::fmt::Display;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use clap::ValueEnum;
+use eyre::{
+    Result,
+    bail,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tokio::process::Command;
+use tracing::debug;
+
+use crate::os::Os;
+use crate::util::paths::PathResolver;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scanner {
+    /// Static analysis for many languages
+    Semgrep,
+    /// Static analysis for Python
+    Bandit,
+    /// Known vulnerabilities in Rust dependencies
+    CargoAudit,
+}
+
+impl Scanner {
+    pub const ALL: [Scanner; 3] = [Scanner::Semgrep, Scanner::Bandit, Scanner::CargoAudit];
+
+    /// The command that runs the scanner with JSON output over `target`, a file or directory.
+    /// `cargo audit` always audits the whole `Cargo.lock` so ignores the target.
+    pub fn command(&self, target: &str) -> Vec<String> {
+        let args: &[&str] = match self {
+            Scanner::Semgrep => &["semgrep", "scan", "--json", "--quiet", "--config", "auto", target],
+            Scanner::Bandit => &["bandit", "--recursive", "--format", "json", "--quiet", target],
+            Scanner::CargoAudit => &["cargo", "audit", "--json"],
+        };
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    /// Whether the scanner applies to the project in `dir`
+    fn applies_to(&self, dir: &Path) -> bool {
+        match self {
+            Scanner::CargoAudit => dir.join("Cargo.lock").exists(),
+            Scanner::Semgrep | Scanner::Bandit => true,
+        }
+    }
+
+    /// Runs the scanner, returning [None] if it isn't installed.
+    pub async fn run(&self, dir: &Path, target: &str) -> Result<Option<Vec<Finding>>> {
+        let command = self.command(target);
+        let output = match Command::new(&command[0])
+            .args(&command[1..])
+            .current_dir(dir)
+            .output()
+            .await
+        {
+            Ok(output) => output,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        // Scanners exit with an error when they report findings, so a failure is only detected
+        // by the lack of a report
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if stdout.trim().is_empty() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            // `cargo audit` isn't installed as a cargo subcommand
+            if *self == Scanner::CargoAudit && stderr.contains("no such command") {
+                return Ok(None);
+            }
+            bail!("{self} failed: {}", stderr.trim());
+        }
+        debug!(scanner = %self, "parsing scanner output");
+        Ok(Some(self.parse(&stdout)?))
+    }
+
+    fn parse(&self, output: &str) -> Result<Vec<Finding>> {
+        let value: serde_json::Value = serde_json::from_str(output)?;
+        let str_at = |value: &serde_json::Value, pointer: &str| {
+            value
+                .pointer(pointer)
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+        let findings = match self {
+            Scanner::Semgrep => value["results"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|result| Finding {
+                    id: String::new(),
+                    scanner: *self,
+                    rule: str_at(result, "/check_id"),
+                    severity: Severity::from_label(&str_at(result, "/extra/severity")),
+                    path: Some(str_at(result, "/path")),
+                    line: result.pointer("/start/line").and_then(|v| v.as_u64()),
+                    message: str_at(result, "/extra/message"),
+                })
+                .collect(),
+            Scanner::Bandit => value["results"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|result| Finding {
+                    id: String::new(),
+                    scanner: *self,
+                    rule: format!("{} {}", str_at(result, "/test_id"), str_at(result, "/test_name")),
+                    severity: Severity::from_label(&str_at(result, "/issue_severity")),
+                    path: Some(str_at(result, "/filename")),
+                    line: result["line_number"].as_u64(),
+                    message: str_at(result, "/issue_text"),
+                })
+                .collect(),
+            Scanner::CargoAudit => value
+                .pointer("/vulnerabilities/list")
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .map(|vulnerability| Finding {
+                    id: String::new(),
+                    scanner: *self,
+                    rule: str_at(vulnerability, "/advisory/id"),
+                    severity: Severity::from_cvss(vulnerability.pointer("/advisory/cvss").and_then(|v| v.as_str())),
+                    path: Some("Cargo.lock".to_string()),
+                    line: None,
+                    message: format!(
+                        "{} {}: {}",
+                        str_at(vulnerability, "/package/name"),
+                        str_at(vulnerability, "/package/version"),
+                        str_at(vulnerability, "/advisory/title")
+                    ),
+                })
+                .collect(),
+        };
+        Ok(findings)
+    }
+}
+
+impl Display for Scanner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Scanner::Semgrep => "semgrep",
+            Scanner::Bandit => "bandit",
+            Scanner::CargoAudit => "cargo-audit",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl Severity {
+    /// Maps the severity labels of the scanners, which use different names for the same levels.
+    fn from_label(label: &str) -> Self {
+        match label.to_ascii_lowercase().as_str() {
+            "error" | "high" | "critical" => Severity::High,
+            "warning" | "medium" => Severity::Medium,
+            _ => Severity::Low,
+        }
+    }
+
+    /// RustSec advisories have a CVSS vector rather than a label, and informational advisories
+    /// have neither.
+    fn from_cvss(vector: Option<&str>) -> Self {
+        match vector {
+            Some(vector) if vector.contains("/C:H") || vector.contains("/I:H") => Severity::High,
+            Some(_) => Severity::Medium,
+            None => Severity::Low,
+        }
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Finding {
+    /// Short id such as `F3`, assigned once the findings of every scanner are collected
+    pub id: String,
+    pub scanner: Scanner,
+    pub rule: String,
+    pub severity: Severity,
+    pub path: Option<String>,
+    pub line: Option<u64>,
+    pub message: String,
+}
+
+impl Finding {
+    /// `path:line` of the finding, if it has a path
+    pub fn location(&self) -> Option<String> {
+        let path = self.path.as_ref()?;
+        Some(match self.line {
+            Some(line) => format!("{path}:{line}"),
+            None => path.clone(),
+        })
+    }
+
+    /// Command that re-runs the scanner over the file of the finding
+    pub fn rescan_command(&self) -> String {
+        let target = self.path.as_deref().unwrap_or(".");
+        shlex::try_join(self.scanner.command(target).iter().map(String::as_str))
+            .unwrap_or_else(|_| self.scanner.command(target).join(" "))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanReport {
+    pub scanned_at: String,
+    /// Scanners that ran, leaving out those not installed
+    pub scanners: Vec<Scanner>,
+    pub findings: Vec<Finding>,
+}
+
+impl ScanReport {
+    /// Runs `scanners`, or every scanner that applies to the current directory if empty, and
+    /// sorts the findings from most to least severe.
+    pub async fn run(os: &Os, scanners: &[Scanner], mut on_skipped: impl FnMut(Scanner)) -> Result<Self> {
+        let dir = os.env.current_dir()?;
+        let explicit = !scanners.is_empty();
+        let scanners = if explicit { scanners } else { &Scanner::ALL[..] };
+
+        let mut report = ScanReport {
+            scanned_at: chrono::Utc::now().to_rfc3339(),
+            ..Default::default()
+        };
+        for scanner in scanners {
+            if !explicit && !scanner.applies_to(&dir) {
+                continue;
+            }
+            match scanner.run(&dir, ".").await? {
+                Some(findings) => {
+                    report.scanners.push(*scanner);
+                    report.findings.extend(findings);
+                },
+                None => on_skipped(*scanner),
+            }
+        }
+
+        report.findings.sort_by(|a, b| b.severity.cmp(&a.severity));
+        for (i, finding) in report.findings.iter_mut().enumerate() {
+            finding.id = format!("F{}", i + 1);
+        }
+        Ok(report)
+    }
+
+    pub async fn load(os: &Os) -> Result<Option<Self>> {
+        let path = PathResolver::new(os).workspace().scan_findings()?;
+        if !os.fs.exists(&path) {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&os.fs.read_to_string(&path).await?)?))
+    }
+
+    pub async fn save(&self, os: &Os) -> Result<()> {
+        let path = PathResolver::new(os).workspace().scan_findings()?;
+        if let Some(parent) = path.parent() {
+            os.fs.create_dir_all(parent).await?;
+        }
+        os.fs.write(&path, serde_json::to_string_pretty(self)?).await?;
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Finding> {
+        self.findings.iter().find(|f| f.id.eq_ignore_ascii_case(id))
+    }
+}
+
+/// Prompt asking the agent to fix a single finding and confirm the fix by scanning again.
+pub fn fix_prompt(finding: &Finding) -> String {
+    format!(
+        "Fix security finding {id} reported by {scanner}.\n\n\
+        Rule: {rule}\nSeverity: {severity}\nLocation: {location}\nMessage: {message}\n\n\
+        Read the code around the finding, then make the smallest change that fixes it without changing \
+        behavior otherwise. If it is a false positive, explain why instead of changing the code. After \
+        changing the code, run `{rescan}` and confirm the finding is no longer reported.",
+        id = finding.id,
+        scanner = finding.scanner,
+        rule = finding.rule,
+        severity = finding.severity,
+        location = finding.location().unwrap_or_else(|| "unknown".to_string()),
+        message = finding.message,
+        rescan = finding.rescan_command(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_semgrep() {
+        let output = r#"{"results": [{
+            "check_id": "python.lang.security.audit.eval-detected",
+            "path": "app.py",
+            "start": {"line": 12, "col": 5},
+            "extra": {"message": "Detected the use of eval()", "severity": "WARNING"}
+        }], "errors": []}"#;
+        assert_eq!(Scanner::Semgrep.parse(output).unwrap(), vec![Finding {
+            id: String::new(),
+            scanner: Scanner::Semgrep,
+            rule: "python.lang.security.audit.eval-detected".to_string(),
+            severity: Severity::Medium,
+            path: Some("app.py".to_string()),
+            line: Some(12),
+            message: "Detected the use of eval()".to_string(),
+        }]);
+    }
+
+    #[test]
+    fn test_parse_bandit() {
+        let output = r#"{"results": [{
+            "test_id": "B602",
+            "test_name": "subprocess_popen_with_shell_equals_true",
+            "filename": "./run.py",
+            "line_number": 4,
+            "issue_severity": "HIGH",
+            "issue_text": "subprocess call with shell=True identified"
+        }]}"#;
+        let findings = Scanner::Bandit.parse(output).unwrap();
+        assert_eq!(findings[0].rule, "B602 subprocess_popen_with_shell_equals_true");
+        assert_eq!(findings[0].severity, Severity::High);
+        assert_eq!(findings[0].location().unwrap(), "./run.py:4");
+    }
+
+    #[test]
+    fn test_parse_cargo_audit() {
+        let output = r#"{"vulnerabilities": {"found": true, "count": 1, "list": [{
+            "advisory": {
+                "id": "RUSTSEC-2023-0071",
+                "title": "Marvin Attack: potential key recovery",
+                "cvss": "CVSS:3.1/AV:N/AC:H/PR:N/UI:N/S:U/C:H/I:N/A:N"
+            },
+            "package": {"name": "rsa", "version": "0.9.6"}
+        }]}}"#;
+        let findings = Scanner::CargoAudit.parse(output).unwrap();
+        assert_eq!(findings[0].rule, "RUSTSEC-2023-0071");
+        assert_eq!(findings[0].severity, Severity::High);
+        assert_eq!(findings[0].message, "rsa 0.9.6: Marvin Attack: potential key recovery");
+        assert_eq!(findings[0].rescan_command(), "cargo audit --json");
+    }
+
+    #[test]
+    fn test_fix_prompt() {
+        let finding = Finding {
+            id: "F2".to_string(),
+            scanner: Scanner::Bandit,
+            rule: "B105".to_string(),
+            severity: Severity::Low,
+            path: Some("my app/config.py".to_string()),
+            line: Some(3),
+            message: "Possible hardcoded password".to_string(),
+        };
+        let prompt = fix_prompt(&finding);
+        assert!(prompt.contains("Location: my app/config.py:3"));
+        assert!(prompt.contains("`bandit --recursive --format json --quiet 'my app/config.py'`"));
+    }
+}
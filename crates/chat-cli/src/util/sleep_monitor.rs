@@ -0,0 +1,144 @@
+//! Detects when the system resumes from sleep, so that work interrupted by the sleep can be
+//! recovered rather than reported as failed.
+//!
+//! The monotonic clock stops while the system is suspended on Linux and macOS, whereas the wall
+//! clock keeps going, so a sleep shows as the wall clock advancing more than the monotonic clock
+//! between two checks.
+
+use std::sync::{
+    Arc,
+    Mutex,
+    Weak,
+};
+use std::time::{
+    Duration,
+    Instant,
+    SystemTime,
+};
+
+use tokio::time::MissedTickBehavior;
+use tracing::info;
+
+/// How often the clocks are compared
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Shortest gap between the clocks treated as a sleep, longer than clock adjustments
+const MIN_SLEEP: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SystemResume {
+    /// When the resume was detected
+    pub at: Instant,
+    /// How long the system slept
+    pub slept: Duration,
+}
+
+#[derive(Debug)]
+struct State {
+    /// Readings of the monotonic and wall clocks at the last check
+    last_check: (Instant, SystemTime),
+    last_resume: Option<SystemResume>,
+}
+
+impl State {
+    fn check(&mut self) {
+        let now = (Instant::now(), SystemTime::now());
+        if let Some(slept) = slept_between(self.last_check, now) {
+            info!(?slept, "the system resumed from sleep");
+            self.last_resume = Some(SystemResume { at: now.0, slept });
+        }
+        self.last_check = now;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SleepMonitor {
+    state: Arc<Mutex<State>>,
+}
+
+impl SleepMonitor {
+    /// Starts checking the clocks in a background task, which stops once the monitor is dropped.
+    ///
+    /// The clocks are also checked on each call, so a resume is noticed before the next periodic
+    /// check.
+    pub fn spawn() -> Self {
+        let state = Arc::new(Mutex::new(State {
+            last_check: (Instant::now(), SystemTime::now()),
+            last_resume: None,
+        }));
+        tokio::spawn(poll(Arc::downgrade(&state)));
+        Self { state }
+    }
+
+    /// The last time the system resumed from sleep, if it slept since the monitor started.
+    pub fn last_resume(&self) -> Option<SystemResume> {
+        let mut state = self.state.lock().expect("not poisoned");
+        state.check();
+        state.last_resume
+    }
+
+    /// How long the system slept, if it resumed from sleep after `since`.
+    pub fn slept_since(&self, since: Instant) -> Option<Duration> {
+        self.last_resume()
+            .filter(|resume| resume.at > since)
+            .map(|resume| resume.slept)
+    }
+}
+
+async fn poll(state: Weak<Mutex<State>>) {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let Some(state) = state.upgrade() else {
+            return;
+        };
+        state.lock().expect("not poisoned").check();
+    }
+}
+
+/// The time slept between two readings of the monotonic and wall clocks.
+fn slept_between(before: (Instant, SystemTime), after: (Instant, SystemTime)) -> Option<Duration> {
+    let wall = after.1.duration_since(before.1).ok()?;
+    wall.checked_sub(after.0.duration_since(before.0))
+        .filter(|slept| *slept >= MIN_SLEEP)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slept_between() {
+        let before = (Instant::now(), SystemTime::now());
+        let after = |monotonic: u64, wall: u64| {
+            (
+                before.0 + Duration::from_secs(monotonic),
+                before.1 + Duration::from_secs(wall),
+            )
+        };
+        assert_eq!(slept_between(before, after(5, 5)), None);
+        assert_eq!(slept_between(before, after(5, 20)), None);
+        assert_eq!(slept_between(before, after(5, 3605)), Some(Duration::from_secs(3600)));
+        // The wall clock was set back
+        assert_eq!(
+            slept_between(before, (before.0, before.1 - Duration::from_secs(60))),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_slept_since() {
+        let monitor = SleepMonitor::spawn();
+        let start = Instant::now();
+        assert_eq!(monitor.slept_since(start), None);
+
+        let resume = SystemResume {
+            at: start + Duration::from_secs(1),
+            slept: Duration::from_secs(600),
+        };
+        monitor.state.lock().unwrap().last_resume = Some(resume);
+        assert_eq!(monitor.last_resume(), Some(resume));
+        assert_eq!(monitor.slept_since(start), Some(Duration::from_secs(600)));
+        assert_eq!(monitor.slept_since(resume.at), None);
+    }
+}
@@ -0,0 +1,125 @@
+//! Timings of the stages of chat startup. Every stage is traced as a `startup` span, and the
+//! recorded timings can be printed as a breakdown with `q chat --profile-startup`.
+
+use std::future::Future;
+use std::io::{
+    self,
+    Write,
+};
+use std::sync::{
+    Mutex,
+    OnceLock,
+};
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use tracing::Instrument;
+
+static PROCESS_START: OnceLock<Instant> = OnceLock::new();
+static STAGES: Mutex<Vec<Stage>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stage {
+    pub name: String,
+    /// Time from the start of the process to the start of the stage
+    pub offset: Duration,
+    pub duration: Duration,
+}
+
+/// Marks the start of the process. Stage offsets are relative to the first call, so this should
+/// be called as early as possible.
+pub fn init() {
+    process_start();
+}
+
+fn process_start() -> Instant {
+    *PROCESS_START.get_or_init(Instant::now)
+}
+
+/// Runs `fut` as the startup stage `name`, recording how long it took. Stages can run
+/// concurrently.
+pub async fn stage<F: Future>(name: &'static str, fut: F) -> F::Output {
+    let start = Instant::now();
+    let output = fut.instrument(tracing::info_span!("startup", stage = name)).await;
+    record(name, start);
+    output
+}
+
+/// Records a stage `name` that started at `start` and just finished, for stages that don't map
+/// to a single future such as launching an MCP server
+pub fn record(name: impl Into<String>, start: Instant) {
+    let name = name.into();
+    let duration = start.elapsed();
+    tracing::debug!(stage = %name, ?duration, "startup stage finished");
+    if let Ok(mut stages) = STAGES.lock() {
+        stages.push(Stage {
+            name,
+            offset: start.saturating_duration_since(process_start()),
+            duration,
+        });
+    }
+}
+
+/// The stages recorded so far, in the order they started
+pub fn stages() -> Vec<Stage> {
+    let mut stages = STAGES.lock().map(|stages| stages.clone()).unwrap_or_default();
+    stages.sort_by_key(|stage| stage.offset);
+    stages
+}
+
+/// Writes a breakdown of the recorded stages along with the time since the process started
+pub fn write_report(output: &mut impl Write) -> io::Result<()> {
+    let stages = stages();
+    let width = stages.iter().map(|stage| stage.name.len()).max().unwrap_or_default();
+
+    writeln!(output, "Startup profile:")?;
+    for stage in &stages {
+        writeln!(
+            output,
+            "  {:<width$}  {:>9}  (started at {})",
+            stage.name,
+            format_duration(stage.duration),
+            format_duration(stage.offset),
+        )?;
+    }
+    writeln!(
+        output,
+        "  {:<width$}  {:>9}",
+        "total",
+        format_duration(process_start().elapsed())
+    )?;
+    writeln!(output, "Stages that overlap ran in parallel.")?;
+
+    Ok(())
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.1}ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stage_is_recorded() {
+        init();
+        let value = stage("test stage", async {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            42
+        })
+        .await;
+        assert_eq!(value, 42);
+
+        let recorded = stages().into_iter().find(|stage| stage.name == "test stage").unwrap();
+        assert!(recorded.duration >= Duration::from_millis(5));
+
+        let mut output = Vec::new();
+        write_report(&mut output).unwrap();
+        let report = String::from_utf8(output).unwrap();
+        assert!(report.contains("test stage"), "{report}");
+        assert!(report.contains("total"), "{report}");
+    }
+}
@@ -0,0 +1,288 @@
+//! Registry of terminal emulators and the quirks the CLI adapts to, such as which escape
+//! sequences they understand and how much of the reported width is usable.
+
+use std::sync::OnceLock;
+
+use crate::os::Env;
+
+/// Terminal emulators and multiplexers that need special handling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Terminal {
+    VSCode,
+    Cursor,
+    ITerm2,
+    TerminalApp,
+    Kitty,
+    WezTerm,
+    Alacritty,
+    Ghostty,
+    WindowsTerminal,
+    /// GNOME Terminal and the other VTE based terminals
+    GnomeTerminal,
+    Konsole,
+    Warp,
+    Hyper,
+    /// Terminals running inside Emacs, e.g. eat and vterm
+    Emacs,
+    LinuxConsole,
+    Tmux,
+    Screen,
+    Zellij,
+}
+
+impl Terminal {
+    /// The emulator the CLI is displayed in, looking through any multiplexer it runs under.
+    pub fn parent_terminal(env: &Env) -> Option<Self> {
+        let var = |key: &str| env.get(key).ok().filter(|v| !v.is_empty());
+
+        match var("TERM_PROGRAM").as_deref() {
+            Some("vscode") if var("CURSOR_TRACE_ID").is_some() => return Some(Self::Cursor),
+            Some("vscode") => return Some(Self::VSCode),
+            Some("iTerm.app") => return Some(Self::ITerm2),
+            Some("Apple_Terminal") => return Some(Self::TerminalApp),
+            Some("WezTerm") => return Some(Self::WezTerm),
+            Some("ghostty") => return Some(Self::Ghostty),
+            Some("WarpTerminal") => return Some(Self::Warp),
+            Some("Hyper") => return Some(Self::Hyper),
+            _ => (),
+        }
+
+        // Multiplexers replace TERM_PROGRAM, but the variables below are inherited from the
+        // emulator the session was started in.
+        let term = var("TERM").unwrap_or_default();
+        if var("LC_TERMINAL").as_deref() == Some("iTerm2") {
+            Some(Self::ITerm2)
+        } else if var("KITTY_WINDOW_ID").is_some() || term == "xterm-kitty" {
+            Some(Self::Kitty)
+        } else if var("WEZTERM_PANE").is_some() {
+            Some(Self::WezTerm)
+        } else if var("GHOSTTY_RESOURCES_DIR").is_some() || term == "xterm-ghostty" {
+            Some(Self::Ghostty)
+        } else if var("ALACRITTY_WINDOW_ID").is_some() || term == "alacritty" {
+            Some(Self::Alacritty)
+        } else if var("WT_SESSION").is_some() {
+            Some(Self::WindowsTerminal)
+        } else if var("KONSOLE_VERSION").is_some() {
+            Some(Self::Konsole)
+        } else if var("VTE_VERSION").is_some() {
+            Some(Self::GnomeTerminal)
+        } else if var("INSIDE_EMACS").is_some() || term.starts_with("eat-") {
+            Some(Self::Emacs)
+        } else if term == "linux" {
+            Some(Self::LinuxConsole)
+        } else {
+            None
+        }
+    }
+
+    /// The multiplexer the CLI runs under, if any.
+    pub fn multiplexer(env: &Env) -> Option<Self> {
+        if env.get("TMUX").is_ok() {
+            Some(Self::Tmux)
+        } else if env.get("ZELLIJ").is_ok() {
+            Some(Self::Zellij)
+        } else if env.get("STY").is_ok() {
+            Some(Self::Screen)
+        } else {
+            None
+        }
+    }
+}
+
+/// Escape sequence a terminal shows as a desktop notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationEscape {
+    /// `OSC 9`, originally from iTerm2
+    Osc9,
+    /// `OSC 99`, the kitty notification protocol
+    Osc99,
+}
+
+impl NotificationEscape {
+    pub fn format(&self, body: &str) -> String {
+        // Control characters would end the sequence early.
+        let body = body.replace(|c: char| c.is_control(), " ");
+        match self {
+            Self::Osc9 => format!("\x1b]9;{body}\x07"),
+            Self::Osc99 => format!("\x1b]99;;{body}\x1b\\"),
+        }
+    }
+}
+
+/// What the current terminal supports, see [`TerminalQuirks::current`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerminalQuirks {
+    pub terminal: Option<Terminal>,
+    pub multiplexer: Option<Terminal>,
+    /// Whether BEL is played as an audible or visual bell
+    pub bell: bool,
+    pub notification: Option<NotificationEscape>,
+    /// Whether `OSC 8` hyperlinks are rendered
+    pub hyperlinks: bool,
+    /// Whether 24-bit SGR colors are rendered, false only when the terminal is known not to
+    pub truecolor: bool,
+    /// Columns at the right edge of the reported width that are covered by the terminal's own UI
+    pub width_offset: usize,
+    /// Whether VS Code's shell integration sequences (`OSC 633`) are understood
+    pub shell_integration: bool,
+}
+
+impl TerminalQuirks {
+    /// Quirks of the terminal the process is running in, detected once.
+    pub fn current() -> &'static Self {
+        static QUIRKS: OnceLock<TerminalQuirks> = OnceLock::new();
+        QUIRKS.get_or_init(|| Self::detect(&Env::new()))
+    }
+
+    pub fn detect(env: &Env) -> Self {
+        let terminal = Terminal::parent_terminal(env);
+        let multiplexer = Terminal::multiplexer(env);
+        let mut quirks = match terminal {
+            Some(terminal) => Self::for_terminal(terminal),
+            None => Self {
+                terminal: None,
+                multiplexer: None,
+                bell: env.get("TERM").is_ok_and(|term| is_bell_compatible_term(&term)),
+                notification: None,
+                hyperlinks: false,
+                truecolor: true,
+                width_offset: 0,
+                shell_integration: false,
+            },
+        };
+
+        quirks.multiplexer = multiplexer;
+        if multiplexer.is_some() {
+            // Multiplexers drop the sequences they don't know unless passthrough is configured.
+            quirks.notification = None;
+            quirks.hyperlinks = false;
+            quirks.shell_integration = false;
+        }
+        if multiplexer == Some(Terminal::Screen) {
+            quirks.truecolor = false;
+        }
+        quirks
+    }
+
+    fn for_terminal(terminal: Terminal) -> Self {
+        let mut quirks = Self {
+            terminal: Some(terminal),
+            multiplexer: None,
+            bell: true,
+            notification: None,
+            hyperlinks: true,
+            truecolor: true,
+            width_offset: 0,
+            shell_integration: false,
+        };
+        match terminal {
+            Terminal::VSCode | Terminal::Cursor => {
+                // The scrollbar and its decorations are drawn over the last column.
+                quirks.width_offset = 1;
+                quirks.shell_integration = true;
+            },
+            Terminal::ITerm2 | Terminal::WezTerm | Terminal::Ghostty => {
+                quirks.notification = Some(NotificationEscape::Osc9);
+            },
+            Terminal::Kitty => {
+                quirks.notification = Some(NotificationEscape::Osc99);
+            },
+            Terminal::TerminalApp | Terminal::LinuxConsole => {
+                quirks.hyperlinks = false;
+                quirks.truecolor = false;
+            },
+            Terminal::Emacs => {
+                quirks.hyperlinks = false;
+            },
+            _ => (),
+        }
+        quirks
+    }
+
+    /// Width available for output given the width the terminal reports.
+    pub fn usable_width(&self, columns: usize) -> usize {
+        columns.saturating_sub(self.width_offset).max(1)
+    }
+}
+
+/// Fallback for terminals that can't be identified, based on `TERM` values known to handle BEL.
+fn is_bell_compatible_term(term: &str) -> bool {
+    [
+        "xterm",
+        "screen",
+        "tmux",
+        "rxvt",
+        "linux",
+        "konsole",
+        "gnome",
+        "alacritty",
+        "iterm2",
+    ]
+    .iter()
+    .any(|prefix| term.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parent_terminal() {
+        let detect = |vars: &[(&str, &str)]| Terminal::parent_terminal(&Env::from_slice(vars));
+        assert_eq!(detect(&[("TERM_PROGRAM", "vscode")]), Some(Terminal::VSCode));
+        assert_eq!(
+            detect(&[("TERM_PROGRAM", "vscode"), ("CURSOR_TRACE_ID", "abc")]),
+            Some(Terminal::Cursor)
+        );
+        assert_eq!(detect(&[("TERM_PROGRAM", "iTerm.app")]), Some(Terminal::ITerm2));
+        assert_eq!(detect(&[("TERM", "xterm-kitty")]), Some(Terminal::Kitty));
+        assert_eq!(detect(&[("TERM", "linux")]), Some(Terminal::LinuxConsole));
+        assert_eq!(detect(&[("TERM", "xterm-256color")]), None);
+        // tmux replaces TERM_PROGRAM but the emulator's variables are inherited.
+        assert_eq!(
+            detect(&[("TERM_PROGRAM", "tmux"), ("TMUX", "/tmp/tmux"), ("WEZTERM_PANE", "0")]),
+            Some(Terminal::WezTerm)
+        );
+        assert_eq!(
+            detect(&[("TERM_PROGRAM", "tmux"), ("LC_TERMINAL", "iTerm2")]),
+            Some(Terminal::ITerm2)
+        );
+    }
+
+    #[test]
+    fn test_quirks() {
+        let detect = |vars: &[(&str, &str)]| TerminalQuirks::detect(&Env::from_slice(vars));
+
+        let vscode = detect(&[("TERM_PROGRAM", "vscode"), ("TERM", "xterm-256color")]);
+        assert!(vscode.bell && vscode.hyperlinks);
+        assert_eq!(vscode.usable_width(80), 79);
+
+        let iterm = detect(&[("TERM_PROGRAM", "iTerm.app")]);
+        assert_eq!(iterm.notification, Some(NotificationEscape::Osc9));
+        assert_eq!(iterm.usable_width(80), 80);
+
+        let iterm_tmux = detect(&[
+            ("TERM_PROGRAM", "tmux"),
+            ("TMUX", "/tmp/tmux"),
+            ("LC_TERMINAL", "iTerm2"),
+        ]);
+        assert_eq!(iterm_tmux.multiplexer, Some(Terminal::Tmux));
+        assert_eq!(iterm_tmux.notification, None);
+        assert!(iterm_tmux.bell && !iterm_tmux.hyperlinks);
+
+        assert!(!detect(&[("TERM_PROGRAM", "Apple_Terminal")]).truecolor);
+        assert!(!detect(&[("TERM", "xterm-256color"), ("STY", "1.pts-0")]).truecolor);
+
+        let unknown = detect(&[("TERM", "xterm-256color")]);
+        assert_eq!(unknown.terminal, None);
+        assert!(unknown.bell && !unknown.hyperlinks);
+        assert!(!detect(&[("TERM", "dumb")]).bell);
+        assert!(!detect(&[]).bell);
+    }
+
+    #[test]
+    fn test_notification_escape() {
+        assert_eq!(NotificationEscape::Osc9.format("done"), "\x1b]9;done\x07");
+        assert_eq!(NotificationEscape::Osc99.format("a\x07b"), "\x1b]99;;a b\x1b\\");
+    }
+}
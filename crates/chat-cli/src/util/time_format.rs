@@ -0,0 +1,154 @@
+//! Formats timestamps for display. They are stored in UTC, and shown in the local timezone or the
+//! one of the `chat.timezone` setting, with the recent ones relative to now unless
+//! `chat.timestampFormat` is `absolute`.
+
+use std::str::FromStr;
+
+use chrono::{
+    DateTime,
+    FixedOffset,
+    Local,
+    TimeZone,
+    Utc,
+};
+use tracing::warn;
+
+use crate::database::settings::{
+    Setting,
+    Settings,
+};
+
+/// Age from which timestamps are shown as dates in the relative format
+const RELATIVE_DAYS: i64 = 7;
+
+/// Timezone timestamps are shown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Zone {
+    #[default]
+    Local,
+    Utc,
+    /// A fixed offset from UTC, such as `+05:30`
+    Offset(FixedOffset),
+}
+
+impl FromStr for Zone {
+    type Err = chrono::ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "local" => Ok(Self::Local),
+            "utc" | "z" => Ok(Self::Utc),
+            offset => offset.parse().map(Self::Offset),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TimeFormatter {
+    zone: Zone,
+    /// Whether recent timestamps are shown relative to `now`
+    relative: bool,
+    now: DateTime<Utc>,
+}
+
+impl TimeFormatter {
+    pub fn new(zone: Zone, relative: bool) -> Self {
+        Self {
+            zone,
+            relative,
+            now: Utc::now(),
+        }
+    }
+
+    pub fn from_settings(settings: &Settings) -> Self {
+        let zone = settings
+            .get_string(Setting::ChatTimezone)
+            .map(|zone| {
+                zone.parse().unwrap_or_else(|err| {
+                    warn!(?err, %zone, "invalid timezone, using the local one");
+                    Zone::Local
+                })
+            })
+            .unwrap_or_default();
+        let relative = match settings.get_string(Setting::ChatTimestampFormat).as_deref() {
+            None | Some("relative") => true,
+            Some("absolute") => false,
+            Some(format) => {
+                warn!(%format, "invalid timestamp format, using the relative one");
+                true
+            },
+        };
+        Self::new(zone, relative)
+    }
+
+    /// The date and time of `at`, like `2025-01-26 12:30`, followed by the timezone unless it is
+    /// the local one.
+    pub fn absolute<Tz: TimeZone>(&self, at: &DateTime<Tz>) -> String {
+        let at = at.with_timezone(&Utc);
+        match self.zone {
+            Zone::Local => at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string(),
+            Zone::Utc => at.format("%Y-%m-%d %H:%M UTC").to_string(),
+            Zone::Offset(offset) => at.with_timezone(&offset).format("%Y-%m-%d %H:%M %:z").to_string(),
+        }
+    }
+
+    /// `at` relative to now, like `3 hr ago`, or [Self::absolute] when it's older than a week, in
+    /// the future or the relative format is disabled.
+    pub fn format<Tz: TimeZone>(&self, at: &DateTime<Tz>) -> String {
+        let duration = self.now.signed_duration_since(at.with_timezone(&Utc));
+        if !self.relative || duration.num_seconds() < 0 || duration.num_days() >= RELATIVE_DAYS {
+            self.absolute(at)
+        } else if duration.num_minutes() < 1 {
+            "just now".to_string()
+        } else if duration.num_minutes() < 60 {
+            format!("{} min ago", duration.num_minutes())
+        } else if duration.num_hours() < 24 {
+            format!("{} hr ago", duration.num_hours())
+        } else {
+            format!("{} days ago", duration.num_days())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+
+    #[test]
+    fn test_zone_from_str() {
+        assert_eq!("local".parse::<Zone>().unwrap(), Zone::Local);
+        assert_eq!("UTC".parse::<Zone>().unwrap(), Zone::Utc);
+        assert_eq!(
+            "+05:30".parse::<Zone>().unwrap(),
+            Zone::Offset(FixedOffset::east_opt(5 * 3600 + 30 * 60).unwrap())
+        );
+        assert!("Mars/Olympus".parse::<Zone>().is_err());
+    }
+
+    #[test]
+    fn test_format_relative() {
+        let formatter = TimeFormatter::new(Zone::Utc, true);
+        let at = |delta: TimeDelta| (formatter.now - delta).fixed_offset();
+        assert_eq!(formatter.format(&at(TimeDelta::seconds(5))), "just now");
+        assert_eq!(formatter.format(&at(TimeDelta::minutes(5))), "5 min ago");
+        assert_eq!(formatter.format(&at(TimeDelta::hours(3))), "3 hr ago");
+        assert_eq!(formatter.format(&at(TimeDelta::days(2))), "2 days ago");
+
+        let old = at(TimeDelta::days(30));
+        assert_eq!(formatter.format(&old), formatter.absolute(&old));
+        let future = at(TimeDelta::hours(-1));
+        assert_eq!(formatter.format(&future), formatter.absolute(&future));
+    }
+
+    #[test]
+    fn test_format_absolute() {
+        let at = DateTime::parse_from_rfc3339("2025-01-26T12:30:09Z").unwrap();
+        assert_eq!(TimeFormatter::new(Zone::Utc, false).format(&at), "2025-01-26 12:30 UTC");
+        assert_eq!(
+            TimeFormatter::new("-07:00".parse().unwrap(), true).absolute(&at),
+            "2025-01-26 05:30 -07:00"
+        );
+    }
+}
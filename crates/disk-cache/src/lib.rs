@@ -0,0 +1,458 @@
+//! A disk-backed cache of serializable values, capped in size and with a time to live.
+//!
+//! Every entry is stored in its own file named by the hash of its key, so several processes can
+//! share a cache directory without coordinating through an index. Once the cache grows past its
+//! size cap, entries are evicted least recently used first, using the modification time of their
+//! file as the time of last access.
+
+use std::fs::{
+    self,
+    File,
+};
+use std::io;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::sync::atomic::{
+    AtomicU64,
+    Ordering,
+};
+use std::time::{
+    Duration,
+    SystemTime,
+    UNIX_EPOCH,
+};
+
+use serde::de::DeserializeOwned;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sha2::{
+    Digest,
+    Sha256,
+};
+use tracing::{
+    debug,
+    warn,
+};
+
+/// Default size cap of a cache, in bytes
+pub const DEFAULT_MAX_SIZE: u64 = 100 * 1024 * 1024;
+/// Default time to live of a cache entry
+pub const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Once the size cap is exceeded, entries are evicted until the cache is back under this fraction
+/// of the cap, so that every subsequent insert doesn't trigger another eviction.
+const EVICTION_TARGET_PERCENT: u64 = 90;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CacheError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// Key of a cache entry, the SHA-256 hash of the content that identifies it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey(String);
+
+impl CacheKey {
+    /// Hashes `parts` into a key. Parts are length-prefixed, so `["ab", "c"]` and `["a", "bc"]`
+    /// produce different keys.
+    pub fn new<I, P>(parts: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<[u8]>,
+    {
+        let mut hasher = Sha256::new();
+        for part in parts {
+            let part = part.as_ref();
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part);
+        }
+        Self(hex::encode(hasher.finalize()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether `name` is the file name of a cache entry.
+    fn is_entry_file_name(name: &str) -> bool {
+        name.len() == 64 && name.bytes().all(|b| b.is_ascii_hexdigit())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheConfig {
+    /// Total size of the entries above which the least recently used ones are evicted
+    pub max_size: u64,
+    /// How long an entry stays valid after it was inserted
+    pub ttl: Duration,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            max_size: DEFAULT_MAX_SIZE,
+            ttl: DEFAULT_TTL,
+        }
+    }
+}
+
+/// The entries currently in a cache, along with the hits, misses, and evictions of this process.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub size: u64,
+    pub max_size: u64,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry<T> {
+    /// Milliseconds since the unix epoch
+    created_at: u64,
+    value: T,
+}
+
+#[derive(Debug)]
+pub struct DiskCache {
+    dir: PathBuf,
+    config: CacheConfig,
+    /// Size of the entries as of the last scan, plus whatever this process inserted since
+    size: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl DiskCache {
+    /// Opens the cache stored in `dir`, creating the directory if it doesn't exist.
+    pub fn open(dir: impl Into<PathBuf>, config: CacheConfig) -> Result<Self, CacheError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let size = scan(&dir)?.iter().map(|file| file.len).sum();
+        Ok(Self {
+            dir,
+            config,
+            size: AtomicU64::new(size),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+
+    /// Returns the value stored for `key`, if any and if it hasn't expired.
+    ///
+    /// A hit marks the entry as recently used. Entries that are expired or can't be read as `T`
+    /// are removed.
+    pub fn get<T: DeserializeOwned>(&self, key: &CacheKey) -> Option<T> {
+        let value = self.read(key);
+        let counter = if value.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    fn read<T: DeserializeOwned>(&self, key: &CacheKey) -> Option<T> {
+        let path = self.entry_path(key);
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return None,
+            Err(err) => {
+                warn!(?err, ?path, "failed to read cache entry");
+                return None;
+            },
+        };
+
+        let entry = match serde_json::from_slice::<Entry<T>>(&bytes) {
+            Ok(entry) => entry,
+            Err(err) => {
+                debug!(?err, ?path, "removing unreadable cache entry");
+                self.remove(key);
+                return None;
+            },
+        };
+        if now_millis().saturating_sub(entry.created_at) >= self.config.ttl.as_millis() as u64 {
+            self.remove(key);
+            return None;
+        }
+
+        if let Err(err) = File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+        {
+            debug!(?err, ?path, "failed to mark cache entry as used");
+        }
+        Some(entry.value)
+    }
+
+    /// Stores `value` for `key`, evicting the least recently used entries if the cache grows past
+    /// its size cap. Values larger than the cap itself are not stored.
+    pub fn insert<T: Serialize>(&self, key: &CacheKey, value: &T) -> Result<(), CacheError> {
+        let bytes = serde_json::to_vec(&Entry {
+            created_at: now_millis(),
+            value,
+        })?;
+        let len = bytes.len() as u64;
+        if len > self.config.max_size {
+            debug!(len, "value is larger than the cache, not storing it");
+            return Ok(());
+        }
+
+        // Write to a temporary file first so that readers never see a partial entry.
+        let path = self.entry_path(key);
+        let tmp_path = path.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&tmp_path, &bytes)?;
+        if let Err(err) = fs::rename(&tmp_path, &path) {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(err.into());
+        }
+
+        let size = self.size.fetch_add(len, Ordering::Relaxed) + len;
+        if size > self.config.max_size {
+            self.evict()?;
+        }
+        Ok(())
+    }
+
+    pub fn remove(&self, key: &CacheKey) {
+        let path = self.entry_path(key);
+        if let Ok(metadata) = fs::metadata(&path) {
+            if fs::remove_file(&path).is_ok() {
+                let _ = self.size.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |size| {
+                    Some(size.saturating_sub(metadata.len()))
+                });
+            }
+        }
+    }
+
+    /// Removes every entry, returning how many were removed.
+    pub fn clear(&self) -> Result<usize, CacheError> {
+        let mut removed = 0;
+        for file in scan(&self.dir)? {
+            match fs::remove_file(&file.path) {
+                Ok(()) => removed += 1,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+                Err(err) => return Err(err.into()),
+            }
+        }
+        self.size.store(0, Ordering::Relaxed);
+        Ok(removed)
+    }
+
+    pub fn stats(&self) -> Result<CacheStats, CacheError> {
+        let files = scan(&self.dir)?;
+        let size = files.iter().map(|file| file.len).sum();
+        self.size.store(size, Ordering::Relaxed);
+        Ok(CacheStats {
+            entries: files.len(),
+            size,
+            max_size: self.config.max_size,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        })
+    }
+
+    /// Removes the entries that were last used before the time to live, then the least recently
+    /// used ones until the cache is back under its size cap.
+    fn evict(&self) -> Result<(), CacheError> {
+        let mut files = scan(&self.dir)?;
+        files.sort_by_key(|file| file.modified);
+
+        let target = self.config.max_size.saturating_mul(EVICTION_TARGET_PERCENT) / 100;
+        let expired_before = SystemTime::now().checked_sub(self.config.ttl).unwrap_or(UNIX_EPOCH);
+        let mut size = files.iter().map(|file| file.len).sum::<u64>();
+        let mut evicted = 0;
+        for file in files {
+            // Entries are sorted oldest first, so once one is neither expired nor needed to get
+            // under the cap, none of the remaining ones are either.
+            if size <= target && file.modified >= expired_before {
+                break;
+            }
+            match fs::remove_file(&file.path) {
+                Ok(()) => {
+                    size -= file.len;
+                    evicted += 1;
+                },
+                Err(err) if err.kind() == io::ErrorKind::NotFound => size -= file.len,
+                Err(err) => warn!(?err, path = ?file.path, "failed to evict cache entry"),
+            }
+        }
+
+        debug!(evicted, size, "evicted cache entries");
+        self.size.store(size, Ordering::Relaxed);
+        self.evictions.fetch_add(evicted, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn entry_path(&self, key: &CacheKey) -> PathBuf {
+        self.dir.join(key.as_str())
+    }
+}
+
+#[derive(Debug)]
+struct EntryFile {
+    path: PathBuf,
+    len: u64,
+    modified: SystemTime,
+}
+
+/// Lists the entry files in `dir`, skipping anything else such as in-progress writes.
+fn scan(dir: &Path) -> Result<Vec<EntryFile>, CacheError> {
+    let mut files = Vec::new();
+    for dir_entry in fs::read_dir(dir)? {
+        let dir_entry = dir_entry?;
+        if !dir_entry.file_name().to_str().is_some_and(CacheKey::is_entry_file_name) {
+            continue;
+        }
+        // Another process may remove the entry while we're scanning.
+        let Ok(metadata) = dir_entry.metadata() else {
+            continue;
+        };
+        if metadata.is_file() {
+            files.push(EntryFile {
+                path: dir_entry.path(),
+                len: metadata.len(),
+                modified: metadata.modified().unwrap_or(UNIX_EPOCH),
+            });
+        }
+    }
+    Ok(files)
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(dir: &Path, config: CacheConfig) -> DiskCache {
+        DiskCache::open(dir.join("cache"), config).unwrap()
+    }
+
+    /// Sets the last use of the entry for `key` to `secs_ago` seconds ago.
+    fn used_ago(cache: &DiskCache, key: &CacheKey, secs_ago: u64) {
+        File::options()
+            .write(true)
+            .open(cache.entry_path(key))
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(secs_ago))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_key() {
+        let key = CacheKey::new(["ab", "c"]);
+        assert_eq!(key, CacheKey::new(["ab".as_bytes(), "c".as_bytes()]));
+        assert_ne!(key, CacheKey::new(["a", "bc"]));
+        assert!(CacheKey::is_entry_file_name(key.as_str()));
+    }
+
+    #[test]
+    fn test_insert_and_get() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache(dir.path(), CacheConfig::default());
+        let key = CacheKey::new(["key"]);
+
+        assert_eq!(cache.get::<String>(&key), None);
+        cache.insert(&key, &"value".to_string()).unwrap();
+        assert_eq!(cache.get::<String>(&key), Some("value".to_string()));
+        // Values that can't be read as the requested type are dropped.
+        assert_eq!(cache.get::<u32>(&key), None);
+        assert_eq!(cache.get::<String>(&key), None);
+
+        let stats = cache.stats().unwrap();
+        assert_eq!((stats.hits, stats.misses), (1, 3));
+        assert_eq!(stats.entries, 0);
+    }
+
+    #[test]
+    fn test_entries_expire() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache(dir.path(), CacheConfig {
+            ttl: Duration::ZERO,
+            ..Default::default()
+        });
+        let key = CacheKey::new(["key"]);
+
+        cache.insert(&key, &1).unwrap();
+        assert_eq!(cache.get::<u32>(&key), None);
+        assert_eq!(cache.stats().unwrap().entries, 0);
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let value = "x".repeat(100);
+        let entry_len = serde_json::to_vec(&Entry {
+            created_at: now_millis(),
+            value: &value,
+        })
+        .unwrap()
+        .len() as u64;
+        // Room for three and a half entries.
+        let cache = cache(dir.path(), CacheConfig {
+            max_size: entry_len * 7 / 2,
+            ..Default::default()
+        });
+        let keys = (0..4).map(|i| CacheKey::new([i.to_string()])).collect::<Vec<_>>();
+
+        for (i, key) in keys.iter().take(3).enumerate() {
+            cache.insert(key, &value).unwrap();
+            used_ago(&cache, key, 10 - i as u64);
+        }
+        // Using the oldest entry makes the second one the least recently used.
+        assert!(cache.get::<String>(&keys[0]).is_some());
+        cache.insert(&keys[3], &value).unwrap();
+
+        assert!(cache.get::<String>(&keys[0]).is_some());
+        assert!(cache.get::<String>(&keys[1]).is_none());
+        assert!(cache.get::<String>(&keys[2]).is_some());
+        assert!(cache.get::<String>(&keys[3]).is_some());
+        let stats = cache.stats().unwrap();
+        assert_eq!(stats.evictions, 1);
+        assert!(stats.size <= stats.max_size);
+
+        // Values larger than the cache are never stored.
+        cache.insert(&keys[1], &"x".repeat(1000)).unwrap();
+        assert!(cache.get::<String>(&keys[1]).is_none());
+    }
+
+    #[test]
+    fn test_clear() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache(dir.path(), CacheConfig::default());
+        for i in 0..3 {
+            cache.insert(&CacheKey::new([i.to_string()]), &i).unwrap();
+        }
+        fs::write(cache.dir().join("unrelated"), "").unwrap();
+
+        // Reopening picks up entries written by another process.
+        let reopened = DiskCache::open(cache.dir(), CacheConfig::default()).unwrap();
+        assert_eq!(reopened.stats().unwrap().entries, 3);
+        assert_eq!(reopened.clear().unwrap(), 3);
+        assert_eq!(cache.stats().unwrap().entries, 0);
+        assert!(cache.dir().join("unrelated").exists());
+    }
+}
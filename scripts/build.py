@@ -68,7 +68,12 @@ def build_chat_bin(
     release: bool,
     output_name: str | None = None,
     targets: Sequence[str] = [],
+    signing_identity: str | None = None,
 ):
+    """
+    `signing_identity` describes how the release will be signed once built, and is reported by
+    `q about`.
+    """
     package = CHAT_PACKAGE_NAME
 
     args = [cargo_cmd_name(), "build", "--locked", "--package", package]
@@ -82,13 +87,13 @@ def build_chat_bin(
     else:
         target_subdir = "debug"
 
-    run_cmd(
-        args,
-        env={
-            **os.environ,
-            **rust_env(release=release),
-        },
-    )
+    env = {
+        **os.environ,
+        **rust_env(release=release),
+    }
+    if signing_identity:
+        env["AMAZON_Q_BUILD_SIGNING_IDENTITY"] = signing_identity
+    run_cmd(args, env=env)
 
     # create "universal" binary for macos
     if isDarwin():
@@ -584,11 +589,20 @@ def build(
         info("Running cargo clippy")
         run_clippy()
 
+    # The binary is signed after it is built, so the identity is passed ahead of time
+    if signing_data:
+        signing_identity = "Apple Developer ID, notarized"
+    elif gpg_signer:
+        signing_identity = f"GPG key {gpg_signer.gpg_id}, on the release archives"
+    else:
+        signing_identity = None
+
     info("Building", CHAT_PACKAGE_NAME)
     chat_path = build_chat_bin(
         release=release,
         output_name=CHAT_BINARY_NAME,
         targets=targets,
+        signing_identity=signing_identity,
     )
 
     if isDarwin():